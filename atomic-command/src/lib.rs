@@ -20,13 +20,14 @@
 //! This library contains the definition for an atomic command. This is used by `atomic_bgp`, as
 //! well as `bgpsim_web` with the feature `atomic_bgp`.
 
-use std::{collections::BTreeSet, iter::once};
+use std::{cmp::Ordering, collections::BTreeSet, iter::once};
 
 use bgpsim::{
     bgp::BgpRibEntry,
-    config::{ConfigModifier, NetworkConfig},
+    config::{ConfigExpr, ConfigModifier, NetworkConfig},
     event::EventQueue,
     prelude::{Network, NetworkFormatter},
+    route_map::RouteMap,
     types::{NetworkError, Prefix, PrefixMap, RouterId},
 };
 use itertools::Itertools;
@@ -128,6 +129,16 @@ pub enum AtomicModifier<P: Prefix> {
         /// The raw commands
         raw: Vec<ConfigModifier<P>>,
     },
+    /// A command to ignore all routes for a prefix, such that the router forwards the traffic using
+    /// a less-specific prefix.
+    IgnoreRoutes {
+        /// On which router should the routes be ignored
+        router: RouterId,
+        /// For which prefix should the routes be ignored
+        prefix: P,
+        /// The raw commands
+        raw: Vec<ConfigModifier<P>>,
+    },
 }
 
 impl<P: Prefix> From<AtomicModifier<P>> for Vec<ConfigModifier<P>> {
@@ -138,6 +149,7 @@ impl<P: Prefix> From<AtomicModifier<P>> for Vec<ConfigModifier<P>> {
             | AtomicModifier::UseTempSession { raw, .. } => vec![raw],
             AtomicModifier::ChangePreference { raw, .. }
             | AtomicModifier::ClearPreference { raw, .. }
+            | AtomicModifier::IgnoreRoutes { raw, .. }
             | AtomicModifier::AddTempSession { raw, .. }
             | AtomicModifier::RemoveTempSession { raw, .. } => raw,
         }
@@ -151,6 +163,7 @@ impl<P: Prefix> AtomicModifier<P> {
             AtomicModifier::Raw(raw) => raw.routers(),
             AtomicModifier::ChangePreference { router, .. }
             | AtomicModifier::ClearPreference { router, .. }
+            | AtomicModifier::IgnoreRoutes { router, .. }
             | AtomicModifier::UseTempSession { router, .. }
             | AtomicModifier::IgnoreTempSession { router, .. } => vec![*router],
             AtomicModifier::AddTempSession {
@@ -173,6 +186,7 @@ impl<P: Prefix> AtomicModifier<P> {
             | AtomicModifier::UseTempSession { raw, .. } => net.apply_modifier(raw),
             AtomicModifier::ChangePreference { raw, .. }
             | AtomicModifier::ClearPreference { raw, .. }
+            | AtomicModifier::IgnoreRoutes { raw, .. }
             | AtomicModifier::AddTempSession { raw, .. }
             | AtomicModifier::RemoveTempSession { raw, .. } => {
                 raw.iter().try_for_each(|c| net.apply_modifier(c))
@@ -189,10 +203,149 @@ impl<P: Prefix> AtomicModifier<P> {
             | AtomicModifier::UseTempSession { raw, .. } => vec![raw],
             AtomicModifier::ChangePreference { raw, .. }
             | AtomicModifier::ClearPreference { raw, .. }
+            | AtomicModifier::IgnoreRoutes { raw, .. }
+            | AtomicModifier::AddTempSession { raw, .. }
+            | AtomicModifier::RemoveTempSession { raw, .. } => raw,
+        }
+    }
+
+    /// Get a mutable reference to the raw commands stored within `self`.
+    pub fn raw_mut(&mut self) -> &mut [ConfigModifier<P>] {
+        match self {
+            AtomicModifier::Raw(raw)
+            | AtomicModifier::IgnoreTempSession { raw, .. }
+            | AtomicModifier::UseTempSession { raw, .. } => std::slice::from_mut(raw),
+            AtomicModifier::ChangePreference { raw, .. }
+            | AtomicModifier::ClearPreference { raw, .. }
+            | AtomicModifier::IgnoreRoutes { raw, .. }
             | AtomicModifier::AddTempSession { raw, .. }
             | AtomicModifier::RemoveTempSession { raw, .. } => raw,
         }
     }
+
+    /// Reverses the modifier, such that applying `self` and then its reverse leaves the
+    /// configuration unchanged. Using a temporary session becomes ignoring it (and vice-versa),
+    /// adding a temporary session becomes removing it (and vice-versa), and the raw commands are
+    /// reversed (see [`ConfigModifier::reverse`]) and applied in reverse order.
+    ///
+    /// A preference change becomes a change towards the neighbor on which the reversed commands
+    /// insert a route-map item that allows routes. If they only insert route-map items that deny
+    /// routes, then it becomes a [`AtomicModifier::IgnoreRoutes`], and if they do not insert any
+    /// route-map item, then it becomes a [`AtomicModifier::ClearPreference`].
+    pub fn reverse(self) -> Self {
+        /// reverse a sequence of raw commands.
+        fn rev<P: Prefix>(raw: Vec<ConfigModifier<P>>) -> Vec<ConfigModifier<P>> {
+            raw.into_iter().rev().map(|c| c.reverse()).collect()
+        }
+
+        match self {
+            AtomicModifier::Raw(raw) => AtomicModifier::Raw(raw.reverse()),
+            AtomicModifier::UseTempSession {
+                router,
+                neighbor,
+                prefix,
+                raw,
+            } => AtomicModifier::IgnoreTempSession {
+                router,
+                neighbor,
+                prefix,
+                raw: raw.reverse(),
+            },
+            AtomicModifier::IgnoreTempSession {
+                router,
+                neighbor,
+                prefix,
+                raw,
+            } => AtomicModifier::UseTempSession {
+                router,
+                neighbor,
+                prefix,
+                raw: raw.reverse(),
+            },
+            AtomicModifier::AddTempSession {
+                router,
+                neighbor,
+                raw,
+            } => AtomicModifier::RemoveTempSession {
+                router,
+                neighbor,
+                raw: rev(raw),
+            },
+            AtomicModifier::RemoveTempSession {
+                router,
+                neighbor,
+                raw,
+            } => AtomicModifier::AddTempSession {
+                router,
+                neighbor,
+                raw: rev(raw),
+            },
+            AtomicModifier::ChangePreference {
+                router,
+                prefix,
+                raw,
+                ..
+            }
+            | AtomicModifier::ClearPreference {
+                router,
+                prefix,
+                raw,
+            }
+            | AtomicModifier::IgnoreRoutes {
+                router,
+                prefix,
+                raw,
+            } => {
+                let raw = rev(raw);
+                let denies = inserted_route_maps(&raw).next().is_some();
+                match (preferred_neighbor(&raw), denies) {
+                    (Some(neighbor), _) => AtomicModifier::ChangePreference {
+                        router,
+                        prefix,
+                        neighbor,
+                        raw,
+                    },
+                    (None, true) => AtomicModifier::IgnoreRoutes {
+                        router,
+                        prefix,
+                        raw,
+                    },
+                    (None, false) => AtomicModifier::ClearPreference {
+                        router,
+                        prefix,
+                        raw,
+                    },
+                }
+            }
+        }
+    }
+}
+
+/// Get the neighbor for which the raw commands insert (or update) an incoming route-map item that
+/// allows routes.
+fn preferred_neighbor<P: Prefix>(raw: &[ConfigModifier<P>]) -> Option<RouterId> {
+    inserted_route_maps(raw)
+        .find(|(_, map)| map.state().is_allow())
+        .map(|(neighbor, _)| neighbor)
+}
+
+/// Iterate over all route-map items that the raw commands insert (or update), together with the
+/// neighbor of each item.
+fn inserted_route_maps<P: Prefix>(
+    raw: &[ConfigModifier<P>],
+) -> impl Iterator<Item = (RouterId, &RouteMap<P>)> {
+    raw.iter().flat_map(|c| match c {
+        ConfigModifier::Insert(ConfigExpr::BgpRouteMap { neighbor, map, .. })
+        | ConfigModifier::Update {
+            to: ConfigExpr::BgpRouteMap { neighbor, map, .. },
+            ..
+        } => vec![(*neighbor, map)],
+        ConfigModifier::BatchRouteMapEdit { updates, .. } => updates
+            .iter()
+            .filter_map(|u| u.new.as_ref().map(|map| (u.neighbor, map)))
+            .collect(),
+        _ => Vec::new(),
+    })
 }
 
 impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for AtomicModifier<P> {
@@ -214,6 +367,9 @@ impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for AtomicModifier<P>
             AtomicModifier::ClearPreference { router, prefix, .. } => {
                 format!("Clear route preference on {} for {prefix}", router.fmt(net),)
             }
+            AtomicModifier::IgnoreRoutes { router, prefix, .. } => {
+                format!("Make {} ignore all routes for {prefix}", router.fmt(net))
+            }
             AtomicModifier::UseTempSession {
                 router,
                 neighbor,
@@ -287,6 +443,22 @@ pub enum AtomicCondition<P: Prefix> {
         /// The selected route has a next hop via x
         next_hop: Option<RouterId>,
     },
+    /// Condition that the router selects no route for this prefix, i.e., it forwards the traffic
+    /// using a less-specific prefix.
+    NoSelectedRoute {
+        /// Which router should be checked
+        router: RouterId,
+        /// Which prefix should be checked
+        prefix: P,
+    },
+    /// Condition that the router receives no route for this prefix from any neighbor, including
+    /// routes that are denied by an incoming route-map.
+    NoReceivedRoute {
+        /// Which router should be checked
+        router: RouterId,
+        /// Which prefix should be checked
+        prefix: P,
+    },
     /// Condition on the availability of a given route. It implies that there exists at least one
     /// route that is from either one of the given neighbors. If `neighbors` is  `None`, then it
     /// just asserts that a route for this prefix is available.
@@ -360,6 +532,14 @@ impl<P: Prefix> From<AtomicCondition<P>> for AtomicConditionExt<P> {
                         .collect(),
                 )),
             },
+            AtomicCondition::NoSelectedRoute { router, prefix } => AtomicConditionExt::CurrentRib {
+                router,
+                prefix,
+                cond: None,
+            },
+            AtomicCondition::NoReceivedRoute { router, prefix } => {
+                AtomicConditionExt::NoReceivedRoute { router, prefix }
+            }
             AtomicCondition::AvailableRoute {
                 router,
                 prefix,
@@ -424,6 +604,12 @@ impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for AtomicCondition<P>
                     router.fmt(net)
                 )
             }
+            Self::NoSelectedRoute { router, prefix } => {
+                format!("{} selects no route for {prefix}", router.fmt(net))
+            }
+            Self::NoReceivedRoute { router, prefix } => {
+                format!("{} receives no route for {prefix}", router.fmt(net))
+            }
             Self::AvailableRoute {
                 router,
                 prefix,
@@ -504,6 +690,13 @@ pub enum AtomicConditionExt<P: Prefix> {
         /// Condition on a single rib entry
         cond: RibCond<P>,
     },
+    /// No incoming RIB entry exists, not even one that is denied by an incoming route-map.
+    NoReceivedRoute {
+        /// Router which must be checked
+        router: RouterId,
+        /// Destination which must be checked.
+        prefix: P,
+    },
     /// A given BGP session is established
     BgpSessionEstablished {
         /// Router which must be checked
@@ -530,7 +723,11 @@ impl<P: Prefix> AtomicConditionExt<P> {
     /// Check if the condition holds for a given RIB entry.
     pub fn check<Q>(&self, net: &Network<P, Q>) -> Result<bool, NetworkError> {
         match self {
-            AtomicConditionExt::None | AtomicConditionExt::BgpSessionEstablished { .. } => Ok(true),
+            AtomicConditionExt::None => Ok(true),
+            AtomicConditionExt::BgpSessionEstablished { router, neighbor } => Ok(net
+                .get_bgp_session_state(*router, *neighbor)?
+                .map(|state| state.is_established())
+                .unwrap_or(false)),
             AtomicConditionExt::CurrentRib {
                 router,
                 prefix,
@@ -560,24 +757,43 @@ impl<P: Prefix> AtomicConditionExt<P> {
                 .values()
                 .flatten()
                 .any(|(x, _)| cond.check(x))),
+            AtomicConditionExt::NoReceivedRoute { router, prefix } => Ok(net
+                .get_device(*router)
+                .internal_or_err()?
+                .get_bgp_rib_in()
+                .get(prefix)
+                .map_or(true, |rib_in| rib_in.is_empty())),
             AtomicConditionExt::RoutesLessPreferred {
                 router,
                 prefix,
                 good_neighbors,
                 route,
             } => {
-                let rib_in = net
-                    .get_device(*router)
-                    .internal_or_err()?
-                    .get_processed_bgp_rib()
-                    .get(prefix)
-                    .cloned();
+                let r = net.get_device(*router).internal_or_err()?;
+                let rib_in = r.get_processed_bgp_rib().get(prefix).cloned();
+                // The age of `route` is only known once it is received from the good neighbor.
+                let mut route = route.clone();
+                if let Some((e, _)) = rib_in
+                    .iter()
+                    .flatten()
+                    .find(|(e, _)| e.from_id == route.from_id)
+                {
+                    route.received = e.received;
+                }
+                // compare the routes the same way as the router does during the decision process.
+                let less_preferred = |e: &BgpRibEntry<P>| {
+                    e.compare(
+                        &route,
+                        r.get_always_compare_med(),
+                        r.get_compare_router_id(),
+                    ) == Ordering::Less
+                };
 
                 Ok(rib_in
                     .iter()
                     .flatten()
                     .filter(|(e, _)| !good_neighbors.contains(&e.from_id))
-                    .all(|(e, _)| e < route)
+                    .all(|(e, _)| less_preferred(e))
                     && rib_in
                         .iter()
                         .flatten()
@@ -667,6 +883,9 @@ impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for AtomicConditionExt
             AtomicConditionExt::AnyKnownRoute { router, cond } => {
                 format!("RibInAny at {}: {}", router.fmt(net), cond.fmt(net))
             }
+            AtomicConditionExt::NoReceivedRoute { router, prefix } => {
+                format!("RibIn at {} for {}: None", router.fmt(net), prefix)
+            }
             AtomicConditionExt::BgpSessionEstablished { router, neighbor } => format!(
                 "BGP Session between {} and {} established",
                 router.fmt(net),
@@ -35,7 +35,7 @@ fn main() {
         };
         routes = {
             e0 -> "10.0.0.0/8" as {path: [1, 3, 4], med: 100, community: 20};
-            e1 -> "10.0.0.0/8" as {path: [2, 4]};
+            e1 -> "10.0.0.0/8" as {path: [2, 4], origin: egp};
         };
         return ((b0, b1), (e0, e1))
     };
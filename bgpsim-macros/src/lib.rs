@@ -51,7 +51,7 @@ use syn::parse_macro_input;
 ///   not already defined in `links`.
 ///
 /// - `routes`: An enumeration of all BGP announcements from external routers. Each announcement is
-///   written as `SRC -> PREFIX as {path: P, [med: M], [communities: C]}`. The symbols mean the
+///   written as `SRC -> PREFIX as {path: P, [med: M], [communities: C], [origin: O]}`. The symbols mean the
 ///   following:
 ///   - `SRC` is the external router that announces the prefix.
 ///   - `PREFIX` is the prefix that should be announced. The prefix can either be a number, a string
@@ -65,6 +65,8 @@ use syn::parse_macro_input;
 ///   - `C` is the set of communities present in the route, and is optional. Similar to `P`, it can
 ///     also either take a single number, an array of numbers, or any other arbitrary expression
 ///     that evaluates to `impl Iterator<Item = I> where I: Into<u32>`.
+///   - `O` is the ORIGIN attribute, and is optional (defaults to IGP). It can either be one of the
+///     identifiers `igp`, `egp`, or `incomplete`, or an expression that evaluates to `Origin`.
 ///
 /// - `Prefix`: The type of the prefix. Choose either `SinglePrefix`, `SimplePrefix`, or
 ///   `Ipv4Prefix` here (optional).
@@ -102,7 +104,7 @@ use syn::parse_macro_input;
 ///     };
 ///     routes = {
 ///         e0 -> "10.0.0.0/8" as {path: [1, 3, 4], med: 100, community: 20};
-///         e1 -> "10.0.0.0/8" as {path: [2, 4]};
+///         e1 -> "10.0.0.0/8" as {path: [2, 4], origin: egp};
 ///     };
 ///     return ((b0, b1), (e0, e1))
 /// };
@@ -157,12 +159,13 @@ use syn::parse_macro_input;
 ///             Some(100),
 ///             [20],
 ///         ).unwrap();
-///     _net.advertise_external_route(
+///     _net.advertise_external_route_with_origin(
 ///             e1,
 ///             Ipv4Net::new(Ipv4Addr::new(10, 0, 0, 0),8).unwrap(),
 ///             [2, 4],
 ///             None,
 ///             [],
+///             ::bgpsim::bgp::Origin::Egp,
 ///         ).unwrap();
 ///     (_net, ((b0, b1), (e0, e1)))
 /// };
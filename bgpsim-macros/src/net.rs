@@ -215,8 +215,15 @@ impl Net {
                     quote!(None)
                 });
                 let communities = r.communities.quote(|c| quote!([#(#c),*]));
-                quote! {
-                    _net.advertise_external_route(#source, #prefix, #as_path, #med, #communities).unwrap();
+                if let Some(origin) = r.origin.as_ref() {
+                    let origin = origin.quote(|o| quote!(::bgpsim::bgp::Origin::#o));
+                    quote! {
+                        _net.advertise_external_route_with_origin(#source, #prefix, #as_path, #med, #communities, #origin).unwrap();
+                    }
+                } else {
+                    quote! {
+                        _net.advertise_external_route(#source, #prefix, #as_path, #med, #communities).unwrap();
+                    }
                 }
             })
             .collect::<Vec<_>>();
@@ -343,6 +350,7 @@ impl Net {
             as_path,
             med,
             communities,
+            origin,
         } in routes
         {
             let src = self.register_node(src)?;
@@ -352,6 +360,7 @@ impl Net {
                 as_path,
                 med,
                 communities,
+                origin,
             });
         }
 
@@ -542,6 +551,7 @@ struct Route<N> {
     as_path: MaybeExpr<Vec<LitInt>>,
     med: MaybeExpr<Option<LitInt>>,
     communities: MaybeExpr<Vec<LitInt>>,
+    origin: Option<MaybeExpr<Ident>>,
 }
 
 impl Parse for Route<Node> {
@@ -560,6 +570,7 @@ impl Parse for Route<Node> {
         let mut as_path = None;
         let mut med = MaybeExpr::Other(None);
         let mut communities = MaybeExpr::Other(Vec::new());
+        let mut origin = None;
 
         fn parse_list_ints(expr: Expr) -> Result<MaybeExpr<Vec<LitInt>>> {
             match expr {
@@ -594,6 +605,23 @@ impl Parse for Route<Node> {
             }
         }
 
+        fn parse_origin(expr: Expr) -> MaybeExpr<Ident> {
+            if let Expr::Path(p) = &expr {
+                if let Some(ident) = p.path.get_ident() {
+                    let variant = match ident.to_string().to_lowercase().as_str() {
+                        "igp" => Some("Igp"),
+                        "egp" => Some("Egp"),
+                        "incomplete" => Some("Incomplete"),
+                        _ => None,
+                    };
+                    if let Some(variant) = variant {
+                        return MaybeExpr::Other(Ident::new(variant, ident.span()));
+                    }
+                }
+            }
+            MaybeExpr::Expr(expr)
+        }
+
         for field in route {
             match field.member {
                 syn::Member::Named(n) => match n.to_string().as_str() {
@@ -606,12 +634,13 @@ impl Parse for Route<Node> {
                     "community" | "communities" => {
                         communities = parse_list_ints(field.expr)?;
                     }
-                    _ => {
-                        return Err(Error::new(
-                            n.span(),
-                            "Unknown field! Expected either `path`, `med`, or `communities`",
-                        ))
+                    "origin" => {
+                        origin = Some(parse_origin(field.expr));
                     }
+                    _ => return Err(Error::new(
+                        n.span(),
+                        "Unknown field! Expected either `path`, `med`, `communities`, or `origin`",
+                    )),
                 },
                 syn::Member::Unnamed(i) => {
                     return Err(Error::new(i.span(), "Only named attributes are allowed!"));
@@ -625,6 +654,7 @@ impl Parse for Route<Node> {
             as_path: as_path.ok_or(missing_as_path)?,
             med,
             communities,
+            origin,
         })
     }
 }
@@ -69,9 +69,11 @@ pub fn BgpSessionQueue(props: &BgpSessionQueueProps) -> Html {
             let p = get_event_pos(p, num, overlap);
             let i = *i;
             match event.clone() {
+                Event::Bgp(_, _, _, BgpsimBgpEvent::Open) => html!(),
                 Event::Bgp(_, src, dst, event) => {
                     html! { <BgpEvent {p} {src} {dst} {event} {i} /> }
                 }
+                Event::Ospf(_, _, _, _) | Event::Timer(_, _, _, _) => html!(),
             }
         })
         .collect()
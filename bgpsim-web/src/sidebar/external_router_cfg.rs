@@ -114,12 +114,13 @@ pub fn ExternalRouterCfg(props: &Properties) -> Html {
             if prefix != route.prefix {
                 let _ = net.net_mut().retract_external_route(id, prefix);
             }
-            let _ = net.net_mut().advertise_external_route(
+            let _ = net.net_mut().advertise_external_route_with_origin(
                 id,
                 route.prefix,
                 route.as_path,
                 route.med,
                 route.community,
+                route.origin,
             );
         });
     });
@@ -17,7 +17,7 @@
 
 use bgpsim::{
     bgp::BgpEvent,
-    event::{Event, EventQueue},
+    event::{Event, EventQueue, TimerEvent},
     formatter::NetworkFormatter,
     interactive::InteractiveNetwork,
 };
@@ -85,11 +85,30 @@ pub struct QueueEventCfgProps {
 pub fn QueueEventCfg(props: &QueueEventCfgProps) -> Html {
     let pos = props.pos;
 
-    let (src, dst, prefix, route) = match &props.event {
-        Event::Bgp(_, src, dst, BgpEvent::Update(route)) => {
-            (*src, *dst, route.prefix, Some(route.clone()))
+    let (src, dst, kind, content) = match &props.event {
+        Event::Bgp(_, src, dst, BgpEvent::Update(route)) => (
+            *src,
+            *dst,
+            "BGP Update",
+            html!(<RouteTable route={route.clone()} />),
+        ),
+        Event::Bgp(_, src, dst, BgpEvent::Withdraw(prefix)) => (
+            *src,
+            *dst,
+            "BGP Withdraw",
+            html!(<PrefixTable prefix={*prefix} />),
+        ),
+        Event::Bgp(_, src, dst, BgpEvent::Open) => (*src, *dst, "BGP Open", html!()),
+        Event::Ospf(_, src, dst, _) => (*src, *dst, "OSPF LS Update", html!()),
+        Event::Timer(_, router, _, TimerEvent::BgpMrai(neighbor)) => {
+            (*router, *neighbor, "BGP MRAI Timer", html!())
+        }
+        Event::Timer(_, router, _, TimerEvent::BgpConnect(neighbor)) => {
+            (*router, *neighbor, "BGP Connect Timer", html!())
+        }
+        Event::Timer(_, router, _, TimerEvent::BgpHold(neighbor)) => {
+            (*router, *neighbor, "BGP Hold Timer", html!())
         }
-        Event::Bgp(_, src, dst, BgpEvent::Withdraw(p)) => (*src, *dst, *p, None),
     };
     let state = Dispatch::<State>::new();
 
@@ -102,10 +121,6 @@ pub fn QueueEventCfg(props: &QueueEventCfgProps) -> Html {
 
     log::debug!("render QueueEventCfg {header}");
 
-    let (kind, content) = match route {
-        Some(route) => ("BGP Update", html!(<RouteTable {route} />)),
-        None => ("BGP Withdraw", html!(<PrefixTable {prefix} />)),
-    };
     let title = format!("{header}: {kind}");
 
     let onclick: Callback<MouseEvent> = if props.executable {
@@ -227,7 +242,10 @@ fn allow_swap(queue: &Queue, pos: usize) -> bool {
     }
     !matches! {
         (queue.get(pos), queue.get(pos + 1)),
-        (Some(Event::Bgp(_, s1, d1, _)), Some(Event::Bgp(_, s2, d2, _)))
+        (
+            Some(Event::Bgp(_, s1, d1, _) | Event::Ospf(_, s1, d1, _)),
+            Some(Event::Bgp(_, s2, d2, _) | Event::Ospf(_, s2, d2, _)),
+        )
         if (s1, d1) == (s2, d2)
     }
 }
@@ -236,9 +254,12 @@ fn allow_execute(queue: &Queue, pos: usize) -> bool {
     if pos >= queue.len() {
         return false;
     }
-    if let Some(Event::Bgp(_, src, dst, _)) = queue.get(pos) {
+    if let Some(Event::Bgp(_, src, dst, _) | Event::Ospf(_, src, dst, _)) = queue.get(pos) {
         for k in 0..pos {
-            if matches!(queue.get(k), Some(Event::Bgp(_, s, d, _)) if (src, dst) == (s, d)) {
+            if matches!(
+                queue.get(k),
+                Some(Event::Bgp(_, s, d, _) | Event::Ospf(_, s, d, _)) if (src, dst) == (s, d)
+            ) {
                 return false;
             }
         }
@@ -18,9 +18,10 @@
 use std::{collections::BTreeSet, iter::once, rc::Rc, str::FromStr};
 
 use bgpsim::{
+    bgp::Origin,
     formatter::NetworkFormatter,
     prefix,
-    route_map::{RouteMapMatch, RouteMapMatchAsPath, RouteMapMatchClause},
+    route_map::{AsPathRegex, RouteMapMatch, RouteMapMatchAsPath, RouteMapMatchClause},
     types::RouterId,
 };
 use itertools::Itertools;
@@ -128,12 +129,13 @@ impl Component for RouteMapMatchCfg {
             Msg::KindUpdate(k) => ctx.props().on_update.emit((ctx.props().index, Some(k))),
             Msg::Delete => ctx.props().on_update.emit((ctx.props().index, None)),
             Msg::InputChange(s) => {
-                self.correct = MatchValue::parse(&s)
+                self.correct = MatchValue::parse(&s, &ctx.props().m)
                     .and_then(|x| match_update(&ctx.props().m, x))
                     .is_some();
             }
             Msg::InputSet(s) => {
-                if let Some(m) = MatchValue::parse(&s).and_then(|x| match_update(&ctx.props().m, x))
+                if let Some(m) = MatchValue::parse(&s, &ctx.props().m)
+                    .and_then(|x| match_update(&ctx.props().m, x))
                 {
                     ctx.props().on_update.emit((ctx.props().index, Some(m)))
                 }
@@ -168,10 +170,14 @@ enum MatchValue {
     List(BTreeSet<u32>),
     PrefixList(BTreeSet<Pfx>),
     Range(u32, u32),
+    Text(String),
 }
 
 impl MatchValue {
-    fn parse(s: &str) -> Option<Self> {
+    fn parse(s: &str, m: &RouteMapMatch<Pfx>) -> Option<Self> {
+        if matches!(m, RouteMapMatch::AsPath(RouteMapMatchAsPath::Regex(_))) {
+            return Some(Self::Text(s.to_string()));
+        }
         if let Ok(p) = Pfx::from_str(s) {
             return Some(Self::PrefixList(once(p).collect()));
         }
@@ -209,6 +215,7 @@ impl MatchValue {
             MatchValue::List(x) => x.iter().join("; "),
             MatchValue::PrefixList(x) => x.iter().join("; "),
             MatchValue::Range(x, y) => format!("{x} - {y}"),
+            MatchValue::Text(x) => x.clone(),
         }
     }
 }
@@ -218,9 +225,13 @@ fn match_kind_text(m: &RouteMapMatch<Pfx>) -> &'static str {
         RouteMapMatch::Prefix(_) => "Prefix in",
         RouteMapMatch::AsPath(RouteMapMatchAsPath::Contains(_)) => "Path has",
         RouteMapMatch::AsPath(RouteMapMatchAsPath::Length(_)) => "Path len",
+        RouteMapMatch::AsPath(RouteMapMatchAsPath::Regex(_)) => "Path matches",
         RouteMapMatch::NextHop(_) => "Next-Hop is",
         RouteMapMatch::Community(_) => "Has community",
         RouteMapMatch::DenyCommunity(_) => "Deny community",
+        RouteMapMatch::Origin(Origin::Igp) => "Origin is IGP",
+        RouteMapMatch::Origin(Origin::Egp) => "Origin is EGP",
+        RouteMapMatch::Origin(Origin::Incomplete) => "Origin is incomplete",
     }
 }
 
@@ -231,9 +242,13 @@ fn match_kind_options() -> Vec<(RouteMapMatch<Pfx>, String)> {
         RouteMapMatch::AsPath(RouteMapMatchAsPath::Length(RouteMapMatchClause::Range(
             1, 10,
         ))),
+        RouteMapMatch::AsPath(RouteMapMatchAsPath::Regex(AsPathRegex::new("_0_").unwrap())),
         RouteMapMatch::NextHop(0.into()),
         RouteMapMatch::Community(0),
         RouteMapMatch::DenyCommunity(0),
+        RouteMapMatch::Origin(Origin::Igp),
+        RouteMapMatch::Origin(Origin::Egp),
+        RouteMapMatch::Origin(Origin::Incomplete),
     ]
     .map(|kind| {
         let text = match_kind_text(&kind).to_string();
@@ -253,6 +268,9 @@ fn match_values(m: &RouteMapMatch<Pfx>) -> MatchValue {
         RouteMapMatch::AsPath(RouteMapMatchAsPath::Length(RouteMapMatchClause::Range(v1, v2))) => {
             MatchValue::Range(*v1 as u32, *v2 as u32)
         }
+        RouteMapMatch::AsPath(RouteMapMatchAsPath::Regex(r)) => {
+            MatchValue::Text(r.as_str().to_string())
+        }
         RouteMapMatch::NextHop(v) => MatchValue::Router(*v),
         RouteMapMatch::Community(v) => MatchValue::Integer(*v),
         RouteMapMatch::DenyCommunity(v) => MatchValue::Integer(*v),
@@ -278,11 +296,15 @@ fn match_update(m: &RouteMapMatch<Pfx>, val: MatchValue) -> Option<RouteMapMatch
                 x as usize, y as usize,
             )))
         }
+        (RouteMapMatch::AsPath(RouteMapMatchAsPath::Regex(_)), MatchValue::Text(x)) => {
+            RouteMapMatch::AsPath(RouteMapMatchAsPath::Regex(AsPathRegex::new(x).ok()?))
+        }
         (RouteMapMatch::NextHop(_), MatchValue::Router(r)) => RouteMapMatch::NextHop(r),
         (RouteMapMatch::Community(_), MatchValue::Integer(x)) => RouteMapMatch::Community(x),
         (RouteMapMatch::DenyCommunity(_), MatchValue::Integer(x)) => {
             RouteMapMatch::DenyCommunity(x)
         }
+        (RouteMapMatch::Origin(o), MatchValue::None) => RouteMapMatch::Origin(*o),
         _ => return None,
     })
 }
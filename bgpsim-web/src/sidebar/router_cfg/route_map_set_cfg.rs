@@ -17,7 +17,12 @@
 
 use std::rc::Rc;
 
-use bgpsim::{formatter::NetworkFormatter, route_map::RouteMapSet, types::RouterId};
+use bgpsim::{
+    bgp::Origin,
+    formatter::NetworkFormatter,
+    route_map::RouteMapSet,
+    types::{AsId, RouterId},
+};
 use yew::prelude::*;
 use yewdux::prelude::*;
 
@@ -128,7 +133,7 @@ impl Component for RouteMapSetCfg {
             }
             Msg::InputSetRouter(r) => {
                 self.value = SetValue::Router(r);
-                if let Some(set) = set_update(&ctx.props().set, self.value) {
+                if let Some(set) = set_update(&ctx.props().set, self.value.clone()) {
                     ctx.props().on_update.emit((ctx.props().index, Some(set)))
                 }
             }
@@ -149,12 +154,13 @@ impl RouteMapSetCfg {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 enum SetValue {
     None,
     Integer(u32),
     Float(f64),
     Router(RouterId),
+    List(Vec<u32>),
 }
 
 impl SetValue {
@@ -163,6 +169,12 @@ impl SetValue {
             .map(Self::Integer)
             .ok()
             .or_else(|| s.parse::<f64>().map(Self::Float).ok())
+            .or_else(|| {
+                s.split_whitespace()
+                    .map(|x| x.parse::<u32>().ok())
+                    .collect::<Option<Vec<u32>>>()
+                    .map(Self::List)
+            })
     }
 
     fn fmt(&self, net: &Net) -> String {
@@ -171,6 +183,11 @@ impl SetValue {
             SetValue::Integer(x) => x.to_string(),
             SetValue::Float(x) => x.to_string(),
             SetValue::Router(r) => r.fmt(&net.net()).to_string(),
+            SetValue::List(x) => x
+                .iter()
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
         }
     }
 }
@@ -182,6 +199,10 @@ fn set_kind_text(set: &RouteMapSet) -> &'static str {
         RouteMapSet::LocalPref(None) => "clear Local Pref",
         RouteMapSet::Med(Some(_)) => "set MED",
         RouteMapSet::Med(None) => "clear MED",
+        RouteMapSet::Origin(Origin::Igp) => "set origin IGP",
+        RouteMapSet::Origin(Origin::Egp) => "set origin EGP",
+        RouteMapSet::Origin(Origin::Incomplete) => "set origin incomplete",
+        RouteMapSet::PrependAsPath(_) => "prepend AS path",
         RouteMapSet::IgpCost(_) => "IGP weight",
         RouteMapSet::SetCommunity(_) => "set community",
         RouteMapSet::DelCommunity(_) => "del community",
@@ -197,6 +218,10 @@ fn set_kind_options(router: RouterId) -> Vec<(RouteMapSet, String)> {
         RouteMapSet::LocalPref(None),
         RouteMapSet::Med(Some(100)),
         RouteMapSet::Med(None),
+        RouteMapSet::Origin(Origin::Igp),
+        RouteMapSet::Origin(Origin::Egp),
+        RouteMapSet::Origin(Origin::Incomplete),
+        RouteMapSet::PrependAsPath(vec![AsId(0)]),
         RouteMapSet::IgpCost(1.0),
         RouteMapSet::SetCommunity(0),
         RouteMapSet::DelCommunity(0),
@@ -218,6 +243,8 @@ fn set_value(set: &RouteMapSet) -> SetValue {
         RouteMapSet::LocalPref(None) => SetValue::None,
         RouteMapSet::Med(Some(x)) => SetValue::Integer(*x),
         RouteMapSet::Med(None) => SetValue::None,
+        RouteMapSet::Origin(_) => SetValue::None,
+        RouteMapSet::PrependAsPath(x) => SetValue::List(x.iter().map(|x| x.0).collect()),
         RouteMapSet::IgpCost(x) => SetValue::Float(*x),
         RouteMapSet::SetCommunity(x) => SetValue::Integer(*x),
         RouteMapSet::DelCommunity(x) => SetValue::Integer(*x),
@@ -233,6 +260,13 @@ fn set_update(set: &RouteMapSet, val: SetValue) -> Option<RouteMapSet> {
         (RouteMapSet::LocalPref(None), SetValue::None) => RouteMapSet::LocalPref(None),
         (RouteMapSet::Med(Some(_)), SetValue::Integer(x)) => RouteMapSet::Med(Some(x)),
         (RouteMapSet::Med(None), SetValue::None) => RouteMapSet::Med(None),
+        (RouteMapSet::Origin(o), SetValue::None) => RouteMapSet::Origin(*o),
+        (RouteMapSet::PrependAsPath(_), SetValue::Integer(x)) => {
+            RouteMapSet::PrependAsPath(vec![AsId(x)])
+        }
+        (RouteMapSet::PrependAsPath(_), SetValue::List(x)) => {
+            RouteMapSet::PrependAsPath(x.into_iter().map(AsId).collect())
+        }
         (RouteMapSet::IgpCost(_), SetValue::Float(x)) => RouteMapSet::IgpCost(x),
         (RouteMapSet::IgpCost(_), SetValue::Integer(x)) => RouteMapSet::IgpCost(x as f64),
         (RouteMapSet::SetCommunity(_), SetValue::Integer(x)) => RouteMapSet::SetCommunity(x),
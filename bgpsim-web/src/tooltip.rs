@@ -170,6 +170,15 @@ impl Component for Tooltip {
                         Event::Bgp(_, _, _, BgpEvent::Withdraw(prefix)) => {
                             html! { <PrefixTable prefix={*prefix} /> }
                         }
+                        Event::Bgp(_, _, _, BgpEvent::Open) => {
+                            html! { <p> {"BGP Open"} </p> }
+                        }
+                        Event::Ospf(_, _, _, event) => {
+                            html! { <p> {event.fmt(&self.net.net())} </p> }
+                        }
+                        Event::Timer(_, _, _, timer) => {
+                            html! { <p> {timer.fmt(&self.net.net())} </p> }
+                        }
                     };
                     html! {
                             <>
@@ -315,6 +324,7 @@ pub fn RouteTable(props: &RouteTableProps) -> Html {
                     html!{<tr> <td class="italic text-main-ia"> {"MED: "} </td> <td> {med} </td> </tr>}
                 } else { html!{} }
             }
+            <tr> <td class="italic text-main-ia"> {"Origin: "} </td> <td> {props.route.origin.to_string()} </td> </tr>
             {
                 if !props.route.community.is_empty() {
                     html!{<tr> <td class="italic text-main-ia"> {"Communities: "} </td> <td> {join(props.route.community.iter(), ", ")} </td> </tr>}
@@ -26,9 +26,33 @@ use ordered_float::NotNan;
 use serde::{Deserialize, Serialize};
 use std::{cmp::Ordering, collections::BTreeSet, hash::Hash};
 
+/// BGP ORIGIN attribute, describing how the route was originally injected into BGP. During the
+/// decision process, lower values are preferred (`Igp` over `Egp` over `Incomplete`).
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+pub enum Origin {
+    /// The route was originated by an interior gateway protocol (or a `network` statement).
+    #[default]
+    Igp,
+    /// The route was learned via EGP.
+    Egp,
+    /// The origin of the route is unknown (e.g., redistributed routes).
+    Incomplete,
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Origin::Igp => write!(f, "IGP"),
+            Origin::Egp => write!(f, "EGP"),
+            Origin::Incomplete => write!(f, "incomplete"),
+        }
+    }
+}
+
 /// Bgp Route
 /// The following attributes are omitted
-/// - ORIGIN: assumed to be always set to IGP
 /// - ATOMIC_AGGREGATE: not used
 /// - AGGREGATOR: not used
 #[derive(Debug, Clone, Eq, Serialize, Deserialize)]
@@ -44,6 +68,9 @@ pub struct BgpRoute<P: Prefix> {
     pub local_pref: Option<u32>,
     /// MED (Multi-Exit Discriminator)
     pub med: Option<u32>,
+    /// ORIGIN
+    #[serde(default)]
+    pub origin: Origin,
     /// Community
     pub community: BTreeSet<u32>,
     /// Optional field ORIGINATOR_ID
@@ -73,6 +100,7 @@ impl<P: Prefix> BgpRoute<P> {
             next_hop,
             local_pref: None,
             med,
+            origin: Origin::Igp,
             community: community.into_iter().collect(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -96,6 +124,7 @@ impl<P: Prefix> BgpRoute<P> {
             next_hop: self.next_hop,
             local_pref: Some(self.local_pref.unwrap_or(100)),
             med: Some(self.med.unwrap_or(0)),
+            origin: self.origin,
             community: self.community.clone(),
             originator_id: self.originator_id,
             cluster_list: self.cluster_list.clone(),
@@ -112,6 +141,7 @@ impl<P: Prefix> PartialEq for BgpRoute<P> {
             && s.next_hop == o.next_hop
             && s.local_pref == o.local_pref
             && s.med == o.med
+            && s.origin == o.origin
             && s.community == o.community
             && s.originator_id == o.originator_id
             && s.cluster_list == o.cluster_list
@@ -140,6 +170,12 @@ impl<P: Prefix> PartialOrd for BgpRoute<P> {
             Ordering::Less => return Some(Ordering::Greater),
         }
 
+        match s.origin.cmp(&o.origin) {
+            Ordering::Equal => {}
+            Ordering::Greater => return Some(Ordering::Less),
+            Ordering::Less => return Some(Ordering::Greater),
+        }
+
         if s.as_path.first() == o.as_path.first() {
             match s.med.unwrap().cmp(&o.med.unwrap()) {
                 Ordering::Equal => {}
@@ -170,6 +206,7 @@ impl<P: Prefix> Hash for BgpRoute<P> {
         s.next_hop.hash(state);
         s.local_pref.hash(state);
         s.med.hash(state);
+        s.origin.hash(state);
         s.community.hash(state);
     }
 }
@@ -229,6 +266,177 @@ impl BgpSessionType {
     }
 }
 
+/// Minimum Route Advertisement Interval (MRAI) of a BGP session. After sending an update to the
+/// neighbor, the router waits for `interval` seconds before sending the next update for any
+/// prefix. All changes that happen in the meantime are batched, and only the most recent route is
+/// advertised once the timer expires.
+///
+/// The timer is modelled using [`crate::event::Event::Timer`]. Queues that are aware of time (like
+/// `SimpleTimingModel` or `GeoTimingModel`) schedule the timer to expire after `interval`
+/// seconds, while the [`crate::event::BasicEventQueue`] simply enqueues it after all other events.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Mrai {
+    /// The minimum time (in seconds) between two updates sent to the neighbor.
+    pub interval: f64,
+    /// Whether withdrawals are also rate-limited. If `false`, withdrawals are sent immediately (as
+    /// suggested by RFC 4271). Cisco IOS and FRR cannot be configured to rate-limit withdrawals;
+    /// the exported configuration only contains the interval.
+    pub withdrawals: bool,
+}
+
+impl Mrai {
+    /// Create a new MRAI that only rate-limits updates.
+    pub fn new(interval: f64) -> Self {
+        Self {
+            interval,
+            withdrawals: false,
+        }
+    }
+
+    /// Create a new MRAI that rate-limits both updates and withdrawals.
+    pub fn with_withdrawals(interval: f64) -> Self {
+        Self {
+            interval,
+            withdrawals: true,
+        }
+    }
+
+    /// Returns `true` if the event must be rate-limited by the MRAI.
+    pub(crate) fn applies_to<P: Prefix>(&self, event: &BgpEvent<P>) -> bool {
+        self.interval > 0.0 && (self.withdrawals || matches!(event, BgpEvent::Update(_)))
+    }
+}
+
+/// State of a BGP session, following the finite-state machine of RFC 4271. The state machine is
+/// only simulated if it is enabled (see [`crate::network::Network::set_bgp_session_timers`]).
+/// Otherwise, all configured sessions are immediately `Established`.
+///
+/// The `Active` and `OpenConfirm` states are not modelled. A session moves to `Connect` once it is
+/// configured. After the establishment delay, the router sends an OPEN message and moves to
+/// `OpenSent`, or falls back to `Idle` if the neighbor is not reachable in the IGP. As soon as it
+/// receives an OPEN message from the neighbor, the session is `Established`, and both routers
+/// exchange their routes. A router also accepts an OPEN message while being in `Idle` or `Connect`,
+/// in which case it answers with an OPEN message itself.
+///
+/// If the neighbor of an established session becomes unreachable, the router starts the hold
+/// timer. If the neighbor is still unreachable once it expires, the session goes down (`Idle`), and
+/// all routes learned from that neighbor are removed. An `Idle` session starts connecting again as
+/// soon as the neighbor is reachable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BgpSessionState {
+    /// The session is down, because the neighbor is not reachable.
+    Idle,
+    /// The router waits for the TCP connection to be established.
+    Connect,
+    /// The router has sent an OPEN message, and waits for the OPEN message of the neighbor.
+    OpenSent,
+    /// The session is established, and routes are exchanged.
+    Established,
+}
+
+impl BgpSessionState {
+    /// Returns `true` if the session is established.
+    pub fn is_established(&self) -> bool {
+        matches!(self, Self::Established)
+    }
+}
+
+impl std::fmt::Display for BgpSessionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BgpSessionState::Idle => write!(f, "Idle"),
+            BgpSessionState::Connect => write!(f, "Connect"),
+            BgpSessionState::OpenSent => write!(f, "OpenSent"),
+            BgpSessionState::Established => write!(f, "Established"),
+        }
+    }
+}
+
+/// Timers of the BGP session finite-state machine (see [`BgpSessionState`]).
+///
+/// Both timers are modelled using [`crate::event::Event::Timer`]. Queues that are aware of time
+/// (like `SimpleTimingModel` or `GeoTimingModel`) schedule them accordingly, while the
+/// [`crate::event::BasicEventQueue`] simply enqueues them after all other events.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BgpSessionTimers {
+    /// Time (in seconds) between configuring the session and sending the OPEN message, i.e., the
+    /// time it takes to establish the TCP connection.
+    pub establishment_delay: f64,
+    /// Time (in seconds) an established session stays up after the neighbor became unreachable.
+    /// As KEEPALIVE messages are not simulated, the hold timer is started as soon as the neighbor is
+    /// no longer reachable in the IGP.
+    pub hold_time: f64,
+}
+
+impl BgpSessionTimers {
+    /// Create new session timers.
+    pub fn new(establishment_delay: f64, hold_time: f64) -> Self {
+        Self {
+            establishment_delay,
+            hold_time,
+        }
+    }
+}
+
+impl Default for BgpSessionTimers {
+    /// Establish the session after 1 second, and use the default hold time of 180 seconds.
+    fn default() -> Self {
+        Self::new(1.0, 180.0)
+    }
+}
+
+/// Configuration of a BGP aggregate (`aggregate-address`). A router originates the aggregate as
+/// long as its BGP table contains at least one more-specific route of the aggregate prefix (the
+/// *contributing* routes). Once the last contributing route disappears, the aggregate is withdrawn.
+///
+/// The aggregate route is originated with the router as next-hop. In the forwarding table, it
+/// points to a discard interface, such that packets matching the aggregate, but none of the
+/// more-specific routes, are dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct BgpAggregate {
+    /// Suppress the advertisement of all contributing routes, such that only the aggregate is
+    /// advertised to the BGP peers (`summary-only`).
+    pub summary_only: bool,
+    /// Include the AS numbers of all contributing routes in the AS path of the aggregate
+    /// (`as-set`). The AS_SET is modelled as the sorted sequence of distinct AS numbers. Further,
+    /// the aggregate inherits the communities and the worst ORIGIN of all contributing routes.
+    /// Without `as-set`, the aggregate has an empty AS path and the ORIGIN `IGP`.
+    pub as_set: bool,
+}
+
+impl BgpAggregate {
+    /// Create a new aggregate configuration.
+    pub fn new(summary_only: bool, as_set: bool) -> Self {
+        Self {
+            summary_only,
+            as_set,
+        }
+    }
+
+    /// Build the aggregate route for `prefix` from all contributing routes. This function returns
+    /// `None` if there is no contributing route.
+    pub(crate) fn aggregate_route<'a, P: Prefix + 'a>(
+        &self,
+        prefix: P,
+        next_hop: RouterId,
+        contributing: impl IntoIterator<Item = &'a BgpRoute<P>>,
+    ) -> Option<BgpRoute<P>> {
+        let mut contributing = contributing.into_iter().peekable();
+        contributing.peek()?;
+        let mut route = BgpRoute::new(next_hop, prefix, Vec::<AsId>::new(), None, None);
+        if self.as_set {
+            let mut as_set = BTreeSet::new();
+            for r in contributing {
+                as_set.extend(r.as_path.iter().copied());
+                route.community.extend(r.community.iter().copied());
+                route.origin = route.origin.max(r.origin);
+            }
+            route.as_path = as_set.into_iter().collect();
+        }
+        Some(route)
+    }
+}
+
 /// BGP Events
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(bound(deserialize = "P: for<'a> serde::Deserialize<'a>"))]
@@ -237,14 +445,18 @@ pub enum BgpEvent<P: Prefix> {
     Withdraw(P),
     /// Update a route, or add a new one.
     Update(BgpRoute<P>),
+    /// Open the BGP session. Such events are only generated if the BGP session finite-state
+    /// machine is simulated (see [`BgpSessionState`]).
+    Open,
 }
 
 impl<P: Prefix> BgpEvent<P> {
-    /// Returns the prefix for which this event is responsible
-    pub fn prefix(&self) -> P {
+    /// Returns the prefix for which this event is responsible, or `None` for `BgpEvent::Open`.
+    pub fn prefix(&self) -> Option<P> {
         match self {
-            Self::Withdraw(p) => *p,
-            Self::Update(r) => r.prefix,
+            Self::Withdraw(p) => Some(*p),
+            Self::Update(r) => Some(r.prefix),
+            Self::Open => None,
         }
     }
 }
@@ -265,6 +477,10 @@ pub struct BgpRibEntry<P: Prefix> {
     pub igp_cost: Option<NotNan<LinkWeight>>,
     /// Local weight of that route, which is the most preferred metric of the entire route.
     pub weight: u32,
+    /// Sequence number of the update that carried this route, local to the receiving router.
+    /// Smaller numbers denote older routes. It is used to prefer the oldest of two eBGP routes.
+    #[serde(default)]
+    pub received: u64,
 }
 
 impl<P: Prefix> Ord for BgpRibEntry<P> {
@@ -284,72 +500,96 @@ impl<P: Prefix> PartialEq for BgpRibEntry<P> {
 
 impl<P: Prefix> PartialOrd for BgpRibEntry<P> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.compare(other, false, false))
+    }
+}
+
+impl<P: Prefix> BgpRibEntry<P> {
+    /// Compare two entries according to the BGP decision process. A route that is preferred over
+    /// the other is `Greater`. The steps of the decision process are:
+    ///
+    /// 1. Prefer the route with the higher weight.
+    /// 2. Prefer the route with the higher LOCAL_PREF.
+    /// 3. Prefer the route with the shorter AS_PATH.
+    /// 4. Prefer the route with the lower ORIGIN (`IGP` over `EGP` over `incomplete`).
+    /// 5. Prefer the route with the lower MED. MEDs are only compared if both routes are received
+    ///    from the same neighboring AS, unless `always_compare_med` is set.
+    /// 6. Prefer routes learned over eBGP over routes learned over iBGP.
+    /// 7. Prefer the route with the lower IGP cost towards the next-hop.
+    /// 8. If both routes are learned over eBGP, prefer the one that was received first, unless
+    ///    `compare_router_id` is set. This step depends on the order in which routes were received.
+    ///    Setting `compare_router_id` makes the outcome independent of that order.
+    /// 9. Prefer the route with the lower router-id (ORIGINATOR_ID if present, otherwise the
+    ///    neighbor from which the route was learned).
+    /// 10. Prefer the route with the shorter CLUSTER_LIST.
+    /// 11. Prefer the route learned from the neighbor with the lower address.
+    pub fn compare(
+        &self,
+        other: &Self,
+        always_compare_med: bool,
+        compare_router_id: bool,
+    ) -> Ordering {
         let s = self.route.clone_default();
         let o = other.route.clone_default();
 
         match self.weight.cmp(&other.weight) {
             Ordering::Equal => {}
-            o => return Some(o),
+            x => return x,
         }
 
         match s.local_pref.unwrap().cmp(&o.local_pref.unwrap()) {
             Ordering::Equal => {}
-            o => return Some(o),
+            x => return x,
         }
 
         match s.as_path.len().cmp(&o.as_path.len()) {
             Ordering::Equal => {}
-            Ordering::Greater => return Some(Ordering::Less),
-            Ordering::Less => return Some(Ordering::Greater),
+            x => return x.reverse(),
         }
 
-        if s.as_path.first() == o.as_path.first() {
+        match s.origin.cmp(&o.origin) {
+            Ordering::Equal => {}
+            x => return x.reverse(),
+        }
+
+        if always_compare_med || s.as_path.first() == o.as_path.first() {
             match s.med.unwrap().cmp(&o.med.unwrap()) {
                 Ordering::Equal => {}
-                Ordering::Greater => return Some(Ordering::Less),
-                Ordering::Less => return Some(Ordering::Greater),
+                x => return x.reverse(),
             }
         }
 
         if self.from_type.is_ebgp() && other.from_type.is_ibgp() {
-            return Some(Ordering::Greater);
+            return Ordering::Greater;
         } else if self.from_type.is_ibgp() && other.from_type.is_ebgp() {
-            return Some(Ordering::Less);
+            return Ordering::Less;
         }
 
         match self.igp_cost.unwrap().partial_cmp(&other.igp_cost.unwrap()) {
             Some(Ordering::Equal) | None => {}
-            Some(Ordering::Greater) => return Some(Ordering::Less),
-            Some(Ordering::Less) => return Some(Ordering::Greater),
+            Some(x) => return x.reverse(),
         }
 
-        match s.next_hop.cmp(&o.next_hop) {
-            Ordering::Equal => {}
-            Ordering::Greater => return Some(Ordering::Less),
-            Ordering::Less => return Some(Ordering::Greater),
+        if !compare_router_id && self.from_type.is_ebgp() && other.from_type.is_ebgp() {
+            match self.received.cmp(&other.received) {
+                Ordering::Equal => {}
+                x => return x.reverse(),
+            }
         }
 
         let s_from = s.originator_id.unwrap_or(self.from_id);
         let o_from = o.originator_id.unwrap_or(other.from_id);
         match s_from.cmp(&o_from) {
             Ordering::Equal => {}
-            Ordering::Greater => return Some(Ordering::Less),
-            Ordering::Less => return Some(Ordering::Greater),
+            x => return x.reverse(),
         }
 
         match s.cluster_list.len().cmp(&o.cluster_list.len()) {
             Ordering::Equal => {}
-            Ordering::Greater => return Some(Ordering::Less),
-            Ordering::Less => return Some(Ordering::Greater),
-        }
-
-        match self.from_id.cmp(&other.from_id) {
-            Ordering::Equal => {}
-            Ordering::Greater => return Some(Ordering::Less),
-            Ordering::Less => return Some(Ordering::Greater),
+            x => return x.reverse(),
         }
 
-        Some(Ordering::Equal)
+        self.from_id.cmp(&other.from_id).reverse()
     }
 }
 
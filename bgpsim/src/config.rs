@@ -74,14 +74,17 @@
 use log::debug;
 
 use crate::{
-    bgp::BgpSessionType,
+    bgp::{BgpAggregate, BgpSessionType, Mrai},
     event::EventQueue,
     formatter::NetworkFormatter,
     network::Network,
     ospf::OspfArea,
     route_map::{RouteMap, RouteMapDirection},
     router::StaticRoute,
-    types::{ConfigError, LinkWeight, NetworkDevice, NetworkError, Prefix, PrefixMap, RouterId},
+    types::{
+        ConfigError, LinkWeight, NetworkDevice, NetworkError, Prefix, PrefixMap, PrefixSet,
+        RouterId,
+    },
 };
 
 use petgraph::algo::FloatMeasure;
@@ -366,6 +369,38 @@ pub enum ConfigExpr<P: Prefix> {
         /// Router where to enable the load balancing
         router: RouterId,
     },
+    /// Set the Minimum Route Advertisement Interval (MRAI) of a BGP session
+    BgpMrai {
+        /// Router to configure the MRAI
+        router: RouterId,
+        /// Neighbor of the BGP session
+        neighbor: RouterId,
+        /// The MRAI of the session
+        mrai: Mrai,
+    },
+    /// Originate a BGP aggregate (`aggregate-address`) as long as a more-specific route exists
+    Aggregate {
+        /// Router to configure the aggregate
+        router: RouterId,
+        /// The aggregate prefix
+        prefix: P,
+        /// Only advertise the aggregate, but suppress all more-specific routes
+        summary_only: bool,
+        /// Collect the AS numbers of all more-specific routes in the AS path of the aggregate
+        as_set: bool,
+    },
+    /// Originate a prefix in BGP (`network`)
+    BgpNetwork {
+        /// Router originating the prefix
+        router: RouterId,
+        /// The originated prefix
+        prefix: P,
+    },
+    /// Redistribute all static routes into BGP (`redistribute static`)
+    BgpRedistributeStatic {
+        /// Router where to redistribute the static routes
+        router: RouterId,
+    },
 }
 
 impl<P: Prefix> ConfigExpr<P> {
@@ -438,6 +473,25 @@ impl<P: Prefix> ConfigExpr<P> {
             ConfigExpr::LoadBalancing { router } => {
                 ConfigExprKey::LoadBalancing { router: *router }
             }
+            ConfigExpr::BgpMrai {
+                router,
+                neighbor,
+                mrai: _,
+            } => ConfigExprKey::BgpMrai {
+                router: *router,
+                neighbor: *neighbor,
+            },
+            ConfigExpr::Aggregate { router, prefix, .. } => ConfigExprKey::Aggregate {
+                router: *router,
+                prefix: *prefix,
+            },
+            ConfigExpr::BgpNetwork { router, prefix } => ConfigExprKey::BgpNetwork {
+                router: *router,
+                prefix: *prefix,
+            },
+            ConfigExpr::BgpRedistributeStatic { router } => {
+                ConfigExprKey::BgpRedistributeStatic { router: *router }
+            }
         }
     }
 
@@ -450,6 +504,10 @@ impl<P: Prefix> ConfigExpr<P> {
             ConfigExpr::BgpRouteMap { router, .. } => vec![*router],
             ConfigExpr::StaticRoute { router, .. } => vec![*router],
             ConfigExpr::LoadBalancing { router } => vec![*router],
+            ConfigExpr::BgpMrai { router, .. } => vec![*router],
+            ConfigExpr::Aggregate { router, .. } => vec![*router],
+            ConfigExpr::BgpNetwork { router, .. } => vec![*router],
+            ConfigExpr::BgpRedistributeStatic { router } => vec![*router],
         }
     }
 }
@@ -515,6 +573,32 @@ pub enum ConfigExprKey<P> {
         /// Router to be configured
         router: RouterId,
     },
+    /// Key for the MRAI of a BGP session
+    BgpMrai {
+        /// Router to be configured
+        router: RouterId,
+        /// Neighbor of the BGP session
+        neighbor: RouterId,
+    },
+    /// Key for a BGP aggregate
+    Aggregate {
+        /// Router to be configured
+        router: RouterId,
+        /// The aggregate prefix
+        prefix: P,
+    },
+    /// Key for originating a prefix in BGP
+    BgpNetwork {
+        /// Router to be configured
+        router: RouterId,
+        /// The originated prefix
+        prefix: P,
+    },
+    /// Key for redistributing static routes into BGP
+    BgpRedistributeStatic {
+        /// Router to be configured
+        router: RouterId,
+    },
 }
 
 impl<P> ConfigExprKey<P> {
@@ -687,6 +771,28 @@ impl<P: Prefix> ConfigPatch<P> {
     }
 }
 
+impl<P: Prefix> From<ConfigModifier<P>> for ConfigPatch<P> {
+    fn from(modifier: ConfigModifier<P>) -> Self {
+        Self {
+            modifiers: vec![modifier],
+        }
+    }
+}
+
+impl<P: Prefix> From<Vec<ConfigModifier<P>>> for ConfigPatch<P> {
+    fn from(modifiers: Vec<ConfigModifier<P>>) -> Self {
+        Self { modifiers }
+    }
+}
+
+impl<P: Prefix> FromIterator<ConfigModifier<P>> for ConfigPatch<P> {
+    fn from_iter<T: IntoIterator<Item = ConfigModifier<P>>>(iter: T) -> Self {
+        Self {
+            modifiers: iter.into_iter().collect(),
+        }
+    }
+}
+
 /// Trait to manage the network using configurations, patches, and modifiers.
 pub trait NetworkConfig<P: Prefix> {
     /// Set the provided network-wide configuration. The network first computes the patch from the
@@ -812,6 +918,31 @@ impl<P: Prefix, Q: EventQueue<P>> NetworkConfig<P> for Network<P, Q> {
                     self.set_load_balancing(*router, true)?;
                     Ok(())
                 }
+                ConfigExpr::BgpMrai {
+                    router,
+                    neighbor,
+                    mrai,
+                } => self
+                    .set_bgp_mrai(*router, *neighbor, Some(*mrai))
+                    .map(|_| ()),
+                ConfigExpr::Aggregate {
+                    router,
+                    prefix,
+                    summary_only,
+                    as_set,
+                } => self
+                    .set_bgp_aggregate(
+                        *router,
+                        *prefix,
+                        Some(BgpAggregate::new(*summary_only, *as_set)),
+                    )
+                    .map(|_| ()),
+                ConfigExpr::BgpNetwork { router, prefix } => {
+                    self.set_bgp_network(*router, *prefix, true).map(|_| ())
+                }
+                ConfigExpr::BgpRedistributeStatic { router } => {
+                    self.set_bgp_redistribute_static(*router, true).map(|_| ())
+                }
             },
             ConfigModifier::Remove(expr) => match expr {
                 ConfigExpr::IgpLinkWeight {
@@ -850,6 +981,18 @@ impl<P: Prefix, Q: EventQueue<P>> NetworkConfig<P> for Network<P, Q> {
                     self.set_load_balancing(*router, false)?;
                     Ok(())
                 }
+                ConfigExpr::BgpMrai {
+                    router, neighbor, ..
+                } => self.set_bgp_mrai(*router, *neighbor, None).map(|_| ()),
+                ConfigExpr::Aggregate { router, prefix, .. } => {
+                    self.set_bgp_aggregate(*router, *prefix, None).map(|_| ())
+                }
+                ConfigExpr::BgpNetwork { router, prefix } => {
+                    self.set_bgp_network(*router, *prefix, false).map(|_| ())
+                }
+                ConfigExpr::BgpRedistributeStatic { router } => {
+                    self.set_bgp_redistribute_static(*router, false).map(|_| ())
+                }
             },
             ConfigModifier::BatchRouteMapEdit { router, updates } => {
                 self.batch_update_route_maps(*router, updates)
@@ -897,6 +1040,28 @@ impl<P: Prefix, Q: EventQueue<P>> NetworkConfig<P> for Network<P, Q> {
                     .internal()
                     .map(|r| !r.get_load_balancing())
                     .unwrap_or(false),
+                ConfigExpr::BgpMrai {
+                    router, neighbor, ..
+                } => self
+                    .get_device(*router)
+                    .internal()
+                    .map(|r| r.get_bgp_mrai(*neighbor).is_none())
+                    .unwrap_or(false),
+                ConfigExpr::Aggregate { router, prefix, .. } => self
+                    .get_device(*router)
+                    .internal()
+                    .map(|r| r.get_bgp_aggregate(*prefix).is_none())
+                    .unwrap_or(false),
+                ConfigExpr::BgpNetwork { router, prefix } => self
+                    .get_device(*router)
+                    .internal()
+                    .map(|r| !r.get_bgp_networks().contains(prefix))
+                    .unwrap_or(false),
+                ConfigExpr::BgpRedistributeStatic { router } => self
+                    .get_device(*router)
+                    .internal()
+                    .map(|r| !r.get_bgp_redistribute_static())
+                    .unwrap_or(false),
             },
             ConfigModifier::Remove(x) | ConfigModifier::Update { from: x, .. } => match x {
                 ConfigExpr::IgpLinkWeight { source, target, .. } => {
@@ -934,6 +1099,28 @@ impl<P: Prefix, Q: EventQueue<P>> NetworkConfig<P> for Network<P, Q> {
                     .internal()
                     .map(|r| r.get_load_balancing())
                     .unwrap_or(false),
+                ConfigExpr::BgpMrai {
+                    router, neighbor, ..
+                } => self
+                    .get_device(*router)
+                    .internal()
+                    .map(|r| r.get_bgp_mrai(*neighbor).is_some())
+                    .unwrap_or(false),
+                ConfigExpr::Aggregate { router, prefix, .. } => self
+                    .get_device(*router)
+                    .internal()
+                    .map(|r| r.get_bgp_aggregate(*prefix).is_some())
+                    .unwrap_or(false),
+                ConfigExpr::BgpNetwork { router, prefix } => self
+                    .get_device(*router)
+                    .internal()
+                    .map(|r| r.get_bgp_networks().contains(prefix))
+                    .unwrap_or(false),
+                ConfigExpr::BgpRedistributeStatic { router } => self
+                    .get_device(*router)
+                    .internal()
+                    .map(|r| r.get_bgp_redistribute_static())
+                    .unwrap_or(false),
             },
             ConfigModifier::BatchRouteMapEdit { router, updates } => {
                 if let Some(r) = self.get_device(*router).internal() {
@@ -1035,7 +1222,7 @@ impl<P: Prefix, Q: EventQueue<P>> NetworkConfig<P> for Network<P, Q> {
                         map: rm.clone(),
                     })?;
                 }
-                for rm in r.get_bgp_route_maps(*neighbor, RouteMapDirection::Incoming) {
+                for rm in r.get_bgp_route_maps(*neighbor, RouteMapDirection::Outgoing) {
                     c.add(ConfigExpr::BgpRouteMap {
                         router: *rid,
                         neighbor: *neighbor,
@@ -1054,6 +1241,38 @@ impl<P: Prefix, Q: EventQueue<P>> NetworkConfig<P> for Network<P, Q> {
                 })?;
             }
 
+            // get all MRAIs
+            for (neighbor, mrai) in r.get_bgp_mrais().iter() {
+                c.add(ConfigExpr::BgpMrai {
+                    router: *rid,
+                    neighbor: *neighbor,
+                    mrai: *mrai,
+                })?;
+            }
+
+            // get all aggregates
+            for (prefix, aggregate) in r.get_bgp_aggregates().iter() {
+                c.add(ConfigExpr::Aggregate {
+                    router: *rid,
+                    prefix: *prefix,
+                    summary_only: aggregate.summary_only,
+                    as_set: aggregate.as_set,
+                })?;
+            }
+
+            // get all originated prefixes
+            for prefix in r.get_bgp_networks().iter() {
+                c.add(ConfigExpr::BgpNetwork {
+                    router: *rid,
+                    prefix: *prefix,
+                })?;
+            }
+
+            // get the redistribution of static routes
+            if r.get_bgp_redistribute_static() {
+                c.add(ConfigExpr::BgpRedistributeStatic { router: *rid })?;
+            }
+
             // get all load balancing configs
             for (id, r) in self.routers.iter() {
                 if r.get_load_balancing() {
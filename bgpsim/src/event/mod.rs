@@ -19,6 +19,7 @@
 
 use std::hash::Hash;
 
+use ordered_float::NotNan;
 use serde::{Deserialize, Serialize};
 
 mod queue;
@@ -30,6 +31,7 @@ pub use rand_queue::{GeoTimingModel, ModelParams, SimpleTimingModel};
 
 use crate::{
     bgp::BgpEvent,
+    ospf::OspfEvent,
     types::{Prefix, RouterId, StepUpdate},
 };
 
@@ -42,21 +44,42 @@ use crate::{
 pub enum Event<P: Prefix, T> {
     /// BGP Event from `#1` to `#2`.
     Bgp(T, RouterId, RouterId, BgpEvent<P>),
+    /// OSPF Event from `#1` to `#2`. Such events are only generated if OSPF is simulated in the
+    /// event-driven mode.
+    Ospf(T, RouterId, RouterId, OspfEvent),
+    /// Timer of router `#1` that expires `#2` seconds after it was started. Queues that are aware
+    /// of time schedule the event accordingly, while all other queues (like [`BasicEventQueue`])
+    /// simply enqueue it.
+    Timer(T, RouterId, NotNan<f64>, TimerEvent),
+}
+
+/// Timers that can expire at a router.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TimerEvent {
+    /// The MRAI timer of the BGP session with the given neighbor has expired (see
+    /// [`crate::bgp::Mrai`]).
+    BgpMrai(RouterId),
+    /// The TCP connection to the given BGP neighbor is established, so the router can send its
+    /// OPEN message (see [`crate::bgp::BgpSessionTimers::establishment_delay`]).
+    BgpConnect(RouterId),
+    /// The hold timer of the BGP session with the given neighbor has expired (see
+    /// [`crate::bgp::BgpSessionTimers::hold_time`]).
+    BgpHold(RouterId),
 }
 
 impl<P: Prefix, T> Event<P, T> {
     /// Returns the prefix for which this event talks about.
     pub fn prefix(&self) -> Option<P> {
         match self {
-            Event::Bgp(_, _, _, BgpEvent::Update(route)) => Some(route.prefix),
-            Event::Bgp(_, _, _, BgpEvent::Withdraw(prefix)) => Some(*prefix),
+            Event::Bgp(_, _, _, bgp_event) => bgp_event.prefix(),
+            Event::Ospf(_, _, _, _) | Event::Timer(_, _, _, _) => None,
         }
     }
 
     /// Get a reference to the priority of this event.
     pub fn priority(&self) -> &T {
         match self {
-            Event::Bgp(p, _, _, _) => p,
+            Event::Bgp(p, _, _, _) | Event::Ospf(p, _, _, _) | Event::Timer(p, _, _, _) => p,
         }
     }
 
@@ -65,10 +88,22 @@ impl<P: Prefix, T> Event<P, T> {
         matches!(self, Event::Bgp(_, _, _, _))
     }
 
+    /// Returns true if the event is an OSPF message
+    pub fn is_ospf_event(&self) -> bool {
+        matches!(self, Event::Ospf(_, _, _, _))
+    }
+
+    /// Returns true if the event is an expiring timer
+    pub fn is_timer_event(&self) -> bool {
+        matches!(self, Event::Timer(_, _, _, _))
+    }
+
     /// Return the router where the event is processed
     pub fn router(&self) -> RouterId {
         match self {
-            Event::Bgp(_, _, router, _) => *router,
+            Event::Bgp(_, _, router, _)
+            | Event::Ospf(_, _, router, _)
+            | Event::Timer(_, router, _, _) => *router,
         }
     }
 }
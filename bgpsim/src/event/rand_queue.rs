@@ -83,7 +83,7 @@ impl<P: Prefix> EventQueue<P> for SimpleTimingModel<P> {
         let mut rng = thread_rng();
         // match on the event
         match event {
-            Event::Bgp(ref mut t, src, dst, _) => {
+            Event::Bgp(ref mut t, src, dst, _) | Event::Ospf(ref mut t, src, dst, _) => {
                 let key = (src, dst);
                 // compute the next time
                 let beta = self.model.get_mut(&key).unwrap_or(&mut self.default_params);
@@ -100,6 +100,11 @@ impl<P: Prefix> EventQueue<P> for SimpleTimingModel<P> {
                 }
                 *t = next_time;
             }
+            Event::Timer(ref mut t, _, delay, _) => {
+                // timers expire after a fixed delay
+                next_time += delay;
+                *t = next_time;
+            }
         }
         // enqueue with the computed time
         self.q.push(event, Reverse(next_time));
@@ -109,11 +114,12 @@ impl<P: Prefix> EventQueue<P> for SimpleTimingModel<P> {
         let (event, _) = self.q.pop()?;
         self.current_time = *event.priority();
         match event {
-            Event::Bgp(_, src, dst, _) => {
+            Event::Bgp(_, src, dst, _) | Event::Ospf(_, src, dst, _) => {
                 if let Some((num, _)) = self.messages.get_mut(&(src, dst)) {
                     *num -= 1;
                 }
             }
+            Event::Timer(_, _, _, _) => {}
         }
         Some(event)
     }
@@ -421,7 +427,7 @@ impl<P: Prefix> EventQueue<P> for GeoTimingModel<P> {
         let mut rng = thread_rng();
         // match on the event
         match event {
-            Event::Bgp(ref mut t, src, dst, _) => {
+            Event::Bgp(ref mut t, src, dst, _) | Event::Ospf(ref mut t, src, dst, _) => {
                 // compute the next time
                 let key = (src, dst);
                 // compute the propagation time
@@ -444,6 +450,11 @@ impl<P: Prefix> EventQueue<P> for GeoTimingModel<P> {
                 }
                 *t = next_time;
             }
+            Event::Timer(ref mut t, _, delay, _) => {
+                // timers expire after a fixed delay
+                next_time += delay;
+                *t = next_time;
+            }
         }
         // enqueue with the computed time
         self.q.push(event, Reverse(next_time));
@@ -453,11 +464,12 @@ impl<P: Prefix> EventQueue<P> for GeoTimingModel<P> {
         let (event, _) = self.q.pop()?;
         self.current_time = *event.priority();
         match event {
-            Event::Bgp(_, src, dst, _) => {
+            Event::Bgp(_, src, dst, _) | Event::Ospf(_, src, dst, _) => {
                 if let Some((num, _)) = self.messages.get_mut(&(src, dst)) {
                     *num -= 1;
                 }
             }
+            Event::Timer(_, _, _, _) => {}
         }
         Some(event)
     }
@@ -30,7 +30,7 @@ use itertools::Itertools;
 use petgraph::visit::EdgeRef;
 
 use crate::{
-    bgp::BgpRoute,
+    bgp::{BgpAggregate, BgpRoute, Origin},
     config::{ConfigExpr, ConfigModifier},
     network::Network,
     ospf::OspfArea,
@@ -45,9 +45,9 @@ use crate::{
 
 use super::{
     cisco_frr_generators::{
-        enable_bgp, enable_ospf, loopback_iface, AsPathList, CommunityList, Interface, PrefixList,
-        RouteMapItem, RouterBgp, RouterBgpNeighbor, RouterOspf, StaticRoute as StaticRouteGen,
-        Target,
+        comment, enable_bgp, enable_ospf, junos_as_path_regex, loopback_iface, AsPathList,
+        CommunityList, Interface, PrefixList, RouteMapItem, RouterBgp, RouterBgpNeighbor,
+        RouterOspf, StaticRoute as StaticRouteGen, Target, NEXUS_REDISTRIBUTE_STATIC_RM,
     },
     Addressor, ExportError, ExternalCfgGen, InternalCfgGen, INTERNAL_AS,
 };
@@ -130,6 +130,11 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
         })
     }
 
+    /// Get the target for which the configuration is generated.
+    pub fn target(&self) -> Target {
+        self.target
+    }
+
     /// Get the local OSPF area of the router. This is equal to the OSPF area with the lowest ID
     /// which is adjacent to that router.
     ///
@@ -192,6 +197,61 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
         }
     }
 
+    /// Get the interface builder for the interface of this router that is connected to either `a`
+    /// or `b` (see [`CiscoFrrCfgGen::iface`]). On Junos, the OSPF area of the link is set as well,
+    /// as all OSPF parameters of an interface are configured within its area.
+    fn ospf_iface<A: Addressor<P>, Q>(
+        &self,
+        net: &Network<P, Q>,
+        a: RouterId,
+        b: RouterId,
+        addressor: &mut A,
+    ) -> Result<Interface, ExportError> {
+        let mut iface = Interface::new(self.iface(a, b, addressor)?);
+        if self.target == Target::Junos {
+            iface.area(net.get_ospf_area(a, b).unwrap_or(OspfArea::BACKBONE));
+        }
+        Ok(iface)
+    }
+
+    /// Move the interface of this router that is connected to either `a` or `b` from the OSPF area
+    /// `from` into the area `to`. On Junos, the interface is removed from the old area, and
+    /// configured from scratch in the new area.
+    fn ospf_area_change<A: Addressor<P>, Q>(
+        &self,
+        net: &Network<P, Q>,
+        a: RouterId,
+        b: RouterId,
+        from: OspfArea,
+        to: OspfArea,
+        addressor: &mut A,
+    ) -> Result<String, ExportError> {
+        let iface_name = self.iface(a, b, addressor)?;
+        if self.target != Target::Junos {
+            return Ok(Interface::new(iface_name).area(to).build(self.target));
+        }
+
+        let mut iface = Interface::new(iface_name);
+        iface.area(to);
+        if let Ok(weight) = net.get_link_weigth(a, b) {
+            iface.cost(weight);
+        }
+        if let Some(hello) = self.ospf_params.0 {
+            iface.hello_interval(hello);
+        }
+        if let Some(dead) = self.ospf_params.1 {
+            iface.dead_interval(dead);
+        }
+        Ok(format!(
+            "{}{}",
+            Interface::new(iface_name)
+                .area(from)
+                .no_area()
+                .build(self.target),
+            iface.build(self.target)
+        ))
+    }
+
     /// Generate the prefix-lists for all equivalence classes
     fn pec_config<A: Addressor<P>>(&mut self, addressor: &mut A) -> String {
         // early exit if there are no pecs
@@ -199,7 +259,7 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
             return String::new();
         }
 
-        let mut config = String::from("!\n! Prefix Equivalence Classes\n!\n");
+        let mut config = section(self.target, "Prefix Equivalence Classes");
         for (prefix, networks) in addressor.get_pecs().iter() {
             let mut pl = PrefixList::new(pec_pl_name(*prefix));
             if let Some((aggregates, prefix_len)) = aggregate_pec(networks) {
@@ -211,8 +271,8 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
                     pl.prefix(*net);
                 }
             }
-            config.push_str(&pl.build());
-            config.push_str("!\n");
+            config.push_str(&pl.build(self.target));
+            config.push_str(&comment(self.target, ""));
         }
 
         config
@@ -228,7 +288,7 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
         let r = self.router;
         let is_internal = net.get_device(self.router).is_internal();
 
-        config.push_str("!\n! Interfaces\n!\n");
+        config.push_str(&section(self.target, "Interfaces"));
         for edge in net.get_topology().edges(r).sorted_by_key(|x| x.id()) {
             let n = edge.target();
 
@@ -257,7 +317,7 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
             }
 
             config.push_str(&iface.build(self.target));
-            config.push_str("!\n");
+            config.push_str(&comment(self.target, ""));
         }
 
         // configure the loopback address
@@ -280,7 +340,7 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
         router: &Router<P>,
         addressor: &mut A,
     ) -> Result<String, ExportError> {
-        let mut config = String::from("!\n! Static Routes\n!\n");
+        let mut config = section(self.target, "Static Routes");
 
         for (p, sr) in router.get_static_routes().iter() {
             for sr in self.static_route(net, addressor, *p, *sr)? {
@@ -330,7 +390,7 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
         let mut router_ospf = RouterOspf::new();
         router_ospf.router_id(addressor.router_address(self.router)?);
         router_ospf.maximum_paths(if router.do_load_balancing { 16 } else { 1 });
-        config.push_str("!\n! OSPF\n!\n");
+        config.push_str(&section(self.target, "OSPF"));
         config.push_str(&router_ospf.build(self.target));
 
         Ok(config)
@@ -351,6 +411,37 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
         let mut router_bgp = RouterBgp::new(self.as_id);
         router_bgp.router_id(addressor.router_address(r)?);
         router_bgp.network(addressor.internal_network());
+        if router.get_always_compare_med() {
+            router_bgp.always_compare_med();
+        }
+        if router.get_compare_router_id() {
+            router_bgp.compare_router_id();
+        }
+        for (prefix, aggregate) in router
+            .get_bgp_aggregates()
+            .iter()
+            .sorted_by_key(|(p, _)| *p)
+        {
+            self.bgp_aggregate(&mut router_bgp, addressor, *prefix, Some(*aggregate))?;
+        }
+        let mut network_routes = String::new();
+        for prefix in router.get_bgp_networks().iter().sorted() {
+            network_routes.push_str(&self.bgp_network(
+                &mut router_bgp,
+                addressor,
+                *prefix,
+                true,
+            )?);
+        }
+        if router.get_bgp_redistribute_static() {
+            router_bgp.redistribute_static();
+            if self.target == Target::CiscoNexus7000 {
+                default_rm.push_str(
+                    &RouteMapItem::new(NEXUS_REDISTRIBUTE_STATIC_RM, u16::MAX, true)
+                        .build(self.target)?,
+                );
+            }
+        }
 
         // create each neighbor
         for (n, ty) in router.bgp_sessions.iter().sorted_by_key(|(x, _)| *x) {
@@ -359,29 +450,80 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
 
             // build the default route-map to permit everything
             default_rm.push_str(
-                &RouteMapItem::new(format!("{rm_name}-in"), u16::MAX, true).build(self.target),
+                &RouteMapItem::new(format!("{rm_name}-in"), u16::MAX, true).build(self.target)?,
             );
             default_rm.push_str(
-                &RouteMapItem::new(format!("{rm_name}-out"), u16::MAX, true).build(self.target),
+                &RouteMapItem::new(format!("{rm_name}-out"), u16::MAX, true).build(self.target)?,
             );
         }
 
         // push the bgp configuration
-        config.push_str("!\n! BGP\n!\n");
+        config.push_str(&section(self.target, "BGP"));
         config.push_str(&default_rm);
-        config.push_str("!\n");
+        config.push_str(&comment(self.target, ""));
         config.push_str(&router_bgp.build(self.target));
         // push the static route for the entire internal network with the lowest preference.
-        config.push_str("!\n");
+        config.push_str(&comment(self.target, ""));
         config.push_str(
             &StaticRouteGen::new(addressor.internal_network())
                 .blackhole()
                 .build(self.target),
         );
+        config.push_str(&network_routes);
 
         Ok(config)
     }
 
+    /// Add (or remove, if `originate` is `false`) the network statements for `prefix`. The router
+    /// only advertises a network if it has a route for it. Therefore, this function returns the
+    /// black-hole static routes for `prefix` (or their removal), which must be added to the
+    /// configuration.
+    fn bgp_network<A: Addressor<P>>(
+        &self,
+        router_bgp: &mut RouterBgp,
+        addressor: &mut A,
+        prefix: P,
+        originate: bool,
+    ) -> Result<String, ExportError> {
+        let mut static_routes = String::new();
+        for net in addressor.prefix(prefix)?.to_vec() {
+            let mut static_route = StaticRouteGen::new(net);
+            static_route.blackhole();
+            if originate {
+                router_bgp.network(net);
+                static_routes.push_str(&static_route.build(self.target));
+            } else {
+                router_bgp.no_network(net);
+                static_routes.push_str(&static_route.no(self.target));
+            }
+        }
+        Ok(static_routes)
+    }
+
+    /// Add (or remove, if `aggregate` is `None`) the BGP aggregate for `prefix`. This function
+    /// fails on Junos, as BGP aggregates are not supported there.
+    fn bgp_aggregate<A: Addressor<P>>(
+        &self,
+        router_bgp: &mut RouterBgp,
+        addressor: &mut A,
+        prefix: P,
+        aggregate: Option<BgpAggregate>,
+    ) -> Result<(), ExportError> {
+        if self.target == Target::Junos {
+            return Err(ExportError::InternalCfgGenError(
+                self.router,
+                String::from("BGP aggregates are not supported on Junos"),
+            ));
+        }
+        for net in addressor.prefix(prefix)?.to_vec() {
+            match aggregate {
+                Some(a) => router_bgp.aggregate_address(net, a.summary_only, a.as_set),
+                None => router_bgp.no_aggregate_address(net),
+            };
+        }
+        Ok(())
+    }
+
     /// Create the configuration for a BGP neighbor
     fn bgp_neigbor_config<A: Addressor<P>, Q>(
         &self,
@@ -400,6 +542,7 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
         } else {
             bgp_neighbor.remote_as(INTERNAL_AS);
             bgp_neighbor.update_source(loopback_iface(self.target, 0));
+            bgp_neighbor.local_address(addressor.router_address(r)?);
             bgp_neighbor.send_community();
         }
 
@@ -408,6 +551,9 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
         bgp_neighbor.route_map_out(format!("{rm_name}-out"));
         bgp_neighbor.next_hop_self();
         bgp_neighbor.soft_reconfiguration_inbound();
+        if let Some(mrai) = net.get_device(r).internal().and_then(|x| x.get_bgp_mrai(n)) {
+            bgp_neighbor.advertisement_interval(mrai.interval);
+        }
         match ty {
             BgpSessionType::IBgpPeer => {}
             BgpSessionType::IBgpClient => {
@@ -453,9 +599,10 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
         };
 
         // write all route-maps
-        config.push_str("!\n! Route-Maps\n");
+        config.push_str(&comment(self.target, ""));
+        config.push_str(&comment(self.target, "Route-Maps"));
         if route_maps.is_empty() {
-            config.push_str("!\n");
+            config.push_str(&comment(self.target, ""));
         }
         for ((n, ty), maps) in route_maps.iter().sorted_by(|(a, _), (b, _)| rm_order(a, b)) {
             let name = format!(
@@ -469,9 +616,14 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
             );
             for rm in maps {
                 let next_ord = self.next_ord(*n, *ty, rm.order(), rm.state());
-                let route_map_item = self.route_map_item(&name, rm, next_ord, net, addressor)?;
-                config.push_str("!\n");
-                config.push_str(&route_map_item.build(self.target));
+                // All route-map items are created in order, so on Junos, each item is inserted
+                // right before the last, default item.
+                let route_map_item = self
+                    .route_map_item(&name, rm, next_ord, net, addressor)?
+                    .insert_before(u16::MAX)
+                    .build(self.target)?;
+                config.push_str(&comment(self.target, ""));
+                config.push_str(&route_map_item);
             }
         }
 
@@ -562,10 +714,23 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
         }
 
         // AsPath match
-        if let Some(as_id) = rm_match_as_path_list(rm) {
-            route_map_item.match_as_path_list(
-                AsPathList::new(format!("{name}-{ord}-asl")).contains_as(as_id),
-            );
+        if let Some(constraint) = rm_match_as_path_list(rm) {
+            let mut asl = AsPathList::new(format!("{name}-{ord}-asl"));
+            match constraint {
+                RouteMapMatchAsPath::Contains(as_id) => asl.contains_as(*as_id),
+                RouteMapMatchAsPath::Regex(regex) => {
+                    if self.target == Target::Junos && junos_as_path_regex(regex.as_str()).is_none()
+                    {
+                        return Err(ExportError::InternalCfgGenError(
+                            self.router,
+                            format!("Cannot translate the as-path regex {regex} for Junos"),
+                        ));
+                    }
+                    asl.regex(regex.as_str())
+                }
+                RouteMapMatchAsPath::Length(_) => unreachable!(),
+            };
+            route_map_item.match_as_path_list(asl);
         }
 
         // match on the next-hop
@@ -576,6 +741,17 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
             );
         }
 
+        // match on the origin
+        if let Some(origin) = rm_match_origin(rm) {
+            if matches!(self.target, Target::CiscoNexus7000 | Target::CiscoIos) {
+                return Err(ExportError::InternalCfgGenError(
+                    self.router,
+                    String::from("Cisco Nexus and IOS cannot match on the ORIGIN attribute"),
+                ));
+            }
+            route_map_item.match_origin(origin);
+        }
+
         // unset all communities using a single community list
         if let Some(communities) = rm_delete_community_list(rm) {
             let mut cl = CommunityList::new(format!("{name}-{ord}-del-cl"));
@@ -585,6 +761,11 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
             route_map_item.delete_community_list(cl);
         }
 
+        // prepend all ASes at once
+        if let Some(path) = rm_prepend_as_path(rm) {
+            route_map_item.prepend_as_path(path);
+        }
+
         // go through all set clauses
         for x in rm.set.iter() {
             _ = match x {
@@ -597,6 +778,8 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
                 RouteMapSet::LocalPref(None) => route_map_item.set_local_pref(100),
                 RouteMapSet::Med(Some(m)) => route_map_item.set_med(*m),
                 RouteMapSet::Med(None) => route_map_item.set_med(0),
+                RouteMapSet::Origin(o) => route_map_item.set_origin(*o),
+                RouteMapSet::PrependAsPath(_) => &mut route_map_item, // nothing to do, already done!
                 RouteMapSet::IgpCost(_) => {
                     unimplemented!("Changing the IGP cost is not implemented yet!")
                 }
@@ -614,6 +797,9 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
                 route_map_item.continues(order(next_ord));
             }
         }
+        if let Some(next_ord) = next_ord {
+            route_map_item.insert_before(order(next_ord));
+        }
 
         Ok(route_map_item)
     }
@@ -625,7 +811,7 @@ impl<P: Prefix> CiscoFrrCfgGen<P> {
         neighbor: RouterId,
         direction: RmDir,
         ord: i16,
-    ) -> Option<String> {
+    ) -> Option<Result<String, ExportError>> {
         let rms = self.route_maps.get(&(neighbor, direction))?;
         let pos = rms.binary_search_by(|(probe, _)| probe.cmp(&ord)).ok()?;
         let (last_ord, last_state) = rms.get(pos.checked_sub(1)?)?;
@@ -711,7 +897,7 @@ impl<P: Prefix, A: Addressor<P>, Q> InternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
             .internal_or(ExportError::NotAnInternalRouter(self.router))?;
 
         // if we are on cisco, enable the ospf and bgp feature
-        config.push_str("!\n");
+        config.push_str(&comment(self.target, ""));
         config.push_str(enable_bgp(self.target));
         config.push_str(enable_ospf(self.target));
 
@@ -737,16 +923,17 @@ impl<P: Prefix, A: Addressor<P>, Q> InternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
                     source,
                     target,
                     weight,
-                } => Ok(Interface::new(self.iface(source, target, addressor)?)
+                } => Ok(self
+                    .ospf_iface(net, source, target, addressor)?
                     .cost(weight)
                     .build(self.target)),
                 ConfigExpr::OspfArea {
                     source,
                     target,
                     area,
-                } => Ok(Interface::new(self.iface(source, target, addressor)?)
-                    .area(area)
-                    .build(self.target)),
+                } => {
+                    self.ospf_area_change(net, source, target, OspfArea::BACKBONE, area, addressor)
+                }
                 ConfigExpr::BgpSession {
                     source,
                     target,
@@ -778,9 +965,9 @@ impl<P: Prefix, A: Addressor<P>, Q> InternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
                             )
                             .build(self.target),
                         RouteMapItem::new(format!("{rm_name}-in"), u16::MAX, true)
-                            .build(self.target),
+                            .build(self.target)?,
                         RouteMapItem::new(format!("{rm_name}-out"), u16::MAX, true)
-                            .build(self.target),
+                            .build(self.target)?,
                     ))
                 }
                 ConfigExpr::BgpRouteMap {
@@ -799,9 +986,10 @@ impl<P: Prefix, A: Addressor<P>, Q> InternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
                             net,
                             addressor,
                         )?
-                        .build(self.target),
+                        .build(self.target)?,
                         // update the continues of the last route-map.
                         self.fix_prev_rm_continue(net, neighbor, direction, map.order())
+                            .transpose()?
                             .unwrap_or_default()
                     ))
                 }
@@ -813,18 +1001,56 @@ impl<P: Prefix, A: Addressor<P>, Q> InternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
                 ConfigExpr::LoadBalancing { .. } => {
                     Ok(RouterOspf::new().maximum_paths(16).build(self.target))
                 }
+                ConfigExpr::BgpMrai { neighbor, mrai, .. } => Ok(RouterBgp::new(self.as_id)
+                    .neighbor(
+                        RouterBgpNeighbor::new(self.router_id_to_ip(neighbor, net, addressor)?)
+                            .advertisement_interval(mrai.interval),
+                    )
+                    .build(self.target)),
+                ConfigExpr::Aggregate {
+                    prefix,
+                    summary_only,
+                    as_set,
+                    ..
+                } => {
+                    let mut router_bgp = RouterBgp::new(self.as_id);
+                    let aggregate = BgpAggregate::new(summary_only, as_set);
+                    self.bgp_aggregate(&mut router_bgp, addressor, prefix, Some(aggregate))?;
+                    Ok(router_bgp.build(self.target))
+                }
+                ConfigExpr::BgpNetwork { prefix, .. } => {
+                    let mut router_bgp = RouterBgp::new(self.as_id);
+                    let static_routes =
+                        self.bgp_network(&mut router_bgp, addressor, prefix, true)?;
+                    Ok(format!("{static_routes}{}", router_bgp.build(self.target)))
+                }
+                ConfigExpr::BgpRedistributeStatic { .. } => {
+                    let rm = if self.target == Target::CiscoNexus7000 {
+                        RouteMapItem::new(NEXUS_REDISTRIBUTE_STATIC_RM, u16::MAX, true)
+                            .build(self.target)?
+                    } else {
+                        String::new()
+                    };
+                    Ok(format!(
+                        "{rm}{}",
+                        RouterBgp::new(self.as_id)
+                            .redistribute_static()
+                            .build(self.target)
+                    ))
+                }
             },
             ConfigModifier::Remove(c) => match c {
-                ConfigExpr::IgpLinkWeight { source, target, .. } => {
-                    Ok(Interface::new(self.iface(source, target, addressor)?)
-                        .no_cost()
-                        .shutdown()
-                        .build(self.target))
-                }
-                ConfigExpr::OspfArea { source, target, .. } => {
-                    Ok(Interface::new(self.iface(source, target, addressor)?)
-                        .area(0)
-                        .build(self.target))
+                ConfigExpr::IgpLinkWeight { source, target, .. } => Ok(self
+                    .ospf_iface(net, source, target, addressor)?
+                    .no_cost()
+                    .shutdown()
+                    .build(self.target)),
+                ConfigExpr::OspfArea {
+                    source,
+                    target,
+                    area,
+                } => {
+                    self.ospf_area_change(net, source, target, area, OspfArea::BACKBONE, addressor)
                 }
                 ConfigExpr::BgpSession { source, target, .. } => Ok(RouterBgp::new(self.as_id)
                     .no_neighbor(RouterBgpNeighbor::new(self.router_id_to_ip(
@@ -860,6 +1086,7 @@ impl<P: Prefix, A: Addressor<P>, Q> InternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
                             .and_then(|ord| {
                                 self.fix_prev_rm_continue(net, neighbor, direction, ord)
                             })
+                            .transpose()?
                             .unwrap_or_default(),
                     ))
                 }
@@ -871,22 +1098,48 @@ impl<P: Prefix, A: Addressor<P>, Q> InternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
                 ConfigExpr::LoadBalancing { .. } => {
                     Ok(RouterOspf::new().maximum_paths(1).build(self.target))
                 }
+                ConfigExpr::BgpMrai { neighbor, .. } => Ok(RouterBgp::new(self.as_id)
+                    .neighbor(
+                        RouterBgpNeighbor::new(self.router_id_to_ip(neighbor, net, addressor)?)
+                            .no_advertisement_interval(),
+                    )
+                    .build(self.target)),
+                ConfigExpr::Aggregate { prefix, .. } => {
+                    let mut router_bgp = RouterBgp::new(self.as_id);
+                    self.bgp_aggregate(&mut router_bgp, addressor, prefix, None)?;
+                    Ok(router_bgp.build(self.target))
+                }
+                ConfigExpr::BgpNetwork { prefix, .. } => {
+                    // first stop advertising the network, and then remove the static routes.
+                    let mut router_bgp = RouterBgp::new(self.as_id);
+                    let static_routes =
+                        self.bgp_network(&mut router_bgp, addressor, prefix, false)?;
+                    Ok(format!("{}{static_routes}", router_bgp.build(self.target)))
+                }
+                ConfigExpr::BgpRedistributeStatic { .. } => Ok(RouterBgp::new(self.as_id)
+                    .no_redistribute_static()
+                    .build(self.target)),
             },
             ConfigModifier::Update { from, to } => match to {
                 ConfigExpr::IgpLinkWeight {
                     source,
                     target,
                     weight,
-                } => Ok(Interface::new(self.iface(source, target, addressor)?)
+                } => Ok(self
+                    .ospf_iface(net, source, target, addressor)?
                     .cost(weight)
                     .build(self.target)),
                 ConfigExpr::OspfArea {
                     source,
                     target,
                     area,
-                } => Ok(Interface::new(self.iface(source, target, addressor)?)
-                    .area(area)
-                    .build(self.target)),
+                } => {
+                    if let ConfigExpr::OspfArea { area: old_area, .. } = from {
+                        self.ospf_area_change(net, source, target, old_area, area, addressor)
+                    } else {
+                        unreachable!("Config Modifier must update the same kind of expression")
+                    }
+                }
                 ConfigExpr::BgpSession {
                     source,
                     target,
@@ -896,6 +1149,7 @@ impl<P: Prefix, A: Addressor<P>, Q> InternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
                         RouterBgpNeighbor::new(self.router_id_to_ip(target, net, addressor)?);
                     if ty == BgpSessionType::IBgpClient && source == self.router {
                         neighbor.route_reflector_client();
+                        neighbor.cluster_id(addressor.router_address(self.router)?);
                     } else if ty == BgpSessionType::IBgpPeer && source == self.router {
                         neighbor.no_route_reflector_client();
                     } else {
@@ -919,7 +1173,7 @@ impl<P: Prefix, A: Addressor<P>, Q> InternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
                             self.route_map_item(&rm_name, &old_map, next_ord, net, addressor)?
                                 .no(self.target),
                             self.route_map_item(&rm_name, &map, next_ord, net, addressor)?
-                                .build(self.target)
+                                .build(self.target)?
                         ))
                     } else {
                         unreachable!("Config Modifier must update the same kind of expression")
@@ -943,6 +1197,27 @@ impl<P: Prefix, A: Addressor<P>, Q> InternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
                     }
                 }
                 ConfigExpr::LoadBalancing { .. } => unreachable!(),
+                ConfigExpr::BgpMrai { neighbor, mrai, .. } => Ok(RouterBgp::new(self.as_id)
+                    .neighbor(
+                        RouterBgpNeighbor::new(self.router_id_to_ip(neighbor, net, addressor)?)
+                            .advertisement_interval(mrai.interval),
+                    )
+                    .build(self.target)),
+                ConfigExpr::Aggregate {
+                    prefix,
+                    summary_only,
+                    as_set,
+                    ..
+                } => {
+                    // remove the old aggregate first, such that no options remain from before.
+                    let mut router_bgp = RouterBgp::new(self.as_id);
+                    let aggregate = BgpAggregate::new(summary_only, as_set);
+                    self.bgp_aggregate(&mut router_bgp, addressor, prefix, None)?;
+                    self.bgp_aggregate(&mut router_bgp, addressor, prefix, Some(aggregate))?;
+                    Ok(router_bgp.build(self.target))
+                }
+                ConfigExpr::BgpNetwork { .. } => unreachable!(),
+                ConfigExpr::BgpRedistributeStatic { .. } => unreachable!(),
             },
             ConfigModifier::BatchRouteMapEdit { router, updates } => updates
                 .into_iter()
@@ -965,7 +1240,7 @@ impl<P: Prefix, A: Addressor<P>, Q> ExternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
             .external_or(ExportError::NotAnExternalRouter(self.router))?;
 
         // if we are on cisco, enable the ospf and bgp feature
-        config.push_str("!\n");
+        config.push_str(&comment(self.target, ""));
         config.push_str(enable_bgp(self.target));
 
         // create the interfaces to the neighbors
@@ -987,14 +1262,14 @@ impl<P: Prefix, A: Addressor<P>, Q> ExternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
         // announce the internal prefix (for now).
         router_bgp.network(addressor.router_network(self.router)?);
         // create the actual config
-        config.push_str("!\n! BGP\n!\n");
+        config.push_str(&section(self.target, "BGP"));
         // first, push all route-maps
-        config.push_str(&RouteMapItem::new(EXTERNAL_RM_IN, u16::MAX, true).build(self.target));
-        config.push_str(&RouteMapItem::new(EXTERNAL_RM_OUT, u16::MAX, true).build(self.target));
-        config.push_str("!\n");
+        config.push_str(&RouteMapItem::new(EXTERNAL_RM_IN, u16::MAX, true).build(self.target)?);
+        config.push_str(&RouteMapItem::new(EXTERNAL_RM_OUT, u16::MAX, true).build(self.target)?);
+        config.push_str(&comment(self.target, ""));
         // then, push the config
         config.push_str(&router_bgp.build(self.target));
-        config.push_str("!\n");
+        config.push_str(&comment(self.target, ""));
         config.push_str(
             &StaticRouteGen::new(addressor.router_network(self.router)?)
                 .blackhole()
@@ -1004,9 +1279,10 @@ impl<P: Prefix, A: Addressor<P>, Q> ExternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
         // create the two route-maps that allow everything
 
         // Create all external advertisements
-        config.push_str("!\n! Create external advertisements\n");
+        config.push_str(&comment(self.target, ""));
+        config.push_str(&comment(self.target, "Create external advertisements"));
         for (_, route) in router.active_routes.iter().sorted_by_key(|(p, _)| *p) {
-            config.push_str("!\n");
+            config.push_str(&comment(self.target, ""));
             config.push_str(&self.advertise_route(net, addressor, route)?);
         }
 
@@ -1060,11 +1336,15 @@ impl<P: Prefix, A: Addressor<P>, Q> ExternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
             RouteMapItem::new(EXTERNAL_RM_OUT, route.prefix.as_num() as u16 + 1, true);
         route_map.match_prefix_list(prefix_list);
         route_map.prepend_as_path(route.as_path.iter().skip(1));
+        route_map.insert_before(u16::MAX);
         route_map.set_med(route.med.unwrap_or(0));
+        if route.origin != Origin::Igp {
+            route_map.set_origin(route.origin);
+        }
         for c in route.community.iter() {
             route_map.set_community(INTERNAL_AS, *c);
         }
-        config.push_str(&route_map.build(self.target));
+        config.push_str(&route_map.build(self.target)?);
 
         Ok(config)
     }
@@ -1092,7 +1372,7 @@ impl<P: Prefix, A: Addressor<P>, Q> ExternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
                         self.remove_loopback_iface(network)
                             .ok_or(ExportError::WithdrawUnadvertisedRoute)?,
                     )
-                    .no(),
+                    .no(self.target),
                 );
             }
         }
@@ -1142,10 +1422,20 @@ impl<P: Prefix, A: Addressor<P>, Q> ExternalCfgGen<P, Q, A> for CiscoFrrCfgGen<P
             .neighbor(RouterBgpNeighbor::new(
                 self.router_id_to_ip(neighbor, net, addressor)?,
             ))
-            .no())
+            .no(self.target))
     }
 }
 
+/// Generate the comment lines that introduce a new section of the configuration.
+fn section(target: Target, name: &str) -> String {
+    format!(
+        "{}{}{}",
+        comment(target, ""),
+        comment(target, name),
+        comment(target, "")
+    )
+}
+
 /// Translate the route-map order from signed to unsigned
 fn order(old: i16) -> u16 {
     ((old as i32) - (i16::MIN as i32)) as u16
@@ -1190,19 +1480,22 @@ fn rm_match_community_list<P: Prefix>(rm: &RouteMap<P>) -> Option<(HashSet<u32>,
 }
 
 /// TODO this is not implemented yet. It only works if there is a single AS that must be present in
-/// the path. Otherwise, it will simply panic!
-fn rm_match_as_path_list<P: Prefix>(rm: &RouteMap<P>) -> Option<AsId> {
-    let mut contained_ases = Vec::new();
+/// the path, or a single regular expression. Otherwise, it will simply panic!
+fn rm_match_as_path_list<P: Prefix>(rm: &RouteMap<P>) -> Option<&RouteMapMatchAsPath> {
+    let mut constraints = Vec::new();
 
     for cond in rm.conds.iter() {
-        if let RouteMapMatch::AsPath(RouteMapMatchAsPath::Contains(as_id)) = cond {
-            contained_ases.push(as_id)
+        if let RouteMapMatch::AsPath(
+            c @ (RouteMapMatchAsPath::Contains(_) | RouteMapMatchAsPath::Regex(_)),
+        ) = cond
+        {
+            constraints.push(c)
         };
     }
 
-    match contained_ases.as_slice() {
+    match constraints.as_slice() {
         [] => None,
-        [as_id] => Some(**as_id),
+        [c] => Some(*c),
         _ => unimplemented!("More complex AS path constraints are not implemented yet!"),
     }
 }
@@ -1224,6 +1517,38 @@ fn rm_match_next_hop<P: Prefix>(rm: &RouteMap<P>) -> Option<RouterId> {
     next_hop
 }
 
+/// Extract the origin that is matched in the route-map
+fn rm_match_origin<P: Prefix>(rm: &RouteMap<P>) -> Option<Origin> {
+    let mut origin: Option<Origin> = None;
+
+    for cond in rm.conds.iter() {
+        if let RouteMapMatch::Origin(o) = cond {
+            if origin.is_none() {
+                origin = Some(*o);
+            } else if origin != Some(*o) {
+                panic!("Multiple different origins matched in a route-map!")
+            }
+        }
+    }
+
+    origin
+}
+
+/// Extract the ASes that are prepended to the AS path. Later set clauses prepend in front of
+/// earlier ones.
+fn rm_prepend_as_path<P: Prefix>(rm: &RouteMap<P>) -> Option<Vec<AsId>> {
+    let mut path: Option<Vec<AsId>> = None;
+
+    for set in rm.set.iter() {
+        if let RouteMapSet::PrependAsPath(p) = set {
+            let old = path.take().unwrap_or_default();
+            path = Some(p.iter().copied().chain(old).collect());
+        }
+    }
+
+    path
+}
+
 /// Extract the set of communities that must be present in the route such that it matches
 fn rm_delete_community_list<P: Prefix>(rm: &RouteMap<P>) -> Option<HashSet<u32>> {
     let mut communities = HashSet::new();
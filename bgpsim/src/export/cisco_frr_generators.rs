@@ -16,26 +16,49 @@
 // 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
 
 //! Module that contains convenience methods to generate configuration in the style of Cisco IOS.
+//! The same builders also generate set-style commands for Juniper Junos (see [`Target::Junos`]).
 
 use ipnet::Ipv4Net;
 use itertools::Itertools;
 use std::net::Ipv4Addr;
 
 use crate::{
+    bgp::Origin,
     ospf::OspfArea,
     types::{AsId, LinkWeight},
 };
 
+use super::ExportError;
+
 /// Instance of the OSPF router.
 const ROUTER_OSPF_INSTANCE: u16 = 10;
+/// Junos policy that advertises all networks of [`RouterBgp::network`].
+const JUNOS_NETWORKS_POLICY: &str = "bgp-networks";
+/// Route-map used to redistribute static routes on Cisco Nexus (see
+/// [`RouterBgp::redistribute_static`]).
+pub(crate) const NEXUS_REDISTRIBUTE_STATIC_RM: &str = "redistribute-static";
+/// Junos policy that sets the next-hop to self (see [`RouterBgpNeighbor::next_hop_self`]).
+const JUNOS_NEXT_HOP_SELF_POLICY: &str = "next-hop-self";
+/// Junos policy that enables load balancing in the forwarding table.
+const JUNOS_LOAD_BALANCE_POLICY: &str = "load-balance";
+/// Junos has no BGP weight. Instead, the weight is emulated by the route preference (where lower
+/// is better), computed as `JUNOS_MAX_PREFERENCE - weight`. Hence, BGP routes are never preferred
+/// over OSPF or static routes.
+const JUNOS_MAX_PREFERENCE: u32 = 65_735;
 
 /// Enumeration of all supported targets
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Target {
     /// Cisco Nexus 7000 Series
     CiscoNexus7000,
+    /// Cisco IOS, where addresses are written with their network mask instead of the prefix
+    /// length.
+    CiscoIos,
     /// Frr
     Frr,
+    /// Juniper Junos, using set-style commands (`set` and `delete`). All interfaces use the logical
+    /// unit 0.
+    Junos,
 }
 
 /// Interface configuration builder for Cisco and FRR.
@@ -80,11 +103,23 @@ impl Interface {
     /// as follows:
     ///
     /// ```
-    /// # use bgpsim::export::cisco_frr_generators::Interface;
-    /// assert_eq!(Interface::new("Ethernet4/1").no(), "no interface Ethernet4/1\n");
+    /// # use bgpsim::export::cisco_frr_generators::{Interface, Target};
+    /// assert_eq!(
+    ///     Interface::new("Ethernet4/1").no(Target::CiscoNexus7000),
+    ///     "no interface Ethernet4/1\n"
+    /// );
+    /// assert_eq!(
+    ///     Interface::new("ge-0/0/1").no(Target::Junos),
+    ///     "delete interfaces ge-0/0/1\n"
+    /// );
     /// ```
-    pub fn no(&self) -> String {
-        format!("no interface {}\n", self.iface_name)
+    pub fn no(&self, target: Target) -> String {
+        match target {
+            Target::CiscoNexus7000 | Target::CiscoIos | Target::Frr => {
+                format!("no interface {}\n", self.iface_name)
+            }
+            Target::Junos => format!("delete interfaces {}\n", self.iface_name),
+        }
     }
 
     /// Set the IP address of the interface.
@@ -251,7 +286,8 @@ impl Interface {
         self
     }
 
-    /// Unset the OSPF area.
+    /// Unset the OSPF area. On Junos, this removes the interface from the OSPF area that is set with
+    /// [`Interface::area`] (or from the backbone area, if no area is set).
     ///
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{Interface, Target};
@@ -449,11 +485,43 @@ impl Interface {
     /// exit
     /// "
     /// );
+    /// assert_eq!(
+    ///     Interface::new("GigabitEthernet0/1")
+    ///         .no_shutdown()
+    ///         .ip_address(ip_addr)
+    ///         .cost(200f64)
+    ///         .area(2)
+    ///         .build(Target::CiscoIos),
+    ///     "\
+    /// interface GigabitEthernet0/1
+    ///   ip address 10.0.0.1 255.0.0.0
+    ///   ip ospf cost 200
+    ///   ip ospf 10 area 2
+    ///   no shutdown
+    /// exit
+    /// "
+    /// );
+    /// assert_eq!(
+    ///     Interface::new("ge-0/0/1")
+    ///         .no_shutdown()
+    ///         .ip_address(ip_addr)
+    ///         .cost(200f64)
+    ///         .area(2)
+    ///         .build(Target::Junos),
+    ///     "\
+    /// set interfaces ge-0/0/1 unit 0 family inet address 10.0.0.1/8
+    /// delete interfaces ge-0/0/1 disable
+    /// set protocols ospf area 0.0.0.2 interface ge-0/0/1.0
+    /// set protocols ospf area 0.0.0.2 interface ge-0/0/1.0 metric 200
+    /// "
+    /// );
     /// ```
     pub fn build(&self, target: Target) -> String {
         let ospf_area_cmd = match target {
             Target::CiscoNexus7000 => format!("ip router ospf {ROUTER_OSPF_INSTANCE} area"),
+            Target::CiscoIos => format!("ip ospf {ROUTER_OSPF_INSTANCE} area"),
             Target::Frr => String::from("ip ospf area"),
+            Target::Junos => return self.build_junos(),
         };
 
         format!(
@@ -469,7 +537,7 @@ exit
                 .map(|(addr, state)| format!(
                     "\n  {}ip address {}",
                     if *state { "" } else { "no " },
-                    addr
+                    fmt_net(target, *addr)
                 ))
                 .collect::<String>(),
             cost = match (self.cost, self.no_cost) {
@@ -507,6 +575,68 @@ exit
             },
         )
     }
+
+    /// Generate the set-style commands for Junos. The OSPF parameters are configured within the
+    /// OSPF area of the interface. Hence, they are only generated if the area is given, as they
+    /// would otherwise enable OSPF on that interface.
+    fn build_junos(&self) -> String {
+        let iface = &self.iface_name;
+        let mut cfg = String::new();
+
+        for (addr, state) in self.ip_address.iter() {
+            let cmd = if *state { "set" } else { "delete" };
+            cfg.push_str(&format!(
+                "{cmd} interfaces {iface} unit 0 family inet address {addr}\n"
+            ));
+        }
+        match (self.mac_address, self.no_mac_address) {
+            (Some(mac), false) => cfg.push_str(&format!(
+                "set interfaces {iface} mac {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}\n",
+                mac[0], mac[1], mac[2], mac[3], mac[4], mac[5],
+            )),
+            (_, true) => cfg.push_str(&format!("delete interfaces {iface} mac\n")),
+            (None, false) => {}
+        }
+        match self.shutdown {
+            Some(true) => cfg.push_str(&format!("set interfaces {iface} disable\n")),
+            Some(false) => cfg.push_str(&format!("delete interfaces {iface} disable\n")),
+            None => {}
+        }
+
+        let ospf = format!(
+            "protocols ospf area {} interface {}",
+            junos_area(self.area.unwrap_or(OspfArea::BACKBONE)),
+            junos_unit(iface)
+        );
+        if self.no_area {
+            cfg.push_str(&format!("delete {ospf}\n"));
+            return cfg;
+        }
+        if self.area.is_none() {
+            return cfg;
+        }
+        cfg.push_str(&format!("set {ospf}\n"));
+        match (self.cost, self.no_cost) {
+            (Some(cost), false) => cfg.push_str(&format!("set {ospf} metric {cost}\n")),
+            (_, true) => cfg.push_str(&format!("delete {ospf} metric\n")),
+            (None, false) => {}
+        }
+        match (self.dead_interval, self.no_dead_interval) {
+            (Some(seconds), false) => {
+                cfg.push_str(&format!("set {ospf} dead-interval {seconds}\n"))
+            }
+            (_, true) => cfg.push_str(&format!("delete {ospf} dead-interval\n")),
+            (None, false) => {}
+        }
+        match (self.hello_interval, self.no_hello_interval) {
+            (Some(seconds), false) => {
+                cfg.push_str(&format!("set {ospf} hello-interval {seconds}\n"))
+            }
+            (_, true) => cfg.push_str(&format!("delete {ospf} hello-interval\n")),
+            (None, false) => {}
+        }
+        cfg
+    }
 }
 
 /// Ospf Router configuration for cisco-like routers.
@@ -540,8 +670,11 @@ impl RouterOspf {
     /// ```
     pub fn no(&self, target: Target) -> String {
         match target {
-            Target::CiscoNexus7000 => format!("no router ospf {ROUTER_OSPF_INSTANCE}\n"),
+            Target::CiscoNexus7000 | Target::CiscoIos => {
+                format!("no router ospf {ROUTER_OSPF_INSTANCE}\n")
+            }
             Target::Frr => String::from("no router ospf\n"),
+            Target::Junos => String::from("delete protocols ospf\n"),
         }
     }
 
@@ -636,10 +769,28 @@ impl RouterOspf {
     /// "
     /// )
     /// ```
+    ///
+    /// On Junos, the router-id is configured globally, and load balancing is enabled using a policy
+    /// in the forwarding table if `maximum_paths` is larger than 1.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterOspf, Target};
+    /// # use std::net::Ipv4Addr;
+    /// let id = Ipv4Addr::new(10, 0, 0, 1);
+    /// assert_eq!(
+    ///     RouterOspf::new().router_id(id).maximum_paths(4).build(Target::Junos),
+    ///     "\
+    /// set routing-options router-id 10.0.0.1
+    /// set policy-options policy-statement load-balance then load-balance per-packet
+    /// set routing-options forwarding-table export load-balance
+    /// "
+    /// )
+    /// ```
     pub fn build(&self, target: Target) -> String {
         let instance_str = match target {
-            Target::CiscoNexus7000 => format!(" {ROUTER_OSPF_INSTANCE}"),
+            Target::CiscoNexus7000 | Target::CiscoIos => format!(" {ROUTER_OSPF_INSTANCE}"),
             Target::Frr => String::new(),
+            Target::Junos => return self.build_junos(),
         };
 
         format!(
@@ -661,6 +812,28 @@ exit
             }
         )
     }
+
+    /// Generate the set-style commands for Junos.
+    fn build_junos(&self) -> String {
+        let mut cfg = String::new();
+        match (self.router_id, self.no_router_id) {
+            (Some(id), false) => cfg.push_str(&format!("set routing-options router-id {id}\n")),
+            (_, true) => cfg.push_str("delete routing-options router-id\n"),
+            (None, false) => {}
+        }
+        match (self.maximum_paths, self.no_maximum_paths) {
+            (Some(k), false) if k > 1 => cfg.push_str(&format!(
+                "set policy-options policy-statement {JUNOS_LOAD_BALANCE_POLICY} \
+                 then load-balance per-packet\n\
+                 set routing-options forwarding-table export {JUNOS_LOAD_BALANCE_POLICY}\n"
+            )),
+            (Some(_), false) | (_, true) => cfg.push_str(&format!(
+                "delete routing-options forwarding-table export {JUNOS_LOAD_BALANCE_POLICY}\n"
+            )),
+            (None, false) => {}
+        }
+        cfg
+    }
 }
 
 /// BGP Router configuration for Cisco-like routers.
@@ -714,6 +887,10 @@ pub struct RouterBgp {
     no_router_id: bool,
     neighbors: Vec<(RouterBgpNeighbor, bool)>,
     networks: Vec<(Ipv4Net, bool)>,
+    aggregates: Vec<(Ipv4Net, Option<(bool, bool)>)>,
+    redistribute_static: Option<bool>,
+    always_compare_med: Option<bool>,
+    compare_router_id: Option<bool>,
 }
 
 impl RouterBgp {
@@ -725,6 +902,10 @@ impl RouterBgp {
             no_router_id: Default::default(),
             neighbors: Default::default(),
             networks: Default::default(),
+            aggregates: Default::default(),
+            redistribute_static: Default::default(),
+            always_compare_med: Default::default(),
+            compare_router_id: Default::default(),
         }
     }
 
@@ -733,12 +914,21 @@ impl RouterBgp {
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{RouterBgp, Target};
     /// assert_eq!(
-    ///     RouterBgp::new(10).no(),
+    ///     RouterBgp::new(10).no(Target::CiscoNexus7000),
     ///     "no router bgp 10\n"
     /// );
+    /// assert_eq!(
+    ///     RouterBgp::new(10).no(Target::Junos),
+    ///     "delete protocols bgp\n"
+    /// );
     /// ```
-    pub fn no(&self) -> String {
-        format!("no router bgp {}\n", self.as_id.0)
+    pub fn no(&self, target: Target) -> String {
+        match target {
+            Target::CiscoNexus7000 | Target::CiscoIos | Target::Frr => {
+                format!("no router bgp {}\n", self.as_id.0)
+            }
+            Target::Junos => String::from("delete protocols bgp\n"),
+        }
     }
 
     /// Set the router-id for the BGP router instance.
@@ -779,6 +969,102 @@ impl RouterBgp {
         self
     }
 
+    /// Compare the MED of routes from different neighboring ASes.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgp, Target};
+    /// assert_eq!(
+    ///     RouterBgp::new(10).always_compare_med().build(Target::Frr),
+    ///     "\
+    /// router bgp 10
+    ///   bgp always-compare-med
+    /// exit
+    /// "
+    /// );
+    /// assert_eq!(
+    ///     RouterBgp::new(10).always_compare_med().build(Target::CiscoNexus7000),
+    ///     "\
+    /// router bgp 10
+    ///   bestpath always-compare-med
+    /// exit
+    /// "
+    /// );
+    /// assert_eq!(
+    ///     RouterBgp::new(10).always_compare_med().build(Target::Junos),
+    ///     "set protocols bgp path-selection always-compare-med\n"
+    /// );
+    /// ```
+    pub fn always_compare_med(&mut self) -> &mut Self {
+        self.always_compare_med = Some(true);
+        self
+    }
+
+    /// Only compare the MED of routes from the same neighboring AS.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgp, Target};
+    /// assert_eq!(
+    ///     RouterBgp::new(10).no_always_compare_med().build(Target::Frr),
+    ///     "\
+    /// router bgp 10
+    ///   no bgp always-compare-med
+    /// exit
+    /// "
+    /// );
+    /// ```
+    pub fn no_always_compare_med(&mut self) -> &mut Self {
+        self.always_compare_med = Some(false);
+        self
+    }
+
+    /// Compare the router-id of eBGP routes instead of preferring the oldest one.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgp, Target};
+    /// assert_eq!(
+    ///     RouterBgp::new(10).compare_router_id().build(Target::Frr),
+    ///     "\
+    /// router bgp 10
+    ///   bgp bestpath compare-routerid
+    /// exit
+    /// "
+    /// );
+    /// assert_eq!(
+    ///     RouterBgp::new(10).compare_router_id().build(Target::CiscoNexus7000),
+    ///     "\
+    /// router bgp 10
+    ///   bestpath compare-routerid
+    /// exit
+    /// "
+    /// );
+    /// assert_eq!(
+    ///     RouterBgp::new(10).compare_router_id().build(Target::Junos),
+    ///     "set protocols bgp path-selection external-router-id\n"
+    /// );
+    /// ```
+    pub fn compare_router_id(&mut self) -> &mut Self {
+        self.compare_router_id = Some(true);
+        self
+    }
+
+    /// Prefer the oldest of two eBGP routes.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgp, Target};
+    /// assert_eq!(
+    ///     RouterBgp::new(10).no_compare_router_id().build(Target::Frr),
+    ///     "\
+    /// router bgp 10
+    ///   no bgp bestpath compare-routerid
+    /// exit
+    /// "
+    /// );
+    /// ```
+    pub fn no_compare_router_id(&mut self) -> &mut Self {
+        self.compare_router_id = Some(false);
+        self
+    }
+
     /// Advertise the specific address over BGP.
     ///
     /// ```
@@ -829,6 +1115,125 @@ impl RouterBgp {
         self
     }
 
+    /// Originate an aggregate of the specific address, as long as a more-specific route exists. If
+    /// `summary_only` is set, all more-specific routes are suppressed. If `as_set` is set, the AS
+    /// path of the aggregate contains the AS numbers of all more-specific routes. Aggregates are not
+    /// supported on Junos, and are ignored.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgp, Target};
+    /// use ipnet::Ipv4Net;
+    ///
+    /// let n1: Ipv4Net = "10.0.0.0/8".parse().unwrap();
+    /// let n2: Ipv4Net = "20.0.0.0/8".parse().unwrap();
+    /// assert_eq!(
+    ///     RouterBgp::new(10)
+    ///         .aggregate_address(n1, false, false)
+    ///         .aggregate_address(n2, true, true)
+    ///         .build(Target::Frr),
+    ///     "\
+    /// router bgp 10
+    ///   address-family ipv4 unicast
+    ///     aggregate-address 10.0.0.0/8
+    ///     aggregate-address 20.0.0.0/8 as-set summary-only
+    ///   exit-address-family
+    /// exit
+    /// "
+    /// )
+    /// ```
+    pub fn aggregate_address(
+        &mut self,
+        network: Ipv4Net,
+        summary_only: bool,
+        as_set: bool,
+    ) -> &mut Self {
+        self.aggregates
+            .push((network, Some((summary_only, as_set))));
+        self
+    }
+
+    /// Stop originating the aggregate of the specific address.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgp, Target};
+    /// use ipnet::Ipv4Net;
+    ///
+    /// let n1: Ipv4Net = "10.0.0.0/8".parse().unwrap();
+    /// assert_eq!(
+    ///     RouterBgp::new(10).no_aggregate_address(n1).build(Target::CiscoNexus7000),
+    ///     "\
+    /// router bgp 10
+    ///   address-family ipv4 unicast
+    ///     no aggregate-address 10.0.0.0/8
+    ///   exit
+    /// exit
+    /// "
+    /// )
+    /// ```
+    pub fn no_aggregate_address(&mut self, network: Ipv4Net) -> &mut Self {
+        self.aggregates.push((network, None));
+        self
+    }
+
+    /// Redistribute all static routes into BGP. On Cisco Nexus, redistribution requires a
+    /// route-map, so the route-map `redistribute-static` is used, which must be configured
+    /// separately. On Junos, static routes are added to the policy that advertises all networks.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgp, Target};
+    /// assert_eq!(
+    ///     RouterBgp::new(10).redistribute_static().build(Target::Frr),
+    ///     "\
+    /// router bgp 10
+    ///   address-family ipv4 unicast
+    ///     redistribute static
+    ///   exit-address-family
+    /// exit
+    /// "
+    /// );
+    /// assert_eq!(
+    ///     RouterBgp::new(10).redistribute_static().build(Target::CiscoNexus7000),
+    ///     "\
+    /// router bgp 10
+    ///   address-family ipv4 unicast
+    ///     redistribute static route-map redistribute-static
+    ///   exit
+    /// exit
+    /// "
+    /// );
+    /// assert_eq!(
+    ///     RouterBgp::new(10).redistribute_static().build(Target::Junos),
+    ///     "\
+    /// set policy-options policy-statement bgp-networks term static from protocol static
+    /// set policy-options policy-statement bgp-networks term static then accept
+    /// "
+    /// );
+    /// ```
+    pub fn redistribute_static(&mut self) -> &mut Self {
+        self.redistribute_static = Some(true);
+        self
+    }
+
+    /// Stop redistributing static routes into BGP.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgp, Target};
+    /// assert_eq!(
+    ///     RouterBgp::new(10).no_redistribute_static().build(Target::Frr),
+    ///     "\
+    /// router bgp 10
+    ///   address-family ipv4 unicast
+    ///     no redistribute static
+    ///   exit-address-family
+    /// exit
+    /// "
+    /// );
+    /// ```
+    pub fn no_redistribute_static(&mut self) -> &mut Self {
+        self.redistribute_static = Some(false);
+        self
+    }
+
     /// Configure a BGP Neighbor using [`RouterBgpNeighbor`]
     ///
     /// ```
@@ -915,35 +1320,135 @@ impl RouterBgp {
     /// "
     /// );
     /// ```
-    pub fn build(&self, target: Target) -> String {
-        // router-id
-        let router_id_pfx = match target {
-            Target::CiscoNexus7000 => "",
-            Target::Frr => "bgp ",
-        };
-        let router_id = match (self.router_id, self.no_router_id) {
-            (Some(id), false) => format!("  {router_id_pfx}router-id {id}\n"),
-            (_, true) => format!("  no {router_id_pfx}router-id\n"),
-            (None, false) => String::new(),
-        };
-
-        // neighbors
-        let mut neighbor_code: String = self
-            .neighbors
-            .iter()
-            .map(|(n, mode)| if *mode { n.build(target) } else { n.no() })
-            .fold(String::new(), |acc, s| acc + &s);
-
-        // remove all address-family code from the neighbors and collect them (in the same order)
-        let af_neighbor_code = match target {
-            Target::CiscoNexus7000 => String::new(),
-            Target::Frr => {
-                let lines = neighbor_code.lines();
-                let mut new_neighbor_code = String::new();
-                let mut af_neighbor_code = String::new();
-                let mut in_af: bool = false;
-                for line in lines {
-                    if line == "  address-family ipv4 unicast" && !in_af {
+    ///
+    /// Cisco IOS uses the same structure, but writes networks with their network mask:
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgp, RouterBgpNeighbor, Target};
+    /// # use std::net::Ipv4Addr;
+    /// use ipnet::Ipv4Net;
+    ///
+    /// let router_id: Ipv4Addr = "10.0.0.1".parse().unwrap();
+    /// let neighbor: Ipv4Addr = "20.0.0.1".parse().unwrap();
+    /// let network: Ipv4Net = "10.0.0.0/8".parse().unwrap();
+    /// assert_eq!(
+    ///     RouterBgp::new(10)
+    ///         .router_id(router_id)
+    ///         .network(network)
+    ///         .aggregate_address(network, true, false)
+    ///         .neighbor(RouterBgpNeighbor::new(neighbor).remote_as(20).next_hop_self())
+    ///         .build(Target::CiscoIos),
+    ///     "\
+    /// router bgp 10
+    ///   bgp router-id 10.0.0.1
+    ///   neighbor 20.0.0.1 remote-as 20
+    ///   address-family ipv4 unicast
+    ///     network 10.0.0.0 mask 255.0.0.0
+    ///     aggregate-address 10.0.0.0 255.0.0.0 summary-only
+    ///     neighbor 20.0.0.1 next-hop-self
+    ///   exit-address-family
+    /// exit
+    /// "
+    /// );
+    /// ```
+    ///
+    /// On Junos, each neighbor is configured in its own group, and all networks are advertised
+    /// using the export policy `bgp-networks`, which is added to the export policies of each
+    /// neighbor with an outgoing route-map.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgp, RouterBgpNeighbor, Target};
+    /// # use std::net::Ipv4Addr;
+    /// use ipnet::Ipv4Net;
+    ///
+    /// let router_id: Ipv4Addr = "10.0.0.1".parse().unwrap();
+    /// let neighbor: Ipv4Addr = "20.0.0.1".parse().unwrap();
+    /// let network: Ipv4Net = "10.0.0.0/8".parse().unwrap();
+    /// assert_eq!(
+    ///     RouterBgp::new(10)
+    ///         .router_id(router_id)
+    ///         .network(network)
+    ///         .neighbor(
+    ///             RouterBgpNeighbor::new(neighbor)
+    ///                 .remote_as(20)
+    ///                 .next_hop_self()
+    ///                 .route_map_in("swisscom-in")
+    ///         )
+    ///         .build(Target::Junos),
+    ///     "\
+    /// set routing-options router-id 10.0.0.1
+    /// set routing-options autonomous-system 10
+    /// set policy-options policy-statement bgp-networks term net-10.0.0.0-8 from route-filter 10.0.0.0/8 exact
+    /// set policy-options policy-statement bgp-networks term net-10.0.0.0-8 then accept
+    /// set protocols bgp group peer-20-0-0-1 type external
+    /// set protocols bgp group peer-20-0-0-1 peer-as 20
+    /// set protocols bgp group peer-20-0-0-1 neighbor 20.0.0.1
+    /// set policy-options policy-statement next-hop-self then next-hop self
+    /// set protocols bgp group peer-20-0-0-1 export next-hop-self
+    /// set protocols bgp group peer-20-0-0-1 import swisscom-in
+    /// "
+    /// );
+    /// ```
+    pub fn build(&self, target: Target) -> String {
+        // router-id
+        let router_id_pfx = match target {
+            Target::CiscoNexus7000 => "",
+            Target::CiscoIos | Target::Frr => "bgp ",
+            Target::Junos => return self.build_junos(),
+        };
+        let router_id = match (self.router_id, self.no_router_id) {
+            (Some(id), false) => format!("  {router_id_pfx}router-id {id}\n"),
+            (_, true) => format!("  no {router_id_pfx}router-id\n"),
+            (None, false) => String::new(),
+        };
+
+        // always-compare-med
+        let med_pfx = match target {
+            Target::CiscoNexus7000 => "bestpath",
+            Target::CiscoIos | Target::Frr | Target::Junos => "bgp",
+        };
+        let always_compare_med = match self.always_compare_med {
+            Some(true) => format!("  {med_pfx} always-compare-med\n"),
+            Some(false) => format!("  no {med_pfx} always-compare-med\n"),
+            None => String::new(),
+        };
+
+        // bestpath compare-routerid
+        let bestpath_pfx = match target {
+            Target::CiscoNexus7000 => "bestpath",
+            Target::CiscoIos | Target::Frr | Target::Junos => "bgp bestpath",
+        };
+        let compare_router_id = match self.compare_router_id {
+            Some(true) => format!("  {bestpath_pfx} compare-routerid\n"),
+            Some(false) => format!("  no {bestpath_pfx} compare-routerid\n"),
+            None => String::new(),
+        };
+
+        // neighbors
+        let mut neighbor_code: String = self
+            .neighbors
+            .iter()
+            .map(
+                |(n, mode)| {
+                    if *mode {
+                        n.build(target)
+                    } else {
+                        n.no(target)
+                    }
+                },
+            )
+            .fold(String::new(), |acc, s| acc + &s);
+
+        // remove all address-family code from the neighbors and collect them (in the same order)
+        let af_neighbor_code = match target {
+            Target::CiscoNexus7000 | Target::Junos => String::new(),
+            Target::CiscoIos | Target::Frr => {
+                let lines = neighbor_code.lines();
+                let mut new_neighbor_code = String::new();
+                let mut af_neighbor_code = String::new();
+                let mut in_af: bool = false;
+                for line in lines {
+                    if line == "  address-family ipv4 unicast" && !in_af {
                         in_af = true;
                     } else if line == "  exit" && in_af {
                         in_af = false;
@@ -965,6 +1470,10 @@ impl RouterBgp {
             .networks
             .iter()
             .map(|(n, mode)| {
+                let n = match target {
+                    Target::CiscoIos => format!("{} mask {}", n.addr(), n.netmask()),
+                    Target::CiscoNexus7000 | Target::Frr | Target::Junos => n.to_string(),
+                };
                 if *mode {
                     format!("    network {n}\n")
                 } else {
@@ -973,30 +1482,116 @@ impl RouterBgp {
             })
             .fold(String::new(), |acc, s| acc + &s);
 
-        let af = if network_code.is_empty() && af_neighbor_code.is_empty() {
+        // aggregates
+        let aggregate_code: String = self
+            .aggregates
+            .iter()
+            .map(|(n, mode)| match mode {
+                Some((summary_only, as_set)) => format!(
+                    "    aggregate-address {}{}{}\n",
+                    fmt_net(target, *n),
+                    if *as_set { " as-set" } else { "" },
+                    if *summary_only { " summary-only" } else { "" },
+                ),
+                None => format!("    no aggregate-address {}\n", fmt_net(target, *n)),
+            })
+            .fold(String::new(), |acc, s| acc + &s);
+
+        // redistribution
+        let redistribute_rm = match target {
+            Target::CiscoNexus7000 => format!(" route-map {NEXUS_REDISTRIBUTE_STATIC_RM}"),
+            Target::CiscoIos | Target::Frr | Target::Junos => String::new(),
+        };
+        let redistribute_code = match self.redistribute_static {
+            Some(true) => format!("    redistribute static{redistribute_rm}\n"),
+            Some(false) => format!("    no redistribute static{redistribute_rm}\n"),
+            None => String::new(),
+        };
+
+        let af = if network_code.is_empty()
+            && aggregate_code.is_empty()
+            && redistribute_code.is_empty()
+            && af_neighbor_code.is_empty()
+        {
             String::new()
         } else {
             let exit_af = match target {
-                Target::CiscoNexus7000 => "",
-                Target::Frr => "-address-family",
+                Target::CiscoNexus7000 | Target::Junos => "",
+                Target::CiscoIos | Target::Frr => "-address-family",
             };
             format!(
-                "  address-family ipv4 unicast\n{network_code}{af_neighbor_code}  exit{exit_af}\n"
+                "  address-family ipv4 unicast\n{network_code}{aggregate_code}{redistribute_code}{af_neighbor_code}  exit{exit_af}\n"
             )
         };
 
         format!(
             "\
 router bgp {id}
-{router_id}{neighbors}{af}\
+{router_id}{always_compare_med}{compare_router_id}{neighbors}{af}\
 exit
 ",
             id = self.as_id.0,
             router_id = router_id,
+            always_compare_med = always_compare_med,
+            compare_router_id = compare_router_id,
             neighbors = neighbor_code,
             af = af
         )
     }
+
+    /// Generate the set-style commands for Junos.
+    fn build_junos(&self) -> String {
+        let mut cfg = String::new();
+        match (self.router_id, self.no_router_id) {
+            (Some(id), false) => cfg.push_str(&format!(
+                "set routing-options router-id {id}\n\
+                 set routing-options autonomous-system {}\n",
+                self.as_id.0
+            )),
+            (_, true) => cfg.push_str("delete routing-options router-id\n"),
+            (None, false) => {}
+        }
+        match self.always_compare_med {
+            Some(true) => cfg.push_str("set protocols bgp path-selection always-compare-med\n"),
+            Some(false) => cfg.push_str("delete protocols bgp path-selection always-compare-med\n"),
+            None => {}
+        }
+        match self.compare_router_id {
+            Some(true) => cfg.push_str("set protocols bgp path-selection external-router-id\n"),
+            Some(false) => cfg.push_str("delete protocols bgp path-selection external-router-id\n"),
+            None => {}
+        }
+        for (net, mode) in self.networks.iter() {
+            let term = format!(
+                "policy-options policy-statement {JUNOS_NETWORKS_POLICY} term net-{}-{}",
+                net.addr(),
+                net.prefix_len()
+            );
+            if *mode {
+                cfg.push_str(&format!(
+                    "set {term} from route-filter {net} exact\nset {term} then accept\n"
+                ));
+            } else {
+                cfg.push_str(&format!("delete {term}\n"));
+            }
+        }
+        let term = format!("policy-options policy-statement {JUNOS_NETWORKS_POLICY} term static");
+        match self.redistribute_static {
+            Some(true) => cfg.push_str(&format!(
+                "set {term} from protocol static\nset {term} then accept\n"
+            )),
+            Some(false) => cfg.push_str(&format!("delete {term}\n")),
+            None => {}
+        }
+        for (n, mode) in self.neighbors.iter() {
+            cfg.push_str(&if *mode {
+                n.build_junos(Some(self.as_id))
+            } else {
+                n.no(Target::Junos)
+            });
+        }
+        cfg
+    }
 }
 
 /// BGP Router neighbor configuration for Cisco-like routers
@@ -1008,6 +1603,8 @@ pub struct RouterBgpNeighbor {
     no_weight: bool,
     update_source: Option<String>,
     no_update_source: bool,
+    advertisement_interval: Option<u32>,
+    no_advertisement_interval: bool,
     next_hop_self: Option<bool>,
     route_reflector_client: Option<bool>,
     route_map_in: Option<String>,
@@ -1016,6 +1613,8 @@ pub struct RouterBgpNeighbor {
     no_route_map_out: bool,
     send_community: Option<bool>,
     soft_reconfiguration: Option<bool>,
+    local_address: Option<Ipv4Addr>,
+    cluster_id: Option<Ipv4Addr>,
 }
 
 impl RouterBgpNeighbor {
@@ -1028,6 +1627,8 @@ impl RouterBgpNeighbor {
             no_weight: Default::default(),
             update_source: Default::default(),
             no_update_source: Default::default(),
+            advertisement_interval: Default::default(),
+            no_advertisement_interval: Default::default(),
             next_hop_self: Default::default(),
             route_reflector_client: Default::default(),
             route_map_in: Default::default(),
@@ -1036,19 +1637,33 @@ impl RouterBgpNeighbor {
             no_route_map_out: Default::default(),
             send_community: Default::default(),
             soft_reconfiguration: Default::default(),
+            local_address: Default::default(),
+            cluster_id: Default::default(),
         }
     }
 
     /// Remove the neighbor from the configuration.
     ///
     /// ```
-    /// # use bgpsim::export::cisco_frr_generators::RouterBgpNeighbor;
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgpNeighbor, Target};
     /// # use std::net::Ipv4Addr;
     /// let neighbor_addr: Ipv4Addr = "20.0.0.1".parse().unwrap();
-    /// assert_eq!(RouterBgpNeighbor::new(neighbor_addr).no(), "  no neighbor 20.0.0.1\n");
+    /// assert_eq!(
+    ///     RouterBgpNeighbor::new(neighbor_addr).no(Target::CiscoNexus7000),
+    ///     "  no neighbor 20.0.0.1\n"
+    /// );
+    /// assert_eq!(
+    ///     RouterBgpNeighbor::new(neighbor_addr).no(Target::Junos),
+    ///     "delete protocols bgp group peer-20-0-0-1\n"
+    /// );
     /// ```
-    pub fn no(&self) -> String {
-        format!("  no neighbor {}\n", self.neighbor_id)
+    pub fn no(&self, target: Target) -> String {
+        match target {
+            Target::CiscoNexus7000 | Target::CiscoIos | Target::Frr => {
+                format!("  no neighbor {}\n", self.neighbor_id)
+            }
+            Target::Junos => format!("delete protocols bgp group {}\n", self.junos_group()),
+        }
     }
 
     /// Set the remote-as. For `Target::Frr`.
@@ -1205,6 +1820,112 @@ impl RouterBgpNeighbor {
         self
     }
 
+    /// Set the minimum route advertisement interval (MRAI) in seconds. The interval is rounded to
+    /// the nearest integer. On Junos, this sets the `out-delay`.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgpNeighbor, Target};
+    /// # use std::net::Ipv4Addr;
+    /// let neighbor_addr: Ipv4Addr = "20.0.0.1".parse().unwrap();
+    /// assert_eq!(
+    ///     RouterBgpNeighbor::new(neighbor_addr)
+    ///         .advertisement_interval(5.0)
+    ///         .build(Target::CiscoNexus7000),
+    /// #   "  ".to_owned() +
+    ///     "\
+    ///   neighbor 20.0.0.1
+    ///     advertisement-interval 5
+    ///   exit
+    /// "
+    /// );
+    /// assert_eq!(
+    ///     RouterBgpNeighbor::new(neighbor_addr)
+    ///         .advertisement_interval(5.0)
+    ///         .build(Target::Frr),
+    ///     "  neighbor 20.0.0.1 advertisement-interval 5\n"
+    /// );
+    /// assert_eq!(
+    ///     RouterBgpNeighbor::new(neighbor_addr)
+    ///         .advertisement_interval(5.0)
+    ///         .build(Target::Junos),
+    ///     "\
+    /// set protocols bgp group peer-20-0-0-1 neighbor 20.0.0.1
+    /// set protocols bgp group peer-20-0-0-1 out-delay 5
+    /// "
+    /// );
+    /// ```
+    pub fn advertisement_interval(&mut self, interval: f64) -> &mut Self {
+        self.advertisement_interval = Some(interval.round().max(0.0) as u32);
+        self
+    }
+
+    /// Unset the minimum route advertisement interval (MRAI), such that the default value is used.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgpNeighbor, Target};
+    /// # use std::net::Ipv4Addr;
+    /// let neighbor_addr: Ipv4Addr = "20.0.0.1".parse().unwrap();
+    /// assert_eq!(
+    ///     RouterBgpNeighbor::new(neighbor_addr)
+    ///         .no_advertisement_interval()
+    ///         .build(Target::Frr),
+    ///     "  no neighbor 20.0.0.1 advertisement-interval\n"
+    /// );
+    /// ```
+    pub fn no_advertisement_interval(&mut self) -> &mut Self {
+        self.no_advertisement_interval = true;
+        self
+    }
+
+    /// Set the local address of the session. This is only used for Junos, where the session is
+    /// sourced from an address instead of an interface (see [`RouterBgpNeighbor::update_source`]).
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgpNeighbor, Target};
+    /// # use std::net::Ipv4Addr;
+    /// let neighbor_addr: Ipv4Addr = "10.0.1.1".parse().unwrap();
+    /// let local_addr: Ipv4Addr = "10.0.0.1".parse().unwrap();
+    /// assert_eq!(
+    ///     RouterBgpNeighbor::new(neighbor_addr)
+    ///         .local_address(local_addr)
+    ///         .build(Target::Junos),
+    ///     "\
+    /// set protocols bgp group peer-10-0-1-1 neighbor 10.0.1.1
+    /// set protocols bgp group peer-10-0-1-1 local-address 10.0.0.1
+    /// "
+    /// );
+    /// ```
+    pub fn local_address(&mut self, addr: Ipv4Addr) -> &mut Self {
+        self.local_address = Some(addr);
+        self
+    }
+
+    /// Set the cluster ID that is used if the neighbor is a route-reflector client. This is only
+    /// used for Junos, where route-reflector clients are configured with the cluster ID of the
+    /// route reflector. If not set, the local address is used (see
+    /// [`RouterBgpNeighbor::local_address`]).
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouterBgpNeighbor, Target};
+    /// # use std::net::Ipv4Addr;
+    /// let neighbor_addr: Ipv4Addr = "10.0.1.1".parse().unwrap();
+    /// let cluster_id: Ipv4Addr = "10.0.0.1".parse().unwrap();
+    /// assert_eq!(
+    ///     RouterBgpNeighbor::new(neighbor_addr)
+    ///         .cluster_id(cluster_id)
+    ///         .route_reflector_client()
+    ///         .build(Target::Junos),
+    ///     "\
+    /// set protocols bgp group peer-10-0-1-1 neighbor 10.0.1.1
+    /// set protocols bgp group peer-10-0-1-1 cluster 10.0.0.1
+    /// "
+    /// );
+    /// ```
+    pub fn cluster_id(&mut self, id: Ipv4Addr) -> &mut Self {
+        self.cluster_id = Some(id);
+        self
+    }
+
     /// Set the `next-hop-self` attribute for all routes that are received by this neighbor, and
     /// sent to that neighbor.
     ///
@@ -1653,9 +2374,11 @@ impl RouterBgpNeighbor {
         self
     }
 
-    /// Generate the configuration lines
+    /// Generate the configuration lines. When building the neighbor for Junos on its own (without
+    /// [`RouterBgp`]), then the session is assumed to be external if the remote AS is set.
     pub fn build(&self, target: Target) -> String {
         let (mut cfg, pre, tab, finish) = match target {
+            Target::Junos => return self.build_junos(None),
             Target::CiscoNexus7000 => (
                 match self.remote_as {
                     Some(id) => format!("  neighbor {} remote-as {}", self.neighbor_id, id.0),
@@ -1665,7 +2388,7 @@ impl RouterBgpNeighbor {
                 "  ",
                 "\n  exit\n",
             ),
-            Target::Frr => (
+            Target::CiscoIos | Target::Frr => (
                 match self.remote_as {
                     Some(id) => format!("  neighbor {} remote-as {}", self.neighbor_id, id.0),
                     None => String::new(),
@@ -1721,6 +2444,13 @@ impl RouterBgpNeighbor {
             (None, false) => {}
         }
 
+        // advertisement interval
+        match (self.advertisement_interval, self.no_advertisement_interval) {
+            (Some(i), false) => cfg.push_str(&format!("\n  {tab}{pre}advertisement-interval {i}")),
+            (_, true) => cfg.push_str(&format!("\n  {tab}no {pre}advertisement-interval")),
+            (None, false) => {}
+        }
+
         // send-community
         match self.send_community.as_ref() {
             Some(true) => af.push_str(&format!("\n    {tab}{pre}send-community")),
@@ -1746,6 +2476,96 @@ impl RouterBgpNeighbor {
         // remote the first newline and return
         String::from(cfg.trim_start_matches('\n'))
     }
+
+    /// Name of the Junos BGP group that contains only this neighbor.
+    fn junos_group(&self) -> String {
+        format!("peer-{}", self.neighbor_id.to_string().replace('.', "-"))
+    }
+
+    /// Generate the set-style commands for Junos. The session is internal if `local_as` equals the
+    /// remote AS. The update source and sending communities are ignored, as Junos uses the local
+    /// address and always sends communities.
+    fn build_junos(&self, local_as: Option<AsId>) -> String {
+        let g = format!("protocols bgp group {}", self.junos_group());
+        let mut cfg = String::new();
+
+        if let Some(remote_as) = self.remote_as {
+            if Some(remote_as) == local_as {
+                cfg.push_str(&format!("set {g} type internal\n"));
+            } else {
+                cfg.push_str(&format!(
+                    "set {g} type external\nset {g} peer-as {}\n",
+                    remote_as.0
+                ));
+            }
+        }
+        cfg.push_str(&format!("set {g} neighbor {}\n", self.neighbor_id));
+        if let Some(addr) = self.local_address {
+            cfg.push_str(&format!("set {g} local-address {addr}\n"));
+        }
+
+        // weight
+        match (self.weight, self.no_weight) {
+            (Some(w), false) => {
+                cfg.push_str(&format!("set {g} preference {}\n", junos_preference(w)))
+            }
+            (_, true) => cfg.push_str(&format!("delete {g} preference\n")),
+            (None, false) => {}
+        }
+
+        // advertisement interval
+        match (self.advertisement_interval, self.no_advertisement_interval) {
+            (Some(i), false) => cfg.push_str(&format!("set {g} out-delay {i}\n")),
+            (_, true) => cfg.push_str(&format!("delete {g} out-delay\n")),
+            (None, false) => {}
+        }
+
+        // route-reflector-client
+        match self.route_reflector_client {
+            Some(true) => {
+                if let Some(id) = self.cluster_id.or(self.local_address) {
+                    cfg.push_str(&format!("set {g} cluster {id}\n"))
+                }
+            }
+            Some(false) => cfg.push_str(&format!("delete {g} cluster\n")),
+            None => {}
+        }
+
+        // next-hop self
+        match self.next_hop_self {
+            Some(true) => cfg.push_str(&format!(
+                "set policy-options policy-statement {JUNOS_NEXT_HOP_SELF_POLICY} then next-hop self\n\
+                 set {g} export {JUNOS_NEXT_HOP_SELF_POLICY}\n"
+            )),
+            Some(false) => cfg.push_str(&format!("delete {g} export {JUNOS_NEXT_HOP_SELF_POLICY}\n")),
+            None => {}
+        }
+
+        // route-map-in
+        match (self.route_map_in.as_ref(), self.no_route_map_in) {
+            (Some(name), false) => cfg.push_str(&format!("set {g} import {name}\n")),
+            (Some(name), true) => cfg.push_str(&format!("delete {g} import {name}\n")),
+            (None, _) => {}
+        }
+
+        // route-map-out
+        match (self.route_map_out.as_ref(), self.no_route_map_out) {
+            (Some(name), false) => cfg.push_str(&format!(
+                "set {g} export {name}\nset {g} export {JUNOS_NETWORKS_POLICY}\n"
+            )),
+            (Some(name), true) => cfg.push_str(&format!("delete {g} export {name}\n")),
+            (None, _) => {}
+        }
+
+        // soft-reconfiguration inbound
+        match self.soft_reconfiguration {
+            Some(true) => cfg.push_str(&format!("set {g} keep all\n")),
+            Some(false) => cfg.push_str(&format!("delete {g} keep\n")),
+            None => {}
+        }
+
+        cfg
+    }
 }
 
 impl From<&mut RouterBgpNeighbor> for RouterBgpNeighbor {
@@ -1785,9 +2605,18 @@ impl StaticRoute {
     ///     StaticRoute::new(dest).via_interface("eth1").no(Target::Frr),
     ///     "no ip route 1.0.0.0/8 eth1\n"
     /// );
+    /// assert_eq!(
+    ///     StaticRoute::new(dest).via_interface("ge-0/0/1").no(Target::Junos),
+    ///     "delete routing-options static route 1.0.0.0/8\n"
+    /// );
     /// ```
     pub fn no(&self, target: Target) -> String {
-        format!("no {}", self.build(target))
+        match target {
+            Target::CiscoNexus7000 | Target::CiscoIos | Target::Frr => {
+                format!("no {}", self.build(target))
+            }
+            Target::Junos => format!("delete routing-options static route {}\n", self.destination),
+        }
     }
 
     /// Route packets via the given address (i.e., pick the same next-hop as written in the routing
@@ -1804,6 +2633,13 @@ impl StaticRoute {
     ///     StaticRoute::new(dest).via_address(target).build(Target::Frr),
     ///     "ip route 1.0.0.0/8 10.0.1.1\n"
     /// );
+    /// assert_eq!(
+    ///     StaticRoute::new(dest).via_address(target).build(Target::Junos),
+    ///     "\
+    /// set routing-options static route 1.0.0.0/8 next-hop 10.0.1.1
+    /// set routing-options static route 1.0.0.0/8 resolve
+    /// "
+    /// );
     /// ```
     pub fn via_address(&mut self, addr: Ipv4Addr) -> &mut Self {
         self.target = Some(addr.to_string());
@@ -1869,19 +2705,59 @@ impl StaticRoute {
 
     /// Build the command. If you have neither called `via_address` or `via_interface`, the `build`
     /// function will create a command that will blackhole all traffic.
-    pub fn build(&self, target: Target) -> String {
-        let null = match target {
-            Target::CiscoNexus7000 => "null 0",
-            Target::Frr => "Null0",
-        };
-        let pref = self.pref.map(|p| format!(" {p}")).unwrap_or_default();
-        format!(
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{StaticRoute, Target};
+    /// use ipnet::Ipv4Net;
+    ///
+    /// let dest: Ipv4Net = "1.0.0.0/8".parse().unwrap();
+    /// assert_eq!(
+    ///     StaticRoute::new(dest).blackhole().build(Target::CiscoIos),
+    ///     "ip route 1.0.0.0 255.0.0.0 Null0\n"
+    /// );
+    /// assert_eq!(
+    ///     StaticRoute::new(dest).via_interface("ge-0/0/1").preference(5).build(Target::Junos),
+    ///     "\
+    /// set routing-options static route 1.0.0.0/8 next-hop ge-0/0/1.0
+    /// set routing-options static route 1.0.0.0/8 preference 5
+    /// "
+    /// );
+    /// assert_eq!(
+    ///     StaticRoute::new(dest).blackhole().build(Target::Junos),
+    ///     "set routing-options static route 1.0.0.0/8 discard\n"
+    /// );
+    /// ```
+    pub fn build(&self, target: Target) -> String {
+        let null = match target {
+            Target::CiscoNexus7000 => "null 0",
+            Target::CiscoIos | Target::Frr => "Null0",
+            Target::Junos => return self.build_junos(),
+        };
+        let pref = self.pref.map(|p| format!(" {p}")).unwrap_or_default();
+        format!(
             "ip route {} {}{}\n",
-            self.destination,
+            fmt_net(target, self.destination),
             self.target.as_deref().unwrap_or(null),
             pref
         )
     }
+
+    /// Generate the set-style commands for Junos.
+    fn build_junos(&self) -> String {
+        let route = format!("routing-options static route {}", self.destination);
+        let mut cfg = match self.target.as_deref() {
+            None => format!("set {route} discard\n"),
+            // Junos only resolves next-hops that are not directly connected with `resolve`.
+            Some(t) if t.parse::<Ipv4Addr>().is_ok() => {
+                format!("set {route} next-hop {t}\nset {route} resolve\n")
+            }
+            Some(t) => format!("set {route} next-hop {}\n", junos_unit(t)),
+        };
+        if let Some(p) = self.pref {
+            cfg.push_str(&format!("set {route} preference {p}\n"));
+        }
+        cfg
+    }
 }
 
 /// Create a route-map item, including the necessary prefix-lists, community-lists and as-path
@@ -1898,7 +2774,7 @@ impl StaticRoute {
 ///         .set_weight(200)
 ///         .set_local_pref(200)
 ///         .continues(20)
-///         .build(Target::CiscoNexus7000),
+///         .build(Target::CiscoNexus7000).unwrap(),
 ///     "\
 /// ip community-list standard test-cl permit 10:10
 /// ip prefix-list test-nh seq 1 permit 10.0.1.1/32
@@ -1922,14 +2798,17 @@ pub struct RouteMapItem {
     match_community_list: Vec<(CommunityList, bool)>,
     match_as_path_list: Vec<(AsPathList, bool)>,
     match_next_hop_pl: Vec<(PrefixList, bool)>,
+    match_origin: Option<(Origin, bool)>,
     set_next_hop: Option<(Ipv4Addr, bool)>,
     set_weight: Option<(u16, bool)>,
     set_local_pref: Option<(u32, bool)>,
     set_med: Option<(u32, bool)>,
+    set_origin: Option<(Origin, bool)>,
     set_community: Vec<(String, bool)>,
     delete_community: Vec<(CommunityList, bool)>,
     prepend_as_path: Option<(Vec<AsId>, bool)>,
     cont: Option<(u16, bool)>,
+    insert_before: Option<u16>,
 }
 
 impl RouteMapItem {
@@ -1944,14 +2823,17 @@ impl RouteMapItem {
             match_community_list: Default::default(),
             match_as_path_list: Default::default(),
             match_next_hop_pl: Default::default(),
+            match_origin: Default::default(),
             set_next_hop: Default::default(),
             set_weight: Default::default(),
             set_local_pref: Default::default(),
             set_med: Default::default(),
+            set_origin: Default::default(),
             set_community: Default::default(),
             delete_community: Default::default(),
             prepend_as_path: Default::default(),
             cont: Default::default(),
+            insert_before: Default::default(),
         }
     }
 
@@ -1965,7 +2847,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .match_prefix_list(PrefixList::new("test-pl").prefix(net))
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// ip prefix-list test-pl seq 1 permit 10.0.0.0/8
     /// route-map test permit 10
@@ -1987,7 +2869,7 @@ impl RouteMapItem {
     ///     RouteMapItem::new("test", 10, true)
     ///         .no_match_prefix_list(PrefixList::new("test-pl-old"))
     ///         .match_prefix_list(PrefixList::new("test-pl-new").prefix(net))
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// no ip prefix-list test-pl-old
     /// ip prefix-list test-pl-new seq 1 permit 20.0.0.0/8
@@ -2010,7 +2892,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .no_match_prefix_list(PrefixList::new("test-pl"))
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// no ip prefix-list test-pl
     /// route-map test permit 10
@@ -2033,7 +2915,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .match_global_prefix_list("global-pl")
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   match ip address prefix-list global-pl
@@ -2054,7 +2936,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .no_match_global_prefix_list("global-pl")
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   no match ip address prefix-list global-pl
@@ -2075,7 +2957,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .match_community_list(CommunityList::new("test-cl").community(10, 10))
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// bgp community-list standard test-cl permit 10:10
     /// route-map test permit 10
@@ -2094,7 +2976,7 @@ impl RouteMapItem {
     ///     RouteMapItem::new("test", 10, true)
     ///         .no_match_community_list(CommunityList::new("test-cl-old"))
     ///         .match_community_list(CommunityList::new("test-cl-new").community(10, 20))
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// no bgp community-list standard test-cl-old
     /// bgp community-list standard test-cl-new permit 10:20
@@ -2118,7 +3000,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .no_match_community_list(CommunityList::new("test-cl"))
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// no bgp community-list standard test-cl
     /// route-map test permit 10
@@ -2143,7 +3025,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .match_as_path_list(AsPathList::new("test-asl").contains_as(10))
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// bgp as-path access-list test-asl permit _10_
     /// route-map test permit 10
@@ -2162,7 +3044,7 @@ impl RouteMapItem {
     ///     RouteMapItem::new("test", 10, true)
     ///         .no_match_as_path_list(AsPathList::new("test-asl-old"))
     ///         .match_as_path_list(AsPathList::new("test-asl-new").contains_as(20))
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// no bgp as-path access-list test-asl-old
     /// bgp as-path access-list test-asl-new permit _20_
@@ -2185,7 +3067,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .no_match_as_path_list(AsPathList::new("test-asl"))
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// no bgp as-path access-list test-asl
     /// route-map test permit 10
@@ -2209,7 +3091,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .match_next_hop(PrefixList::new("test-nh-pl").prefix(net))
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// ip prefix-list test-nh-pl seq 1 permit 10.0.0.0/8
     /// route-map test permit 10
@@ -2231,7 +3113,7 @@ impl RouteMapItem {
     ///     RouteMapItem::new("test", 10, true)
     ///         .no_match_next_hop(PrefixList::new("test-nh-pl-old"))
     ///         .match_next_hop(PrefixList::new("test-nh-pl-new").prefix(net))
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// no ip prefix-list test-nh-pl-old
     /// ip prefix-list test-nh-pl-new seq 1 permit 20.0.0.0/8
@@ -2254,7 +3136,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .no_match_next_hop(PrefixList::new("test-nh-pl"))
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// no ip prefix-list test-nh-pl
     /// route-map test permit 10
@@ -2268,6 +3150,50 @@ impl RouteMapItem {
         self
     }
 
+    /// Match on the ORIGIN attribute. This is not supported on Cisco Nexus and IOS.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
+    /// # use bgpsim::bgp::Origin;
+    /// assert_eq!(
+    ///     RouteMapItem::new("test", 10, true)
+    ///         .match_origin(Origin::Egp)
+    ///         .build(Target::Frr)
+    ///         .unwrap(),
+    ///     "\
+    /// route-map test permit 10
+    ///   match origin egp
+    /// exit
+    /// "
+    /// );
+    /// ```
+    pub fn match_origin(&mut self, origin: Origin) -> &mut Self {
+        self.match_origin = Some((origin, true));
+        self
+    }
+
+    /// Remove the match on the ORIGIN attribute.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
+    /// # use bgpsim::bgp::Origin;
+    /// assert_eq!(
+    ///     RouteMapItem::new("test", 10, true)
+    ///         .no_match_origin(Origin::Egp)
+    ///         .build(Target::Frr)
+    ///         .unwrap(),
+    ///     "\
+    /// route-map test permit 10
+    ///   no match origin egp
+    /// exit
+    /// "
+    /// );
+    /// ```
+    pub fn no_match_origin(&mut self, origin: Origin) -> &mut Self {
+        self.match_origin = Some((origin, false));
+        self
+    }
+
     /// Set the next-hop field to a specific value.
     ///
     /// ```
@@ -2275,7 +3201,7 @@ impl RouteMapItem {
     /// # use std::net::Ipv4Addr;
     /// let nh: Ipv4Addr = "10.0.0.1".parse().unwrap();
     /// assert_eq!(
-    ///     RouteMapItem::new("test", 10, true).set_next_hop(nh).build(Target::Frr),
+    ///     RouteMapItem::new("test", 10, true).set_next_hop(nh).build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   set ip next-hop 10.0.0.1
@@ -2293,7 +3219,7 @@ impl RouteMapItem {
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
     /// assert_eq!(
-    ///     RouteMapItem::new("test", 10, true).no_set_next_hop().build(Target::Frr),
+    ///     RouteMapItem::new("test", 10, true).no_set_next_hop().build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   no set ip next-hop
@@ -2311,7 +3237,7 @@ impl RouteMapItem {
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
     /// assert_eq!(
-    ///     RouteMapItem::new("test", 10, true).set_weight(200).build(Target::Frr),
+    ///     RouteMapItem::new("test", 10, true).set_weight(200).build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   set weight 200
@@ -2329,7 +3255,7 @@ impl RouteMapItem {
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
     /// assert_eq!(
-    ///     RouteMapItem::new("test", 10, true).no_set_weight().build(Target::Frr),
+    ///     RouteMapItem::new("test", 10, true).no_set_weight().build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   no set weight
@@ -2347,7 +3273,7 @@ impl RouteMapItem {
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
     /// assert_eq!(
-    ///     RouteMapItem::new("test", 10, true).set_local_pref(200).build(Target::Frr),
+    ///     RouteMapItem::new("test", 10, true).set_local_pref(200).build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   set local-preference 200
@@ -2365,7 +3291,7 @@ impl RouteMapItem {
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
     /// assert_eq!(
-    ///     RouteMapItem::new("test", 10, true).no_set_local_pref().build(Target::Frr),
+    ///     RouteMapItem::new("test", 10, true).no_set_local_pref().build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   no set local-preference
@@ -2383,7 +3309,7 @@ impl RouteMapItem {
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
     /// assert_eq!(
-    ///     RouteMapItem::new("test", 10, true).set_med(200).build(Target::Frr),
+    ///     RouteMapItem::new("test", 10, true).set_med(200).build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   set metric 200
@@ -2401,7 +3327,7 @@ impl RouteMapItem {
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
     /// assert_eq!(
-    ///     RouteMapItem::new("test", 10, true).no_set_med().build(Target::Frr),
+    ///     RouteMapItem::new("test", 10, true).no_set_med().build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   no set metric
@@ -2414,12 +3340,52 @@ impl RouteMapItem {
         self
     }
 
+    /// Set the ORIGIN attribute of the route
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
+    /// # use bgpsim::bgp::Origin;
+    /// assert_eq!(
+    ///     RouteMapItem::new("test", 10, true)
+    ///         .set_origin(Origin::Incomplete)
+    ///         .build(Target::Frr)
+    ///         .unwrap(),
+    ///     "\
+    /// route-map test permit 10
+    ///   set origin incomplete
+    /// exit
+    /// "
+    /// );
+    /// ```
+    pub fn set_origin(&mut self, origin: Origin) -> &mut Self {
+        self.set_origin = Some((origin, true));
+        self
+    }
+
+    /// Remove the set of the ORIGIN attribute.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
+    /// assert_eq!(
+    ///     RouteMapItem::new("test", 10, true).no_set_origin().build(Target::Frr).unwrap(),
+    ///     "\
+    /// route-map test permit 10
+    ///   no set origin
+    /// exit
+    /// "
+    /// );
+    /// ```
+    pub fn no_set_origin(&mut self) -> &mut Self {
+        self.set_origin = Some((Origin::Igp, false));
+        self
+    }
+
     /// Set a specific community tag
     ///
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
     /// assert_eq!(
-    ///     RouteMapItem::new("test", 10, true).set_community(10, 10).build(Target::Frr),
+    ///     RouteMapItem::new("test", 10, true).set_community(10, 10).build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   set community 10:10
@@ -2433,13 +3399,27 @@ impl RouteMapItem {
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
     /// assert_eq!(
-    ///     RouteMapItem::new("test", 10, true).set_community(10, 10).build(Target::CiscoNexus7000),
+    ///     RouteMapItem::new("test", 10, true)
+    ///         .set_community(10, 10)
+    ///         .build(Target::CiscoNexus7000)
+    ///         .unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   set community additive 10:10
     /// exit
     /// "
     /// );
+    /// assert_eq!(
+    ///     RouteMapItem::new("test", 10, true)
+    ///         .set_community(10, 10)
+    ///         .build(Target::CiscoIos)
+    ///         .unwrap(),
+    ///     "\
+    /// route-map test permit 10
+    ///   set community 10:10 additive
+    /// exit
+    /// "
+    /// );
     /// ```
     pub fn set_community(&mut self, as_id: impl Into<AsId>, community: u32) -> &mut Self {
         self.set_community
@@ -2452,7 +3432,10 @@ impl RouteMapItem {
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
     /// assert_eq!(
-    ///     RouteMapItem::new("test", 10, true).no_set_community(10, 10).build(Target::Frr),
+    ///     RouteMapItem::new("test", 10, true)
+    ///         .no_set_community(10, 10)
+    ///         .build(Target::Frr)
+    ///         .unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   no set community 10:10
@@ -2468,7 +3451,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .no_set_community(10, 10)
-    ///         .build(Target::CiscoNexus7000),
+    ///         .build(Target::CiscoNexus7000).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   no set community additive 10:10
@@ -2489,7 +3472,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .delete_community_list(CommunityList::new("test-cl-del").community(10, 10))
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// bgp community-list standard test-cl-del permit 10:10
     /// route-map test permit 10
@@ -2510,7 +3493,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .no_remove_community_list(CommunityList::new("test-cl-del"))
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// no bgp community-list standard test-cl-del
     /// route-map test permit 10
@@ -2534,7 +3517,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .prepend_as_path([1, 2, 3])
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   set as-path prepend 1 2 3
@@ -2557,7 +3540,7 @@ impl RouteMapItem {
     /// assert_eq!(
     ///     RouteMapItem::new("test", 10, true)
     ///         .no_prepend_as_path()
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   no set as-path prepend
@@ -2576,7 +3559,7 @@ impl RouteMapItem {
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
     /// assert_eq!(
-    ///     RouteMapItem::new("test", 10, true).continues(20).build(Target::Frr),
+    ///     RouteMapItem::new("test", 10, true).continues(20).build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   continue 20
@@ -2595,7 +3578,7 @@ impl RouteMapItem {
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
     /// assert_eq!(
-    ///     RouteMapItem::new("test", 10, true).no_continues().build(Target::Frr),
+    ///     RouteMapItem::new("test", 10, true).no_continues().build(Target::Frr).unwrap(),
     ///     "\
     /// route-map test permit 10
     ///   no continue
@@ -2608,6 +3591,26 @@ impl RouteMapItem {
         self
     }
 
+    /// Place the route-map item before the item with the given order. This is only used for Junos,
+    /// where policy terms are evaluated in the order in which they were configured, and new terms
+    /// are appended at the end. The item with order `next_seq` must already exist.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, Target};
+    /// assert_eq!(
+    ///     RouteMapItem::new("test", 10, true).insert_before(20).build(Target::Junos).unwrap(),
+    ///     "\
+    /// set policy-options policy-statement test term 10 from protocol bgp
+    /// set policy-options policy-statement test term 10 then accept
+    /// insert policy-options policy-statement test term 10 before term 20
+    /// "
+    /// );
+    /// ```
+    pub fn insert_before(&mut self, next_seq: u16) -> &mut Self {
+        self.insert_before = Some(next_seq);
+        self
+    }
+
     /// Remove the route-map item, along with all prefix-lists, community-lists, and as-path
     /// access-lists that belong to that route-map item.
     ///
@@ -2629,8 +3632,15 @@ impl RouteMapItem {
     /// ```
     pub fn no(&self, target: Target) -> String {
         let mut cfg = String::new();
+        if target == Target::Junos {
+            // the term must be removed before the lists it references.
+            cfg.push_str(&format!(
+                "delete policy-options policy-statement {} term {}\n",
+                self.name, self.order
+            ));
+        }
         for (pl, _) in self.match_prefix_list.iter() {
-            cfg.push_str(&pl.no());
+            cfg.push_str(&pl.no(target));
         }
         for (cl, _) in self.match_community_list.iter() {
             cfg.push_str(&cl.no(target));
@@ -2638,8 +3648,15 @@ impl RouteMapItem {
         for (asl, _) in self.match_as_path_list.iter() {
             cfg.push_str(&asl.no(target));
         }
+        if target == Target::Junos {
+            // next-hops are matched directly, without a prefix list.
+            for (cl, _) in self.delete_community.iter() {
+                cfg.push_str(&cl.no(target));
+            }
+            return cfg;
+        }
         for (pl, _) in self.match_next_hop_pl.iter() {
-            cfg.push_str(&pl.no());
+            cfg.push_str(&pl.no(target));
         }
         for (cl, _) in self.delete_community.iter() {
             cfg.push_str(&cl.no(target));
@@ -2661,7 +3678,7 @@ impl RouteMapItem {
     ///         .match_community_list(CommunityList::new("test-cl").community(10, 10))
     ///         .set_weight(100)
     ///         .continues(20)
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// bgp community-list standard test-cl permit 10:10
     /// route-map test permit 10
@@ -2672,15 +3689,46 @@ impl RouteMapItem {
     /// "
     /// );
     /// ```
-    pub fn build(&self, target: Target) -> String {
+    ///
+    /// On Junos, the route-map item is a term of a policy statement, and the weight is translated
+    /// into a route preference:
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{RouteMapItem, CommunityList, PrefixList, Target};
+    /// assert_eq!(
+    ///     RouteMapItem::new("test", 10, true)
+    ///         .match_community_list(CommunityList::new("test-cl").community(10, 10))
+    ///         .set_weight(100)
+    ///         .set_community(10, 20)
+    ///         .continues(20)
+    ///         .build(Target::Junos).unwrap(),
+    ///     "\
+    /// set policy-options community test-cl members 10:10
+    /// set policy-options policy-statement test term 10 from protocol bgp
+    /// set policy-options policy-statement test term 10 from community test-cl
+    /// set policy-options policy-statement test term 10 then preference 65635
+    /// set policy-options community community-10-20 members 10:20
+    /// set policy-options policy-statement test term 10 then community add community-10-20
+    /// set policy-options policy-statement test term 10 then next term
+    /// "
+    /// );
+    /// ```
+    pub fn build(&self, target: Target) -> Result<String, ExportError> {
+        if target == Target::Junos {
+            return self.build_junos();
+        }
         let mut cfg = String::new();
         // build all prefix-lists, community-lists, and as-path access-lists
         for (pl, mode) in self.match_prefix_list.iter() {
-            cfg.push_str(&if *mode { pl.build() } else { pl.no() });
+            cfg.push_str(&if *mode {
+                pl.build(target)
+            } else {
+                pl.no(target)
+            });
         }
         for (cl, mode) in self.match_community_list.iter() {
             cfg.push_str(&if *mode {
-                cl.build(target)
+                cl.build(target)?
             } else {
                 cl.no(target)
             });
@@ -2693,11 +3741,15 @@ impl RouteMapItem {
             });
         }
         for (pl, mode) in self.match_next_hop_pl.iter() {
-            cfg.push_str(&if *mode { pl.build() } else { pl.no() });
+            cfg.push_str(&if *mode {
+                pl.build(target)
+            } else {
+                pl.no(target)
+            });
         }
         for (cl, mode) in self.delete_community.iter() {
             cfg.push_str(&if *mode {
-                cl.build(target)
+                cl.build(target)?
             } else {
                 cl.no(target)
             });
@@ -2731,6 +3783,12 @@ impl RouteMapItem {
             cfg.push_str(if *mode { "  " } else { "  no " });
             cfg.push_str(&format!("match ip next-hop prefix-list {}\n", pl.name));
         }
+        // match_origin: Option<(Origin, bool)>,
+        match self.match_origin {
+            Some((x, true)) => cfg.push_str(&format!("  match origin {}\n", origin_keyword(x))),
+            Some((x, false)) => cfg.push_str(&format!("  no match origin {}\n", origin_keyword(x))),
+            None => {}
+        }
         // set_next_hop: Option<(Ipv4Addr, bool)>,
         match self.set_next_hop {
             Some((x, true)) => cfg.push_str(&format!("  set ip next-hop {x}\n")),
@@ -2755,15 +3813,23 @@ impl RouteMapItem {
             Some((_, false)) => cfg.push_str("  no set metric\n"),
             None => {}
         }
-        // add the word `additive` only to cisco devices.
-        let additive = match target {
-            Target::CiscoNexus7000 => "additive ",
-            Target::Frr => "",
+        // set_origin: Option<(Origin, bool)>,
+        match self.set_origin {
+            Some((x, true)) => cfg.push_str(&format!("  set origin {}\n", origin_keyword(x))),
+            Some((_, false)) => cfg.push_str("  no set origin\n"),
+            None => {}
+        }
+        // add the word `additive` only to cisco devices. Nexus expects it before the community,
+        // and IOS after it.
+        let (additive, additive_ios) = match target {
+            Target::CiscoNexus7000 => ("additive ", ""),
+            Target::CiscoIos => ("", " additive"),
+            Target::Frr | Target::Junos => ("", ""),
         };
         // set_community: Vec<(String, bool)>,
         for (c, mode) in self.set_community.iter() {
             cfg.push_str(if *mode { "  " } else { "  no " });
-            cfg.push_str(&format!("set community {additive}{c}\n"));
+            cfg.push_str(&format!("set community {additive}{c}{additive_ios}\n"));
         }
         // remove_community: Vec<(CommunityList, bool)>,
         for (c, mode) in self.delete_community.iter() {
@@ -2787,7 +3853,155 @@ impl RouteMapItem {
         }
 
         cfg.push_str("exit\n");
-        cfg
+        Ok(cfg)
+    }
+
+    /// Generate the set-style commands for Junos. Each route-map item is a term of the policy
+    /// statement with the same name, which only matches BGP routes. Terms that match on a
+    /// prefix-list also match direct and static routes, such that they apply to the networks
+    /// originated by the router itself (see [`RouterBgp::network`]). `continue` is translated to
+    /// `next term`, which is only exact if the next term is the one to continue at.
+    fn build_junos(&self) -> Result<String, ExportError> {
+        let target = Target::Junos;
+        let p = format!(
+            "policy-options policy-statement {} term {}",
+            self.name, self.order
+        );
+        let set_or_delete = |mode: bool| if mode { "set" } else { "delete" };
+        let mut cfg = String::new();
+
+        // build all route-filter-lists, communities, and as-paths
+        for (pl, mode) in self.match_prefix_list.iter() {
+            cfg.push_str(&if *mode {
+                pl.build(target)
+            } else {
+                pl.no(target)
+            });
+        }
+        for (cl, mode) in self
+            .match_community_list
+            .iter()
+            .chain(self.delete_community.iter())
+        {
+            cfg.push_str(&if *mode {
+                cl.build(target)?
+            } else {
+                cl.no(target)
+            });
+        }
+        for (asl, mode) in self.match_as_path_list.iter() {
+            cfg.push_str(&if *mode {
+                asl.build(target)
+            } else {
+                asl.no(target)
+            });
+        }
+
+        // match conditions
+        let protocol = if self.match_prefix_list.iter().any(|(_, mode)| *mode)
+            || self.match_global_prefix_list.iter().any(|(_, mode)| *mode)
+        {
+            "[ bgp direct static ]"
+        } else {
+            "bgp"
+        };
+        cfg.push_str(&format!("set {p} from protocol {protocol}\n"));
+        for (pl, mode) in self.match_prefix_list.iter() {
+            let cmd = set_or_delete(*mode);
+            cfg.push_str(&format!("{cmd} {p} from route-filter-list {}\n", pl.name));
+        }
+        for (pl, mode) in self.match_global_prefix_list.iter() {
+            let cmd = set_or_delete(*mode);
+            cfg.push_str(&format!("{cmd} {p} from route-filter-list {pl}\n"));
+        }
+        for (cl, mode) in self.match_community_list.iter() {
+            let cmd = set_or_delete(*mode);
+            cfg.push_str(&format!("{cmd} {p} from community {}\n", cl.name));
+        }
+        for (asl, mode) in self.match_as_path_list.iter() {
+            let cmd = set_or_delete(*mode);
+            cfg.push_str(&format!("{cmd} {p} from as-path {}\n", asl.name));
+        }
+        for (pl, mode) in self.match_next_hop_pl.iter() {
+            let cmd = set_or_delete(*mode);
+            for (net, _) in pl.prefixes.iter() {
+                cfg.push_str(&format!("{cmd} {p} from next-hop {}\n", net.addr()));
+            }
+        }
+        if let Some((x, mode)) = self.match_origin {
+            let cmd = set_or_delete(mode);
+            cfg.push_str(&format!("{cmd} {p} from origin {}\n", origin_keyword(x)));
+        }
+
+        // actions
+        match self.set_next_hop {
+            Some((x, true)) => cfg.push_str(&format!("set {p} then next-hop {x}\n")),
+            Some((_, false)) => cfg.push_str(&format!("delete {p} then next-hop\n")),
+            None => {}
+        }
+        match self.set_weight {
+            Some((x, true)) => cfg.push_str(&format!(
+                "set {p} then preference {}\n",
+                junos_preference(x)
+            )),
+            Some((_, false)) => cfg.push_str(&format!("delete {p} then preference\n")),
+            None => {}
+        }
+        match self.set_local_pref {
+            Some((x, true)) => cfg.push_str(&format!("set {p} then local-preference {x}\n")),
+            Some((_, false)) => cfg.push_str(&format!("delete {p} then local-preference\n")),
+            None => {}
+        }
+        match self.set_med {
+            Some((x, true)) => cfg.push_str(&format!("set {p} then metric {x}\n")),
+            Some((_, false)) => cfg.push_str(&format!("delete {p} then metric\n")),
+            None => {}
+        }
+        match self.set_origin {
+            Some((x, true)) => {
+                cfg.push_str(&format!("set {p} then origin {}\n", origin_keyword(x)))
+            }
+            Some((_, false)) => cfg.push_str(&format!("delete {p} then origin\n")),
+            None => {}
+        }
+        for (c, mode) in self.set_community.iter() {
+            let name = junos_community(c);
+            if *mode {
+                cfg.push_str(&format!(
+                    "set policy-options community {name} members {c}\n\
+                     set {p} then community add {name}\n"
+                ));
+            } else {
+                cfg.push_str(&format!("delete {p} then community add {name}\n"));
+            }
+        }
+        for (c, mode) in self.delete_community.iter() {
+            let cmd = set_or_delete(*mode);
+            cfg.push_str(&format!("{cmd} {p} then community delete {}\n", c.name));
+        }
+        match self.prepend_as_path.as_ref() {
+            Some((path, true)) if !path.is_empty() => cfg.push_str(&format!(
+                "set {p} then as-path-prepend \"{}\"\n",
+                path.iter().map(|x| x.0).join(" ")
+            )),
+            Some((_, false)) => cfg.push_str(&format!("delete {p} then as-path-prepend\n")),
+            _ => {}
+        }
+
+        // flow control
+        if self.mode == "deny" {
+            cfg.push_str(&format!("set {p} then reject\n"));
+        } else if let Some((_, true)) = self.cont {
+            cfg.push_str(&format!("set {p} then next term\n"));
+        } else {
+            cfg.push_str(&format!("set {p} then accept\n"));
+        }
+
+        if let Some(next) = self.insert_before {
+            cfg.push_str(&format!("insert {p} before term {next}\n"));
+        }
+
+        Ok(cfg)
     }
 }
 
@@ -2810,26 +4024,35 @@ impl PrefixList {
     /// Remove the prefix list.
     ///
     /// ```
-    /// # use bgpsim::export::cisco_frr_generators::PrefixList;
+    /// # use bgpsim::export::cisco_frr_generators::{PrefixList, Target};
     /// assert_eq!(
-    ///     PrefixList::new("test").no(),
+    ///     PrefixList::new("test").no(Target::Frr),
     ///     "no ip prefix-list test\n"
     /// );
+    /// assert_eq!(
+    ///     PrefixList::new("test").no(Target::Junos),
+    ///     "delete policy-options route-filter-list test\n"
+    /// );
     /// ```
-    pub fn no(&self) -> String {
-        format!("no ip prefix-list {}\n", self.name)
+    pub fn no(&self, target: Target) -> String {
+        match target {
+            Target::CiscoNexus7000 | Target::CiscoIos | Target::Frr => {
+                format!("no ip prefix-list {}\n", self.name)
+            }
+            Target::Junos => format!("delete policy-options route-filter-list {}\n", self.name),
+        }
     }
 
     /// Permit the given network. Calling permit multiple times, the resulting prefix list will
     /// permit one of the given prefixes.
     /// ```
-    /// # use bgpsim::export::cisco_frr_generators::PrefixList;
+    /// # use bgpsim::export::cisco_frr_generators::{PrefixList, Target};
     /// use ipnet::Ipv4Net;
     ///
     /// let n1 = "10.0.0.0/8".parse().unwrap();
     /// let n2 = "20.0.0.0/8".parse().unwrap();
     /// assert_eq!(
-    ///     PrefixList::new("test").prefix(n1).prefix(n2).build(),
+    ///     PrefixList::new("test").prefix(n1).prefix(n2).build(Target::Frr),
     ///     "ip prefix-list test seq 1 permit 10.0.0.0/8\n".to_owned() +
     ///     "ip prefix-list test seq 2 permit 20.0.0.0/8\n"
     /// );
@@ -2846,13 +4069,13 @@ impl PrefixList {
     /// The following prefix-list will match all networks `10.X.0.0/16`:
     ///
     /// ```
-    /// # use bgpsim::export::cisco_frr_generators::PrefixList;
+    /// # use bgpsim::export::cisco_frr_generators::{PrefixList, Target};
     /// use ipnet::Ipv4Net;
     ///
     /// let n1 = "10.0.0.0/8".parse().unwrap();
     /// let n2 = "20.0.0.0/8".parse().unwrap();
     /// assert_eq!(
-    ///     PrefixList::new("test").prefix_eq(n1, 16).prefix_eq(n2, 8).build(),
+    ///     PrefixList::new("test").prefix_eq(n1, 16).prefix_eq(n2, 8).build(Target::Frr),
     ///     "ip prefix-list test seq 1 permit 10.0.0.0/8 eq 16\n".to_string() +
     ///     "ip prefix-list test seq 2 permit 20.0.0.0/8\n"
     /// );
@@ -2872,12 +4095,12 @@ impl PrefixList {
     /// provided argument `len`. Make sure that `len > prefix.prefix_len()`.
     ///
     /// ```
-    /// # use bgpsim::export::cisco_frr_generators::PrefixList;
+    /// # use bgpsim::export::cisco_frr_generators::{PrefixList, Target};
     /// use ipnet::Ipv4Net;
     ///
     /// let n = "10.0.0.0/8".parse().unwrap();
     /// assert_eq!(
-    ///     PrefixList::new("test").prefix_le(n, 10).build(),
+    ///     PrefixList::new("test").prefix_le(n, 10).build(Target::Frr),
     ///     "ip prefix-list test seq 1 permit 10.0.0.0/8 le 10\n"
     /// );
     /// ```
@@ -2891,12 +4114,12 @@ impl PrefixList {
     /// provided argument `len`. Make sure that `len > prefix.prefix_len()`.
     ///
     /// ```
-    /// # use bgpsim::export::cisco_frr_generators::PrefixList;
+    /// # use bgpsim::export::cisco_frr_generators::{PrefixList, Target};
     /// use ipnet::Ipv4Net;
     ///
     /// let n = "10.0.0.0/8".parse().unwrap();
     /// assert_eq!(
-    ///     PrefixList::new("test").prefix_ge(n, 16).build(),
+    ///     PrefixList::new("test").prefix_ge(n, 16).build(Target::Frr),
     ///     "ip prefix-list test seq 1 permit 10.0.0.0/8 ge 16\n"
     /// );
     /// ```
@@ -2906,8 +4129,41 @@ impl PrefixList {
         self
     }
 
-    /// Build the prefix list.
-    pub fn build(&self) -> String {
+    /// Build the prefix list. On Junos, the prefix list is a route-filter-list.
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{PrefixList, Target};
+    /// use ipnet::Ipv4Net;
+    ///
+    /// let n = "10.0.0.0/8".parse().unwrap();
+    /// assert_eq!(
+    ///     PrefixList::new("test").prefix(n).prefix_eq(n, 16).prefix_le(n, 10).build(Target::Junos),
+    ///     "\
+    /// set policy-options route-filter-list test 10.0.0.0/8 exact
+    /// set policy-options route-filter-list test 10.0.0.0/8 prefix-length-range /16-/16
+    /// set policy-options route-filter-list test 10.0.0.0/8 upto /10
+    /// "
+    /// );
+    /// ```
+    pub fn build(&self, target: Target) -> String {
+        if target == Target::Junos {
+            return self
+                .prefixes
+                .iter()
+                .map(|(net, opt)| {
+                    let range = match opt {
+                        None => String::from("exact"),
+                        Some(("le", len)) => format!("upto /{len}"),
+                        Some(("ge", len)) => format!("prefix-length-range /{len}-/32"),
+                        Some((_, len)) => format!("prefix-length-range /{len}-/{len}"),
+                    };
+                    format!(
+                        "set policy-options route-filter-list {} {net} {range}\n",
+                        self.name
+                    )
+                })
+                .join("");
+        }
         self.prefixes
             .iter()
             .enumerate()
@@ -2958,8 +4214,9 @@ impl CommunityList {
     /// ```
     pub fn no(&self, target: Target) -> String {
         let root = match target {
-            Target::CiscoNexus7000 => "ip",
+            Target::CiscoNexus7000 | Target::CiscoIos => "ip",
             Target::Frr => "bgp",
+            Target::Junos => return format!("delete policy-options community {}\n", self.name),
         };
         format!("no {} community-list standard {}\n", root, self.name)
     }
@@ -2969,7 +4226,11 @@ impl CommunityList {
     /// ```
     /// # use bgpsim::export::cisco_frr_generators::{CommunityList, Target};
     /// assert_eq!(
-    ///     CommunityList::new("test").community(10, 10).community(10, 20).build(Target::Frr),
+    ///     CommunityList::new("test")
+    ///         .community(10, 10)
+    ///         .community(10, 20)
+    ///         .build(Target::Frr)
+    ///         .unwrap(),
     ///     "bgp community-list standard test permit 10:10 10:20\n"
     /// );
     /// ```
@@ -2987,7 +4248,7 @@ impl CommunityList {
     ///     CommunityList::new("test")
     ///         .community(10, 10).community(10, 20)
     ///         .deny(10, 30).deny(10, 40)
-    ///         .build(Target::Frr),
+    ///         .build(Target::Frr).unwrap(),
     ///     "\
     /// bgp community-list standard test deny 10:30
     /// bgp community-list standard test deny 10:40
@@ -3001,11 +4262,41 @@ impl CommunityList {
         self
     }
 
-    /// Build the community list.
-    pub fn build(&self, target: Target) -> String {
+    /// Build the community list. On Junos, the community list can either require communities to
+    /// be present, or require communities to be absent, but not both at the same time. In that
+    /// case, this function returns [`ExportError::UnsupportedCommunityList`].
+    ///
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{CommunityList, Target};
+    /// assert_eq!(
+    ///     CommunityList::new("test")
+    ///         .community(10, 10)
+    ///         .community(10, 20)
+    ///         .build(Target::Junos)
+    ///         .unwrap(),
+    ///     "\
+    /// set policy-options community test members 10:10
+    /// set policy-options community test members 10:20
+    /// "
+    /// );
+    /// assert_eq!(
+    ///     CommunityList::new("test").deny(10, 30).deny(10, 40).build(Target::Junos).unwrap(),
+    ///     "\
+    /// set policy-options community test invert-match
+    /// set policy-options community test members \"^(10:30|10:40)$\"
+    /// "
+    /// );
+    /// assert!(CommunityList::new("test")
+    ///     .community(10, 10)
+    ///     .deny(10, 30)
+    ///     .build(Target::Junos)
+    ///     .is_err());
+    /// ```
+    pub fn build(&self, target: Target) -> Result<String, ExportError> {
         let root = match target {
-            Target::CiscoNexus7000 => "ip",
+            Target::CiscoNexus7000 | Target::CiscoIos => "ip",
             Target::Frr => "bgp",
+            Target::Junos => return self.build_junos(),
         };
         let permit = format!(
             "{} community-list standard {} permit {}\n",
@@ -3018,7 +4309,27 @@ impl CommunityList {
             .iter()
             .map(|c| format!("{root} community-list standard {} deny {c}\n", self.name))
             .join("");
-        format!("{deny}{permit}")
+        Ok(format!("{deny}{permit}"))
+    }
+
+    /// Generate the set-style commands for Junos.
+    fn build_junos(&self) -> Result<String, ExportError> {
+        let c = format!("policy-options community {}", self.name);
+        match (
+            self.communities.is_empty(),
+            self.deny_communities.is_empty(),
+        ) {
+            (_, true) => Ok(self
+                .communities
+                .iter()
+                .map(|x| format!("set {c} members {x}\n"))
+                .join("")),
+            (true, false) => Ok(format!(
+                "set {c} invert-match\nset {c} members \"^({})$\"\n",
+                self.deny_communities.iter().join("|")
+            )),
+            (false, false) => Err(ExportError::UnsupportedCommunityList(self.name.clone())),
+        }
     }
 }
 
@@ -3033,6 +4344,7 @@ impl From<&mut CommunityList> for CommunityList {
 pub struct AsPathList {
     name: String,
     regex: String,
+    junos_regex: String,
 }
 
 impl AsPathList {
@@ -3041,6 +4353,7 @@ impl AsPathList {
         Self {
             name: name.into(),
             regex: String::new(),
+            junos_regex: String::new(),
         }
     }
 
@@ -3055,8 +4368,9 @@ impl AsPathList {
     /// ```
     pub fn no(&self, target: Target) -> String {
         let root = match target {
-            Target::CiscoNexus7000 => "ip",
+            Target::CiscoNexus7000 | Target::CiscoIos => "ip",
             Target::Frr => "bgp",
+            Target::Junos => return format!("delete policy-options as-path {}\n", self.name),
         };
         format!("no {} as-path access-list {}\n", root, self.name)
     }
@@ -3068,17 +4382,49 @@ impl AsPathList {
     ///     AsPathList::new("test").contains_as(10).build(Target::Frr),
     ///     "bgp as-path access-list test permit _10_\n"
     /// );
+    /// assert_eq!(
+    ///     AsPathList::new("test").contains_as(10).build(Target::Junos),
+    ///     "set policy-options as-path test \".* 10 .*\"\n"
+    /// );
     /// ```
     pub fn contains_as(&mut self, as_id: impl Into<AsId>) -> &mut Self {
-        self.regex = format!("_{}_", as_id.into().0);
+        let as_id = as_id.into().0;
+        self.regex = format!("_{as_id}_");
+        self.junos_regex = format!(".* {as_id} .*");
+        self
+    }
+
+    /// Use an arbitrary regular expression (in the syntax of Cisco and FRR, see
+    /// [`crate::route_map::AsPathRegex`]). For Junos, the expression is translated with
+    /// [`junos_as_path_regex`]. If this translation fails, the expression is used unchanged.
+    /// ```
+    /// # use bgpsim::export::cisco_frr_generators::{AsPathList, Target};
+    /// assert_eq!(
+    ///     AsPathList::new("test").regex("^10_20_").build(Target::Frr),
+    ///     "bgp as-path access-list test permit ^10_20_\n"
+    /// );
+    /// assert_eq!(
+    ///     AsPathList::new("test").regex("^10_20_").build(Target::Junos),
+    ///     "set policy-options as-path test \"10 20 .*\"\n"
+    /// );
+    /// ```
+    pub fn regex(&mut self, regex: impl Into<String>) -> &mut Self {
+        self.regex = regex.into();
+        self.junos_regex = junos_as_path_regex(&self.regex).unwrap_or_else(|| self.regex.clone());
         self
     }
 
     /// Build the as-path access-list.
     pub fn build(&self, target: Target) -> String {
         let root = match target {
-            Target::CiscoNexus7000 => "ip",
+            Target::CiscoNexus7000 | Target::CiscoIos => "ip",
             Target::Frr => "bgp",
+            Target::Junos => {
+                return format!(
+                    "set policy-options as-path {} \"{}\"\n",
+                    self.name, self.junos_regex
+                )
+            }
         };
         format!(
             "{} as-path access-list {} permit {}\n",
@@ -3093,26 +4439,113 @@ impl From<&mut AsPathList> for AsPathList {
     }
 }
 
-/// Enable the BGP feature using commands. This does nothing on FRR.
+/// Enable the BGP feature using commands. This does nothing on Cisco IOS, FRR, and Junos.
 pub fn enable_bgp(target: Target) -> &'static str {
     match target {
         Target::CiscoNexus7000 => "feature bgp\n",
-        Target::Frr => "",
+        Target::CiscoIos | Target::Frr | Target::Junos => "",
     }
 }
 
-/// Enable the OSPF feature using commands. This does nothing on FRR.
+/// Enable the OSPF feature using commands. This does nothing on Cisco IOS, FRR, and Junos.
 pub fn enable_ospf(target: Target) -> &'static str {
     match target {
         Target::CiscoNexus7000 => "feature ospf\n",
-        Target::Frr => "",
+        Target::CiscoIos | Target::Frr | Target::Junos => "",
     }
 }
 
 /// Get the interface name for the given loopback.
 pub fn loopback_iface(target: Target, idx: u8) -> String {
     match target {
-        Target::CiscoNexus7000 => format!("Loopback{idx}"),
+        Target::CiscoNexus7000 | Target::CiscoIos => format!("Loopback{idx}"),
         Target::Frr => String::from("lo"),
+        Target::Junos => String::from("lo0"),
+    }
+}
+
+/// Generate a comment line. If the text is empty, only the comment marker is written.
+///
+/// ```
+/// # use bgpsim::export::cisco_frr_generators::{comment, Target};
+/// assert_eq!(comment(Target::Frr, "BGP"), "! BGP\n");
+/// assert_eq!(comment(Target::Junos, ""), "#\n");
+/// ```
+pub fn comment(target: Target, text: &str) -> String {
+    let marker = match target {
+        Target::CiscoNexus7000 | Target::CiscoIos | Target::Frr => "!",
+        Target::Junos => "#",
+    };
+    if text.is_empty() {
+        format!("{marker}\n")
+    } else {
+        format!("{marker} {text}\n")
+    }
+}
+
+/// Format a network or an interface address. Cisco IOS expects the address followed by its network
+/// mask, while all other targets use the prefix length.
+fn fmt_net(target: Target, net: Ipv4Net) -> String {
+    match target {
+        Target::CiscoIos => format!("{} {}", net.addr(), net.netmask()),
+        Target::CiscoNexus7000 | Target::Frr | Target::Junos => net.to_string(),
     }
 }
+
+/// Name of the logical unit 0 of an interface on Junos.
+fn junos_unit(iface: &str) -> String {
+    format!("{iface}.0")
+}
+
+/// Format an OSPF area in the dotted notation used by Junos.
+fn junos_area(area: OspfArea) -> Ipv4Addr {
+    Ipv4Addr::from(area.num())
+}
+
+/// Translate a BGP weight into a Junos route preference.
+fn junos_preference(weight: u16) -> u32 {
+    JUNOS_MAX_PREFERENCE - weight as u32
+}
+
+/// Name of the Junos community that contains exactly the community `c` (formatted as `AS:C`).
+fn junos_community(c: &str) -> String {
+    format!("community-{}", c.replace(':', "-"))
+}
+
+/// Keyword of the ORIGIN attribute used in route-maps (`igp`, `egp`, or `incomplete`).
+pub(crate) fn origin_keyword(origin: Origin) -> &'static str {
+    match origin {
+        Origin::Igp => "igp",
+        Origin::Egp => "egp",
+        Origin::Incomplete => "incomplete",
+    }
+}
+
+/// Translate an as-path regular expression from the Cisco and FRR syntax into the Junos syntax,
+/// where the expression matches entire AS numbers instead of characters. Only expressions that
+/// consist of AS numbers separated by `_`, optionally anchored with `^` and `$`, can be translated.
+/// If the expression cannot be translated, this function returns `None`.
+///
+/// ```
+/// # use bgpsim::export::cisco_frr_generators::junos_as_path_regex;
+/// assert_eq!(junos_as_path_regex("_10_"), Some(String::from(".* 10 .*")));
+/// assert_eq!(junos_as_path_regex("^10_20$"), Some(String::from("10 20")));
+/// assert_eq!(junos_as_path_regex("^(10|20)_"), None);
+/// ```
+pub fn junos_as_path_regex(regex: &str) -> Option<String> {
+    let (start, rest) = match (regex.strip_prefix('^'), regex.strip_prefix('_')) {
+        (Some(rest), _) => ("", rest),
+        (None, Some(rest)) => (".* ", rest),
+        (None, None) => return None,
+    };
+    let (rest, end) = match (rest.strip_suffix('$'), rest.strip_suffix('_')) {
+        (Some(rest), _) => (rest, ""),
+        (None, Some(rest)) => (rest, " .*"),
+        (None, None) => return None,
+    };
+    let path = rest
+        .split('_')
+        .map(|x| x.parse::<u32>().ok())
+        .collect::<Option<Vec<u32>>>()?;
+    Some(format!("{start}{}{end}", path.iter().join(" ")))
+}
@@ -0,0 +1,1746 @@
+// BgpSim: BGP Network Simulator written in Rust
+// Copyright (C) 2022-2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Module for importing running-configurations of Cisco and FRR routers into a [`Network`]. Use
+//! the [`CiscoFrrParser`] to read the configuration of every device, and then call
+//! [`CiscoFrrParser::get_network`] to reconstruct the network, together with the [`AddressPlan`]
+//! that reproduces the IP addresses found in the configuration.
+//!
+//! The parser understands the subset of the configuration language that is generated by the
+//! [`super::CiscoFrrCfgGen`], in the Cisco Nexus, the Cisco IOS, and the FRR dialect:
+//!
+//! - Interfaces with their address, OSPF cost and area, and `shutdown`,
+//! - `router ospf` (router-id, `maximum-paths`, and `network ... area ...`),
+//! - `router bgp` with its neighbors, route-maps, route-reflector clients, networks, aggregates,
+//!   `redistribute static`, and `always-compare-med`,
+//! - route-maps, prefix-lists, community-lists, and as-path access-lists,
+//! - static routes.
+//!
+//! Every device that runs `router ospf` is treated as an internal router, and every other device
+//! as an external router. Links are created between all pairs of interfaces that share the same
+//! subnet, and eBGP neighbors without a configuration are added as new external routers. Every
+//! statement that cannot be represented in the network is collected as an
+//! [`UnsupportedStatement`], instead of being silently dropped.
+//!
+//! Communities are only imported if they belong to the internal AS ([`super::INTERNAL_AS`]), and
+//! interfaces without an explicit `ip ospf cost` have a link weight of 1.
+
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
+    fmt,
+    net::Ipv4Addr,
+    str::FromStr,
+};
+
+use ipnet::Ipv4Net;
+use itertools::Itertools;
+use thiserror::Error;
+
+use super::{DefaultAddressor, DefaultAddressorBuilder, ExportError, LinkId, INTERNAL_AS};
+use crate::{
+    bgp::{BgpAggregate, BgpSessionType, Mrai, Origin},
+    event::EventQueue,
+    network::Network,
+    ospf::OspfArea,
+    route_map::{AsPathRegex, RouteMap, RouteMapBuilder, RouteMapDirection},
+    router::StaticRoute,
+    types::{AsId, Ipv4Prefix, LinkWeight, NetworkError, Prefix, RouterId},
+};
+
+/// Parser for the running-configurations of Cisco and FRR routers.
+///
+/// ```rust
+/// # use bgpsim::prelude::*;
+/// # use bgpsim::types::Ipv4Prefix;
+/// use bgpsim::export::cisco_frr_parser::CiscoFrrParser;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let r1 = "router ospf\n  router-id 10.0.0.1\nexit\ninterface eth1\n  ip address 10.128.0.1/30\n  ip ospf cost 10\n  ip ospf area 0\nexit\n";
+/// let r2 = "router ospf\n  router-id 10.0.1.1\nexit\ninterface eth1\n  ip address 10.128.0.2/30\n  ip ospf cost 20\n  ip ospf area 0\nexit\n";
+///
+/// let parser = CiscoFrrParser::new([("r1", r1), ("r2", r2)]);
+/// let imported = parser.get_network(BasicEventQueue::<Ipv4Prefix>::new())?;
+/// let net = imported.net;
+///
+/// assert_eq!(net.get_link_weigth(0.into(), 1.into())?, 10.0);
+/// assert_eq!(net.get_link_weigth(1.into(), 0.into())?, 20.0);
+/// assert!(imported.unsupported.is_empty());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CiscoFrrParser {
+    devices: Vec<DeviceCfg>,
+    unsupported: Vec<UnsupportedStatement>,
+}
+
+impl CiscoFrrParser {
+    /// Parse the configuration of all devices, given as pairs of the device name and its
+    /// running-configuration. A `hostname` statement in the configuration overrides the given
+    /// name. Routers are created in the order in which they are given.
+    pub fn new<'a, S: Into<String>>(configs: impl IntoIterator<Item = (S, &'a str)>) -> Self {
+        let mut unsupported = Vec::new();
+        let devices = configs
+            .into_iter()
+            .map(|(name, config)| DeviceCfg::parse(name.into(), config, &mut unsupported))
+            .collect();
+        Self {
+            devices,
+            unsupported,
+        }
+    }
+
+    /// Get all statements that could not be parsed. This list does not yet contain statements
+    /// that can only be rejected while building the network. Use
+    /// [`ImportedNetwork::unsupported`] for the complete list.
+    pub fn unsupported(&self) -> &[UnsupportedStatement] {
+        &self.unsupported
+    }
+
+    /// Reconstruct the network from the parsed configurations. This will create all routers
+    /// (internal and external), links, link weights and OSPF areas, static routes, BGP sessions,
+    /// route-maps, and the routes advertised by external routers.
+    pub fn get_network<Q: EventQueue<Ipv4Prefix>>(
+        &self,
+        queue: Q,
+    ) -> Result<ImportedNetwork<Q>, CiscoFrrParseError> {
+        Importer::new(self, queue)?.import()
+    }
+}
+
+/// The result of importing a set of configurations using the [`CiscoFrrParser`].
+#[derive(Debug)]
+pub struct ImportedNetwork<Q> {
+    /// The reconstructed network.
+    pub net: Network<Ipv4Prefix, Q>,
+    /// The addresses and interfaces that were found in the configurations.
+    pub addresses: AddressPlan,
+    /// All statements that could not be imported.
+    pub unsupported: Vec<UnsupportedStatement>,
+}
+
+/// The IP addresses and interface names used in the imported configurations. Use
+/// [`AddressPlan::addressor`] to get a [`DefaultAddressor`] that reproduces these addresses when
+/// generating configurations for the imported network, and [`AddressPlan::iface_names`] to get the
+/// interface names for the config generator.
+#[derive(Debug, Clone)]
+pub struct AddressPlan {
+    args: DefaultAddressorBuilder,
+    routers: HashMap<RouterId, (Ipv4Net, Ipv4Addr)>,
+    links: HashMap<LinkId, Ipv4Net>,
+    interfaces: HashMap<RouterId, HashMap<RouterId, (usize, Ipv4Addr)>>,
+    iface_names: HashMap<RouterId, Vec<String>>,
+}
+
+impl AddressPlan {
+    /// Get the address ranges that were inferred from the configurations. These are used to
+    /// assign addresses to new routers and links.
+    pub fn args(&self) -> &DefaultAddressorBuilder {
+        &self.args
+    }
+
+    /// Get the names of all interfaces of a router that connect to a neighbor, ordered by their
+    /// interface index. Routers that were added without a configuration have no interface names.
+    pub fn iface_names(&self, router: RouterId) -> Vec<String> {
+        self.iface_names.get(&router).cloned().unwrap_or_default()
+    }
+
+    /// Create a [`DefaultAddressor`] that uses the addresses found in the configurations.
+    pub fn addressor<'a, P: Prefix, Q>(
+        &self,
+        net: &'a Network<P, Q>,
+    ) -> Result<DefaultAddressor<'a, P, Q>, ExportError> {
+        DefaultAddressor::with_assignments(
+            net,
+            &self.args,
+            self.routers.clone(),
+            self.links.clone(),
+            self.interfaces.clone(),
+        )
+    }
+}
+
+/// A statement that was not imported into the network.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedStatement {
+    /// Name of the device whose configuration contains the statement.
+    pub router: String,
+    /// Line number of the statement (starting at 1).
+    pub line: usize,
+    /// The statement itself, without indentation.
+    pub statement: String,
+    /// Why the statement was not imported.
+    pub reason: String,
+}
+
+impl fmt::Display for UnsupportedStatement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: `{}` ({})",
+            self.router, self.line, self.statement, self.reason
+        )
+    }
+}
+
+/// Error while reconstructing the network from the configurations.
+#[derive(Debug, Error)]
+pub enum CiscoFrrParseError {
+    /// Network error occurred while generating the network
+    #[error("Network occurred while generating it: {0}")]
+    NetworkError(#[from] NetworkError),
+    /// Two devices have the same name.
+    #[error("Multiple devices are called {0}!")]
+    DuplicateRouter(String),
+    /// An external router does not run BGP.
+    #[error("Cannot determine the AS number of the external router {0}!")]
+    MissingAsId(String),
+    /// The router-id of a device could not be found.
+    #[error("Cannot determine the router-id of {0}!")]
+    MissingRouterId(String),
+    /// Invalid network or prefix length.
+    #[error("Invalid network: {0}")]
+    InvalidNetmask(#[from] ipnet::PrefixLenError),
+}
+
+/// Location of a statement in the configuration.
+#[derive(Debug, Clone)]
+struct Src {
+    line: usize,
+    text: String,
+}
+
+/// Everything that was parsed from the configuration of a single device.
+#[derive(Debug, Clone, Default)]
+struct DeviceCfg {
+    name: String,
+    ifaces: Vec<IfaceCfg>,
+    ospf: Option<OspfCfg>,
+    bgp: Option<BgpCfg>,
+    route_maps: HashMap<String, BTreeMap<u16, RouteMapCfg>>,
+    prefix_lists: HashMap<String, Vec<Ipv4Net>>,
+    community_lists: HashMap<String, CommunityListCfg>,
+    as_path_lists: HashMap<String, Vec<(bool, String)>>,
+    static_routes: Vec<StaticRouteCfg>,
+    current_rm: Option<(String, u16)>,
+}
+
+#[derive(Debug, Clone)]
+struct IfaceCfg {
+    name: String,
+    addrs: Vec<Ipv4Net>,
+    cost: Option<LinkWeight>,
+    area: Option<OspfArea>,
+    shutdown: bool,
+}
+
+impl IfaceCfg {
+    /// Check if the interface is a loopback interface.
+    fn is_loopback(&self) -> bool {
+        self.name.to_lowercase().starts_with("lo")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct OspfCfg {
+    router_id: Option<Ipv4Addr>,
+    maximum_paths: usize,
+    networks: Vec<(Ipv4Net, OspfArea)>,
+}
+
+#[derive(Debug, Clone)]
+struct BgpCfg {
+    as_id: AsId,
+    router_id: Option<Ipv4Addr>,
+    networks: Vec<(Src, Ipv4Net)>,
+    aggregates: Vec<(Src, Ipv4Net, BgpAggregate)>,
+    redistribute_static: Option<(Src, Option<String>)>,
+    neighbors: Vec<NeighborCfg>,
+    always_compare_med: Option<Src>,
+    compare_router_id: Option<Src>,
+}
+
+#[derive(Debug, Clone)]
+struct NeighborCfg {
+    src: Src,
+    addr: Ipv4Addr,
+    remote_as: Option<AsId>,
+    rr_client: bool,
+    route_map_in: Option<(Src, String)>,
+    route_map_out: Option<(Src, String)>,
+    advertisement_interval: Option<(Src, u32)>,
+}
+
+#[derive(Debug, Clone)]
+struct RouteMapCfg {
+    src: Src,
+    allow: bool,
+    matches: Vec<(Src, RouteMapMatchCfg)>,
+    sets: Vec<(Src, RouteMapSetCfg)>,
+    continues: Option<Option<u16>>,
+}
+
+impl RouteMapCfg {
+    /// Check if the route-map item permits everything without changing anything.
+    fn is_permit_all(&self) -> bool {
+        self.allow && self.matches.is_empty() && self.sets.is_empty() && self.continues.is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
+enum RouteMapMatchCfg {
+    PrefixList(String),
+    NextHop(String),
+    Community(String),
+    AsPath(String),
+    Origin(Origin),
+}
+
+#[derive(Debug, Clone)]
+enum RouteMapSetCfg {
+    Weight(u32),
+    LocalPref(u32),
+    Med(u32),
+    NextHop(Ipv4Addr),
+    Community(Vec<u32>),
+    DelCommunity(String),
+    Prepend(Vec<AsId>),
+    Origin(Origin),
+}
+
+#[derive(Debug, Clone, Default)]
+struct CommunityListCfg {
+    permit: Option<Vec<u32>>,
+    deny: Vec<u32>,
+}
+
+#[derive(Debug, Clone)]
+struct StaticRouteCfg {
+    src: Src,
+    net: Ipv4Net,
+    target: StaticTarget,
+}
+
+#[derive(Debug, Clone)]
+enum StaticTarget {
+    Drop,
+    Address(Ipv4Addr),
+    Iface(String),
+}
+
+/// A (possibly nested) block of the configuration, used while parsing.
+struct Block {
+    indent: usize,
+    words: Vec<String>,
+    ok: bool,
+}
+
+impl DeviceCfg {
+    /// Parse the configuration of a single device.
+    fn parse(name: String, config: &str, unsupported: &mut Vec<UnsupportedStatement>) -> Self {
+        let mut dev = Self {
+            name,
+            ..Default::default()
+        };
+        let mut errors = Vec::new();
+        let mut blocks: Vec<Block> = Vec::new();
+
+        for (i, raw) in config.lines().enumerate() {
+            let text = raw.trim();
+            if text.is_empty() || text.starts_with('!') || text.starts_with('#') {
+                continue;
+            }
+            let indent = raw.len() - raw.trim_start().len();
+            while blocks.last().map(|b| b.indent >= indent).unwrap_or(false) {
+                blocks.pop();
+            }
+            let words = text.split_whitespace().collect_vec();
+            // blocks are closed by their indentation.
+            if matches!(words[0], "exit" | "exit-address-family" | "end") {
+                continue;
+            }
+            // skip everything inside of a block that is not supported.
+            if blocks.iter().any(|b| !b.ok) {
+                continue;
+            }
+            let src = Src {
+                line: i + 1,
+                text: text.to_string(),
+            };
+            let result = dev.parse_stmt(&blocks, &words, &src);
+            blocks.push(Block {
+                indent,
+                words: words.iter().map(|w| w.to_string()).collect(),
+                ok: result.is_ok(),
+            });
+            if let Err(reason) = result {
+                errors.push((src, reason));
+            }
+        }
+
+        unsupported.extend(
+            errors
+                .into_iter()
+                .map(|(src, reason)| unsupported_stmt(&dev.name, &src, reason)),
+        );
+        dev
+    }
+
+    /// Parse a single statement, given the blocks in which it is nested.
+    fn parse_stmt(&mut self, blocks: &[Block], words: &[&str], src: &Src) -> Result<(), String> {
+        let root = match blocks.first() {
+            Some(b) => b.words.iter().map(String::as_str).collect_vec(),
+            None => return self.parse_global(words, src),
+        };
+        match root.as_slice() {
+            ["interface", ..] => self.parse_iface(words),
+            ["router", "ospf", ..] => self.parse_ospf(words),
+            ["router", "bgp", ..] => self.parse_bgp(&blocks[1..], words, src),
+            ["route-map", ..] => self.parse_route_map(words, src),
+            _ => Err(String::from("unknown block")),
+        }
+    }
+
+    /// Parse a top-level statement.
+    fn parse_global(&mut self, words: &[&str], src: &Src) -> Result<(), String> {
+        match words {
+            ["hostname", name] => self.name = name.to_string(),
+            ["feature", "bgp" | "ospf"] | ["frr", "version" | "defaults", ..] => {}
+            ["interface", name] => self.ifaces.push(IfaceCfg {
+                name: name.to_string(),
+                addrs: Vec::new(),
+                cost: None,
+                area: None,
+                shutdown: false,
+            }),
+            ["router", "ospf"] | ["router", "ospf", _] => {
+                self.ospf.get_or_insert_with(|| OspfCfg {
+                    maximum_paths: 1,
+                    ..Default::default()
+                });
+            }
+            ["router", "bgp", as_id] => {
+                let as_id = AsId(num(as_id)?);
+                match self.bgp.as_ref() {
+                    Some(bgp) if bgp.as_id != as_id => {
+                        return Err(String::from("multiple BGP instances are not supported"))
+                    }
+                    Some(_) => {}
+                    None => {
+                        self.bgp = Some(BgpCfg {
+                            as_id,
+                            router_id: None,
+                            networks: Vec::new(),
+                            aggregates: Vec::new(),
+                            redistribute_static: None,
+                            neighbors: Vec::new(),
+                            always_compare_med: None,
+                            compare_router_id: None,
+                        })
+                    }
+                }
+            }
+            ["route-map", name, state @ ("permit" | "deny"), order] => {
+                let order: u16 = num(order)?;
+                let allow = *state == "permit";
+                let entry = self
+                    .route_maps
+                    .entry(name.to_string())
+                    .or_default()
+                    .entry(order)
+                    .or_insert_with(|| RouteMapCfg {
+                        src: src.clone(),
+                        allow,
+                        matches: Vec::new(),
+                        sets: Vec::new(),
+                        continues: None,
+                    });
+                entry.allow = allow;
+                self.current_rm = Some((name.to_string(), order));
+            }
+            ["ip", "prefix-list", name, rest @ ..] => {
+                let rest = match rest {
+                    ["seq", _, rest @ ..] => rest,
+                    rest => rest,
+                };
+                let list = self.prefix_lists.entry(name.to_string()).or_default();
+                match rest {
+                    ["permit", net] => list.push(parse_net(net, None)?),
+                    ["permit", ..] => {
+                        return Err(String::from(
+                            "prefix ranges (`ge` or `le`) are not supported",
+                        ))
+                    }
+                    ["deny", ..] => {
+                        return Err(String::from(
+                            "deny entries in prefix-lists are not supported",
+                        ))
+                    }
+                    _ => return Err(String::from("unknown prefix-list entry")),
+                }
+            }
+            ["ip" | "bgp", "community-list", rest @ ..] => {
+                let (name, state, communities) = match rest {
+                    ["standard", name, state, communities @ ..]
+                    | [name, state @ ("permit" | "deny"), communities @ ..] => {
+                        (*name, *state, communities)
+                    }
+                    _ => return Err(String::from("only standard community-lists are supported")),
+                };
+                let communities = communities
+                    .iter()
+                    .map(|c| parse_community(c))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let list = self.community_lists.entry(name.to_string()).or_default();
+                match (state, communities.as_slice()) {
+                    (_, []) => return Err(String::from("missing community")),
+                    ("permit", _) if list.permit.is_some() => {
+                        return Err(String::from(
+                            "community-lists with multiple permit entries are not supported",
+                        ))
+                    }
+                    ("permit", _) => list.permit = Some(communities),
+                    ("deny", [c]) => list.deny.push(*c),
+                    ("deny", _) => {
+                        return Err(String::from(
+                            "deny entries with multiple communities are not supported",
+                        ))
+                    }
+                    _ => return Err(String::from("unknown community-list entry")),
+                }
+            }
+            ["ip" | "bgp", "as-path", "access-list", name, state @ ("permit" | "deny"), regex] => {
+                self.as_path_lists
+                    .entry(name.to_string())
+                    .or_default()
+                    .push((*state == "permit", regex.to_string()))
+            }
+            ["ip", "route", rest @ ..] => {
+                let (net, rest) = match rest {
+                    [net, rest @ ..] if net.contains('/') => (parse_net(net, None)?, rest),
+                    [addr, mask, rest @ ..] => (parse_net(addr, Some(mask))?, rest),
+                    _ => return Err(String::from("missing destination")),
+                };
+                let target = match rest {
+                    ["null", "0"] | ["Null0" | "null0" | "blackhole"] => StaticTarget::Drop,
+                    [target] => match target.parse::<Ipv4Addr>() {
+                        Ok(addr) => StaticTarget::Address(addr),
+                        Err(_) => StaticTarget::Iface(target.to_string()),
+                    },
+                    _ => {
+                        return Err(String::from(
+                            "static routes with an administrative distance or options are not supported",
+                        ))
+                    }
+                };
+                self.static_routes.push(StaticRouteCfg {
+                    src: src.clone(),
+                    net: net.trunc(),
+                    target,
+                })
+            }
+            _ => return Err(String::from("not supported")),
+        }
+        Ok(())
+    }
+
+    /// Parse a statement within an interface block.
+    fn parse_iface(&mut self, words: &[&str]) -> Result<(), String> {
+        let iface = self.ifaces.last_mut().unwrap();
+        match words {
+            ["ip", "address", net] | ["ip", "address", net, "secondary"] => {
+                iface.addrs.push(parse_net(net, None)?)
+            }
+            ["ip", "address", addr, mask] | ["ip", "address", addr, mask, "secondary"] => {
+                iface.addrs.push(parse_net(addr, Some(mask))?)
+            }
+            ["ip", "ospf", "cost", cost] => iface.cost = Some(num(cost)?),
+            ["no", "ip", "ospf", "cost"] => iface.cost = None,
+            ["ip", "ospf", "area", area]
+            | ["ip", "ospf", _, "area", area]
+            | ["ip", "router", "ospf", _, "area", area] => iface.area = Some(parse_area(area)?),
+            ["shutdown"] => iface.shutdown = true,
+            ["no", "shutdown"] => iface.shutdown = false,
+            // timers and descriptions do not change the routing decisions.
+            ["ip", "ospf", "dead-interval" | "hello-interval", _]
+            | ["mac-address", _]
+            | ["description", ..] => {}
+            _ => return Err(String::from("not supported")),
+        }
+        Ok(())
+    }
+
+    /// Parse a statement within the `router ospf` block.
+    fn parse_ospf(&mut self, words: &[&str]) -> Result<(), String> {
+        let ospf = self.ospf.as_mut().unwrap();
+        match words {
+            ["router-id", id] | ["ospf", "router-id", id] => ospf.router_id = Some(addr(id)?),
+            ["maximum-paths", k] => ospf.maximum_paths = num(k)?,
+            ["network", net, "area", area] => ospf
+                .networks
+                .push((parse_net(net, None)?, parse_area(area)?)),
+            ["network", net, wildcard, "area", area] => {
+                let mask = Ipv4Addr::from(!u32::from(addr(wildcard)?)).to_string();
+                ospf.networks
+                    .push((parse_net(net, Some(&mask))?, parse_area(area)?))
+            }
+            ["log-adjacency-changes", ..] => {}
+            _ => return Err(String::from("not supported")),
+        }
+        Ok(())
+    }
+
+    /// Parse a statement within the `router bgp` block. On Cisco, neighbors are configured in
+    /// nested blocks, while FRR repeats the neighbor address on each line.
+    fn parse_bgp(&mut self, blocks: &[Block], words: &[&str], src: &Src) -> Result<(), String> {
+        let bgp = self.bgp.as_mut().unwrap();
+        let nested_neighbor = blocks.iter().find_map(|b| match b.words.as_slice() {
+            [n, addr, ..] if n == "neighbor" => Some(addr.clone()),
+            _ => None,
+        });
+        let (neighbor, words) = match (nested_neighbor, words) {
+            (Some(n), words) => (Some(addr(&n)?), words),
+            (None, ["neighbor", n, words @ ..]) => (Some(addr(n)?), words),
+            (None, words) => (None, words),
+        };
+
+        if let Some(neighbor) = neighbor {
+            let pos = match bgp.neighbors.iter().position(|n| n.addr == neighbor) {
+                Some(pos) => pos,
+                None => {
+                    bgp.neighbors.push(NeighborCfg {
+                        src: src.clone(),
+                        addr: neighbor,
+                        remote_as: None,
+                        rr_client: false,
+                        route_map_in: None,
+                        route_map_out: None,
+                        advertisement_interval: None,
+                    });
+                    bgp.neighbors.len() - 1
+                }
+            };
+            let n = &mut bgp.neighbors[pos];
+            match words {
+                ["remote-as", as_id] => {
+                    n.remote_as = Some(AsId(num(as_id)?));
+                    n.src = src.clone();
+                }
+                ["route-reflector-client"] => n.rr_client = true,
+                ["route-map", name, "in"] => n.route_map_in = Some((src.clone(), name.to_string())),
+                ["route-map", name, "out"] => {
+                    n.route_map_out = Some((src.clone(), name.to_string()))
+                }
+                ["advertisement-interval", i] => {
+                    n.advertisement_interval = Some((src.clone(), num(i)?))
+                }
+                ["weight", w] if *w != "100" => {
+                    return Err(String::from("per-session weights are not supported"))
+                }
+                // these options do not change the routing decisions.
+                []
+                | ["weight", _]
+                | ["update-source", _]
+                | ["next-hop-self"]
+                | ["send-community", ..]
+                | ["soft-reconfiguration", "inbound"]
+                | ["activate"]
+                | ["description", ..]
+                | ["address-family", "ipv4", "unicast"] => {}
+                _ => return Err(String::from("not supported")),
+            }
+        } else {
+            match words {
+                ["router-id", id] | ["bgp", "router-id", id] => bgp.router_id = Some(addr(id)?),
+                ["bgp" | "bestpath", "always-compare-med"] => {
+                    bgp.always_compare_med = Some(src.clone())
+                }
+                ["bgp", "bestpath", "compare-routerid"] | ["bestpath", "compare-routerid"] => {
+                    bgp.compare_router_id = Some(src.clone())
+                }
+                ["address-family", "ipv4", "unicast"] | ["address-family", "ipv4"] => {}
+                ["network", net] => bgp.networks.push((src.clone(), parse_net(net, None)?)),
+                ["network", net, "mask", mask] => bgp
+                    .networks
+                    .push((src.clone(), parse_net(net, Some(mask))?)),
+                ["aggregate-address", net, options @ ..] => {
+                    // Cisco IOS writes the network mask after the address
+                    let (net, options) = match options {
+                        [mask, options @ ..] if mask.parse::<Ipv4Addr>().is_ok() => {
+                            (parse_net(net, Some(mask))?, options)
+                        }
+                        _ => (parse_net(net, None)?, options),
+                    };
+                    let mut aggregate = BgpAggregate::default();
+                    for option in options {
+                        match *option {
+                            "summary-only" => aggregate.summary_only = true,
+                            "as-set" => aggregate.as_set = true,
+                            _ => return Err(format!("aggregate option `{option}` not supported")),
+                        }
+                    }
+                    bgp.aggregates.push((src.clone(), net, aggregate))
+                }
+                ["redistribute", "static"] => bgp.redistribute_static = Some((src.clone(), None)),
+                ["redistribute", "static", "route-map", name] => {
+                    bgp.redistribute_static = Some((src.clone(), Some(name.to_string())))
+                }
+                _ => return Err(String::from("not supported")),
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse a statement within a route-map item.
+    fn parse_route_map(&mut self, words: &[&str], src: &Src) -> Result<(), String> {
+        let (name, order) = self.current_rm.as_ref().unwrap();
+        let rm = self
+            .route_maps
+            .get_mut(name)
+            .and_then(|x| x.get_mut(order))
+            .unwrap();
+        let src = src.clone();
+        match words {
+            ["match", "ip", "address", "prefix-list", pl] => rm
+                .matches
+                .push((src, RouteMapMatchCfg::PrefixList(pl.to_string()))),
+            ["match", "ip", "next-hop", "prefix-list", pl] => rm
+                .matches
+                .push((src, RouteMapMatchCfg::NextHop(pl.to_string()))),
+            ["match", "community", cl] => rm
+                .matches
+                .push((src, RouteMapMatchCfg::Community(cl.to_string()))),
+            ["match", "as-path", asl] => rm
+                .matches
+                .push((src, RouteMapMatchCfg::AsPath(asl.to_string()))),
+            ["match", "origin", o] => rm
+                .matches
+                .push((src, RouteMapMatchCfg::Origin(parse_origin(o)?))),
+            ["set", "weight", w] => rm.sets.push((src, RouteMapSetCfg::Weight(num(w)?))),
+            ["set", "local-preference", lp] => {
+                rm.sets.push((src, RouteMapSetCfg::LocalPref(num(lp)?)))
+            }
+            ["set", "metric", med] => rm.sets.push((src, RouteMapSetCfg::Med(num(med)?))),
+            ["set", "origin", o] => rm
+                .sets
+                .push((src, RouteMapSetCfg::Origin(parse_origin(o)?))),
+            ["set", "ip", "next-hop", nh] => {
+                rm.sets.push((src, RouteMapSetCfg::NextHop(addr(nh)?)))
+            }
+            ["set", "community", communities @ ..] => {
+                let communities = communities
+                    .iter()
+                    .filter(|c| **c != "additive")
+                    .map(|c| parse_community(c))
+                    .collect::<Result<Vec<_>, _>>()?;
+                rm.sets.push((src, RouteMapSetCfg::Community(communities)))
+            }
+            ["set", "comm-list", cl, "delete"] => rm
+                .sets
+                .push((src, RouteMapSetCfg::DelCommunity(cl.to_string()))),
+            ["set", "as-path", "prepend", path @ ..] => {
+                let path = path
+                    .iter()
+                    .map(|a| num(a).map(AsId))
+                    .collect::<Result<Vec<_>, _>>()?;
+                rm.sets.push((src, RouteMapSetCfg::Prepend(path)))
+            }
+            ["continue"] => rm.continues = Some(None),
+            ["continue", order] => rm.continues = Some(Some(num(order)?)),
+            ["description", ..] => {}
+            _ => return Err(String::from("not supported")),
+        }
+        Ok(())
+    }
+
+    /// Get the router-id of the device.
+    fn router_id(&self) -> Option<Ipv4Addr> {
+        self.bgp
+            .as_ref()
+            .and_then(|b| b.router_id)
+            .or_else(|| self.ospf.as_ref().and_then(|o| o.router_id))
+            .or_else(|| {
+                self.ifaces
+                    .iter()
+                    .filter(|i| i.is_loopback())
+                    .flat_map(|i| i.addrs.iter())
+                    .map(|a| a.addr())
+                    .next()
+            })
+    }
+
+    /// Check if the device is an internal router.
+    fn is_internal(&self) -> bool {
+        self.ospf.is_some()
+    }
+
+    /// Get the OSPF area of an interface, either configured on the interface, or by the `network`
+    /// statement in `router ospf`.
+    fn iface_area(&self, iface: &IfaceCfg) -> Option<OspfArea> {
+        iface.area.or_else(|| {
+            let ospf = self.ospf.as_ref()?;
+            iface.addrs.iter().find_map(|a| {
+                ospf.networks
+                    .iter()
+                    .find(|(n, _)| n.contains(&a.addr()))
+                    .map(|(_, area)| *area)
+            })
+        })
+    }
+}
+
+/// State used while reconstructing the network.
+struct Importer<'a, Q> {
+    parser: &'a CiscoFrrParser,
+    net: Network<Ipv4Prefix, Q>,
+    /// Router-ID of each device, in the same order as the devices of the parser.
+    ids: Vec<RouterId>,
+    /// Owner of each address
+    owners: HashMap<Ipv4Addr, RouterId>,
+    plan: AddressPlan,
+    /// The network containing all internal routers.
+    internal_network: Option<Ipv4Net>,
+    unsupported: Vec<UnsupportedStatement>,
+}
+
+impl<'a, Q: EventQueue<Ipv4Prefix>> Importer<'a, Q> {
+    /// Create all routers.
+    fn new(parser: &'a CiscoFrrParser, queue: Q) -> Result<Self, CiscoFrrParseError> {
+        let mut net = Network::new(queue);
+        let mut names = HashSet::new();
+        let mut ids = Vec::new();
+        let mut owners = HashMap::new();
+
+        for dev in parser.devices.iter() {
+            if !names.insert(dev.name.as_str()) {
+                return Err(CiscoFrrParseError::DuplicateRouter(dev.name.clone()));
+            }
+            let router_id = dev
+                .router_id()
+                .ok_or_else(|| CiscoFrrParseError::MissingRouterId(dev.name.clone()))?;
+            let id = if dev.is_internal() {
+                net.add_router(dev.name.clone())
+            } else {
+                let as_id = dev
+                    .bgp
+                    .as_ref()
+                    .ok_or_else(|| CiscoFrrParseError::MissingAsId(dev.name.clone()))?
+                    .as_id;
+                net.add_external_router(dev.name.clone(), as_id)
+            };
+            ids.push(id);
+            owners.insert(router_id, id);
+            for a in dev.ifaces.iter().flat_map(|i| i.addrs.iter()) {
+                owners.insert(a.addr(), id);
+            }
+        }
+
+        Ok(Self {
+            parser,
+            net,
+            ids,
+            owners,
+            plan: AddressPlan {
+                args: DefaultAddressorBuilder::default(),
+                routers: HashMap::new(),
+                links: HashMap::new(),
+                interfaces: HashMap::new(),
+                iface_names: HashMap::new(),
+            },
+            internal_network: None,
+            unsupported: parser.unsupported.clone(),
+        })
+    }
+
+    /// Reconstruct the entire network
+    fn import(mut self) -> Result<ImportedNetwork<Q>, CiscoFrrParseError> {
+        self.links()?;
+        self.address_plan()?;
+        self.ospf()?;
+        self.static_routes()?;
+        self.bgp_sessions()?;
+        self.route_maps()?;
+        self.advertisements()?;
+
+        Ok(ImportedNetwork {
+            net: self.net,
+            addresses: self.plan,
+            unsupported: self.unsupported,
+        })
+    }
+
+    /// Report a statement as unsupported.
+    fn unsupported(&mut self, dev: usize, src: &Src, reason: impl Into<String>) {
+        let stmt = unsupported_stmt(&self.parser.devices[dev].name, src, reason.into());
+        self.unsupported.push(stmt);
+    }
+
+    /// Create all links between interfaces on the same subnet, and create the external routers
+    /// for all eBGP neighbors that have no configuration.
+    fn links(&mut self) -> Result<(), CiscoFrrParseError> {
+        let parser = self.parser;
+        // all interfaces that may connect to a neighbor, grouped by their subnet.
+        let mut subnets: BTreeMap<Ipv4Net, Vec<(usize, usize, Ipv4Addr)>> = BTreeMap::new();
+        for (d, dev) in parser.devices.iter().enumerate() {
+            for (i, iface) in dev.ifaces.iter().enumerate() {
+                if iface.is_loopback() {
+                    continue;
+                }
+                for a in iface.addrs.iter() {
+                    subnets.entry(a.trunc()).or_default().push((d, i, a.addr()));
+                }
+            }
+        }
+
+        // create all eBGP neighbors without a configuration.
+        for (d, dev) in parser.devices.iter().enumerate() {
+            let bgp = match dev.bgp.as_ref() {
+                Some(bgp) => bgp,
+                None => continue,
+            };
+            for n in bgp.neighbors.iter() {
+                if self.owners.contains_key(&n.addr) {
+                    continue;
+                }
+                let remote_as = match n.remote_as {
+                    Some(remote_as) if remote_as != bgp.as_id => remote_as,
+                    _ => continue,
+                };
+                let subnet = subnets
+                    .iter()
+                    .find(|(net, ifaces)| net.contains(&n.addr) && ifaces.iter().any(|x| x.0 == d))
+                    .map(|(net, _)| *net);
+                match subnet {
+                    Some(subnet) => {
+                        let id = self
+                            .net
+                            .add_external_router(format!("{remote_as}_{}", n.addr), remote_as);
+                        self.owners.insert(n.addr, id);
+                        self.ids.push(id);
+                        // use a placeholder for the device and interface index.
+                        subnets
+                            .get_mut(&subnet)
+                            .unwrap()
+                            .push((usize::MAX, usize::MAX, n.addr));
+                    }
+                    None => {
+                        let src = n.src.clone();
+                        self.unsupported(d, &src, "the eBGP neighbor is not directly connected")
+                    }
+                }
+            }
+        }
+
+        for (subnet, ifaces) in subnets {
+            let ((d_a, i_a, addr_a), (d_b, i_b, addr_b)) = match ifaces.as_slice() {
+                [a, b] => (*a, *b),
+                _ => continue,
+            };
+            let a = self.owners[&addr_a];
+            let b = self.owners[&addr_b];
+            if a == b {
+                continue;
+            }
+            self.net.add_link(a, b);
+            self.plan.links.insert(LinkId::new(a, b), subnet);
+            for (r, n, d, i, addr) in [(a, b, d_a, i_a, addr_a), (b, a, d_b, i_b, addr_b)] {
+                let names = self.plan.iface_names.entry(r).or_default();
+                let idx = if d == usize::MAX {
+                    0
+                } else {
+                    names.push(parser.devices[d].ifaces[i].name.clone());
+                    names.len() - 1
+                };
+                self.plan
+                    .interfaces
+                    .entry(r)
+                    .or_default()
+                    .insert(n, (idx, addr));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Infer the address ranges, and assign the router addresses.
+    fn address_plan(&mut self) -> Result<(), CiscoFrrParseError> {
+        let parser = self.parser;
+        let mut args = DefaultAddressorBuilder::default();
+
+        // the link prefix length is given by the links
+        if let Some(len) = self.plan.links.values().map(|n| n.prefix_len()).min() {
+            args.link_prefix_len = len;
+        }
+
+        // the internal network is the network that contains the router-id of internal routers,
+        // and is announced over BGP.
+        let internal_devs = parser
+            .devices
+            .iter()
+            .filter(|d| d.is_internal())
+            .collect_vec();
+        let announced = internal_devs.iter().find_map(|d| {
+            let id = d.router_id()?;
+            d.bgp
+                .as_ref()?
+                .networks
+                .iter()
+                .map(|(_, n)| n.trunc())
+                .find(|n| n.contains(&id))
+        });
+        let internal_network = match announced {
+            Some(n) => Some(n),
+            None => supernet(
+                internal_devs
+                    .iter()
+                    .filter_map(|d| d.router_id())
+                    .map(Ipv4Net::from)
+                    .chain(self.plan.links.values().copied()),
+            ),
+        };
+        if let Some(n) = internal_network {
+            args.internal_ip_range = n;
+            args.local_prefix_len = args.local_prefix_len.max(n.prefix_len() + 1).min(32);
+        }
+        self.internal_network = internal_network;
+
+        // the external routers announce their router network over BGP
+        let mut external_networks = Vec::new();
+        for (d, dev) in parser.devices.iter().enumerate() {
+            let id = dev.router_id().unwrap();
+            let r = self.ids[d];
+            if dev.is_internal() {
+                let net = Ipv4Net::new(id, args.local_prefix_len)?.trunc();
+                self.plan.routers.insert(r, (net, id));
+            } else {
+                let announced = dev
+                    .bgp
+                    .iter()
+                    .flat_map(|b| b.networks.iter())
+                    .map(|(_, n)| n.trunc())
+                    .find(|n| n.contains(&id));
+                if let Some(net) = announced {
+                    external_networks.push(net);
+                    self.plan.routers.insert(r, (net, id));
+                }
+            }
+        }
+        if let Some(len) = external_networks.iter().map(|n| n.prefix_len()).min() {
+            args.external_prefix_len = len;
+        }
+        if let Some(n) = supernet(external_networks.iter().copied()) {
+            args.external_ip_range = if n.prefix_len() > 8 {
+                Ipv4Net::new(n.addr(), 8)?.trunc()
+            } else {
+                n
+            };
+        }
+        // assign all external routers without an announced network
+        for (d, dev) in parser.devices.iter().enumerate() {
+            let r = self.ids[d];
+            if let Entry::Vacant(e) = self.plan.routers.entry(r) {
+                let id = dev.router_id().unwrap();
+                let net = Ipv4Net::new(id, args.external_prefix_len)?.trunc();
+                e.insert((net, id));
+            }
+        }
+
+        self.plan.args = args;
+        Ok(())
+    }
+
+    /// Set the link weights, OSPF areas, and load balancing.
+    fn ospf(&mut self) -> Result<(), CiscoFrrParseError> {
+        let parser = self.parser;
+        // lookup of the interface config of each link
+        let mut ifaces: HashMap<(RouterId, RouterId), (&DeviceCfg, &IfaceCfg)> = HashMap::new();
+        for (d, dev) in parser.devices.iter().enumerate() {
+            let r = self.ids[d];
+            let neighbors = match self.plan.interfaces.get(&r) {
+                Some(neighbors) => neighbors,
+                None => continue,
+            };
+            for iface in dev.ifaces.iter() {
+                for (n, _) in neighbors
+                    .iter()
+                    .filter(|(_, (_, a))| iface.addrs.iter().any(|x| x.addr() == *a))
+                {
+                    ifaces.insert((r, *n), (dev, iface));
+                }
+            }
+        }
+
+        // The weight of an interface. Links between internal routers need OSPF on both sides.
+        let weight = |r: RouterId, n: RouterId, n_int: bool| match ifaces.get(&(r, n)) {
+            Some((dev, iface)) if !n_int || dev.iface_area(iface).is_some() => {
+                Some(iface.cost.unwrap_or(1.0))
+            }
+            _ => None,
+        };
+        let shutdown = |r: RouterId, n: RouterId| {
+            ifaces
+                .get(&(r, n))
+                .map(|(_, iface)| iface.shutdown)
+                .unwrap_or(false)
+        };
+
+        let mut links = self.plan.links.keys().map(|l| (l.0, l.1)).collect_vec();
+        links.sort();
+        for (a, b) in links {
+            if shutdown(a, b) || shutdown(b, a) {
+                continue;
+            }
+            let a_int = self.net.get_device(a).is_internal();
+            let b_int = self.net.get_device(b).is_internal();
+            let weights = match (a_int, b_int) {
+                (true, true) => weight(a, b, true).zip(weight(b, a, true)),
+                (true, false) => weight(a, b, false).map(|w| (w, w)),
+                (false, true) => weight(b, a, false).map(|w| (w, w)),
+                (false, false) => None,
+            };
+            let (w_ab, w_ba) = match weights {
+                Some(w) => w,
+                None => continue,
+            };
+            self.net.set_link_weight(a, b, w_ab)?;
+            self.net.set_link_weight(b, a, w_ba)?;
+
+            if a_int && b_int {
+                let area = ifaces
+                    .get(&(a, b))
+                    .and_then(|(dev, iface)| dev.iface_area(iface));
+                if let Some(area) = area.filter(|a| !a.is_backbone()) {
+                    self.net.set_ospf_area(a, b, area)?;
+                }
+            }
+        }
+
+        for (d, dev) in parser.devices.iter().enumerate() {
+            if dev.ospf.as_ref().map(|o| o.maximum_paths > 1) == Some(true) {
+                self.net.set_load_balancing(self.ids[d], true)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Create all static routes on internal routers.
+    fn static_routes(&mut self) -> Result<(), CiscoFrrParseError> {
+        let parser = self.parser;
+        for (d, dev) in parser.devices.iter().enumerate() {
+            let r = self.ids[d];
+            let announced: HashSet<Ipv4Net> = dev
+                .bgp
+                .iter()
+                .flat_map(|b| b.networks.iter())
+                .map(|(_, n)| n.trunc())
+                .collect();
+            for sr in dev.static_routes.iter() {
+                // static routes that are used to announce a network over BGP
+                if matches!(sr.target, StaticTarget::Drop) && announced.contains(&sr.net) {
+                    continue;
+                }
+                if !dev.is_internal() {
+                    self.unsupported(
+                        d,
+                        &sr.src,
+                        "static routes on external routers are not supported",
+                    );
+                    continue;
+                }
+                let target = match &sr.target {
+                    StaticTarget::Drop => Some(StaticRoute::Drop),
+                    StaticTarget::Address(a) => {
+                        self.owners.get(a).map(|n| StaticRoute::Indirect(*n))
+                    }
+                    StaticTarget::Iface(name) => dev
+                        .ifaces
+                        .iter()
+                        .find(|i| &i.name == name)
+                        .and_then(|i| i.addrs.first())
+                        .and_then(|a| {
+                            self.plan
+                                .interfaces
+                                .get(&r)?
+                                .iter()
+                                .find(|(_, (_, x))| *x == a.addr())
+                                .map(|(n, _)| StaticRoute::Direct(*n))
+                        }),
+                };
+                match target {
+                    Some(target) => {
+                        self.net
+                            .set_static_route(r, Ipv4Prefix::from(sr.net), Some(target))?;
+                    }
+                    None => self.unsupported(d, &sr.src, "the next-hop is unknown"),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Establish all BGP sessions.
+    fn bgp_sessions(&mut self) -> Result<(), CiscoFrrParseError> {
+        let parser = self.parser;
+        let mut established = HashSet::new();
+        for (d, dev) in parser.devices.iter().enumerate() {
+            let r = self.ids[d];
+            let bgp = match dev.bgp.as_ref() {
+                Some(bgp) => bgp,
+                None => continue,
+            };
+            if let Some(src) = bgp.always_compare_med.as_ref() {
+                if dev.is_internal() {
+                    self.net.set_always_compare_med(r, true)?;
+                } else {
+                    self.unsupported(d, src, "not supported on external routers");
+                }
+            }
+            if let Some(src) = bgp.compare_router_id.as_ref() {
+                if dev.is_internal() {
+                    self.net.set_compare_router_id(r, true)?;
+                } else {
+                    self.unsupported(d, src, "not supported on external routers");
+                }
+            }
+            for (src, net, aggregate) in bgp.aggregates.iter() {
+                if dev.is_internal() {
+                    self.net.set_bgp_aggregate(
+                        r,
+                        Ipv4Prefix::from(net.trunc()),
+                        Some(*aggregate),
+                    )?;
+                } else {
+                    self.unsupported(d, src, "not supported on external routers");
+                }
+            }
+            // networks of external routers are translated into advertisements.
+            if dev.is_internal() {
+                for (_, net) in bgp.networks.iter() {
+                    let net = net.trunc();
+                    if Some(net) != self.internal_network {
+                        self.net.set_bgp_network(r, Ipv4Prefix::from(net), true)?;
+                    }
+                }
+            }
+            if let Some((src, route_map)) = bgp.redistribute_static.as_ref() {
+                let permit_all = route_map
+                    .as_ref()
+                    .map(|name| {
+                        dev.route_maps
+                            .get(name)
+                            .map(|x| x.values().all(|rm| rm.is_permit_all()))
+                            .unwrap_or(false)
+                    })
+                    .unwrap_or(true);
+                if !dev.is_internal() {
+                    self.unsupported(d, src, "not supported on external routers");
+                } else if !permit_all {
+                    self.unsupported(d, src, "route-maps for redistribution are not supported");
+                } else {
+                    self.net.set_bgp_redistribute_static(r, true)?;
+                }
+            }
+            // all addresses of this device, used to find the reverse session.
+            let own_addrs: HashSet<Ipv4Addr> = dev
+                .ifaces
+                .iter()
+                .flat_map(|i| i.addrs.iter().map(|a| a.addr()))
+                .chain(dev.router_id())
+                .collect();
+            for n in bgp.neighbors.iter() {
+                // unknown neighbors were already reported while creating the links.
+                let peer = match self.owners.get(&n.addr) {
+                    Some(peer) => *peer,
+                    None => continue,
+                };
+                let r_int = dev.is_internal();
+                let peer_as = self.net.get_device(peer).external().map(|e| e.as_id());
+                let reason = match (n.remote_as, r_int, peer_as) {
+                    (None, _, _) => Some("the neighbor has no `remote-as`"),
+                    (_, false, Some(_)) => {
+                        Some("sessions between external routers are not supported")
+                    }
+                    (Some(x), true, None) if x != bgp.as_id => {
+                        Some("sessions between internal routers must use iBGP")
+                    }
+                    (Some(x), true, Some(y)) if x != y => {
+                        Some("the AS number of the neighbor does not match")
+                    }
+                    _ => None,
+                };
+                if let Some(reason) = reason {
+                    self.unsupported(d, &n.src, reason);
+                    continue;
+                }
+
+                // the peer must also configure the session, unless it has no configuration.
+                let peer_dev = self.ids.iter().position(|x| *x == peer);
+                let reverse = peer_dev.and_then(|i| parser.devices.get(i)).map(|p| {
+                    p.bgp
+                        .iter()
+                        .flat_map(|b| b.neighbors.iter())
+                        .find(|x| own_addrs.contains(&x.addr))
+                });
+                let peer_rr_client = match reverse {
+                    Some(Some(x)) => x.rr_client,
+                    Some(None) => {
+                        self.unsupported(d, &n.src, "the neighbor does not configure the session");
+                        continue;
+                    }
+                    None => false,
+                };
+
+                // the MRAI is configured on each side of the session independently.
+                if let Some((src, interval)) = n.advertisement_interval.as_ref() {
+                    if r_int {
+                        self.net
+                            .set_bgp_mrai(r, peer, Some(Mrai::new(*interval as f64)))?;
+                    } else {
+                        self.unsupported(d, src, "not supported on external routers");
+                    }
+                }
+
+                if !established.insert(LinkId::new(r, peer)) {
+                    continue;
+                }
+                let (src, dst, ty) = if !r_int || peer_as.is_some() {
+                    (r, peer, BgpSessionType::EBgp)
+                } else if n.rr_client {
+                    (r, peer, BgpSessionType::IBgpClient)
+                } else if peer_rr_client {
+                    (peer, r, BgpSessionType::IBgpClient)
+                } else {
+                    (r, peer, BgpSessionType::IBgpPeer)
+                };
+                self.net.set_bgp_session(src, dst, Some(ty))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Create all route-maps of internal routers.
+    fn route_maps(&mut self) -> Result<(), CiscoFrrParseError> {
+        let parser = self.parser;
+        for (d, dev) in parser.devices.iter().enumerate() {
+            let r = self.ids[d];
+            let bgp = match dev.bgp.as_ref() {
+                Some(bgp) if dev.is_internal() => bgp,
+                _ => continue,
+            };
+            for n in bgp.neighbors.iter() {
+                let peer = match self.owners.get(&n.addr) {
+                    Some(peer) => *peer,
+                    None => continue,
+                };
+                if self
+                    .net
+                    .get_device(r)
+                    .internal()
+                    .map(|x| x.get_bgp_session_type(peer).is_none())
+                    .unwrap_or(true)
+                {
+                    continue;
+                }
+                for (dir, rm) in [
+                    (RouteMapDirection::Incoming, &n.route_map_in),
+                    (RouteMapDirection::Outgoing, &n.route_map_out),
+                ] {
+                    if let Some((src, name)) = rm {
+                        for map in self.route_map(d, src, name) {
+                            self.net.set_bgp_route_map(r, peer, dir, map)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Translate a route-map of an internal router.
+    fn route_map(&mut self, d: usize, src: &Src, name: &str) -> Vec<RouteMap<Ipv4Prefix>> {
+        let dev = &self.parser.devices[d];
+        let items = match dev.route_maps.get(name) {
+            Some(items) => items,
+            None => {
+                self.unsupported(d, src, format!("route-map {name} is not defined"));
+                return Vec::new();
+            }
+        };
+
+        let mut maps = Vec::new();
+        let orders = items.keys().copied().collect_vec();
+        // the last item that permits everything is the default behavior of bgpsim
+        let permits_all = items
+            .iter()
+            .next_back()
+            .map(|(_, rm)| rm.is_permit_all())
+            .unwrap_or(false);
+
+        for (pos, (order, item)) in items.iter().enumerate() {
+            if permits_all && pos + 1 == items.len() {
+                break;
+            }
+            let mut rm = RouteMapBuilder::new();
+            rm.order_sgn(signed_order(*order));
+            if item.allow {
+                rm.allow();
+            } else {
+                rm.deny();
+            }
+
+            let mut valid = true;
+            for (src, m) in item.matches.iter() {
+                if let Err(reason) = self.route_map_match(d, &mut rm, m) {
+                    self.unsupported(d, src, format!("{reason}; ignoring the route-map item"));
+                    valid = false;
+                }
+            }
+            if !valid {
+                continue;
+            }
+
+            for (src, s) in item.sets.iter() {
+                if let Err(reason) = self.route_map_set(d, &mut rm, s) {
+                    self.unsupported(d, src, reason);
+                }
+            }
+
+            // the flow of a deny item has no effect; keep the builder's default.
+            match item.continues {
+                None if !item.allow => &mut rm,
+                None => rm.exit(),
+                Some(None) => rm.continue_next(),
+                Some(Some(next)) if next <= *order => {
+                    self.unsupported(d, &item.src, "can only continue with a later item");
+                    rm.exit()
+                }
+                Some(Some(next)) if orders.get(pos + 1) == Some(&next) => rm.continue_next(),
+                Some(Some(next)) => rm.continue_at(signed_order(next)),
+            };
+
+            maps.push(rm.build());
+        }
+
+        // add the implicit deny at the end of the route-map.
+        if !permits_all && orders.last() != Some(&u16::MAX) {
+            maps.push(RouteMapBuilder::new().order_sgn(i16::MAX).deny().build());
+        }
+
+        maps
+    }
+
+    /// Add a match statement to the route-map.
+    fn route_map_match(
+        &self,
+        d: usize,
+        rm: &mut RouteMapBuilder<Ipv4Prefix>,
+        m: &RouteMapMatchCfg,
+    ) -> Result<(), String> {
+        let dev = &self.parser.devices[d];
+        match m {
+            RouteMapMatchCfg::PrefixList(pl) => {
+                let nets = dev
+                    .prefix_lists
+                    .get(pl)
+                    .filter(|x| !x.is_empty())
+                    .ok_or_else(|| format!("prefix-list {pl} is not defined"))?;
+                for net in nets {
+                    rm.match_prefix(Ipv4Prefix::from(net.trunc()));
+                }
+            }
+            RouteMapMatchCfg::NextHop(pl) => {
+                let nh = match dev.prefix_lists.get(pl).map(|x| x.as_slice()) {
+                    Some([net]) if net.prefix_len() == 32 => self.owners.get(&net.addr()),
+                    _ => None,
+                }
+                .ok_or_else(|| format!("prefix-list {pl} does not match a known next-hop"))?;
+                rm.match_next_hop(*nh);
+            }
+            RouteMapMatchCfg::Community(cl) => {
+                let list = dev
+                    .community_lists
+                    .get(cl)
+                    .ok_or_else(|| format!("community-list {cl} is not defined"))?;
+                for c in list.permit.iter().flatten() {
+                    rm.match_community(*c);
+                }
+                for c in list.deny.iter() {
+                    rm.match_deny_community(*c);
+                }
+            }
+            RouteMapMatchCfg::AsPath(asl) => {
+                let regex = match dev.as_path_lists.get(asl).map(|x| x.as_slice()) {
+                    Some([(true, regex)]) => regex,
+                    _ => return Err(format!("as-path access-list {asl} is not supported")),
+                };
+                let as_id = regex
+                    .strip_prefix('_')
+                    .and_then(|x| x.strip_suffix('_'))
+                    .and_then(|x| x.parse::<u32>().ok());
+                if let Some(as_id) = as_id {
+                    rm.match_as_path_contains(AsId(as_id));
+                } else {
+                    rm.match_as_path_regex(AsPathRegex::new(regex.as_str()).map_err(|e| {
+                        format!("as-path access-list {asl} has an invalid regex: {e}")
+                    })?);
+                }
+            }
+            RouteMapMatchCfg::Origin(o) => {
+                rm.match_origin(*o);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a set statement to the route-map.
+    fn route_map_set(
+        &self,
+        d: usize,
+        rm: &mut RouteMapBuilder<Ipv4Prefix>,
+        s: &RouteMapSetCfg,
+    ) -> Result<(), String> {
+        let dev = &self.parser.devices[d];
+        match s {
+            RouteMapSetCfg::Weight(w) => {
+                rm.set_weight(*w);
+            }
+            RouteMapSetCfg::LocalPref(lp) => {
+                rm.set_local_pref(*lp);
+            }
+            RouteMapSetCfg::Med(med) => {
+                rm.set_med(*med);
+            }
+            RouteMapSetCfg::NextHop(nh) => {
+                let nh = self
+                    .owners
+                    .get(nh)
+                    .ok_or_else(|| format!("next-hop {nh} is unknown"))?;
+                rm.set_next_hop(*nh);
+            }
+            RouteMapSetCfg::Community(cs) => {
+                for c in cs {
+                    rm.set_community(*c);
+                }
+            }
+            RouteMapSetCfg::DelCommunity(cl) => {
+                let list = dev
+                    .community_lists
+                    .get(cl)
+                    .ok_or_else(|| format!("community-list {cl} is not defined"))?;
+                for c in list.permit.iter().flatten() {
+                    rm.remove_community(*c);
+                }
+            }
+            RouteMapSetCfg::Origin(o) => {
+                rm.set_origin(*o);
+            }
+            RouteMapSetCfg::Prepend(path) => {
+                rm.prepend_as_path(path.iter().copied());
+            }
+        }
+        Ok(())
+    }
+
+    /// Advertise all routes of external routers.
+    fn advertisements(&mut self) -> Result<(), CiscoFrrParseError> {
+        let parser = self.parser;
+        for (d, dev) in parser.devices.iter().enumerate() {
+            let r = self.ids[d];
+            let bgp = match dev.bgp.as_ref() {
+                Some(bgp) if !dev.is_internal() => bgp,
+                _ => continue,
+            };
+            let own_network = self.plan.routers.get(&r).map(|(n, _)| *n);
+
+            // all announced networks, except the own network.
+            let mut networks: BTreeMap<Ipv4Net, Src> = BTreeMap::new();
+            for (src, n) in bgp.networks.iter() {
+                if Some(n.trunc()) != own_network {
+                    networks.insert(n.trunc(), src.clone());
+                }
+            }
+
+            // the outgoing route-map, which must be the same for all neighbors.
+            let mut route_map_out: Option<&str> = None;
+            for n in bgp.neighbors.iter() {
+                if let Some((src, name)) = n.route_map_in.as_ref() {
+                    let permit_all = dev
+                        .route_maps
+                        .get(name)
+                        .map(|x| x.values().all(|rm| rm.is_permit_all()))
+                        .unwrap_or(false);
+                    if !permit_all {
+                        self.unsupported(
+                            d,
+                            src,
+                            "incoming route-maps are not supported on external routers",
+                        );
+                    }
+                }
+                if let Some((src, name)) = n.route_map_out.as_ref() {
+                    match route_map_out {
+                        None => route_map_out = Some(name.as_str()),
+                        Some(x) if x == name.as_str() => {}
+                        Some(_) => self.unsupported(
+                            d,
+                            src,
+                            "external routers must use the same route-map for all neighbors",
+                        ),
+                    }
+                }
+            }
+
+            // translate the route-map into advertisements
+            let items = route_map_out
+                .and_then(|name| dev.route_maps.get(name))
+                .into_iter()
+                .flat_map(|x| x.values());
+            for item in items {
+                if item.is_permit_all() {
+                    continue;
+                }
+                let prefixes = match item.matches.as_slice() {
+                    [(_, RouteMapMatchCfg::PrefixList(pl))] if item.allow => {
+                        dev.prefix_lists.get(pl).cloned().unwrap_or_default()
+                    }
+                    _ => {
+                        self.unsupported(
+                            d,
+                            &item.src,
+                            "external routers only support route-map items that match a single prefix-list",
+                        );
+                        continue;
+                    }
+                };
+                let mut as_path = vec![bgp.as_id];
+                let mut med = None;
+                let mut origin = Origin::Igp;
+                let mut communities = Vec::new();
+                for (src, s) in item.sets.iter() {
+                    match s {
+                        RouteMapSetCfg::Med(m) => med = Some(*m),
+                        RouteMapSetCfg::Origin(o) => origin = *o,
+                        RouteMapSetCfg::Community(cs) => communities.extend(cs.iter().copied()),
+                        RouteMapSetCfg::Prepend(path) => as_path.extend(path.iter().copied()),
+                        _ => self.unsupported(d, src, "not supported on external routers"),
+                    }
+                }
+                for prefix in prefixes {
+                    if networks.remove(&prefix.trunc()).is_some() {
+                        self.net.advertise_external_route_with_origin(
+                            r,
+                            Ipv4Prefix::from(prefix.trunc()),
+                            as_path.clone(),
+                            med,
+                            communities.clone(),
+                            origin,
+                        )?;
+                    }
+                }
+            }
+
+            // advertise all remaining networks
+            for (prefix, _) in networks {
+                self.net.advertise_external_route(
+                    r,
+                    Ipv4Prefix::from(prefix),
+                    [bgp.as_id],
+                    None,
+                    [],
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Create an unsupported statement
+fn unsupported_stmt(router: &str, src: &Src, reason: String) -> UnsupportedStatement {
+    UnsupportedStatement {
+        router: router.to_string(),
+        line: src.line,
+        statement: src.text.clone(),
+        reason,
+    }
+}
+
+/// Translate the route-map order from unsigned to signed. This is the inverse of the ordering
+/// used by the [`super::CiscoFrrCfgGen`].
+fn signed_order(order: u16) -> i16 {
+    ((order as i32) + (i16::MIN as i32)) as i16
+}
+
+/// Get the smallest network that contains all given networks.
+fn supernet(nets: impl IntoIterator<Item = Ipv4Net>) -> Option<Ipv4Net> {
+    let mut nets = nets.into_iter();
+    let mut result = nets.next()?.trunc();
+    for net in nets {
+        while !result.contains(&net) {
+            result = result.supernet()?;
+        }
+    }
+    Some(result)
+}
+
+/// Parse a number
+fn num<T: FromStr>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("cannot parse `{s}`"))
+}
+
+/// Parse an IP address
+fn addr(s: &str) -> Result<Ipv4Addr, String> {
+    s.parse().map_err(|_| format!("cannot parse address `{s}`"))
+}
+
+/// Parse a network, either in the form `a.b.c.d/x`, or as an address with a netmask.
+fn parse_net(s: &str, mask: Option<&str>) -> Result<Ipv4Net, String> {
+    match mask {
+        Some(mask) => Ipv4Net::with_netmask(addr(s)?, addr(mask)?)
+            .map_err(|_| format!("invalid netmask `{mask}`")),
+        None => s.parse().map_err(|_| format!("cannot parse network `{s}`")),
+    }
+}
+
+/// Parse an OSPF area, either as a number or in the dotted form.
+fn parse_area(s: &str) -> Result<OspfArea, String> {
+    match s.parse::<u32>() {
+        Ok(x) => Ok(OspfArea::from(x)),
+        Err(_) => Ok(OspfArea::from(u32::from(addr(s)?))),
+    }
+}
+
+/// Parse the ORIGIN attribute (`igp`, `egp`, or `incomplete`).
+fn parse_origin(s: &str) -> Result<Origin, String> {
+    match s {
+        "igp" => Ok(Origin::Igp),
+        "egp" => Ok(Origin::Egp),
+        "incomplete" => Ok(Origin::Incomplete),
+        _ => Err(format!("cannot parse origin `{s}`")),
+    }
+}
+
+/// Parse a community of the form `AS:VALUE`. Only communities of the internal AS are supported.
+fn parse_community(s: &str) -> Result<u32, String> {
+    match s.split_once(':') {
+        Some((as_id, c)) if num::<u32>(as_id)? == INTERNAL_AS.0 => num(c),
+        _ => Err(format!(
+            "only communities of the form `{}:X` are supported",
+            INTERNAL_AS.0
+        )),
+    }
+}
@@ -23,6 +23,7 @@ use std::{
 };
 
 use ipnet::{Ipv4Net, Ipv4Subnets};
+use itertools::Itertools;
 
 use super::{ip_err, Addressor, ExportError, LinkId, MaybePec};
 use crate::{
@@ -205,6 +206,80 @@ impl<'a, P: Prefix, Q> DefaultAddressor<'a, P, Q> {
             pecs: Default::default(),
         })
     }
+
+    /// Create a new Default IP Addressor that starts with the given addresses already assigned.
+    /// All new addresses are taken after the last assigned network of the respective range.
+    pub(crate) fn with_assignments(
+        net: &'a Network<P, Q>,
+        args: &DefaultAddressorBuilder,
+        router_addrs: HashMap<RouterId, (Ipv4Net, Ipv4Addr)>,
+        link_addrs: HashMap<LinkId, Ipv4Net>,
+        interfaces: HashMap<RouterId, HashMap<RouterId, (usize, Ipv4Addr)>>,
+    ) -> Result<Self, ExportError> {
+        let mut this = Self::new(net, args)?;
+
+        let routers = this.subnet_for_internal_routers();
+        let internal_links = this.subnet_for_internal_links();
+        let external_links = this.subnet_for_external_links();
+        skip_assigned(
+            &mut this.internal_router_addr_iter,
+            routers,
+            router_addrs.values().map(|(n, _)| *n),
+        );
+        skip_assigned(
+            &mut this.internal_link_addr_iter,
+            internal_links,
+            link_addrs.values().copied(),
+        );
+        skip_assigned(
+            &mut this.external_link_addr_iter,
+            external_links,
+            link_addrs.values().copied(),
+        );
+
+        // external routers are assigned a network for each AS.
+        let mut as_networks: HashMap<AsId, Ipv4Net> = HashMap::new();
+        for (r, (router_net, _)) in router_addrs.iter().sorted_by_key(|(r, _)| *r) {
+            let as_id = match net.get_device(*r).external() {
+                Some(e) => e.as_id(),
+                None => continue,
+            };
+            if !args.external_ip_range.contains(router_net) {
+                continue;
+            }
+            let as_net = *as_networks
+                .entry(as_id)
+                .or_insert(Ipv4Net::new(router_net.addr(), args.external_prefix_len)?.trunc());
+            let iter = match this.external_router_addr_iters.entry(as_id) {
+                Entry::Occupied(e) => e.into_mut(),
+                Entry::Vacant(e) => e.insert(as_net.subnets(this.external_router_prefix_len)?),
+            };
+            skip_assigned(iter, as_net, std::iter::once(*router_net));
+        }
+        skip_assigned(
+            &mut this.external_as_addr_iter,
+            args.external_ip_range,
+            as_networks.into_values(),
+        );
+
+        this.router_addrs = router_addrs;
+        this.link_addrs = link_addrs;
+        this.interfaces = interfaces;
+        Ok(this)
+    }
+}
+
+/// Advance the iterator past the last network within `range` that is already assigned.
+fn skip_assigned(iter: &mut Ipv4Subnets, range: Ipv4Net, assigned: impl Iterator<Item = Ipv4Net>) {
+    if let Some(last) = assigned
+        .filter(|n| range.contains(n))
+        .map(|n| n.network())
+        .max()
+    {
+        while iter.clone().next().map(|n| n.network() <= last) == Some(true) {
+            iter.next();
+        }
+    }
 }
 
 impl<'a, P: Prefix, Q> DefaultAddressor<'a, P, Q> {
@@ -24,12 +24,14 @@ use std::{
 };
 
 use crate::{
-    bgp::BgpRoute,
+    bgp::{BgpRoute, Origin},
     network::Network,
     types::{AsId, Prefix, PrefixMap, RouterId},
 };
 
-use super::{Addressor, ExportError, ExternalCfgGen, INTERNAL_AS};
+use super::{
+    cisco_frr_generators::origin_keyword, Addressor, ExportError, ExternalCfgGen, INTERNAL_AS,
+};
 
 /// Config generator for [ExaBGP](https://github.com/Exa-Networks/exabgp)
 ///
@@ -349,9 +351,14 @@ neighbor {} {{
 /// Get the text to announce a route.
 fn route_text<P: Prefix>(route: &BgpRoute<P>, address: Ipv4Net) -> Result<String, ExportError> {
     Ok(format!(
-        "announce route {} next-hop self as-path [{}]{}{}",
+        "announce route {} next-hop self as-path [{}]{}{}{}",
         address,
         route.as_path.iter().map(|x| x.0).join(", "),
+        if route.origin == Origin::Igp {
+            String::new()
+        } else {
+            format!(" origin {}", origin_keyword(route.origin))
+        },
         if let Some(med) = route.med {
             format!(" metric {med}")
         } else {
@@ -34,6 +34,7 @@ use crate::{
 
 mod cisco_frr;
 pub mod cisco_frr_generators;
+pub mod cisco_frr_parser;
 mod default;
 mod exabgp;
 
@@ -382,6 +383,9 @@ pub enum ExportError {
     /// Did not expect a prefix equivalence class at this point.
     #[error("Did not expect a prefix equivalence class of {0}!")]
     UnexpectedPec(Ipv4Net),
+    /// The community list both requires and excludes communities, which Junos cannot express.
+    #[error("Community list {0} cannot both require and exclude communities on Junos!")]
+    UnsupportedCommunityList(String),
 }
 
 /// Return `ExportError::NotEnoughAddresses` if the option is `None`.
@@ -21,7 +21,7 @@
 //! operators.
 
 use crate::{
-    bgp::{BgpEvent, BgpRoute},
+    bgp::{BgpEvent, BgpRoute, Origin},
     event::{Event, EventOutcome},
     types::{AsId, DeviceError, Prefix, PrefixMap, RouterId, StepUpdate},
 };
@@ -90,11 +90,13 @@ impl<P: Prefix> ExternalRouter<P> {
         }
     }
 
-    /// Handle an `Event` and produce the necessary result. Always returns Ok((false, vec![])), to
-    /// tell that the forwarding state has not changed.
+    /// Handle an `Event` and produce the necessary result. The forwarding state never changes. The
+    /// only events that trigger new events are OPEN messages from a neighbor (see
+    /// [`crate::bgp::BgpSessionState`]). The external router answers them with an OPEN message,
+    /// followed by all advertised routes.
     ///
     /// *Undo Functionality*: this function will push a new undo event to the queue.
-    pub(crate) fn handle_event<T>(
+    pub(crate) fn handle_event<T: Default>(
         &mut self,
         event: Event<P, T>,
     ) -> Result<EventOutcome<P, T>, DeviceError> {
@@ -102,6 +104,20 @@ impl<P: Prefix> ExternalRouter<P> {
         #[cfg(feature = "undo")]
         self.undo_stack.push(Vec::new());
 
+        if let Event::Bgp(_, from, to, BgpEvent::Open) = event {
+            if to == self.router_id && self.neighbors.contains(&from) {
+                let events = std::iter::once(Event::Bgp(
+                    T::default(),
+                    self.router_id,
+                    from,
+                    BgpEvent::Open,
+                ))
+                .chain(self.update_events(from))
+                .collect();
+                return Ok((StepUpdate::default(), events));
+            }
+        }
+
         if let Some(prefix) = event.prefix() {
             Ok((StepUpdate::new(prefix, vec![], vec![]), vec![]))
         } else {
@@ -163,12 +179,14 @@ impl<P: Prefix> ExternalRouter<P> {
         as_path: Vec<AsId>,
         med: Option<u32>,
         community: I,
+        origin: Origin,
     ) -> (BgpRoute<P>, Vec<Event<P, T>>) {
         // prepare undo stack
         #[cfg(feature = "undo")]
         self.undo_stack.push(Vec::new());
 
-        let route = BgpRoute::new(self.router_id, prefix, as_path, med, community);
+        let mut route = BgpRoute::new(self.router_id, prefix, as_path, med, community);
+        route.origin = origin;
 
         let old_route = self.active_routes.insert(prefix, route.clone());
 
@@ -245,22 +263,27 @@ impl<P: Prefix> ExternalRouter<P> {
                 .unwrap()
                 .push(UndoAction::DelBgpSession(router));
             // send all prefixes to this router
-            self.active_routes
-                .iter()
-                .map(|(_, r)| {
-                    Event::Bgp(
-                        T::default(),
-                        self.router_id,
-                        router,
-                        BgpEvent::Update(r.clone()),
-                    )
-                })
-                .collect()
+            self.update_events(router)
         } else {
             Vec::new()
         })
     }
 
+    /// Generate an UPDATE message to `router` for each advertised route.
+    pub(crate) fn update_events<T: Default>(&self, router: RouterId) -> Vec<Event<P, T>> {
+        self.active_routes
+            .iter()
+            .map(|(_, r)| {
+                Event::Bgp(
+                    T::default(),
+                    self.router_id,
+                    router,
+                    BgpEvent::Update(r.clone()),
+                )
+            })
+            .collect()
+    }
+
     /// Close an existing eBGP session with an internal router.
     ///
     /// *Undo Functionality*: this function will push a new undo event to the queue.
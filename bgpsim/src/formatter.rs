@@ -27,9 +27,10 @@ use itertools::{join, Itertools};
 use crate::{
     bgp::{BgpEvent, BgpRibEntry, BgpRoute},
     config::{Config, ConfigExpr, ConfigExprKey, ConfigModifier, ConfigPatch, RouteMapEdit},
-    event::{BasicEventQueue, Event, FmtPriority},
+    event::{BasicEventQueue, Event, FmtPriority, TimerEvent},
     forwarding_state::{ForwardingState, TO_DST},
     network::Network,
+    ospf::{Lsa, OspfEvent},
     policies::{FwPolicy, PathCondition, PathConditionCNF, PolicyError, Waypoint},
     record::{ConvergenceRecording, ConvergenceTrace, FwDelta},
     route_map::{RouteMap, RouteMapDirection, RouteMapMatch, RouteMapSet, RouteMapState},
@@ -228,6 +229,35 @@ impl<'a, 'n, P: Prefix, Q, T: FmtPriority> NetworkFormatter<'a, 'n, P, Q> for Ev
                 event.fmt(net),
                 p.fmt()
             ),
+            Event::Ospf(p, from, to, event) => format!(
+                "OSPF Event: {} -> {}: {} {}",
+                from.fmt(net),
+                to.fmt(net),
+                event.fmt(net),
+                p.fmt()
+            ),
+            Event::Timer(p, router, delay, timer) => format!(
+                "Timer: {}: {} (after {delay}s) {}",
+                router.fmt(net),
+                timer.fmt(net),
+                p.fmt()
+            ),
+        }
+    }
+}
+
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for TimerEvent {
+    type Formatter = String;
+
+    fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
+        match self {
+            TimerEvent::BgpMrai(neighbor) => format!("MRAI of {} expired", neighbor.fmt(net)),
+            TimerEvent::BgpConnect(neighbor) => {
+                format!("Connection to {} established", neighbor.fmt(net))
+            }
+            TimerEvent::BgpHold(neighbor) => {
+                format!("Hold timer of {} expired", neighbor.fmt(net))
+            }
         }
     }
 }
@@ -239,6 +269,7 @@ impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for BgpEvent<P> {
         match self {
             BgpEvent::Withdraw(prefix) => format!("Withdraw {prefix}"),
             BgpEvent::Update(route) => format!("Update {}", route.fmt(net)),
+            BgpEvent::Open => String::from("Open"),
         }
     }
 }
@@ -247,6 +278,34 @@ impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for BgpEvent<P> {
 // BGP Route
 //
 
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for OspfEvent {
+    type Formatter = String;
+
+    fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
+        match self {
+            OspfEvent::LsUpdate(lsas) => {
+                format!("LS Update [{}]", lsas.iter().map(|l| l.fmt(net)).join(", "))
+            }
+        }
+    }
+}
+
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for Lsa {
+    type Formatter = String;
+
+    fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
+        format!(
+            "LSA {{ router: {}, seq: {}, links: {{{}}} }}",
+            self.router.fmt(net),
+            self.seq,
+            self.links
+                .iter()
+                .map(|(r, w, a)| format!("{}: {w} ({a})", r.fmt(net)))
+                .join(", ")
+        )
+    }
+}
+
 impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for BgpRoute<P> {
     type Formatter = String;
 
@@ -318,6 +377,7 @@ impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for RouteMapMatch<P> {
             RouteMapMatch::NextHop(nh) => format!("NextHop == {}", nh.fmt(net)),
             RouteMapMatch::Community(c) => format!("Community {c}"),
             RouteMapMatch::DenyCommunity(c) => format!("Deny Community {c}"),
+            RouteMapMatch::Origin(o) => format!("Origin == {o}"),
         }
     }
 }
@@ -334,6 +394,10 @@ impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for RouteMapSet {
             RouteMapSet::LocalPref(None) => "clear LocalPref".to_string(),
             RouteMapSet::Med(Some(med)) => format!("MED = {med}"),
             RouteMapSet::Med(None) => "clear MED".to_string(),
+            RouteMapSet::Origin(o) => format!("Origin = {o}"),
+            RouteMapSet::PrependAsPath(path) => {
+                format!("Prepend AsPath [{}]", path.iter().map(|x| x.0).join(", "))
+            }
             RouteMapSet::IgpCost(w) => format!("IgpCost = {w:.2}"),
             RouteMapSet::SetCommunity(c) => format!("Set community {c}"),
             RouteMapSet::DelCommunity(c) => format!("Remove community {c}"),
@@ -433,6 +497,38 @@ impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for ConfigExpr<P> {
             ConfigExpr::LoadBalancing { router } => {
                 format!("Load Balancing: {}", router.fmt(net))
             }
+            ConfigExpr::BgpMrai {
+                router,
+                neighbor,
+                mrai,
+            } => format!(
+                "BGP MRAI on {} for {}: {}s{}",
+                router.fmt(net),
+                neighbor.fmt(net),
+                mrai.interval,
+                if mrai.withdrawals {
+                    " (including withdrawals)"
+                } else {
+                    ""
+                }
+            ),
+            ConfigExpr::Aggregate {
+                router,
+                prefix,
+                summary_only,
+                as_set,
+            } => format!(
+                "BGP Aggregate on {}: {prefix}{}{}",
+                router.fmt(net),
+                if *summary_only { " summary-only" } else { "" },
+                if *as_set { " as-set" } else { "" }
+            ),
+            ConfigExpr::BgpNetwork { router, prefix } => {
+                format!("BGP Network on {}: {prefix}", router.fmt(net))
+            }
+            ConfigExpr::BgpRedistributeStatic { router } => {
+                format!("BGP Redistribute Static: {}", router.fmt(net))
+            }
         }
     }
 }
@@ -476,6 +572,18 @@ impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for ConfigExprKey<P> {
             ConfigExprKey::LoadBalancing { router } => {
                 format!("Load Balancing: {}", router.fmt(net))
             }
+            ConfigExprKey::BgpMrai { router, neighbor } => {
+                format!("BGP MRAI on {} for {}", router.fmt(net), neighbor.fmt(net))
+            }
+            ConfigExprKey::Aggregate { router, prefix } => {
+                format!("BGP Aggregate on {}: {prefix}", router.fmt(net))
+            }
+            ConfigExprKey::BgpNetwork { router, prefix } => {
+                format!("BGP Network on {}: {prefix}", router.fmt(net))
+            }
+            ConfigExprKey::BgpRedistributeStatic { router } => {
+                format!("BGP Redistribute Static: {}", router.fmt(net))
+            }
         }
     }
 }
@@ -280,13 +280,28 @@ impl<P: Prefix> ForwardingState<P> {
             .unwrap_or(&EMPTY_SET)
     }
 
-    /// Returns `true` if `router` is a terminal for `prefix`.
+    /// Returns `true` if `router` is a terminal for `prefix`, i.e., if the forwarding entry of
+    /// `router` (using longest prefix matching) points to the destination.
     pub fn is_terminal(&self, router: RouterId, prefix: P) -> bool {
-        self.get_terminals(prefix).contains(&router)
+        self.state
+            .get(&router)
+            .and_then(|fib| fib.get_lpm(&prefix))
+            .map(|(_, nhs)| nhs == &[*TO_DST])
+            .unwrap_or(false)
     }
 
-    /// Get the next hops of a router for a specific prefix. If that router does not know any route,
-    /// `Ok(None)` is returned.
+    /// Get the prefix of the forwarding entry that `router` uses to forward traffic towards
+    /// `prefix`, using longest prefix matching. This function returns `None` if `router` has no
+    /// matching forwarding entry.
+    pub fn get_lpm_prefix(&self, router: RouterId, prefix: P) -> Option<P> {
+        self.state
+            .get(&router)
+            .and_then(|fib| fib.get_lpm(&prefix))
+            .map(|(p, _)| *p)
+    }
+
+    /// Get the next hops of a router for a specific prefix (using longest prefix matching). If that
+    /// router does not know any route, `Ok(None)` is returned.
     ///
     /// **Warning** This function may return an empty slice for internal routers that black-hole
     /// prefixes, and for terminals. Use [`ForwardingState::is_black_hole`] to check if a router
@@ -295,8 +310,8 @@ impl<P: Prefix> ForwardingState<P> {
         let nh = self
             .state
             .get(&router)
-            .and_then(|fib| fib.get(&prefix))
-            .map(|p| p.as_slice())
+            .and_then(|fib| fib.get_lpm(&prefix))
+            .map(|(_, p)| p.as_slice())
             .unwrap_or_default();
         if nh == [*TO_DST] {
             &[]
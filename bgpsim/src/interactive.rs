@@ -194,6 +194,12 @@ impl<P: Prefix, Q: EventQueue<P>> InteractiveNetwork<P, Q> for Network<P, Q> {
                     // UndoAction::AddExternalRouter(id, router) => {
                     //     self.external_routers.insert(id, *router);
                     // }
+                    UndoAction::SetEventDrivenOspf(event_driven) => {
+                        self.event_driven_ospf = event_driven;
+                    }
+                    UndoAction::SetBgpSessionTimers(timers) => {
+                        self.bgp_session_timers = timers;
+                    }
                     UndoAction::UndoDevice(id) => {
                         self.get_device_mut(id).undo_event()?;
                     }
@@ -395,6 +401,8 @@ impl<'a, P: Prefix, Q> PartialClone<'a, P, Q> {
         new.stop_after = source.stop_after;
         new.skip_queue = source.skip_queue;
         new.verbose = source.verbose;
+        new.event_driven_ospf = source.event_driven_ospf;
+        new.bgp_session_timers = source.bgp_session_timers;
 
         // clone new.net if the configuration is different
         if !self.reuse_config {
@@ -432,16 +440,24 @@ impl<'a, P: Prefix, Q> PartialClone<'a, P, Q> {
 
             if !self.reuse_config {
                 r.do_load_balancing = r_source.do_load_balancing;
+                r.always_compare_med = r_source.always_compare_med;
+                r.compare_router_id = r_source.compare_router_id;
                 r.neighbors = r_source.neighbors.clone();
                 r.static_routes = r_source.static_routes.clone();
                 r.bgp_sessions = r_source.bgp_sessions.clone();
                 r.bgp_sessions = r_source.bgp_sessions.clone();
                 r.bgp_route_maps_in = r_source.bgp_route_maps_in.clone();
                 r.bgp_route_maps_out = r_source.bgp_route_maps_out.clone();
+                r.bgp_aggregates = r_source.bgp_aggregates.clone();
+                r.bgp_networks = r_source.bgp_networks.clone();
+                r.bgp_redistribute_static = r_source.bgp_redistribute_static;
+                r.bgp_mrai = r_source.bgp_mrai.clone();
+                r.bgp_session_timers = r_source.bgp_session_timers;
             }
 
             if !self.reuse_igp_state {
                 r.igp_table = r_source.igp_table.clone();
+                r.ospf_lsdb = r_source.ospf_lsdb.clone();
             }
 
             if !self.reuse_bgp_state {
@@ -449,6 +465,8 @@ impl<'a, P: Prefix, Q> PartialClone<'a, P, Q> {
                 r.bgp_rib = r_source.bgp_rib.clone();
                 r.bgp_rib_out = r_source.bgp_rib_out.clone();
                 r.bgp_known_prefixes = r_source.bgp_known_prefixes.clone();
+                r.bgp_mrai_timers = r_source.bgp_mrai_timers.clone();
+                r.bgp_session_state = r_source.bgp_session_state.clone();
             }
 
             #[cfg(feature = "undo")]
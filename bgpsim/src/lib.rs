@@ -37,6 +37,8 @@
 //! The network simulates IGP as an instantaneous computation using shortest path algorithms from
 //! Petgraph. BGP however is simulated using a message passing technique. The reason is that one can
 //! assume IGP converges much faster than BGP does.
+//! To observe transient IGP states (e.g., micro-loops), OSPF can be simulated using message
+//! passing as well (see [`network::Network::set_event_driven_ospf`]).
 //!
 //! The network can be configured using functions directly on the instance itself. However, it can
 //! also be configured using a configuration language. For that, make sure to `use` the trait
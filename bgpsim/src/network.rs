@@ -21,13 +21,16 @@
 //! network.
 
 use crate::{
-    bgp::{BgpSessionType, BgpState, BgpStateRef},
+    bgp::{
+        BgpAggregate, BgpSessionState, BgpSessionTimers, BgpSessionType, BgpState, BgpStateRef,
+        Mrai, Origin,
+    },
     config::{NetworkConfig, RouteMapEdit},
     event::{BasicEventQueue, Event, EventQueue},
     external_router::ExternalRouter,
     forwarding_state::ForwardingState,
     interactive::InteractiveNetwork,
-    ospf::{Ospf, OspfArea, OspfState},
+    ospf::{Lsa, Ospf, OspfArea, OspfState},
     route_map::{RouteMap, RouteMapDirection},
     router::{Router, StaticRoute},
     types::{
@@ -97,6 +100,10 @@ static DEFAULT_STOP_AFTER: usize = 1_000_000;
 pub struct Network<P: Prefix = SimplePrefix, Q = BasicEventQueue<SimplePrefix>> {
     pub(crate) net: IgpNetwork,
     pub(crate) ospf: Ospf,
+    #[serde(default)]
+    pub(crate) event_driven_ospf: bool,
+    #[serde(default)]
+    pub(crate) bgp_session_timers: Option<BgpSessionTimers>,
     pub(crate) routers: HashMap<RouterId, Router<P>>,
     pub(crate) external_routers: HashMap<RouterId, ExternalRouter<P>>,
     pub(crate) known_prefixes: P::Set,
@@ -116,6 +123,8 @@ impl<P: Prefix, Q: Clone> Clone for Network<P, Q> {
         Self {
             net: self.net.clone(),
             ospf: self.ospf.clone(),
+            event_driven_ospf: self.event_driven_ospf,
+            bgp_session_timers: self.bgp_session_timers,
             routers: self.routers.clone(),
             external_routers: self.external_routers.clone(),
             known_prefixes: self.known_prefixes.clone(),
@@ -141,6 +150,8 @@ impl<P: Prefix, Q> Network<P, Q> {
         Self {
             net: IgpNetwork::new(),
             ospf: Ospf::new(),
+            event_driven_ospf: false,
+            bgp_session_timers: None,
             routers: HashMap::new(),
             known_prefixes: Default::default(),
             external_routers: HashMap::new(),
@@ -159,7 +170,8 @@ impl<P: Prefix, Q> Network<P, Q> {
     ///
     /// *Undo Functionality*: this function will push a new undo event to the queue.
     pub fn add_router(&mut self, name: impl Into<String>) -> RouterId {
-        let new_router = Router::new(name.into(), self.net.add_node(()), AsId(65001));
+        let mut new_router = Router::new(name.into(), self.net.add_node(()), AsId(65001));
+        new_router.bgp_session_timers = self.bgp_session_timers;
         let router_id = new_router.router_id();
         self.routers.insert(router_id, new_router);
 
@@ -403,6 +415,50 @@ impl<P: Prefix, Q> Network<P, Q> {
 
         Ok(self.ospf.get_area(source, target))
     }
+
+    /// Returns `true` if OSPF is simulated in the event-driven mode (see
+    /// [`Network::set_event_driven_ospf`]).
+    pub fn get_event_driven_ospf(&self) -> bool {
+        self.event_driven_ospf
+    }
+
+    /// Returns the timers of the BGP session finite-state machine, or `None` if it is not simulated
+    /// (see [`Network::set_bgp_session_timers`]).
+    pub fn get_bgp_session_timers(&self) -> Option<BgpSessionTimers> {
+        self.bgp_session_timers
+    }
+
+    /// Returns the state of the BGP session from `router` to `neighbor`, or `None` if `router` has
+    /// no session configured with `neighbor`. If the BGP session finite-state machine is simulated
+    /// (see [`Network::set_bgp_session_timers`]), this is the state of that state machine.
+    /// Otherwise, the session is `Established` only if `neighbor` also has a session configured
+    /// with `router`, and if `neighbor` is reachable from `router`. In any other case, the session
+    /// is `Idle`.
+    pub fn get_bgp_session_state(
+        &self,
+        router: RouterId,
+        neighbor: RouterId,
+    ) -> Result<Option<BgpSessionState>, NetworkError> {
+        let r = self.get_device(router).internal_or_err()?;
+        if self.bgp_session_timers.is_some() || r.get_bgp_session_type(neighbor).is_none() {
+            return Ok(r.get_bgp_session_state(neighbor));
+        }
+        let configured = match self.get_device(neighbor) {
+            NetworkDevice::InternalRouter(n) => n.get_bgp_session_type(router).is_some(),
+            NetworkDevice::ExternalRouter(n) => n.get_bgp_sessions().contains(&router),
+            NetworkDevice::None(_) => false,
+        };
+        let reachable = r
+            .get_igp_fw_table()
+            .get(&neighbor)
+            .map(|(nhs, _)| !nhs.is_empty())
+            .unwrap_or(false);
+        Ok(Some(if configured && reachable {
+            BgpSessionState::Established
+        } else {
+            BgpSessionState::Idle
+        }))
+    }
 }
 
 impl<P: Prefix, Q: EventQueue<P>> Network<P, Q> {
@@ -422,6 +478,8 @@ impl<P: Prefix, Q: EventQueue<P>> Network<P, Q> {
         Ok(Network {
             net: self.net,
             ospf: self.ospf,
+            event_driven_ospf: self.event_driven_ospf,
+            bgp_session_timers: self.bgp_session_timers,
             routers: self.routers,
             external_routers: self.external_routers,
             known_prefixes: self.known_prefixes,
@@ -502,7 +560,11 @@ impl<P: Prefix, Q: EventQueue<P>> Network<P, Q> {
                 .ok_or(NetworkError::DeviceNotFound(source))?;
             if source_type.is_some() {
                 let events = r.establish_ebgp_session(target)?;
-                self.enqueue_events(events);
+                // with the BGP session finite-state machine, routes are only sent once the session
+                // is established.
+                if self.bgp_session_timers.is_none() {
+                    self.enqueue_events(events);
+                }
             } else {
                 r.close_ebgp_session(target)?;
             }
@@ -522,7 +584,11 @@ impl<P: Prefix, Q: EventQueue<P>> Network<P, Q> {
                 .ok_or(NetworkError::DeviceNotFound(target))?;
             if target_type.is_some() {
                 let events = r.establish_ebgp_session(source)?;
-                self.enqueue_events(events);
+                // with the BGP session finite-state machine, routes are only sent once the session
+                // is established.
+                if self.bgp_session_timers.is_none() {
+                    self.enqueue_events(events);
+                }
             } else {
                 r.close_ebgp_session(source)?;
             }
@@ -723,7 +789,8 @@ impl<P: Prefix, Q: EventQueue<P>> Network<P, Q> {
     }
 
     /// Update or remove a static route on some router. This function will not cuase any
-    /// convergence, as the change is local only. But its action can still be undone.
+    /// convergence, as the change is local only, unless the router redistributes static routes
+    /// into BGP (see [`Network::set_bgp_redistribute_static`]). But its action can still be undone.
     ///
     /// *Undo Functionality*: this function will push a new undo event to the queue.
     pub fn set_static_route(
@@ -734,14 +801,24 @@ impl<P: Prefix, Q: EventQueue<P>> Network<P, Q> {
     ) -> Result<Option<StaticRoute>, NetworkError> {
         // prepare undo stack
         #[cfg(feature = "undo")]
-        self.undo_stack
-            .push(vec![vec![UndoAction::UndoDevice(router)]]);
+        self.undo_stack.push(Vec::new());
 
-        Ok(self
+        let (old_val, events) = self
             .routers
             .get_mut(&router)
             .ok_or(NetworkError::DeviceNotFound(router))?
-            .set_static_route(prefix, route))
+            .set_static_route(prefix, route)?;
+
+        // add the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(vec![UndoAction::UndoDevice(router)]);
+
+        self.enqueue_events(events);
+        self.do_queue_maybe_skip()?;
+        Ok(old_val)
     }
 
     /// Enable or disable Load Balancing on a single device in the network.
@@ -767,6 +844,328 @@ impl<P: Prefix, Q: EventQueue<P>> Network<P, Q> {
         Ok(old_val)
     }
 
+    /// Enable or disable `always-compare-med` on a single device in the network, and let the
+    /// network converge. If enabled, the router compares the MED attribute of routes received from
+    /// different neighboring ASes. This function returns the old value.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub fn set_always_compare_med(
+        &mut self,
+        router: RouterId,
+        always_compare_med: bool,
+    ) -> Result<bool, NetworkError> {
+        // prepare undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack.push(Vec::new());
+
+        let (old_val, events) = self
+            .routers
+            .get_mut(&router)
+            .ok_or(NetworkError::DeviceNotFound(router))?
+            .set_always_compare_med(always_compare_med)?;
+
+        // add the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(vec![UndoAction::UndoDevice(router)]);
+
+        self.enqueue_events(events);
+        self.do_queue_maybe_skip()?;
+        Ok(old_val.unwrap_or_default())
+    }
+
+    /// Enable or disable `bestpath compare-routerid` on a single device in the network, and let the
+    /// network converge. If enabled, the router no longer prefers the oldest of two eBGP routes, but
+    /// compares their router-id instead. This function returns the old value.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub fn set_compare_router_id(
+        &mut self,
+        router: RouterId,
+        compare_router_id: bool,
+    ) -> Result<bool, NetworkError> {
+        // prepare undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack.push(Vec::new());
+
+        let (old_val, events) = self
+            .routers
+            .get_mut(&router)
+            .ok_or(NetworkError::DeviceNotFound(router))?
+            .set_compare_router_id(compare_router_id)?;
+
+        // add the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(vec![UndoAction::UndoDevice(router)]);
+
+        self.enqueue_events(events);
+        self.do_queue_maybe_skip()?;
+        Ok(old_val.unwrap_or_default())
+    }
+
+    /// Configure or remove a BGP aggregate (`aggregate-address`) for `prefix` on a single device in
+    /// the network, and let the network converge. The router originates the aggregate as long as it
+    /// knows at least one more-specific route of `prefix` (see [`BgpAggregate`]). This function
+    /// returns the old configuration.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub fn set_bgp_aggregate(
+        &mut self,
+        router: RouterId,
+        prefix: P,
+        aggregate: Option<BgpAggregate>,
+    ) -> Result<Option<BgpAggregate>, NetworkError> {
+        // prepare undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack.push(Vec::new());
+
+        let (old_val, events) = self
+            .routers
+            .get_mut(&router)
+            .ok_or(NetworkError::DeviceNotFound(router))?
+            .set_bgp_aggregate(prefix, aggregate)?;
+
+        // add the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(vec![UndoAction::UndoDevice(router)]);
+
+        self.enqueue_events(events);
+        self.do_queue_maybe_skip()?;
+        Ok(old_val)
+    }
+
+    /// Originate `prefix` in BGP on a single device in the network (`network`), or stop
+    /// originating it, and let the network converge. The route is originated with an empty AS path
+    /// and origin IGP. It is selected and advertised like any other route, i.e., it passes through
+    /// the decision process and the outgoing route-maps. The router is treated as the destination
+    /// of `prefix`, i.e., traffic towards `prefix` terminates there (unless the router has a
+    /// static route for `prefix`). This function returns whether the router has originated
+    /// `prefix` before.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub fn set_bgp_network(
+        &mut self,
+        router: RouterId,
+        prefix: P,
+        originate: bool,
+    ) -> Result<bool, NetworkError> {
+        // prepare undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack.push(Vec::new());
+
+        let (old_val, events) = self
+            .routers
+            .get_mut(&router)
+            .ok_or(NetworkError::DeviceNotFound(router))?
+            .set_bgp_network(prefix, originate)?;
+
+        // add the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(vec![UndoAction::UndoDevice(router)]);
+
+        self.enqueue_events(events);
+        self.do_queue_maybe_skip()?;
+        Ok(old_val.unwrap_or_default())
+    }
+
+    /// Enable or disable the redistribution of static routes into BGP on a single device in the
+    /// network (`redistribute static`), and let the network converge. Each static route of that
+    /// router is then originated with an empty AS path and origin incomplete. This function returns
+    /// the old value.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub fn set_bgp_redistribute_static(
+        &mut self,
+        router: RouterId,
+        redistribute: bool,
+    ) -> Result<bool, NetworkError> {
+        // prepare undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack.push(Vec::new());
+
+        let (old_val, events) = self
+            .routers
+            .get_mut(&router)
+            .ok_or(NetworkError::DeviceNotFound(router))?
+            .set_bgp_redistribute_static(redistribute)?;
+
+        // add the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(vec![UndoAction::UndoDevice(router)]);
+
+        self.enqueue_events(events);
+        self.do_queue_maybe_skip()?;
+        Ok(old_val.unwrap_or_default())
+    }
+
+    /// Set or remove the Minimum Route Advertisement Interval (MRAI) of the BGP session from
+    /// `router` to `neighbor`, and return the old value. While the MRAI timer of a session is
+    /// running, the router defers all advertisements to that neighbor until the timer expires (see
+    /// [`Mrai`]). Changing the MRAI does not trigger any events.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub fn set_bgp_mrai(
+        &mut self,
+        router: RouterId,
+        neighbor: RouterId,
+        mrai: Option<Mrai>,
+    ) -> Result<Option<Mrai>, NetworkError> {
+        // update the device
+        let old_val = self
+            .routers
+            .get_mut(&router)
+            .ok_or(NetworkError::DeviceNotFound(router))?
+            .set_bgp_mrai(neighbor, mrai);
+
+        // push undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .push(vec![vec![UndoAction::UndoDevice(router)]]);
+
+        Ok(old_val)
+    }
+
+    /// Enable or disable the event-driven OSPF mode, and return the old value. By default, OSPF is
+    /// not simulated, but the converged IGP state is computed instantly whenever the topology
+    /// changes. In the event-driven mode, routers flood their LSAs to their neighbors using
+    /// [`Event::Ospf`], and each router recomputes its IGP table (SPF computation) whenever it
+    /// learns about a new LSA. The forwarding state then reveals the transient states (e.g.,
+    /// micro-loops) during IGP reconvergence.
+    ///
+    /// Enabling the event-driven mode initializes the link-state database of each router with the
+    /// current topology. Disabling it clears the link-state databases, and recomputes the IGP
+    /// tables instantly.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub fn set_event_driven_ospf(&mut self, event_driven: bool) -> Result<bool, NetworkError> {
+        if self.event_driven_ospf == event_driven {
+            return Ok(event_driven);
+        }
+
+        // prepare undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .push(vec![vec![UndoAction::SetEventDrivenOspf(!event_driven)]]);
+
+        self.event_driven_ospf = event_driven;
+
+        let lsdb: HashMap<RouterId, Lsa> = if event_driven {
+            let external_routers = self.external_routers.keys().copied().collect();
+            self.routers
+                .keys()
+                .map(|r| Lsa {
+                    router: *r,
+                    seq: 0,
+                    links: self.ospf.lsa_links(&self.net, *r, &external_routers),
+                })
+                .filter(|lsa| !lsa.links.is_empty())
+                .map(|lsa| (lsa.router, lsa))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+
+        for r in self.routers.values_mut() {
+            r.set_ospf_lsdb(lsdb.clone());
+
+            // add the undo action
+            #[cfg(feature = "undo")]
+            self.undo_stack
+                .last_mut()
+                .unwrap()
+                .last_mut()
+                .unwrap()
+                .push(UndoAction::UndoDevice(r.router_id()));
+        }
+
+        if !event_driven {
+            self.write_igp_fw_tables()?;
+        }
+
+        Ok(!event_driven)
+    }
+
+    /// Enable (`Some`) or disable (`None`) the BGP session finite-state machine, and return the old
+    /// timers. By default, the state machine is not simulated, and BGP sessions are established as
+    /// soon as they are configured. Otherwise, a new session goes through `Connect` and `OpenSent`
+    /// before it is `Established` (see [`BgpSessionState`]), and routes are only exchanged over
+    /// established sessions. The timers determine how long it takes to establish a session, and how
+    /// long a session stays up after the neighbor became unreachable (see [`BgpSessionTimers`]).
+    ///
+    /// Enabling the state machine considers all configured sessions to be established. Disabling it
+    /// establishes all pending sessions immediately. This function will run the simulation
+    /// afterwards (unless the network is in manual simulation mode).
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub fn set_bgp_session_timers(
+        &mut self,
+        timers: Option<BgpSessionTimers>,
+    ) -> Result<Option<BgpSessionTimers>, NetworkError> {
+        if self.bgp_session_timers == timers {
+            return Ok(timers);
+        }
+
+        // prepare undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .push(vec![vec![UndoAction::SetBgpSessionTimers(
+                self.bgp_session_timers,
+            )]]);
+
+        let old_timers = std::mem::replace(&mut self.bgp_session_timers, timers);
+
+        let mut events = Vec::new();
+        let mut router_ids: Vec<RouterId> = self.routers.keys().copied().collect();
+        router_ids.sort();
+
+        // external routers only send their routes once the session is established.
+        if timers.is_none() {
+            for r in router_ids.iter().map(|r| &self.routers[r]) {
+                for (peer, _) in r.get_bgp_sessions().iter().filter(|(_, ty)| ty.is_ebgp()) {
+                    if r.get_bgp_session_state(*peer) == Some(BgpSessionState::Established) {
+                        continue;
+                    }
+                    if let Some(ext) = self.external_routers.get(peer) {
+                        events.extend(ext.update_events(r.router_id()));
+                    }
+                }
+            }
+        }
+
+        for id in router_ids {
+            let r = self.routers.get_mut(&id).unwrap();
+            events.extend(r.set_bgp_session_timers(timers));
+
+            // add the undo action
+            #[cfg(feature = "undo")]
+            self.undo_stack
+                .last_mut()
+                .unwrap()
+                .last_mut()
+                .unwrap()
+                .push(UndoAction::UndoDevice(id));
+        }
+
+        self.enqueue_events(events);
+        self.do_queue_maybe_skip()?;
+
+        Ok(old_timers)
+    }
+
     /// Advertise an external route and let the network converge, The source must be a `RouterId`
     /// of an `ExternalRouter`. If not, an error is returned. When advertising a route, all
     /// eBGP neighbors will receive an update with the new route. If a neighbor is added later
@@ -782,6 +1181,35 @@ impl<P: Prefix, Q: EventQueue<P>> Network<P, Q> {
         med: Option<u32>,
         community: C,
     ) -> Result<(), NetworkError>
+    where
+        A: IntoIterator,
+        A::Item: Into<AsId>,
+        C: IntoIterator<Item = u32>,
+    {
+        self.advertise_external_route_with_origin(
+            source,
+            prefix,
+            as_path,
+            med,
+            community,
+            Origin::Igp,
+        )
+    }
+
+    /// Advertise an external route with the given ORIGIN attribute and let the network converge.
+    /// See [`Network::advertise_external_route`], which advertises the route with origin
+    /// [`Origin::Igp`].
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub fn advertise_external_route_with_origin<A, C>(
+        &mut self,
+        source: RouterId,
+        prefix: impl Into<P>,
+        as_path: A,
+        med: Option<u32>,
+        community: C,
+        origin: Origin,
+    ) -> Result<(), NetworkError>
     where
         A: IntoIterator,
         A::Item: Into<AsId>,
@@ -803,7 +1231,7 @@ impl<P: Prefix, Q: EventQueue<P>> Network<P, Q> {
             .external_routers
             .get_mut(&source)
             .ok_or(NetworkError::DeviceNotFound(source))?
-            .advertise_prefix(prefix, as_path, med, community);
+            .advertise_prefix(prefix, as_path, med, community, origin);
 
         // add the undo action
         #[cfg(feature = "undo")]
@@ -941,6 +1369,11 @@ impl<P: Prefix, Q: EventQueue<P>> Network<P, Q> {
             self.set_bgp_session(router, neighbor, None)?;
         }
 
+        // flush the LSA of the removed router
+        for r in self.routers.values_mut() {
+            r.ospf_lsdb.remove(&router);
+        }
+
         // remove the node from the list
         if internal {
             self.routers.remove(&router);
@@ -1020,6 +1453,10 @@ impl<P: Prefix, Q: EventQueue<P>> Network<P, Q> {
     /// the BGP table. and run the algorithm. This will happen all at once, in a very unpredictable
     /// manner. If you want to do this more predictable, use `write_ibgp_fw_table`.
     ///
+    /// If OSPF is simulated in the event-driven mode, then each router only originates a new LSA
+    /// (if its links have changed). The IGP tables are then updated while simulating the flooding
+    /// of these LSAs.
+    ///
     /// The function returns Ok(true) if all events caused by the igp fw table write are handled
     /// correctly. Returns Ok(false) if the max number of iterations is exceeded, and returns an
     /// error if an event was not handled correctly.
@@ -1028,14 +1465,17 @@ impl<P: Prefix, Q: EventQueue<P>> Network<P, Q> {
     /// to the IGP state of devices will be added to the last event of the last action, while the
     /// queue updates will get their own event of the last action.
     pub(crate) fn write_igp_fw_tables(&mut self) -> Result<(), NetworkError> {
-        // compute the ospf state
-        let ospf_state = self
-            .ospf
-            .compute(&self.net, &self.external_routers.keys().copied().collect());
+        let external_routers = self.external_routers.keys().copied().collect();
+        // compute the ospf state, unless OSPF is event-driven
+        let ospf_state =
+            (!self.event_driven_ospf).then(|| self.ospf.compute(&self.net, &external_routers));
         // update igp table
         let mut events = vec![];
         for r in self.routers.values_mut() {
-            events.append(&mut r.write_igp_forwarding_table(&self.net, &ospf_state)?);
+            events.append(&mut match ospf_state.as_ref() {
+                Some(ospf_state) => r.write_igp_forwarding_table(&self.net, ospf_state)?,
+                None => r.originate_ospf_lsa(&self.net, &self.ospf, &external_routers)?,
+            });
 
             // add the undo action
             #[cfg(feature = "undo")]
@@ -1198,6 +1638,9 @@ pub(crate) enum UndoAction {
     // AddRouter(RouterId, Box<Router>),
     // /// Add an external router to the network
     // AddExternalRouter(RouterId, Box<ExternalRouter>),
+    /// Enable or disable the event-driven OSPF mode.
+    SetEventDrivenOspf(bool),
+    SetBgpSessionTimers(Option<BgpSessionTimers>),
     /// Perform the undo action on a device
     UndoDevice(RouterId),
 }
@@ -16,8 +16,11 @@
 // 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
 
 //! This module contains the OSPF implementation. It computes the converged OSPF state, which can be
-//! used by routers to write their IGP table. No message passing is simulated, but the final state
-//! is computed using shortest path algorithms.
+//! used by routers to write their IGP table. By default, no message passing is simulated, but the
+//! final state is computed using shortest path algorithms. In the event-driven mode (see
+//! [`Network::set_event_driven_ospf`](crate::network::Network::set_event_driven_ospf)), routers
+//! flood [`Lsa`]s using [`OspfEvent`]s, and each router computes its IGP table based on its own
+//! link-state database.
 
 use std::{
     collections::{HashMap, HashSet},
@@ -25,6 +28,7 @@ use std::{
 };
 
 use itertools::Itertools;
+use ordered_float::NotNan;
 use petgraph::{algo::floyd_warshall, visit::EdgeRef, Directed, Graph};
 use serde::{Deserialize, Serialize};
 use serde_with::{As, Same};
@@ -117,6 +121,34 @@ impl From<isize> for OspfArea {
     }
 }
 
+/// Link-State Advertisement (LSA) of a single router, describing all its links towards other
+/// internal routers. LSAs are only exchanged if OSPF is simulated in the event-driven mode.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Lsa {
+    /// Router that originated the LSA.
+    pub router: RouterId,
+    /// Sequence number of the LSA. An LSA with a larger sequence number is more recent.
+    pub seq: u32,
+    /// All links of `router` towards internal routers, together with their (directed) weight and
+    /// their OSPF area. Links with infinite weight are also included.
+    pub links: Vec<(RouterId, NotNan<LinkWeight>, OspfArea)>,
+}
+
+impl Lsa {
+    /// Get an iterator over all neighbors listed in the LSA.
+    pub fn neighbors(&self) -> impl Iterator<Item = RouterId> + '_ {
+        self.links.iter().map(|(r, _, _)| *r)
+    }
+}
+
+/// OSPF Events, exchanged between neighboring internal routers in the event-driven mode.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OspfEvent {
+    /// Link-State Update, carrying a set of LSAs. The receiver installs all LSAs that are more
+    /// recent than its own copy, and floods them to all other neighbors.
+    LsUpdate(Vec<Lsa>),
+}
+
 /// Data struture capturing the distributed OSPF state.
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub(crate) struct Ospf {
@@ -317,6 +349,65 @@ impl Ospf {
         }
     }
 
+    /// Get the links of `router` as described in its LSA, sorted by the neighbor. Links towards
+    /// external nodes are ignored.
+    pub(crate) fn lsa_links(
+        &self,
+        g: &IgpNetwork,
+        router: RouterId,
+        external_nodes: &HashSet<RouterId>,
+    ) -> Vec<(RouterId, NotNan<LinkWeight>, OspfArea)> {
+        g.edges(router)
+            .filter(|e| !external_nodes.contains(&e.target()))
+            .map(|e| {
+                (
+                    e.target(),
+                    NotNan::new(*e.weight()).unwrap(),
+                    self.get_area(router, e.target()),
+                )
+            })
+            .sorted()
+            .collect()
+    }
+
+    /// Compute the `OspfState` as seen by a router with the link-state database `lsdb`. The
+    /// topology is reconstructed from the LSAs, where a link from `a` to `b` is only considered if
+    /// the LSA of `b` also lists `a` (two-way connectivity check). Then, the `OspfState` is
+    /// computed as in [`Ospf::compute`].
+    pub(crate) fn compute_from_lsdb(lsdb: &HashMap<RouterId, Lsa>) -> OspfState {
+        let mut g = IgpNetwork::default();
+        let mut ospf = Ospf::new();
+
+        // add all nodes, such that the node indices match the router ids.
+        let max_node_index = lsdb
+            .values()
+            .flat_map(|lsa| once(lsa.router).chain(lsa.neighbors()))
+            .map(|r| r.index() + 1)
+            .max()
+            .unwrap_or_default();
+        for _ in 0..max_node_index {
+            g.add_node(());
+        }
+
+        // add all links that pass the two-way connectivity check
+        for lsa in lsdb.values() {
+            for (neighbor, weight, area) in lsa.links.iter() {
+                if lsdb
+                    .get(neighbor)
+                    .map(|n| n.neighbors().contains(&lsa.router))
+                    .unwrap_or(false)
+                {
+                    g.add_edge(lsa.router, *neighbor, **weight);
+                    if !area.is_backbone() {
+                        ospf.set_area(lsa.router, *neighbor, *area);
+                    }
+                }
+            }
+        }
+
+        ospf.compute(&g, &HashSet::new())
+    }
+
     /// Return the bidirectional key of a pair of routers
     #[inline]
     fn key(a: RouterId, b: RouterId) -> (RouterId, RouterId) {
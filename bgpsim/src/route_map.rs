@@ -20,11 +20,13 @@
 //! This module contains the necessary structures to build route maps for internal BGP routers.
 
 use crate::{
-    bgp::BgpRibEntry,
+    bgp::{BgpRibEntry, Origin},
     types::{AsId, LinkWeight, Prefix, PrefixSet, RouterId},
 };
 
+use itertools::Itertools;
 use ordered_float::NotNan;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::{cmp::Ordering, fmt};
 
@@ -288,6 +290,14 @@ impl<P: Prefix> RouteMapBuilder<P> {
         self
     }
 
+    /// Add a match condition to the Route-Map, matching the as path against a regular expression
+    /// (see [`AsPathRegex`]).
+    pub fn match_as_path_regex(&mut self, regex: AsPathRegex) -> &mut Self {
+        self.conds
+            .push(RouteMapMatch::AsPath(RouteMapMatchAsPath::Regex(regex)));
+        self
+    }
+
     /// Add a match condition to the Route-Map, matching on the next hop
     pub fn match_next_hop(&mut self, next_hop: RouterId) -> &mut Self {
         self.conds.push(RouteMapMatch::NextHop(next_hop));
@@ -306,6 +316,12 @@ impl<P: Prefix> RouteMapBuilder<P> {
         self
     }
 
+    /// Add a match condition to the Route-Map, matching on the ORIGIN attribute
+    pub fn match_origin(&mut self, origin: Origin) -> &mut Self {
+        self.conds.push(RouteMapMatch::Origin(origin));
+        self
+    }
+
     /// Add a set expression to the Route-Map.
     pub fn add_set(&mut self, set: RouteMapSet) -> &mut Self {
         self.set.push(set);
@@ -355,6 +371,24 @@ impl<P: Prefix> RouteMapBuilder<P> {
         self
     }
 
+    /// Add a set expression, overwriting the ORIGIN attribute
+    pub fn set_origin(&mut self, origin: Origin) -> &mut Self {
+        self.set.push(RouteMapSet::Origin(origin));
+        self
+    }
+
+    /// Add a set expression, prepending the given ASes to the AS path. The first element of `path`
+    /// will be the first AS in the resulting path.
+    pub fn prepend_as_path<As: Into<AsId>>(
+        &mut self,
+        path: impl IntoIterator<Item = As>,
+    ) -> &mut Self {
+        self.set.push(RouteMapSet::PrependAsPath(
+            path.into_iter().map(|x| x.into()).collect(),
+        ));
+        self
+    }
+
     /// Add a set expression, overwriting the Igp Cost to reach the next-hop
     pub fn set_igp_cost(&mut self, cost: LinkWeight) -> &mut Self {
         self.set.push(RouteMapSet::IgpCost(cost));
@@ -487,6 +521,8 @@ pub enum RouteMapMatch<P: Prefix> {
     Community(u32),
     /// Match on the absence of a given community.
     DenyCommunity(u32),
+    /// Matches on the ORIGIN attribute
+    Origin(Origin),
 }
 
 impl<P: Prefix> RouteMapMatch<P> {
@@ -498,6 +534,7 @@ impl<P: Prefix> RouteMapMatch<P> {
             Self::NextHop(nh) => entry.route.next_hop == *nh,
             Self::Community(com) => entry.route.community.contains(com),
             Self::DenyCommunity(com) => !entry.route.community.contains(com),
+            Self::Origin(origin) => entry.route.origin == *origin,
         }
     }
 }
@@ -549,6 +586,8 @@ pub enum RouteMapMatchAsPath {
     Contains(AsId),
     /// Match on the length of the As Path
     Length(RouteMapMatchClause<usize>),
+    /// Match the As Path against a regular expression
+    Regex(AsPathRegex),
 }
 
 impl RouteMapMatchAsPath {
@@ -557,6 +596,7 @@ impl RouteMapMatchAsPath {
         match self {
             Self::Contains(as_id) => path.contains(as_id),
             Self::Length(clause) => clause.matches(&path.len()),
+            Self::Regex(regex) => regex.matches(path),
         }
     }
 }
@@ -568,10 +608,82 @@ impl fmt::Display for RouteMapMatchAsPath {
                 f.write_fmt(format_args!("{} in AsPath", as_id.0))
             }
             RouteMapMatchAsPath::Length(c) => f.write_fmt(format_args!("len(AsPath) {c}")),
+            RouteMapMatchAsPath::Regex(r) => f.write_fmt(format_args!("AsPath =~ {r}")),
         }
     }
 }
 
+/// Regular expression on the As Path, using the syntax of Cisco and FRR as-path access-lists. The
+/// As Path is written as a space-separated list of AS numbers (e.g., `"10 20 30"`), with the
+/// neighboring AS first. The expression uses the syntax of the [`regex`] crate, except that `_`
+/// matches the beginning or the end of the path, or any delimiter between two AS numbers.
+///
+/// ```
+/// # use bgpsim::route_map::AsPathRegex;
+/// let regex = AsPathRegex::new("_20_").unwrap();
+/// assert!(regex.matches(&[10.into(), 20.into(), 30.into()]));
+/// assert!(!regex.matches(&[10.into(), 200.into()]));
+///
+/// let regex = AsPathRegex::new("^10_").unwrap();
+/// assert!(regex.matches(&[10.into(), 20.into()]));
+/// assert!(!regex.matches(&[20.into(), 10.into()]));
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct AsPathRegex {
+    pattern: String,
+    regex: Regex,
+}
+
+impl AsPathRegex {
+    /// Compile a new regular expression. This function returns an error if the expression is
+    /// invalid.
+    pub fn new(pattern: impl Into<String>) -> Result<Self, regex::Error> {
+        let pattern = pattern.into();
+        let regex = Regex::new(&pattern.replace('_', "(?:^|$|[ ,{}()])"))?;
+        Ok(Self { pattern, regex })
+    }
+
+    /// Get the expression as it was written.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns true if the As Path matches the expression.
+    pub fn matches(&self, path: &[AsId]) -> bool {
+        self.regex
+            .is_match(&path.iter().map(|as_id| as_id.0).join(" "))
+    }
+}
+
+impl PartialEq for AsPathRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Eq for AsPathRegex {}
+
+impl fmt::Display for AsPathRegex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+impl TryFrom<String> for AsPathRegex {
+    type Error = regex::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<AsPathRegex> for String {
+    fn from(value: AsPathRegex) -> Self {
+        value.pattern
+    }
+}
+
 /// Set action, if a route map matches
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum RouteMapSet {
@@ -584,6 +696,10 @@ pub enum RouteMapSet {
     LocalPref(Option<u32>),
     /// overwrite the MED attribute (None means reset to 0)
     Med(Option<u32>),
+    /// overwrite the ORIGIN attribute
+    Origin(Origin),
+    /// Prepend the given ASes to the AS path (the first element will be the first AS of the path)
+    PrependAsPath(Vec<AsId>),
     /// overwrite the distance attribute (IGP weight). This does not affect peers.
     IgpCost(LinkWeight),
     /// Set the community value
@@ -604,6 +720,10 @@ impl RouteMapSet {
             Self::Weight(w) => entry.weight = w.unwrap_or(100),
             Self::LocalPref(lp) => entry.route.local_pref = Some(lp.unwrap_or(100)),
             Self::Med(med) => entry.route.med = Some(med.unwrap_or(0)),
+            Self::Origin(origin) => entry.route.origin = *origin,
+            Self::PrependAsPath(path) => {
+                entry.route.as_path.splice(0..0, path.iter().copied());
+            }
             Self::IgpCost(w) => entry.igp_cost = Some(NotNan::new(*w).unwrap()),
             Self::SetCommunity(c) => {
                 entry.route.community.insert(*c);
@@ -18,12 +18,16 @@
 //! Module defining an internal router with BGP functionality.
 
 use crate::{
-    bgp::{BgpEvent, BgpRibEntry, BgpRoute, BgpSessionType},
+    bgp::{
+        BgpAggregate, BgpEvent, BgpRibEntry, BgpRoute, BgpSessionState, BgpSessionTimers,
+        BgpSessionType, Mrai, Origin,
+    },
     config::RouteMapEdit,
-    event::{Event, EventOutcome},
+    event::{Event, EventOutcome, TimerEvent},
     formatter::NetworkFormatter,
+    forwarding_state::TO_DST,
     network::Network,
-    ospf::OspfState,
+    ospf::{Lsa, Ospf, OspfEvent, OspfState},
     route_map::{
         RouteMap,
         RouteMapDirection::{self, Incoming, Outgoing},
@@ -40,6 +44,7 @@ use ordered_float::NotNan;
 use petgraph::visit::EdgeRef;
 use serde::{Deserialize, Serialize};
 use std::{
+    cmp::Ordering,
     collections::{HashMap, HashSet},
     fmt::Write,
     mem::swap,
@@ -58,6 +63,10 @@ pub struct Router<P: Prefix> {
     pub(crate) neighbors: HashMap<RouterId, LinkWeight>,
     /// forwarding table for IGP messages
     pub igp_table: HashMap<RouterId, (Vec<RouterId>, LinkWeight)>,
+    /// OSPF link-state database, storing the most recent LSA of each internal router (including
+    /// this one). It is only maintained if OSPF is simulated in the event-driven mode, and empty
+    /// otherwise.
+    pub(crate) ospf_lsdb: HashMap<RouterId, Lsa>,
     /// Static Routes for Prefixes
     pub(crate) static_routes: P::Map<StaticRoute>,
     /// hashmap of all bgp sessions
@@ -78,11 +87,42 @@ pub struct Router<P: Prefix> {
     pub(crate) bgp_route_maps_in: HashMap<RouterId, Vec<RouteMap<P>>>,
     /// BGP Route-Maps for Output
     pub(crate) bgp_route_maps_out: HashMap<RouterId, Vec<RouteMap<P>>>,
+    /// BGP aggregates (`aggregate-address`), mapping the aggregate prefix to its configuration.
+    /// The aggregate route itself is stored in `bgp_rib_in` as a route received from the router
+    /// itself.
+    pub(crate) bgp_aggregates: P::Map<BgpAggregate>,
+    /// Prefixes originated by the router itself (`network`). Like aggregates, the originated
+    /// routes are stored in `bgp_rib_in` as routes received from the router itself. Traffic
+    /// towards these prefixes terminates at the router.
+    pub(crate) bgp_networks: P::Set,
+    /// Flag to tell if static routes are redistributed into BGP (`redistribute static`).
+    pub(crate) bgp_redistribute_static: bool,
+    /// Minimum Route Advertisement Interval (MRAI) of BGP sessions.
+    pub(crate) bgp_mrai: HashMap<RouterId, Mrai>,
+    /// Running MRAI timers. A neighbor is present in this map as long as the MRAI timer of that
+    /// session is running. The set contains all prefixes whose advertisement was deferred until
+    /// the timer expires.
+    pub(crate) bgp_mrai_timers: HashMap<RouterId, P::Set>,
+    /// Timers of the BGP session finite-state machine. If `None`, the state machine is not
+    /// simulated, and all configured sessions are established immediately.
+    pub(crate) bgp_session_timers: Option<BgpSessionTimers>,
+    /// State of the configured BGP sessions. It is only maintained if the BGP session finite-state
+    /// machine is simulated, and empty otherwise.
+    pub(crate) bgp_session_state: HashMap<RouterId, BgpSessionState>,
     /// Flag to tell if load balancing is enabled. If load balancing is enabled, then the router
     /// will load balance packets towards a destination if multiple paths exist with equal
     /// cost. load balancing will only work within OSPF. BGP Additional Paths is not yet
     /// implemented.
     pub(crate) do_load_balancing: bool,
+    /// Flag to tell if the MED attribute is compared between routes from different neighboring
+    /// ASes (`bgp always-compare-med`).
+    pub(crate) always_compare_med: bool,
+    /// Flag to tell if the oldest of two eBGP routes is no longer preferred, and the router-id is
+    /// compared instead (`bgp bestpath compare-routerid`).
+    pub(crate) compare_router_id: bool,
+    /// Number of BGP updates received so far. It is used to remember the order in which routes
+    /// were received (see [`BgpRibEntry::received`]).
+    pub(crate) bgp_update_count: u64,
     /// Stack to undo action from every event. Each processed event will push a new vector onto the
     /// stack, containing all actions to perform in order to undo the event.
     #[cfg(feature = "undo")]
@@ -96,6 +136,7 @@ impl<P: Prefix> Clone for Router<P> {
             router_id: self.router_id,
             as_id: self.as_id,
             igp_table: self.igp_table.clone(),
+            ospf_lsdb: self.ospf_lsdb.clone(),
             neighbors: self.neighbors.clone(),
             static_routes: self.static_routes.clone(),
             bgp_sessions: self.bgp_sessions.clone(),
@@ -105,7 +146,17 @@ impl<P: Prefix> Clone for Router<P> {
             bgp_known_prefixes: self.bgp_known_prefixes.clone(),
             bgp_route_maps_in: self.bgp_route_maps_in.clone(),
             bgp_route_maps_out: self.bgp_route_maps_out.clone(),
+            bgp_aggregates: self.bgp_aggregates.clone(),
+            bgp_networks: self.bgp_networks.clone(),
+            bgp_redistribute_static: self.bgp_redistribute_static,
+            bgp_mrai: self.bgp_mrai.clone(),
+            bgp_mrai_timers: self.bgp_mrai_timers.clone(),
+            bgp_session_timers: self.bgp_session_timers,
+            bgp_session_state: self.bgp_session_state.clone(),
             do_load_balancing: self.do_load_balancing,
+            always_compare_med: self.always_compare_med,
+            compare_router_id: self.compare_router_id,
+            bgp_update_count: self.bgp_update_count,
             #[cfg(feature = "undo")]
             undo_stack: self.undo_stack.clone(),
         }
@@ -119,6 +170,7 @@ impl<P: Prefix> Router<P> {
             router_id,
             as_id,
             igp_table: HashMap::new(),
+            ospf_lsdb: HashMap::new(),
             neighbors: HashMap::new(),
             static_routes: Default::default(),
             bgp_sessions: HashMap::new(),
@@ -128,7 +180,17 @@ impl<P: Prefix> Router<P> {
             bgp_known_prefixes: Default::default(),
             bgp_route_maps_in: HashMap::new(),
             bgp_route_maps_out: HashMap::new(),
+            bgp_aggregates: Default::default(),
+            bgp_networks: Default::default(),
+            bgp_redistribute_static: false,
+            bgp_mrai: HashMap::new(),
+            bgp_mrai_timers: HashMap::new(),
+            bgp_session_timers: None,
+            bgp_session_state: HashMap::new(),
             do_load_balancing: false,
+            always_compare_med: false,
+            compare_router_id: false,
+            bgp_update_count: 0,
             #[cfg(feature = "undo")]
             undo_stack: Vec::new(),
         }
@@ -217,12 +279,17 @@ impl<P: Prefix> Router<P> {
                 // first, check if the event was received from a bgp peer
                 if !self.bgp_sessions.contains_key(&from) {
                     warn!("Received a bgp event form a non-neighbor! Ignore event!");
-                    let prefix = bgp_event.prefix();
-                    let old = self.get_next_hop(prefix);
-                    return Ok((StepUpdate::new(prefix, old.clone(), old), vec![]));
+                    return Ok((self.unchanged_step(bgp_event.prefix()), vec![]));
                 }
                 // phase 1 of BGP protocol
                 let (prefix, new) = match bgp_event {
+                    BgpEvent::Open => {
+                        return Ok((StepUpdate::default(), self.receive_bgp_open(from)?));
+                    }
+                    _ if !self.is_bgp_session_established(from) => {
+                        warn!("Received a bgp event on a session that is not established! Ignore event!");
+                        return Ok((self.unchanged_step(bgp_event.prefix()), vec![]));
+                    }
                     BgpEvent::Update(route) => match self.insert_bgp_route(route, from)? {
                         (p, true) => (p, true),
                         (p, false) => {
@@ -254,10 +321,9 @@ impl<P: Prefix> Router<P> {
                 if changed {
                     let new = self.get_next_hop(prefix);
                     // phase 3
-                    Ok((
-                        StepUpdate::new(prefix, old, new),
-                        self.run_bgp_route_dissemination_for_prefix(prefix)?,
-                    ))
+                    let mut events = self.run_bgp_route_dissemination_for_prefix(prefix)?;
+                    events.append(&mut self.update_bgp_aggregates_for_prefix(prefix)?);
+                    Ok((StepUpdate::new(prefix, old, new), events))
                 } else {
                     Ok((StepUpdate::new(prefix, old.clone(), old), Vec::new()))
                 }
@@ -266,9 +332,64 @@ impl<P: Prefix> Router<P> {
                 error!(
                     "Recenved a BGP event that is not targeted at this router! Ignore the event!"
                 );
-                let prefix = bgp_event.prefix();
-                let old = self.get_next_hop(prefix);
-                Ok((StepUpdate::new(prefix, old.clone(), old), vec![]))
+                Ok((self.unchanged_step(bgp_event.prefix()), vec![]))
+            }
+            Event::Ospf(_, from, to, OspfEvent::LsUpdate(lsas)) if to == self.router_id => {
+                // an empty link-state database means that OSPF is not event-driven.
+                if self.ospf_lsdb.is_empty() {
+                    warn!("Received an OSPF event while OSPF is not event-driven! Ignore event!");
+                    return Ok((StepUpdate::default(), vec![]));
+                }
+                // install all LSAs that are more recent. Ignore LSAs originated by self.
+                let router_id = self.router_id;
+                let new_lsas: Vec<Lsa> = lsas
+                    .into_iter()
+                    .filter(|lsa| lsa.router != router_id)
+                    .filter(|lsa| self.install_ospf_lsa(lsa.clone()))
+                    .collect();
+                if new_lsas.is_empty() {
+                    return Ok((StepUpdate::default(), vec![]));
+                }
+                // flood the new LSAs to all other neighbors
+                let mut events: Vec<Event<P, T>> = self
+                    .ospf_lsdb
+                    .get(&self.router_id)
+                    .into_iter()
+                    .flat_map(|lsa| lsa.neighbors())
+                    .filter(|n| *n != from)
+                    .map(|n| {
+                        Event::Ospf(
+                            T::default(),
+                            self.router_id,
+                            n,
+                            OspfEvent::LsUpdate(new_lsas.clone()),
+                        )
+                    })
+                    .collect();
+                // run the SPF computation
+                events.append(&mut self.run_ospf_spf(self.neighbors.clone())?);
+                Ok((StepUpdate::default(), events))
+            }
+            Event::Ospf(_, _, _, _) => {
+                error!(
+                    "Recenved an OSPF event that is not targeted at this router! Ignore the event!"
+                );
+                Ok((StepUpdate::default(), vec![]))
+            }
+            Event::Timer(_, router, _, TimerEvent::BgpMrai(peer)) if router == self.router_id => {
+                Ok((StepUpdate::default(), self.expire_bgp_mrai_timer(peer)))
+            }
+            Event::Timer(_, router, _, TimerEvent::BgpConnect(peer))
+                if router == self.router_id =>
+            {
+                Ok((StepUpdate::default(), self.expire_bgp_connect_timer(peer)))
+            }
+            Event::Timer(_, router, _, TimerEvent::BgpHold(peer)) if router == self.router_id => {
+                Ok((StepUpdate::default(), self.expire_bgp_hold_timer(peer)?))
+            }
+            Event::Timer(_, _, _, _) => {
+                error!("Recenved a timer that does not belong to this router! Ignore the event!");
+                Ok((StepUpdate::default(), vec![]))
             }
         }
     }
@@ -280,7 +401,9 @@ impl<P: Prefix> Router<P> {
     #[cfg_attr(docsrs, doc(cfg(feature = "undo")))]
     pub(crate) fn undo_event(&mut self) {
         if let Some(actions) = self.undo_stack.pop() {
-            for action in actions {
+            // undo the actions in reverse order, such that an entry that was modified multiple
+            // times during the same event is restored to its original value.
+            for action in actions.into_iter().rev() {
                 match action {
                     UndoAction::BgpRibIn(prefix, peer, Some(entry)) => {
                         self.bgp_rib_in
@@ -352,10 +475,65 @@ impl<P: Prefix> Router<P> {
                     UndoAction::BgpSession(peer, None) => {
                         self.bgp_sessions.remove(&peer);
                     }
+                    UndoAction::BgpAggregate(prefix, Some(aggregate)) => {
+                        self.bgp_aggregates.insert(prefix, aggregate);
+                    }
+                    UndoAction::BgpAggregate(prefix, None) => {
+                        self.bgp_aggregates.remove(&prefix);
+                    }
+                    UndoAction::BgpNetwork(prefix, true) => {
+                        self.bgp_networks.insert(prefix);
+                    }
+                    UndoAction::BgpNetwork(prefix, false) => {
+                        self.bgp_networks.remove(&prefix);
+                    }
+                    UndoAction::BgpRedistributeStatic(value) => {
+                        self.bgp_redistribute_static = value
+                    }
+                    UndoAction::BgpMrai(peer, Some(mrai)) => {
+                        self.bgp_mrai.insert(peer, mrai);
+                    }
+                    UndoAction::BgpMrai(peer, None) => {
+                        self.bgp_mrai.remove(&peer);
+                    }
+                    UndoAction::BgpSessionTimers(timers) => {
+                        self.bgp_session_timers = timers;
+                    }
+                    UndoAction::BgpSessionState(peer, Some(state)) => {
+                        self.bgp_session_state.insert(peer, state);
+                    }
+                    UndoAction::BgpSessionState(peer, None) => {
+                        self.bgp_session_state.remove(&peer);
+                    }
+                    UndoAction::BgpMraiTimer(peer, Some(pending)) => {
+                        self.bgp_mrai_timers.insert(peer, pending);
+                    }
+                    UndoAction::BgpMraiTimer(peer, None) => {
+                        self.bgp_mrai_timers.remove(&peer);
+                    }
+                    UndoAction::BgpMraiPending(peer, prefix, true) => {
+                        self.bgp_mrai_timers
+                            .get_mut(&peer)
+                            .map(|pending| pending.insert(prefix));
+                    }
+                    UndoAction::BgpMraiPending(peer, prefix, false) => {
+                        self.bgp_mrai_timers
+                            .get_mut(&peer)
+                            .map(|pending| pending.remove(&prefix));
+                    }
                     UndoAction::IgpForwardingTable(t, n) => {
                         self.igp_table = t;
                         self.neighbors = n;
                     }
+                    UndoAction::OspfLsa(router, Some(lsa)) => {
+                        self.ospf_lsdb.insert(router, lsa);
+                    }
+                    UndoAction::OspfLsa(router, None) => {
+                        self.ospf_lsdb.remove(&router);
+                    }
+                    UndoAction::OspfLsdb(lsdb) => {
+                        self.ospf_lsdb = lsdb.into_iter().map(|lsa| (lsa.router, lsa)).collect();
+                    }
                     UndoAction::DelKnownPrefix(p) => {
                         self.bgp_known_prefixes.remove(&p);
                     }
@@ -366,6 +544,9 @@ impl<P: Prefix> Router<P> {
                         self.static_routes.remove(&prefix);
                     }
                     UndoAction::SetLoadBalancing(value) => self.do_load_balancing = value,
+                    UndoAction::SetAlwaysCompareMed(value) => self.always_compare_med = value,
+                    UndoAction::SetCompareRouterId(value) => self.compare_router_id = value,
+                    UndoAction::BgpUpdateCount(value) => self.bgp_update_count = value,
                 }
             }
         }
@@ -393,7 +574,11 @@ impl<P: Prefix> Router<P> {
         result
     }
 
-    /// Get the IGP next hop for a prefix. Prefixes are matched using longest prefix match.
+    /// Get the IGP next hop for a prefix. Prefixes are matched using longest prefix match. For
+    /// prefixes originated by the router itself (`network`), the router is the destination of the
+    /// traffic (unless it has a static route for that prefix). Then, the returned next hop is the
+    /// same marker that the [`ForwardingState`](crate::forwarding_state::ForwardingState) uses
+    /// for external routers.
     pub fn get_next_hop(&self, prefix: P) -> Vec<RouterId> {
         fn sr_next_hops<P: Prefix>(r: &Router<P>, target: &StaticRoute) -> Vec<RouterId> {
             match target {
@@ -412,17 +597,30 @@ impl<P: Prefix> Router<P> {
             }
         }
 
+        fn bgp_next_hops<P: Prefix>(
+            r: &Router<P>,
+            prefix: &P,
+            entry: &BgpRibEntry<P>,
+        ) -> Vec<RouterId> {
+            if entry.from_id == r.router_id && r.bgp_networks.contains(prefix) {
+                // the router itself is the destination of prefixes that it originates.
+                vec![*TO_DST]
+            } else {
+                r.igp_table[&entry.route.next_hop].0.clone()
+            }
+        }
+
         // first, check the static routes
         let sr = self.static_routes.get_lpm(&prefix);
         let bgp = self.bgp_rib.get_lpm(&prefix);
         let next_hops = match (sr, bgp) {
             (None, None) => vec![],
             (Some((_, target)), None) => sr_next_hops(self, target),
-            (None, Some((_, entry))) => self.igp_table[&entry.route.next_hop].0.clone(),
+            (None, Some((nh_bgp, entry))) => bgp_next_hops(self, nh_bgp, entry),
             (Some((nh_sr, target)), Some((nh_bgp, _))) if nh_bgp.contains(nh_sr) => {
                 sr_next_hops(self, target)
             }
-            (Some(_), Some((_, entry))) => self.igp_table[&entry.route.next_hop].0.clone(),
+            (Some(_), Some((nh_bgp, entry))) => bgp_next_hops(self, nh_bgp, entry),
         };
 
         if self.do_load_balancing {
@@ -470,28 +668,247 @@ impl<P: Prefix> Router<P> {
         do_load_balancing
     }
 
+    /// Check if the MED attribute is compared between routes from different neighboring ASes.
+    pub fn get_always_compare_med(&self) -> bool {
+        self.always_compare_med
+    }
+
+    /// Compare the MED attribute of all routes, even if they are received from different
+    /// neighboring ASes (`bgp always-compare-med`). By default, the MED is only compared between
+    /// routes from the same neighboring AS. This function returns the old value, along with all
+    /// events triggered by re-running the BGP decision process.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub(crate) fn set_always_compare_med<T: Default>(
+        &mut self,
+        mut always_compare_med: bool,
+    ) -> UpdateOutcome<bool, P, T> {
+        // prepare the undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack.push(Vec::new());
+
+        std::mem::swap(&mut self.always_compare_med, &mut always_compare_med);
+
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(UndoAction::SetAlwaysCompareMed(always_compare_med));
+
+        Ok((Some(always_compare_med), self.update_bgp_tables(false)?))
+    }
+
+    /// Check if the router-id is compared instead of preferring the oldest of two eBGP routes.
+    pub fn get_compare_router_id(&self) -> bool {
+        self.compare_router_id
+    }
+
+    /// Compare the router-id of two eBGP routes instead of preferring the one that was received
+    /// first (`bgp bestpath compare-routerid`). By default, the oldest route is preferred, which
+    /// makes the outcome depend on the order in which routes were received. This function returns
+    /// the old value, along with all events triggered by re-running the BGP decision process.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub(crate) fn set_compare_router_id<T: Default>(
+        &mut self,
+        mut compare_router_id: bool,
+    ) -> UpdateOutcome<bool, P, T> {
+        // prepare the undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack.push(Vec::new());
+
+        std::mem::swap(&mut self.compare_router_id, &mut compare_router_id);
+
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(UndoAction::SetCompareRouterId(compare_router_id));
+
+        Ok((Some(compare_router_id), self.update_bgp_tables(false)?))
+    }
+
+    /// Get the Minimum Route Advertisement Interval (MRAI) of the BGP session with `neighbor`.
+    pub fn get_bgp_mrai(&self, neighbor: RouterId) -> Option<Mrai> {
+        self.bgp_mrai.get(&neighbor).copied()
+    }
+
+    /// Get the Minimum Route Advertisement Interval (MRAI) of all BGP sessions that have one.
+    pub fn get_bgp_mrais(&self) -> &HashMap<RouterId, Mrai> {
+        &self.bgp_mrai
+    }
+
+    /// Set or remove the Minimum Route Advertisement Interval (MRAI) of the BGP session with
+    /// `neighbor`, and return the old value. A timer that is currently running is not affected;
+    /// the new value is used when the timer is started the next time.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub(crate) fn set_bgp_mrai(&mut self, neighbor: RouterId, mrai: Option<Mrai>) -> Option<Mrai> {
+        let old_mrai = if let Some(mrai) = mrai {
+            self.bgp_mrai.insert(neighbor, mrai)
+        } else {
+            self.bgp_mrai.remove(&neighbor)
+        };
+
+        // prepare the undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .push(vec![UndoAction::BgpMrai(neighbor, old_mrai)]);
+
+        old_mrai
+    }
+
     /// Change or remove a static route from the router. This function returns the old static route
-    /// (if it exists).
+    /// (if it exists). If static routes are redistributed into BGP, this function also returns all
+    /// events triggered by originating (or withdrawing) the route.
     ///
     /// *Undo Functionality*: this function will push a new undo event to the queue.
-    #[allow(clippy::let_and_return)]
-    pub(crate) fn set_static_route(
+    pub(crate) fn set_static_route<T: Default>(
         &mut self,
         prefix: P,
         route: Option<StaticRoute>,
-    ) -> Option<StaticRoute> {
+    ) -> UpdateOutcome<StaticRoute, P, T> {
+        // prepare the undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack.push(Vec::new());
+
         let old_route = if let Some(route) = route {
             self.static_routes.insert(prefix, route)
         } else {
             self.static_routes.remove(&prefix)
         };
 
+        // add the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(UndoAction::StaticRoute(prefix, old_route));
+
+        let events = if self.bgp_redistribute_static {
+            self.update_local_bgp_route(prefix)?
+        } else {
+            Vec::new()
+        };
+
+        Ok((old_route, events))
+    }
+
+    /// Get the BGP aggregate (`aggregate-address`) configured for `prefix`.
+    pub fn get_bgp_aggregate(&self, prefix: P) -> Option<BgpAggregate> {
+        self.bgp_aggregates.get(&prefix).copied()
+    }
+
+    /// Get all BGP aggregates (`aggregate-address`) configured on the router.
+    pub fn get_bgp_aggregates(&self) -> &P::Map<BgpAggregate> {
+        &self.bgp_aggregates
+    }
+
+    /// Configure or remove the BGP aggregate (`aggregate-address`) for `prefix`. This function
+    /// originates (or withdraws) the aggregate route, and updates the advertisement of all
+    /// contributing routes. It returns the old configuration, along with all events triggered by
+    /// this change.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub(crate) fn set_bgp_aggregate<T: Default>(
+        &mut self,
+        prefix: P,
+        aggregate: Option<BgpAggregate>,
+    ) -> UpdateOutcome<BgpAggregate, P, T> {
         // prepare the undo stack
         #[cfg(feature = "undo")]
+        self.undo_stack.push(Vec::new());
+
+        let old_aggregate = if let Some(aggregate) = aggregate {
+            self.bgp_aggregates.insert(prefix, aggregate)
+        } else {
+            self.bgp_aggregates.remove(&prefix)
+        };
+
+        // add the undo action
+        #[cfg(feature = "undo")]
         self.undo_stack
-            .push(vec![UndoAction::StaticRoute(prefix, old_route)]);
+            .last_mut()
+            .unwrap()
+            .push(UndoAction::BgpAggregate(prefix, old_aggregate));
+
+        let mut events = self.update_local_bgp_route(prefix)?;
+        // `summary-only` may have changed, so the contributing routes must be re-advertised.
+        events.append(&mut self.update_bgp_tables(true)?);
+
+        Ok((old_aggregate, events))
+    }
 
-        old_route
+    /// Get the set of prefixes originated by the router (`network`).
+    pub fn get_bgp_networks(&self) -> &P::Set {
+        &self.bgp_networks
+    }
+
+    /// Originate `prefix` in BGP (`network`), or stop originating it. The route is originated
+    /// with an empty AS path and origin IGP, and is selected and advertised like any other route.
+    /// This function returns whether the router has originated `prefix` before, along with all
+    /// events triggered by this change.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub(crate) fn set_bgp_network<T: Default>(
+        &mut self,
+        prefix: P,
+        originate: bool,
+    ) -> UpdateOutcome<bool, P, T> {
+        // prepare the undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack.push(Vec::new());
+
+        let old_value = if originate {
+            !self.bgp_networks.insert(prefix)
+        } else {
+            self.bgp_networks.remove(&prefix)
+        };
+
+        // add the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(UndoAction::BgpNetwork(prefix, old_value));
+
+        Ok((Some(old_value), self.update_local_bgp_route(prefix)?))
+    }
+
+    /// Returns `true` if static routes are redistributed into BGP (`redistribute static`).
+    pub fn get_bgp_redistribute_static(&self) -> bool {
+        self.bgp_redistribute_static
+    }
+
+    /// Enable or disable the redistribution of static routes into BGP (`redistribute static`).
+    /// Each static route is originated with an empty AS path and origin incomplete. This function
+    /// returns the old value, along with all events triggered by this change.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub(crate) fn set_bgp_redistribute_static<T: Default>(
+        &mut self,
+        mut redistribute: bool,
+    ) -> UpdateOutcome<bool, P, T> {
+        // prepare the undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack.push(Vec::new());
+
+        std::mem::swap(&mut self.bgp_redistribute_static, &mut redistribute);
+
+        // add the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(UndoAction::BgpRedistributeStatic(redistribute));
+
+        let prefixes: Vec<P> = self.static_routes.keys().copied().collect();
+        let mut events = Vec::new();
+        for prefix in prefixes {
+            events.append(&mut self.update_local_bgp_route(prefix)?);
+        }
+
+        Ok((Some(redistribute), events))
     }
 
     /// Set a BGP session with a neighbor. If `session_type` is `None`, then any potentially
@@ -513,20 +930,9 @@ impl<P: Prefix> Router<P> {
         let old_type = if let Some(ty) = session_type {
             self.bgp_sessions.insert(target, ty)
         } else {
+            // remove the entries in the rib tables
+            self.clear_bgp_rib_in(target);
             for prefix in self.bgp_known_prefixes.iter() {
-                // remove the entry in the rib tables
-                if let Some(_rib) = self
-                    .bgp_rib_in
-                    .get_mut(prefix)
-                    .and_then(|rib| rib.remove(&target))
-                {
-                    // add the undo action
-                    #[cfg(feature = "undo")]
-                    self.undo_stack
-                        .last_mut()
-                        .unwrap()
-                        .push(UndoAction::BgpRibIn(*prefix, target, Some(_rib)))
-                }
                 if let Some(_rib) = self
                     .bgp_rib_out
                     .get_mut(prefix)
@@ -541,6 +947,21 @@ impl<P: Prefix> Router<P> {
                 }
             }
 
+            // stop the MRAI timer
+            if let Some(_pending) = self.bgp_mrai_timers.remove(&target) {
+                // add the undo action
+                #[cfg(feature = "undo")]
+                self.undo_stack
+                    .last_mut()
+                    .unwrap()
+                    .push(UndoAction::BgpMraiTimer(target, Some(_pending)))
+            }
+
+            // forget the state of the session
+            if self.bgp_session_state.contains_key(&target) {
+                self.set_bgp_session_state(target, None);
+            }
+
             self.bgp_sessions.remove(&target)
         };
 
@@ -552,8 +973,14 @@ impl<P: Prefix> Router<P> {
             .push(UndoAction::BgpSession(target, old_type));
 
         // udpate the tables
-        self.update_bgp_tables(true)
-            .map(|events| (old_type, events))
+        let mut events = self.update_bgp_tables(true)?;
+
+        // start connecting to the new neighbor
+        if old_type.is_none() && session_type.is_some() {
+            events.extend(self.start_bgp_session(target));
+        }
+
+        Ok((old_type, events))
     }
 
     /// Returns an interator over all BGP sessions
@@ -566,6 +993,76 @@ impl<P: Prefix> Router<P> {
         self.bgp_sessions.get(&neighbor).copied()
     }
 
+    /// Returns the state of the BGP session with `neighbor`, or `None` if no session is configured.
+    /// If the BGP session finite-state machine is not simulated, all configured sessions are
+    /// `Established`, as the router alone cannot tell whether the neighbor is reachable and has
+    /// the session configured as well. Use [`crate::network::Network::get_bgp_session_state`] to
+    /// also take the neighbor into account.
+    pub fn get_bgp_session_state(&self, neighbor: RouterId) -> Option<BgpSessionState> {
+        if !self.bgp_sessions.contains_key(&neighbor) {
+            return None;
+        }
+        Some(if self.bgp_session_timers.is_some() {
+            self.bgp_session_state
+                .get(&neighbor)
+                .copied()
+                .unwrap_or(BgpSessionState::Idle)
+        } else {
+            BgpSessionState::Established
+        })
+    }
+
+    /// Returns the timers of the BGP session finite-state machine, or `None` if it is not
+    /// simulated.
+    pub fn get_bgp_session_timers(&self) -> Option<BgpSessionTimers> {
+        self.bgp_session_timers
+    }
+
+    /// Enable (`Some`) or disable (`None`) the BGP session finite-state machine, and return all
+    /// events triggered by this change. When enabling it, all configured sessions are considered
+    /// to be established. When disabling it, all sessions that are not yet established are
+    /// established immediately, sending the content of `bgp_rib_out` to the neighbor.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub(crate) fn set_bgp_session_timers<T: Default>(
+        &mut self,
+        timers: Option<BgpSessionTimers>,
+    ) -> Vec<Event<P, T>> {
+        // prepare the undo stack
+        #[cfg(feature = "undo")]
+        self.undo_stack.push(Vec::new());
+
+        let peers: Vec<RouterId> = self.bgp_sessions.keys().copied().sorted().collect();
+        let mut events = Vec::new();
+
+        match (self.bgp_session_timers, timers) {
+            (None, Some(_)) => {
+                for peer in peers {
+                    self.set_bgp_session_state(peer, Some(BgpSessionState::Established));
+                }
+            }
+            (Some(_), None) => {
+                for peer in peers {
+                    if !self.is_bgp_session_established(peer) {
+                        events.append(&mut self.establish_bgp_session(peer));
+                    }
+                    self.set_bgp_session_state(peer, None);
+                }
+            }
+            _ => {}
+        }
+
+        let _old = std::mem::replace(&mut self.bgp_session_timers, timers);
+        // add the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(UndoAction::BgpSessionTimers(_old));
+
+        events
+    }
+
     /// Update or remove a route-map from the router. If a route-map with the same order (for the
     /// same direction) already exist, then it will be replaced by the new route-map. The old
     /// route-map will be returned. This function will also return all events triggered by this
@@ -852,16 +1349,166 @@ impl<P: Prefix> Router<P> {
         #[cfg(feature = "undo")]
         self.undo_stack.push(Vec::new());
 
-        // clear the forwarding table
-        let mut swap_table = HashMap::new();
-        swap(&mut self.igp_table, &mut swap_table);
+        let neighbors = self.igp_neighbors(graph);
+        self.write_igp_table(neighbors, graph.node_indices(), ospf)
+    }
+
+    /// Originate a new LSA if the links of this router in `graph` differ from the ones in the last
+    /// LSA originated by this router. The new LSA is flooded to all internal neighbors, while new
+    /// neighbors receive the entire link-state database. Afterwards, the router recomputes its IGP
+    /// table based on its link-state database, and updates the BGP tables.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub(crate) fn originate_ospf_lsa<T: Default>(
+        &mut self,
+        graph: &IgpNetwork,
+        ospf: &Ospf,
+        external_routers: &HashSet<RouterId>,
+    ) -> Result<Vec<Event<P, T>>, DeviceError> {
+        // prepare the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack.push(Vec::new());
+
+        let neighbors = self.igp_neighbors(graph);
+        let links = ospf.lsa_links(graph, self.router_id, external_routers);
+        let old_lsa = self.ospf_lsdb.get(&self.router_id);
+
+        // check if the LSA has changed.
+        if old_lsa
+            .map(|lsa| lsa.links == links)
+            .unwrap_or(links.is_empty())
+        {
+            // only the links towards external routers might have changed.
+            if neighbors == self.neighbors {
+                return Ok(Vec::new());
+            }
+            return self.run_ospf_spf(neighbors);
+        }
+
+        let old_neighbors: HashSet<RouterId> = old_lsa
+            .into_iter()
+            .flat_map(|lsa| lsa.neighbors())
+            .collect();
+        let lsa = Lsa {
+            router: self.router_id,
+            seq: old_lsa.map(|lsa| lsa.seq + 1).unwrap_or_default(),
+            links,
+        };
+        self.install_ospf_lsa(lsa.clone());
+
+        // flood the LSA, and perform the database exchange with all new neighbors.
+        let mut events: Vec<Event<P, T>> = lsa
+            .neighbors()
+            .map(|n| {
+                let lsas = if old_neighbors.contains(&n) {
+                    vec![lsa.clone()]
+                } else {
+                    self.ospf_lsdb
+                        .values()
+                        .sorted_by_key(|lsa| lsa.router)
+                        .cloned()
+                        .collect()
+                };
+                Event::Ospf(T::default(), self.router_id, n, OspfEvent::LsUpdate(lsas))
+            })
+            .collect();
+
+        events.append(&mut self.run_ospf_spf(neighbors)?);
+        Ok(events)
+    }
+
+    /// Replace the OSPF link-state database.
+    ///
+    /// *Undo Functionality*: this function will push a new undo event to the queue.
+    pub(crate) fn set_ospf_lsdb(&mut self, mut lsdb: HashMap<RouterId, Lsa>) {
+        swap(&mut self.ospf_lsdb, &mut lsdb);
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .push(vec![UndoAction::OspfLsdb(lsdb.into_values().collect())]);
+    }
 
-        // create the new neighbors hashmap
-        let mut neighbors: HashMap<RouterId, LinkWeight> = graph
+    /// Get the OSPF link-state database. It is only maintained if OSPF is simulated in the
+    /// event-driven mode, and empty otherwise.
+    pub fn get_ospf_lsdb(&self) -> &HashMap<RouterId, Lsa> {
+        &self.ospf_lsdb
+    }
+
+    /// Install `lsa` in the link-state database if it is more recent than the stored one. This
+    /// function returns `true` if the LSA was installed.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn install_ospf_lsa(&mut self, lsa: Lsa) -> bool {
+        if self
+            .ospf_lsdb
+            .get(&lsa.router)
+            .map(|old| old.seq >= lsa.seq)
+            .unwrap_or(false)
+        {
+            return false;
+        }
+        let router = lsa.router;
+        let _old_lsa = self.ospf_lsdb.insert(router, lsa);
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(UndoAction::OspfLsa(router, _old_lsa));
+        true
+    }
+
+    /// Recompute the IGP table based on the link-state database (SPF computation), and update the
+    /// BGP tables.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn run_ospf_spf<T: Default>(
+        &mut self,
+        neighbors: HashMap<RouterId, LinkWeight>,
+    ) -> Result<Vec<Event<P, T>>, DeviceError> {
+        let ospf = Ospf::compute_from_lsdb(&self.ospf_lsdb);
+        let targets = std::iter::once(self.router_id)
+            .chain(self.ospf_lsdb.keys().copied())
+            .chain(neighbors.keys().copied())
+            .unique()
+            .collect::<Vec<_>>();
+        self.write_igp_table(neighbors, targets, &ospf)
+    }
+
+    /// Get all neighbors of this router in `graph` that are connected with a finite link weight.
+    fn igp_neighbors(&self, graph: &IgpNetwork) -> HashMap<RouterId, LinkWeight> {
+        graph
             .edges(self.router_id)
             .map(|r| (r.target(), *r.weight()))
             .filter(|(_, w)| w.is_finite())
-            .collect();
+            .collect()
+    }
+
+    /// Replace the IGP table and the neighbors, where the IGP table contains all `targets` as
+    /// computed by `ospf`, and update the BGP tables.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn write_igp_table<T: Default>(
+        &mut self,
+        mut neighbors: HashMap<RouterId, LinkWeight>,
+        targets: impl IntoIterator<Item = RouterId>,
+        ospf: &OspfState,
+    ) -> Result<Vec<Event<P, T>>, DeviceError> {
+        // remember which BGP neighbors were reachable before (only needed for the BGP session
+        // finite-state machine)
+        let reachable_before: HashSet<RouterId> = if self.bgp_session_timers.is_some() {
+            self.bgp_sessions
+                .keys()
+                .filter(|peer| self.igp_table.contains_key(peer))
+                .copied()
+                .collect()
+        } else {
+            HashSet::new()
+        };
+
+        // clear the forwarding table
+        let mut swap_table = HashMap::new();
+        swap(&mut self.igp_table, &mut swap_table);
+
+        // replace the neighbors hashmap
         swap(&mut self.neighbors, &mut neighbors);
 
         // add the undo action
@@ -871,7 +1518,7 @@ impl<P: Prefix> Router<P> {
             .unwrap()
             .push(UndoAction::IgpForwardingTable(swap_table, neighbors));
 
-        for target in graph.node_indices() {
+        for target in targets {
             if target == self.router_id {
                 self.igp_table.insert(target, (vec![], 0.0));
                 continue;
@@ -890,7 +1537,9 @@ impl<P: Prefix> Router<P> {
             }
         }
 
-        self.update_bgp_tables(false)
+        let mut events = self.update_bgp_tables(false)?;
+        events.extend(self.update_bgp_session_reachability(&reachable_before));
+        Ok(events)
     }
 
     /// Update the bgp tables only. If `force_dissemination` is set to true, then this function will
@@ -909,6 +1558,10 @@ impl<P: Prefix> Router<P> {
             if changed || force_dissemination {
                 events.append(&mut self.run_bgp_route_dissemination_for_prefix(prefix)?);
             }
+            // if the selected route has changed, update all aggregates that contain the prefix.
+            if changed {
+                events.append(&mut self.update_bgp_aggregates_for_prefix(prefix)?);
+            }
         }
         Ok(events)
     }
@@ -947,6 +1600,17 @@ impl<P: Prefix> Router<P> {
     // Private Functions
     // -----------------
 
+    /// Create a `StepUpdate` for an event that does not change the forwarding state of `prefix`.
+    fn unchanged_step(&self, prefix: Option<P>) -> StepUpdate<P> {
+        match prefix {
+            Some(prefix) => {
+                let old = self.get_next_hop(prefix);
+                StepUpdate::new(prefix, old.clone(), old)
+            }
+            None => StepUpdate::default(),
+        }
+    }
+
     /// Only run bgp decision process (phase 2) in case a new route appears for a specific
     /// prefix. This function assumes that the route was already added to `self.bgp_rib_in`, so the
     /// arguments of this function are both the prefix and the neighbor. This function will then
@@ -972,7 +1636,11 @@ impl<P: Prefix> Router<P> {
             (None, None) => Ok(false),
             // otherwise, if the new route is better than the old one, we can replace it in any
             // case, even if the origin of both routes would be the same.
-            (old, Some(new)) if new > old => {
+            (old, Some(new))
+                if old.map_or(Ordering::Greater, |old| {
+                    new.compare(old, self.always_compare_med, self.compare_router_id)
+                }) == Ordering::Greater =>
+            {
                 // replace the old with the better, new route
                 let _old_entry = self.bgp_rib.insert(prefix, new);
                 // add the undo action
@@ -1011,10 +1679,9 @@ impl<P: Prefix> Router<P> {
 
         // find the new best route
         let new_entry = self.bgp_rib_in.get(&prefix).and_then(|rib| {
-            Iterator::max(
-                rib.values()
-                    .filter_map(|e| self.process_bgp_rib_in_route(e.clone()).ok().flatten()),
-            )
+            rib.values()
+                .filter_map(|e| self.process_bgp_rib_in_route(e.clone()).ok().flatten())
+                .max_by(|a, b| a.compare(b, self.always_compare_med, self.compare_router_id))
         });
 
         // check if the entry will get changed
@@ -1049,6 +1716,7 @@ impl<P: Prefix> Router<P> {
         let mut events = Vec::new();
 
         let rib_best = self.bgp_rib.get(&prefix);
+        let suppressed = self.is_bgp_route_suppressed(prefix);
 
         for (peer, peer_type) in self.bgp_sessions.iter() {
             // get the current route
@@ -1056,9 +1724,10 @@ impl<P: Prefix> Router<P> {
                 self.bgp_rib_out.get(&prefix).and_then(|x| x.get(peer));
             // before applying route maps, we check if neither the old, nor the new routes should be
             // advertised
-            let will_advertise = rib_best
-                .map(|r| should_export_route(r.from_id, r.from_type, *peer, *peer_type))
-                .unwrap_or(false);
+            let will_advertise = !suppressed
+                && rib_best
+                    .map(|r| should_export_route(r.from_id, r.from_type, *peer, *peer_type))
+                    .unwrap_or(false);
 
             // early exit if nothing will change
             if !will_advertise && current_route.is_none() {
@@ -1129,14 +1798,477 @@ impl<P: Prefix> Router<P> {
             };
             // add the event to the queue
             if let Some(event) = event {
-                events.push(Event::Bgp(T::default(), self.router_id, *peer, event));
+                events.push((*peer, event));
+            }
+        }
+
+        // send the events, respecting the MRAI of each session
+        Ok(events
+            .into_iter()
+            .flat_map(|(peer, event)| self.send_bgp_event(peer, event))
+            .collect())
+    }
+
+    /// Send a BGP event to `peer`, respecting the MRAI of the session (see [`Mrai`]). While the
+    /// MRAI timer is running, the advertisement is deferred until the timer expires. Otherwise, the
+    /// event is sent immediately, and the timer is started.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn send_bgp_event<T: Default>(
+        &mut self,
+        peer: RouterId,
+        event: BgpEvent<P>,
+    ) -> Vec<Event<P, T>> {
+        let prefix = match event.prefix() {
+            Some(prefix) => prefix,
+            // OPEN messages are neither rate-limited nor deferred.
+            None => return vec![Event::Bgp(T::default(), self.router_id, peer, event)],
+        };
+        // Routes are only exchanged over established sessions. The `bgp_rib_out` is still
+        // updated, and its content is sent once the session is established.
+        if !self.is_bgp_session_established(peer) {
+            return Vec::new();
+        }
+        let rate_limited = self
+            .bgp_mrai
+            .get(&peer)
+            .map(|mrai| mrai.applies_to(&event))
+            .unwrap_or(false);
+        let event = Event::Bgp(T::default(), self.router_id, peer, event);
+
+        match self.bgp_mrai_timers.get_mut(&peer) {
+            Some(pending) if rate_limited => {
+                // defer the advertisement until the timer expires
+                if pending.insert(prefix) {
+                    // add the undo action
+                    #[cfg(feature = "undo")]
+                    self.undo_stack
+                        .last_mut()
+                        .unwrap()
+                        .push(UndoAction::BgpMraiPending(peer, prefix, false));
+                }
+                vec![]
+            }
+            Some(pending) => {
+                // the event is sent immediately, so any deferred advertisement is obsolete.
+                if pending.remove(&prefix) {
+                    // add the undo action
+                    #[cfg(feature = "undo")]
+                    self.undo_stack
+                        .last_mut()
+                        .unwrap()
+                        .push(UndoAction::BgpMraiPending(peer, prefix, true));
+                }
+                vec![event]
             }
+            None if rate_limited => std::iter::once(event)
+                .chain(self.start_bgp_mrai_timer(peer))
+                .collect(),
+            None => vec![event],
         }
+    }
 
-        // check if the current information is the same
+    /// Start the MRAI timer of the BGP session with `peer`, and return the event that expires the
+    /// timer. This function does nothing if the session has no MRAI.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn start_bgp_mrai_timer<T: Default>(&mut self, peer: RouterId) -> Option<Event<P, T>> {
+        let interval = self
+            .bgp_mrai
+            .get(&peer)
+            .and_then(|mrai| NotNan::new(mrai.interval).ok())
+            .filter(|interval| interval.into_inner() > 0.0)?;
+
+        let _old = self.bgp_mrai_timers.insert(peer, Default::default());
+        // add the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(UndoAction::BgpMraiTimer(peer, _old));
+
+        Some(Event::Timer(
+            T::default(),
+            self.router_id,
+            interval,
+            TimerEvent::BgpMrai(peer),
+        ))
+    }
+
+    /// Handle the expiration of the MRAI timer of the BGP session with `peer`. All advertisements
+    /// that were deferred while the timer was running are sent now, using the current content of
+    /// the `bgp_rib_out`. If anything was sent, the timer is started again.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn expire_bgp_mrai_timer<T: Default>(&mut self, peer: RouterId) -> Vec<Event<P, T>> {
+        let pending = match self.bgp_mrai_timers.remove(&peer) {
+            Some(pending) => pending,
+            // The timer was stopped in the meantime, e.g., because the session was removed.
+            None => return Vec::new(),
+        };
+
+        // add the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(UndoAction::BgpMraiTimer(peer, Some(pending.clone())));
+
+        let mut events: Vec<Event<P, T>> = pending
+            .iter()
+            .sorted()
+            .map(|prefix| {
+                let event = match self.bgp_rib_out.get(prefix).and_then(|x| x.get(&peer)) {
+                    Some(entry) => BgpEvent::Update(entry.route.clone()),
+                    None => BgpEvent::Withdraw(*prefix),
+                };
+                Event::Bgp(T::default(), self.router_id, peer, event)
+            })
+            .collect();
+
+        // restart the timer if we have sent anything
+        if !events.is_empty() {
+            events.extend(self.start_bgp_mrai_timer(peer));
+        }
+
+        events
+    }
+
+    /// Remove all routes learned from `peer` from the `bgp_rib_in`. This function does not run the
+    /// decision process.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn clear_bgp_rib_in(&mut self, peer: RouterId) {
+        for prefix in self.bgp_known_prefixes.iter() {
+            if let Some(_rib) = self
+                .bgp_rib_in
+                .get_mut(prefix)
+                .and_then(|rib| rib.remove(&peer))
+            {
+                // add the undo action
+                #[cfg(feature = "undo")]
+                self.undo_stack
+                    .last_mut()
+                    .unwrap()
+                    .push(UndoAction::BgpRibIn(*prefix, peer, Some(_rib)))
+            }
+        }
+    }
+
+    /// Returns `true` if the BGP session with `peer` is established (see
+    /// [`Router::get_bgp_session_state`]).
+    fn is_bgp_session_established(&self, peer: RouterId) -> bool {
+        self.get_bgp_session_state(peer)
+            .map(|state| state.is_established())
+            .unwrap_or(false)
+    }
+
+    /// Set (or forget) the state of the BGP session with `peer`.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn set_bgp_session_state(&mut self, peer: RouterId, state: Option<BgpSessionState>) {
+        let _old = match state {
+            Some(state) => self.bgp_session_state.insert(peer, state),
+            None => self.bgp_session_state.remove(&peer),
+        };
+        // add the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(UndoAction::BgpSessionState(peer, _old));
+    }
+
+    /// Start connecting to `peer`, i.e., move the session to `Connect`, and return the timer after
+    /// which the OPEN message is sent. This function does nothing if the BGP session finite-state
+    /// machine is not simulated.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn start_bgp_session<T: Default>(&mut self, peer: RouterId) -> Option<Event<P, T>> {
+        let timers = self.bgp_session_timers?;
+        self.set_bgp_session_state(peer, Some(BgpSessionState::Connect));
+        Some(Event::Timer(
+            T::default(),
+            self.router_id,
+            NotNan::new(timers.establishment_delay).unwrap_or_else(|_| NotNan::from(0u8)),
+            TimerEvent::BgpConnect(peer),
+        ))
+    }
+
+    /// Handle the expiration of the establishment delay of the BGP session with `peer`. If the
+    /// neighbor is reachable, the router sends its OPEN message and moves the session to
+    /// `OpenSent`. Otherwise, the connection fails, and the session falls back to `Idle`. Nothing
+    /// happens if the session is no longer in `Connect`, e.g., because the OPEN message of the
+    /// neighbor was received in the meantime.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn expire_bgp_connect_timer<T: Default>(&mut self, peer: RouterId) -> Vec<Event<P, T>> {
+        if self.bgp_session_timers.is_none()
+            || self.get_bgp_session_state(peer) != Some(BgpSessionState::Connect)
+        {
+            return Vec::new();
+        }
+        if self.igp_table.contains_key(&peer) {
+            self.set_bgp_session_state(peer, Some(BgpSessionState::OpenSent));
+            vec![Event::Bgp(
+                T::default(),
+                self.router_id,
+                peer,
+                BgpEvent::Open,
+            )]
+        } else {
+            self.set_bgp_session_state(peer, Some(BgpSessionState::Idle));
+            Vec::new()
+        }
+    }
+
+    /// Handle the expiration of the hold timer of the BGP session with `peer`. If the neighbor is
+    /// still unreachable, the session goes down (`Idle`), and all routes learned from the neighbor
+    /// are removed. Otherwise, nothing happens.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn expire_bgp_hold_timer<T: Default>(
+        &mut self,
+        peer: RouterId,
+    ) -> Result<Vec<Event<P, T>>, DeviceError> {
+        if self.get_bgp_session_state(peer) != Some(BgpSessionState::Established)
+            || self.bgp_session_timers.is_none()
+            || self.igp_table.contains_key(&peer)
+        {
+            return Ok(Vec::new());
+        }
+        self.set_bgp_session_state(peer, Some(BgpSessionState::Idle));
+        self.clear_bgp_rib_in(peer);
+        self.update_bgp_tables(false)
+    }
+
+    /// Update the BGP sessions after the IGP table has changed. The router starts the hold timer of
+    /// each established session whose neighbor was reachable before (`reachable_before`), but is
+    /// no longer reachable. Further, it starts connecting to each neighbor of an `Idle` session
+    /// that is now reachable.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn update_bgp_session_reachability<T: Default>(
+        &mut self,
+        reachable_before: &HashSet<RouterId>,
+    ) -> Vec<Event<P, T>> {
+        let timers = match self.bgp_session_timers {
+            Some(timers) => timers,
+            None => return Vec::new(),
+        };
+        let mut events = Vec::new();
+        for peer in self
+            .bgp_sessions
+            .keys()
+            .copied()
+            .sorted()
+            .collect::<Vec<_>>()
+        {
+            let reachable = self.igp_table.contains_key(&peer);
+            match self.get_bgp_session_state(peer) {
+                Some(BgpSessionState::Idle) if reachable => {
+                    events.extend(self.start_bgp_session(peer));
+                }
+                Some(BgpSessionState::Established)
+                    if !reachable && reachable_before.contains(&peer) =>
+                {
+                    events.push(Event::Timer(
+                        T::default(),
+                        self.router_id,
+                        NotNan::new(timers.hold_time).unwrap_or_else(|_| NotNan::from(0u8)),
+                        TimerEvent::BgpHold(peer),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+
+    /// Handle an OPEN message received from `peer`. If the router has not yet sent its own OPEN
+    /// message, it answers with one. Then, the session is established. If the session was already
+    /// established, the neighbor has restarted the session. In that case, the router removes all
+    /// routes learned from the neighbor, answers with an OPEN message, and establishes the session
+    /// again.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn receive_bgp_open<T: Default>(
+        &mut self,
+        peer: RouterId,
+    ) -> Result<Vec<Event<P, T>>, DeviceError> {
+        let open = Event::Bgp(T::default(), self.router_id, peer, BgpEvent::Open);
+        Ok(match self.get_bgp_session_state(peer) {
+            Some(BgpSessionState::Idle) | Some(BgpSessionState::Connect) => std::iter::once(open)
+                .chain(self.establish_bgp_session(peer))
+                .collect(),
+            Some(BgpSessionState::OpenSent) => self.establish_bgp_session(peer),
+            Some(BgpSessionState::Established) if self.bgp_session_timers.is_some() => {
+                self.clear_bgp_rib_in(peer);
+                let mut events = self.update_bgp_tables(false)?;
+                events.push(open);
+                events.extend(self.establish_bgp_session(peer));
+                events
+            }
+            Some(BgpSessionState::Established) | None => Vec::new(),
+        })
+    }
+
+    /// Move the BGP session with `peer` to `Established`, and send all routes in `bgp_rib_out` to
+    /// the neighbor. If the session has an MRAI, the MRAI timer is started.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn establish_bgp_session<T: Default>(&mut self, peer: RouterId) -> Vec<Event<P, T>> {
+        self.set_bgp_session_state(peer, Some(BgpSessionState::Established));
+
+        let mut events: Vec<Event<P, T>> = self
+            .bgp_rib_out
+            .iter()
+            .filter_map(|(prefix, rib)| rib.get(&peer).map(|entry| (*prefix, entry)))
+            .sorted_by_key(|(prefix, _)| *prefix)
+            .map(|(_, entry)| {
+                Event::Bgp(
+                    T::default(),
+                    self.router_id,
+                    peer,
+                    BgpEvent::Update(entry.route.clone()),
+                )
+            })
+            .collect();
+
+        // rate-limit all subsequent updates
+        if !events.is_empty() && !self.bgp_mrai_timers.contains_key(&peer) {
+            events.extend(self.start_bgp_mrai_timer(peer));
+        }
+
+        events
+    }
+
+    /// Returns `true` if the advertisement of `prefix` is suppressed by an aggregate that is
+    /// configured with `summary-only`.
+    fn is_bgp_route_suppressed(&self, prefix: P) -> bool {
+        self.bgp_aggregates.iter().any(|(aggregate, cfg)| {
+            cfg.summary_only && *aggregate != prefix && aggregate.contains(&prefix)
+        })
+    }
+
+    /// Update all aggregates that contain `prefix` (except `prefix` itself). This function must be
+    /// called whenever the selected route for `prefix` has changed.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn update_bgp_aggregates_for_prefix<T: Default>(
+        &mut self,
+        prefix: P,
+    ) -> Result<Vec<Event<P, T>>, DeviceError> {
+        let aggregates: Vec<P> = self
+            .bgp_aggregates
+            .keys()
+            .filter(|aggregate| **aggregate != prefix && aggregate.contains(&prefix))
+            .copied()
+            .collect();
+        let mut events = Vec::new();
+        for aggregate in aggregates {
+            events.append(&mut self.update_local_bgp_route(aggregate)?);
+        }
         Ok(events)
     }
 
+    /// Compute the route that the router originates itself for `prefix`, if any. An aggregate (with
+    /// at least one contributing route in `bgp_rib`) takes precedence over a network statement,
+    /// which in turn takes precedence over a redistributed static route.
+    fn local_bgp_route(&self, prefix: P) -> Option<BgpRoute<P>> {
+        let router_id = self.router_id;
+        let aggregate = self.bgp_aggregates.get(&prefix).and_then(|cfg| {
+            cfg.aggregate_route(
+                prefix,
+                router_id,
+                self.bgp_rib
+                    .children(&prefix)
+                    .filter(|(p, _)| **p != prefix)
+                    .map(|(_, entry)| &entry.route),
+            )
+        });
+        if aggregate.is_some() {
+            return aggregate;
+        }
+
+        let origin = if self.bgp_networks.contains(&prefix) {
+            Origin::Igp
+        } else if self.bgp_redistribute_static && self.static_routes.get(&prefix).is_some() {
+            Origin::Incomplete
+        } else {
+            return None;
+        };
+        let mut route = BgpRoute::new(router_id, prefix, Vec::<AsId>::new(), None, None);
+        route.origin = origin;
+        Some(route)
+    }
+
+    /// Originate, update, or withdraw the route that the router originates itself for `prefix`
+    /// (see `local_bgp_route`). This route is stored in `bgp_rib_in` as a route received
+    /// from the router itself, with a weight of 32768 (like on Cisco and FRR), such that it is
+    /// preferred over all routes received from neighbors. Then, the decision process and the
+    /// dissemination is executed for `prefix`.
+    ///
+    /// *Undo Functionality*: this function will push some actions to the last undo event.
+    fn update_local_bgp_route<T: Default>(
+        &mut self,
+        prefix: P,
+    ) -> Result<Vec<Event<P, T>>, DeviceError> {
+        let router_id = self.router_id;
+        let new_route = self.local_bgp_route(prefix);
+        let old_route = self
+            .bgp_rib_in
+            .get(&prefix)
+            .and_then(|rib| rib.get(&router_id))
+            .map(|entry| &entry.route);
+        if new_route.as_ref() == old_route {
+            return Ok(Vec::new());
+        }
+
+        let _old_entry = if let Some(route) = new_route {
+            if self.bgp_known_prefixes.insert(prefix) {
+                // add the undo action, but only if the prefix was not known before.
+                #[cfg(feature = "undo")]
+                self.undo_stack
+                    .last_mut()
+                    .unwrap()
+                    .push(UndoAction::DelKnownPrefix(prefix));
+            }
+            let entry = BgpRibEntry {
+                route,
+                from_type: BgpSessionType::IBgpClient,
+                from_id: router_id,
+                to_id: None,
+                igp_cost: None,
+                weight: 32768,
+                received: 0,
+            };
+            self.bgp_rib_in
+                .get_mut_or_default(prefix)
+                .insert(router_id, entry)
+        } else {
+            self.bgp_rib_in
+                .get_mut(&prefix)
+                .and_then(|rib| rib.remove(&router_id))
+        };
+
+        // add the undo action
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(UndoAction::BgpRibIn(prefix, router_id, _old_entry));
+
+        if self.run_bgp_decision_process_for_prefix(prefix)? {
+            let mut events = self.run_bgp_route_dissemination_for_prefix(prefix)?;
+            // the route may itself contribute to a less specific aggregate.
+            events.append(&mut self.update_bgp_aggregates_for_prefix(prefix)?);
+            Ok(events)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
     /// Tries to insert the route into the bgp_rib_in table. If the same route already exists in the table,
     /// replace the route. It returns the prefix for which the route was inserted. The incoming
     /// routes are not processed here (no route maps apply). This is by design, so that changing
@@ -1166,6 +2298,12 @@ impl<P: Prefix> Router<P> {
         // This is because when configuration chagnes, the routes should also change without needing
         // to receive them again.
         // Also, we don't yet compute the igp cost.
+        #[cfg(feature = "undo")]
+        self.undo_stack
+            .last_mut()
+            .unwrap()
+            .push(UndoAction::BgpUpdateCount(self.bgp_update_count));
+        self.bgp_update_count += 1;
         let new_entry = BgpRibEntry {
             route,
             from_type,
@@ -1173,6 +2311,7 @@ impl<P: Prefix> Router<P> {
             to_id: None,
             igp_cost: None,
             weight: 100,
+            received: self.bgp_update_count,
         };
 
         let prefix = new_entry.route.prefix;
@@ -1280,7 +2419,11 @@ impl<P: Prefix> Router<P> {
 
         // Further, we check if the route is reflected. If so, modify the ORIGINATOR_ID and the
         // CLUSTER_LIST.
-        if entry.from_type.is_ibgp() && target_session_type.is_ibgp() {
+        // Locally originated routes are never reflected.
+        if entry.from_type.is_ibgp()
+            && target_session_type.is_ibgp()
+            && entry.from_id != self.router_id
+        {
             // route is to be reflected. Modify the ORIGINATOR_ID and the CLUSTER_LIST.
             entry.route.originator_id.get_or_insert(entry.from_id);
             // append self to the cluster_list
@@ -1345,6 +2488,8 @@ impl<P: Prefix> PartialEq for Router<P> {
     fn eq(&self, other: &Self) -> bool {
         if !(self.name == other.name
             && self.do_load_balancing == other.do_load_balancing
+            && self.always_compare_med == other.always_compare_med
+            && self.compare_router_id == other.compare_router_id
             && self.router_id == other.router_id
             && self.as_id == other.as_id
             && self.igp_table == other.igp_table
@@ -1352,7 +2497,12 @@ impl<P: Prefix> PartialEq for Router<P> {
             && self.bgp_sessions == other.bgp_sessions
             && self.bgp_rib == other.bgp_rib
             && self.bgp_route_maps_in == other.bgp_route_maps_in
-            && self.bgp_route_maps_out == other.bgp_route_maps_out)
+            && self.bgp_route_maps_out == other.bgp_route_maps_out
+            && self.bgp_aggregates == other.bgp_aggregates
+            && self.bgp_networks == other.bgp_networks
+            && self.bgp_redistribute_static == other.bgp_redistribute_static
+            && self.bgp_mrai == other.bgp_mrai
+            && self.bgp_session_timers == other.bgp_session_timers)
         {
             return false;
         }
@@ -1382,13 +2532,26 @@ pub(crate) enum UndoAction<P: Prefix> {
     BgpRibOut(P, RouterId, Option<BgpRibEntry<P>>),
     BgpRouteMap(RouterId, RouteMapDirection, i16, Option<RouteMap<P>>),
     BgpSession(RouterId, Option<BgpSessionType>),
+    BgpAggregate(P, Option<BgpAggregate>),
+    BgpNetwork(P, bool),
+    BgpRedistributeStatic(bool),
+    BgpSessionTimers(Option<BgpSessionTimers>),
+    BgpSessionState(RouterId, Option<BgpSessionState>),
+    BgpMrai(RouterId, Option<Mrai>),
+    BgpMraiTimer(RouterId, Option<P::Set>),
+    BgpMraiPending(RouterId, P, bool),
     IgpForwardingTable(
         HashMap<RouterId, (Vec<RouterId>, LinkWeight)>,
         HashMap<RouterId, LinkWeight>,
     ),
+    OspfLsa(RouterId, Option<Lsa>),
+    OspfLsdb(Vec<Lsa>),
     DelKnownPrefix(P),
     StaticRoute(P, Option<StaticRoute>),
     SetLoadBalancing(bool),
+    SetAlwaysCompareMed(bool),
+    SetCompareRouterId(bool),
+    BgpUpdateCount(u64),
 }
 
 /// Static route description that can either point to the direct link to the target, or to use the
@@ -1426,6 +2589,7 @@ impl<P: Prefix> Serialize for Router<P> {
             as_id: AsId,
             neighbors: Vec<(RouterId, LinkWeight)>,
             igp_table: Vec<(RouterId, (Vec<RouterId>, LinkWeight))>,
+            ospf_lsdb: Vec<Lsa>,
             static_routes: P::Map<StaticRoute>,
             bgp_sessions: Vec<(RouterId, BgpSessionType)>,
             bgp_rib_in: P::Map<Vec<(RouterId, BgpRibEntry<P>)>>,
@@ -1434,7 +2598,17 @@ impl<P: Prefix> Serialize for Router<P> {
             bgp_known_prefixes: P::Set,
             bgp_route_maps_in: Vec<(RouterId, Vec<RouteMap<P>>)>,
             bgp_route_maps_out: Vec<(RouterId, Vec<RouteMap<P>>)>,
+            bgp_aggregates: P::Map<BgpAggregate>,
+            bgp_networks: P::Set,
+            bgp_redistribute_static: bool,
+            bgp_mrai: Vec<(RouterId, Mrai)>,
+            bgp_mrai_timers: Vec<(RouterId, P::Set)>,
+            bgp_session_timers: Option<BgpSessionTimers>,
+            bgp_session_state: Vec<(RouterId, BgpSessionState)>,
             do_load_balancing: bool,
+            always_compare_med: bool,
+            compare_router_id: bool,
+            bgp_update_count: u64,
             #[cfg(feature = "undo")]
             undo_stack: Vec<Vec<UndoAction<P>>>,
         }
@@ -1444,6 +2618,7 @@ impl<P: Prefix> Serialize for Router<P> {
             as_id: self.as_id,
             neighbors: self.neighbors.clone().into_iter().collect(),
             igp_table: self.igp_table.clone().into_iter().collect(),
+            ospf_lsdb: self.ospf_lsdb.values().cloned().collect(),
             static_routes: self.static_routes.clone(),
             bgp_sessions: self.bgp_sessions.clone().into_iter().collect(),
             bgp_rib_in: self
@@ -1462,7 +2637,17 @@ impl<P: Prefix> Serialize for Router<P> {
             bgp_known_prefixes: self.bgp_known_prefixes.clone(),
             bgp_route_maps_in: self.bgp_route_maps_in.clone().into_iter().collect(),
             bgp_route_maps_out: self.bgp_route_maps_out.clone().into_iter().collect(),
+            bgp_aggregates: self.bgp_aggregates.clone(),
+            bgp_networks: self.bgp_networks.clone(),
+            bgp_redistribute_static: self.bgp_redistribute_static,
+            bgp_mrai: self.bgp_mrai.clone().into_iter().collect(),
+            bgp_mrai_timers: self.bgp_mrai_timers.clone().into_iter().collect(),
+            bgp_session_timers: self.bgp_session_timers,
+            bgp_session_state: self.bgp_session_state.clone().into_iter().collect(),
             do_load_balancing: self.do_load_balancing,
+            always_compare_med: self.always_compare_med,
+            compare_router_id: self.compare_router_id,
+            bgp_update_count: self.bgp_update_count,
             #[cfg(feature = "undo")]
             undo_stack: self.undo_stack.clone(),
         }
@@ -1483,6 +2668,8 @@ impl<'de, P: Prefix> Deserialize<'de> for Router<P> {
             as_id: AsId,
             neighbors: Vec<(RouterId, LinkWeight)>,
             igp_table: Vec<(RouterId, (Vec<RouterId>, LinkWeight))>,
+            #[serde(default)]
+            ospf_lsdb: Vec<Lsa>,
             static_routes: P::Map<StaticRoute>,
             bgp_sessions: Vec<(RouterId, BgpSessionType)>,
             bgp_rib_in: P::Map<Vec<(RouterId, BgpRibEntry<P>)>>,
@@ -1491,7 +2678,27 @@ impl<'de, P: Prefix> Deserialize<'de> for Router<P> {
             bgp_known_prefixes: P::Set,
             bgp_route_maps_in: Vec<(RouterId, Vec<RouteMap<P>>)>,
             bgp_route_maps_out: Vec<(RouterId, Vec<RouteMap<P>>)>,
+            #[serde(default)]
+            bgp_aggregates: P::Map<BgpAggregate>,
+            #[serde(default)]
+            bgp_networks: P::Set,
+            #[serde(default)]
+            bgp_redistribute_static: bool,
+            #[serde(default)]
+            bgp_mrai: Vec<(RouterId, Mrai)>,
+            #[serde(default)]
+            bgp_mrai_timers: Vec<(RouterId, P::Set)>,
+            #[serde(default)]
+            bgp_session_timers: Option<BgpSessionTimers>,
+            #[serde(default)]
+            bgp_session_state: Vec<(RouterId, BgpSessionState)>,
             do_load_balancing: bool,
+            #[serde(default)]
+            always_compare_med: bool,
+            #[serde(default)]
+            compare_router_id: bool,
+            #[serde(default)]
+            bgp_update_count: u64,
             #[cfg(feature = "undo")]
             undo_stack: Vec<Vec<UndoAction<P>>>,
         }
@@ -1502,6 +2709,11 @@ impl<'de, P: Prefix> Deserialize<'de> for Router<P> {
             as_id: router.as_id,
             neighbors: router.neighbors.into_iter().collect(),
             igp_table: router.igp_table.into_iter().collect(),
+            ospf_lsdb: router
+                .ospf_lsdb
+                .into_iter()
+                .map(|lsa| (lsa.router, lsa))
+                .collect(),
             static_routes: router.static_routes,
             bgp_sessions: router.bgp_sessions.into_iter().collect(),
             bgp_rib_in: router
@@ -1518,7 +2730,17 @@ impl<'de, P: Prefix> Deserialize<'de> for Router<P> {
             bgp_known_prefixes: router.bgp_known_prefixes,
             bgp_route_maps_in: router.bgp_route_maps_in.into_iter().collect(),
             bgp_route_maps_out: router.bgp_route_maps_out.into_iter().collect(),
+            bgp_aggregates: router.bgp_aggregates,
+            bgp_networks: router.bgp_networks,
+            bgp_redistribute_static: router.bgp_redistribute_static,
+            bgp_mrai: router.bgp_mrai.into_iter().collect(),
+            bgp_mrai_timers: router.bgp_mrai_timers.into_iter().collect(),
+            bgp_session_timers: router.bgp_session_timers,
+            bgp_session_state: router.bgp_session_state.into_iter().collect(),
             do_load_balancing: router.do_load_balancing,
+            always_compare_med: router.always_compare_med,
+            compare_router_id: router.compare_router_id,
+            bgp_update_count: router.bgp_update_count,
             #[cfg(feature = "undo")]
             undo_stack: router.undo_stack.into_iter().collect(),
         })
@@ -240,6 +240,33 @@ where
                 ConfigExpr::LoadBalancing { router } => ConfigExpr::LoadBalancing {
                     router: node(router)?,
                 },
+                ConfigExpr::BgpMrai {
+                    router,
+                    neighbor,
+                    mrai,
+                } => ConfigExpr::BgpMrai {
+                    router: node(router)?,
+                    neighbor: node(neighbor)?,
+                    mrai,
+                },
+                ConfigExpr::Aggregate {
+                    router,
+                    prefix,
+                    summary_only,
+                    as_set,
+                } => ConfigExpr::Aggregate {
+                    router: node(router)?,
+                    prefix,
+                    summary_only,
+                    as_set,
+                },
+                ConfigExpr::BgpNetwork { router, prefix } => ConfigExpr::BgpNetwork {
+                    router: node(router)?,
+                    prefix,
+                },
+                ConfigExpr::BgpRedistributeStatic { router } => ConfigExpr::BgpRedistributeStatic {
+                    router: node(router)?,
+                },
             };
             net.apply_modifier(&ConfigModifier::Insert(expr))?;
         }
@@ -0,0 +1,243 @@
+// BgpSim: BGP Network Simulator written in Rust
+// Copyright (C) 2022-2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test the tie-breakers of the BGP decision process.
+
+use crate::{
+    bgp::{BgpEvent, BgpRoute, BgpSessionType::*, Origin},
+    event::Event,
+    route_map::{AsPathRegex, RouteMapBuilder, RouteMapDirection::Incoming},
+    router::Router,
+    types::{AsId, Ipv4Prefix, Prefix, PrefixMap, RouterId, SimplePrefix, SinglePrefix},
+};
+
+use maplit::hashmap;
+
+#[generic_tests::define]
+mod t {
+    use super::*;
+
+    /// Router 0 in AS 65001 with eBGP sessions to 100 and 101, and iBGP sessions to 1 and 2.
+    fn router<P: Prefix>() -> Router<P> {
+        let mut r = Router::<P>::new("test".to_string(), 0.into(), AsId(65001));
+        r.set_bgp_session::<()>(100.into(), Some(EBgp)).unwrap();
+        r.set_bgp_session::<()>(101.into(), Some(EBgp)).unwrap();
+        r.set_bgp_session::<()>(1.into(), Some(IBgpPeer)).unwrap();
+        r.set_bgp_session::<()>(2.into(), Some(IBgpPeer)).unwrap();
+        r.igp_table = hashmap! {
+            100.into() => (vec![100.into()], 1.0),
+            101.into() => (vec![101.into()], 1.0),
+            1.into()   => (vec![1.into()], 1.0),
+            2.into()   => (vec![2.into()], 1.0),
+        };
+        r
+    }
+
+    fn route<P: Prefix>(
+        from: impl Into<RouterId>,
+        as_path: impl IntoIterator<Item = u32>,
+        med: Option<u32>,
+        origin: Origin,
+    ) -> BgpRoute<P> {
+        let mut route = BgpRoute::new(
+            from.into(),
+            P::from(0),
+            as_path.into_iter().map(AsId),
+            med,
+            [],
+        );
+        route.origin = origin;
+        route
+    }
+
+    fn update<P: Prefix>(r: &mut Router<P>, from: impl Into<RouterId>, route: BgpRoute<P>) {
+        r.handle_event(Event::Bgp(
+            (),
+            from.into(),
+            0.into(),
+            BgpEvent::Update(route),
+        ))
+        .unwrap();
+    }
+
+    fn selected<P: Prefix>(r: &Router<P>) -> RouterId {
+        r.get_selected_bgp_route(P::from(0)).unwrap().from_id
+    }
+
+    #[test]
+    fn origin<P: Prefix>() {
+        let mut r = router::<P>();
+        update(&mut r, 100, route(100, [1, 10], None, Origin::Incomplete));
+        assert_eq!(selected(&r), 100.into());
+        update(&mut r, 101, route(101, [2, 10], None, Origin::Egp));
+        assert_eq!(selected(&r), 101.into());
+        update(&mut r, 100, route(100, [1, 10], None, Origin::Igp));
+        assert_eq!(selected(&r), 100.into());
+    }
+
+    #[test]
+    fn as_path_prepend<P: Prefix>() {
+        let mut r = router::<P>();
+        update(&mut r, 100, route(100, [1, 10], None, Origin::Igp));
+        update(&mut r, 101, route(101, [2, 10], None, Origin::Igp));
+        assert_eq!(selected(&r), 100.into());
+
+        // prepend the routes from 100 that were originated in AS 10.
+        r.set_bgp_route_map::<()>(
+            100.into(),
+            Incoming,
+            RouteMapBuilder::new()
+                .order(10)
+                .allow()
+                .match_as_path_regex(AsPathRegex::new("_10$").unwrap())
+                .prepend_as_path([1])
+                .build(),
+        )
+        .unwrap();
+        assert_eq!(selected(&r), 101.into());
+    }
+
+    #[test]
+    fn med_only_from_same_neighbor_as<P: Prefix>() {
+        // Routes from different neighboring ASes. The MED is ignored, and the older one is chosen.
+        let mut r = router::<P>();
+        update(&mut r, 101, route(101, [2, 10], Some(20), Origin::Igp));
+        update(&mut r, 100, route(100, [1, 10], Some(10), Origin::Igp));
+        assert_eq!(selected(&r), 101.into());
+
+        // Routes from the same neighboring AS. The route with the lower MED is chosen.
+        let mut r = router::<P>();
+        update(&mut r, 101, route(101, [1, 10], Some(20), Origin::Igp));
+        update(&mut r, 100, route(100, [1, 10], Some(10), Origin::Igp));
+        assert_eq!(selected(&r), 100.into());
+    }
+
+    #[test]
+    fn always_compare_med<P: Prefix>() {
+        let mut r = router::<P>();
+        update(&mut r, 101, route(101, [2, 10], Some(20), Origin::Igp));
+        update(&mut r, 100, route(100, [1, 10], Some(10), Origin::Igp));
+        assert_eq!(selected(&r), 101.into());
+        #[cfg(feature = "undo")]
+        let r_before = r.clone();
+
+        let (old, events) = r.set_always_compare_med::<()>(true).unwrap();
+        assert_eq!(old, Some(false));
+        assert!(r.get_always_compare_med());
+        assert_eq!(selected(&r), 100.into());
+        // the new route is advertised to 1, 2, and 101, and withdrawn from 100.
+        assert_eq!(events.len(), 4);
+
+        #[cfg(feature = "undo")]
+        {
+            r.undo_event();
+            assert_eq!(r, r_before);
+            assert_eq!(selected(&r), 101.into());
+        }
+    }
+
+    #[test]
+    fn ebgp_over_ibgp<P: Prefix>() {
+        let mut r = router::<P>();
+        r.igp_table.insert(100.into(), (vec![100.into()], 10.0));
+        update(&mut r, 1, route(100, [1, 10], None, Origin::Igp));
+        assert_eq!(selected(&r), 1.into());
+        update(&mut r, 100, route(100, [1, 10], None, Origin::Igp));
+        assert_eq!(selected(&r), 100.into());
+    }
+
+    #[test]
+    fn oldest_ebgp_route<P: Prefix>() {
+        let mut r = router::<P>();
+        update(&mut r, 101, route(101, [2, 10], None, Origin::Igp));
+        update(&mut r, 100, route(100, [1, 10], None, Origin::Igp));
+        assert_eq!(selected(&r), 101.into());
+
+        // re-advertising the route makes it the newer one.
+        update(&mut r, 101, route(101, [2, 10], None, Origin::Egp));
+        update(&mut r, 101, route(101, [2, 10], None, Origin::Igp));
+        assert_eq!(selected(&r), 100.into());
+    }
+
+    #[test]
+    fn compare_router_id<P: Prefix>() {
+        let mut r = router::<P>();
+        update(&mut r, 101, route(101, [2, 10], None, Origin::Igp));
+        update(&mut r, 100, route(100, [1, 10], None, Origin::Igp));
+        assert_eq!(selected(&r), 101.into());
+        #[cfg(feature = "undo")]
+        let r_before = r.clone();
+
+        // ignore the age of the routes, and choose the lower router-id instead.
+        let (old, _) = r.set_compare_router_id::<()>(true).unwrap();
+        assert_eq!(old, Some(false));
+        assert!(r.get_compare_router_id());
+        assert_eq!(selected(&r), 100.into());
+
+        // the outcome no longer depends on the order in which routes are received.
+        update(&mut r, 100, route(100, [1, 10], None, Origin::Egp));
+        update(&mut r, 100, route(100, [1, 10], None, Origin::Igp));
+        assert_eq!(selected(&r), 100.into());
+
+        #[cfg(feature = "undo")]
+        {
+            r.undo_event();
+            r.undo_event();
+            r.undo_event();
+            assert_eq!(r, r_before);
+            assert_eq!(selected(&r), 101.into());
+        }
+    }
+
+    #[cfg(feature = "undo")]
+    #[test]
+    fn undo_restores_update_count<P: Prefix>() {
+        let mut r = router::<P>();
+        update(&mut r, 101, route(101, [2, 10], None, Origin::Igp));
+        let count = r.bgp_update_count;
+        update(&mut r, 100, route(100, [1, 10], None, Origin::Igp));
+        assert_eq!(r.bgp_update_count, count + 1);
+        r.undo_event();
+        assert_eq!(r.bgp_update_count, count);
+
+        // a route received after the undo is still newer than all others
+        update(&mut r, 100, route(100, [1, 10], None, Origin::Igp));
+        assert_eq!(selected(&r), 101.into());
+        assert_eq!(
+            r.get_bgp_rib_in().get(&P::from(0)).unwrap()[&100.into()].received,
+            count + 1
+        );
+    }
+
+    #[test]
+    fn lowest_router_id_for_ibgp<P: Prefix>() {
+        // the age of iBGP routes is ignored.
+        let mut r = router::<P>();
+        update(&mut r, 2, route(101, [2, 10], None, Origin::Igp));
+        update(&mut r, 1, route(100, [1, 10], None, Origin::Igp));
+        assert_eq!(selected(&r), 1.into());
+    }
+
+    #[instantiate_tests(<SinglePrefix>)]
+    mod single {}
+
+    #[instantiate_tests(<SimplePrefix>)]
+    mod simple {}
+
+    #[instantiate_tests(<Ipv4Prefix>)]
+    mod ipv4 {}
+}
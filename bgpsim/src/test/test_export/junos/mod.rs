@@ -0,0 +1,550 @@
+// BgpSim: BGP Network Simulator written in Rust
+// Copyright (C) 2022-2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+use pretty_assertions::assert_str_eq;
+
+use crate::{
+    bgp::BgpSessionType::{EBgp, IBgpClient, IBgpPeer},
+    config::{ConfigExpr, ConfigModifier::*},
+    export::{
+        cisco_frr_generators::Target::Junos as Target, CiscoFrrCfgGen, ExportError, InternalCfgGen,
+    },
+    route_map::{RouteMapBuilder, RouteMapDirection::Incoming},
+    router::StaticRoute,
+    types::{NonOverlappingPrefix, Prefix, SimplePrefix, SinglePrefix},
+};
+
+#[generic_tests::define]
+mod t {
+    use super::*;
+
+    #[test]
+    fn generate_internal_config_route_maps<P: Prefix>() {
+        assert_str_eq!(
+            super::super::generate_internal_config_route_maps::<P>(Target),
+            include_str!("internal_config_route_maps")
+        );
+    }
+
+    #[test]
+    fn generate_external_config<P: Prefix>() {
+        assert_str_eq!(
+            super::super::generate_external_config::<P>(Target),
+            include_str!("external_config")
+        );
+    }
+
+    #[test]
+    fn generate_external_config_pec<P: Prefix + NonOverlappingPrefix>() {
+        assert_str_eq!(
+            super::super::generate_external_config_pec::<P>(Target),
+            include_str!("external_config_pec")
+        );
+    }
+
+    #[instantiate_tests(<SinglePrefix>)]
+    mod single {}
+
+    #[instantiate_tests(<SimplePrefix>)]
+    mod simple {}
+}
+
+#[test]
+fn generate_internal_config_full_mesh() {
+    assert_str_eq!(
+        super::generate_internal_config_full_mesh(Target),
+        include_str!("internal_config_full_mesh")
+    );
+}
+
+#[test]
+fn generate_internal_config_route_reflector() {
+    assert_str_eq!(
+        super::generate_internal_config_route_reflector(Target),
+        include_str!("internal_config_route_reflection")
+    );
+}
+
+#[test]
+fn generate_internal_config_route_maps_with_pec() {
+    assert_str_eq!(
+        super::generate_internal_config_route_maps_with_pec::<SimplePrefix>(Target),
+        include_str!("internal_config_route_maps_pec")
+    );
+}
+
+#[test]
+fn generate_internal_config_route_maps_edit() {
+    let net = super::net_for_route_maps::<SimplePrefix>();
+    let mut ip = super::addressor(&net);
+    let mut cfg_gen =
+        CiscoFrrCfgGen::new(&net, 0.into(), Target, super::iface_names(Target)).unwrap();
+
+    let config = InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+
+    assert_str_eq!(config, include_str!("internal_config_route_maps"));
+
+    // now, edit the configuration
+    let cmd1 = ConfigExpr::BgpRouteMap {
+        router: 0.into(),
+        neighbor: 4.into(),
+        direction: Incoming,
+        map: RouteMapBuilder::new()
+            .deny()
+            .order(11)
+            .match_as_path_contains(100.into())
+            .build(),
+    };
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(&net, &mut ip, Insert(cmd1))
+            .unwrap(),
+        "\
+set policy-options as-path neighbor-R0_ext_4-in-32779-asl \".* 100 .*\"
+set policy-options policy-statement neighbor-R0_ext_4-in term 32779 from protocol bgp
+set policy-options policy-statement neighbor-R0_ext_4-in term 32779 from as-path neighbor-R0_ext_4-in-32779-asl
+set policy-options policy-statement neighbor-R0_ext_4-in term 32779 then reject
+insert policy-options policy-statement neighbor-R0_ext_4-in term 32779 before term 32788
+set policy-options policy-statement neighbor-R0_ext_4-in term 32778 from protocol bgp
+set policy-options policy-statement neighbor-R0_ext_4-in term 32778 then next term
+"
+    );
+
+    let cmd2a = ConfigExpr::BgpRouteMap {
+        router: 0.into(),
+        neighbor: 4.into(),
+        direction: Incoming,
+        map: RouteMapBuilder::new()
+            .allow()
+            .order(12)
+            .match_community(100)
+            .set_local_pref(200)
+            .build(),
+    };
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(&net, &mut ip, Insert(cmd2a.clone()))
+            .unwrap(),
+        "\
+set policy-options community neighbor-R0_ext_4-in-32780-cl members 65535:100
+set policy-options policy-statement neighbor-R0_ext_4-in term 32780 from protocol bgp
+set policy-options policy-statement neighbor-R0_ext_4-in term 32780 from community neighbor-R0_ext_4-in-32780-cl
+set policy-options policy-statement neighbor-R0_ext_4-in term 32780 then local-preference 200
+set policy-options policy-statement neighbor-R0_ext_4-in term 32780 then next term
+insert policy-options policy-statement neighbor-R0_ext_4-in term 32780 before term 32788
+"
+    );
+
+    let cmd3 = ConfigExpr::BgpRouteMap {
+        router: 0.into(),
+        neighbor: 4.into(),
+        direction: Incoming,
+        map: RouteMapBuilder::new()
+            .allow()
+            .order(13)
+            .match_community(200)
+            .set_community(300)
+            .build(),
+    };
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(&net, &mut ip, Insert(cmd3.clone()))
+            .unwrap(),
+        "\
+set policy-options community neighbor-R0_ext_4-in-32781-cl members 65535:200
+set policy-options policy-statement neighbor-R0_ext_4-in term 32781 from protocol bgp
+set policy-options policy-statement neighbor-R0_ext_4-in term 32781 from community neighbor-R0_ext_4-in-32781-cl
+set policy-options community community-65535-300 members 65535:300
+set policy-options policy-statement neighbor-R0_ext_4-in term 32781 then community add community-65535-300
+set policy-options policy-statement neighbor-R0_ext_4-in term 32781 then next term
+insert policy-options policy-statement neighbor-R0_ext_4-in term 32781 before term 32788
+set policy-options policy-statement neighbor-R0_ext_4-in term 32780 from protocol bgp
+set policy-options policy-statement neighbor-R0_ext_4-in term 32780 then next term
+"
+    );
+
+    let cmd2b = ConfigExpr::BgpRouteMap {
+        router: 0.into(),
+        neighbor: 4.into(),
+        direction: Incoming,
+        map: RouteMapBuilder::new()
+            .deny()
+            .order(12)
+            .match_community(100)
+            .build(),
+    };
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(
+                &net,
+                &mut ip,
+                Update {
+                    from: cmd2a,
+                    to: cmd2b
+                }
+            )
+            .unwrap(),
+        "\
+delete policy-options policy-statement neighbor-R0_ext_4-in term 32780
+delete policy-options community neighbor-R0_ext_4-in-32780-cl
+set policy-options community neighbor-R0_ext_4-in-32780-cl members 65535:100
+set policy-options policy-statement neighbor-R0_ext_4-in term 32780 from protocol bgp
+set policy-options policy-statement neighbor-R0_ext_4-in term 32780 from community neighbor-R0_ext_4-in-32780-cl
+set policy-options policy-statement neighbor-R0_ext_4-in term 32780 then reject
+insert policy-options policy-statement neighbor-R0_ext_4-in term 32780 before term 32781
+"
+    );
+
+    let cmd4 = ConfigExpr::BgpRouteMap {
+        router: 0.into(),
+        neighbor: 4.into(),
+        direction: Incoming,
+        map: RouteMapBuilder::new()
+            .allow()
+            .order(20)
+            .match_community(20)
+            .match_prefix(1.into())
+            .set_weight(200)
+            .build(),
+    };
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(&net, &mut ip, Remove(cmd4))
+            .unwrap(),
+        "\
+delete policy-options policy-statement neighbor-R0_ext_4-in term 32788
+delete policy-options route-filter-list neighbor-R0_ext_4-in-32788-pl
+delete policy-options community neighbor-R0_ext_4-in-32788-cl
+set policy-options policy-statement neighbor-R0_ext_4-in term 32781 from protocol bgp
+set policy-options policy-statement neighbor-R0_ext_4-in term 32781 then next term
+"
+    );
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(&net, &mut ip, Remove(cmd3))
+            .unwrap(),
+        "\
+delete policy-options policy-statement neighbor-R0_ext_4-in term 32781
+delete policy-options community neighbor-R0_ext_4-in-32781-cl
+"
+    );
+}
+
+#[test]
+fn generate_external_config_withdraw() {
+    let (cfg, cmd) = super::generate_external_config_withdraw(Target);
+    assert_str_eq!(cfg, include_str!("external_config_withdraw"));
+    assert_str_eq!(cmd, include_str!("external_config_withdraw_cmd"))
+}
+
+#[test]
+fn generate_internal_config_ospf_edit() {
+    let net = super::net_for_route_maps::<SimplePrefix>();
+    let mut ip = super::addressor(&net);
+    let mut cfg_gen =
+        CiscoFrrCfgGen::new(&net, 0.into(), Target, super::iface_names(Target)).unwrap();
+    InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+
+    let weight = |weight| ConfigExpr::IgpLinkWeight {
+        source: 0.into(),
+        target: 1.into(),
+        weight,
+    };
+    let area = |area: u32| ConfigExpr::OspfArea {
+        source: 0.into(),
+        target: 1.into(),
+        area: area.into(),
+    };
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(
+                &net,
+                &mut ip,
+                Update {
+                    from: weight(100.0),
+                    to: weight(200.0)
+                }
+            )
+            .unwrap(),
+        "\
+set protocols ospf area 0.0.0.0 interface ge-0/0/0.0
+set protocols ospf area 0.0.0.0 interface ge-0/0/0.0 metric 200
+"
+    );
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(&net, &mut ip, Insert(area(1)))
+            .unwrap(),
+        "\
+delete protocols ospf area 0.0.0.0 interface ge-0/0/0.0
+set protocols ospf area 0.0.0.1 interface ge-0/0/0.0
+set protocols ospf area 0.0.0.1 interface ge-0/0/0.0 metric 100
+set protocols ospf area 0.0.0.1 interface ge-0/0/0.0 dead-interval 5
+set protocols ospf area 0.0.0.1 interface ge-0/0/0.0 hello-interval 1
+"
+    );
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(
+                &net,
+                &mut ip,
+                Update {
+                    from: area(1),
+                    to: area(2)
+                }
+            )
+            .unwrap(),
+        "\
+delete protocols ospf area 0.0.0.1 interface ge-0/0/0.0
+set protocols ospf area 0.0.0.2 interface ge-0/0/0.0
+set protocols ospf area 0.0.0.2 interface ge-0/0/0.0 metric 100
+set protocols ospf area 0.0.0.2 interface ge-0/0/0.0 dead-interval 5
+set protocols ospf area 0.0.0.2 interface ge-0/0/0.0 hello-interval 1
+"
+    );
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(&net, &mut ip, Remove(area(2)))
+            .unwrap(),
+        "\
+delete protocols ospf area 0.0.0.2 interface ge-0/0/0.0
+set protocols ospf area 0.0.0.0 interface ge-0/0/0.0
+set protocols ospf area 0.0.0.0 interface ge-0/0/0.0 metric 100
+set protocols ospf area 0.0.0.0 interface ge-0/0/0.0 dead-interval 5
+set protocols ospf area 0.0.0.0 interface ge-0/0/0.0 hello-interval 1
+"
+    );
+}
+
+#[test]
+fn generate_internal_config_route_maps_community_error() {
+    let net = super::net_for_route_maps::<SimplePrefix>();
+    let mut ip = super::addressor(&net);
+    let mut cfg_gen =
+        CiscoFrrCfgGen::new(&net, 0.into(), Target, super::iface_names(Target)).unwrap();
+    InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+
+    // Junos cannot require some communities to be present and others to be absent at once.
+    let cmd = ConfigExpr::BgpRouteMap {
+        router: 0.into(),
+        neighbor: 4.into(),
+        direction: Incoming,
+        map: RouteMapBuilder::new()
+            .deny()
+            .order(11)
+            .match_community(100)
+            .match_deny_community(200)
+            .build(),
+    };
+    assert!(matches!(
+        cfg_gen.generate_command(&net, &mut ip, Insert(cmd)),
+        Err(ExportError::UnsupportedCommunityList(_))
+    ));
+}
+
+#[test]
+fn generate_internal_config_bgp_session_edit() {
+    let net = super::net_for_route_maps::<SimplePrefix>();
+    let mut ip = super::addressor(&net);
+    let mut cfg_gen =
+        CiscoFrrCfgGen::new(&net, 0.into(), Target, super::iface_names(Target)).unwrap();
+    InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+
+    let internal = |session_type| ConfigExpr::BgpSession {
+        source: 0.into(),
+        target: 1.into(),
+        session_type,
+    };
+    let external = ConfigExpr::BgpSession {
+        source: 0.into(),
+        target: 4.into(),
+        session_type: EBgp,
+    };
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(&net, &mut ip, Remove(internal(IBgpPeer)))
+            .unwrap(),
+        "delete protocols bgp group peer-10-0-1-1\n"
+    );
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(&net, &mut ip, Insert(internal(IBgpPeer)))
+            .unwrap(),
+        "\
+set protocols bgp group peer-10-0-1-1 type internal
+set protocols bgp group peer-10-0-1-1 neighbor 10.0.1.1
+set protocols bgp group peer-10-0-1-1 local-address 10.0.0.1
+set protocols bgp group peer-10-0-1-1 preference 65635
+set policy-options policy-statement next-hop-self then next-hop self
+set protocols bgp group peer-10-0-1-1 export next-hop-self
+set protocols bgp group peer-10-0-1-1 import neighbor-R1-in
+set protocols bgp group peer-10-0-1-1 export neighbor-R1-out
+set protocols bgp group peer-10-0-1-1 export bgp-networks
+set protocols bgp group peer-10-0-1-1 keep all
+set policy-options policy-statement neighbor-R1-in term 65535 from protocol bgp
+set policy-options policy-statement neighbor-R1-in term 65535 then accept
+set policy-options policy-statement neighbor-R1-out term 65535 from protocol bgp
+set policy-options policy-statement neighbor-R1-out term 65535 then accept
+"
+    );
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(
+                &net,
+                &mut ip,
+                Update {
+                    from: internal(IBgpPeer),
+                    to: internal(IBgpClient)
+                }
+            )
+            .unwrap(),
+        "\
+set protocols bgp group peer-10-0-1-1 neighbor 10.0.1.1
+set protocols bgp group peer-10-0-1-1 cluster 10.0.0.1
+"
+    );
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(&net, &mut ip, Remove(external.clone()))
+            .unwrap(),
+        "delete protocols bgp group peer-10-192-0-2\n"
+    );
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(&net, &mut ip, Insert(external))
+            .unwrap(),
+        "\
+set protocols bgp group peer-10-192-0-2 type external
+set protocols bgp group peer-10-192-0-2 peer-as 4
+set protocols bgp group peer-10-192-0-2 neighbor 10.192.0.2
+set protocols bgp group peer-10-192-0-2 preference 65635
+set policy-options policy-statement next-hop-self then next-hop self
+set protocols bgp group peer-10-192-0-2 export next-hop-self
+set protocols bgp group peer-10-192-0-2 import neighbor-R0_ext_4-in
+set protocols bgp group peer-10-192-0-2 export neighbor-R0_ext_4-out
+set protocols bgp group peer-10-192-0-2 export bgp-networks
+set protocols bgp group peer-10-192-0-2 keep all
+set policy-options policy-statement neighbor-R0_ext_4-in term 65535 from protocol bgp
+set policy-options policy-statement neighbor-R0_ext_4-in term 65535 then accept
+set policy-options policy-statement neighbor-R0_ext_4-out term 65535 from protocol bgp
+set policy-options policy-statement neighbor-R0_ext_4-out term 65535 then accept
+"
+    );
+}
+
+#[test]
+fn generate_internal_config_static_route_edit() {
+    let net = super::net_for_route_maps::<SimplePrefix>();
+    let mut ip = super::addressor(&net);
+    let mut cfg_gen =
+        CiscoFrrCfgGen::new(&net, 0.into(), Target, super::iface_names(Target)).unwrap();
+    InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+
+    let sr = |target| ConfigExpr::StaticRoute {
+        router: 0.into(),
+        prefix: SimplePrefix::from(0),
+        target,
+    };
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(&net, &mut ip, Insert(sr(StaticRoute::Direct(1.into()))))
+            .unwrap(),
+        "set routing-options static route 100.0.0.0/24 next-hop ge-0/0/0.0\n"
+    );
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(
+                &net,
+                &mut ip,
+                Update {
+                    from: sr(StaticRoute::Direct(1.into())),
+                    to: sr(StaticRoute::Indirect(2.into()))
+                }
+            )
+            .unwrap(),
+        "\
+delete routing-options static route 100.0.0.0/24
+set routing-options static route 100.0.0.0/24 next-hop 10.0.2.1
+set routing-options static route 100.0.0.0/24 resolve
+"
+    );
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(
+                &net,
+                &mut ip,
+                Update {
+                    from: sr(StaticRoute::Indirect(2.into())),
+                    to: sr(StaticRoute::Drop)
+                }
+            )
+            .unwrap(),
+        "\
+delete routing-options static route 100.0.0.0/24
+set routing-options static route 100.0.0.0/24 discard
+"
+    );
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(&net, &mut ip, Remove(sr(StaticRoute::Drop)))
+            .unwrap(),
+        "delete routing-options static route 100.0.0.0/24\n"
+    );
+}
+
+#[test]
+fn generate_internal_config_load_balancing_edit() {
+    let net = super::net_for_route_maps::<SimplePrefix>();
+    let mut ip = super::addressor(&net);
+    let mut cfg_gen =
+        CiscoFrrCfgGen::new(&net, 0.into(), Target, super::iface_names(Target)).unwrap();
+    InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+
+    let lb = ConfigExpr::LoadBalancing { router: 0.into() };
+
+    assert_str_eq!(
+        cfg_gen
+            .generate_command(&net, &mut ip, Insert(lb.clone()))
+            .unwrap(),
+        "\
+set policy-options policy-statement load-balance then load-balance per-packet
+set routing-options forwarding-table export load-balance
+"
+    );
+
+    assert_str_eq!(
+        cfg_gen.generate_command(&net, &mut ip, Remove(lb)).unwrap(),
+        "delete routing-options forwarding-table export load-balance\n"
+    );
+}
@@ -32,11 +32,18 @@ use crate::{
 mod cisco;
 mod exabgp;
 mod frr;
+mod ios;
+mod junos;
+mod parser;
+#[cfg(feature = "rand")]
+mod round_trip;
 
 pub(self) fn iface_names(target: Target) -> Vec<String> {
     match target {
         Target::CiscoNexus7000 => (1..=48).map(|i| format!("Ethernet8/{i}")).collect(),
+        Target::CiscoIos => (0..48).map(|i| format!("GigabitEthernet0/{i}")).collect(),
         Target::Frr => (1..=8).map(|i| format!("eth{i}")).collect(),
+        Target::Junos => (0..48).map(|i| format!("ge-0/0/{i}")).collect(),
     }
 }
 
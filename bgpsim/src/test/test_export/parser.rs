@@ -0,0 +1,542 @@
+// BgpSim: BGP Network Simulator written in Rust
+// Copyright (C) 2022-2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+use std::collections::HashMap;
+
+use pretty_assertions::assert_eq;
+
+use super::{addressor, iface_names, net_for_route_maps};
+use crate::{
+    bgp::{BgpAggregate, BgpSessionType, Mrai, Origin},
+    event::BasicEventQueue,
+    export::{
+        cisco_frr_generators::Target, cisco_frr_parser::CiscoFrrParser, Addressor, CiscoFrrCfgGen,
+        ExternalCfgGen, InternalCfgGen,
+    },
+    network::Network,
+    route_map::{AsPathRegex, RouteMapBuilder, RouteMapDirection},
+    router::StaticRoute,
+    types::{Ipv4Prefix, RouterId},
+};
+
+/// Generate the configuration of all routers in the network, ordered by their router-id.
+pub(super) fn export_all<A: Addressor<Ipv4Prefix>>(
+    net: &Network<Ipv4Prefix, BasicEventQueue<Ipv4Prefix>>,
+    ip: &mut A,
+    target: Target,
+    ifaces: impl Fn(RouterId) -> Vec<String>,
+) -> Vec<(String, String)> {
+    let mut routers = net.get_routers();
+    routers.extend(net.get_external_routers());
+    routers.sort();
+    routers
+        .into_iter()
+        .map(|r| {
+            let mut cfg_gen = CiscoFrrCfgGen::new(net, r, target, ifaces(r)).unwrap();
+            let cfg = if net.get_device(r).is_internal() {
+                InternalCfgGen::generate_config(&mut cfg_gen, net, ip).unwrap()
+            } else {
+                ExternalCfgGen::generate_config(&mut cfg_gen, net, ip).unwrap()
+            };
+            (net.get_router_name(r).unwrap().to_string(), cfg)
+        })
+        .collect()
+}
+
+fn net_with_advertisements() -> Network<Ipv4Prefix, BasicEventQueue<Ipv4Prefix>> {
+    let mut net = net_for_route_maps::<Ipv4Prefix>();
+    net.advertise_external_route(4.into(), 0, [4, 4, 2, 1], Some(10), [20])
+        .unwrap();
+    net.advertise_external_route(5.into(), 0, [5, 3, 1], None, [10, 30])
+        .unwrap();
+    net.advertise_external_route_with_origin(5.into(), 1, [5], Some(0), [], Origin::Incomplete)
+        .unwrap();
+    net.set_bgp_route_map(
+        1.into(),
+        5.into(),
+        RouteMapDirection::Incoming,
+        RouteMapBuilder::new()
+            .allow()
+            .order(10)
+            .match_community(30)
+            .set_origin(Origin::Egp)
+            .exit()
+            .build(),
+    )
+    .unwrap();
+    net.set_always_compare_med(0.into(), true).unwrap();
+    net.set_compare_router_id(1.into(), true).unwrap();
+    net
+}
+
+fn import_round_trip(target: Target) {
+    let net = net_with_advertisements();
+    let mut ip = addressor(&net);
+    let configs = export_all(&net, &mut ip, target, |_| iface_names(target));
+
+    let parser = CiscoFrrParser::new(configs.iter().map(|(n, c)| (n.as_str(), c.as_str())));
+    let imported = parser.get_network(BasicEventQueue::new()).unwrap();
+    assert_eq!(imported.unsupported, vec![]);
+    let new = imported.net;
+
+    // same routers, topology, and sessions
+    let sorted = |mut x: Vec<RouterId>| {
+        x.sort();
+        x
+    };
+    assert_eq!(sorted(new.get_routers()), sorted(net.get_routers()));
+    assert_eq!(
+        sorted(new.get_external_routers()),
+        sorted(net.get_external_routers())
+    );
+    for r in net.get_routers() {
+        let old_r = net.get_device(r).unwrap_internal();
+        let new_r = new.get_device(r).unwrap_internal();
+        assert_eq!(new_r.name(), old_r.name());
+        assert_eq!(new_r.get_bgp_sessions(), old_r.get_bgp_sessions());
+        assert_eq!(
+            new_r.get_always_compare_med(),
+            old_r.get_always_compare_med()
+        );
+        assert_eq!(new_r.get_compare_router_id(), old_r.get_compare_router_id());
+        for n in net.get_topology().neighbors(r) {
+            assert_eq!(
+                new.get_link_weigth(r, n).unwrap(),
+                net.get_link_weigth(r, n).unwrap()
+            );
+            for dir in [RouteMapDirection::Incoming, RouteMapDirection::Outgoing] {
+                assert_eq!(
+                    new_r.get_bgp_route_maps(n, dir),
+                    old_r.get_bgp_route_maps(n, dir)
+                );
+            }
+        }
+    }
+    assert!(new.weak_eq(&net));
+    assert_eq!(
+        new.get_device(5.into())
+            .unwrap_external()
+            .get_advertised_route(Ipv4Prefix::from(1))
+            .map(|r| r.origin),
+        Some(Origin::Incomplete)
+    );
+
+    // the address plan reproduces the same configuration
+    let plan = imported.addresses;
+    let mut new_ip = plan.addressor(&new).unwrap();
+    let new_configs = export_all(&new, &mut new_ip, target, |r| plan.iface_names(r));
+    for ((name, cfg), (new_name, new_cfg)) in configs.iter().zip(new_configs.iter()) {
+        assert_eq!(name, new_name);
+        assert_eq!(cfg.lines().count(), new_cfg.lines().count());
+        let mut lines = cfg.lines().collect::<Vec<_>>();
+        let mut new_lines = new_cfg.lines().collect::<Vec<_>>();
+        lines.sort();
+        new_lines.sort();
+        assert_eq!(lines, new_lines);
+    }
+}
+
+#[test]
+fn import_frr() {
+    import_round_trip(Target::Frr)
+}
+
+#[test]
+fn import_cisco() {
+    import_round_trip(Target::CiscoNexus7000)
+}
+
+#[test]
+fn import_ios() {
+    import_round_trip(Target::CiscoIos)
+}
+
+#[test]
+fn export_match_origin() {
+    let mut net = net_with_advertisements();
+    net.set_bgp_route_map(
+        0.into(),
+        4.into(),
+        RouteMapDirection::Incoming,
+        RouteMapBuilder::new()
+            .deny()
+            .order(5)
+            .match_origin(Origin::Incomplete)
+            .build(),
+    )
+    .unwrap();
+    let mut ip = addressor(&net);
+
+    let mut cfg_gen =
+        CiscoFrrCfgGen::new(&net, 0.into(), Target::Frr, iface_names(Target::Frr)).unwrap();
+    let cfg = InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+    assert!(cfg.contains("  match origin incomplete\n"));
+    assert!(cfg.contains("  bgp always-compare-med\n"));
+
+    // Cisco Nexus and IOS cannot match on the origin
+    for target in [Target::CiscoNexus7000, Target::CiscoIos] {
+        let mut cfg_gen = CiscoFrrCfgGen::new(&net, 0.into(), target, iface_names(target)).unwrap();
+        assert!(InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).is_err());
+    }
+
+    // the route-map is imported again
+    let configs = export_all(&net, &mut ip, Target::Frr, |_| iface_names(Target::Frr));
+    let imported = CiscoFrrParser::new(configs.iter().map(|(n, c)| (n.as_str(), c.as_str())))
+        .get_network(BasicEventQueue::new())
+        .unwrap();
+    assert_eq!(imported.unsupported, vec![]);
+    assert!(imported.net.weak_eq(&net));
+}
+
+#[test]
+fn export_as_path_regex_prepend() {
+    let mut net = net_with_advertisements();
+    net.set_bgp_route_map(
+        0.into(),
+        4.into(),
+        RouteMapDirection::Incoming,
+        RouteMapBuilder::new()
+            .allow()
+            .order(5)
+            .match_as_path_regex(AsPathRegex::new("^4_4_").unwrap())
+            .prepend_as_path([4, 4])
+            .build(),
+    )
+    .unwrap();
+    let mut ip = addressor(&net);
+
+    let mut cfg_gen =
+        CiscoFrrCfgGen::new(&net, 0.into(), Target::Frr, iface_names(Target::Frr)).unwrap();
+    let cfg = InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+    assert!(cfg.contains(" permit ^4_4_\n"));
+    assert!(cfg.contains("  set as-path prepend 4 4\n"));
+
+    let target = Target::Junos;
+    let mut cfg_gen = CiscoFrrCfgGen::new(&net, 0.into(), target, iface_names(target)).unwrap();
+    let cfg = InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+    assert!(cfg.contains(" \"4 4 .*\"\n"));
+    assert!(cfg.contains(" then as-path-prepend \"4 4\"\n"));
+
+    // both routes are imported again
+    for target in [Target::Frr, Target::CiscoNexus7000, Target::CiscoIos] {
+        let configs = export_all(&net, &mut ip, target, |_| iface_names(target));
+        let imported = CiscoFrrParser::new(configs.iter().map(|(n, c)| (n.as_str(), c.as_str())))
+            .get_network(BasicEventQueue::new())
+            .unwrap();
+        assert_eq!(imported.unsupported, vec![]);
+        assert!(imported.net.weak_eq(&net));
+    }
+
+    // Junos cannot use arbitrary regular expressions
+    net.set_bgp_route_map(
+        0.into(),
+        4.into(),
+        RouteMapDirection::Incoming,
+        RouteMapBuilder::new()
+            .allow()
+            .order(5)
+            .match_as_path_regex(AsPathRegex::new("^(4|5)_").unwrap())
+            .build(),
+    )
+    .unwrap();
+    let mut ip = addressor(&net);
+    let mut cfg_gen = CiscoFrrCfgGen::new(&net, 0.into(), target, iface_names(target)).unwrap();
+    assert!(InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).is_err());
+}
+
+#[test]
+fn export_mrai() {
+    let mut net = net_with_advertisements();
+    net.set_bgp_mrai(0.into(), 4.into(), Some(Mrai::new(5.0)))
+        .unwrap();
+    let mut ip = addressor(&net);
+
+    let mut cfg_gen =
+        CiscoFrrCfgGen::new(&net, 0.into(), Target::Frr, iface_names(Target::Frr)).unwrap();
+    let cfg = InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+    assert!(cfg.contains(" advertisement-interval 5\n"));
+
+    let target = Target::Junos;
+    let mut cfg_gen = CiscoFrrCfgGen::new(&net, 0.into(), target, iface_names(target)).unwrap();
+    let cfg = InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+    assert!(cfg.contains(" out-delay 5\n"));
+
+    for target in [Target::Frr, Target::CiscoNexus7000, Target::CiscoIos] {
+        let configs = export_all(&net, &mut ip, target, |_| iface_names(target));
+        let imported = CiscoFrrParser::new(configs.iter().map(|(n, c)| (n.as_str(), c.as_str())))
+            .get_network(BasicEventQueue::new())
+            .unwrap();
+        assert_eq!(imported.unsupported, vec![]);
+        let r0 = imported.net.get_device(0.into()).unwrap_internal();
+        assert_eq!(r0.get_bgp_mrai(4.into()), Some(Mrai::new(5.0)));
+        assert_eq!(r0.get_bgp_mrai(1.into()), None);
+    }
+}
+
+#[test]
+fn export_aggregate() {
+    let mut net = net_with_advertisements();
+    let agg = Ipv4Prefix::from("100.0.0.0/16".parse::<ipnet::Ipv4Net>().unwrap());
+    net.set_bgp_aggregate(0.into(), agg, Some(BgpAggregate::new(true, true)))
+        .unwrap();
+    let mut ip = addressor(&net);
+
+    let mut cfg_gen =
+        CiscoFrrCfgGen::new(&net, 0.into(), Target::Frr, iface_names(Target::Frr)).unwrap();
+    let cfg = InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+    assert!(cfg.contains(" aggregate-address 100.0.0.0/16 as-set summary-only\n"));
+
+    let target = Target::Junos;
+    let mut cfg_gen = CiscoFrrCfgGen::new(&net, 0.into(), target, iface_names(target)).unwrap();
+    assert!(InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).is_err());
+
+    for target in [Target::Frr, Target::CiscoNexus7000, Target::CiscoIos] {
+        let configs = export_all(&net, &mut ip, target, |_| iface_names(target));
+        let imported = CiscoFrrParser::new(configs.iter().map(|(n, c)| (n.as_str(), c.as_str())))
+            .get_network(BasicEventQueue::new())
+            .unwrap();
+        assert_eq!(imported.unsupported, vec![]);
+        let r0 = imported.net.get_device(0.into()).unwrap_internal();
+        assert_eq!(
+            r0.get_bgp_aggregate(agg),
+            Some(BgpAggregate::new(true, true))
+        );
+        let r1 = imported.net.get_device(1.into()).unwrap_internal();
+        assert_eq!(r1.get_bgp_aggregate(agg), None);
+    }
+}
+
+#[test]
+fn export_network() {
+    let mut net = net_with_advertisements();
+    let p5 = Ipv4Prefix::from(5);
+    let p6 = Ipv4Prefix::from(6);
+    net.set_bgp_network(0.into(), p5, true).unwrap();
+    net.set_static_route(1.into(), p6, Some(StaticRoute::Drop))
+        .unwrap();
+    net.set_bgp_redistribute_static(1.into(), true).unwrap();
+    let mut ip = addressor(&net);
+
+    let mut cfg_gen =
+        CiscoFrrCfgGen::new(&net, 0.into(), Target::Frr, iface_names(Target::Frr)).unwrap();
+    let cfg = InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+    assert!(cfg.contains("    network 100.0.5.0/24\n"));
+    assert!(cfg.contains("ip route 100.0.5.0/24 Null0\n"));
+
+    let mut cfg_gen =
+        CiscoFrrCfgGen::new(&net, 1.into(), Target::Frr, iface_names(Target::Frr)).unwrap();
+    let cfg = InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+    assert!(cfg.contains("    redistribute static\n"));
+
+    let target = Target::CiscoNexus7000;
+    let mut cfg_gen = CiscoFrrCfgGen::new(&net, 1.into(), target, iface_names(target)).unwrap();
+    let cfg = InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+    assert!(cfg.contains("    redistribute static route-map redistribute-static\n"));
+    assert!(cfg.contains("route-map redistribute-static permit 65535\n"));
+
+    let target = Target::Junos;
+    let mut cfg_gen = CiscoFrrCfgGen::new(&net, 1.into(), target, iface_names(target)).unwrap();
+    let cfg = InternalCfgGen::generate_config(&mut cfg_gen, &net, &mut ip).unwrap();
+    assert!(cfg.contains(" term static from protocol static\n"));
+
+    for target in [Target::Frr, Target::CiscoNexus7000, Target::CiscoIos] {
+        let configs = export_all(&net, &mut ip, target, |_| iface_names(target));
+        let imported = CiscoFrrParser::new(configs.iter().map(|(n, c)| (n.as_str(), c.as_str())))
+            .get_network(BasicEventQueue::new())
+            .unwrap();
+        assert_eq!(imported.unsupported, vec![]);
+        let r0 = imported.net.get_device(0.into()).unwrap_internal();
+        assert_eq!(r0.get_bgp_networks().iter().collect::<Vec<_>>(), vec![&p5]);
+        assert_eq!(r0.get_static_routes().get(&p5), None);
+        assert!(!r0.get_bgp_redistribute_static());
+        let r1 = imported.net.get_device(1.into()).unwrap_internal();
+        assert!(r1.get_bgp_networks().iter().next().is_none());
+        assert_eq!(r1.get_static_routes().get(&p6), Some(&StaticRoute::Drop));
+        assert!(r1.get_bgp_redistribute_static());
+    }
+}
+
+#[test]
+fn import_unconfigured_ebgp_peer() {
+    let r0 = "\
+hostname r0
+interface eth0
+  ip address 10.128.0.1/30
+  ip ospf cost 5
+  ip ospf area 0
+exit
+interface eth1
+  ip address 10.192.0.1/30
+  ip ospf cost 1
+exit
+interface lo
+  ip address 10.0.0.1/32
+exit
+router ospf
+  ospf router-id 10.0.0.1
+  maximum-paths 4
+exit
+router bgp 65535
+  neighbor 10.0.1.1 remote-as 65535
+  neighbor 10.192.0.2 remote-as 100
+  address-family ipv4 unicast
+    neighbor 10.0.1.1 route-reflector-client
+  exit-address-family
+exit
+";
+    let r1 = "\
+interface eth0
+  ip address 10.128.0.2/30
+  ip ospf cost 7
+  ip ospf area 0.0.0.1
+exit
+interface lo
+  ip address 10.0.1.1/32
+exit
+router ospf
+  router-id 10.0.1.1
+exit
+router bgp 65535
+  neighbor 10.0.0.1 remote-as 65535
+exit
+ip route 100.0.0.0/24 eth0
+";
+    let imported = CiscoFrrParser::new([("a", r0), ("r1", r1)])
+        .get_network(BasicEventQueue::new())
+        .unwrap();
+    assert_eq!(imported.unsupported, vec![]);
+    let net = imported.net;
+
+    let (r0, r1, e) = (0.into(), 1.into(), 2.into());
+    assert_eq!(net.get_router_name(r0).unwrap(), "r0");
+    assert_eq!(net.get_router_name(e).unwrap(), "AS100_10.192.0.2");
+    assert_eq!(net.get_device(e).unwrap_external().as_id(), 100.into());
+    assert_eq!(net.get_link_weigth(r0, r1).unwrap(), 5.0);
+    assert_eq!(net.get_link_weigth(r1, r0).unwrap(), 7.0);
+    assert_eq!(net.get_link_weigth(r0, e).unwrap(), 1.0);
+    assert_eq!(net.get_link_weigth(e, r0).unwrap(), 1.0);
+    assert!(net.get_device(r0).unwrap_internal().get_load_balancing());
+    assert!(!net.get_device(r1).unwrap_internal().get_load_balancing());
+    assert_eq!(
+        net.get_device(r0).unwrap_internal().get_bgp_sessions(),
+        &HashMap::from([(r1, BgpSessionType::IBgpClient), (e, BgpSessionType::EBgp)])
+    );
+    assert_eq!(
+        net.get_device(r1)
+            .unwrap_internal()
+            .get_static_routes()
+            .get(&"100.0.0.0/24".parse().unwrap()),
+        Some(&crate::router::StaticRoute::Direct(r0))
+    );
+
+    let plan = imported.addresses;
+    assert_eq!(plan.iface_names(r0), vec!["eth0", "eth1"]);
+    assert_eq!(plan.iface_names(e), Vec::<String>::new());
+    let mut ip = plan.addressor(&net).unwrap();
+    assert_eq!(
+        ip.router_address(r1).unwrap(),
+        "10.0.1.1".parse::<std::net::Ipv4Addr>().unwrap()
+    );
+    assert_eq!(
+        ip.iface_address(e, r0).unwrap(),
+        "10.192.0.2".parse::<std::net::Ipv4Addr>().unwrap()
+    );
+    // new addresses are assigned after the existing ones
+    assert_eq!(
+        ip.router_address(e).unwrap(),
+        "2.0.0.1".parse::<std::net::Ipv4Addr>().unwrap()
+    );
+}
+
+#[test]
+fn import_unsupported() {
+    let r0 = "\
+interface eth0
+  ip address 10.128.0.1/30
+  ip ospf cost 5
+  ip ospf bfd
+exit
+interface lo
+  ip address 10.0.0.1/32
+exit
+router ospf
+  router-id 10.0.0.1
+exit
+router rip
+  network 10.0.0.0/8
+exit
+router bgp 65535
+  neighbor 10.0.1.1 remote-as 65535
+  neighbor 10.0.1.1 weight 200
+  neighbor 10.0.1.1 route-map rm-in in
+exit
+ip prefix-list pl seq 5 permit 100.0.0.0/8 le 24
+route-map rm-in permit 10
+  match ip address prefix-list pl
+  set weight 200
+exit
+route-map rm-in permit 20
+  set local-preference 50
+  set comm-list undefined delete
+exit
+";
+    let r1 = "\
+interface eth0
+  ip address 10.128.0.2/30
+  ip ospf cost 5
+exit
+router ospf
+  router-id 10.0.1.1
+exit
+router bgp 65535
+  neighbor 10.0.0.1 remote-as 65535
+exit
+";
+    let imported = CiscoFrrParser::new([("r0", r0), ("r1", r1)])
+        .get_network(BasicEventQueue::new())
+        .unwrap();
+    let unsupported = imported
+        .unsupported
+        .iter()
+        .map(|x| (x.router.as_str(), x.line, x.statement.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        unsupported,
+        vec![
+            ("r0", 4, "ip ospf bfd"),
+            ("r0", 12, "router rip"),
+            ("r0", 17, "neighbor 10.0.1.1 weight 200"),
+            ("r0", 20, "ip prefix-list pl seq 5 permit 100.0.0.0/8 le 24"),
+            ("r0", 22, "match ip address prefix-list pl"),
+            ("r0", 27, "set comm-list undefined delete"),
+        ]
+    );
+
+    // OSPF is not enabled on the interfaces, and the unsupported route-map item is skipped.
+    let net = imported.net;
+    assert_eq!(
+        net.get_link_weigth(0.into(), 1.into()).unwrap(),
+        f64::INFINITY
+    );
+    let maps = net
+        .get_device(0.into())
+        .unwrap_internal()
+        .get_bgp_route_maps(1.into(), RouteMapDirection::Incoming);
+    assert_eq!(maps.len(), 2);
+    assert_eq!(maps[0].order(), -32748);
+    assert!(maps[1].state().is_deny());
+}
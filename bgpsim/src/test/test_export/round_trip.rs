@@ -0,0 +1,280 @@
+// BgpSim: BGP Network Simulator written in Rust
+// Copyright (C) 2022-2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Property-based round-trip tests between the Cisco/FRR exporter and the importer. A random
+//! network is generated using the [`NetworkBuilder`], exported, and parsed back. The imported
+//! network must then be in the same state as the original one.
+
+use proptest::{collection::vec, prelude::*};
+use rand::{rngs::StdRng, SeedableRng};
+
+use super::{addressor, parser::export_all};
+use crate::{
+    builder::{
+        extend_to_k_external_routers_seeded, k_highest_degree_nodes_seeded,
+        uniform_integer_link_weight_seeded, unique_preferences_seeded, NetworkBuilder,
+    },
+    event::BasicEventQueue,
+    export::{cisco_frr_generators::Target, cisco_frr_parser::CiscoFrrParser},
+    network::Network,
+    route_map::{AsPathRegex, RouteMapBuilder, RouteMapDirection},
+    types::{Ipv4Prefix, RouterId},
+};
+
+type Net = Network<Ipv4Prefix, BasicEventQueue<Ipv4Prefix>>;
+
+/// Description of a single route-map item on an eBGP session.
+#[derive(Debug, Clone)]
+struct RmItem {
+    allow: bool,
+    cond: RmCond,
+    local_pref: Option<u32>,
+    community: Option<u32>,
+    /// Prepend the path with `4 * prepend` ASes. The advertised paths differ in length by one, so
+    /// prepending a multiple of four ASes never causes two routes to have equal length. Otherwise,
+    /// the outcome would depend on the order of events.
+    prepend: Option<u32>,
+    exit: bool,
+}
+
+#[derive(Debug, Clone)]
+enum RmCond {
+    Any,
+    Community(u32),
+    Prefix(u32),
+    /// AS path starting with the given AS. External routers have the AS number of their router-id.
+    AsPath(u32),
+}
+
+/// Parameters used to generate a random network.
+#[derive(Debug, Clone)]
+struct NetParams {
+    /// Number of internal routers
+    n: usize,
+    /// Parent of each router `i > 0` (modulo `i`), which makes the network connected.
+    parents: Vec<usize>,
+    /// Additional links between internal routers.
+    links: Vec<(usize, usize)>,
+    /// Number of external routers
+    externals: usize,
+    /// Number of route reflectors. If zero, then use an iBGP full-mesh. At most one route
+    /// reflector is used, as clients cannot break the tie between the same route reflected by two
+    /// route reflectors, such that the outcome depends on the order of events.
+    route_reflectors: usize,
+    /// Number of prefixes that are advertised.
+    prefixes: u32,
+    load_balancing: bool,
+    /// Incoming route-maps on the eBGP sessions of internal routers.
+    route_maps: Vec<Vec<RmItem>>,
+    seed: u64,
+}
+
+fn rm_item() -> impl Strategy<Value = RmItem> {
+    (
+        any::<bool>(),
+        prop_oneof![
+            Just(RmCond::Any),
+            (1u32..4).prop_map(RmCond::Community),
+            (0u32..3).prop_map(RmCond::Prefix),
+            (2u32..10).prop_map(RmCond::AsPath),
+        ],
+        prop::option::of(50u32..150),
+        prop::option::of(1u32..4),
+        prop::option::of(1u32..4),
+        any::<bool>(),
+    )
+        .prop_map(
+            |(allow, cond, local_pref, community, prepend, exit)| RmItem {
+                allow,
+                cond,
+                local_pref,
+                community,
+                prepend,
+                exit,
+            },
+        )
+}
+
+fn net_params() -> impl Strategy<Value = NetParams> {
+    (2usize..7)
+        .prop_flat_map(|n| {
+            (
+                Just(n),
+                vec(any::<usize>(), n - 1),
+                vec((0..n, 0..n), 0..n),
+                1usize..4,
+                0usize..2,
+                1u32..4,
+                any::<bool>(),
+                vec(vec(rm_item(), 0..4), 0..4),
+                any::<u64>(),
+            )
+        })
+        .prop_map(
+            |(n, parents, links, externals, rrs, prefixes, lb, route_maps, seed)| NetParams {
+                n,
+                parents,
+                links,
+                externals,
+                route_reflectors: rrs,
+                prefixes,
+                load_balancing: lb,
+                route_maps,
+                seed,
+            },
+        )
+}
+
+/// Build the network described by `params`.
+fn build_net(params: &NetParams) -> Net {
+    let mut rng = StdRng::seed_from_u64(params.seed);
+    let mut net: Net = Network::new(BasicEventQueue::new());
+
+    let routers: Vec<RouterId> = (0..params.n)
+        .map(|i| net.add_router(format!("R{i}")))
+        .collect();
+    for (i, p) in params.parents.iter().enumerate() {
+        net.add_link(routers[i + 1], routers[p % (i + 1)]);
+    }
+    for (a, b) in params.links.iter() {
+        if a != b
+            && net
+                .get_topology()
+                .find_edge(routers[*a], routers[*b])
+                .is_none()
+        {
+            net.add_link(routers[*a], routers[*b]);
+        }
+    }
+    net.build_connected_graph();
+
+    let externals = net
+        .build_external_routers(
+            extend_to_k_external_routers_seeded,
+            (&mut rng, params.externals),
+        )
+        .unwrap();
+    net.build_link_weights_seeded(&mut rng, uniform_integer_link_weight_seeded, (1, 100))
+        .unwrap();
+    if params.route_reflectors == 0 {
+        net.build_ibgp_full_mesh().unwrap();
+    } else {
+        net.build_ibgp_route_reflection(
+            k_highest_degree_nodes_seeded,
+            (&mut rng, params.route_reflectors),
+        )
+        .unwrap();
+    }
+    net.build_ebgp_sessions().unwrap();
+
+    if params.load_balancing {
+        for r in routers.iter() {
+            net.set_load_balancing(*r, true).unwrap();
+        }
+    }
+
+    for (ext, items) in externals.iter().zip(params.route_maps.iter()) {
+        let r = net.get_topology().neighbors(*ext).next().unwrap();
+        for (i, item) in items.iter().enumerate() {
+            let mut rm = RouteMapBuilder::new();
+            rm.order(10 * (i as u16 + 1));
+            if item.allow {
+                rm.allow();
+            } else {
+                rm.deny();
+            }
+            match item.cond {
+                RmCond::Any => {}
+                RmCond::Community(c) => {
+                    rm.match_community(c);
+                }
+                RmCond::Prefix(p) => {
+                    rm.match_prefix(Ipv4Prefix::from(p));
+                }
+                RmCond::AsPath(a) => {
+                    rm.match_as_path_regex(AsPathRegex::new(format!("^{a}_")).unwrap());
+                }
+            }
+            if let Some(lp) = item.local_pref {
+                rm.set_local_pref(lp);
+            }
+            if let Some(c) = item.community {
+                rm.set_community(c);
+            }
+            if let Some(a) = item.prepend {
+                rm.prepend_as_path(vec![a; 4 * a as usize]);
+            }
+            if item.exit {
+                rm.exit();
+            } else {
+                rm.continue_next();
+            }
+            net.set_bgp_route_map(r, *ext, RouteMapDirection::Incoming, rm.build())
+                .unwrap();
+        }
+    }
+
+    for p in 0..params.prefixes {
+        net.build_advertisements(
+            Ipv4Prefix::from(p),
+            unique_preferences_seeded,
+            (&mut rng, params.externals),
+        )
+        .unwrap();
+    }
+
+    net
+}
+
+fn round_trip(target: Target, params: NetParams) -> Result<(), TestCaseError> {
+    let net = build_net(&params);
+    let mut ip = addressor(&net);
+    let ifaces: Vec<String> = match target {
+        Target::CiscoNexus7000 => (1..=48).map(|i| format!("Ethernet8/{i}")).collect(),
+        Target::CiscoIos => (0..48).map(|i| format!("GigabitEthernet0/{i}")).collect(),
+        _ => (1..=48).map(|i| format!("eth{i}")).collect(),
+    };
+    let configs = export_all(&net, &mut ip, target, |_| ifaces.clone());
+
+    let parser = CiscoFrrParser::new(configs.iter().map(|(n, c)| (n.as_str(), c.as_str())));
+    let imported = parser.get_network(BasicEventQueue::new()).unwrap();
+    prop_assert_eq!(imported.unsupported, vec![]);
+    let new = imported.net;
+
+    prop_assert_eq!(new.get_forwarding_state(), net.get_forwarding_state());
+    prop_assert!(new.weak_eq(&net));
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn round_trip_frr(params in net_params()) {
+        round_trip(Target::Frr, params)?;
+    }
+
+    #[test]
+    fn round_trip_cisco(params in net_params()) {
+        round_trip(Target::CiscoNexus7000, params)?;
+    }
+
+    #[test]
+    fn round_trip_ios(params in net_params()) {
+        round_trip(Target::CiscoIos, params)?;
+    }
+}
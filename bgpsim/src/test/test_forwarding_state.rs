@@ -411,4 +411,93 @@ mod ipv4 {
             vec![vec![r2, r3, r4, e4]]
         );
     }
+
+    #[test]
+    fn bgp_aggregate() {
+        use crate::bgp::BgpAggregate;
+        use crate::types::NetworkError;
+
+        let mut net: Network<_, BasicEventQueue<Ipv4Prefix>> = Network::new(Default::default());
+
+        let r1 = net.add_router("R1");
+        let r2 = net.add_router("R2");
+        let e1 = net.add_external_router("e1", 1);
+        let e2 = net.add_external_router("e2", 2);
+
+        net.add_link(r1, r2);
+        net.add_link(r1, e1);
+        net.add_link(r2, e2);
+
+        net.set_link_weight(r1, r2, 1.0).unwrap();
+        net.set_link_weight(r2, r1, 1.0).unwrap();
+        net.set_link_weight(r1, e1, 1.0).unwrap();
+        net.set_link_weight(e1, r1, 1.0).unwrap();
+        net.set_link_weight(r2, e2, 1.0).unwrap();
+        net.set_link_weight(e2, r2, 1.0).unwrap();
+
+        net.set_bgp_session(r1, r2, Some(IBgpPeer)).unwrap();
+        net.set_bgp_session(r1, e1, Some(EBgp)).unwrap();
+        net.set_bgp_session(r2, e2, Some(EBgp)).unwrap();
+
+        net.advertise_external_route(e1, prefix!("100.0.0.0/16"), [1, 10], None, None)
+            .unwrap();
+
+        net.set_bgp_aggregate(
+            r1,
+            prefix!("100.0.0.0/8" as),
+            Some(BgpAggregate::new(true, false)),
+        )
+        .unwrap();
+
+        // R2 only learns the aggregate, and sends it towards R1.
+        let r = net.get_device(r2).unwrap_internal();
+        assert!(r
+            .get_selected_bgp_route(prefix!("100.0.0.0/16" as))
+            .is_none());
+        assert_eq!(
+            r.get_selected_bgp_route(prefix!("100.0.0.0/8" as))
+                .unwrap()
+                .route
+                .next_hop,
+            r1
+        );
+        assert!(net
+            .get_config()
+            .unwrap()
+            .iter()
+            .any(|e| matches!(e, Aggregate { router, .. } if *router == r1)));
+
+        let mut fw_state = net.get_forwarding_state();
+        assert_eq!(
+            fw_state.get_paths(r2, prefix!("100.0.0.1/32" as)).unwrap(),
+            vec![vec![r2, r1, e1]]
+        );
+        assert_eq!(
+            fw_state.get_paths(r2, prefix!("100.1.0.1/32" as)),
+            Err(NetworkError::ForwardingBlackHole(vec![r2, r1]))
+        );
+
+        // removing the aggregate re-advertises the more-specific route
+        net.set_bgp_aggregate(r1, prefix!("100.0.0.0/8" as), None)
+            .unwrap();
+        let r = net.get_device(r2).unwrap_internal();
+        assert!(r
+            .get_selected_bgp_route(prefix!("100.0.0.0/8" as))
+            .is_none());
+        assert!(r
+            .get_selected_bgp_route(prefix!("100.0.0.0/16" as))
+            .is_some());
+
+        #[cfg(feature = "undo")]
+        {
+            net.undo_action().unwrap();
+            let r = net.get_device(r2).unwrap_internal();
+            assert!(r
+                .get_selected_bgp_route(prefix!("100.0.0.0/8" as))
+                .is_some());
+            assert!(r
+                .get_selected_bgp_route(prefix!("100.0.0.0/16" as))
+                .is_none());
+        }
+    }
 }
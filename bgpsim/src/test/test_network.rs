@@ -23,10 +23,14 @@ mod t {
     use std::collections::{BTreeMap, BTreeSet};
 
     use crate::{
-        bgp::{BgpRoute, BgpSessionType::*},
+        bgp::{BgpRoute, BgpSessionState, BgpSessionTimers, BgpSessionType::*, Origin},
         builder::{constant_link_weight, equal_preferences, NetworkBuilder},
-        config::{ConfigExpr::IgpLinkWeight, NetworkConfig},
+        config::{
+            ConfigExpr::{self, IgpLinkWeight},
+            NetworkConfig,
+        },
         event::BasicEventQueue,
+        interactive::InteractiveNetwork,
         network::Network,
         prelude::BgpSessionType,
         route_map::{
@@ -424,6 +428,173 @@ mod t {
         assert_eq!(net, net_hist_1);
     }
 
+    fn get_session_state<P: Prefix>(
+        net: &Network<P, BasicEventQueue<P>>,
+        router: RouterId,
+        neighbor: RouterId,
+    ) -> Option<BgpSessionState> {
+        net.get_device(router)
+            .unwrap_internal()
+            .get_bgp_session_state(neighbor)
+    }
+
+    #[test]
+    fn test_bgp_session_fsm<P: Prefix>() {
+        let mut net = get_test_net_bgp::<P>();
+        let p = P::from(0);
+
+        net.advertise_external_route(*E1, p, vec![AsId(65101), AsId(65201)], None, None)
+            .unwrap();
+        net.advertise_external_route(*E4, p, vec![AsId(65104), AsId(65201)], None, None)
+            .unwrap();
+        net.set_bgp_session_timers(Some(BgpSessionTimers::default()))
+            .unwrap();
+
+        // all configured sessions are established
+        for r in net.get_routers() {
+            for n in net
+                .get_device(r)
+                .unwrap_internal()
+                .get_bgp_sessions()
+                .keys()
+            {
+                assert_eq!(
+                    get_session_state(&net, r, *n),
+                    Some(BgpSessionState::Established)
+                );
+            }
+        }
+        test_route!(net, *R4, p, [*R4, *E4]);
+
+        // re-establish the session between R4 and E4
+        net.set_bgp_session(*R4, *E4, None).unwrap();
+        test_route!(net, *R4, p, [*R4, *R2, *R3, *R1, *E1]);
+        assert_eq!(get_session_state(&net, *R4, *E4), None);
+
+        net.manual_simulation();
+        net.set_bgp_session(*R4, *E4, Some(EBgp)).unwrap();
+        assert_eq!(
+            get_session_state(&net, *R4, *E4),
+            Some(BgpSessionState::Connect)
+        );
+        net.simulate_step().unwrap();
+        assert_eq!(
+            get_session_state(&net, *R4, *E4),
+            Some(BgpSessionState::OpenSent)
+        );
+        test_route!(net, *R4, p, [*R4, *R2, *R3, *R1, *E1]);
+
+        net.simulate().unwrap();
+        assert_eq!(
+            get_session_state(&net, *R4, *E4),
+            Some(BgpSessionState::Established)
+        );
+        test_route!(net, *R4, p, [*R4, *E4]);
+        test_route!(net, *R2, p, [*R2, *R4, *E4]);
+    }
+
+    #[test]
+    fn test_bgp_session_state_without_fsm<P: Prefix>() {
+        let mut net = get_test_net_bgp::<P>();
+
+        assert_eq!(
+            net.get_bgp_session_state(*R1, *R2),
+            Ok(Some(BgpSessionState::Established))
+        );
+        assert_eq!(
+            net.get_bgp_session_state(*R1, *E1),
+            Ok(Some(BgpSessionState::Established))
+        );
+        assert_eq!(net.get_bgp_session_state(*R1, *E4), Ok(None));
+        assert!(net.get_bgp_session_state(*E1, *R1).is_err());
+
+        // a session that is only configured on one end is not established.
+        net.routers
+            .get_mut(&*R2)
+            .unwrap()
+            .set_bgp_session::<()>(*R1, None)
+            .unwrap();
+        assert_eq!(
+            net.get_bgp_session_state(*R1, *R2),
+            Ok(Some(BgpSessionState::Idle))
+        );
+        assert_eq!(net.get_bgp_session_state(*R2, *R1), Ok(None));
+
+        // a session with an unreachable neighbor is not established.
+        net.remove_link(*R1, *E1).unwrap();
+        assert_eq!(
+            net.get_bgp_session_state(*R1, *E1),
+            Ok(Some(BgpSessionState::Idle))
+        );
+    }
+
+    #[test]
+    fn test_bgp_session_hold_timer<P: Prefix>() {
+        let mut net = get_test_net_bgp::<P>();
+        let p = P::from(0);
+
+        net.advertise_external_route(*E1, p, vec![AsId(65101), AsId(65201)], None, None)
+            .unwrap();
+        net.advertise_external_route(*E4, p, vec![AsId(65104), AsId(65201)], None, None)
+            .unwrap();
+        net.set_bgp_session_timers(Some(BgpSessionTimers::default()))
+            .unwrap();
+
+        // once the hold timer expires, the session is down, and all routes are removed.
+        net.remove_link(*R4, *E4).unwrap();
+        assert_eq!(
+            get_session_state(&net, *R4, *E4),
+            Some(BgpSessionState::Idle)
+        );
+        assert!(net
+            .get_device(*R4)
+            .unwrap_internal()
+            .get_processed_bgp_rib()
+            .get(&p)
+            .into_iter()
+            .flatten()
+            .all(|(e, _)| e.from_id != *E4));
+        test_route!(net, *R4, p, [*R4, *R2, *R3, *R1, *E1]);
+
+        // The session is established again once the neighbor is reachable.
+        net.add_link(*R4, *E4);
+        net.set_link_weight(*R4, *E4, 1.0).unwrap();
+        net.set_link_weight(*E4, *R4, 1.0).unwrap();
+        assert_eq!(
+            get_session_state(&net, *R4, *E4),
+            Some(BgpSessionState::Established)
+        );
+        test_route!(net, *R4, p, [*R4, *E4]);
+    }
+
+    #[cfg(feature = "undo")]
+    #[test]
+    fn test_bgp_session_fsm_undo<P: Prefix>() {
+        let mut net = get_test_net_bgp::<P>();
+        let p = P::from(0);
+        net.advertise_external_route(*E1, p, vec![AsId(65101), AsId(65201)], None, None)
+            .unwrap();
+        let net_hist_1 = net.clone();
+
+        net.set_bgp_session_timers(Some(BgpSessionTimers::default()))
+            .unwrap();
+        let net_hist_2 = net.clone();
+
+        net.set_bgp_session(*R1, *E1, None).unwrap();
+        let net_hist_3 = net.clone();
+
+        net.set_bgp_session(*R1, *E1, Some(EBgp)).unwrap();
+        test_route!(net, *R4, p, [*R4, *R2, *R3, *R1, *E1]);
+
+        net.undo_action().unwrap();
+        assert_eq!(net, net_hist_3);
+        net.undo_action().unwrap();
+        assert_eq!(net, net_hist_2);
+        net.undo_action().unwrap();
+        assert_eq!(net, net_hist_1);
+        assert_eq!(net.get_bgp_session_timers(), None);
+    }
+
     #[test]
     fn test_bgp_rib_entries<P: Prefix>() {
         use ordered_float::NotNan;
@@ -602,6 +773,134 @@ mod t {
         assert_eq!(net, net_trace_1);
     }
 
+    /// Returns the next-hop of the route selected by `router` for `prefix`, if any.
+    fn selected_next_hop<P: Prefix>(
+        net: &Network<P, BasicEventQueue<P>>,
+        router: RouterId,
+        prefix: P,
+    ) -> Option<RouterId> {
+        net.get_device(router)
+            .unwrap_internal()
+            .get_selected_bgp_route(prefix)
+            .map(|entry| entry.route.next_hop)
+    }
+
+    #[test]
+    fn test_bgp_network<P: Prefix>() {
+        let mut net = get_test_net_bgp::<P>();
+        let p = P::from(0);
+
+        // R2 originates the prefix, and all other routers learn it.
+        assert!(!net.set_bgp_network(*R2, p, true).unwrap());
+        let entry = net
+            .get_device(*R2)
+            .unwrap_internal()
+            .get_selected_bgp_route(p)
+            .unwrap()
+            .clone();
+        assert_eq!(entry.from_id, *R2);
+        assert_eq!(entry.route.origin, Origin::Igp);
+        assert!(entry.route.as_path.is_empty());
+        for r in [*R1, *R3, *R4] {
+            assert_eq!(selected_next_hop(&net, r, p), Some(*R2));
+        }
+        // traffic terminates at R2.
+        test_route!(net, *R2, p, [*R2]);
+        test_route!(net, *R1, p, [*R1, *R3, *R2]);
+        test_route!(net, *R3, p, [*R3, *R2]);
+        test_route!(net, *R4, p, [*R4, *R2]);
+        assert!(net.get_config().unwrap().iter().any(|e| e
+            == &ConfigExpr::BgpNetwork {
+                router: *R2,
+                prefix: p
+            }));
+
+        // the originated route passes through the outgoing route-maps.
+        net.set_bgp_route_map(
+            *R2,
+            *R4,
+            Outgoing,
+            RouteMap::new(10, Deny, vec![], vec![], Continue),
+        )
+        .unwrap();
+        assert_eq!(selected_next_hop(&net, *R1, p), Some(*R2));
+        assert_eq!(selected_next_hop(&net, *R4, p), None);
+
+        // the originated route has the shortest AS path, and is therefore preferred.
+        net.advertise_external_route(*E1, p, vec![AsId(65101), AsId(65201)], None, None)
+            .unwrap();
+        assert_eq!(selected_next_hop(&net, *R2, p), Some(*R2));
+        assert_eq!(selected_next_hop(&net, *R1, p), Some(*R2));
+        test_route!(net, *R1, p, [*R1, *R3, *R2]);
+
+        // stop originating the prefix.
+        assert!(net.set_bgp_network(*R2, p, false).unwrap());
+        assert_eq!(selected_next_hop(&net, *R2, p), Some(*R1));
+        assert_eq!(selected_next_hop(&net, *R3, p), Some(*R1));
+        test_route!(net, *R2, p, [*R2, *R3, *R1, *E1]);
+    }
+
+    #[test]
+    fn test_bgp_redistribute_static<P: Prefix>() {
+        let mut net = get_test_net_bgp::<P>();
+        let p = P::from(0);
+
+        net.set_static_route(*R4, p, Some(Direct(*E4))).unwrap();
+        assert_eq!(selected_next_hop(&net, *R1, p), None);
+
+        // enabling redistribution originates all existing static routes.
+        assert!(!net.set_bgp_redistribute_static(*R4, true).unwrap());
+        let entry = net
+            .get_device(*R4)
+            .unwrap_internal()
+            .get_selected_bgp_route(p)
+            .unwrap()
+            .clone();
+        assert_eq!(entry.route.origin, Origin::Incomplete);
+        for r in [*R1, *R2, *R3] {
+            assert_eq!(selected_next_hop(&net, r, p), Some(*R4));
+        }
+        assert_eq!(
+            net.get_device(*R4).unwrap_internal().get_next_hop(p),
+            vec![*E4]
+        );
+
+        // static routes follow the redistribution
+        net.set_static_route(*R4, p, None).unwrap();
+        assert_eq!(selected_next_hop(&net, *R1, p), None);
+        net.set_static_route(*R4, p, Some(Drop)).unwrap();
+        assert_eq!(selected_next_hop(&net, *R1, p), Some(*R4));
+
+        // disabling redistribution withdraws all static routes.
+        assert!(net.set_bgp_redistribute_static(*R4, false).unwrap());
+        assert_eq!(selected_next_hop(&net, *R1, p), None);
+    }
+
+    #[cfg(feature = "undo")]
+    #[test]
+    fn test_bgp_network_undo<P: Prefix>() {
+        let mut net = get_test_net_bgp::<P>();
+        let p = P::from(0);
+
+        let net_trace_1 = net.clone();
+        net.set_bgp_network(*R2, p, true).unwrap();
+        let net_trace_2 = net.clone();
+        net.set_static_route(*R4, p, Some(Direct(*E4))).unwrap();
+        let net_trace_3 = net.clone();
+        net.set_bgp_redistribute_static(*R4, true).unwrap();
+        let net_trace_4 = net.clone();
+        net.set_bgp_network(*R2, p, false).unwrap();
+
+        net.undo_action().unwrap();
+        assert_eq!(net, net_trace_4);
+        net.undo_action().unwrap();
+        assert_eq!(net, net_trace_3);
+        net.undo_action().unwrap();
+        assert_eq!(net, net_trace_2);
+        net.undo_action().unwrap();
+        assert_eq!(net, net_trace_1);
+    }
+
     #[test]
     fn test_bgp_decision<P: Prefix>() {
         let mut net = get_test_net_bgp::<P>();
@@ -1330,6 +1629,7 @@ mod t {
             next_hop: *E1,
             local_pref: None,
             med: None,
+            origin: Origin::Igp,
             community: Default::default(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -1340,6 +1640,7 @@ mod t {
             next_hop: *R1,
             local_pref: Some(100),
             med: Some(0),
+            origin: Origin::Igp,
             community: Default::default(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -1350,6 +1651,7 @@ mod t {
             next_hop: *E4,
             local_pref: None,
             med: None,
+            origin: Origin::Igp,
             community: Default::default(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -1360,6 +1662,7 @@ mod t {
             next_hop: *R4,
             local_pref: Some(100),
             med: Some(0),
+            origin: Origin::Igp,
             community: Default::default(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -1406,6 +1709,7 @@ mod t {
             next_hop: *E4,
             local_pref: None,
             med: None,
+            origin: Origin::Igp,
             community: Default::default(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -1416,6 +1720,7 @@ mod t {
             next_hop: *R4,
             local_pref: Some(100),
             med: Some(0),
+            origin: Origin::Igp,
             community: Default::default(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -1431,6 +1736,7 @@ mod t {
             next_hop: *R1,
             local_pref: None,
             med: None,
+            origin: Origin::Igp,
             community: Default::default(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -1504,6 +1810,7 @@ mod t {
             next_hop: *E1,
             local_pref: None,
             med: None,
+            origin: Origin::Igp,
             community: Default::default(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -1514,6 +1821,7 @@ mod t {
             next_hop: *R1,
             local_pref: Some(100),
             med: Some(0),
+            origin: Origin::Igp,
             community: Default::default(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -1524,6 +1832,7 @@ mod t {
             next_hop: *E4,
             local_pref: None,
             med: None,
+            origin: Origin::Igp,
             community: Default::default(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -1534,6 +1843,7 @@ mod t {
             next_hop: *R4,
             local_pref: Some(100),
             med: Some(0),
+            origin: Origin::Igp,
             community: Default::default(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -1583,6 +1893,7 @@ mod t {
             next_hop: *E4,
             local_pref: None,
             med: None,
+            origin: Origin::Igp,
             community: Default::default(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -1593,6 +1904,7 @@ mod t {
             next_hop: *R4,
             local_pref: Some(100),
             med: Some(0),
+            origin: Origin::Igp,
             community: Default::default(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -1608,6 +1920,7 @@ mod t {
             next_hop: *R1,
             local_pref: None,
             med: Some(0),
+            origin: Origin::Igp,
             community: Default::default(),
             originator_id: None,
             cluster_list: Vec::new(),
@@ -125,6 +125,125 @@ mod t {
         test_route!(net, b1, prefix, [b1, e1]);
     }
 
+    #[test]
+    #[cfg(feature = "rand_queue")]
+    fn test_simple_model_mrai<P: Prefix>() {
+        use crate::{
+            bgp::{BgpEvent, Mrai},
+            event::Event,
+            interactive::InteractiveNetwork,
+        };
+
+        let mut net: Network<P, _> = Network::new(SimpleTimingModel::new(ModelParams::new(
+            0.1, 1.0, 2.0, 5.0, 0.1,
+        )));
+
+        let prefix = P::from(0);
+
+        let (e0, b0, r0, r1, b1, _) = setup_simple(&mut net);
+        net.set_bgp_mrai(b0, r0, Some(Mrai::new(30.0))).unwrap();
+
+        // advertise two routes in short succession
+        net.manual_simulation();
+        net.advertise_external_route(e0, prefix, vec![AsId(1), AsId(2), AsId(3)], None, None)
+            .unwrap();
+        net.advertise_external_route(e0, prefix, vec![AsId(1), AsId(2)], None, None)
+            .unwrap();
+
+        // collect all updates sent from b0 to r0, together with the time of their arrival.
+        let mut updates = Vec::new();
+        while let Some((_, event)) = net.simulate_step().unwrap() {
+            if let Event::Bgp(t, src, dst, BgpEvent::Update(route)) = event {
+                if (src, dst) == (b0, r0) {
+                    updates.push((t.into_inner(), route.as_path.len()));
+                }
+            }
+        }
+
+        // the second update is deferred until the MRAI expires.
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].1, 3);
+        assert_eq!(updates[1].1, 2);
+        assert!(updates[1].0 - updates[0].0 > 29.0);
+
+        // check that all routes are correct
+        test_route!(net, b0, prefix, [b0, e0]);
+        test_route!(net, r0, prefix, [r0, b0, e0]);
+        test_route!(net, r1, prefix, [r1, r0, b0, e0]);
+        test_route!(net, b1, prefix, [b1, r1, r0, b0, e0]);
+    }
+
+    #[test]
+    #[cfg(feature = "rand_queue")]
+    fn test_simple_model_bgp_session_fsm<P: Prefix>() {
+        use crate::{
+            bgp::{BgpEvent, BgpSessionState, BgpSessionTimers},
+            event::{Event, TimerEvent},
+            interactive::InteractiveNetwork,
+        };
+
+        let mut net: Network<P, _> = Network::new(SimpleTimingModel::new(ModelParams::new(
+            0.1, 1.0, 2.0, 5.0, 0.1,
+        )));
+        net.set_bgp_session_timers(Some(BgpSessionTimers::new(10.0, 60.0)))
+            .unwrap();
+
+        let prefix = P::from(0);
+
+        net.manual_simulation();
+        let (e0, b0, r0, r1, b1, e1) = setup_simple(&mut net);
+        net.advertise_external_route(e0, prefix, vec![AsId(1), AsId(2), AsId(3)], None, None)
+            .unwrap();
+
+        // b0 does not propagate any route before its sessions are established
+        let mut first_open = None;
+        let mut first_update = None;
+        while let Some((_, event)) = net.simulate_step().unwrap() {
+            match event {
+                Event::Bgp(t, _, _, BgpEvent::Open) if first_open.is_none() => {
+                    first_open = Some(t.into_inner())
+                }
+                Event::Bgp(t, src, _, BgpEvent::Update(_))
+                    if src == b0 && first_update.is_none() =>
+                {
+                    first_update = Some(t.into_inner())
+                }
+                _ => {}
+            }
+        }
+        assert!(first_open.unwrap() > 10.0);
+        assert!(first_update.unwrap() > first_open.unwrap());
+
+        // check that all routes are correct
+        test_route!(net, b0, prefix, [b0, e0]);
+        test_route!(net, r0, prefix, [r0, b0, e0]);
+        test_route!(net, r1, prefix, [r1, r0, b0, e0]);
+        test_route!(net, b1, prefix, [b1, r1, r0, b0, e0]);
+
+        // the session stays up until the hold timer expires.
+        let start = net.queue().get_time().unwrap();
+        net.remove_link(b0, e0).unwrap();
+        let mut hold_expired = None;
+        while let Some((_, event)) = net.simulate_step().unwrap() {
+            if let Event::Timer(t, _, _, TimerEvent::BgpHold(_)) = event {
+                assert_eq!(
+                    net.get_device(b0)
+                        .unwrap_internal()
+                        .get_bgp_session_state(e0),
+                    Some(BgpSessionState::Idle)
+                );
+                hold_expired = Some(t.into_inner());
+            }
+        }
+        assert!(hold_expired.unwrap() >= start + 60.0);
+        assert_eq!(
+            net.get_device(b1)
+                .unwrap_internal()
+                .get_bgp_session_state(e1),
+            Some(BgpSessionState::Established)
+        );
+    }
+
     #[test]
     #[cfg(feature = "rand_queue")]
     fn test_geo_model<P: Prefix>() {
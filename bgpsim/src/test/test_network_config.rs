@@ -1030,7 +1030,7 @@ mod t {
             neighbor: r2,
             direction: Outgoing,
             map: RouteMapBuilder::new()
-                .order(10)
+                .order(50)
                 .deny()
                 .match_community(10)
                 .build(),
@@ -1045,6 +1045,67 @@ mod t {
         assert!(net.weak_eq(&net2));
     }
 
+    #[test]
+    fn get_config_route_map_direction<P: Prefix>() {
+        let mut net: Network<P, _> = Network::default();
+        let r1 = net.add_router("r1");
+        let r2 = net.add_router("r2");
+        net.add_link(r1, r2);
+        net.set_link_weight(r1, r2, 1.0).unwrap();
+        net.set_link_weight(r2, r1, 1.0).unwrap();
+        net.set_bgp_session(r1, r2, Some(IBgpPeer)).unwrap();
+        let map_in = RouteMapBuilder::new().order(10).deny().build();
+        let map_out = RouteMapBuilder::new().order(20).allow().build();
+        net.set_bgp_route_map(r1, r2, Incoming, map_in.clone())
+            .unwrap();
+        net.set_bgp_route_map(r1, r2, Outgoing, map_out.clone())
+            .unwrap();
+
+        let c = net.get_config().unwrap();
+        assert_eq!(
+            c.get(
+                ConfigExpr::BgpRouteMap {
+                    router: r1,
+                    neighbor: r2,
+                    direction: Incoming,
+                    map: map_in.clone(),
+                }
+                .key()
+            ),
+            Some(&ConfigExpr::BgpRouteMap {
+                router: r1,
+                neighbor: r2,
+                direction: Incoming,
+                map: map_in,
+            })
+        );
+        assert_eq!(
+            c.get(
+                ConfigExpr::BgpRouteMap {
+                    router: r1,
+                    neighbor: r2,
+                    direction: Outgoing,
+                    map: map_out.clone(),
+                }
+                .key()
+            ),
+            Some(&ConfigExpr::BgpRouteMap {
+                router: r1,
+                neighbor: r2,
+                direction: Outgoing,
+                map: map_out,
+            })
+        );
+
+        // applying the configuration to a fresh network must yield the same network
+        let mut net2: Network<P, _> = Network::default();
+        net2.add_router("r1");
+        net2.add_router("r2");
+        net2.add_link(r1, r2);
+        net2.set_config(&c).unwrap();
+        assert_eq!(net2.get_config().unwrap(), c);
+    }
+
     #[instantiate_tests(<SinglePrefix>)]
     mod single {}
 
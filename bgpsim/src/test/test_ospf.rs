@@ -20,6 +20,7 @@
 use crate::{
     builder::{constant_link_weight, NetworkBuilder},
     event::BasicEventQueue,
+    interactive::InteractiveNetwork,
     network::Network,
     ospf::OspfArea,
     types::{AsId, NetworkError, RouterId, SimplePrefix as Prefix},
@@ -27,7 +28,7 @@ use crate::{
 
 #[test]
 fn only_backbone() {
-    let (mut net, r, p8, p9, p10) = test_net().unwrap();
+    let (mut net, r, p8, p9, p10) = test_net(false).unwrap();
 
     let mut state = net.get_forwarding_state();
     assert_eq!(
@@ -77,7 +78,7 @@ fn only_backbone() {
 
 #[test]
 fn left_right() {
-    let (mut net, r, p8, p9, p10) = test_net().unwrap();
+    let (mut net, r, p8, p9, p10) = test_net(false).unwrap();
 
     net.set_ospf_area(r.0, r.1, 1).unwrap();
     net.set_ospf_area(r.1, r.2, 1).unwrap();
@@ -157,7 +158,7 @@ fn left_right() {
 
 #[test]
 fn left_mid_right() {
-    let (mut net, r, p8, p9, p10) = test_net().unwrap();
+    let (mut net, r, p8, p9, p10) = test_net(false).unwrap();
 
     net.set_ospf_area(r.4, r.0, 1).unwrap();
     net.set_ospf_area(r.4, r.5, 1).unwrap();
@@ -230,7 +231,7 @@ fn left_mid_right() {
 
 #[test]
 fn left_right_bottom() {
-    let (mut net, r, p8, p9, p10) = test_net().unwrap();
+    let (mut net, r, p8, p9, p10) = test_net(false).unwrap();
 
     net.set_ospf_area(r.4, r.0, 1).unwrap();
     net.set_ospf_area(r.4, r.5, 1).unwrap();
@@ -354,6 +355,132 @@ fn disconnected_backbone() {
     );
 }
 
+#[test]
+fn event_driven_equals_instant() {
+    let (mut reference, r, p8, p9, p10) = test_net(false).unwrap();
+    let (mut net, _, _, _, _) = test_net(true).unwrap();
+    assert_same_igp_state(&net, &reference);
+
+    for n in [&mut net, &mut reference] {
+        n.set_ospf_area(r.4, r.0, 1).unwrap();
+        n.set_ospf_area(r.4, r.5, 1).unwrap();
+        n.set_ospf_area(r.4, r.7, 1).unwrap();
+        n.set_ospf_area(r.5, r.1, 2).unwrap();
+        n.set_ospf_area(r.5, r.6, 2).unwrap();
+    }
+    assert_same_igp_state(&net, &reference);
+
+    for n in [&mut net, &mut reference] {
+        n.set_link_weight(r.0, r.1, 5.0).unwrap();
+        n.set_link_weight(r.7, r.6, 3.0).unwrap();
+    }
+    assert_same_igp_state(&net, &reference);
+
+    for n in [&mut net, &mut reference] {
+        n.remove_link(r.1, r.2).unwrap();
+        n.remove_link(r.4, r.7).unwrap();
+    }
+    assert_same_igp_state(&net, &reference);
+
+    let mut state = net.get_forwarding_state();
+    assert_eq!(
+        state.get_paths(r.0, p8).unwrap(),
+        vec![vec![r.0, r.4, r.5, r.8]]
+    );
+    assert_eq!(
+        state.get_paths(r.0, p9).unwrap(),
+        vec![vec![r.0, r.3, r.2, r.6, r.9]]
+    );
+    assert_eq!(
+        state.get_paths(r.0, p10).unwrap(),
+        vec![vec![r.0, r.3, r.7, r.10]]
+    );
+
+    // switching back to the instant mode keeps the same state.
+    net.set_event_driven_ospf(false).unwrap();
+    assert_same_igp_state(&net, &reference);
+}
+
+#[test]
+fn event_driven_micro_loop() {
+    let mut net: Network<Prefix, BasicEventQueue<Prefix>> = Network::default();
+    net.set_event_driven_ospf(true).unwrap();
+
+    let a = net.add_router("A");
+    let b = net.add_router("B");
+    let c = net.add_router("C");
+    let d = net.add_router("D");
+    let e = net.add_external_router("E", AsId(100));
+
+    net.add_link(a, b);
+    net.add_link(a, d);
+    net.add_link(b, c);
+    net.add_link(c, d);
+    net.add_link(d, e);
+
+    net.build_link_weights(constant_link_weight, 1.0).unwrap();
+    net.set_link_weight(c, d, 2.0).unwrap();
+    net.set_link_weight(d, c, 2.0).unwrap();
+    net.build_ibgp_full_mesh().unwrap();
+    net.build_ebgp_sessions().unwrap();
+
+    let p = Prefix::from(0);
+    net.advertise_external_route(e, p, [100], None, None)
+        .unwrap();
+
+    test_route!(net, a, p, [a, d, e]);
+    test_route!(net, b, p, [b, a, d, e]);
+
+    // increase the weight from a to d. Only `a` knows about the change, and immediately sends
+    // traffic via `b`, which still uses `a`.
+    net.manual_simulation();
+    net.set_link_weight(a, d, 10.0).unwrap();
+    assert!(net.queue().0.iter().all(|e| e.is_ospf_event()));
+    test_bad_route!(fw_loop, net, a, p, [a, b, a]);
+
+    // once the LSA is flooded, the micro-loop disappears.
+    net.simulate().unwrap();
+    test_route!(net, a, p, [a, b, c, d, e]);
+    test_route!(net, b, p, [b, c, d, e]);
+}
+
+#[cfg(feature = "undo")]
+#[test]
+fn event_driven_undo() {
+    let (mut net, r, _, _, _) = test_net(true).unwrap();
+    let reference = net.clone();
+
+    net.set_link_weight(r.0, r.1, 5.0).unwrap();
+    net.remove_link(r.4, r.7).unwrap();
+    net.undo_action().unwrap();
+    net.undo_action().unwrap();
+
+    assert_same_igp_state(&net, &reference);
+    for router in net.get_routers() {
+        assert_eq!(
+            net.get_device(router).unwrap_internal().get_ospf_lsdb(),
+            reference
+                .get_device(router)
+                .unwrap_internal()
+                .get_ospf_lsdb(),
+        );
+    }
+}
+
+/// Assert that both networks have the same IGP tables and the same forwarding state.
+fn assert_same_igp_state(
+    a: &Network<Prefix, BasicEventQueue<Prefix>>,
+    b: &Network<Prefix, BasicEventQueue<Prefix>>,
+) {
+    for r in a.get_routers() {
+        assert_eq!(
+            a.get_device(r).unwrap_internal().get_igp_fw_table(),
+            b.get_device(r).unwrap_internal().get_igp_fw_table(),
+        );
+    }
+    assert_eq!(a.get_forwarding_state(), b.get_forwarding_state());
+}
+
 type Routers = (
     RouterId,
     RouterId,
@@ -369,7 +496,9 @@ type Routers = (
 );
 
 #[allow(clippy::type_complexity)]
-fn test_net() -> Result<
+fn test_net(
+    event_driven_ospf: bool,
+) -> Result<
     (
         Network<Prefix, BasicEventQueue<Prefix>>,
         Routers,
@@ -380,6 +509,7 @@ fn test_net() -> Result<
     NetworkError,
 > {
     let mut net = Network::default();
+    net.set_event_driven_ospf(event_driven_ospf)?;
 
     let r0 = net.add_router("R0");
     let r1 = net.add_router("R1");
@@ -19,7 +19,7 @@ use maplit::btreeset;
 use ordered_float::NotNan;
 
 use crate::{
-    bgp::{BgpRibEntry, BgpRoute, BgpSessionType::*},
+    bgp::{BgpRibEntry, BgpRoute, BgpSessionType::*, Origin},
     route_map::{
         RouteMapFlow::*, RouteMapMatch as Match, RouteMapMatchAsPath as AClause,
         RouteMapMatchClause as Clause, RouteMapSet as Set, RouteMapState::*, *,
@@ -40,6 +40,7 @@ mod t1 {
                 next_hop: 0.into(),
                 local_pref: Some(1),
                 med: Some(10),
+                origin: Origin::Igp,
                 community: Default::default(),
                 originator_id: None,
                 cluster_list: Vec::new(),
@@ -49,6 +50,7 @@ mod t1 {
             to_id: None,
             igp_cost: Some(NotNan::new(10.0).unwrap()),
             weight: 100,
+            received: 0,
         };
 
         // Next Hop
@@ -302,6 +304,7 @@ mod t1 {
                 next_hop: 0.into(),
                 local_pref: None,
                 med: None,
+                origin: Origin::Igp,
                 community: Default::default(),
                 originator_id: None,
                 cluster_list: Vec::new(),
@@ -311,6 +314,7 @@ mod t1 {
             to_id: None,
             igp_cost: Some(NotNan::new(10.0).unwrap()),
             weight: 100,
+            received: 0,
         };
 
         let rms = vec![
@@ -358,6 +362,7 @@ mod t1 {
                 next_hop: 0.into(),
                 local_pref: None,
                 med: None,
+                origin: Origin::Igp,
                 community: Default::default(),
                 originator_id: None,
                 cluster_list: Vec::new(),
@@ -367,6 +372,7 @@ mod t1 {
             to_id: None,
             igp_cost: Some(NotNan::new(10.0).unwrap()),
             weight: 100,
+            received: 0,
         };
 
         let rms = vec![
@@ -414,6 +420,7 @@ mod t1 {
                 next_hop: 0.into(),
                 local_pref: None,
                 med: None,
+                origin: Origin::Igp,
                 community: Default::default(),
                 originator_id: None,
                 cluster_list: Vec::new(),
@@ -423,6 +430,7 @@ mod t1 {
             to_id: None,
             igp_cost: Some(NotNan::new(10.0).unwrap()),
             weight: 100,
+            received: 0,
         };
 
         let rms = vec![
@@ -467,6 +475,7 @@ mod t1 {
                 next_hop: 0.into(),
                 local_pref: None,
                 med: None,
+                origin: Origin::Igp,
                 community: Default::default(),
                 originator_id: None,
                 cluster_list: Vec::new(),
@@ -476,6 +485,7 @@ mod t1 {
             to_id: None,
             igp_cost: Some(NotNan::new(10.0).unwrap()),
             weight: 100,
+            received: 0,
         };
 
         let rms = vec![
@@ -534,6 +544,7 @@ mod t2 {
                 next_hop: 0.into(),
                 local_pref: None,
                 med: None,
+                origin: Origin::Igp,
                 community: Default::default(),
                 originator_id: None,
                 cluster_list: Vec::new(),
@@ -543,6 +554,7 @@ mod t2 {
             to_id: None,
             igp_cost: Some(NotNan::new(10.0).unwrap()),
             weight: 100,
+            received: 0,
         };
 
         // Match on NextHop
@@ -677,6 +689,7 @@ mod t2 {
                 next_hop: 0.into(),
                 local_pref: None,
                 med: None,
+                origin: Origin::Igp,
                 community: Default::default(),
                 originator_id: None,
                 cluster_list: Vec::new(),
@@ -686,6 +699,7 @@ mod t2 {
             to_id: None,
             igp_cost: Some(NotNan::new(10.0).unwrap()),
             weight: 100,
+            received: 0,
         };
 
         // And Clause
@@ -726,6 +740,127 @@ mod t2 {
         assert!(map.apply(entry).1.is_none());
     }
 
+    #[test]
+    fn match_set_origin<P: Prefix>() {
+        let default_entry = BgpRibEntry {
+            route: BgpRoute::<P> {
+                prefix: P::from(0),
+                as_path: vec![AsId(0)],
+                next_hop: 0.into(),
+                local_pref: None,
+                med: None,
+                origin: Origin::Igp,
+                community: Default::default(),
+                originator_id: None,
+                cluster_list: Vec::new(),
+            },
+            from_type: EBgp,
+            from_id: 0.into(),
+            to_id: None,
+            igp_cost: Some(NotNan::new(10.0).unwrap()),
+            weight: 100,
+            received: 0,
+        };
+
+        let map = RouteMapBuilder::<P>::new()
+            .order(10)
+            .allow()
+            .match_origin(Origin::Egp)
+            .set_origin(Origin::Incomplete)
+            .exit()
+            .build();
+        assert_eq!(
+            map,
+            RouteMap::new(
+                10,
+                Allow,
+                vec![Match::Origin(Origin::Egp)],
+                vec![Set::Origin(Origin::Incomplete)],
+                Exit
+            )
+        );
+
+        // route with origin IGP does not match
+        let (flow, entry) = map.apply(default_entry.clone());
+        assert_eq!(flow, Continue);
+        assert_eq!(entry.unwrap().route.origin, Origin::Igp);
+
+        // route with origin EGP matches and gets updated
+        let mut entry = default_entry;
+        entry.route.origin = Origin::Egp;
+        let (flow, entry) = map.apply(entry);
+        assert_eq!(flow, Exit);
+        assert_eq!(entry.unwrap().route.origin, Origin::Incomplete);
+    }
+
+    #[test]
+    fn match_as_path_regex_prepend<P: Prefix>() {
+        let default_entry = BgpRibEntry {
+            route: BgpRoute::<P> {
+                prefix: P::from(0),
+                as_path: vec![AsId(10), AsId(20)],
+                next_hop: 0.into(),
+                local_pref: None,
+                med: None,
+                origin: Origin::Igp,
+                community: Default::default(),
+                originator_id: None,
+                cluster_list: Vec::new(),
+            },
+            from_type: EBgp,
+            from_id: 0.into(),
+            to_id: None,
+            igp_cost: Some(NotNan::new(10.0).unwrap()),
+            weight: 100,
+            received: 0,
+        };
+
+        let regex = AsPathRegex::new("^10_").unwrap();
+        let map = RouteMapBuilder::<P>::new()
+            .order(10)
+            .allow()
+            .match_as_path_regex(regex.clone())
+            .prepend_as_path([1, 1])
+            .exit()
+            .build();
+        assert_eq!(
+            map,
+            RouteMap::new(
+                10,
+                Allow,
+                vec![Match::AsPath(AClause::Regex(regex))],
+                vec![Set::PrependAsPath(vec![AsId(1), AsId(1)])],
+                Exit
+            )
+        );
+
+        // route starting with AS 10 matches, and the path gets prepended.
+        let (flow, entry) = map.apply(default_entry.clone());
+        assert_eq!(flow, Exit);
+        assert_eq!(
+            entry.unwrap().route.as_path,
+            vec![AsId(1), AsId(1), AsId(10), AsId(20)]
+        );
+
+        // routes not starting with AS 10 do not match.
+        for path in [vec![AsId(20), AsId(10)], vec![AsId(100)], vec![]] {
+            let mut entry = default_entry.clone();
+            entry.route.as_path = path.clone();
+            let (flow, entry) = map.apply(entry);
+            assert_eq!(flow, Continue);
+            assert_eq!(entry.unwrap().route.as_path, path);
+        }
+
+        // the delimiter `_` also matches the end of the path
+        let regex = AsPathRegex::new("_20_").unwrap();
+        assert!(regex.matches(&[AsId(10), AsId(20)]));
+        assert!(regex.matches(&[AsId(20)]));
+        assert!(!regex.matches(&[AsId(10), AsId(200)]));
+        assert!(!regex.matches(&[AsId(120)]));
+
+        assert!(AsPathRegex::new("(10").is_err());
+    }
+
     #[test]
     fn builder_multiple_prefixes<P: Prefix>() {
         assert_eq!(
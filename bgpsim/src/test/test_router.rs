@@ -20,15 +20,18 @@ use std::collections::HashSet;
 #[allow(unused_imports)]
 use crate::bgp::BgpSessionType::{EBgp, IBgpClient, IBgpPeer};
 use crate::{
-    bgp::{BgpEvent, BgpRoute},
-    event::Event,
+    bgp::{BgpEvent, BgpRoute, BgpSessionState, BgpSessionTimers, Mrai, Origin},
+    event::{Event, TimerEvent},
     external_router::*,
     ospf::Ospf,
     router::*,
-    types::{AsId, IgpNetwork, Ipv4Prefix, Prefix, SimplePrefix, SinglePrefix},
+    types::{
+        AsId, IgpNetwork, Ipv4Prefix, Prefix, PrefixMap, RouterId, SimplePrefix, SinglePrefix,
+    },
 };
 
 use maplit::{hashmap, hashset};
+use ordered_float::NotNan;
 
 #[generic_tests::define]
 mod t2 {
@@ -73,6 +76,7 @@ mod t2 {
                     next_hop: 100.into(),
                     local_pref: None,
                     med: None,
+                    origin: Origin::Igp,
                     community: Default::default(),
                     originator_id: None,
                     cluster_list: Vec::new(),
@@ -115,6 +119,7 @@ mod t2 {
                     next_hop: 11.into(),
                     local_pref: Some(50),
                     med: None,
+                    origin: Origin::Igp,
                     community: Default::default(),
                     originator_id: None,
                     cluster_list: Vec::new(),
@@ -160,6 +165,7 @@ mod t2 {
                     next_hop: 10.into(),
                     local_pref: None,
                     med: None,
+                    origin: Origin::Igp,
                     community: Default::default(),
                     originator_id: None,
                     cluster_list: Vec::new(),
@@ -201,6 +207,7 @@ mod t2 {
                     next_hop: 5.into(),
                     local_pref: Some(150),
                     med: None,
+                    origin: Origin::Igp,
                     community: Default::default(),
                     originator_id: None,
                     cluster_list: Vec::new(),
@@ -232,6 +239,9 @@ mod t2 {
                     assert_eq!(to, 5.into());
                     assert_eq!(prefix, P::from(200));
                 }
+                Event::Bgp(_, _, _, BgpEvent::Open)
+                | Event::Ospf(_, _, _, _)
+                | Event::Timer(_, _, _, _) => unreachable!(),
             }
         }
 
@@ -284,6 +294,9 @@ mod t2 {
                     assert_eq!(to, 100.into());
                     assert_eq!(prefix, P::from(200));
                 }
+                Event::Bgp(_, _, _, BgpEvent::Open)
+                | Event::Ospf(_, _, _, _)
+                | Event::Timer(_, _, _, _) => unreachable!(),
             }
         }
 
@@ -354,6 +367,7 @@ mod t2 {
                 next_hop: 100.into(),
                 local_pref: None,
                 med: None,
+                origin: Origin::Igp,
                 community: Default::default(),
                 originator_id: None,
                 cluster_list: Vec::new(),
@@ -379,6 +393,7 @@ mod t2 {
                 next_hop: 11.into(),
                 local_pref: Some(50),
                 med: None,
+                origin: Origin::Igp,
                 community: Default::default(),
                 originator_id: None,
                 cluster_list: Vec::new(),
@@ -404,6 +419,7 @@ mod t2 {
                 next_hop: 10.into(),
                 local_pref: None,
                 med: None,
+                origin: Origin::Igp,
                 community: Default::default(),
                 originator_id: None,
                 cluster_list: Vec::new(),
@@ -440,6 +456,7 @@ mod t2 {
                 next_hop: 5.into(),
                 local_pref: Some(150),
                 med: None,
+                origin: Origin::Igp,
                 community: Default::default(),
                 originator_id: None,
                 cluster_list: Vec::new(),
@@ -515,6 +532,328 @@ mod t2 {
         assert_eq!(r, store_r_1);
     }
 
+    #[cfg(feature = "undo")]
+    #[test]
+    fn test_undo_same_entry_twice<P: Prefix>() {
+        let p = P::from(0);
+        let mut r = Router::<P>::new("test".to_string(), 0.into(), AsId(65001));
+        let store_r = r.clone();
+
+        // An event may modify the same entry multiple times. Build such an undo event by hand,
+        // where the static route is first added and then replaced.
+        r.set_static_route::<()>(p, Some(StaticRoute::Direct(1.into())))
+            .unwrap();
+        r.set_static_route::<()>(p, Some(StaticRoute::Direct(2.into())))
+            .unwrap();
+        let second = r.undo_stack.pop().unwrap();
+        let first = r.undo_stack.pop().unwrap();
+        r.undo_stack.push(first.into_iter().chain(second).collect());
+
+        // undoing the event must restore the state before the first modification, which requires
+        // the actions to be undone in reverse order.
+        r.undo_event();
+        assert!(r.get_static_routes().iter().next().is_none());
+        assert_eq!(r, store_r);
+    }
+
+    /// Setup a router with an eBGP session to `100` and an iBGP session to `1`, which has an MRAI
+    /// of 5 seconds.
+    fn setup_mrai<P: Prefix>(mrai: Mrai) -> Router<P> {
+        let mut r = Router::<P>::new("test".to_string(), 0.into(), AsId(65001));
+        r.set_bgp_session::<()>(100.into(), Some(EBgp)).unwrap();
+        r.set_bgp_session::<()>(1.into(), Some(IBgpPeer)).unwrap();
+        r.set_bgp_mrai(1.into(), Some(mrai));
+        r.igp_table = hashmap! {
+            100.into() => (vec![100.into()], 0.0),
+            1.into()   => (vec![1.into()], 1.0),
+        };
+        r
+    }
+
+    fn mrai_update<P: Prefix>(as_path: Vec<u32>) -> Event<P, ()> {
+        Event::Bgp(
+            (),
+            100.into(),
+            0.into(),
+            BgpEvent::Update(BgpRoute::new(100.into(), P::from(200), as_path, None, None)),
+        )
+    }
+
+    fn mrai_timer<P: Prefix>() -> Event<P, ()> {
+        Event::Timer(
+            (),
+            0.into(),
+            NotNan::new(5.0).unwrap(),
+            TimerEvent::BgpMrai(1.into()),
+        )
+    }
+
+    fn sent_as_path<P: Prefix>(event: &Event<P, ()>) -> Option<Vec<AsId>> {
+        match event {
+            Event::Bgp(_, from, to, BgpEvent::Update(route)) => {
+                assert_eq!((*from, *to), (0.into(), 1.into()));
+                Some(route.as_path.clone())
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_bgp_mrai<P: Prefix>() {
+        let mut r = setup_mrai::<P>(Mrai::new(5.0));
+
+        // the first update is sent immediately, and the timer is started.
+        let (_, events) = r.handle_event(mrai_update(vec![1, 2, 3])).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            sent_as_path(&events[0]),
+            Some(vec![AsId(1), AsId(2), AsId(3)])
+        );
+        assert_eq!(events[1], mrai_timer());
+
+        // the second update is deferred until the timer expires
+        let (_, events) = r.handle_event(mrai_update(vec![1, 2])).unwrap();
+        assert!(events.is_empty());
+        let (_, events) = r.handle_event(mrai_update(vec![1])).unwrap();
+        assert!(events.is_empty());
+
+        // once the timer expires, only the most recent route is sent, and the timer is restarted.
+        let (_, events) = r.handle_event(mrai_timer()).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(sent_as_path(&events[0]), Some(vec![AsId(1)]));
+        assert_eq!(events[1], mrai_timer());
+
+        // the timer stops if nothing was deferred.
+        let (_, events) = r.handle_event(mrai_timer()).unwrap();
+        assert!(events.is_empty());
+        assert!(r.bgp_mrai_timers.is_empty());
+
+        // withdrawals are sent immediately, and do not start the timer.
+        let (_, events) = r
+            .handle_event(Event::Bgp(
+                (),
+                100.into(),
+                0.into(),
+                BgpEvent::Withdraw(P::from(200)),
+            ))
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![Event::Bgp(
+                (),
+                0.into(),
+                1.into(),
+                BgpEvent::Withdraw(P::from(200))
+            )]
+        );
+        assert!(r.bgp_mrai_timers.is_empty());
+    }
+
+    #[test]
+    fn test_bgp_mrai_withdrawals<P: Prefix>() {
+        let mut r = setup_mrai::<P>(Mrai::with_withdrawals(5.0));
+        let withdraw = Event::Bgp((), 100.into(), 0.into(), BgpEvent::Withdraw(P::from(200)));
+
+        let (_, events) = r.handle_event(mrai_update(vec![1, 2, 3])).unwrap();
+        assert_eq!(events.len(), 2);
+
+        // the withdrawal is deferred
+        let (_, events) = r.handle_event(withdraw).unwrap();
+        assert!(events.is_empty());
+
+        let (_, events) = r.handle_event(mrai_timer()).unwrap();
+        assert_eq!(
+            events,
+            vec![
+                Event::Bgp((), 0.into(), 1.into(), BgpEvent::Withdraw(P::from(200))),
+                mrai_timer()
+            ]
+        );
+
+        // removing the session stops the timer
+        r.set_bgp_session::<()>(1.into(), None).unwrap();
+        assert!(r.bgp_mrai_timers.is_empty());
+        let (_, events) = r.handle_event(mrai_timer()).unwrap();
+        assert!(events.is_empty());
+    }
+
+    #[cfg(feature = "undo")]
+    #[test]
+    fn test_bgp_mrai_undo<P: Prefix>() {
+        let mut r = setup_mrai::<P>(Mrai::new(5.0));
+
+        let mut stored = vec![r.clone()];
+        r.handle_event(mrai_update(vec![1, 2, 3])).unwrap();
+        stored.push(r.clone());
+        r.handle_event(mrai_update(vec![1, 2])).unwrap();
+        stored.push(r.clone());
+        r.handle_event(mrai_update(vec![1])).unwrap();
+        stored.push(r.clone());
+        r.handle_event(mrai_timer()).unwrap();
+        stored.push(r.clone());
+        r.handle_event(mrai_timer()).unwrap();
+
+        while let Some(store) = stored.pop() {
+            r.undo_event();
+            assert_eq!(r, store);
+            assert_eq!(r.bgp_mrai_timers, store.bgp_mrai_timers);
+        }
+
+        // undo setting the MRAI
+        r.undo_event();
+        assert_eq!(r.get_bgp_mrai(1.into()), None);
+    }
+
+    fn setup_session_fsm<P: Prefix>() -> Router<P> {
+        let mut r = Router::<P>::new("test".to_string(), 0.into(), AsId(65001));
+        r.set_bgp_session_timers::<()>(Some(BgpSessionTimers::new(1.0, 10.0)));
+        r.igp_table = hashmap! {
+            100.into() => (vec![100.into()], 0.0),
+            1.into()   => (vec![1.into()], 1.0),
+        };
+        r
+    }
+
+    fn connect_timer<P: Prefix>(peer: u32) -> Event<P, ()> {
+        Event::Timer(
+            (),
+            0.into(),
+            NotNan::new(1.0).unwrap(),
+            TimerEvent::BgpConnect(peer.into()),
+        )
+    }
+
+    fn open<P: Prefix>(from: u32, to: u32) -> Event<P, ()> {
+        Event::Bgp((), from.into(), to.into(), BgpEvent::Open)
+    }
+
+    #[test]
+    fn test_bgp_session_fsm<P: Prefix>() {
+        let mut r = setup_session_fsm::<P>();
+
+        let (_, events) = r.set_bgp_session::<()>(100.into(), Some(EBgp)).unwrap();
+        assert_eq!(events, vec![connect_timer(100)]);
+        assert_eq!(
+            r.get_bgp_session_state(100.into()),
+            Some(BgpSessionState::Connect)
+        );
+
+        // routes are ignored until the session is established
+        r.handle_event(mrai_update(vec![1, 2, 3])).unwrap();
+        assert!(r.get_selected_bgp_route(P::from(200)).is_none());
+
+        // send the OPEN message once the connection is established
+        let (_, events) = r.handle_event(connect_timer(100)).unwrap();
+        assert_eq!(events, vec![open(0, 100)]);
+        assert_eq!(
+            r.get_bgp_session_state(100.into()),
+            Some(BgpSessionState::OpenSent)
+        );
+
+        let (_, events) = r.handle_event(open(100, 0)).unwrap();
+        assert!(events.is_empty());
+        assert_eq!(
+            r.get_bgp_session_state(100.into()),
+            Some(BgpSessionState::Established)
+        );
+        r.handle_event(mrai_update(vec![1, 2, 3])).unwrap();
+        assert!(r.get_selected_bgp_route(P::from(200)).is_some());
+
+        // The new session does not receive the route before it is established.
+        let (_, events) = r.set_bgp_session::<()>(1.into(), Some(IBgpPeer)).unwrap();
+        assert_eq!(events, vec![connect_timer(1)]);
+
+        // The OPEN message of the neighbor arrives before sending our own. The router answers, and
+        // sends the route.
+        let (_, events) = r.handle_event(open(1, 0)).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0], open(0, 1));
+        assert_eq!(
+            sent_as_path(&events[1]),
+            Some(vec![AsId(1), AsId(2), AsId(3)])
+        );
+        assert_eq!(
+            r.get_bgp_session_state(1.into()),
+            Some(BgpSessionState::Established)
+        );
+
+        // the connect timer is ignored, as the session is already established
+        let (_, events) = r.handle_event(connect_timer(1)).unwrap();
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_bgp_session_fsm_unreachable<P: Prefix>() {
+        let mut r = setup_session_fsm::<P>();
+        r.set_bgp_session::<()>(2.into(), Some(IBgpPeer)).unwrap();
+
+        // the connection fails, since the neighbor is unreachable
+        let (_, events) = r.handle_event(connect_timer(2)).unwrap();
+        assert!(events.is_empty());
+        assert_eq!(
+            r.get_bgp_session_state(2.into()),
+            Some(BgpSessionState::Idle)
+        );
+
+        // the session is still established if the neighbor connects to the router.
+        let (_, events) = r.handle_event(open(2, 0)).unwrap();
+        assert_eq!(events, vec![open(0, 2)]);
+        assert_eq!(
+            r.get_bgp_session_state(2.into()),
+            Some(BgpSessionState::Established)
+        );
+    }
+
+    #[test]
+    fn test_bgp_session_fsm_disable<P: Prefix>() {
+        let mut r = setup_session_fsm::<P>();
+        r.set_bgp_session::<()>(100.into(), Some(EBgp)).unwrap();
+        r.handle_event(connect_timer(100)).unwrap();
+        r.handle_event(open(100, 0)).unwrap();
+        r.handle_event(mrai_update(vec![1, 2, 3])).unwrap();
+        r.set_bgp_session::<()>(1.into(), Some(IBgpPeer)).unwrap();
+
+        // disabling the state machine establishes all pending sessions immediately.
+        let events = r.set_bgp_session_timers::<()>(None);
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            sent_as_path(&events[0]),
+            Some(vec![AsId(1), AsId(2), AsId(3)])
+        );
+        assert!(r.bgp_session_state.is_empty());
+        assert_eq!(
+            r.get_bgp_session_state(1.into()),
+            Some(BgpSessionState::Established)
+        );
+    }
+
+    #[cfg(feature = "undo")]
+    #[test]
+    fn test_bgp_session_fsm_undo<P: Prefix>() {
+        let mut r = setup_session_fsm::<P>();
+
+        let mut stored = vec![r.clone()];
+        r.set_bgp_session::<()>(100.into(), Some(EBgp)).unwrap();
+        stored.push(r.clone());
+        r.handle_event(connect_timer(100)).unwrap();
+        stored.push(r.clone());
+        r.handle_event(open(100, 0)).unwrap();
+        stored.push(r.clone());
+        r.handle_event(mrai_update(vec![1, 2, 3])).unwrap();
+        stored.push(r.clone());
+        r.set_bgp_session::<()>(100.into(), None).unwrap();
+
+        while let Some(store) = stored.pop() {
+            r.undo_event();
+            assert_eq!(r, store);
+            assert_eq!(r.bgp_session_state, store.bgp_session_state);
+        }
+
+        // undo enabling the state machine
+        r.undo_event();
+        assert_eq!(r.get_bgp_session_timers(), None);
+    }
+
     #[instantiate_tests(<SimplePrefix>)]
     mod simple {}
 
@@ -713,7 +1052,7 @@ mod t1 {
         assert!(events.is_empty());
 
         // advertise route
-        let (_, events) = r.advertise_prefix(P::from(0), vec![AsId(0)], None, None);
+        let (_, events) = r.advertise_prefix(P::from(0), vec![AsId(0)], None, None, Origin::Igp);
 
         // check that one event was created
         assert_eq!(events.len(), 1);
@@ -729,6 +1068,7 @@ mod t1 {
                     next_hop: 0.into(),
                     local_pref: None,
                     med: None,
+                    origin: Origin::Igp,
                     community: Default::default(),
                     originator_id: None,
                     cluster_list: Vec::new(),
@@ -753,8 +1093,13 @@ mod t1 {
         let mut r = ExternalRouter::<P>::new("router".to_string(), 0.into(), AsId(65001));
 
         // advertise route
-        let (_, events) =
-            r.advertise_prefix::<(), Option<u32>>(P::from(0), vec![AsId(0)], None, None);
+        let (_, events) = r.advertise_prefix::<(), Option<u32>>(
+            P::from(0),
+            vec![AsId(0)],
+            None,
+            None,
+            Origin::Igp,
+        );
 
         // check that no event was created
         assert_eq!(events.len(), 0);
@@ -776,6 +1121,7 @@ mod t1 {
                     next_hop: 0.into(),
                     local_pref: None,
                     med: None,
+                    origin: Origin::Igp,
                     community: Default::default(),
                     originator_id: None,
                     cluster_list: Vec::new(),
@@ -802,7 +1148,7 @@ mod t1 {
         let r_clone_1 = r.clone();
 
         // advertise route
-        r.advertise_prefix::<(), Option<u32>>(P::from(0), vec![AsId(0)], None, None);
+        r.advertise_prefix::<(), Option<u32>>(P::from(0), vec![AsId(0)], None, None, Origin::Igp);
         let r_clone_2 = r.clone();
 
         // emove the route
@@ -821,7 +1167,7 @@ mod t1 {
         let mut r = ExternalRouter::<P>::new("router".to_string(), 0.into(), AsId(65001));
 
         // advertise route
-        r.advertise_prefix::<(), Option<u32>>(P::from(0), vec![AsId(0)], None, None);
+        r.advertise_prefix::<(), Option<u32>>(P::from(0), vec![AsId(0)], None, None, Origin::Igp);
         let r_clone_1 = r.clone();
 
         // add a neighbor and check that the route is advertised
@@ -912,7 +1258,10 @@ mod t1 {
 
 mod ipv4 {
     use super::*;
-    use crate::bgp::BgpSessionType::{EBgp, IBgpClient, IBgpPeer};
+    use crate::bgp::{
+        BgpAggregate,
+        BgpSessionType::{EBgp, IBgpClient, IBgpPeer},
+    };
     use ipnet::Ipv4Net;
 
     #[test]
@@ -1018,7 +1367,8 @@ mod ipv4 {
         .unwrap();
 
         // set a static route
-        r.set_static_route(p1, Some(StaticRoute::Direct(1.into())));
+        r.set_static_route::<()>(p1, Some(StaticRoute::Direct(1.into())))
+            .unwrap();
 
         // check that the next hop is generated properly.
         assert_eq!(
@@ -1042,4 +1392,97 @@ mod ipv4 {
             vec![]
         );
     }
+
+    #[test]
+    fn bgp_aggregate() {
+        let mut r = Router::<Ipv4Prefix>::new("test".to_string(), 0.into(), AsId(65001));
+        r.set_bgp_session::<()>(100.into(), Some(EBgp)).unwrap();
+        r.set_bgp_session::<()>(1.into(), Some(IBgpPeer)).unwrap();
+        r.igp_table = hashmap! {
+            0.into()   => (vec![], 0.0),
+            100.into() => (vec![100.into()], 0.0),
+            1.into()   => (vec![1.into()], 1.0),
+        };
+
+        let agg: Ipv4Prefix = "10.0.0.0/8".parse::<Ipv4Net>().unwrap().into();
+        let p0: Ipv4Prefix = "10.0.0.0/16".parse::<Ipv4Net>().unwrap().into();
+
+        // without any contributing route, nothing is originated.
+        let (old, events) = r
+            .set_bgp_aggregate::<()>(agg, Some(BgpAggregate::new(false, false)))
+            .unwrap();
+        assert_eq!(old, None);
+        assert!(events.is_empty());
+        assert!(r.get_selected_bgp_route(agg).is_none());
+
+        // a contributing route originates the aggregate
+        let (_, events) = r
+            .handle_event(Event::Bgp(
+                (),
+                100.into(),
+                0.into(),
+                BgpEvent::Update(BgpRoute::new(100.into(), p0, 1..=5, None, None)),
+            ))
+            .unwrap();
+        let updates: HashSet<(RouterId, Ipv4Prefix)> = events
+            .into_iter()
+            .map(|e| match e {
+                Event::Bgp(_, _, to, BgpEvent::Update(r)) => (to, r.prefix),
+                _ => panic!("Test failed"),
+            })
+            .collect();
+        assert_eq!(
+            updates,
+            hashset! {(1.into(), p0), (1.into(), agg), (100.into(), agg)}
+        );
+        let entry = r.get_selected_bgp_route(agg).unwrap();
+        assert_eq!(entry.from_id, 0.into());
+        assert_eq!(entry.route.next_hop, 0.into());
+        assert!(entry.route.as_path.is_empty());
+        assert_eq!(entry.route.origin, Origin::Igp);
+
+        // more-specific traffic is forwarded, the rest is dropped.
+        assert_eq!(
+            r.get_next_hop("10.0.0.1/32".parse::<Ipv4Net>().unwrap().into()),
+            vec![100.into()]
+        );
+        assert_eq!(
+            r.get_next_hop("10.1.0.1/32".parse::<Ipv4Net>().unwrap().into()),
+            vec![]
+        );
+
+        // summary-only withdraws the more-specific route, and as-set updates the AS path.
+        let (old, events) = r
+            .set_bgp_aggregate::<()>(agg, Some(BgpAggregate::new(true, true)))
+            .unwrap();
+        assert_eq!(old, Some(BgpAggregate::new(false, false)));
+        let events: HashSet<(RouterId, Ipv4Prefix, bool)> = events
+            .into_iter()
+            .map(|e| match e {
+                Event::Bgp(_, _, to, BgpEvent::Update(r)) => (to, r.prefix, true),
+                Event::Bgp(_, _, to, BgpEvent::Withdraw(p)) => (to, p, false),
+                _ => panic!("Test failed"),
+            })
+            .collect();
+        assert_eq!(
+            events,
+            hashset! {(1.into(), p0, false), (1.into(), agg, true), (100.into(), agg, true)}
+        );
+        let entry = r.get_selected_bgp_route(agg).unwrap();
+        assert_eq!(entry.route.as_path, (1..=5).map(AsId).collect::<Vec<_>>());
+
+        // withdrawing the last contributing route withdraws the aggregate
+        let (_, events) = r
+            .handle_event(Event::Bgp((), 100.into(), 0.into(), BgpEvent::Withdraw(p0)))
+            .unwrap();
+        let withdraws: HashSet<(RouterId, Ipv4Prefix)> = events
+            .into_iter()
+            .map(|e| match e {
+                Event::Bgp(_, _, to, BgpEvent::Withdraw(p)) => (to, p),
+                _ => panic!("Test failed"),
+            })
+            .collect();
+        assert_eq!(withdraws, hashset! {(1.into(), agg), (100.into(), agg)});
+        assert!(r.get_selected_bgp_route(agg).is_none());
+    }
 }
@@ -623,7 +623,7 @@ impl From<i32> for SimplePrefix {
 impl From<Ipv4Addr> for SimplePrefix {
     fn from(value: Ipv4Addr) -> Self {
         let num: u32 = value.into();
-        SimplePrefix((num - (100 << 24)) >> 8)
+        SimplePrefix(num.wrapping_sub(100 << 24) >> 8)
     }
 }
 
@@ -641,7 +641,7 @@ impl From<SimplePrefix> for u32 {
 
 impl From<SimplePrefix> for Ipv4Addr {
     fn from(value: SimplePrefix) -> Self {
-        let num = (value.0 << 8) + (100 << 24);
+        let num = (value.0 << 8).wrapping_add(100 << 24);
         Ipv4Addr::from(num)
     }
 }
@@ -23,10 +23,7 @@ use bgpsim::{forwarding_state::ForwardingState, prelude::*};
 use itertools::Itertools;
 use petgraph::{algo::tarjan_scc, stable_graph::StableGraph};
 
-use crate::{
-    decomposition::{CommandInfo, FwDiff},
-    P,
-};
+use crate::decomposition::{CommandInfo, FwDiff};
 
 /// Compute the set of all simple loops in the overlapped forwarding graph. This function was ported
 /// from [`networkx::algorithms::cycles::simple_cycles`](https://networkx.org/documentation/stable/reference/algorithms/generated/networkx.algorithms.cycles.simple_cycles.html#simple-cycles).
@@ -35,7 +32,7 @@ use crate::{
 /// circuit, is a closed path where no node appears twice. Two elementary circuits are distinct if
 /// they are not cyclic permutations of each other. This is a nonrecursive, iterator/generator
 /// version of Johnson’s algorithm [\[1\]](https://doi.org/10.1137/0204007).
-pub fn all_loops<Q>(info: &CommandInfo<'_, Q>, prefix: P) -> HashSet<Vec<RouterId>> {
+pub fn all_loops<P: Prefix, Q>(info: &CommandInfo<'_, P, Q>, prefix: P) -> HashSet<Vec<RouterId>> {
     let delta = if let Some(d) = info.fw_diff.get(&prefix) {
         d
     } else {
@@ -176,7 +173,7 @@ fn unblock(
 type Graph = StableGraph<(), (), petgraph::Directed, u32>;
 
 /// Generate a new graph from a forwarding delta.
-pub fn graph<Q>(
+pub fn graph<P: Prefix, Q>(
     net: &Network<P, Q>,
     fw: &ForwardingState<P>,
     delta: &HashMap<RouterId, FwDiff>,
@@ -196,12 +193,11 @@ pub fn graph<Q>(
 
     for node in net.get_routers() {
         if let Some(FwDiff { old: a, new: b }) = delta.get(&node) {
-            vec![*a, *b]
+            a.iter().chain(b.iter()).copied().unique().collect()
         } else {
-            vec![fw.get_next_hops(node, prefix).first().copied()]
+            fw.get_next_hops(node, prefix).to_vec()
         }
         .into_iter()
-        .flatten()
         .for_each(|nh| {
             graph.add_edge(node, nh, ());
         });
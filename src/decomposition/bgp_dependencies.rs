@@ -27,12 +27,13 @@ use bgpsim::{
 use log::info;
 
 use super::CommandInfo;
-use crate::P;
 
 /// Extract all BGP dependencies from the BGP state before and after. This function will extract not
 /// only the selected routes, but also all routes that are received from other routers, but are
 /// essentially the same.
-pub fn find_dependencies<Q>(info: &'_ CommandInfo<'_, Q>) -> HashMap<P, BgpDependencies> {
+pub fn find_dependencies<P: Prefix, Q>(
+    info: &'_ CommandInfo<'_, P, Q>,
+) -> HashMap<P, BgpDependencies> {
     info!("Extract the BGP Dependencies.");
     let mut result = HashMap::new();
 
@@ -106,8 +107,8 @@ pub fn find_dependencies<Q>(info: &'_ CommandInfo<'_, Q>) -> HashMap<P, BgpDepen
 /// We use a custom equality here, that ignores `cluster_list` and `from_type`, and compares
 /// `originator_id.unwrap_or(from_id)` instead of `originator_id` or `from_id`.
 #[inline]
-fn get_peers_advertising_route<Q>(
-    info: &CommandInfo<'_, Q>,
+fn get_peers_advertising_route<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
     bgp_state: Option<&BgpState<P>>,
     router: RouterId,
     from: RouterId,
@@ -124,6 +125,7 @@ fn get_peers_advertising_route<Q>(
                 &r.community,
                 &r.local_pref,
                 &r.med,
+                &r.origin,
                 &r.next_hop,
                 &r.prefix,
                 r.originator_id.as_ref().unwrap_or(f),
@@ -132,6 +134,7 @@ fn get_peers_advertising_route<Q>(
                 &route.community,
                 &route.local_pref,
                 &route.med,
+                &route.origin,
                 &route.next_hop,
                 &route.prefix,
                 route.originator_id.as_ref().unwrap_or(&from),
@@ -0,0 +1,335 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Module to plan a campaign of multiple reconfiguration commands.
+//!
+//! A [`Campaign`] takes a list of commands, and groups them into stages. Each stage is a
+//! [`ConfigPatch`] that is decomposed jointly (using [`decompose_patch`]). The stages are applied
+//! one after the other, i.e., the final state of one stage is the initial state of the next one.
+//! The planner greedily picks the stage with the lowest [`CampaignCost`], and extends it with
+//! further commands as long as applying them jointly is cheaper than applying them in separate
+//! stages.
+
+use std::{
+    collections::HashMap,
+    iter::Sum,
+    ops::{Add, AddAssign},
+};
+
+use bgpsim::{
+    config::{ConfigModifier, ConfigPatch, NetworkConfig},
+    event::EventQueue,
+    prelude::Network,
+    types::Prefix,
+};
+use log::info;
+
+use super::{decompose_patch, Decomposition, DecompositionError};
+use crate::specification::Specification;
+
+/// A campaign of multiple reconfiguration commands. Create a campaign using [`Campaign::new`] if
+/// the commands can be applied in any order, or using [`Campaign::ordered`] if the commands must
+/// be applied in the given order. Then, call [`Campaign::plan`] to compute the stages.
+#[derive(Debug, Clone)]
+pub struct Campaign<P: Prefix> {
+    /// Commands to apply during the campaign.
+    commands: Vec<ConfigModifier<P>>,
+    /// Wether the commands must be applied in the given order.
+    ordered: bool,
+    /// Wether multiple commands may be grouped into a single stage.
+    grouping: bool,
+}
+
+impl<P: Prefix> Campaign<P> {
+    /// Create a new campaign in which the commands can be applied in any order.
+    pub fn new(commands: impl IntoIterator<Item = ConfigModifier<P>>) -> Self {
+        Self {
+            commands: commands.into_iter().collect(),
+            ordered: false,
+            grouping: true,
+        }
+    }
+
+    /// Create a new campaign in which the commands must be applied in the given order. The
+    /// planner may still group consecutive commands into a single stage.
+    pub fn ordered(commands: impl IntoIterator<Item = ConfigModifier<P>>) -> Self {
+        Self {
+            commands: commands.into_iter().collect(),
+            ordered: true,
+            grouping: true,
+        }
+    }
+
+    /// Enable or disable grouping of multiple commands into a single stage. If disabled, each
+    /// stage consists of exactly one command. Grouping is enabled by default.
+    pub fn grouping(mut self, grouping: bool) -> Self {
+        self.grouping = grouping;
+        self
+    }
+
+    /// Get the commands of the campaign.
+    pub fn commands(&self) -> &[ConfigModifier<P>] {
+        &self.commands
+    }
+
+    /// Plan the campaign on the given network. Each stage is decomposed on the final state of the
+    /// previous stage, and it must satisfy the specification `spec`.
+    pub fn plan<Q>(
+        &self,
+        net: &Network<P, Q>,
+        spec: &Specification<P>,
+    ) -> Result<CampaignPlan<P>, DecompositionError<P>>
+    where
+        Q: EventQueue<P> + Clone,
+    {
+        let mut net = net.clone();
+        let mut remaining = self.commands.clone();
+        let mut stages = Vec::new();
+
+        while !remaining.is_empty() {
+            // pick the first command of the stage
+            let (idx, mut stage) = if self.ordered {
+                (
+                    0,
+                    CampaignStage::new(&net, vec![remaining[0].clone()], spec)?,
+                )
+            } else {
+                cheapest_stage(&net, &remaining, spec)?
+            };
+            remaining.remove(idx);
+
+            // extend the stage as long as it reduces the cost.
+            if self.grouping {
+                let mut net_after = net.clone();
+                net_after.apply_patch(&stage.patch)?;
+                let mut i = 0;
+                while i < remaining.len() {
+                    let cmd = &remaining[i];
+                    if let Some(merged) = try_merge(&net, &net_after, &stage, cmd, spec) {
+                        net_after.apply_modifier(cmd)?;
+                        stage = merged;
+                        remaining.remove(i);
+                    } else if self.ordered {
+                        break;
+                    } else {
+                        i += 1;
+                    }
+                }
+            }
+
+            info!(
+                "Campaign stage {} with {} commands and cost {}",
+                stages.len(),
+                stage.patch.modifiers.len(),
+                stage.cost
+            );
+            net.apply_patch(&stage.patch)?;
+            stages.push(stage);
+        }
+
+        Ok(CampaignPlan { stages })
+    }
+}
+
+/// Find the command in `commands` that has the lowest cost when applied to `net` in a separate
+/// stage. If no command can be decomposed, return the error of the first one.
+fn cheapest_stage<P: Prefix, Q>(
+    net: &Network<P, Q>,
+    commands: &[ConfigModifier<P>],
+    spec: &Specification<P>,
+) -> Result<(usize, CampaignStage<P>), DecompositionError<P>>
+where
+    Q: EventQueue<P> + Clone,
+{
+    let mut best: Option<(usize, CampaignStage<P>)> = None;
+    let mut error = None;
+    for (i, cmd) in commands.iter().enumerate() {
+        match CampaignStage::new(net, vec![cmd.clone()], spec) {
+            Ok(stage) => {
+                if best
+                    .as_ref()
+                    .map(|(_, b)| stage.cost.total() < b.cost.total())
+                    .unwrap_or(true)
+                {
+                    best = Some((i, stage))
+                }
+            }
+            Err(e) => {
+                log::debug!("Cannot decompose command {i} in the current state: {e}");
+                error.get_or_insert(e);
+            }
+        }
+    }
+    best.ok_or_else(|| error.unwrap())
+}
+
+/// Try to add `cmd` to `stage`. Return the merged stage only if it is not more expensive than
+/// applying `cmd` in a separate stage after `stage`. `net_before` is the initial state of `stage`,
+/// and `net_after` is its final state.
+fn try_merge<P: Prefix, Q>(
+    net_before: &Network<P, Q>,
+    net_after: &Network<P, Q>,
+    stage: &CampaignStage<P>,
+    cmd: &ConfigModifier<P>,
+    spec: &Specification<P>,
+) -> Option<CampaignStage<P>>
+where
+    Q: EventQueue<P> + Clone,
+{
+    let separate = CampaignStage::new(net_after, vec![cmd.clone()], spec).ok()?;
+    let mut commands = stage.patch.modifiers.clone();
+    commands.push(cmd.clone());
+    let merged = CampaignStage::new(net_before, commands, spec).ok()?;
+    (merged.cost.total() <= stage.cost.total() + separate.cost.total()).then_some(merged)
+}
+
+/// The result of planning a [`Campaign`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "P: for<'a> serde::Deserialize<'a>"))
+)]
+pub struct CampaignPlan<P: Prefix> {
+    /// Stages to apply, in order.
+    pub stages: Vec<CampaignStage<P>>,
+}
+
+impl<P: Prefix> CampaignPlan<P> {
+    /// Get the aggregated cost of all stages.
+    pub fn cost(&self) -> CampaignCost {
+        self.stages.iter().map(|s| s.cost).sum()
+    }
+
+    /// Get an iterator over the decompositions of all stages, in the order in which they must be
+    /// applied.
+    pub fn decompositions(&self) -> impl Iterator<Item = &Decomposition<P>> {
+        self.stages.iter().map(|s| &s.decomposition)
+    }
+}
+
+/// A single stage of a [`CampaignPlan`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "P: for<'a> serde::Deserialize<'a>"))
+)]
+pub struct CampaignStage<P: Prefix> {
+    /// Commands that are applied jointly in this stage.
+    pub patch: ConfigPatch<P>,
+    /// Decomposition of the stage.
+    pub decomposition: Decomposition<P>,
+    /// Cost of the stage.
+    pub cost: CampaignCost,
+}
+
+impl<P: Prefix> CampaignStage<P> {
+    /// Decompose the commands jointly and compute the cost.
+    fn new<Q>(
+        net: &Network<P, Q>,
+        commands: Vec<ConfigModifier<P>>,
+        spec: &Specification<P>,
+    ) -> Result<Self, DecompositionError<P>>
+    where
+        Q: EventQueue<P> + Clone,
+    {
+        let patch = ConfigPatch::from(commands);
+        let decomposition = decompose_patch(net, &patch, spec)?;
+        let cost = CampaignCost::new(&decomposition);
+        Ok(Self {
+            patch,
+            decomposition,
+            cost,
+        })
+    }
+}
+
+/// Cost of a stage (or an entire campaign).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct CampaignCost {
+    /// Number of temporary BGP sessions (see [`NodeSchedule::cost`]).
+    ///
+    /// [`NodeSchedule::cost`]: super::ilp_scheduler::NodeSchedule::cost
+    pub temp_sessions: usize,
+    /// Number of rounds executed by the controller. Rounds of different prefixes are executed in
+    /// parallel.
+    pub rounds: usize,
+}
+
+impl CampaignCost {
+    /// Compute the cost of a decomposition.
+    pub fn new<P: Prefix>(decomp: &Decomposition<P>) -> Self {
+        let max_rounds =
+            |stage: &HashMap<P, Vec<_>>| stage.values().map(|x| x.len()).max().unwrap_or(0);
+        Self {
+            temp_sessions: decomp
+                .schedule
+                .values()
+                .flat_map(|s| s.values())
+                .map(|s| s.cost())
+                .sum(),
+            rounds: decomp.setup_commands.len()
+                + max_rounds(&decomp.atomic_before)
+                + decomp.main_commands.len()
+                + max_rounds(&decomp.atomic_after)
+                + decomp.cleanup_commands.len(),
+        }
+    }
+
+    /// Get the total cost, which weights the temporary sessions twice as much as the rounds (just
+    /// like the objective of the scheduler).
+    pub fn total(&self) -> usize {
+        self.rounds + 2 * self.temp_sessions
+    }
+}
+
+impl std::fmt::Display for CampaignCost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({} temporary sessions, {} rounds)",
+            self.total(),
+            self.temp_sessions,
+            self.rounds
+        )
+    }
+}
+
+impl Add for CampaignCost {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            temp_sessions: self.temp_sessions + rhs.temp_sessions,
+            rounds: self.rounds + rhs.rounds,
+        }
+    }
+}
+
+impl AddAssign for CampaignCost {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sum for CampaignCost {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
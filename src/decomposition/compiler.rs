@@ -29,46 +29,61 @@ use bgpsim::{
     config::{
         ConfigExpr, ConfigExprKey,
         ConfigModifier::{self, *},
-        RouteMapEdit,
+        ConfigPatch, RouteMapEdit,
     },
-    prelude::{BgpSessionType, NetworkFormatter},
+    prelude::{BgpSessionType, Network, NetworkFormatter},
     route_map::{RouteMapBuilder, RouteMapDirection},
-    types::RouterId,
+    types::{Prefix, PrefixMap, RouterId},
 };
+use ipnet::Ipv4Net;
+use itertools::Itertools;
 use lazy_static::lazy_static;
 
-use crate::{Decomposition, P};
+use crate::Decomposition;
 
 use super::{
     bgp_dependencies::BgpDependencies,
     ilp_scheduler::{FwStateTrace, NodeSchedule, Schedule},
-    CommandInfo, DecompositionError,
+    rollback::{self, Rollback},
+    CommandInfo, DecompositionError, Fallback,
 };
 
 /// Type definition for a single stage
-type Stage = Vec<Vec<AtomicCommand<P>>>;
+type Stage<P> = Vec<Vec<AtomicCommand<P>>>;
+/// Type definition for the commands of a single prefix: The rounds before the main commands, the
+/// commands in between the main commands, and the rounds after the main commands.
+type PrefixCommands<P> = (Stage<P>, Vec<AtomicCommand<P>>, Stage<P>);
+/// Type definition for the commands of all prefixes: The rounds before the main commands, the
+/// commands in between the main commands, and the rounds after the main commands.
+type AllPrefixCommands<P> = (
+    HashMap<P, Stage<P>>,
+    Vec<AtomicCommand<P>>,
+    HashMap<P, Stage<P>>,
+);
 
 /// Build the atomic decomposition of the command.
-pub fn build<Q>(
-    info: &CommandInfo<'_, Q>,
+pub fn build<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
     bgp_deps: HashMap<P, BgpDependencies>,
     schedules: HashMap<P, (Schedule, FwStateTrace)>,
-) -> Result<Decomposition, DecompositionError> {
+) -> Result<Decomposition<P>, DecompositionError<P>> {
     log::info!("Generate the final decomposition based on the schedule.");
-    match info.command.key() {
-        Some(ConfigExprKey::BgpRouteMap { .. }) | Some(ConfigExprKey::BgpSession { .. }) => {
-            _build(info, bgp_deps, schedules)
+    for (i, cmd) in info.command.modifiers.iter().enumerate() {
+        match cmd.key() {
+            Some(ConfigExprKey::BgpRouteMap { .. }) | Some(ConfigExprKey::BgpSession { .. }) => {}
+            None if matches!(cmd, BatchRouteMapEdit { .. }) => {}
+            _ => return Err(DecompositionError::UnsupportedCommand(i)),
         }
-        _ => unimplemented!(),
     }
+    _build(info, bgp_deps, schedules)
 }
 
 /// Build function with type specialization for each decomposer.
-fn _build<Q>(
-    info: &CommandInfo<'_, Q>,
+fn _build<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
     bgp_deps: HashMap<P, BgpDependencies>,
     schedules: HashMap<P, (Schedule, FwStateTrace)>,
-) -> Result<Decomposition, DecompositionError> {
+) -> Result<Decomposition<P>, DecompositionError<P>> {
     let mut schedule = HashMap::new();
     let mut fw_state_trace = HashMap::new();
     for (p, (sched, trace)) in schedules {
@@ -78,7 +93,20 @@ fn _build<Q>(
 
     let temp_sessions = get_temp_sessions(info, &schedule)?;
 
-    let (atomic_before, atomic_after) = atomic_commands(info, &schedule, &bgp_deps)?;
+    let ((atomic_before, main_round, atomic_after), (rev_before, rev_main_round, rev_after)) =
+        atomic_commands(info, &schedule, &bgp_deps)?;
+    let setup_commands = setup_commands(info, &schedule, &bgp_deps, &temp_sessions)?;
+    let cleanup_commands = cleanup_commands(info, &schedule, &bgp_deps, &temp_sessions)?;
+
+    // build the rollback plan, and verify it on the forwarding state trace.
+    let rollback = Rollback {
+        setup_commands: reverse_setup_commands(&setup_commands, info, &bgp_deps)?,
+        atomic_before: rev_before,
+        main_commands: main_command(info, rev_main_round, true),
+        atomic_after: rev_after,
+        cleanup_commands: reverse_cleanup_commands(&cleanup_commands, info),
+        unverified: rollback::verify(info, &fw_state_trace),
+    };
 
     // first, build the basic structure of the composition with the setup and cleanup commands, as
     // well as with the main commands.
@@ -87,11 +115,12 @@ fn _build<Q>(
         bgp_deps: Default::default(),
         schedule: Default::default(),
         fw_state_trace,
-        setup_commands: setup_commands(info, &schedule, &bgp_deps, &temp_sessions)?,
-        cleanup_commands: cleanup_commands(info, &schedule, &bgp_deps, &temp_sessions)?,
+        setup_commands,
+        cleanup_commands,
         atomic_before,
-        main_commands: main_command(info),
+        main_commands: main_command(info, main_round, false),
         atomic_after,
+        rollback,
     };
 
     // finally, set the schedule
@@ -120,28 +149,33 @@ const NEW_ROUTE_WEIGHT: u32 = 3000; // u16::MAX as u32 - 1;
 
 /// Get the order for modifying temporary bgp sessions (outgoing route-maps to specifically allow
 /// routes)
-fn temp_session_order(prefix: P) -> i16 {
+fn temp_session_order<P: Prefix>(prefix: P) -> i16 {
     lazy_static! {
-        static ref ASSIGNMENT: Mutex<HashMap<P, i16>> = Mutex::new(HashMap::new());
+        static ref ASSIGNMENT: Mutex<HashMap<Ipv4Net, i16>> = Mutex::new(HashMap::new());
     }
     let mut ass = ASSIGNMENT.lock().unwrap();
     let next = ass.len();
-    *ass.entry(prefix).or_insert(next as i16)
+    *ass.entry(prefix.into()).or_insert(next as i16)
 }
 
 /// Get the order for modifying temporary bgp sessions (outgoing route-maps to specifically allow
 /// routes)
-fn pref_order(prefix: P) -> i16 {
+fn pref_order<P: Prefix>(prefix: P) -> i16 {
     lazy_static! {
-        static ref ASSIGNMENT: Mutex<HashMap<P, i16>> = Mutex::new(HashMap::new());
+        static ref ASSIGNMENT: Mutex<HashMap<Ipv4Net, i16>> = Mutex::new(HashMap::new());
     }
     let mut ass = ASSIGNMENT.lock().unwrap();
     let next = i16::MIN + 1 + ass.len() as i16;
-    *ass.entry(prefix).or_insert(next)
+    *ass.entry(prefix.into()).or_insert(next)
 }
 
 /// Get the config expr to prefer a specific route.
-fn prefer_route(router: RouterId, neighbor: RouterId, prefix: P, weight: u32) -> ConfigExpr<P> {
+fn prefer_route<P: Prefix>(
+    router: RouterId,
+    neighbor: RouterId,
+    prefix: P,
+    weight: u32,
+) -> ConfigExpr<P> {
     ConfigExpr::BgpRouteMap {
         router,
         neighbor,
@@ -155,8 +189,40 @@ fn prefer_route(router: RouterId, neighbor: RouterId, prefix: P, weight: u32) ->
     }
 }
 
+/// Get the config expr to ignore all routes for a prefix learned from a specific neighbor. It uses
+/// the same order as [`prefer_route`], such that the two route-map items replace each other.
+fn ignore_route<P: Prefix>(router: RouterId, neighbor: RouterId, prefix: P) -> ConfigExpr<P> {
+    ConfigExpr::BgpRouteMap {
+        router,
+        neighbor,
+        direction: RouteMapDirection::Incoming,
+        map: RouteMapBuilder::new()
+            .deny()
+            .order_sgn(pref_order(prefix))
+            .match_prefix(prefix)
+            .build(),
+    }
+}
+
+/// Get all neighbors from which `router` learns a route for `prefix` in the given network.
+fn rib_in_neighbors<P: Prefix, Q>(
+    net: &Network<P, Q>,
+    router: RouterId,
+    prefix: P,
+) -> Result<Vec<RouterId>, DecompositionError<P>> {
+    Ok(net
+        .get_device(router)
+        .internal_or_err()?
+        .get_bgp_rib_in()
+        .get(&prefix)
+        .into_iter()
+        .flat_map(|t| t.keys().copied())
+        .sorted()
+        .collect())
+}
+
 /// Generate the atomic modifier to use a temporary session
-fn use_temp_session(router: RouterId, egress: RouterId, prefix: P) -> AtomicModifier<P> {
+fn use_temp_session<P: Prefix>(router: RouterId, egress: RouterId, prefix: P) -> AtomicModifier<P> {
     AtomicModifier::UseTempSession {
         router,
         neighbor: egress,
@@ -177,7 +243,11 @@ fn use_temp_session(router: RouterId, egress: RouterId, prefix: P) -> AtomicModi
 }
 
 /// Generate the atomic modifier to ignore a temporary session
-fn ignore_temp_session(router: RouterId, egress: RouterId, prefix: P) -> AtomicModifier<P> {
+fn ignore_temp_session<P: Prefix>(
+    router: RouterId,
+    egress: RouterId,
+    prefix: P,
+) -> AtomicModifier<P> {
     AtomicModifier::IgnoreTempSession {
         router,
         neighbor: egress,
@@ -198,89 +268,115 @@ fn ignore_temp_session(router: RouterId, egress: RouterId, prefix: P) -> AtomicM
 }
 
 /// Get the neighbor that will announce the old route towards `router` as long as `router` selects
-/// the old route.
-fn old_neighbor<Q>(
+/// the old route. If multiple neighbors announce it equally long, prefer the one from which
+/// `router` selects the old route.
+fn old_neighbor<P: Prefix, Q>(
     router: RouterId,
-    info: &CommandInfo<'_, Q>,
+    info: &CommandInfo<'_, P, Q>,
     schedules: &Schedule,
     bgp_deps: &BgpDependencies,
     prefix: P,
 ) -> Option<RouterId> {
+    let selected = info
+        .bgp_before
+        .get(&prefix)
+        .and_then(|bgp| bgp.get(router).map(|(n, _)| n));
     bgp_deps
         .get(&router)
-        .unwrap()
-        .old_from
-        .iter()
+        .into_iter()
+        .flat_map(|deps| deps.old_from.iter())
         .map(|n| {
             (
                 *n,
                 schedules.get(n).map(|s| s.old_route).unwrap_or(usize::MAX),
             )
         })
-        .max_by_key(|(_, x)| *x)
+        .max_by_key(|(n, x)| (*x, Some(*n) == selected))
         .map(|(n, _)| n)
-        .or_else(|| {
-            info.bgp_before
-                .get(&prefix)
-                .and_then(|bgp| bgp.get(router).map(|(n, _)| n))
-        })
+        .or(selected)
 }
 
 /// Get the neighbor that will announce the new route route towards `router` as soon as `router`
-/// selects the new route.
-fn new_neighbor<Q>(
+/// selects the new route. If multiple neighbors announce it equally early, prefer the one from
+/// which `router` selects the new route.
+fn new_neighbor<P: Prefix, Q>(
     router: RouterId,
-    info: &CommandInfo<'_, Q>,
+    info: &CommandInfo<'_, P, Q>,
     schedules: &Schedule,
     bgp_deps: &BgpDependencies,
     prefix: P,
 ) -> Option<RouterId> {
+    let selected = info
+        .bgp_after
+        .get(&prefix)
+        .and_then(|bgp| bgp.get(router).map(|(n, _)| n));
     bgp_deps
         .get(&router)
-        .unwrap()
-        .new_from
-        .iter()
+        .into_iter()
+        .flat_map(|deps| deps.new_from.iter())
         .map(|n| (*n, schedules.get(n).map(|s| s.new_route).unwrap_or(0)))
-        .min_by_key(|(_, x)| *x)
+        .min_by_key(|(n, x)| (*x, Some(*n) != selected))
         .map(|(n, _)| n)
-        .or_else(|| {
-            info.bgp_after
-                .get(&prefix)
-                .and_then(|bgp| bgp.get(router).map(|(n, _)| n))
-        })
+        .or(selected)
 }
 
 /// Get the next-hop attribute of the old route
-fn old_nh<Q>(info: &CommandInfo<'_, Q>, router: RouterId, prefix: P) -> Option<RouterId> {
+fn old_nh<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    router: RouterId,
+    prefix: P,
+) -> Option<RouterId> {
     info.bgp_before
         .get(&prefix)
-        .unwrap()
-        .get(router)
+        .and_then(|bgp| bgp.get(router))
         .map(|(_, r)| r.next_hop)
 }
 
 /// Get the next-hop attribute of the new route
-fn new_nh<Q>(info: &CommandInfo<'_, Q>, router: RouterId, prefix: P) -> Option<RouterId> {
+fn new_nh<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    router: RouterId,
+    prefix: P,
+) -> Option<RouterId> {
     info.bgp_after
         .get(&prefix)
-        .unwrap()
-        .get(router)
+        .and_then(|bgp| bgp.get(router))
         .map(|(_, r)| r.next_hop)
 }
 
 /// Generate the commands for the setup stage
-fn setup_commands<Q>(
-    info: &CommandInfo<'_, Q>,
+fn setup_commands<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
     schedules: &HashMap<P, Schedule>,
     bgp_deps: &HashMap<P, BgpDependencies>,
     temp_sessions: &HashSet<(RouterId, RouterId)>,
-) -> Result<Stage, DecompositionError> {
+) -> Result<Stage<P>, DecompositionError<P>> {
     let mut cmds = Vec::new();
 
-    // first, force the initial state
+    // first, force the initial state. Routers that only know a route after the migration ignore all
+    // routes until they select the new route.
     for (p, s) in schedules {
         let deps = bgp_deps.get(p).unwrap();
         for r in s.keys() {
+            if old_neighbor(*r, info, s, deps, *p).is_none()
+                && new_neighbor(*r, info, s, deps, *p).is_some()
+            {
+                cmds.push(AtomicCommand {
+                    command: AtomicModifier::IgnoreRoutes {
+                        router: *r,
+                        prefix: *p,
+                        raw: rib_in_neighbors(&info.net_after, *r, *p)?
+                            .into_iter()
+                            .map(|n| Insert(ignore_route(*r, n, *p)))
+                            .collect(),
+                    },
+                    precondition: AtomicCondition::None,
+                    postcondition: AtomicCondition::NoSelectedRoute {
+                        router: *r,
+                        prefix: *p,
+                    },
+                })
+            }
             if let Some(n) = old_neighbor(*r, info, s, deps, *p) {
                 cmds.push(AtomicCommand {
                     command: AtomicModifier::ChangePreference {
@@ -346,41 +442,157 @@ fn setup_commands<Q>(
     Ok(vec![cmds])
 }
 
-/// Generate the main command for the decomposition
-fn main_command<Q>(info: &CommandInfo<'_, Q>) -> Stage {
-    vec![vec![AtomicCommand {
-        command: AtomicModifier::Raw(info.command.clone()),
-        precondition: AtomicCondition::None,
-        postcondition: AtomicCondition::None,
-    }]]
+/// Generate the main commands for the decomposition. If the patch only adds or only removes
+/// sessions, then all commands are applied in a single round. Otherwise, the main stage consists
+/// of three rounds: first, apply all commands that do not remove a session, then perform the
+/// `main_round` (i.e., the round `cmd_round` of each prefix), and finally, remove the sessions.
+///
+/// If `reverse` is set, then the raw commands are reversed, such that the result has the same shape
+/// as the main stage, but it can be used for the rollback plan.
+fn main_command<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    main_round: Vec<AtomicCommand<P>>,
+    reverse: bool,
+) -> Stage<P> {
+    let raw = |cmd: &ConfigModifier<P>| {
+        let cmd = if reverse {
+            cmd.clone().reverse()
+        } else {
+            cmd.clone()
+        };
+        // When rolling back, wait until each session that is added again is established, such
+        // that the routes learned over that session are available in the following rounds.
+        let postcondition = match &cmd {
+            Insert(ConfigExpr::BgpSession { source, target, .. })
+            | Update {
+                to: ConfigExpr::BgpSession { source, target, .. },
+                ..
+            } if reverse => {
+                // the session state is checked on the internal router.
+                let (router, neighbor) = if info.net_before.get_device(*source).is_internal() {
+                    (*source, *target)
+                } else {
+                    (*target, *source)
+                };
+                AtomicCondition::BgpSessionEstablished { router, neighbor }
+            }
+            _ => AtomicCondition::None,
+        };
+        AtomicCommand {
+            command: AtomicModifier::Raw(cmd),
+            precondition: AtomicCondition::None,
+            postcondition,
+        }
+    };
+    match main_position(&info.command) {
+        MainPosition::Before | MainPosition::After => {
+            vec![info.command.modifiers.iter().map(raw).collect()]
+        }
+        MainPosition::Around => vec![
+            info.command
+                .modifiers
+                .iter()
+                .filter(|c| !does_cmd_remove_session(c))
+                .map(raw)
+                .collect(),
+            main_round,
+            info.command
+                .modifiers
+                .iter()
+                .filter(|c| does_cmd_remove_session(c))
+                .map(raw)
+                .collect(),
+        ]
+        .into_iter()
+        .filter(|round| !round.is_empty())
+        .collect(),
+    }
+}
+
+/// Position of the main commands relative to the round `cmd_round` of each prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MainPosition {
+    /// Apply the main commands before round `cmd_round`.
+    Before,
+    /// Apply the main commands after round `cmd_round`.
+    After,
+    /// Apply the commands that do not remove sessions before, and the ones that remove a session
+    /// after round `cmd_round`.
+    Around,
 }
 
-/// Generate the atomic for all prefixes
+/// Compute the position of the main commands of the patch.
+fn main_position<P: Prefix>(patch: &ConfigPatch<P>) -> MainPosition {
+    let num_removes = patch
+        .modifiers
+        .iter()
+        .filter(|c| does_cmd_remove_session(c))
+        .count();
+    if num_removes == 0 {
+        MainPosition::Before
+    } else if num_removes == patch.modifiers.len() {
+        MainPosition::After
+    } else {
+        MainPosition::Around
+    }
+}
+
+/// Generate the atomic for all prefixes. In addition to the commands before and after the main
+/// commands, this function returns the commands that must be executed in between the main
+/// commands (see [`MainPosition::Around`]). The second element of the result contains the reverse
+/// commands for the rollback plan, in the same shape.
+///
+/// Prefixes that were scheduled together (see [`CommandInfo::prefix_groups`]) must execute their
+/// rounds in lockstep. Therefore, the stages of all prefixes in a group are merged into the stage
+/// of the least-specific prefix.
 #[allow(clippy::type_complexity)]
-fn atomic_commands<Q>(
-    info: &CommandInfo<'_, Q>,
+fn atomic_commands<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
     schedules: &HashMap<P, Schedule>,
     bgp_deps: &HashMap<P, BgpDependencies>,
-) -> Result<(HashMap<P, Stage>, HashMap<P, Stage>), DecompositionError> {
-    let mut atomic_before = HashMap::new();
-    let mut atomic_after = HashMap::new();
-    for (p, schedule) in schedules {
-        let (cmds_before, cmds_after) =
-            atomic_commands_for_prefix(info, schedule, bgp_deps.get(p).unwrap(), *p)?;
-        atomic_before.insert(*p, cmds_before);
-        atomic_after.insert(*p, cmds_after);
+) -> Result<(AllPrefixCommands<P>, AllPrefixCommands<P>), DecompositionError<P>> {
+    let mut forward: AllPrefixCommands<P> = Default::default();
+    let mut reverse: AllPrefixCommands<P> = Default::default();
+    for group in info.prefix_groups() {
+        let leader = group[0];
+        for p in group.iter().filter(|p| schedules.contains_key(p)) {
+            let (cmds, rev_cmds) = atomic_commands_for_prefix(
+                info,
+                schedules.get(p).unwrap(),
+                bgp_deps.get(p).unwrap(),
+                *p,
+            )?;
+            for (result, (cmds_before, cmds_main, cmds_after)) in
+                [(&mut forward, cmds), (&mut reverse, rev_cmds)]
+            {
+                merge_stages(result.0.entry(leader).or_default(), cmds_before);
+                result.1.extend(cmds_main);
+                merge_stages(result.2.entry(leader).or_default(), cmds_after);
+            }
+        }
     }
-    Ok((atomic_before, atomic_after))
+    Ok((forward, reverse))
 }
 
-/// Get the round at which we must apply the command.
-fn get_cmd_round(
-    cmd: &ConfigModifier<P>,
+/// Merge `other` into `stage`, such that the commands of the same round are executed together.
+fn merge_stages<P: Prefix>(stage: &mut Stage<P>, other: Stage<P>) {
+    if stage.len() < other.len() {
+        stage.resize_with(other.len(), Vec::new);
+    }
+    for (round, cmds) in stage.iter_mut().zip(other) {
+        round.extend(cmds);
+    }
+}
+
+/// Get the round at which we must apply the command. All routers modified by any command of the
+/// patch must migrate in the same round.
+fn get_cmd_round<P: Prefix>(
+    patch: &ConfigPatch<P>,
     schedules: &Schedule,
     prefix: P,
-) -> Result<usize, DecompositionError> {
+) -> Result<usize, DecompositionError<P>> {
     let mut cmd_round = None;
-    for r in cmd.routers() {
+    for r in patch.modifiers.iter().flat_map(|c| c.routers()) {
         if let Some(s) = schedules.get(&r) {
             if s.old_route != s.fw_state || s.new_route != s.fw_state {
                 return Err(DecompositionError::InconsistentMainCommandRound(
@@ -405,7 +617,7 @@ fn get_cmd_round(
 
 /// If the command removes a session, then we need to apply that command after the router actually
 /// have updated.
-fn does_cmd_remove_session(cmd: &ConfigModifier<P>) -> bool {
+fn does_cmd_remove_session<P: Prefix>(cmd: &ConfigModifier<P>) -> bool {
     matches!(cmd, ConfigModifier::Remove(ConfigExpr::BgpSession { .. }))
         || matches!(
             cmd,
@@ -422,26 +634,44 @@ fn does_cmd_remove_session(cmd: &ConfigModifier<P>) -> bool {
         )
 }
 
-/// Generate the atomic commands for a single prefix.
-fn atomic_commands_for_prefix<Q>(
-    info: &CommandInfo<'_, Q>,
+/// Generate the atomic commands for a single prefix. The returned tuple contains the rounds
+/// before the main commands, the commands to execute in between the main commands (only for
+/// [`MainPosition::Around`]), and the rounds after the main commands. The second element contains
+/// the reverse commands for the rollback plan in the same shape (see [`reverse_prefix_stage`]).
+fn atomic_commands_for_prefix<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
     schedules: &Schedule,
     bgp_deps: &BgpDependencies,
     prefix: P,
-) -> Result<(Stage, Stage), DecompositionError> {
+) -> Result<(PrefixCommands<P>, PrefixCommands<P>), DecompositionError<P>> {
     // check if the schedule is non-empty
     if schedules.is_empty() {
-        return Ok((Vec::new(), Vec::new()));
+        return Ok(Default::default());
     }
 
     let cmd_round = get_cmd_round(&info.command, schedules, prefix)?;
     let num_rounds = schedules.values().map(|s| s.new_route).max().unwrap_or(0) + 1;
-    let mut stage: Stage = (0..num_rounds).map(|_| Vec::new()).collect();
+    let mut stage: Stage<P> = (0..num_rounds).map(|_| Vec::new()).collect();
 
     // build the stage according to our rules
     for (r, s) in schedules {
+        let old_n = old_neighbor(*r, info, schedules, bgp_deps, prefix);
+        let new_n = new_neighbor(*r, info, schedules, bgp_deps, prefix);
+        // routers that do not know a route before or after always have `r_old == r_fw == r_new`.
+        match (old_n, new_n) {
+            (Some(_), Some(_)) => {}
+            (Some(old_n), None) => {
+                ignore_routes_in_r_old(&mut stage, *r, old_n, info, schedules, prefix)?;
+                continue;
+            }
+            (None, Some(_)) => {
+                prefer_new_route_in_r_new(&mut stage, *r, info, schedules, bgp_deps, prefix)?;
+                continue;
+            }
+            (None, None) => continue,
+        }
         // first, make r select the new route at r_new (that's common in all phases)
-        prefer_new_route_in_r_new(&mut stage, *r, info, schedules, bgp_deps, prefix);
+        prefer_new_route_in_r_new(&mut stage, *r, info, schedules, bgp_deps, prefix)?;
         // then, check which rule applies
         if s.old_route == s.fw_state && s.fw_state == s.new_route {
             // nothing to do!
@@ -454,40 +684,170 @@ fn atomic_commands_for_prefix<Q>(
         }
     }
 
+    let rev_stage = reverse_prefix_stage(&stage, info, schedules, bgp_deps, prefix)?;
+
     // now split the stage at cmd_round and return
-    let stage_after = if does_cmd_remove_session(&info.command) {
-        stage.split_off(cmd_round + 1)
-    } else {
-        stage.split_off(cmd_round)
+    let split = |mut stage: Stage<P>| match main_position(&info.command) {
+        MainPosition::Before => {
+            let stage_after = stage.split_off(cmd_round);
+            (stage, Vec::new(), stage_after)
+        }
+        MainPosition::After => {
+            let stage_after = stage.split_off(cmd_round + 1);
+            (stage, Vec::new(), stage_after)
+        }
+        MainPosition::Around => {
+            let stage_after = stage.split_off(cmd_round + 1);
+            let main_round = stage.pop().unwrap_or_default();
+            (stage, main_round, stage_after)
+        }
     };
-    Ok((stage, stage_after))
+    Ok((split(stage), split(rev_stage)))
 }
 
-/// Add the commands to prefer the new route in r_new.
+/// Route that a router selects in between two rounds of the schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SelectedRoute {
+    /// The old route with the increased weight (see [`setup_commands`]).
+    Old,
+    /// The route of the old egress, learned over the temporary session.
+    TempOld,
+    /// The route of the new egress, learned over the temporary session.
+    TempNew,
+}
+
+/// Reverse the stage of a single prefix for the rollback plan. Each command is stored at the same
+/// position as the command it reverses. Reversing the commands of round `k` of a router will make
+/// it select the route it has selected before round `k`:
+///
+/// - up to round `r_old`: the old route,
+/// - up to round `r_fw`: the route from the old egress over the temporary session,
+/// - up to round `r_new`: the route from the new egress over the temporary session.
+///
+/// Commands that remove the currently selected route (i.e., the reverse of changing the
+/// preference, and the reverse of using a temporary session) wait until the route to select is
+/// available. Commands that use a temporary session again (i.e., the reverse of ignoring it) wait
+/// until that session is established. All commands wait until that route is selected. Routers
+/// that do not know any route before the migration ignore all routes again, and wait until they no
+/// longer select any route.
+fn reverse_prefix_stage<P: Prefix, Q>(
+    stage: &Stage<P>,
+    info: &CommandInfo<'_, P, Q>,
+    schedules: &Schedule,
+    bgp_deps: &BgpDependencies,
+    prefix: P,
+) -> Result<Stage<P>, DecompositionError<P>> {
+    let missing = |router| DecompositionError::MissingRoute(prefix, router);
+    let selected_route = |router: RouterId, round: usize| -> Result<_, DecompositionError<P>> {
+        let s = schedules.get(&router).ok_or_else(|| missing(router))?;
+        Ok(if round <= s.old_route {
+            old_neighbor(router, info, schedules, bgp_deps, prefix)
+                .map(|neighbor| (SelectedRoute::Old, neighbor, old_nh(info, router, prefix)))
+        } else if round <= s.fw_state {
+            let egress = old_nh(info, router, prefix).ok_or_else(|| missing(router))?;
+            Some((SelectedRoute::TempOld, egress, Some(egress)))
+        } else {
+            let egress = new_nh(info, router, prefix).ok_or_else(|| missing(router))?;
+            Some((SelectedRoute::TempNew, egress, Some(egress)))
+        })
+    };
+
+    stage
+        .iter()
+        .enumerate()
+        .map(|(round, cmds)| {
+            cmds.iter()
+                .map(|cmd| {
+                    let router = cmd.command.routers()[0];
+                    let (route, neighbor, next_hop) = match selected_route(router, round)? {
+                        Some(selected) => selected,
+                        None => {
+                            return Ok(AtomicCommand {
+                                command: cmd.command.clone().reverse(),
+                                precondition: AtomicCondition::None,
+                                postcondition: AtomicCondition::NoSelectedRoute { router, prefix },
+                            })
+                        }
+                    };
+                    let weight = match route {
+                        SelectedRoute::Old => OLD_ROUTE_WEIGHT,
+                        SelectedRoute::TempOld | SelectedRoute::TempNew => TMP_ROUTE_WEIGHT,
+                    };
+                    let precondition = match cmd.command {
+                        AtomicModifier::ChangePreference { .. }
+                        | AtomicModifier::UseTempSession { .. } => {
+                            AtomicCondition::AvailableRoute {
+                                router,
+                                prefix,
+                                neighbor: Some(neighbor),
+                                weight: (route != SelectedRoute::Old).then_some(weight),
+                                next_hop,
+                            }
+                        }
+                        AtomicModifier::IgnoreTempSession {
+                            router, neighbor, ..
+                        } => AtomicCondition::BgpSessionEstablished { router, neighbor },
+                        _ => AtomicCondition::None,
+                    };
+                    Ok(AtomicCommand {
+                        command: cmd.command.clone().reverse(),
+                        precondition,
+                        postcondition: AtomicCondition::SelectedRoute {
+                            router,
+                            prefix,
+                            neighbor: Some(neighbor),
+                            weight: Some(weight),
+                            next_hop,
+                        },
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Add the commands to prefer the new route in r_new. If the router does not know any route before
+/// the migration, then it stops ignoring all routes (see [`setup_commands`]) instead of removing
+/// the preference of the old route.
 ///
 /// This command is the same for all four rules.
-fn prefer_new_route_in_r_new<Q>(
-    stage: &mut Stage,
+fn prefer_new_route_in_r_new<P: Prefix, Q>(
+    stage: &mut Stage<P>,
     router: RouterId,
-    info: &CommandInfo<'_, Q>,
+    info: &CommandInfo<'_, P, Q>,
     schedules: &Schedule,
     bgp_deps: &BgpDependencies,
     prefix: P,
-) {
+) -> Result<(), DecompositionError<P>> {
     let s = schedules.get(&router).unwrap();
-    let old_n = old_neighbor(router, info, schedules, bgp_deps, prefix).expect("TODO");
-    let new_n = new_neighbor(router, info, schedules, bgp_deps, prefix).expect("TODO");
+    let new_n = new_neighbor(router, info, schedules, bgp_deps, prefix)
+        .ok_or(DecompositionError::MissingRoute(prefix, router))?;
     let new_egress = new_nh(info, router, prefix);
+    let mut raw = match old_neighbor(router, info, schedules, bgp_deps, prefix) {
+        Some(old_n) => vec![Remove(prefer_route(
+            router,
+            old_n,
+            prefix,
+            OLD_ROUTE_WEIGHT,
+        ))],
+        None => rib_in_neighbors(&info.net_after, router, prefix)?
+            .into_iter()
+            .map(|n| Remove(ignore_route(router, n, prefix)))
+            .collect(),
+    };
+    raw.push(Insert(prefer_route(
+        router,
+        new_n,
+        prefix,
+        NEW_ROUTE_WEIGHT,
+    )));
     // changing the preference must be done in any case:
     stage[s.new_route].push(AtomicCommand {
         command: AtomicModifier::ChangePreference {
             router,
             prefix,
             neighbor: new_n,
-            raw: vec![
-                Remove(prefer_route(router, old_n, prefix, OLD_ROUTE_WEIGHT)),
-                Insert(prefer_route(router, new_n, prefix, NEW_ROUTE_WEIGHT)),
-            ],
+            raw,
         },
         precondition: AtomicCondition::AvailableRoute {
             router,
@@ -504,13 +864,63 @@ fn prefer_new_route_in_r_new<Q>(
             next_hop: new_egress,
         },
     });
+    Ok(())
+}
+
+/// Add the commands for a router that does not know any route after the migration. In round
+/// `r_old`, it removes the preference of the old route, and ignores the routes of all neighbors
+/// from which it learns a route before the migration. If the router falls back to a less-specific
+/// prefix (see [`PrefixDependency`](super::PrefixDependency)), it waits until it forwards that
+/// prefix to the new next-hop.
+fn ignore_routes_in_r_old<P: Prefix, Q>(
+    stage: &mut Stage<P>,
+    router: RouterId,
+    old_n: RouterId,
+    info: &CommandInfo<'_, P, Q>,
+    schedules: &Schedule,
+    prefix: P,
+) -> Result<(), DecompositionError<P>> {
+    let s = schedules.get(&router).unwrap();
+    let mut raw = vec![Remove(prefer_route(
+        router,
+        old_n,
+        prefix,
+        OLD_ROUTE_WEIGHT,
+    ))];
+    raw.extend(
+        rib_in_neighbors(info.net_before, router, prefix)?
+            .into_iter()
+            .map(|n| Insert(ignore_route(router, n, prefix))),
+    );
+    let precondition = info
+        .prefix_deps
+        .iter()
+        .find(|d| d.prefix == prefix && d.router == router && d.fallback == Fallback::Final)
+        .map(|d| AtomicCondition::SelectedRoute {
+            router,
+            prefix: d.covering,
+            neighbor: None,
+            weight: None,
+            next_hop: new_nh(info, router, d.covering),
+        })
+        .unwrap_or(AtomicCondition::None);
+    stage[s.old_route].push(AtomicCommand {
+        command: AtomicModifier::IgnoreRoutes {
+            router,
+            prefix,
+            raw,
+        },
+        precondition,
+        postcondition: AtomicCondition::NoSelectedRoute { router, prefix },
+    });
+    Ok(())
 }
 
 /// Compilation rule when `r_old < r_fw == r_new`.
-fn apply_rule_2<Q>(
-    stage: &mut Stage,
+fn apply_rule_2<P: Prefix, Q>(
+    stage: &mut Stage<P>,
     router: RouterId,
-    info: &CommandInfo<'_, Q>,
+    info: &CommandInfo<'_, P, Q>,
     schedules: &Schedule,
     bgp_deps: &BgpDependencies,
     prefix: P,
@@ -550,10 +960,10 @@ fn apply_rule_2<Q>(
 }
 
 /// Compilation rule when `r_old == r_fw < r_new`
-fn apply_rule_3<Q>(
-    stage: &mut Stage,
+fn apply_rule_3<P: Prefix, Q>(
+    stage: &mut Stage<P>,
     router: RouterId,
-    info: &CommandInfo<'_, Q>,
+    info: &CommandInfo<'_, P, Q>,
     schedules: &Schedule,
     bgp_deps: &BgpDependencies,
     prefix: P,
@@ -590,10 +1000,10 @@ fn apply_rule_3<Q>(
 }
 
 /// Compilation rule when `r_old < r_fw < r_new`
-fn apply_rule_4<Q>(
-    stage: &mut Stage,
+fn apply_rule_4<P: Prefix, Q>(
+    stage: &mut Stage<P>,
     router: RouterId,
-    info: &CommandInfo<'_, Q>,
+    info: &CommandInfo<'_, P, Q>,
     schedules: &Schedule,
     bgp_deps: &BgpDependencies,
     prefix: P,
@@ -667,10 +1077,10 @@ fn apply_rule_4<Q>(
 }
 
 /// Compilation rule when `r_old < r_fw < r_new`, but when the egress is the same.
-fn apply_rule_4_same_egress<Q>(
-    stage: &mut Stage,
+fn apply_rule_4_same_egress<P: Prefix, Q>(
+    stage: &mut Stage<P>,
     router: RouterId,
-    info: &CommandInfo<'_, Q>,
+    info: &CommandInfo<'_, P, Q>,
     schedules: &Schedule,
     bgp_deps: &BgpDependencies,
     prefix: P,
@@ -710,12 +1120,12 @@ fn apply_rule_4_same_egress<Q>(
 }
 
 /// Generate the commands for the setup stage
-fn cleanup_commands<Q>(
-    info: &CommandInfo<'_, Q>,
+fn cleanup_commands<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
     schedules: &HashMap<P, Schedule>,
     bgp_deps: &HashMap<P, BgpDependencies>,
     temp_sessions: &HashSet<(RouterId, RouterId)>,
-) -> Result<Stage, DecompositionError> {
+) -> Result<Stage<P>, DecompositionError<P>> {
     let mut cmds = Vec::new();
 
     // first, remove the weight in the final state.
@@ -749,6 +1159,24 @@ fn cleanup_commands<Q>(
                     precondition,
                     postcondition: AtomicCondition::None,
                 })
+            } else if old_neighbor(*r, info, s, deps, *p).is_some() {
+                // the router ignores all routes since it lost the old route. Stop ignoring them
+                // only after all neighbors have withdrawn their route.
+                cmds.push(AtomicCommand {
+                    command: AtomicModifier::ClearPreference {
+                        router: *r,
+                        prefix: *p,
+                        raw: rib_in_neighbors(info.net_before, *r, *p)?
+                            .into_iter()
+                            .map(|n| Remove(ignore_route(*r, n, *p)))
+                            .collect(),
+                    },
+                    precondition: AtomicCondition::NoReceivedRoute {
+                        router: *r,
+                        prefix: *p,
+                    },
+                    postcondition: AtomicCondition::None,
+                })
             }
         }
     }
@@ -794,6 +1222,95 @@ fn cleanup_commands<Q>(
     Ok(vec![cmds])
 }
 
+/// Reverse the setup stage for the rollback plan. Removing the increased weight of the old route
+/// waits until all other routes are less preferred than the old route (as it is done in the
+/// [`cleanup_commands`]), and then until the router selects a route via the old next-hop. By the time the
+/// temporary sessions are removed, the reverse of the first round has already made all routers
+/// select their old route, such that no router uses them anymore.
+fn reverse_setup_commands<P: Prefix, Q>(
+    stage: &Stage<P>,
+    info: &CommandInfo<'_, P, Q>,
+    bgp_deps: &HashMap<P, BgpDependencies>,
+) -> Result<Stage<P>, DecompositionError<P>> {
+    let mut rev_stage = rollback::reverse_stage(stage);
+    for (cmd, rev_cmd) in stage.iter().flatten().zip(rev_stage.iter_mut().flatten()) {
+        if let AtomicModifier::ChangePreference {
+            router,
+            prefix,
+            neighbor,
+            ..
+        } = cmd.command
+        {
+            let route = info
+                .net_before
+                .get_device(router)
+                .internal_or_err()?
+                .get_selected_bgp_route(prefix)
+                .ok_or(DecompositionError::MissingRoute(prefix, router))?;
+            let mut good_neighbors = bgp_deps
+                .get(&prefix)
+                .and_then(|deps| deps.get(&router))
+                .map(|deps| deps.old_from.clone())
+                .unwrap_or_default();
+            good_neighbors.insert(neighbor);
+            rev_cmd.precondition = AtomicCondition::RoutesLessPreferred {
+                router,
+                prefix,
+                good_neighbors,
+                route: route.clone(),
+            };
+            // the router may select the old route from any of the good neighbors.
+            rev_cmd.postcondition = AtomicCondition::SelectedRoute {
+                router,
+                prefix,
+                neighbor: None,
+                weight: None,
+                next_hop: old_nh(info, router, prefix),
+            };
+        }
+    }
+    Ok(rev_stage)
+}
+
+/// Reverse the cleanup stage for the rollback plan. The weight of the new route is increased
+/// again, and the reverse commands wait until the router selects the new route with that weight.
+/// Routers without a new route ignore all routes again, and wait until they select no route.
+/// Temporary sessions are added again, and the reverse commands wait until they are established.
+fn reverse_cleanup_commands<P: Prefix, Q>(
+    stage: &Stage<P>,
+    info: &CommandInfo<'_, P, Q>,
+) -> Stage<P> {
+    let mut rev_stage = rollback::reverse_stage(stage);
+    for cmd in rev_stage.iter_mut().flatten() {
+        match cmd.command {
+            AtomicModifier::AddTempSession {
+                router, neighbor, ..
+            } => {
+                cmd.postcondition = AtomicCondition::BgpSessionEstablished { router, neighbor };
+            }
+            AtomicModifier::ChangePreference {
+                router,
+                prefix,
+                neighbor,
+                ..
+            } => {
+                cmd.postcondition = AtomicCondition::SelectedRoute {
+                    router,
+                    prefix,
+                    neighbor: Some(neighbor),
+                    weight: Some(NEW_ROUTE_WEIGHT),
+                    next_hop: new_nh(info, router, prefix),
+                };
+            }
+            AtomicModifier::IgnoreRoutes { router, prefix, .. } => {
+                cmd.postcondition = AtomicCondition::NoSelectedRoute { router, prefix };
+            }
+            _ => {}
+        }
+    }
+    rev_stage
+}
+
 /// Compute the set of all necessary static routes during the migration.
 ///
 /// The returned set of static routes are to be read as follows: This is a mapping from two routers
@@ -801,10 +1318,10 @@ fn cleanup_commands<Q>(
 /// p_b_a)` is mapped to two list of prefixes: the first one `p_a_b` describes the prefixes for
 /// which `a` eventually uses router `b` as a static route target, while `p_b_a` describes those
 /// for which router `b` will set-up a static route via `a`.
-fn get_temp_sessions<Q>(
-    info: &CommandInfo<'_, Q>,
+fn get_temp_sessions<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
     schedules: &HashMap<P, HashMap<RouterId, NodeSchedule>>,
-) -> Result<HashSet<(RouterId, RouterId)>, DecompositionError> {
+) -> Result<HashSet<(RouterId, RouterId)>, DecompositionError<P>> {
     /// get a key for session between a and b, by ordering a and b.
     fn key(a: RouterId, b: RouterId) -> (RouterId, RouterId) {
         if a < b {
@@ -857,7 +1374,7 @@ fn get_temp_sessions<Q>(
 }
 
 /// Batch together all similar route-map updates of all commands in the decomposition
-fn batch_route_map_updates(decomp: &mut Decomposition) {
+fn batch_route_map_updates<P: Prefix>(decomp: &mut Decomposition<P>) {
     batch_route_map_updates_of_stage(&mut decomp.setup_commands);
     batch_route_map_updates_of_stage(&mut decomp.main_commands);
     batch_route_map_updates_of_stage(&mut decomp.cleanup_commands);
@@ -869,11 +1386,24 @@ fn batch_route_map_updates(decomp: &mut Decomposition) {
         .atomic_after
         .values_mut()
         .for_each(batch_route_map_updates_of_stage);
+
+    let rollback = &mut decomp.rollback;
+    batch_route_map_updates_of_stage(&mut rollback.setup_commands);
+    batch_route_map_updates_of_stage(&mut rollback.main_commands);
+    batch_route_map_updates_of_stage(&mut rollback.cleanup_commands);
+    rollback
+        .atomic_before
+        .values_mut()
+        .for_each(batch_route_map_updates_of_stage);
+    rollback
+        .atomic_after
+        .values_mut()
+        .for_each(batch_route_map_updates_of_stage);
 }
 
 /// Batch together all similar route-map updates of all commands in the decomposition
 #[allow(clippy::ptr_arg)]
-fn batch_route_map_updates_of_stage(stage: &mut Vec<Vec<AtomicCommand<P>>>) {
+fn batch_route_map_updates_of_stage<P: Prefix>(stage: &mut Vec<Vec<AtomicCommand<P>>>) {
     for step in stage.iter_mut() {
         for cmd in step.iter_mut() {
             match &mut cmd.command {
@@ -883,7 +1413,8 @@ fn batch_route_map_updates_of_stage(stage: &mut Vec<Vec<AtomicCommand<P>>>) {
                 AtomicModifier::AddTempSession { raw, .. }
                 | AtomicModifier::RemoveTempSession { raw, .. }
                 | AtomicModifier::ChangePreference { raw, .. }
-                | AtomicModifier::ClearPreference { raw, .. } => {
+                | AtomicModifier::ClearPreference { raw, .. }
+                | AtomicModifier::IgnoreRoutes { raw, .. } => {
                     *raw = batch_route_map_updates_of_commands(raw.clone());
                 }
             }
@@ -891,8 +1422,11 @@ fn batch_route_map_updates_of_stage(stage: &mut Vec<Vec<AtomicCommand<P>>>) {
     }
 }
 
-/// Batch together all similar route-map updates of all commands in the decomposition
-fn batch_route_map_updates_of_commands(cmds: Vec<ConfigModifier<P>>) -> Vec<ConfigModifier<P>> {
+/// Batch together all similar route-map updates of the given commands. Existing batches are merged
+/// with the other updates on the same router.
+pub(super) fn batch_route_map_updates_of_commands<P: Prefix>(
+    cmds: Vec<ConfigModifier<P>>,
+) -> Vec<ConfigModifier<P>> {
     /// key for matching on modifying the same route-map
     type Key = (RouterId, RouteMapDirection, i16);
     let mut route_map_updates: HashMap<RouterId, HashMap<Key, RouteMapEdit<P>>> = HashMap::new();
@@ -974,6 +1508,20 @@ fn batch_route_map_updates_of_commands(cmds: Vec<ConfigModifier<P>>) -> Vec<Conf
                     panic!("Cannot modify the same route map twice")
                 }
             },
+            ConfigModifier::BatchRouteMapEdit { router, updates } => {
+                let router_updates = route_map_updates.entry(router).or_default();
+                for edit in updates {
+                    let order = edit.new.as_ref().or(edit.old.as_ref()).unwrap().order;
+                    match router_updates.entry((edit.neighbor, edit.direction, order)) {
+                        std::collections::hash_map::Entry::Vacant(e) => {
+                            e.insert(edit);
+                        }
+                        std::collections::hash_map::Entry::Occupied(_) => {
+                            panic!("Cannot modify the same route map twice")
+                        }
+                    }
+                }
+            }
             cmd => {
                 result.push(cmd);
             }
@@ -0,0 +1,462 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Device change scripts
+//!
+//! Offline export of a [`Decomposition`] as ordered CLI snippets for each stage, step, and router,
+//! generated with [`CiscoFrrCfgGen`]. Each snippet carries the pre- and postconditions of its
+//! atomic commands as `show` commands, such that an external automation can execute the plan with
+//! its own tooling (see [`DeviceScripts::write_to_dir`] for the layout of the exported directory).
+//!
+//! The scripts can be generated for all targets supported by [`CiscoFrrCfgGen`], i.e., Cisco Nexus
+//! 7000 (NX-OS), Cisco IOS, FRR, and Junos. The `show` commands request JSON output, except on
+//! Cisco IOS, which only prints text.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write as _,
+    net::Ipv4Addr,
+    path::Path,
+};
+
+use atomic_command::{AtomicCommand, AtomicCondition};
+use bgpsim::{
+    config::ConfigModifier,
+    export::{
+        cisco_frr_generators::{comment, Target},
+        Addressor, CiscoFrrCfgGen, ExportError, InternalCfgGen,
+    },
+    prelude::*,
+};
+use ipnet::Ipv4Net;
+use itertools::Itertools;
+
+use super::Decomposition;
+
+/// Number of networks to check for a condition on a prefix equivalence class. The networks are
+/// sampled uniformly, and always include the first and last network.
+const PEC_NUM_CHECK: usize = 10;
+
+/// Ordered change scripts of a migration plan.
+#[derive(Debug, Clone)]
+pub struct DeviceScripts<P: Prefix> {
+    /// All non-empty stages in the order in which they must be executed.
+    pub stages: Vec<StageScripts<P>>,
+}
+
+/// Change scripts of a single stage.
+#[derive(Debug, Clone)]
+pub struct StageScripts<P: Prefix> {
+    /// Position of the stage in the plan. Stages with the same index (the stages of different
+    /// prefixes before and after the main commands) can be executed in parallel.
+    pub index: usize,
+    /// Name of the stage (`setup`, `before`, `main`, `after`, or `cleanup`).
+    pub name: &'static str,
+    /// The prefix, if the stage is executed for a single prefix.
+    pub prefix: Option<P>,
+    /// The steps of the stage, each containing the script of all routers that are modified in that
+    /// step. All scripts of a step can be executed in parallel.
+    pub steps: Vec<Vec<RouterScript>>,
+}
+
+/// Change script of a single router in one step.
+#[derive(Debug, Clone)]
+pub struct RouterScript {
+    /// The router that is configured.
+    pub router: RouterId,
+    /// Name of the router
+    pub name: String,
+    /// Target for which the configuration is generated.
+    pub target: Target,
+    /// Checks that must hold before applying the configuration.
+    pub pre: Vec<ShowCheck>,
+    /// Configuration commands to apply.
+    pub config: String,
+    /// Checks that must hold after applying the configuration, before proceeding with the next
+    /// step.
+    pub post: Vec<ShowCheck>,
+}
+
+/// Condition that can be verified with a `show` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShowCheck {
+    /// The condition of the atomic command that is checked.
+    pub condition: String,
+    /// What the output of the command must show.
+    pub expect: String,
+    /// The `show` command to execute on the router.
+    pub command: String,
+}
+
+impl<P: Prefix> Decomposition<P> {
+    /// Generate the change scripts of each stage, step, and router, using the configuration
+    /// generator of each internal router in `gens`. The generators must be created for the initial
+    /// network `net`, as they keep track of the route-maps that are modified by the commands.
+    pub fn device_scripts<Q, A: Addressor<P>>(
+        &self,
+        net: &Network<P, Q>,
+        addressor: &mut A,
+        gens: &mut HashMap<RouterId, CiscoFrrCfgGen<P>>,
+    ) -> Result<DeviceScripts<P>, ExportError> {
+        let mut stages = Vec::new();
+        let mut push = |index, name, prefix, commands: &[Vec<AtomicCommand<P>>]| {
+            let steps = commands
+                .iter()
+                .map(|step| step_scripts(net, addressor, gens, step))
+                .filter_ok(|step| !step.is_empty())
+                .collect::<Result<Vec<_>, ExportError>>()?;
+            if !steps.is_empty() {
+                stages.push(StageScripts {
+                    index,
+                    name,
+                    prefix,
+                    steps,
+                });
+            }
+            Ok::<(), ExportError>(())
+        };
+
+        push(1, "setup", None, &self.setup_commands)?;
+        for (p, stage) in self.atomic_before.iter().sorted_by_key(|(p, _)| **p) {
+            push(2, "before", Some(*p), stage)?;
+        }
+        push(3, "main", None, &self.main_commands)?;
+        for (p, stage) in self.atomic_after.iter().sorted_by_key(|(p, _)| **p) {
+            push(4, "after", Some(*p), stage)?;
+        }
+        push(5, "cleanup", None, &self.cleanup_commands)?;
+
+        Ok(DeviceScripts { stages })
+    }
+}
+
+impl<P: Prefix> DeviceScripts<P> {
+    /// Write the scripts into the directory `path`, which is created if it does not exist. Each
+    /// step is stored in `<stage>/step-<n>/`, where `<stage>` is the index and name of the stage
+    /// (followed by the prefix, if any). A step contains up to three files for each router:
+    ///
+    /// - `<router>.pre`: `show` commands whose output must satisfy the preconditions,
+    /// - `<router>.cfg`: the configuration commands to apply, and
+    /// - `<router>.post`: `show` commands whose output must satisfy the postconditions.
+    ///
+    /// The expected outcome of each `show` command is written as a comment right above it. The
+    /// file `plan.txt` lists all steps and their routers in the order of execution.
+    pub fn write_to_dir(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let root = path.as_ref();
+        std::fs::create_dir_all(root)?;
+
+        let mut plan = String::from(
+            "# Execute the steps in order. For each router of a step, wait until all checks in\n\
+             # `<router>.pre` hold, apply `<router>.cfg`, and wait until all checks in\n\
+             # `<router>.post` hold. All routers of a step, and all stages with the same index, can\n\
+             # be executed in parallel.\n",
+        );
+
+        for stage in self.stages.iter() {
+            let stage_dir = match stage.prefix {
+                Some(p) => format!(
+                    "{}-{}-{}",
+                    stage.index,
+                    stage.name,
+                    file_name(&p.to_string())
+                ),
+                None => format!("{}-{}", stage.index, stage.name),
+            };
+            for (i, step) in stage.steps.iter().enumerate() {
+                let step_dir = format!("{stage_dir}/step-{:02}", i + 1);
+                let dir = root.join(&step_dir);
+                std::fs::create_dir_all(&dir)?;
+                for script in step {
+                    let name = file_name(&script.name);
+                    if !script.pre.is_empty() {
+                        std::fs::write(
+                            dir.join(format!("{name}.pre")),
+                            checks(script.target, &script.pre),
+                        )?;
+                    }
+                    if !script.config.is_empty() {
+                        std::fs::write(dir.join(format!("{name}.cfg")), &script.config)?;
+                    }
+                    if !script.post.is_empty() {
+                        std::fs::write(
+                            dir.join(format!("{name}.post")),
+                            checks(script.target, &script.post),
+                        )?;
+                    }
+                }
+                let routers = step.iter().map(|s| file_name(&s.name)).join(" ");
+                let _ = writeln!(plan, "{step_dir} {routers}");
+            }
+        }
+
+        std::fs::write(root.join("plan.txt"), plan)
+    }
+}
+
+/// Generate the scripts of all routers for a single step. Multiple commands affecting the same
+/// router are merged into a single script.
+fn step_scripts<P: Prefix, Q, A: Addressor<P>>(
+    net: &Network<P, Q>,
+    addressor: &mut A,
+    gens: &mut HashMap<RouterId, CiscoFrrCfgGen<P>>,
+    step: &[AtomicCommand<P>],
+) -> Result<Vec<RouterScript>, ExportError> {
+    let mut scripts: BTreeMap<RouterId, RouterScript> = BTreeMap::new();
+
+    for cmd in step {
+        for r in cmd.command.routers() {
+            if net.get_device(r).is_external() {
+                continue;
+            }
+            if !gens.contains_key(&r) {
+                return Err(ExportError::InternalCfgGenError(
+                    r,
+                    "No configuration generator given".to_string(),
+                ));
+            }
+            let script = script_of(&mut scripts, r, net, gens);
+            let gen = gens.get_mut(&r).unwrap();
+            script
+                .config
+                .push_str(&comment(gen.target(), &cmd.command.fmt(net)));
+            for c in Vec::<ConfigModifier<P>>::from(cmd.command.clone())
+                .into_iter()
+                .filter(|c| c.routers().contains(&r))
+            {
+                script
+                    .config
+                    .push_str(&gen.generate_command(net, addressor, c)?);
+            }
+        }
+
+        for (r, check) in show_checks(&cmd.precondition, net, addressor, gens)? {
+            script_of(&mut scripts, r, net, gens).pre.push(check);
+        }
+        for (r, check) in show_checks(&cmd.postcondition, net, addressor, gens)? {
+            script_of(&mut scripts, r, net, gens).post.push(check);
+        }
+    }
+
+    Ok(scripts.into_values().collect())
+}
+
+/// Get the script of the router, creating an empty one if it does not exist yet. The router must
+/// have a configuration generator.
+fn script_of<'a, P: Prefix, Q>(
+    scripts: &'a mut BTreeMap<RouterId, RouterScript>,
+    router: RouterId,
+    net: &Network<P, Q>,
+    gens: &HashMap<RouterId, CiscoFrrCfgGen<P>>,
+) -> &'a mut RouterScript {
+    scripts.entry(router).or_insert_with(|| RouterScript {
+        router,
+        name: router.fmt(net).to_string(),
+        target: gens[&router].target(),
+        pre: Vec::new(),
+        config: String::new(),
+        post: Vec::new(),
+    })
+}
+
+/// Translate an atomic condition into `show` commands on the routers that are checked. Conditions
+/// on routers without a configuration generator (i.e., external routers) are ignored.
+fn show_checks<P: Prefix, Q, A: Addressor<P>>(
+    cond: &AtomicCondition<P>,
+    net: &Network<P, Q>,
+    addressor: &mut A,
+    gens: &HashMap<RouterId, CiscoFrrCfgGen<P>>,
+) -> Result<Vec<(RouterId, ShowCheck)>, ExportError> {
+    let condition = cond.fmt(net);
+    let target = |r: &RouterId| gens.get(r).map(|g| g.target());
+
+    let mut checks = Vec::new();
+    match cond {
+        AtomicCondition::None => {}
+        AtomicCondition::SelectedRoute {
+            router,
+            prefix,
+            neighbor,
+            weight,
+            next_hop,
+        }
+        | AtomicCondition::AvailableRoute {
+            router,
+            prefix,
+            neighbor,
+            weight,
+            next_hop,
+        } => {
+            if let Some(target) = target(router) {
+                let kind = if matches!(cond, AtomicCondition::SelectedRoute { .. }) {
+                    "the best path"
+                } else {
+                    "a path"
+                };
+                let mut expect = format!("{kind} exists");
+                if let Some(n) = neighbor {
+                    let n = neighbor_address(*router, *n, net, addressor)?;
+                    let _ = write!(expect, ", learned from {n}");
+                }
+                if let Some(nh) = next_hop {
+                    let nh = neighbor_address(*router, *nh, net, addressor)?;
+                    let _ = write!(expect, ", with next-hop {nh}");
+                }
+                if let Some(w) = weight {
+                    let _ = write!(expect, ", with weight {w}");
+                }
+                for net in addressor.prefix(*prefix)?.sample_uniform_n(PEC_NUM_CHECK) {
+                    checks.push((
+                        *router,
+                        ShowCheck {
+                            condition: condition.clone(),
+                            expect: expect.clone(),
+                            command: show_bgp_route(target, net),
+                        },
+                    ));
+                }
+            }
+        }
+        AtomicCondition::NoSelectedRoute { router, prefix }
+        | AtomicCondition::NoReceivedRoute { router, prefix } => {
+            if let Some(target) = target(router) {
+                let expect = if matches!(cond, AtomicCondition::NoSelectedRoute { .. }) {
+                    "no best path exists"
+                } else {
+                    "no path exists"
+                };
+                for net in addressor.prefix(*prefix)?.sample_uniform_n(PEC_NUM_CHECK) {
+                    checks.push((
+                        *router,
+                        ShowCheck {
+                            condition: condition.clone(),
+                            expect: expect.to_string(),
+                            command: show_bgp_route(target, net),
+                        },
+                    ));
+                }
+            }
+        }
+        AtomicCondition::BgpSessionEstablished { router, neighbor } => {
+            for (a, b) in [(*router, *neighbor), (*neighbor, *router)] {
+                if let Some(target) = target(&a) {
+                    let b = neighbor_address(a, b, net, addressor)?;
+                    checks.push((
+                        a,
+                        ShowCheck {
+                            condition: condition.clone(),
+                            expect: "the session is in state Established".to_string(),
+                            command: show_bgp_neighbor(target, b),
+                        },
+                    ));
+                }
+            }
+        }
+        AtomicCondition::RoutesLessPreferred {
+            router,
+            prefix,
+            good_neighbors,
+            route,
+        } => {
+            if let Some(target) = target(router) {
+                let good: BTreeSet<Ipv4Addr> = good_neighbors
+                    .iter()
+                    .map(|n| neighbor_address(*router, *n, net, addressor))
+                    .collect::<Result<_, _>>()?;
+                let nh = neighbor_address(*router, route.route.next_hop, net, addressor)?;
+                let expect = format!(
+                    "all paths from {} have next-hop {nh}, and all other paths are less preferred \
+                     than weight {}, local-pref {}, AS path length {}, MED {}",
+                    good.iter().join(", "),
+                    route.weight,
+                    route.route.local_pref.unwrap_or(100),
+                    route.route.as_path.len(),
+                    route.route.med.unwrap_or(0),
+                );
+                for net in addressor.prefix(*prefix)?.sample_uniform_n(PEC_NUM_CHECK) {
+                    checks.push((
+                        *router,
+                        ShowCheck {
+                            condition: condition.clone(),
+                            expect: expect.clone(),
+                            command: show_bgp_route(target, net),
+                        },
+                    ));
+                }
+            }
+        }
+    }
+    Ok(checks)
+}
+
+/// Get the address by which `router` knows `neighbor`. This is the loopback address for internal
+/// routers, and the interface address for external routers.
+fn neighbor_address<P: Prefix, Q, A: Addressor<P>>(
+    router: RouterId,
+    neighbor: RouterId,
+    net: &Network<P, Q>,
+    addressor: &mut A,
+) -> Result<Ipv4Addr, ExportError> {
+    if net.get_device(neighbor).is_internal() {
+        addressor.router_address(neighbor)
+    } else {
+        addressor.iface_address(neighbor, router)
+    }
+}
+
+/// `show` command listing all BGP paths of a network in JSON format (text on Cisco IOS).
+fn show_bgp_route(target: Target, net: &Ipv4Net) -> String {
+    match target {
+        Target::CiscoNexus7000 => format!("show bgp ipv4 unicast {net} | json"),
+        Target::CiscoIos => format!("show bgp ipv4 unicast {net}"),
+        Target::Frr => format!("show bgp ipv4 unicast {net} json"),
+        Target::Junos => format!("show route {net} protocol bgp detail | display json"),
+    }
+}
+
+/// `show` command for the state of a BGP neighbor in JSON format (text on Cisco IOS).
+fn show_bgp_neighbor(target: Target, neighbor: Ipv4Addr) -> String {
+    match target {
+        Target::CiscoNexus7000 => format!("show bgp ipv4 unicast neighbors {neighbor} | json"),
+        Target::CiscoIos => format!("show bgp ipv4 unicast neighbors {neighbor}"),
+        Target::Frr => format!("show bgp neighbors {neighbor} json"),
+        Target::Junos => format!("show bgp neighbor {neighbor} | display json"),
+    }
+}
+
+/// Render a list of checks, with the condition and the expectation as comments.
+fn checks(target: Target, checks: &[ShowCheck]) -> String {
+    let mut out = String::new();
+    for check in checks {
+        out.push_str(&comment(target, &check.condition));
+        out.push_str(&comment(target, &format!("expect: {}", check.expect)));
+        let _ = writeln!(out, "{}", check.command);
+    }
+    out
+}
+
+/// Replace all characters that should not appear in a file name.
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -30,6 +30,28 @@
 //! reachability with sending to a terminal), or to be equal to the next hop. In case this router
 //! may change its forwarding decision, we add an if-then-else clause, which uses the old or the new
 //! next-hop.
+//!
+//! # Load Balancing
+//! If a router forwards traffic over multiple next-hops, then a property must hold on every path.
+//! Hence, the variable of a router is the conjunction of the variables of all its next-hops. As a
+//! consequence, a negated property is not the negation of the property on that router (some paths
+//! may satisfy the property, while others don't). Instead, we push the negation towards the path
+//! properties (e.g., [`Property::Reachability`] and [`Property::Waypoint`]), and evaluate the
+//! negated path properties recursively, just like the positive ones.
+//!
+//! # Path Properties with State
+//! Some path properties cannot be evaluated by requiring the same property on the next hop. For
+//! [`Property::Sequence`], the next hop must satisfy the remainder of the sequence once the first
+//! waypoint is reached, and for [`Property::MaxHops`], the next hop must satisfy the property with
+//! one hop less. We create variables for all those derived properties (see
+//! [`Property::get_subprops`]), and the property on a router is the conjunction of the *next
+//! property* (see `Property::next_prop`) on all of its next-hops.
+//!
+//! # Structure
+//! The condition of a property on a router only refers to other conditions in the same round, and
+//! to the variable telling whether the router has already changed. Hence, its structure (see
+//! [`CondStructure`]) does not depend on the number of steps. We compute it once for each prefix
+//! (see [`prop_structure`]), and instantiate it for every round (see [`prop_constraints`]).
 
 use std::{
     collections::{HashMap, HashSet},
@@ -37,42 +59,62 @@ use std::{
 };
 
 use bgpsim::{forwarding_state::ForwardingState, prelude::*};
-use good_lp::{constraint, variable, ProblemVariables, SolverModel, Variable};
+use boolinator::Boolinator;
+use good_lp::{constraint, variable, Expression, ProblemVariables, SolverModel, Variable};
 use itertools::{iproduct, Itertools};
 
 use crate::{
     decomposition::CommandInfo,
     specification::{Invariant, Property, SpecExpr},
-    P,
 };
 
-use super::{or_tools::*, IlpVars};
+use super::{or_tools::*, IlpStructure, IlpVars};
 
 /// Type to represent all variables needed to check for conditions.
 pub(super) type CondsType = HashMap<Property, HashMap<RouterId, Vec<Variable>>>;
 /// Type to represent variables for the specification (LTL).
 pub(super) type SpecExprType = HashMap<SpecExprExt, HashMap<usize, Variable>>;
+/// Type to represent the structure of all conditions, independent of the number of steps.
+pub(super) type CondStructureType = HashMap<Property, HashMap<RouterId, CondStructure>>;
+
+/// Structure of the condition of a property on a single router, which is independent of the round.
+/// Other conditions are referenced by their property and router, and always refer to the same
+/// round.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum CondStructure {
+    /// The condition is always satisfied (`true`) or always violated (`false`).
+    Const(bool),
+    /// The condition is equal to another condition.
+    Equal(Property, RouterId),
+    /// The condition is the conjunction of other conditions.
+    All(Vec<(Property, RouterId)>),
+    /// The condition is the disjunction of other conditions.
+    Any(Vec<(Property, RouterId)>),
+    /// The router changes its next-hops. Before the router has changed, the condition is the
+    /// conjunction of `old`, and afterwards, it is the conjunction of `new`. `None` means that the
+    /// condition is violated.
+    Update {
+        /// Conditions after the router has changed its next-hops.
+        new: Option<Vec<(Property, RouterId)>>,
+        /// Conditions before the router has changed its next-hops.
+        old: Option<Vec<(Property, RouterId)>>,
+    },
+}
 
 /// Create all variables needed for the specification, i.e., to satisfy the forwarding policies.
-pub(super) fn spec_variables<Q>(
+pub(super) fn spec_variables(
     p: &mut ProblemVariables,
-    info: &CommandInfo<'_, Q>,
-    all_nodes: &HashSet<RouterId>,
+    structure: &IlpStructure<'_>,
     max_steps: usize,
-    prefix: P,
 ) -> (CondsType, SpecExprType) {
-    let spec = info.spec.get(&prefix).cloned().unwrap_or(SpecExpr::True);
-    // get the set of all spec expressions.
-    let props: HashSet<_> = spec
-        .get_invariants()
-        .into_iter()
-        .flat_map(|i| i.prop.get_subprops())
-        .collect();
     // Create the boolean variables for every possible condition, router and step.
-    let prop_vars = props
-        .into_iter()
+    let prop_vars = structure
+        .conds
+        .keys()
+        .cloned()
         .zip(repeat_with(|| {
-            all_nodes
+            structure
+                .all_nodes
                 .iter()
                 .copied()
                 .zip(repeat_with(|| {
@@ -85,7 +127,7 @@ pub(super) fn spec_variables<Q>(
         .collect();
 
     let mut spec_vars = SpecExprType::new();
-    build_spec_vars(p, &mut spec_vars, spec, 0, max_steps);
+    build_spec_vars(p, &mut spec_vars, structure.spec.clone(), 0, max_steps);
     (prop_vars, spec_vars)
 }
 
@@ -166,90 +208,117 @@ fn build_spec_vars(
     }
 }
 
-/// Setup all constraints for all properties
-pub(super) fn prop_constraints<Q>(
-    problem: &mut impl SolverModel,
-    vars: &IlpVars,
-    info: &CommandInfo<'_, Q>,
+/// Compute the structure of the conditions of all properties on all routers. This only depends on
+/// the forwarding state before and after the update, but not on the number of steps.
+pub(super) fn prop_structure<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    spec: &SpecExpr,
     prefix: P,
-) {
-    for (prop, r, round) in iproduct!(vars.props(), info.routers(), vars.steps()) {
-        // depending on the condition, add the constraints.
-        let c = vars.get_c(prop, r, round);
-        match prop {
+) -> CondStructureType {
+    // get the set of all spec expressions.
+    let props: HashSet<_> = spec
+        .get_invariants()
+        .into_iter()
+        .flat_map(|i| i.prop.get_ilp_subprops())
+        .collect();
+
+    let mut structure = CondStructureType::new();
+    for (prop, r) in iproduct!(props, info.routers()) {
+        let cond = match &prop {
             // Special case for external routers
-            Property::Reachability | Property::Waypoint(_)
-                if info.net_before.get_device(r).is_external() =>
-            {
+            _ if prop.is_path_prop() && info.net_before.get_device(r).is_external() => {
                 match prop.local_sat(r, None, &info.fw_before, prefix) {
-                    Some(true) => problem.add_constraint(constraint!(c == 1)),
-                    Some(false) => problem.add_constraint(constraint!(c == 0)),
+                    Some(sat) => CondStructure::Const(sat),
                     None => unreachable!(),
-                };
+                }
             }
-            // Reachability and waypoint properties depend on the next-hop that they have.
-            Property::Reachability | Property::Waypoint(_) => {
+            // Reachability and waypoint properties (and their negation) depend on the next-hops
+            // that they have.
+            _ if prop.is_path_prop() => {
                 // TODO deal with black holes here!
                 //
-                // extract the next hop. Notice, that z is the state before the update (i.e., b = 0), and
-                // y is the state after the update (i.e., b = 1).
-                let nh_old = info.fw_before.get_next_hops(r, prefix).last().copied();
-                let nh_new = info.fw_after.get_next_hops(r, prefix).last().copied();
-
-                // check if the condition can be satisfied just by considering the next-hop.
-                let sat_old = prop.local_sat(r, nh_old, &info.fw_before, prefix);
-                let sat_new = prop.local_sat(r, nh_new, &info.fw_after, prefix);
+                // extract the next hops. Notice, that z is the state before the update (i.e., b =
+                // 0), and y is the state after the update (i.e., b = 1).
+                let nh_old = info.fw_before.get_next_hops(r, prefix);
+                let nh_new = info.fw_after.get_next_hops(r, prefix);
 
-                // prepare the c variable to be constrained in this round.
-                let c_nh_new = vars.get_c(prop, nh_new.unwrap(), round);
-                let c_nh_old = vars.get_c(prop, nh_old.unwrap(), round);
+                // check if the condition can be satisfied just by considering the next-hops.
+                let sat_old = prop.next_hops_sat(r, nh_old, &info.fw_before, prefix);
 
                 // check if there was a change
                 if nh_old == nh_new {
                     // no update happens. Set the cond variable either to true or false if the the
-                    // condition is already satisfied by the next-hop, or to the value of the
-                    // next-hop.
+                    // condition is already satisfied by the next-hops, or to the conjunction of
+                    // the next-hops.
                     match sat_old {
-                        Some(true) => problem.add_constraint(constraint!(c == 1)),
-                        Some(false) => problem.add_constraint(constraint!(c == 0)),
-                        None => problem.add_constraint(constraint!(c == c_nh_old)),
-                    };
+                        Some(ys) if ys.is_empty() => CondStructure::Const(true),
+                        Some(ys) => CondStructure::All(ys),
+                        None => CondStructure::Const(false),
+                    }
                 } else {
-                    // helper function to turn a boolean into a float.
-                    let b2f = |b| if b { 1.0 } else { 0.0 };
-                    // update happens. Assign `c` to the value of the new next-hop or old next-hop,
-                    // depending if the router has already changed its routing decision.
-                    let has_changed = vars.get_b(r, round);
-                    match (sat_old, sat_new) {
-                        (None, None) => {
-                            c_if_then_else(problem, has_changed, c, c_nh_new, c_nh_old);
-                        }
-                        (None, Some(nh_new_sat)) => {
-                            c_if_then_else(problem, has_changed, c, b2f(nh_new_sat), c_nh_old);
-                        }
-                        (Some(nh_old_sat), None) => {
-                            c_if_then_else(problem, has_changed, c, c_nh_new, b2f(nh_old_sat));
-                        }
-                        (Some(nh_old_sat), Some(nh_new_sat)) => {
-                            c_if_then_else_yz(problem, has_changed, c, nh_new_sat, nh_old_sat)
-                        }
+                    let sat_new = prop.next_hops_sat(r, nh_new, &info.fw_after, prefix);
+                    CondStructure::Update {
+                        new: sat_new,
+                        old: sat_old,
                     }
                 }
             }
-            Property::All(ps) => {
-                let ys = ps.iter().map(|p| vars.get_c(p, r, round)).collect_vec();
-                c_all(problem, c, ys);
-            }
-            Property::Any(ps) => {
-                let ys = ps.iter().map(|p| vars.get_c(p, r, round)).collect_vec();
-                c_any(problem, c, ys);
+            Property::All(ps) => CondStructure::All(ps.iter().map(|p| (p.clone(), r)).collect()),
+            Property::Any(ps) => CondStructure::Any(ps.iter().map(|p| (p.clone(), r)).collect()),
+            // `p` is not a path property (handled above). Push the negation towards the path
+            // properties.
+            Property::Not(p) => CondStructure::Equal(p.negate(), r),
+            Property::True => CondStructure::Const(true),
+            Property::Reachability
+            | Property::Waypoint(_)
+            | Property::Edge(_, _)
+            | Property::Sequence(_)
+            | Property::MaxHops(_)
+            | Property::Egress(_)
+            | Property::Avoid(_) => {
+                unreachable!("Path properties are handled above!")
             }
-            Property::Not(p) => {
-                let y = vars.get_c(p, r, round);
-                problem.add_constraint(constraint!(c == 1 - y));
-            }
-            Property::True => {
-                problem.add_constraint(constraint!(c == 1));
+        };
+        structure.entry(prop).or_default().insert(r, cond);
+    }
+    structure
+}
+
+/// Setup all constraints for all properties
+pub(super) fn prop_constraints(
+    problem: &mut impl SolverModel,
+    vars: &IlpVars,
+    structure: &IlpStructure<'_>,
+) {
+    for (prop, conds) in structure.conds.iter() {
+        for ((r, cond), round) in iproduct!(conds.iter(), vars.steps()) {
+            // depending on the condition, add the constraints.
+            let c = vars.get_c(prop, *r, round);
+            let get = |(p, r): &(Property, RouterId)| vars.get_c(p, *r, round);
+            match cond {
+                CondStructure::Const(true) => {
+                    problem.add_constraint(constraint!(c == 1));
+                }
+                CondStructure::Const(false) => {
+                    problem.add_constraint(constraint!(c == 0));
+                }
+                CondStructure::Equal(p, r) => {
+                    let y = vars.get_c(p, *r, round);
+                    problem.add_constraint(constraint!(c == y));
+                }
+                CondStructure::All(ys) => c_all(problem, c, ys.iter().map(get).collect()),
+                CondStructure::Any(ys) => c_any(problem, c, ys.iter().map(get).collect()),
+                CondStructure::Update { new, old } => {
+                    // helper function to turn the conjunction into a list of expressions.
+                    let conj = |x: &Option<Vec<(Property, RouterId)>>| match x {
+                        Some(ys) => ys.iter().map(get).map(Expression::from).collect(),
+                        None => vec![Expression::from(0)],
+                    };
+                    // update happens. Assign `c` to the value of the new next-hops or old
+                    // next-hops, depending if the router has already changed its routing decision.
+                    let has_changed = vars.get_b(*r, round);
+                    c_if_then_else_all(problem, has_changed, c, conj(new), conj(old));
+                }
             }
         }
     }
@@ -257,11 +326,10 @@ pub(super) fn prop_constraints<Q>(
 
 /// Create the constraints for all specifications. Further, assert that the root specificatoin is
 /// satisfied in round 0.
-pub(super) fn spec_constraints<Q>(
+pub(super) fn spec_constraints(
     problem: &mut impl SolverModel,
     vars: &IlpVars,
-    info: &CommandInfo<'_, Q>,
-    prefix: P,
+    structure: &IlpStructure<'_>,
 ) {
     // add the constraints to build all specificatoin entries
     let max_round = vars.max_steps;
@@ -327,11 +395,7 @@ pub(super) fn spec_constraints<Q>(
         }
 
         // finally, assert that the main expression is true
-        let root = info
-            .spec
-            .get(&prefix)
-            .map(|x| x.clone().into())
-            .unwrap_or(SpecExprExt::True);
+        let root: SpecExprExt = structure.spec.clone().into();
         if max_round > 0 {
             let root_s = vars.get_s(&root, 0);
             problem.add_constraint(constraint!(root_s == 1.0));
@@ -340,10 +404,109 @@ pub(super) fn spec_constraints<Q>(
 }
 
 impl Property {
+    /// Returns `true` if the property is a path property, i.e., [`Property::Reachability`],
+    /// [`Property::Waypoint`], [`Property::Edge`], [`Property::Sequence`], [`Property::MaxHops`],
+    /// [`Property::Egress`], [`Property::Avoid`], or the negation of either of them.
+    fn is_path_prop(&self) -> bool {
+        match self {
+            Property::Reachability
+            | Property::Waypoint(_)
+            | Property::Edge(_, _)
+            | Property::Sequence(_)
+            | Property::MaxHops(_)
+            | Property::Egress(_)
+            | Property::Avoid(_) => true,
+            Property::Not(p) => !matches!(**p, Property::Not(_)) && p.is_path_prop(),
+            _ => false,
+        }
+    }
+
+    /// Get the path property that must be satisfied by the next-hops of router `r`, such that
+    /// `self` is satisfied on `r`. This is only different from `self` for properties that carry
+    /// some state along the path.
+    fn next_prop(&self, r: RouterId) -> Property {
+        match self {
+            Property::Sequence(ws) if ws.first() == Some(&r) => {
+                Property::Sequence(ws[1..].to_vec())
+            }
+            Property::MaxHops(k) => Property::MaxHops(k.saturating_sub(1)),
+            Property::Not(p) => Property::Not(Box::new(p.next_prop(r))),
+            p => p.clone(),
+        }
+    }
+
+    /// Push the negation of `self` one level down, such that the resulting property is equivalent
+    /// to `!self` on every path. Path properties are simply negated.
+    fn negate(&self) -> Property {
+        match self {
+            Property::All(xs) => Property::Any(xs.iter().map(Property::negate).collect()),
+            Property::Any(xs) => Property::All(xs.iter().map(Property::negate).collect()),
+            Property::Not(x) => x.as_ref().clone(),
+            Property::True => Property::Any(Vec::new()),
+            p => Property::Not(Box::new(p.clone())),
+        }
+    }
+
+    /// Get the set of all properties for which we need to create variables, including `self`. In
+    /// addition to all sub-properties, this also contains all negations that are pushed down
+    /// towards the path properties.
+    fn get_ilp_subprops(&self) -> HashSet<Property> {
+        let mut props = self.get_subprops();
+        let mut todo: Vec<Property> = props.iter().cloned().collect();
+        while let Some(p) = todo.pop() {
+            if let Property::Not(x) = &p {
+                // for negated path properties, also negate the derived properties of the next-hops.
+                let subs = if p.is_path_prop() {
+                    x.get_subprops()
+                        .into_iter()
+                        .map(|sub| Property::Not(Box::new(sub)))
+                        .collect()
+                } else {
+                    x.negate().get_subprops()
+                };
+                for sub in subs {
+                    if props.insert(sub.clone()) {
+                        todo.push(sub);
+                    }
+                }
+            }
+        }
+        props
+    }
+
+    /// Evaluate the path property on a router that forwards traffic to all `next_hops`. The
+    /// property is satisfied if it is satisfied on each of them. This function returns `None` if
+    /// the property is violated, and otherwise, the list of conditions (property and router) that
+    /// must all be satisfied. If the list is empty, then the property is always satisfied.
+    fn next_hops_sat<P: Prefix>(
+        &self,
+        r: RouterId,
+        next_hops: &[RouterId],
+        fw: &ForwardingState<P>,
+        prefix: P,
+    ) -> Option<Vec<(Property, RouterId)>> {
+        if next_hops.is_empty() {
+            return self
+                .local_sat(r, None, fw, prefix)
+                .unwrap()
+                .as_some(Vec::new());
+        }
+        let next_prop = self.next_prop(r);
+        let mut ys = Vec::new();
+        for nh in next_hops.iter().copied() {
+            match self.local_sat(r, Some(nh), fw, prefix) {
+                Some(true) => {}
+                Some(false) => return None,
+                None => ys.push((next_prop.clone(), nh)),
+            }
+        }
+        Some(ys)
+    }
+
     /// Returns `Some(true)` or `Some(false)` if the router with the selected next hop already
     /// satisfies of violates the condition. If this cannot be determined only by considering this
     /// local view, return `None`.
-    fn local_sat(
+    fn local_sat<P: Prefix>(
         &self,
         r: RouterId,
         nh: Option<RouterId>,
@@ -370,7 +533,57 @@ impl Property {
                     }
                 }
             }
-            _ => unreachable!("local_set should only be called on Reachability or Waypoint!"),
+            Property::Edge(a, b) if *a == r => Some(nh == Some(*b)),
+            Property::Edge(_, _) => match nh {
+                Some(r) if fw.is_terminal(r, prefix) => Some(false),
+                Some(_) => None,
+                None => Some(false),
+            },
+            Property::Sequence(ws) => {
+                let rest = if ws.first() == Some(&r) {
+                    &ws[1..]
+                } else {
+                    &ws[..]
+                };
+                if rest.is_empty() {
+                    Some(true)
+                } else {
+                    match nh {
+                        Some(r) if fw.is_terminal(r, prefix) => Some(rest == [r]),
+                        Some(_) => None,
+                        None => Some(false),
+                    }
+                }
+            }
+            Property::MaxHops(k) => match nh {
+                Some(_) if *k == 0 => Some(false),
+                Some(r) if fw.is_terminal(r, prefix) => Some(true),
+                Some(_) => None,
+                None => Some(fw.is_terminal(r, prefix)),
+            },
+            Property::Egress(es) => match nh {
+                Some(r) if fw.is_terminal(r, prefix) => Some(es.contains(&r)),
+                Some(_) => None,
+                None => Some(fw.is_terminal(r, prefix) && es.contains(&r)),
+            },
+            Property::Avoid(x) if *x == r || nh == Some(*x) => Some(false),
+            Property::Avoid(_) => match nh {
+                Some(r) if fw.is_terminal(r, prefix) => Some(true),
+                Some(_) => None,
+                None => Some(true),
+            },
+            Property::Not(p) => {
+                // A black hole does not reach the destination, which satisfies all path properties
+                // except reachability and avoidance (for which the local result is exact). Hence,
+                // their negation is violated.
+                let black_hole = nh.is_none() && !fw.is_terminal(r, prefix);
+                if black_hole && !matches!(**p, Property::Reachability | Property::Avoid(_)) {
+                    Some(false)
+                } else {
+                    p.local_sat(r, nh, fw, prefix).map(|sat| !sat)
+                }
+            }
+            _ => unreachable!("local_set should only be called on path properties!"),
         }
     }
 }
@@ -78,6 +78,21 @@
 //! x - n - t >= z - b
 //! x - n - t <= z + b
 //! ```
+//!
+//! # Load Balancing
+//! If a router forwards traffic to multiple next-hops (either before or after the update), then
+//! `y` and `z` are sets of variables. In that case, we only bound `x` from below, and we require
+//! that the router cannot change at the same step as any router along any of its paths:
+//!
+//! ```text
+//! x >= n
+//! x >= y_i - (1 - b)   for all y_i
+//! x >= z_i - b         for all z_i
+//! n + y_i <= 1         for all y_i
+//! n + z_i <= 1         for all z_i
+//! ```
+//!
+//! If the next-hops do not change, then `x` is the disjunction of all `y_i`.
 
 use std::{
     collections::{HashMap, HashSet},
@@ -88,9 +103,7 @@ use bgpsim::types::RouterId;
 use good_lp::{constraint, variable, Expression, ProblemVariables, SolverModel, Variable};
 use itertools::Itertools;
 
-use crate::{decomposition::CommandInfo, P};
-
-use super::{or_tools::*, IlpVars};
+use super::{or_tools::*, IlpStructure, IlpVars};
 
 /// Type definition for `changed` and `changed_step` variables
 pub(super) type HasChangedType = HashMap<RouterId, Vec<Variable>>;
@@ -143,11 +156,7 @@ pub(super) fn has_changed_constraints(problem: &mut impl SolverModel, vars: &Ilp
     for (node, round) in vars.r.iter() {
         let bs = &vars.b[node];
 
-        for (b_prev, b_next) in bs[0..(bs.len() - 1)]
-            .iter()
-            .copied()
-            .zip(bs[1..].iter().copied())
-        {
+        for (b_prev, b_next) in bs.iter().copied().zip(bs.iter().skip(1).copied()) {
             problem.add_constraint(constraint!(b_prev <= b_next));
         }
 
@@ -158,11 +167,10 @@ pub(super) fn has_changed_constraints(problem: &mut impl SolverModel, vars: &Ilp
 
 /// Setup all constraints for the boolean variable encoding if a router has already changed its
 /// decision at this point.
-pub(super) fn has_changed_path_constraints<Q>(
+pub(super) fn has_changed_path_constraints(
     problem: &mut impl SolverModel,
     vars: &IlpVars,
-    info: &CommandInfo<'_, Q>,
-    prefix: P,
+    structure: &IlpStructure<'_>,
 ) {
     // create the changed_step variables. To do that, reate the constraints to make `n = 1` if
     // `round == step` and `n = 0` otherwise. This is done by subtracting: `n[i] = b[i] - b[i-1]`.
@@ -185,8 +193,13 @@ pub(super) fn has_changed_path_constraints<Q>(
     let ps = &vars.p;
 
     for (r, r_ps) in ps {
-        let nhz = info.fw_before.get_next_hops(*r, prefix).last().copied();
-        let nhy = info.fw_after.get_next_hops(*r, prefix).last().copied();
+        let (nhz, nhy) = &structure.next_hops[r];
+        if nhz.len() > 1 || nhy.len() > 1 {
+            has_changed_path_ecmp_constraints(problem, vars, *r, nhy, nhz);
+            continue;
+        }
+        let nhz = nhz.last().copied();
+        let nhy = nhy.last().copied();
         for (round, (p, t)) in r_ps.iter().enumerate() {
             match (nhy, nhz) {
                 (None, None) => {
@@ -223,3 +236,36 @@ pub(super) fn has_changed_path_constraints<Q>(
         }
     }
 }
+
+/// Setup the constraints for the `changed_step_path` variables of a router that load-balances its
+/// traffic over multiple next-hops, either before or after the update.
+fn has_changed_path_ecmp_constraints(
+    problem: &mut impl SolverModel,
+    vars: &IlpVars,
+    router: RouterId,
+    nhy: &[RouterId],
+    nhz: &[RouterId],
+) {
+    let ps = &vars.p;
+    for (round, (p, t)) in ps[&router].iter().enumerate() {
+        // the temporary variable is not needed.
+        problem.add_constraint(constraint!(*t == 0));
+        let ys = nhy.iter().map(|nh| ps[nh][round].0).collect_vec();
+        if nhy == nhz {
+            c_any(problem, *p, ys);
+            continue;
+        }
+        let zs = nhz.iter().map(|nh| ps[nh][round].0).collect_vec();
+        let n = vars.n[&router][round];
+        let b = vars.b[&router][round];
+        problem.add_constraint(constraint!(*p >= n));
+        for y in ys {
+            problem.add_constraint(constraint!(*p >= y - (1 - b)));
+            problem.add_constraint(constraint!(n + y <= 1));
+        }
+        for z in zs {
+            problem.add_constraint(constraint!(*p >= z - b));
+            problem.add_constraint(constraint!(n + z <= 1));
+        }
+    }
+}
@@ -0,0 +1,350 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Heuristic scheduler that finds a safe (but typically more expensive) schedule without solving
+//! an ILP.
+//!
+//! The heuristic works in two phases. First, it greedily orders all routers such that the
+//! specification is satisfied after each individual forwarding change. Routers that are closer to
+//! their egress in the final forwarding state are preferred (i.e., the routers are updated in
+//! reverse topological order of the final forwarding state), and the ordering respects the
+//! temporary sessions constraints (see [`super::temp_bgp_sessions_constraints`]). Each router
+//! changes its forwarding in its own round, except for the routers modified by the patch, which
+//! all change in the same round (see [`super::patch_constraints`]).
+//!
+//! Second, it computes the rounds `r_old` and `r_new` of each router as the largest (or smallest)
+//! values that satisfy the BGP propagation constraints (see [`super::bgp_cost`]). To that end, the
+//! forwarding changes are placed in the middle of a large number of rounds, such that the old
+//! route can disappear before, and the new route can appear after all forwarding changes. Any
+//! difference between the round of the forwarding change and `r_old` or `r_new` is covered by a
+//! temporary BGP session. Finally, all unused rounds are removed.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use bgpsim::prelude::*;
+use good_lp::ResolutionError;
+use itertools::Itertools;
+
+use super::{check_properties, FwStateTrace, GroupStructure, IlpStructure, NodeSchedule, Schedule};
+use crate::{
+    decomposition::{
+        bgp_dependencies::{BgpDependencies, BgpDependency},
+        CommandInfo,
+    },
+    specification::{Checker, Specification},
+};
+
+/// Find a safe schedule for a given prefix using the heuristic scheduler, and check the resulting
+/// schedule.
+pub fn schedule_heuristic<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    bgp_deps: &HashMap<P, BgpDependencies>,
+    prefix: P,
+) -> Result<(Schedule, FwStateTrace), ResolutionError> {
+    let structure = IlpStructure::new(info, bgp_deps.get(&prefix), prefix);
+    let (schedule, num_steps) = heuristic_schedule(info, &structure, prefix)?;
+    let fw_state_trace = check_properties(info, &schedule, num_steps, prefix)?;
+    Ok((schedule, fw_state_trace))
+}
+
+/// Compute the heuristic schedule for the given group of prefixes, and return the schedule of each
+/// member together with their number of steps. The heuristic cannot schedule prefixes that depend
+/// on each other, so the group must contain only a single prefix.
+pub(super) fn heuristic_schedule_group<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    group: &GroupStructure<'_>,
+    prefixes: &[P],
+) -> Result<(Vec<Schedule>, usize), ResolutionError> {
+    match (group.members.as_slice(), prefixes) {
+        ([structure], [prefix]) => {
+            let (schedule, num_steps) = heuristic_schedule(info, structure, *prefix)?;
+            Ok((vec![schedule], num_steps))
+        }
+        _ => Err(ResolutionError::Str(format!(
+            "Heuristic scheduler: cannot schedule {} together with the prefixes that depend on it",
+            prefixes[0]
+        ))),
+    }
+}
+
+/// Compute the heuristic schedule for the given prefix, and return the schedule together with its
+/// number of steps.
+pub(super) fn heuristic_schedule<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    structure: &IlpStructure<'_>,
+    prefix: P,
+) -> Result<(Schedule, usize), ResolutionError> {
+    if structure.nodes.is_empty() {
+        return Ok((Default::default(), 0));
+    }
+
+    let order = safe_order(info, structure, prefix)?;
+
+    // place the forwarding changes in the middle, leaving enough rounds before and after.
+    let n = order.len() as isize;
+    let num_rounds = 3 * n + 2;
+    let r: HashMap<RouterId, isize> = order
+        .iter()
+        .enumerate()
+        .flat_map(|(i, step)| step.iter().map(move |r| (*r, n + 1 + i as isize)))
+        .collect();
+
+    let r_old = old_rounds(structure, &r, num_rounds)?;
+    let r_new = new_rounds(structure, &r, num_rounds)?;
+
+    // the routers modified by the patch cannot use temporary sessions
+    for router in structure.patch_routers.iter() {
+        if r_old[router] != r[router] || r_new[router] != r[router] {
+            return Err(ResolutionError::Str(format!(
+                "Heuristic scheduler: {router:?} is modified by the patch, but requires a temporary session"
+            )));
+        }
+    }
+
+    // the routers without a route cannot use temporary sessions
+    for router in structure.fallback_routers.iter() {
+        if r_old[router] != r[router] || r_new[router] != r[router] {
+            return Err(ResolutionError::Str(format!(
+                "Heuristic scheduler: {router:?} knows no route, but requires a temporary session"
+            )));
+        }
+    }
+
+    // check the constraints for the temporary BGP sessions
+    for (router, border_router) in structure.temp_sessions_old.iter() {
+        if r[router] + 1 > r_old[border_router] {
+            return Err(ResolutionError::Str(format!(
+                "Heuristic scheduler: {router:?} cannot change before {border_router:?} loses the old route"
+            )));
+        }
+    }
+    for (router, border_router) in structure.temp_sessions_new.iter() {
+        if r[router] < r_new[border_router] + 1 {
+            return Err(ResolutionError::Str(format!(
+                "Heuristic scheduler: {router:?} cannot change after {border_router:?} learns the new route"
+            )));
+        }
+    }
+
+    // remove all unused rounds. This preserves all (strict) inequalities between rounds.
+    let rounds: Vec<isize> = r
+        .values()
+        .chain(r_old.values())
+        .chain(r_new.values())
+        .copied()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let rank = |x: isize| rounds.binary_search(&x).unwrap();
+
+    let schedule = order
+        .iter()
+        .flatten()
+        .map(|router| {
+            (
+                *router,
+                NodeSchedule {
+                    fw_state: rank(r[router]),
+                    old_route: rank(r_old[router]),
+                    new_route: rank(r_new[router]),
+                },
+            )
+        })
+        .collect();
+
+    Ok((schedule, rounds.len()))
+}
+
+/// Greedily order all routers, such that the specification is satisfied after each step. Each step
+/// consists of a single router, or of all routers modified by the patch.
+fn safe_order<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    structure: &IlpStructure<'_>,
+    prefix: P,
+) -> Result<Vec<Vec<RouterId>>, ResolutionError> {
+    let spec: Specification<P> = info
+        .spec
+        .get(&prefix)
+        .map(|expr| (prefix, expr.clone()))
+        .into_iter()
+        .collect();
+    let mut checker = Checker::new(&spec);
+    let mut fw_state = info.fw_before.clone();
+    if !checker.step(&mut fw_state) {
+        return Err(ResolutionError::Str(format!(
+            "Heuristic scheduler: the initial state violates the specification for {prefix}"
+        )));
+    }
+
+    // routers that must be scheduled before some other router.
+    let mut before: HashMap<RouterId, HashSet<RouterId>> = HashMap::new();
+    for (router, border_router) in structure.temp_sessions_old.iter() {
+        before.entry(*border_router).or_default().insert(*router);
+    }
+    for (router, border_router) in structure.temp_sessions_new.iter() {
+        before.entry(*router).or_default().insert(*border_router);
+    }
+
+    // distance to the egress in the final forwarding state.
+    let mut fw_after = info.fw_after.clone();
+    let dist: HashMap<RouterId, usize> = structure
+        .nodes
+        .iter()
+        .map(|r| {
+            let d = fw_after
+                .get_paths(*r, prefix)
+                .ok()
+                .and_then(|paths| paths.iter().map(Vec::len).min())
+                .unwrap_or(usize::MAX);
+            (*r, d)
+        })
+        .collect();
+
+    let mut remaining: BTreeSet<RouterId> = structure.nodes.iter().copied().collect();
+    let mut order = Vec::with_capacity(remaining.len());
+
+    while !remaining.is_empty() {
+        let candidates = remaining
+            .iter()
+            .map(|r| {
+                if structure.patch_routers.contains(r) {
+                    structure.patch_routers.clone()
+                } else {
+                    vec![*r]
+                }
+            })
+            .unique()
+            .filter(|step| {
+                step.iter().all(|r| {
+                    before
+                        .get(r)
+                        .map(|b| b.iter().all(|x| !remaining.contains(x)))
+                        .unwrap_or(true)
+                })
+            })
+            .sorted_by_key(|step| step.iter().map(|r| (dist[r], *r)).min());
+
+        let mut next = None;
+        for step in candidates {
+            let mut c = checker.clone();
+            let mut fw = fw_state.clone();
+            for router in step.iter() {
+                let next_hops = info.fw_after.get_next_hops(*router, prefix);
+                if next_hops != info.fw_before.get_next_hops(*router, prefix) {
+                    fw.update(*router, prefix, next_hops.to_vec());
+                }
+            }
+            if c.step(&mut fw) {
+                checker = c;
+                fw_state = fw;
+                next = Some(step);
+                break;
+            }
+        }
+
+        match next {
+            Some(step) => {
+                for router in step.iter() {
+                    remaining.remove(router);
+                }
+                order.push(step);
+            }
+            None => {
+                return Err(ResolutionError::Str(format!(
+                    "Heuristic scheduler: cannot find a safe order for {prefix}"
+                )))
+            }
+        }
+    }
+
+    Ok(order)
+}
+
+/// Compute the largest round `r_old` for each router, such that `r_old <= r`, and such that each
+/// router loses the old route before all routers from which it learns the old route.
+fn old_rounds(
+    structure: &IlpStructure<'_>,
+    r: &HashMap<RouterId, isize>,
+    num_rounds: isize,
+) -> Result<HashMap<RouterId, isize>, ResolutionError> {
+    let mut r_old: HashMap<RouterId, isize> = r
+        .iter()
+        .map(|(k, v)| (*k, (*v).min(num_rounds - 1)))
+        .collect();
+    loop {
+        let mut changed = false;
+        for (router, BgpDependency { old_from, .. }) in structure.bgp_deps.into_iter().flatten() {
+            if old_from.is_empty() || !r_old.contains_key(router) {
+                continue;
+            }
+            let bound = old_from
+                .iter()
+                .map(|x| r_old.get(x).copied().unwrap_or(num_rounds))
+                .max()
+                .unwrap()
+                - 1;
+            if r_old[router] > bound {
+                if bound < 0 {
+                    return Err(ResolutionError::Str(format!(
+                        "Heuristic scheduler: {router:?} cannot keep the old route long enough"
+                    )));
+                }
+                r_old.insert(*router, bound);
+                changed = true;
+            }
+        }
+        if !changed {
+            return Ok(r_old);
+        }
+    }
+}
+
+/// Compute the smallest round `r_new` for each router, such that `r_new >= r`, and such that each
+/// router learns the new route after any router from which it learns the new route.
+fn new_rounds(
+    structure: &IlpStructure<'_>,
+    r: &HashMap<RouterId, isize>,
+    num_rounds: isize,
+) -> Result<HashMap<RouterId, isize>, ResolutionError> {
+    let mut r_new: HashMap<RouterId, isize> = r.iter().map(|(k, v)| (*k, (*v).max(0))).collect();
+    loop {
+        let mut changed = false;
+        for (router, BgpDependency { new_from, .. }) in structure.bgp_deps.into_iter().flatten() {
+            if new_from.is_empty() || !r_new.contains_key(router) {
+                continue;
+            }
+            let bound = new_from
+                .iter()
+                .map(|x| r_new.get(x).copied().unwrap_or(0))
+                .min()
+                .unwrap()
+                + 1;
+            if r_new[router] < bound {
+                if bound >= num_rounds {
+                    return Err(ResolutionError::Str(format!(
+                        "Heuristic scheduler: {router:?} cannot learn the new route early enough"
+                    )));
+                }
+                r_new.insert(*router, bound);
+                changed = true;
+            }
+        }
+        if !changed {
+            return Ok(r_new);
+        }
+    }
+}
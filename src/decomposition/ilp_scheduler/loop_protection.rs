@@ -28,16 +28,16 @@ use std::{collections::HashSet, iter::zip};
 
 use bgpsim::prelude::*;
 use good_lp::{constraint, Expression, ProblemVariables, SolverModel};
+use itertools::Itertools;
 
-use crate::{
-    decomposition::{all_loops::all_loops, CommandInfo},
-    P,
-};
+use crate::decomposition::{all_loops::all_loops, CommandInfo};
 
-use super::IlpVars;
+use super::{IlpStructure, IlpVars};
 
 /// Type used for loop protection
 pub(super) type LoopProtectionType = ();
+/// Type used to store all loops, independent of the number of steps.
+pub(super) type LoopsType = Vec<Vec<(RouterId, bool)>>;
 
 /// Create all variables needed for the loop protection (i.e., none)
 pub(super) fn loop_protection_variables(
@@ -47,29 +47,49 @@ pub(super) fn loop_protection_variables(
 ) -> LoopProtectionType {
 }
 
-/// Setup the loop protection thing by computing all possible loops.
-pub(super) fn loop_protection_constraints<Q>(
+/// Compute all possible loops. For each loop, this function returns the routers along the loop that
+/// change their next-hop, together with a boolean that is `true` if the loop is formed after the
+/// router has changed its next-hop, and `false` if it is formed before.
+pub(super) fn loop_structure<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    nodes: &HashSet<RouterId>,
+    prefix: P,
+) -> LoopsType {
+    all_loops(info, prefix)
+        .into_iter()
+        .map(|cycle| {
+            let mut cycle_shift = cycle.clone();
+            cycle_shift.rotate_left(1);
+            // edges that exist both before and after the update (with load balancing) are always
+            // present.
+            let cycle_state: Vec<_> = zip(cycle, cycle_shift)
+                .filter(|(a, _)| nodes.contains(a))
+                .map(|(a, b)| {
+                    (
+                        a,
+                        info.fw_before.get_next_hops(a, prefix).contains(&b),
+                        info.fw_after.get_next_hops(a, prefix).contains(&b),
+                    )
+                })
+                .filter(|(_, old, new)| !(*old && *new))
+                .map(|(a, _, new)| (a, new))
+                .collect();
+            assert!(!cycle_state.is_empty());
+            cycle_state
+        })
+        .sorted()
+        .collect()
+}
+
+/// Setup the loop protection thing for all possible loops.
+pub(super) fn loop_protection_constraints(
     problem: &mut impl SolverModel,
     vars: &IlpVars,
-    info: &CommandInfo<'_, Q>,
-    prefix: P,
+    structure: &IlpStructure<'_>,
 ) {
     #[allow(clippy::let_unit_value)]
     let _ = vars.loop_protection;
-    // compute all loops
-    for cycle in all_loops(info, prefix) {
-        let mut cycle_shift = cycle.clone();
-        cycle_shift.rotate_left(1);
-        let cycle_state: Vec<_> = zip(cycle.into_iter(), cycle_shift.into_iter())
-            .filter(|(a, _)| vars.r.contains_key(a))
-            .map(|(a, b)| {
-                (
-                    a,
-                    info.fw_after.get_next_hops(a, prefix).first() == Some(&b),
-                )
-            })
-            .collect();
-        assert!(cycle_state.len() >= 2);
+    for cycle_state in structure.loops.iter() {
         for step in 0..(vars.max_steps) {
             let sum = cycle_state
                 .iter()
@@ -123,10 +143,10 @@ pub(super) fn loop_protection_variables(
 }
 
 /// Setup the loop protection thing.
-pub(super) fn loop_protection_constraints<Q>(
+pub(super) fn loop_protection_constraints<P: Prefix, Q>(
     problem: &mut impl SolverModel,
     vars: &IlpVars,
-    info: &CommandInfo<'_, Q>,
+    info: &CommandInfo<'_, P, Q>,
     prefix: Prefix,
 ) {
     let vs = &vars.loop_protection;
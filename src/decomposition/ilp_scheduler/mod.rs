@@ -19,39 +19,50 @@
 //! soft dependencies.
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     iter::repeat_with,
+    marker::PhantomData,
     ops::Range,
     time::{Duration, Instant},
 };
 
-use bgpsim::{forwarding_state::ForwardingState, prelude::*};
+use bgpsim::{bgp::BgpState, forwarding_state::ForwardingState, prelude::*};
 use good_lp::{
-    constraint,
-    solvers::coin_cbc::{coin_cbc as create_solver, CoinCbcProblem},
-    variable, ProblemVariables, ResolutionError, Solution, SolverModel, Variable,
+    constraint, variable, Expression, ProblemVariables, ResolutionError, Solution, SolverModel,
+    Variable,
 };
 use itertools::Itertools;
 use log::info;
 
-use super::{bgp_dependencies::BgpDependencies, CommandInfo};
-use crate::{
-    specification::{Checker, Property},
-    P,
-};
+use super::{bgp_dependencies::BgpDependencies, CommandInfo, Fallback};
+use crate::specification::{Checker, Property, SpecExpr};
 
 mod bgp_cost;
 mod conditions;
 mod has_changed;
+mod heuristic;
 #[cfg(feature = "explicit-loop-checker")]
 mod loop_protection;
 mod or_tools;
+mod parallel;
+mod solver;
+mod warm_start;
 
 use bgp_cost::*;
 use conditions::*;
 use has_changed::*;
 #[cfg(feature = "explicit-loop-checker")]
 use loop_protection::*;
+use solver::CountingModel;
+use warm_start::*;
+
+pub use heuristic::schedule_heuristic;
+pub use parallel::{schedule_all, schedule_all_with, ScheduleOptions, SchedulerKind};
+#[cfg(feature = "cbc")]
+pub use solver::Cbc;
+#[cfg(feature = "microlp")]
+pub use solver::MicroLp;
+pub use solver::{DefaultBackend, SolverBackend};
 
 /// The schedule of an individual node, storing when it will change its forwarding, up to when it
 /// will know the old route, and from when it will know the new route.
@@ -107,8 +118,8 @@ pub type Schedule = HashMap<RouterId, NodeSchedule>;
 pub type FwStateTrace = Vec<HashSet<(RouterId, Vec<RouterId>)>>;
 
 /// Find the optimal schedule for a given prefix. We are using the maximal number of steps here.
-pub fn schedule<Q>(
-    info: &CommandInfo<'_, Q>,
+pub fn schedule<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
     bgp_deps: &HashMap<P, BgpDependencies>,
     prefix: P,
 ) -> Result<(Schedule, FwStateTrace), ResolutionError> {
@@ -141,11 +152,39 @@ impl std::fmt::Display for ProblemSize {
     }
 }
 
+/// Strategy to explore the number of steps in [`schedule_smart_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum StepSearch {
+    /// Increase the number of steps one by one, until an acceptable solution is found.
+    #[default]
+    Linear,
+    /// Double the number of steps until an acceptable solution is found, and then perform a binary
+    /// search between the largest number of steps without, and the smallest number of steps with
+    /// an acceptable solution. This requires solving fewer models if the schedule requires many
+    /// steps.
+    BinarySearch,
+}
+
+impl StepSearch {
+    /// Get the next number of steps to explore, given that there exists no acceptable solution with
+    /// `lower` steps, and that there exists an acceptable solution with `upper` steps (if
+    /// known). Returns `None` if the search is done.
+    fn next(&self, lower: usize, upper: Option<usize>, max_steps: usize) -> Option<usize> {
+        match (self, upper) {
+            (_, Some(upper)) if upper <= lower + 1 => None,
+            (_, None) if lower >= max_steps => None,
+            (StepSearch::Linear, _) => Some(lower + 1),
+            (StepSearch::BinarySearch, None) => Some((2 * lower).clamp(1, max_steps)),
+            (StepSearch::BinarySearch, Some(upper)) => Some(lower + (upper - lower) / 2),
+        }
+    }
+}
+
 /// Find the optimal schedule for a given prefix in a smart way. We increase the number of steps
 /// until either we use less than the allowed number of temporary sessions, or we exceed the time
-/// budget.
-pub fn schedule_smart<Q>(
-    info: &CommandInfo<'_, Q>,
+/// budget. This is equivalent to [`schedule_smart_with`] using [`StepSearch::Linear`].
+pub fn schedule_smart<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
     bgp_deps: &HashMap<P, BgpDependencies>,
     prefix: P,
     time_budget: Duration,
@@ -154,57 +193,46 @@ pub fn schedule_smart<Q>(
     Result<(Schedule, FwStateTrace), ResolutionError>,
     ProblemSize,
 ) {
-    let max_steps: usize = info.fw_diff.get(&prefix).map(|x| x.len()).unwrap_or(0);
-    if max_steps == 0 {
-        return schedule_with_max_steps(info, bgp_deps, prefix, max_steps, None);
-    }
-
-    let mut largest_size = Default::default();
-    let start_time = Instant::now();
-    let deadline = start_time + time_budget;
-
-    for num_steps in 1..=max_steps {
-        let remaining_budget = deadline.duration_since(Instant::now());
-        log::info!("Solving model with {num_steps}/{max_steps} steps");
-        let (result, size) =
-            schedule_with_max_steps(info, bgp_deps, prefix, num_steps, Some(remaining_budget));
-        match result {
-            Ok(x) => {
-                log::info!("Found a solution!");
-                // compute the cost
-                let cost: usize = x.0.values().map(NodeSchedule::cost).sum();
-                if cost <= allowed_temp_sessions {
-                    // Found an acceptable solution!
-                    log::info!(
-                        "Found a solution with {num_steps} steps and {cost} temporary sessions after {}s",
-                        start_time.elapsed().as_secs_f64()
-                    );
-                    return (Ok(x), size);
-                }
-            }
-            Err(_) if Instant::now() >= deadline => {
-                // we reached our deadline! return the last solution
-                return (
-                    Err(ResolutionError::Str(format!(
-                        "Time budget is not large enough! Explored {}/{max_steps} steps",
-                        num_steps - 1
-                    ))),
-                    size,
-                );
-            }
-            Err(_) => {
-                // could not find a solution yet. Simply retry.
-                log::info!("No solutoin yet! try with more steps.");
-            }
-        }
-        largest_size = size;
-    }
-    (Err(ResolutionError::Infeasible), largest_size)
+    schedule_smart_with(
+        info,
+        bgp_deps,
+        prefix,
+        time_budget,
+        allowed_temp_sessions,
+        StepSearch::Linear,
+    )
+}
+
+/// Find the schedule with the smallest number of steps that uses at most the allowed number of
+/// temporary sessions, exploring the number of steps according to `search`. The structure of the
+/// model is computed only once, and each model is warm-started with the solution of the largest
+/// model with fewer steps. If the time budget is exceeded, this function returns the best
+/// acceptable solution found so far (if any).
+pub fn schedule_smart_with<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    bgp_deps: &HashMap<P, BgpDependencies>,
+    prefix: P,
+    time_budget: Duration,
+    allowed_temp_sessions: usize,
+    search: StepSearch,
+) -> (
+    Result<(Schedule, FwStateTrace), ResolutionError>,
+    ProblemSize,
+) {
+    let (mut scheduler, idx) = PrefixScheduler::<DefaultBackend>::new(info, bgp_deps, prefix);
+    let deadline = Instant::now() + time_budget;
+    let (result, size) = scheduler.search(deadline, allowed_temp_sessions, search);
+    let result = result.and_then(|(mut schedules, num_steps)| {
+        let schedule = schedules.swap_remove(idx);
+        let fw_state_trace = check_properties(info, &schedule, num_steps, prefix)?;
+        Ok((schedule, fw_state_trace))
+    });
+    (result, size)
 }
 
 /// Find the optimal schedule for a given prefix
-pub fn schedule_with_max_steps<Q>(
-    info: &CommandInfo<'_, Q>,
+pub fn schedule_with_max_steps<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
     bgp_deps: &HashMap<P, BgpDependencies>,
     prefix: P,
     num_steps: usize,
@@ -213,126 +241,440 @@ pub fn schedule_with_max_steps<Q>(
     Result<(Schedule, FwStateTrace), ResolutionError>,
     ProblemSize,
 ) {
-    // check if the update is empty
     info!("Prepare the ILP problem to schedule {}", prefix);
-    if bgp_deps.get(&prefix).map(|x| x.is_empty()).unwrap_or(true)
-        && info
-            .fw_diff
-            .get(&prefix)
-            .map(|x| x.is_empty())
-            .unwrap_or(true)
-    {
-        return (Ok(Default::default()), Default::default());
+    let (mut scheduler, idx) = PrefixScheduler::<DefaultBackend>::new(info, bgp_deps, prefix);
+    let (result, size) = scheduler.solve(num_steps, timeout);
+    let result = result.and_then(|mut schedules| {
+        let schedule = schedules.swap_remove(idx);
+        let fw_state_trace = check_properties(info, &schedule, num_steps, prefix)?;
+        Ok((schedule, fw_state_trace))
+    });
+    (result, size)
+}
+
+/// ILP scheduler for a single prefix (together with all prefixes that depend on it, see
+/// [`GroupStructure`]), that solves the model for different numbers of steps. The structure of the
+/// model is computed only once, and each model is warm-started with the feasible solution of the
+/// largest model with fewer steps. The scheduler does not depend on the prefix itself, such that it
+/// can be solved in a different thread, and such that its schedule can be reused for all prefixes
+/// with the same structure. The models are solved using the solver backend `S`.
+#[derive(Debug)]
+struct PrefixScheduler<'a, S> {
+    /// The step-independent structure of the model.
+    structure: GroupStructure<'a>,
+    /// Feasible solutions found so far (one for each member of the group), indexed by their number
+    /// of steps.
+    solutions: BTreeMap<usize, Vec<WarmStart>>,
+    /// The solver backend.
+    backend: PhantomData<fn() -> S>,
+}
+
+impl<'a, S: SolverBackend> PrefixScheduler<'a, S> {
+    /// Create a new scheduler for the group of the given prefix, and compute the structure of the
+    /// model. This function also returns the position of `prefix` within its group.
+    fn new<P: Prefix, Q>(
+        info: &CommandInfo<'_, P, Q>,
+        bgp_deps: &'a HashMap<P, BgpDependencies>,
+        prefix: P,
+    ) -> (Self, usize) {
+        let group = info
+            .prefix_groups()
+            .into_iter()
+            .find(|g| g.contains(&prefix))
+            .unwrap_or_else(|| vec![prefix]);
+        let idx = group.iter().position(|p| *p == prefix).unwrap_or_default();
+        let structure = GroupStructure::new(info, bgp_deps, &group);
+        (Self::from_structure(structure), idx)
+    }
+
+    /// Create a new scheduler from a structure that was computed before.
+    fn from_structure(structure: GroupStructure<'a>) -> Self {
+        Self {
+            structure,
+            solutions: BTreeMap::new(),
+            backend: PhantomData,
+        }
     }
 
-    // create the variables
-    let (problem, vars) = setup_vars(info, bgp_deps.get(&prefix), prefix, num_steps);
+    /// Find the schedule with the smallest number of steps that uses at most
+    /// `allowed_temp_sessions` temporary sessions, exploring the number of steps according to
+    /// `search` for each member of the group. If successful, this function returns the schedule of
+    /// each member and their number of steps.
+    #[allow(clippy::type_complexity)]
+    fn search(
+        &mut self,
+        deadline: Instant,
+        allowed_temp_sessions: usize,
+        search: StepSearch,
+    ) -> (Result<(Vec<Schedule>, usize), ResolutionError>, ProblemSize) {
+        let max_steps = self.structure.max_steps;
+        if max_steps == 0 {
+            let (result, size) = self.solve(max_steps, None);
+            return (result.map(|x| (x, max_steps)), size);
+        }
 
-    // create the coin_cbc problem
-    let mut problem = create_solver(problem.minimise(vars.cost));
+        let mut largest_size = ProblemSize::default();
+        let start_time = Instant::now();
 
-    // disable logging during tests
-    #[cfg(any(test, feature = "hide-cbc-output"))]
-    {
-        problem.set_parameter("logLevel", "0");
-    }
-
-    #[cfg(feature = "cbc-parallel")]
-    problem.set_parameter("threads", &format!("{}", num_cpus::get()));
-
-    if let Some(t) = timeout {
-        problem.set_parameter("seconds", &t.as_secs().to_string());
-    }
-
-    // create all constraints
-    setup_constraints(&mut problem, &vars, info, bgp_deps.get(&prefix), prefix);
-
-    let model = problem.as_inner();
-    let size = ProblemSize {
-        cols: model.num_cols() as usize,
-        rows: model.num_rows() as usize,
-        steps: num_steps,
-    };
-
-    // solve the problem
-    info!("Solving the ILP model...");
-    let solution = match problem.solve() {
-        Ok(s) => s,
-        Err(e) => return (Err(e), size),
-    };
-
-    // validate the solution
-    info!("Found a solution! Validating the solution...");
-    validate_solution(&vars, &solution);
-    let fw_state_trace = check_properties(info, &vars, &solution, prefix);
-
-    // build the schedule
-    let schedule = vars
-        .r
-        .keys()
-        .map(|r_id| {
-            (
-                *r_id,
-                NodeSchedule {
-                    fw_state: solution.value(vars.r[r_id]).round() as usize,
-                    old_route: solution.value(vars.r_old[r_id]).round() as usize,
-                    new_route: solution.value(vars.r_new[r_id]).round() as usize,
-                },
-            )
-        })
-        .collect();
+        // largest number of steps known to have no acceptable solution.
+        let mut lower: usize = 0;
+        // acceptable solution with the smallest number of steps.
+        let mut best: Option<(usize, Vec<Schedule>, ProblemSize)> = None;
 
-    (Ok((schedule, fw_state_trace)), size)
+        while let Some(num_steps) = search.next(lower, best.as_ref().map(|x| x.0), max_steps) {
+            let remaining_budget = deadline.duration_since(Instant::now());
+            log::info!("Solving model with {num_steps}/{max_steps} steps");
+            let (result, size) = self.solve(num_steps, Some(remaining_budget));
+            if size.steps > largest_size.steps {
+                largest_size = size;
+            }
+            match result {
+                Ok(x) => {
+                    log::info!("Found a solution!");
+                    // compute the cost of each member
+                    let cost: usize = x
+                        .iter()
+                        .map(|s| s.values().map(NodeSchedule::cost).sum())
+                        .max()
+                        .unwrap_or_default();
+                    if cost <= allowed_temp_sessions {
+                        // Found an acceptable solution!
+                        log::info!(
+                            "Found a solution with {num_steps} steps and {cost} temporary sessions after {}s",
+                            start_time.elapsed().as_secs_f64()
+                        );
+                        best = Some((num_steps, x, size));
+                    } else {
+                        lower = num_steps;
+                    }
+                }
+                Err(_) if Instant::now() >= deadline => {
+                    if let Some((num_steps, x, size)) = best {
+                        // we reached our deadline, but we already have an acceptable solution.
+                        log::info!("Time budget exceeded! Use the best solution found so far.");
+                        return (Ok((x, num_steps)), size);
+                    }
+                    // we reached our deadline! return the last solution
+                    return (
+                        Err(ResolutionError::Str(format!(
+                            "Time budget is not large enough! Explored {lower}/{max_steps} steps",
+                        ))),
+                        size,
+                    );
+                }
+                Err(_) => {
+                    // could not find a solution yet. Simply retry.
+                    log::info!("No solutoin yet! try with more steps.");
+                    lower = num_steps;
+                }
+            }
+        }
+
+        match best {
+            Some((num_steps, x, size)) => (Ok((x, num_steps)), size),
+            None => (Err(ResolutionError::Infeasible), largest_size),
+        }
+    }
+
+    /// Find the optimal schedule of each member of the group using `num_steps` steps.
+    fn solve(
+        &mut self,
+        num_steps: usize,
+        timeout: Option<Duration>,
+    ) -> (Result<Vec<Schedule>, ResolutionError>, ProblemSize) {
+        let members = &self.structure.members;
+
+        // check if the update is empty
+        if members.iter().all(|s| s.nodes.is_empty()) {
+            return (
+                Ok(vec![Default::default(); members.len()]),
+                Default::default(),
+            );
+        }
+
+        // create the variables
+        let mut problem = ProblemVariables::new();
+        let vars: Vec<IlpVars> = members
+            .iter()
+            .map(|s| setup_vars(&mut problem, s, num_steps))
+            .collect();
+        let cost: Expression = vars.iter().map(|v| v.cost).sum();
+
+        // create the solver-specific problem
+        let cols = problem.len();
+        let mut problem = CountingModel::new(S::create(problem.minimise(cost)));
+
+        if let Some(t) = timeout {
+            problem = problem.with_time_limit(t.as_secs_f64());
+        }
+
+        // create all constraints
+        for (v, s) in vars.iter().zip(members) {
+            setup_constraints(&mut problem, v, s);
+        }
+        group_constraints(&mut problem, &vars, &self.structure);
+
+        let size = ProblemSize {
+            cols,
+            rows: problem.num_rows(),
+            steps: num_steps,
+        };
+
+        // warm-start the model with the solution of the largest model with fewer steps.
+        if let Some((steps, warm_start)) = self.solutions.range(..num_steps).next_back() {
+            info!("Warm-start the ILP model with the solution using {steps} steps");
+            problem = problem.with_initial_solution(
+                warm_start
+                    .iter()
+                    .zip(vars.iter())
+                    .flat_map(|(w, v)| w.initial_solution(v)),
+            );
+        }
+
+        // solve the problem
+        info!("Solving the ILP model...");
+        let solution = match problem.solve() {
+            Ok(s) => s,
+            Err(e) => return (Err(e), size),
+        };
+
+        // validate the solution
+        info!("Found a solution! Validating the solution...");
+        vars.iter().for_each(|v| validate_solution(v, &solution));
+
+        // build the schedule of each member
+        let schedules: Vec<Schedule> = vars
+            .iter()
+            .map(|vars| {
+                vars.r
+                    .keys()
+                    .map(|r_id| {
+                        (
+                            *r_id,
+                            NodeSchedule {
+                                fw_state: solution.value(vars.r[r_id]).round() as usize,
+                                old_route: solution.value(vars.r_old[r_id]).round() as usize,
+                                new_route: solution.value(vars.r_new[r_id]).round() as usize,
+                            },
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // remember the solution to warm-start larger models.
+        self.solutions.insert(
+            num_steps,
+            vars.iter()
+                .zip(schedules.iter())
+                .map(|(v, s)| WarmStart::new(v, s, &solution))
+                .collect(),
+        );
+
+        (Ok(schedules), size)
+    }
 }
 
-/// Setup all variables needed for the ILP thing to work.
-fn setup_vars<Q>(
-    info: &CommandInfo<'_, Q>,
-    bgp_deps: Option<&BgpDependencies>,
-    prefix: P,
+/// Structure of the ILP model for a single prefix that does not depend on the number of steps. It
+/// is computed once for each prefix, and reused for building the models with different numbers of
+/// steps.
+#[derive(Debug, PartialEq, Eq)]
+struct IlpStructure<'a> {
+    /// Maximum number of steps, i.e., the number of routers that change their forwarding.
     max_steps: usize,
-) -> (ProblemVariables, IlpVars) {
-    // create the problem
-    let mut problem = ProblemVariables::new();
-    let p = &mut problem;
-
-    // get the set of all routers that will change eventually.
-    let nodes: HashSet<RouterId> = info
-        .fw_diff
-        .get(&prefix)
-        .iter()
-        .flat_map(|d| d.keys())
-        .chain(bgp_deps.into_iter().flat_map(|d| d.keys()))
-        .copied()
-        .collect();
+    /// BGP dependencies of the prefix.
+    bgp_deps: Option<&'a BgpDependencies>,
+    /// Set of all routers that will change eventually.
+    nodes: HashSet<RouterId>,
+    /// Set of all routers in the network.
+    all_nodes: HashSet<RouterId>,
+    /// Routers modified by the patch that will change eventually (sorted). As the main commands
+    /// are applied at once, all of them must change their forwarding in the same round, and
+    /// without any temporary session.
+    patch_routers: Vec<RouterId>,
+    /// Internal routers that will change eventually, and that know no route for the prefix before
+    /// or after the update (sorted). They forward the traffic using a less-specific prefix, and
+    /// they cannot use any temporary session.
+    fallback_routers: Vec<RouterId>,
+    /// The specification of the prefix.
+    spec: SpecExpr,
+    /// Structure of the conditions of all properties on all routers.
+    conds: CondStructureType,
+    /// Next-hops of each router before and after the update.
+    next_hops: HashMap<RouterId, (Vec<RouterId>, Vec<RouterId>)>,
+    /// Pairs of routers `(r, b)`, where `r` must change its forwarding before the border router `b`
+    /// no longer knows the old route.
+    temp_sessions_old: Vec<(RouterId, RouterId)>,
+    /// Pairs of routers `(r, b)`, where `r` must change its forwarding after the border router `b`
+    /// knows the new route.
+    temp_sessions_new: Vec<(RouterId, RouterId)>,
+    /// All possible forwarding loops.
+    #[cfg(feature = "explicit-loop-checker")]
+    loops: LoopsType,
+}
+
+impl<'a> IlpStructure<'a> {
+    /// Compute the structure of the model for the given prefix.
+    fn new<P: Prefix, Q>(
+        info: &CommandInfo<'_, P, Q>,
+        bgp_deps: Option<&'a BgpDependencies>,
+        prefix: P,
+    ) -> Self {
+        // get the set of all routers that will change eventually.
+        let nodes: HashSet<RouterId> = info
+            .fw_diff
+            .get(&prefix)
+            .iter()
+            .flat_map(|d| d.keys())
+            .chain(bgp_deps.into_iter().flat_map(|d| d.keys()))
+            .copied()
+            .collect();
+
+        let all_nodes: HashSet<RouterId> = info.net_before.get_topology().node_indices().collect();
+
+        let patch_routers: Vec<RouterId> = info
+            .command
+            .modifiers
+            .iter()
+            .flat_map(|c| c.routers())
+            .filter(|r| nodes.contains(r))
+            .sorted()
+            .dedup()
+            .collect();
+
+        let knows_route = |bgp: &HashMap<P, BgpState<P>>, r: RouterId| {
+            bgp.get(&prefix).and_then(|s| s.get(r)).is_some()
+        };
+        let fallback_routers: Vec<RouterId> = nodes
+            .iter()
+            .copied()
+            .filter(|r| info.net_before.get_device(*r).is_internal())
+            .filter(|r| !knows_route(&info.bgp_before, *r) || !knows_route(&info.bgp_after, *r))
+            .sorted()
+            .collect();
+
+        let spec = info.spec.get(&prefix).cloned().unwrap_or(SpecExpr::True);
+        let conds = prop_structure(info, &spec, prefix);
+
+        let next_hops = all_nodes
+            .iter()
+            .map(|r| {
+                (
+                    *r,
+                    (
+                        info.fw_before.get_next_hops(*r, prefix).to_vec(),
+                        info.fw_after.get_next_hops(*r, prefix).to_vec(),
+                    ),
+                )
+            })
+            .collect();
+
+        let (temp_sessions_old, temp_sessions_new) = temp_bgp_sessions(info, &nodes, prefix);
+
+        Self {
+            max_steps: info.fw_diff.get(&prefix).map(|x| x.len()).unwrap_or(0),
+            bgp_deps,
+            #[cfg(feature = "explicit-loop-checker")]
+            loops: loop_structure(info, &nodes, prefix),
+            nodes,
+            all_nodes,
+            patch_routers,
+            fallback_routers,
+            spec,
+            conds,
+            next_hops,
+            temp_sessions_old,
+            temp_sessions_new,
+        }
+    }
+}
 
-    let all_nodes: HashSet<RouterId> = info.net_before.get_topology().node_indices().collect();
+/// Structure of the joint model for a group of prefixes that must be scheduled together (see
+/// [`CommandInfo::prefix_groups`]). Each member has its own variables and constraints, but all
+/// members share the same rounds. Like [`IlpStructure`], it does not depend on the prefixes
+/// themselves.
+#[derive(Debug, PartialEq, Eq)]
+struct GroupStructure<'a> {
+    /// Structure of each member, ordered from the least-specific to the most-specific prefix.
+    members: Vec<IlpStructure<'a>>,
+    /// Tuples `(i, j, router, fallback)`, where member `i` forwards its traffic using member `j`
+    /// on `router` (see [`PrefixDependency`](super::PrefixDependency)).
+    links: Vec<(usize, usize, RouterId, Fallback)>,
+    /// Maximum number of steps, i.e., the number of forwarding changes of all members.
+    max_steps: usize,
+}
+
+impl<'a> GroupStructure<'a> {
+    /// Compute the structure of the model for the given group of prefixes.
+    fn new<P: Prefix, Q>(
+        info: &CommandInfo<'_, P, Q>,
+        bgp_deps: &'a HashMap<P, BgpDependencies>,
+        group: &[P],
+    ) -> Self {
+        let members: Vec<IlpStructure<'a>> = group
+            .iter()
+            .map(|p| IlpStructure::new(info, bgp_deps.get(p), *p))
+            .collect();
+        let pos = |p: P| group.iter().position(|x| *x == p);
+        let links = info
+            .prefix_deps
+            .iter()
+            .filter_map(|dep| {
+                Some((
+                    pos(dep.prefix)?,
+                    pos(dep.covering)?,
+                    dep.router,
+                    dep.fallback,
+                ))
+            })
+            .filter(|(i, j, r, _)| {
+                members[*i].nodes.contains(r)
+                    && members[*j].nodes.contains(r)
+                    && !members[*i].patch_routers.contains(r)
+            })
+            .sorted()
+            .collect();
+        Self {
+            max_steps: members.iter().map(|s| s.max_steps).sum(),
+            members,
+            links,
+        }
+    }
+}
+
+/// Setup all variables needed for the ILP thing to work. The variables are added to `problem`.
+fn setup_vars(
+    problem: &mut ProblemVariables,
+    structure: &IlpStructure<'_>,
+    max_steps: usize,
+) -> IlpVars {
+    let p = problem;
+    let nodes = &structure.nodes;
+    let all_nodes = &structure.all_nodes;
 
     // count the number of variables, i.e., the maximum number of steps
     let max_f = max_steps as f64;
 
-    let (c, s) = spec_variables(p, info, &all_nodes, max_steps, prefix);
+    let (c, s) = spec_variables(p, structure, max_steps);
 
     // Create a variable that tracks the maximum round.
-    let vars = IlpVars {
+    IlpVars {
         max_steps,
         max_steps_v: p.add(variable().integer().min(0).max(max_f - 1.0)),
         cost: p.add(variable().integer().min(0)),
-        session_needed: session_needed_variables(p, &nodes),
-        r: round_variables(p, &nodes, max_f),
-        r_old: round_variables(p, &nodes, max_f),
-        r_new: round_variables(p, &nodes, max_f),
-        b: has_changed_variables(p, &nodes, max_steps),
-        n: has_changed_variables(p, &nodes, max_steps),
-        p: has_changed_path_variables(p, &all_nodes, max_steps),
+        session_needed: session_needed_variables(p, nodes),
+        r: round_variables(p, nodes, max_f),
+        r_old: round_variables(p, nodes, max_f),
+        r_new: round_variables(p, nodes, max_f),
+        b: has_changed_variables(p, nodes, max_steps),
+        n: has_changed_variables(p, nodes, max_steps),
+        p: has_changed_path_variables(p, all_nodes, max_steps),
         c,
         s,
-        min_max: min_max_variables(p, bgp_deps, max_steps),
+        min_max: min_max_variables(p, structure.bgp_deps, max_steps),
         #[cfg(feature = "explicit-loop-checker")]
-        loop_protection: loop_protection_variables(p, &all_nodes, max_steps),
-    };
-
-    (problem, vars)
+        loop_protection: loop_protection_variables(p, all_nodes, max_steps),
+    }
 }
 
 /// create the boolean variables to express wether a temporary bgp session is needed in either the
@@ -366,28 +708,26 @@ fn round_variables(
 }
 
 /// Setup all constraints needed for the problem.
-fn setup_constraints<Q>(
-    problem: &mut CoinCbcProblem,
+fn setup_constraints<M: SolverModel>(
+    problem: &mut CountingModel<M>,
     vars: &IlpVars,
-    info: &CommandInfo<'_, Q>,
-    bgp_deps: Option<&BgpDependencies>,
-    prefix: P,
+    structure: &IlpStructure<'_>,
 ) {
     // setup the cost constraint
-    let mut rows = problem.as_inner().num_rows();
+    let mut rows = problem.num_rows();
     log::debug!("{rows} equations before start");
 
     setup_cost_constraints(problem, vars);
 
-    let new_rows = problem.as_inner().num_rows();
+    let new_rows = problem.num_rows();
     let delta = new_rows - rows;
     rows = new_rows;
     log::debug!("{delta} equations for `setup_cost_constraints`");
 
     // setup the bgp propagation constraints
-    bgp_propagation_constraints(problem, vars, bgp_deps);
+    bgp_propagation_constraints(problem, vars, structure.bgp_deps);
 
-    let new_rows = problem.as_inner().num_rows();
+    let new_rows = problem.num_rows();
     let delta = new_rows - rows;
     rows = new_rows;
     log::debug!("{delta} equations for `bgp_propagation_constraints`");
@@ -395,7 +735,7 @@ fn setup_constraints<Q>(
     // create constraints for all minimum and maximum variables
     min_max_deps_constraints(problem, vars);
 
-    let new_rows = problem.as_inner().num_rows();
+    let new_rows = problem.num_rows();
     let delta = new_rows - rows;
     rows = new_rows;
     log::debug!("{delta} equations for `min_max_deps_constraints`");
@@ -403,31 +743,31 @@ fn setup_constraints<Q>(
     // create all conditions for `vars.changed`.
     has_changed_constraints(problem, vars);
 
-    let new_rows = problem.as_inner().num_rows();
+    let new_rows = problem.num_rows();
     let delta = new_rows - rows;
     rows = new_rows;
     log::debug!("{delta} equations for `has_changed_constraints`");
 
     // setup all conditions for `vars.changed_step` and `vars.changed_step_ptah`.
-    has_changed_path_constraints(problem, vars, info, prefix);
+    has_changed_path_constraints(problem, vars, structure);
 
-    let new_rows = problem.as_inner().num_rows();
+    let new_rows = problem.num_rows();
     let delta = new_rows - rows;
     rows = new_rows;
     log::debug!("{delta} equations for `has_changed_path_constraints`");
 
     // create all constraints for the forwarding policies and the conditions
-    prop_constraints(problem, vars, info, prefix);
+    prop_constraints(problem, vars, structure);
 
-    let new_rows = problem.as_inner().num_rows();
+    let new_rows = problem.num_rows();
     let delta = new_rows - rows;
     rows = new_rows;
     log::debug!("{delta} equations for `prop_constraints`");
 
     // create all constraints to satisfy all forwarding policies at every step.
-    spec_constraints(problem, vars, info, prefix);
+    spec_constraints(problem, vars, structure);
 
-    let new_rows = problem.as_inner().num_rows();
+    let new_rows = problem.num_rows();
     let delta = new_rows - rows;
     rows = new_rows;
     log::debug!("{delta} equations for `spec_constraints`");
@@ -435,19 +775,35 @@ fn setup_constraints<Q>(
     // create all constraints for loop protection
     #[cfg(feature = "explicit-loop-checker")]
     {
-        loop_protection_constraints(problem, vars, info, prefix);
-        let new_rows = problem.as_inner().num_rows();
+        loop_protection_constraints(problem, vars, structure);
+        let new_rows = problem.num_rows();
         let delta = new_rows - rows;
         rows = new_rows;
         log::debug!("{delta} equations for `loop_protection_constraints`");
     }
 
+    // create the constraints that all routers modified by the patch migrate in the same round.
+    patch_constraints(problem, vars, structure);
+
+    let new_rows = problem.num_rows();
+    let delta = new_rows - rows;
+    rows = new_rows;
+    log::debug!("{delta} equations for `patch_constraints`");
+
+    // create the constraints that all routers without a route cannot use temporary sessions.
+    fallback_constraints(problem, vars, structure);
+
+    let new_rows = problem.num_rows();
+    let delta = new_rows - rows;
+    rows = new_rows;
+    log::debug!("{delta} equations for `fallback_constraints`");
+
     // create the temporary BGP session constraints such that a router can only make a static route
     // (which means using the route from the temporary session) if the router on the border has
     // already chosen the old or new route.
-    temp_bgp_sessions_constraints(problem, vars, info, prefix);
+    temp_bgp_sessions_constraints(problem, vars, structure);
 
-    let new_rows = problem.as_inner().num_rows();
+    let new_rows = problem.num_rows();
     let delta = new_rows - rows;
     rows = new_rows;
     log::debug!("{delta} equations for `temp_bgp_session_constraints`");
@@ -472,6 +828,69 @@ fn setup_cost_constraints(problem: &mut impl SolverModel, vars: &IlpVars) {
     problem.add_constraint(constraint!(vars.cost == vars.max_steps_v + 2 * bgp_cost));
 }
 
+/// Require that all routers modified by the patch select the old route up to, and the new route
+/// from the round in which they change their forwarding, and that all of them change their
+/// forwarding in the same round. The main commands are applied at once in that round (see
+/// `compiler::get_cmd_round`).
+fn patch_constraints(problem: &mut impl SolverModel, vars: &IlpVars, structure: &IlpStructure<'_>) {
+    for router in structure.patch_routers.iter() {
+        let r = vars.r[router];
+        problem.add_constraint(constraint!(vars.r_old[router] == r));
+        problem.add_constraint(constraint!(vars.r_new[router] == r));
+    }
+    for (a, b) in structure.patch_routers.iter().tuple_windows() {
+        problem.add_constraint(constraint!(vars.r[a] == vars.r[b]));
+    }
+}
+
+/// Require that all routers that know no route for the prefix before or after the update select the
+/// old route up to, and the new route from the round in which they change their forwarding. The
+/// compiler changes their forwarding by ignoring (or accepting) all routes for the prefix at once.
+fn fallback_constraints(
+    problem: &mut impl SolverModel,
+    vars: &IlpVars,
+    structure: &IlpStructure<'_>,
+) {
+    for router in structure.fallback_routers.iter() {
+        let r = vars.r[router];
+        problem.add_constraint(constraint!(vars.r_old[router] == r));
+        problem.add_constraint(constraint!(vars.r_new[router] == r));
+    }
+}
+
+/// Create the constraints that link the members of a group:
+///
+/// - If a router falls back to the less-specific prefix only in the initial state, it must select
+///   its own route **before** the less-specific prefix changes its forwarding on that router.
+/// - If a router falls back to the less-specific prefix only in the final state, it must ignore its
+///   own route **after** the less-specific prefix has changed its forwarding on that router.
+/// - If a router falls back in both states, both prefixes change their forwarding in the same
+///   round.
+///
+/// Further, the main commands are applied only once for the entire group, so the routers modified
+/// by the patch of all members must change their forwarding in the same round.
+fn group_constraints(problem: &mut impl SolverModel, vars: &[IlpVars], group: &GroupStructure<'_>) {
+    for (i, j, router, fallback) in group.links.iter() {
+        let r_prefix = vars[*i].r[router];
+        let r_covering = vars[*j].r[router];
+        problem.add_constraint(match fallback {
+            Fallback::Initial => constraint!(r_prefix + 1 <= r_covering),
+            Fallback::Final => constraint!(r_prefix >= r_covering + 1),
+            Fallback::Both => constraint!(r_prefix == r_covering),
+        });
+    }
+
+    for (a, b) in group
+        .members
+        .iter()
+        .zip(vars)
+        .filter_map(|(s, v)| s.patch_routers.first().map(|r| v.r[r]))
+        .tuple_windows()
+    {
+        problem.add_constraint(constraint!(a == b));
+    }
+}
+
 /// Require the two following constraints for each router:
 ///
 /// - If the router in the initial state is not a border router, and if its initial egress router is
@@ -480,22 +899,45 @@ fn setup_cost_constraints(problem: &mut impl SolverModel, vars: &IlpVars) {
 /// - If the router in the final state is not a border router, and if its final egress router is not
 ///   an egress router in the initial state, make sure that the router must have changed its
 ///   forwarding **after** the egress router has changed its forwarding.
-fn temp_bgp_sessions_constraints<Q>(
+fn temp_bgp_sessions_constraints(
     problem: &mut impl SolverModel,
     vars: &IlpVars,
-    info: &CommandInfo<'_, Q>,
-    prefix: P,
+    structure: &IlpStructure<'_>,
 ) {
+    for (router, border_router) in structure.temp_sessions_old.iter() {
+        // add the condition that the router must chagne its forwarding (`r`) before the egress
+        // router has changed its routing decision (`r_old`).
+        let r = vars.r[router];
+        let r_old_egress = vars.r_old[border_router];
+        problem.add_constraint(constraint!(r + 1 <= r_old_egress));
+    }
+
+    for (router, border_router) in structure.temp_sessions_new.iter() {
+        // add the condition that the router must chagne its forwarding (`r`) after the egress
+        // router has changed its routing decision (`r_new`).
+        let r = vars.r[router];
+        let r_new_egress = vars.r_new[border_router];
+        problem.add_constraint(constraint!(r >= r_new_egress + 1));
+    }
+}
+
+/// Compute the pairs of routers for which [`temp_bgp_sessions_constraints`] adds the constraints.
+/// The first vector contains the constraints for the initial state, and the second one the
+/// constraints for the final state. Each pair contains the router and its border router.
+#[allow(clippy::type_complexity)]
+fn temp_bgp_sessions<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    nodes: &HashSet<RouterId>,
+    prefix: P,
+) -> (Vec<(RouterId, RouterId)>, Vec<(RouterId, RouterId)>) {
     /// If the router in the initial state is not a border router, and if its initial egress router
     /// is no longer an egress router in the final state, make sure that the router must change its
     /// forwarding **before** the egress router changes its forwarding.
-    fn handle_initial_state<Q>(
-        problem: &mut impl SolverModel,
-        vars: &IlpVars,
-        info: &CommandInfo<'_, Q>,
+    fn handle_initial_state<P: Prefix, Q>(
+        info: &CommandInfo<'_, P, Q>,
         prefix: P,
         router: RouterId,
-    ) -> Option<()> {
+    ) -> Option<(RouterId, RouterId)> {
         let net = info.net_before;
         let bgp_before = info.bgp_before.get(&prefix)?;
         let bgp_after = info.bgp_after.get(&prefix)?;
@@ -517,25 +959,17 @@ fn temp_bgp_sessions_constraints<Q>(
             return None;
         }
 
-        // add the condition that the router must chagne its forwarding (`r`) before the egress
-        // router has changed its routing decision (`r_old`).
-        let r = vars.r[&router];
-        let r_old_egress = vars.r_old[&border_router];
-        problem.add_constraint(constraint!(r + 1 <= r_old_egress));
-
-        Some(())
+        Some((router, border_router))
     }
 
     /// If the router in the final state is not a border router, and if its final egress router is
     /// not an egress router in the initial state, make sure that the router must have changed its
     /// forwarding **after** the egress router has changed its forwarding.
-    fn handle_final_state<Q>(
-        problem: &mut impl SolverModel,
-        vars: &IlpVars,
-        info: &CommandInfo<'_, Q>,
+    fn handle_final_state<P: Prefix, Q>(
+        info: &CommandInfo<'_, P, Q>,
         prefix: P,
         router: RouterId,
-    ) -> Option<()> {
+    ) -> Option<(RouterId, RouterId)> {
         let net = info.net_before;
         let bgp_before = info.bgp_before.get(&prefix)?;
         let bgp_after = info.bgp_after.get(&prefix)?;
@@ -556,19 +990,21 @@ fn temp_bgp_sessions_constraints<Q>(
             return None;
         }
 
-        // add the condition that the router must chagne its forwarding (`r`) after the egress
-        // router has changed its routing decision (`r_new`).
-        let r = vars.r[&router];
-        let r_new_egress = vars.r_new[&border_router];
-        problem.add_constraint(constraint!(r >= r_new_egress + 1));
-
-        Some(())
+        Some((router, border_router))
     }
 
-    for router in vars.r.keys().cloned() {
-        let _ = handle_initial_state(problem, vars, info, prefix, router);
-        let _ = handle_final_state(problem, vars, info, prefix, router);
-    }
+    (
+        nodes
+            .iter()
+            .sorted()
+            .filter_map(|r| handle_initial_state(info, prefix, *r))
+            .collect(),
+        nodes
+            .iter()
+            .sorted()
+            .filter_map(|r| handle_final_state(info, prefix, *r))
+            .collect(),
+    )
 }
 
 /// Validate that the solution makes any sense.
@@ -605,19 +1041,27 @@ fn validate_solution(vars: &IlpVars, solution: &impl Solution) {
 }
 
 /// Make sure that all properties are satisfied properly. This is done by building a forwarding
-/// state and checking the conditions.
-fn check_properties<Q>(
-    info: &CommandInfo<'_, Q>,
-    vars: &IlpVars,
-    solution: &impl Solution,
+/// state and checking the conditions. If the specification is violated, this function logs an
+/// error and returns `Err`.
+fn check_properties<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    schedule: &Schedule,
+    num_steps: usize,
     prefix: P,
-) -> FwStateTrace {
-    /// check the invariants for the given prefix. if an invariant is violated, log an error and panic.
-    fn check(checker: &mut Checker<'_>, fw: &mut ForwardingState<P>) {
+) -> Result<FwStateTrace, ResolutionError> {
+    /// check the invariants for the given prefix. if an invariant is violated, log an error.
+    fn check<P: Prefix>(
+        checker: &mut Checker<'_, P>,
+        fw: &mut ForwardingState<P>,
+    ) -> Result<(), ResolutionError> {
         if !checker.step(fw) {
             log::error!("Specification violated at step {}", checker.num_steps());
-            panic!("Specification violated at step {}", checker.num_steps());
+            return Err(ResolutionError::Str(format!(
+                "Specification violated at step {}",
+                checker.num_steps()
+            )));
         }
+        Ok(())
     }
 
     let mut plan: HashMap<usize, HashSet<(RouterId, Vec<RouterId>)>> = HashMap::new();
@@ -625,14 +1069,14 @@ fn check_properties<Q>(
     let mut checker = Checker::new(info.spec);
 
     // check the initial forwarding state.
-    check(&mut checker, &mut fw_state);
+    check(&mut checker, &mut fw_state)?;
 
     // check each forwarding state. During this time, also generate a nicely formatted logging
     // string that prints all steps and their forwarding delta.
-    for step in vars.steps() {
+    for step in 0..num_steps {
         // perform all fw deltas
-        for (router, r) in vars.r.iter() {
-            if step == solution.value(*r).round() as usize {
+        for (router, sched) in schedule.iter() {
+            if step == sched.fw_state {
                 let next_hops = info.fw_after.get_next_hops(*router, prefix).to_vec();
                 let prev_hops = info.fw_before.get_next_hops(*router, prefix);
                 if next_hops != prev_hops {
@@ -642,13 +1086,15 @@ fn check_properties<Q>(
             }
         }
         // check the conditions
-        check(&mut checker, &mut fw_state);
+        check(&mut checker, &mut fw_state)?;
     }
 
     // check the final state
     if !checker.check_prefix(prefix) {
         log::error!("Specification violated on complete trace");
-        panic!("Specification violated on complete trace");
+        return Err(ResolutionError::Str(
+            "Specification violated on complete trace".to_string(),
+        ));
     }
 
     // transfor te plan into an FwStateTrace
@@ -658,7 +1104,7 @@ fn check_properties<Q>(
         .map(|(_, x)| x)
         .collect();
 
-    plan
+    Ok(plan)
 }
 
 /// Structure for maintaining all variables of the ILP Scheduler
@@ -720,11 +1166,6 @@ impl IlpVars {
         self.n[&router][round]
     }
 
-    /// Get an iterator over all properties in the model.
-    fn props(&self) -> impl Iterator<Item = &Property> {
-        self.c.keys()
-    }
-
     /// Get the range of all steps in the model
     fn steps(&self) -> Range<usize> {
         0..self.max_steps
@@ -157,28 +157,37 @@ pub fn c_if_then_else(
     problem.add_constraint(constraint!(x() <= z() + b()));
 }
 
-/// Implement a simple if then else. This will implement the following: `x = y if b else z`. Here,
-/// both `y` and `z` are already known boolean values. This is implemented as follows:
+/// Implement an if then else of two conjunctions. This will implement the following: `x = all(ys)
+/// if b else all(zs)`, where the conjunction of an empty set is `true`. It is implemented as
+/// follows:
 ///
 /// ```text
-/// x = 1     if  y &&  z
-///     0     if !y && !z
-///     b     if  y && !z
-///     1 - b if !y &&  z
+/// x <= y_i + (1 - b)                     for all y_i
+/// x >= sum(y_i) - (|ys| - 1) - (1 - b)
+/// x <= z_i + b                           for all z_i
+/// x >= sum(z_i) - (|zs| - 1) - b
 /// ```
-pub fn c_if_then_else_yz(
+///
+/// Make sure that all variables are binary decision variables!
+pub fn c_if_then_else_all(
     problem: &mut impl SolverModel,
     b: Variable,
     x: Variable,
-    y: bool,
-    z: bool,
+    ys: Vec<Expression>,
+    zs: Vec<Expression>,
 ) {
-    problem.add_constraint(match (y, z) {
-        (true, true) => constraint!(x == 1),
-        (true, false) => constraint!(x == b),
-        (false, true) => constraint!(x == 1 - b),
-        (false, false) => constraint!(x == 0),
-    });
+    let n_ys = ys.len() as f64;
+    let n_zs = zs.len() as f64;
+    for y in ys.iter() {
+        problem.add_constraint(constraint!(x <= y.clone() + (1 - b)));
+    }
+    let sum_ys: Expression = ys.into_iter().sum();
+    problem.add_constraint(constraint!(x >= sum_ys - n_ys + 1.0 - (1 - b)));
+    for z in zs.iter() {
+        problem.add_constraint(constraint!(x <= z.clone() + b));
+    }
+    let sum_zs: Expression = zs.into_iter().sum();
+    problem.add_constraint(constraint!(x >= sum_zs - n_zs + 1.0 - b));
 }
 
 /// Implement a conjunction of all variables. This is done in the following way:
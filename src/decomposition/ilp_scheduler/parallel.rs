@@ -0,0 +1,234 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Module for scheduling all prefixes concurrently on a pool of worker threads.
+//!
+//! Prefixes whose forwarding states depend on each other (see
+//! [`CommandInfo::prefix_groups`]) are scheduled together in a single model. Many prefixes (or
+//! groups of prefixes) are typically affected in exactly the same way by a reconfiguration. Before
+//! scheduling, all groups are collected into classes whose ILP models are identical (i.e., they have
+//! the same forwarding difference, the same BGP dependencies, the same specification, and so on).
+//! Each class is solved only once, and its schedule is reused for all groups of that class.
+//!
+//! All classes are then solved concurrently. The time budget is global, and it is shared fairly
+//! among all classes: whenever a worker starts solving a class, it receives its share of the
+//! remaining time, considering the number of classes that are still waiting and the number of
+//! workers. If a class finishes early, the remaining classes can use the left-over time.
+//!
+//! Notice, that CBC is not thread-safe by default. With the feature `singlethread-cbc` (enabled by
+//! default), the calls into CBC are serialized, while the model construction still runs in
+//! parallel. Other solver backends (see [`SolverBackend`]) solve all models in parallel.
+
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use bgpsim::{event::EventQueue, types::Prefix};
+use good_lp::ResolutionError;
+use itertools::Itertools;
+use log::info;
+use rayon::prelude::*;
+
+use super::{
+    check_properties, heuristic::heuristic_schedule_group, DefaultBackend, FwStateTrace,
+    GroupStructure, PrefixScheduler, Schedule, SolverBackend, StepSearch,
+};
+use crate::decomposition::{bgp_dependencies::BgpDependencies, CommandInfo};
+
+/// Options for scheduling all prefixes of a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScheduleOptions {
+    /// Global time budget for scheduling all prefixes.
+    pub time_budget: Duration,
+    /// Maximum number of temporary BGP sessions for each prefix.
+    pub allowed_temp_sessions: usize,
+    /// How to explore the number of steps.
+    pub search: StepSearch,
+    /// Number of worker threads. If `None`, use one thread per CPU.
+    pub threads: Option<usize>,
+    /// Which scheduler to use.
+    pub scheduler: SchedulerKind,
+}
+
+/// Scheduler used to find the schedule of each prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SchedulerKind {
+    /// Only use the ILP scheduler. If it exceeds its time budget, scheduling fails.
+    Ilp,
+    /// Use the ILP scheduler, and fall back to the heuristic scheduler if the ILP scheduler
+    /// exceeds its time budget without finding an acceptable solution.
+    #[default]
+    IlpWithFallback,
+    /// Only use the heuristic scheduler (see [`super::schedule_heuristic`]). The resulting schedule
+    /// is safe, but it typically requires more steps and more temporary BGP sessions.
+    Heuristic,
+}
+
+impl Default for ScheduleOptions {
+    fn default() -> Self {
+        Self {
+            time_budget: Duration::from_secs(24 * 60 * 60),
+            allowed_temp_sessions: usize::MAX,
+            search: StepSearch::Linear,
+            threads: None,
+            scheduler: SchedulerKind::IlpWithFallback,
+        }
+    }
+}
+
+/// Find the schedule for all prefixes in `info`. Prefixes with identical ILP models are solved
+/// only once, and all distinct models are solved concurrently on a pool of worker threads. If any
+/// prefix cannot be scheduled, this function returns the error of that prefix.
+pub fn schedule_all<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    bgp_deps: &HashMap<P, BgpDependencies>,
+    options: ScheduleOptions,
+) -> Result<HashMap<P, (Schedule, FwStateTrace)>, ResolutionError>
+where
+    Q: EventQueue<P>,
+{
+    schedule_all_with::<DefaultBackend, P, Q>(info, bgp_deps, options)
+}
+
+/// Find the schedule for all prefixes in `info` like [`schedule_all`], using the solver backend
+/// `S`.
+pub fn schedule_all_with<S: SolverBackend, P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    bgp_deps: &HashMap<P, BgpDependencies>,
+    options: ScheduleOptions,
+) -> Result<HashMap<P, (Schedule, FwStateTrace)>, ResolutionError>
+where
+    Q: EventQueue<P>,
+{
+    let deadline = Instant::now() + options.time_budget;
+
+    // group all prefixes into classes with identical models.
+    let mut classes: Vec<(GroupStructure<'_>, Vec<Vec<P>>)> = Vec::new();
+    let mut buckets: HashMap<Vec<_>, Vec<usize>> = HashMap::new();
+    for group in info.prefix_groups() {
+        let structure = GroupStructure::new(info, bgp_deps, &group);
+        // use the forwarding difference as a hashable key to find candidate classes.
+        let key = group
+            .iter()
+            .map(|prefix| {
+                info.fw_diff
+                    .get(prefix)
+                    .into_iter()
+                    .flatten()
+                    .sorted_by_key(|(r, _)| **r)
+                    .collect_vec()
+            })
+            .collect_vec();
+        let bucket = buckets.entry(key).or_default();
+        match bucket.iter().find(|i| classes[**i].0 == structure) {
+            Some(i) => classes[*i].1.push(group),
+            None => {
+                bucket.push(classes.len());
+                classes.push((structure, vec![group]));
+            }
+        }
+    }
+    info!(
+        "Scheduling {} prefixes in {} classes",
+        info.prefixes.len(),
+        classes.len()
+    );
+
+    let (structures, members): (Vec<_>, Vec<_>) = classes.into_iter().unzip();
+
+    // Solve each class. Each result is paired with a flag that tells whether the time budget was
+    // exceeded.
+    let results: Vec<_> = if options.scheduler == SchedulerKind::Heuristic {
+        structures
+            .iter()
+            .zip(members.iter())
+            .map(|(structure, groups)| {
+                (heuristic_schedule_group(info, structure, &groups[0]), false)
+            })
+            .collect()
+    } else {
+        solve_concurrently::<S>(structures, deadline, options)?
+    };
+
+    // check the properties and reuse the schedule for all groups in the class.
+    let mut schedules = HashMap::new();
+    for ((result, timed_out), groups) in results.into_iter().zip(members) {
+        let (group_schedules, num_steps) = match result {
+            Err(e) if timed_out && options.scheduler == SchedulerKind::IlpWithFallback => {
+                log::warn!(
+                    "{e} Falling back to the heuristic scheduler for {}",
+                    groups[0][0]
+                );
+                let structure = GroupStructure::new(info, bgp_deps, &groups[0]);
+                heuristic_schedule_group(info, &structure, &groups[0])?
+            }
+            result => result?,
+        };
+        for group in groups {
+            for (prefix, schedule) in group.into_iter().zip(group_schedules.iter()) {
+                let fw_state_trace = check_properties(info, schedule, num_steps, prefix)?;
+                schedules.insert(prefix, (schedule.clone(), fw_state_trace));
+            }
+        }
+    }
+
+    Ok(schedules)
+}
+
+/// Solve all models concurrently using the ILP scheduler on a pool of worker threads, sharing the
+/// time budget fairly among all models. Each result is paired with a flag that tells whether the
+/// time budget of that model was exceeded.
+#[allow(clippy::type_complexity)]
+fn solve_concurrently<S: SolverBackend>(
+    structures: Vec<GroupStructure<'_>>,
+    deadline: Instant,
+    options: ScheduleOptions,
+) -> Result<Vec<(Result<(Vec<Schedule>, usize), ResolutionError>, bool)>, ResolutionError> {
+    let threads = options.threads.unwrap_or_else(num_cpus::get);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|e| ResolutionError::Str(e.to_string()))?;
+
+    let num_classes = structures.len();
+    let started = AtomicUsize::new(0);
+
+    Ok(pool.install(|| {
+        structures
+            .into_par_iter()
+            .map(|structure| {
+                // compute the fair share of the remaining time budget.
+                let waiting = num_classes - started.fetch_add(1, Ordering::SeqCst);
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                let share = if waiting > threads {
+                    remaining.mul_f64(threads as f64 / waiting as f64)
+                } else {
+                    remaining
+                };
+                let class_deadline = Instant::now() + share;
+                let (result, _) = PrefixScheduler::<S>::from_structure(structure).search(
+                    class_deadline,
+                    options.allowed_temp_sessions,
+                    options.search,
+                );
+                (result, Instant::now() >= class_deadline)
+            })
+            .collect()
+    }))
+}
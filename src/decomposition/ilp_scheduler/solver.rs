@@ -0,0 +1,151 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Module for abstracting over the ILP solver backend.
+//!
+//! The scheduler is generic over a [`SolverBackend`], which creates the solver-specific model from
+//! the problem. The available backends depend on the enabled cargo features:
+//!
+//! - `cbc` (default): [`Cbc`], which uses COIN-OR CBC. This requires a system installation of CBC.
+//! - `microlp`: [`MicroLp`], a pure-Rust solver that does not require any system library.
+//!
+//! The [`DefaultBackend`] is CBC if the feature `cbc` is enabled, and microlp otherwise.
+
+use good_lp::{
+    constraint::ConstraintReference, variable::UnsolvedProblem, Constraint, ResolutionError,
+    SolverModel, Variable, WithInitialSolution, WithTimeLimit,
+};
+
+#[cfg(not(any(feature = "cbc", feature = "microlp")))]
+compile_error!("At least one ILP solver backend must be enabled (feature `cbc` or `microlp`).");
+
+/// ILP solver backend used by the scheduler.
+pub trait SolverBackend {
+    /// The solver-specific model.
+    type Model: SolverModel<Error = ResolutionError> + WithInitialSolution + WithTimeLimit;
+
+    /// Create the solver-specific model for the given problem, and configure the solver (e.g., its
+    /// output or the number of threads).
+    fn create(problem: UnsolvedProblem) -> Self::Model;
+}
+
+/// Solver backend using COIN-OR CBC.
+#[cfg(feature = "cbc")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Cbc;
+
+#[cfg(feature = "cbc")]
+impl SolverBackend for Cbc {
+    type Model = good_lp::solvers::coin_cbc::CoinCbcProblem;
+
+    #[allow(unused_mut)]
+    fn create(problem: UnsolvedProblem) -> Self::Model {
+        let mut model = good_lp::solvers::coin_cbc::coin_cbc(problem);
+
+        // disable logging during tests
+        #[cfg(any(test, feature = "hide-cbc-output"))]
+        model.set_parameter("logLevel", "0");
+
+        #[cfg(feature = "cbc-parallel")]
+        model.set_parameter("threads", &format!("{}", num_cpus::get()));
+
+        model
+    }
+}
+
+/// Pure-Rust solver backend using microlp.
+#[cfg(feature = "microlp")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MicroLp;
+
+#[cfg(feature = "microlp")]
+impl SolverBackend for MicroLp {
+    type Model = good_lp::solvers::microlp::MicroLpProblem;
+
+    fn create(problem: UnsolvedProblem) -> Self::Model {
+        good_lp::solvers::microlp::microlp(problem)
+    }
+}
+
+/// The default solver backend.
+#[cfg(feature = "cbc")]
+pub type DefaultBackend = Cbc;
+
+/// The default solver backend.
+#[cfg(all(feature = "microlp", not(feature = "cbc")))]
+pub type DefaultBackend = MicroLp;
+
+/// Model that counts the number of constraints, independent of the solver backend.
+pub(super) struct CountingModel<M> {
+    /// The solver-specific model.
+    model: M,
+    /// Number of constraints added to the model.
+    rows: usize,
+}
+
+impl<M> CountingModel<M> {
+    /// Wrap a solver-specific model.
+    pub(super) fn new(model: M) -> Self {
+        Self { model, rows: 0 }
+    }
+
+    /// Get the number of constraints added so far.
+    pub(super) fn num_rows(&self) -> usize {
+        self.rows
+    }
+}
+
+impl<M: WithInitialSolution> CountingModel<M> {
+    /// Set the initial solution of the model.
+    pub(super) fn with_initial_solution(
+        self,
+        solution: impl IntoIterator<Item = (Variable, f64)>,
+    ) -> Self {
+        Self {
+            model: self.model.with_initial_solution(solution),
+            rows: self.rows,
+        }
+    }
+}
+
+impl<M: WithTimeLimit> CountingModel<M> {
+    /// Set the time limit of the solver.
+    pub(super) fn with_time_limit(self, seconds: f64) -> Self {
+        Self {
+            model: self.model.with_time_limit(seconds),
+            rows: self.rows,
+        }
+    }
+}
+
+impl<M: SolverModel> SolverModel for CountingModel<M> {
+    type Solution = M::Solution;
+    type Error = M::Error;
+
+    fn solve(self) -> Result<Self::Solution, Self::Error> {
+        self.model.solve()
+    }
+
+    fn add_constraint(&mut self, c: Constraint) -> ConstraintReference {
+        self.rows += 1;
+        self.model.add_constraint(c)
+    }
+
+    fn name() -> &'static str {
+        M::name()
+    }
+}
@@ -0,0 +1,194 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Module for warm-starting the ILP model with the solution of a model with fewer steps.
+//!
+//! Any feasible schedule with `k` steps is also feasible in a model with `k' > k` steps, in which
+//! nothing happens during the last `k' - k` steps. In those additional steps, every router has
+//! already changed (`b = 1`), no router changes (`n = 0`, and hence, `p = 0`), and every condition
+//! keeps its value from the last step of the smaller model. All other variables (the temporary
+//! sessions, minima and maxima, the cost, and the specification) are computed from the schedule and
+//! the conditions.
+
+use std::collections::HashMap;
+
+use bgpsim::types::RouterId;
+use good_lp::{Solution, Variable};
+use itertools::Itertools;
+
+use crate::specification::{Invariant, Property};
+
+use super::{bgp_cost::ConstraintType, conditions::SpecExprExt, IlpVars, Schedule};
+
+/// Values of a feasible solution, used to warm-start models with more steps.
+#[derive(Debug, Clone)]
+pub(super) struct WarmStart {
+    /// Number of steps of the model.
+    num_steps: usize,
+    /// The schedule, i.e., the values of `r`, `r_old` and `r_new`.
+    schedule: Schedule,
+    /// Values of the `changed_step_path` variables and their temporary variables.
+    p: HashMap<RouterId, Vec<(bool, bool)>>,
+    /// Values of the variables for the conditions.
+    c: HashMap<Property, HashMap<RouterId, Vec<bool>>>,
+}
+
+impl WarmStart {
+    /// Extract the warm start from a solution of the model with variables `vars`.
+    pub(super) fn new(vars: &IlpVars, schedule: &Schedule, solution: &impl Solution) -> Self {
+        let value = |v: &Variable| solution.value(*v) > 0.5;
+        Self {
+            num_steps: vars.max_steps,
+            schedule: schedule.clone(),
+            p: vars
+                .p
+                .iter()
+                .map(|(r, ps)| (*r, ps.iter().map(|(p, t)| (value(p), value(t))).collect()))
+                .collect(),
+            c: vars
+                .c
+                .iter()
+                .map(|(prop, cs)| {
+                    (
+                        prop.clone(),
+                        cs.iter()
+                            .map(|(r, xs)| (*r, xs.iter().map(value).collect()))
+                            .collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Compute the initial solution for the model with variables `vars`. The model must have at
+    /// least as many steps as the model from which the warm start was extracted.
+    pub(super) fn initial_solution(&self, vars: &IlpVars) -> Vec<(Variable, f64)> {
+        debug_assert!(vars.max_steps >= self.num_steps);
+        let f = |x: bool| if x { 1.0 } else { 0.0 };
+        let mut sol = Vec::new();
+
+        // schedule, temporary sessions, and the has-changed variables
+        for (router, s) in self.schedule.iter() {
+            sol.push((vars.r[router], s.fw_state as f64));
+            sol.push((vars.r_old[router], s.old_route as f64));
+            sol.push((vars.r_new[router], s.new_route as f64));
+            let (old, new) = vars.session_needed[router];
+            sol.push((old, f(s.old_route < s.fw_state)));
+            sol.push((new, f(s.fw_state < s.new_route)));
+            for round in vars.steps() {
+                sol.push((vars.get_b(*router, round), f(round >= s.fw_state)));
+                sol.push((vars.get_n(*router, round), f(round == s.fw_state)));
+            }
+        }
+
+        // cost
+        let steps = self
+            .schedule
+            .values()
+            .map(|s| s.fw_state)
+            .max()
+            .unwrap_or(0);
+        let bgp_cost: usize = self.schedule.values().map(|s| s.cost()).sum();
+        sol.push((vars.max_steps_v, steps as f64));
+        sol.push((vars.cost, (steps + 2 * bgp_cost) as f64));
+
+        // changed_step_path
+        for (router, ps) in vars.p.iter() {
+            for (round, (p, t)) in ps.iter().enumerate() {
+                let (p_val, t_val) = self.p[router].get(round).copied().unwrap_or_default();
+                sol.push((*p, f(p_val)));
+                sol.push((*t, f(t_val)));
+            }
+        }
+
+        // conditions
+        for (prop, cs) in vars.c.iter() {
+            for (router, xs) in cs.iter() {
+                for (round, c) in xs.iter().enumerate() {
+                    sol.push((*c, f(self.get_c(prop, *router, round))));
+                }
+            }
+        }
+
+        // minima and maxima of the BGP dependencies
+        for ((nodes, ty), m) in vars.min_max.iter() {
+            let values = nodes
+                .iter()
+                .map(|x| match (ty, self.schedule.get(x)) {
+                    (ConstraintType::OldFrom, Some(s)) => s.old_route,
+                    (ConstraintType::OldFrom, None) => vars.max_steps,
+                    (ConstraintType::NewFrom, Some(s)) => s.new_route,
+                    (ConstraintType::NewFrom, None) => 0,
+                })
+                .collect_vec();
+            let pos = match ty {
+                ConstraintType::OldFrom => values.iter().position_max(),
+                ConstraintType::NewFrom => values.iter().position_min(),
+            }
+            .unwrap_or_default();
+            sol.push((m.x, values.get(pos).copied().unwrap_or_default() as f64));
+            for (i, b) in m.b.iter().enumerate() {
+                sol.push((*b, f(i == pos)));
+            }
+        }
+
+        // specification
+        for (expr, xs) in vars.s.iter() {
+            for (round, s) in xs.iter() {
+                sol.push((*s, f(self.eval(expr, *round, vars.max_steps))));
+            }
+        }
+
+        sol
+    }
+
+    /// Get the value of the condition of `prop` on `router` in the given round. All rounds after
+    /// the last step have the same value as the last step.
+    fn get_c(&self, prop: &Property, router: RouterId, round: usize) -> bool {
+        self.c[prop][&router][round.min(self.num_steps - 1)]
+    }
+
+    /// Evaluate the specification expression in the given round, in the same way as it is encoded
+    /// in the model with `max_round` steps.
+    fn eval(&self, expr: &SpecExprExt, round: usize, max_round: usize) -> bool {
+        match expr {
+            SpecExprExt::True => true,
+            SpecExprExt::Not(x) => !self.eval(x, round, max_round),
+            SpecExprExt::All(xs) => xs.iter().all(|x| self.eval(x, round, max_round)),
+            SpecExprExt::Any(xs) => xs.iter().any(|x| self.eval(x, round, max_round)),
+            SpecExprExt::Next(x) => self.eval(x, (round + 1).min(max_round), max_round),
+            SpecExprExt::Finally(x) => (round..max_round).any(|k| self.eval(x, k, max_round)),
+            SpecExprExt::Globally(x) => (round..max_round).all(|k| self.eval(x, k, max_round)),
+            SpecExprExt::Until(a, b) => (round..max_round).any(|k| {
+                self.eval(
+                    &SpecExprExt::UntilFixed(a.clone(), b.clone(), k),
+                    round,
+                    max_round,
+                )
+            }),
+            SpecExprExt::UntilFixed(a, b, round_sat) => {
+                (round..*round_sat).all(|k| self.eval(a, k, max_round))
+                    && self.eval(b, *round_sat, max_round)
+            }
+            SpecExprExt::WeakUntil(a, b) => {
+                self.eval(&SpecExprExt::Until(a.clone(), b.clone()), round, max_round)
+                    || self.eval(&SpecExprExt::Globally(a.clone()), round, max_round)
+            }
+            SpecExprExt::Invariant(Invariant { router, prop }) => self.get_c(prop, *router, round),
+        }
+    }
+}
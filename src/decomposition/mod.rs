@@ -22,30 +22,40 @@ use std::collections::{HashMap, HashSet};
 
 use bgpsim::{
     bgp::BgpState,
-    config::{ConfigModifier, NetworkConfig},
+    config::{Config, ConfigExpr, ConfigExprKey, ConfigModifier, ConfigPatch, NetworkConfig},
     event::EventQueue,
     forwarding_state::ForwardingState,
     prelude::Network,
-    types::{NetworkError, RouterId},
+    route_map::{RouteMap, RouteMapDirection, RouteMapFlow, RouteMapMatch},
+    types::{NetworkError, Prefix, PrefixSet, RouterId},
 };
+use boolinator::Boolinator;
 use good_lp::ResolutionError;
+use ipnet::Ipv4Net;
+use itertools::{iproduct, Itertools};
 use log::info;
 use thiserror::Error;
 
 use crate::{
-    decomposition::ilp_scheduler::{FwStateTrace, NodeSchedule, Schedule},
+    decomposition::ilp_scheduler::{FwStateTrace, NodeSchedule, Schedule, ScheduleOptions},
     specification::Specification,
-    P,
 };
 
-use self::bgp_dependencies::BgpDependencies;
+use self::{bgp_dependencies::BgpDependencies, rollback::Rollback};
 
 #[cfg(feature = "explicit-loop-checker")]
 pub(self) mod all_loops;
 // pub mod atomic;
 pub mod bgp_dependencies;
+pub mod campaign;
 pub mod compiler;
+#[cfg(feature = "export-config")]
+#[cfg_attr(docsrs, doc(cfg(feature = "export-config")))]
+pub mod device_scripts;
 pub mod ilp_scheduler;
+pub mod report;
+pub mod rollback;
+pub mod visualizer;
 
 use atomic_command::{AtomicCommand, AtomicCondition, AtomicModifier};
 
@@ -53,9 +63,13 @@ use atomic_command::{AtomicCommand, AtomicCondition, AtomicModifier};
 /// apply those commands.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
-pub struct Decomposition {
-    /// Original command which has been decomposed
-    pub original_command: ConfigModifier<P>,
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "P: for<'a> serde::Deserialize<'a>"))
+)]
+pub struct Decomposition<P: Prefix> {
+    /// Original commands which have been decomposed, in the order in which they were given.
+    pub original_command: ConfigPatch<P>,
     /// BGP Dependencies for each prefix
     pub bgp_deps: HashMap<P, BgpDependencies>,
     /// The computed schedule for each router and each prefix.
@@ -70,76 +84,601 @@ pub struct Decomposition {
     pub cleanup_commands: Vec<Vec<AtomicCommand<P>>>,
     /// Atomic commands and their ordering, which need to be applied *before* the main command is
     /// applied. The outer vector represents the order in which to apply the commands, and the inner
-    /// vector stores several config modifiers that can be executed simultaneously.
+    /// vector stores several config modifiers that can be executed simultaneously. Prefixes that
+    /// are scheduled together are stored under the least-specific prefix of their group (see
+    /// [`CommandInfo::prefix_groups`]).
     pub atomic_before: HashMap<P, Vec<Vec<AtomicCommand<P>>>>,
     /// The main commands to apply. These typically only involve applying the original
-    /// command. However, this also involves adding a special tag to the specific session that is
-    /// traversed.
+    /// commands. However, this also involves adding a special tag to the specific session that is
+    /// traversed. If the patch both removes and creates sessions, then the main stage contains
+    /// multiple rounds (see [`compiler`]).
     pub main_commands: Vec<Vec<AtomicCommand<P>>>,
     /// Atomic commands and their ordering, which need to be applied *after* the main command is
     /// applied. The outer vector represents the order in which to apply the commands, and the inner
-    /// vector stores several config modifiers that can be executed simultaneously.
+    /// vector stores several config modifiers that can be executed simultaneously. Prefixes that
+    /// are scheduled together are stored under the least-specific prefix of their group (see
+    /// [`CommandInfo::prefix_groups`]).
     pub atomic_after: HashMap<P, Vec<Vec<AtomicCommand<P>>>>,
+    /// The rollback plan, which reverses all commands applied so far (see [`rollback`]).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rollback: Rollback<P>,
 }
 
-impl Decomposition {
-    /// Generate the baseline decomposition that only contains a single command without any
+impl<P: Prefix> Decomposition<P> {
+    /// Generate the baseline decomposition that applies all commands at once without any
     /// conditions.
-    pub fn baseline(command: ConfigModifier<P>) -> Self {
+    pub fn baseline(command: impl Into<ConfigPatch<P>>) -> Self {
+        let command = command.into();
+        let main_commands: Vec<Vec<AtomicCommand<P>>> = vec![command
+            .modifiers
+            .iter()
+            .map(|c| AtomicCommand {
+                command: AtomicModifier::Raw(c.clone()),
+                precondition: AtomicCondition::None,
+                postcondition: AtomicCondition::None,
+            })
+            .collect()];
+        let rollback = Rollback {
+            main_commands: rollback::reverse_stage(&main_commands),
+            ..Default::default()
+        };
         Self {
-            original_command: command.clone(),
+            original_command: command,
             bgp_deps: Default::default(),
             schedule: Default::default(),
             fw_state_trace: Default::default(),
             setup_commands: Default::default(),
             cleanup_commands: Default::default(),
             atomic_before: Default::default(),
-            main_commands: vec![vec![AtomicCommand {
-                command: AtomicModifier::Raw(command),
-                precondition: AtomicCondition::None,
-                postcondition: AtomicCondition::None,
-            }]],
+            main_commands,
             atomic_after: Default::default(),
+            rollback,
         }
     }
 }
 
 /// Decompose the command and return a [`Decomposition`].
-pub fn decompose<Q>(
+pub fn decompose<P: Prefix, Q>(
     net: &Network<P, Q>,
     command: ConfigModifier<P>,
-    spec: &Specification,
-) -> Result<Decomposition, DecompositionError>
+    spec: &Specification<P>,
+) -> Result<Decomposition<P>, DecompositionError<P>>
+where
+    Q: EventQueue<P> + Clone,
+{
+    decompose_patch(net, &ConfigPatch::from(command), spec)
+}
+
+/// Decompose an entire patch and return a single [`Decomposition`]. All prefixes are scheduled
+/// jointly on the combined state before and after the patch, and the main commands apply the
+/// entire patch at once. The patch is rejected if two of its commands modify the same
+/// configuration expression.
+pub fn decompose_patch<P: Prefix, Q>(
+    net: &Network<P, Q>,
+    patch: &ConfigPatch<P>,
+    spec: &Specification<P>,
+) -> Result<Decomposition<P>, DecompositionError<P>>
+where
+    Q: EventQueue<P> + Clone,
+{
+    decompose_patch_with(net, patch, spec, Default::default())
+}
+
+/// Decompose an entire patch like [`decompose_patch`], using the given options for scheduling the
+/// prefixes. All prefixes are scheduled concurrently, and prefixes with identical models are
+/// solved only once (see [`ilp_scheduler::schedule_all`]).
+pub fn decompose_patch_with<P: Prefix, Q>(
+    net: &Network<P, Q>,
+    patch: &ConfigPatch<P>,
+    spec: &Specification<P>,
+    options: ScheduleOptions,
+) -> Result<Decomposition<P>, DecompositionError<P>>
 where
     Q: EventQueue<P> + Clone,
 {
-    let info = CommandInfo::new(net, command, spec)?;
+    let info = CommandInfo::new(net, patch.clone(), spec)?;
     let bgp_deps = bgp_dependencies::find_dependencies(&info);
 
-    let schedules: HashMap<P, (Schedule, FwStateTrace)> = info
-        .prefixes
-        .iter()
-        .map(|p| Ok((*p, ilp_scheduler::schedule(&info, &bgp_deps, *p)?)))
-        .collect::<Result<HashMap<_, _>, DecompositionError>>()?;
+    let schedules: HashMap<P, (Schedule, FwStateTrace)> =
+        ilp_scheduler::schedule_all(&info, &bgp_deps, options)?;
 
     compiler::build(&info, bgp_deps, schedules)
 }
 
-/// A single forwarding delta, storing the old and the new next-hop
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Compute a new [`Decomposition`] that brings the network from its current state to the `target`
+/// configuration. This is used to re-plan the update if the network diverges from the expected
+/// trace while executing a decomposition (e.g., due to an unexpected link failure). The network
+/// must have converged, and `command` is the original command of the decomposition that was
+/// executed so far (see [`Decomposition::original_command`]).
+///
+/// The difference between the current configuration and the `target` (ignoring the link weights
+/// of links that no longer exist) consists of the part of `command` that was not yet applied, and
+/// of the temporary sessions and route-maps of the previous decomposition. Only the former is
+/// decomposed, starting from the current state of the network (ignoring the temporary sessions
+/// that do not change any selected route). The temporary configuration of the previous
+/// decomposition stays in place until the end, and it is removed in the last two rounds of the
+/// cleanup stage (first the route-maps, then the sessions). Only the route-maps that make a router
+/// use a temporary session are removed earlier, together with the first command that makes that
+/// router move away from the route (as they would override any route preference of the new
+/// decomposition). If the new decomposition inserts a
+/// temporary expression that is still present from the previous one, it takes over that
+/// expression (the insertion becomes an update, and rolling back restores the old expression).
+/// If there is no difference, the returned decomposition contains no commands. The function
+/// returns [`DecompositionError::UnreachableTarget`] if the commands of the new decomposition do
+/// not reach the `target`. In that case, the previous decomposition must be rolled back before
+/// re-planning.
+pub fn replan<P: Prefix, Q>(
+    net: &Network<P, Q>,
+    target: &Config<P>,
+    command: &ConfigPatch<P>,
+    spec: &Specification<P>,
+    options: ScheduleOptions,
+) -> Result<Decomposition<P>, DecompositionError<P>>
+where
+    Q: EventQueue<P> + Clone,
+{
+    let mut patch = net.get_config()?.get_diff(target);
+    // ignore the link weights of links that have failed in the meantime.
+    patch.modifiers.retain(|m| patch_keep(net, m));
+
+    // separate the remaining part of the command from the temporary configuration.
+    let command_keys: HashSet<ConfigExprKey<P>> =
+        command.modifiers.iter().filter_map(|m| m.key()).collect();
+    let (remaining, temporary): (Vec<_>, Vec<_>) = patch
+        .modifiers
+        .into_iter()
+        .partition(|m| m.key().map(|k| command_keys.contains(&k)) == Some(true));
+    if remaining.is_empty() {
+        return Ok(Decomposition::baseline(temporary));
+    }
+
+    // Plan on a model of the network without the temporary sessions of the previous decomposition
+    // that do not change any selected route (i.e., their route-maps still deny all routes).
+    let mut model = net.clone();
+    for m in temporary.iter() {
+        if let ConfigModifier::Remove(ConfigExpr::BgpSession { .. }) = m {
+            let mut candidate = model.clone();
+            candidate.apply_modifier(m)?;
+            if same_bgp_routes(&model, &candidate) {
+                model = candidate;
+            }
+        }
+    }
+    let mut decomp = decompose_patch_with(&model, &remaining.into(), spec, options)?;
+
+    // The temporary route-maps of the previous decomposition that use a temporary session allow
+    // routes and exit the route-map with their own weight, thereby overriding the route preferences
+    // of the new decomposition. Each of them is removed as soon as the router moves away from that
+    // route.
+    let overrides: Vec<(RouterId, RouterId, RouteMap<P>)> = temporary
+        .iter()
+        .filter_map(|m| match m {
+            ConfigModifier::Remove(ConfigExpr::BgpRouteMap {
+                router,
+                neighbor,
+                direction: RouteMapDirection::Incoming,
+                map,
+            }) if map.state().is_allow() && map.flow == RouteMapFlow::Exit => {
+                Some((*router, *neighbor, map.clone()))
+            }
+            _ => None,
+        })
+        .collect();
+    let folded: HashSet<ConfigExprKey<P>> = overrides
+        .iter()
+        .filter(|(router, neighbor, map)| fold_removal(&mut decomp, *router, *neighbor, map))
+        .map(|(router, neighbor, map)| ConfigExprKey::BgpRouteMap {
+            router: *router,
+            neighbor: *neighbor,
+            direction: RouteMapDirection::Incoming,
+            order: map.order,
+        })
+        .collect();
+
+    // The new decomposition takes over the temporary configuration of the previous one if it
+    // inserts an expression with the same key. Check that applying all commands reaches the target.
+    let temporary_keys: HashSet<ConfigExprKey<P>> =
+        temporary.iter().filter_map(|m| m.key()).collect();
+    let mut adopted: HashMap<ConfigExprKey<P>, ConfigExpr<P>> = HashMap::new();
+    let mut config = net.get_config()?;
+    let commands = decomp
+        .setup_commands
+        .iter_mut()
+        .chain(decomp.atomic_before.values_mut().flatten())
+        .chain(decomp.main_commands.iter_mut())
+        .chain(decomp.atomic_after.values_mut().flatten())
+        .chain(decomp.cleanup_commands.iter_mut())
+        .flatten();
+    for cmd in commands {
+        for raw in cmd.command.raw_mut() {
+            adopt(raw, &config, &temporary_keys, &mut adopted);
+            config
+                .apply_modifier(raw)
+                .map_err(|_| DecompositionError::UnreachableTarget)?;
+        }
+    }
+    // rolling back restores the adopted expressions instead of removing them.
+    let rollback = &mut decomp.rollback;
+    let rollback_commands = rollback
+        .setup_commands
+        .iter_mut()
+        .chain(rollback.atomic_before.values_mut().flatten())
+        .chain(rollback.main_commands.iter_mut())
+        .chain(rollback.atomic_after.values_mut().flatten())
+        .chain(rollback.cleanup_commands.iter_mut())
+        .flatten();
+    for cmd in rollback_commands {
+        cmd.command
+            .raw_mut()
+            .iter_mut()
+            .for_each(|raw| restore(raw, &adopted));
+    }
+
+    // ignore the weight of routes that are affected by the temporary route-maps of the previous
+    // decomposition, as long as they are present.
+    let Decomposition {
+        setup_commands,
+        atomic_before,
+        main_commands,
+        atomic_after,
+        cleanup_commands,
+        rollback,
+        ..
+    } = &mut decomp;
+    let all_commands = setup_commands
+        .iter_mut()
+        .chain(atomic_before.values_mut().flatten())
+        .chain(main_commands.iter_mut())
+        .chain(atomic_after.values_mut().flatten())
+        .chain(cleanup_commands.iter_mut())
+        .chain(rollback.setup_commands.iter_mut())
+        .chain(rollback.atomic_before.values_mut().flatten())
+        .chain(rollback.main_commands.iter_mut())
+        .chain(rollback.atomic_after.values_mut().flatten())
+        .chain(rollback.cleanup_commands.iter_mut())
+        .flatten();
+    for cmd in all_commands {
+        ignore_overridden_weight(&mut cmd.precondition, &overrides);
+        ignore_overridden_weight(&mut cmd.postcondition, &overrides);
+    }
+
+    // remove the remaining temporary configuration of the previous decomposition at the very end.
+    let (sessions, others): (Vec<_>, Vec<_>) = temporary
+        .into_iter()
+        .filter(|m| {
+            m.key()
+                .map(|k| adopted.contains_key(&k) || folded.contains(&k))
+                != Some(true)
+        })
+        .partition(|m| matches!(m.key(), Some(ConfigExprKey::BgpSession { .. })));
+    let cleanup = [others, sessions]
+        .into_iter()
+        .filter(|round| !round.is_empty())
+        .map(|round| {
+            round
+                .into_iter()
+                .map(|m| AtomicCommand {
+                    command: AtomicModifier::Raw(m),
+                    precondition: AtomicCondition::None,
+                    postcondition: AtomicCondition::None,
+                })
+                .collect::<Vec<AtomicCommand<P>>>()
+        })
+        .collect::<Vec<_>>();
+    for cmd in cleanup.iter().flatten() {
+        config
+            .apply_modifier(&cmd.command.clone().into_raw()[0])
+            .map_err(|_| DecompositionError::UnreachableTarget)?;
+    }
+    decomp
+        .rollback
+        .cleanup_commands
+        .extend(rollback::reverse_stage(&cleanup));
+    decomp.cleanup_commands.extend(cleanup);
+
+    if config
+        .get_diff(target)
+        .modifiers
+        .iter()
+        .any(|m| patch_keep(net, m))
+    {
+        return Err(DecompositionError::UnreachableTarget);
+    }
+
+    Ok(decomp)
+}
+
+/// Returns `true` if all routers select the same BGP routes in `a` and in `b`.
+fn same_bgp_routes<P: Prefix, Q>(a: &Network<P, Q>, b: &Network<P, Q>) -> bool {
+    let selected = |net: &Network<P, Q>, r: RouterId, p: P| {
+        net.get_device(r)
+            .internal()
+            .and_then(|r| r.get_selected_bgp_route(p))
+            .map(|e| (e.route.clone(), e.from_id))
+    };
+    let prefixes: HashSet<P> = a
+        .get_known_prefixes()
+        .chain(b.get_known_prefixes())
+        .copied()
+        .collect();
+    iproduct!(a.get_routers(), prefixes.iter())
+        .all(|(r, p)| selected(a, r, *p) == selected(b, r, *p))
+}
+
+/// If `raw` inserts an expression whose key is part of the `temporary` configuration of the
+/// previous decomposition, and which is currently present in `config`, then replace the old
+/// expression instead. The replaced expression is stored in `adopted`.
+fn adopt<P: Prefix>(
+    raw: &mut ConfigModifier<P>,
+    config: &Config<P>,
+    temporary: &HashSet<ConfigExprKey<P>>,
+    adopted: &mut HashMap<ConfigExprKey<P>, ConfigExpr<P>>,
+) {
+    match raw {
+        ConfigModifier::Insert(expr) => {
+            let key = expr.key();
+            if let (true, Some(old)) = (temporary.contains(&key), config.get(key.clone())) {
+                adopted.insert(key, old.clone());
+                *raw = ConfigModifier::Update {
+                    from: old.clone(),
+                    to: expr.clone(),
+                };
+            }
+        }
+        ConfigModifier::BatchRouteMapEdit { router, updates } => {
+            for edit in updates.iter_mut().filter(|e| e.old.is_none()) {
+                let key = match edit.clone().into_modifier(*router).key() {
+                    Some(key) if temporary.contains(&key) => key,
+                    _ => continue,
+                };
+                if let Some(old @ ConfigExpr::BgpRouteMap { map, .. }) = config.get(key.clone()) {
+                    edit.old = Some(map.clone());
+                    adopted.insert(key, old.clone());
+                }
+            }
+        }
+        ConfigModifier::Remove(_) | ConfigModifier::Update { .. } => {}
+    }
+}
+
+/// If `raw` removes an expression that was `adopted` from the previous decomposition (see
+/// [`adopt`]), then restore the old expression instead.
+fn restore<P: Prefix>(
+    raw: &mut ConfigModifier<P>,
+    adopted: &HashMap<ConfigExprKey<P>, ConfigExpr<P>>,
+) {
+    match raw {
+        ConfigModifier::Remove(expr) => {
+            if let Some(old) = adopted.get(&expr.key()) {
+                *raw = ConfigModifier::Update {
+                    from: expr.clone(),
+                    to: old.clone(),
+                };
+            }
+        }
+        ConfigModifier::BatchRouteMapEdit { router, updates } => {
+            for edit in updates.iter_mut().filter(|e| e.new.is_none()) {
+                let old = edit
+                    .clone()
+                    .into_modifier(*router)
+                    .key()
+                    .and_then(|k| adopted.get(&k));
+                if let Some(ConfigExpr::BgpRouteMap { map, .. }) = old {
+                    edit.new = Some(map.clone());
+                }
+            }
+        }
+        ConfigModifier::Insert(_) | ConfigModifier::Update { .. } => {}
+    }
+}
+
+/// Remove the temporary route-map `map` of the previous decomposition (see [`replan`]) together
+/// with the first atomic command of `decomp` that makes `router` move away from the routes learned
+/// from `neighbor` that `map` matches, and restore it in the reverse of that command. Returns
+/// `false` if `decomp` makes `router` prefer those routes before moving away from them, or if it
+/// never moves away from them.
+fn fold_removal<P: Prefix>(
+    decomp: &mut Decomposition<P>,
+    router: RouterId,
+    neighbor: RouterId,
+    map: &RouteMap<P>,
+) -> bool {
+    let expr = ConfigExpr::BgpRouteMap {
+        router,
+        neighbor,
+        direction: RouteMapDirection::Incoming,
+        map: map.clone(),
+    };
+    let Decomposition {
+        atomic_before,
+        atomic_after,
+        rollback,
+        ..
+    } = decomp;
+    let stages = [
+        (atomic_before, &mut rollback.atomic_before),
+        (atomic_after, &mut rollback.atomic_after),
+    ];
+    for (stages, rev_stages) in stages {
+        for (prefix, stage) in stages.iter_mut() {
+            let rev_stage = rev_stages.get_mut(prefix).into_iter().flatten();
+            for (round, rev_round) in stage.iter_mut().zip(rev_stage) {
+                for (cmd, rev_cmd) in round.iter_mut().zip(rev_round.iter_mut()) {
+                    match moves_away(&cmd.command, router, neighbor, map) {
+                        Some(true) => {
+                            return batch_route_map_edit(
+                                &mut cmd.command,
+                                router,
+                                ConfigModifier::Remove(expr.clone()),
+                            ) && batch_route_map_edit(
+                                &mut rev_cmd.command,
+                                router,
+                                ConfigModifier::Insert(expr),
+                            )
+                        }
+                        Some(false) => return false,
+                        None => {}
+                    }
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Ignore the weight in `cond` if the route is affected by one of the `overrides`, i.e., by an
+/// incoming route-map on the same router and neighbor that matches the prefix.
+fn ignore_overridden_weight<P: Prefix>(
+    cond: &mut AtomicCondition<P>,
+    overrides: &[(RouterId, RouterId, RouteMap<P>)],
+) {
+    match cond {
+        AtomicCondition::SelectedRoute {
+            router,
+            prefix,
+            neighbor: Some(neighbor),
+            weight,
+            ..
+        }
+        | AtomicCondition::AvailableRoute {
+            router,
+            prefix,
+            neighbor: Some(neighbor),
+            weight,
+            ..
+        } => {
+            let overridden = overrides
+                .iter()
+                .any(|(r, n, map)| r == router && n == neighbor && matches_prefix(map, prefix));
+            if overridden {
+                *weight = None;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns `true` if the route-map item `map` matches all routes for `prefix`, ignoring all
+/// conditions other than those on the prefix.
+fn matches_prefix<P: Prefix>(map: &RouteMap<P>, prefix: &P) -> bool {
+    map.conds().iter().all(|c| match c {
+        RouteMapMatch::Prefix(prefixes) => prefixes.contains(prefix),
+        _ => true,
+    })
+}
+
+/// Returns `Some(true)` if `command` makes `router` move away from the routes for a prefix matched
+/// by `map` that it learns from `neighbor`, and `Some(false)` if `command` makes `router` prefer
+/// those routes. Returns `None` if `command` does not change the route of `router` for such a
+/// prefix.
+fn moves_away<P: Prefix>(
+    command: &AtomicModifier<P>,
+    router: RouterId,
+    neighbor: RouterId,
+    map: &RouteMap<P>,
+) -> Option<bool> {
+    let (r, prefix, n) = match command {
+        AtomicModifier::ChangePreference {
+            router,
+            prefix,
+            neighbor,
+            ..
+        }
+        | AtomicModifier::UseTempSession {
+            router,
+            prefix,
+            neighbor,
+            ..
+        } => (*router, prefix, Some(*neighbor)),
+        AtomicModifier::ClearPreference { router, prefix, .. }
+        | AtomicModifier::IgnoreRoutes { router, prefix, .. } => (*router, prefix, None),
+        AtomicModifier::Raw(_)
+        | AtomicModifier::IgnoreTempSession { .. }
+        | AtomicModifier::AddTempSession { .. }
+        | AtomicModifier::RemoveTempSession { .. } => return None,
+    };
+    (r == router && matches_prefix(map, prefix)).then_some(n != Some(neighbor))
+}
+
+/// Apply the route-map `edit` on `router` in the same batch as the route-map updates of `command`
+/// on that router. Returns `false` if `command` does not update any route-map on `router`.
+fn batch_route_map_edit<P: Prefix>(
+    command: &mut AtomicModifier<P>,
+    router: RouterId,
+    edit: ConfigModifier<P>,
+) -> bool {
+    let updates_route_map = |m: &ConfigModifier<P>| match m {
+        ConfigModifier::Insert(ConfigExpr::BgpRouteMap { router: r, .. })
+        | ConfigModifier::Remove(ConfigExpr::BgpRouteMap { router: r, .. })
+        | ConfigModifier::Update {
+            to: ConfigExpr::BgpRouteMap { router: r, .. },
+            ..
+        }
+        | ConfigModifier::BatchRouteMapEdit { router: r, .. } => *r == router,
+        _ => false,
+    };
+    match command.raw_mut().iter_mut().find(|m| updates_route_map(m)) {
+        Some(raw) => {
+            *raw = compiler::batch_route_map_updates_of_commands(vec![raw.clone(), edit]).remove(0);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Returns `false` for modifiers that configure links that no longer exist in the network.
+fn patch_keep<P: Prefix, Q>(net: &Network<P, Q>, modifier: &ConfigModifier<P>) -> bool {
+    match modifier {
+        ConfigModifier::Insert(ConfigExpr::IgpLinkWeight { source, target, .. })
+        | ConfigModifier::Insert(ConfigExpr::OspfArea { source, target, .. }) => {
+            net.get_topology().find_edge(*source, *target).is_some()
+        }
+        _ => true,
+    }
+}
+
+/// A single forwarding delta, storing the old and the new set of next-hops. The set is empty if
+/// the router drops the traffic, and it contains multiple next-hops if load balancing is enabled.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FwDiff {
-    /// Old next-hop
-    old: Option<RouterId>,
-    /// New next-hop
-    new: Option<RouterId>,
+    /// Old next-hops
+    old: Vec<RouterId>,
+    /// New next-hops
+    new: Vec<RouterId>,
+}
+
+impl FwDiff {
+    /// Compute the forwarding difference of all `routers` for `prefix`, using longest prefix
+    /// matching. A router is also considered to have changed if it changes from being a terminal
+    /// to dropping the traffic, or vice-versa.
+    fn compute<P: Prefix>(
+        before: &ForwardingState<P>,
+        after: &ForwardingState<P>,
+        routers: &[RouterId],
+        prefix: P,
+    ) -> HashMap<RouterId, Self> {
+        routers
+            .iter()
+            .copied()
+            .filter_map(|r| {
+                let diff = FwDiff {
+                    old: before.get_next_hops(r, prefix).to_vec(),
+                    new: after.get_next_hops(r, prefix).to_vec(),
+                };
+                (diff.old != diff.new
+                    || before.is_terminal(r, prefix) != after.is_terminal(r, prefix))
+                .as_some((r, diff))
+            })
+            .collect()
+    }
 }
 
 /// Datastructure for storing all information about the command that can be directly observed from
 /// the simulator result.
 #[derive(Debug)]
-pub struct CommandInfo<'n, Q> {
-    /// Reconfiguration command to decompose
-    pub command: ConfigModifier<P>,
+pub struct CommandInfo<'n, P: Prefix, Q> {
+    /// Reconfiguration commands to decompose. They are all applied together.
+    pub command: ConfigPatch<P>,
     /// Network before the reconfiguration command
     pub net_before: &'n Network<P, Q>,
     /// Network after the reconfiguration command
@@ -150,6 +689,8 @@ pub struct CommandInfo<'n, Q> {
     pub fw_after: ForwardingState<P>,
     /// Difference of the forwarding state for each individual prefix
     pub fw_diff: HashMap<P, HashMap<RouterId, FwDiff>>,
+    /// Routers on which the forwarding state of a prefix depends on a less-specific prefix.
+    pub prefix_deps: Vec<PrefixDependency<P>>,
     /// Set of prefixes
     pub prefixes: HashSet<P>,
     /// Initial BGP state
@@ -157,20 +698,24 @@ pub struct CommandInfo<'n, Q> {
     /// Final BGP state
     pub bgp_after: HashMap<P, BgpState<P>>,
     /// Invariants during the migration.
-    pub spec: &'n Specification,
+    pub spec: &'n Specification<P>,
 }
 
-impl<'n, Q> CommandInfo<'n, Q>
+impl<'n, P: Prefix, Q> CommandInfo<'n, P, Q>
 where
     Q: EventQueue<P> + Clone,
 {
     /// Create a new decomposition structure that keeps all information about the reconfiguration
-    /// command that can be directly observed from the simulator.
+    /// command that can be directly observed from the simulator. The `command` can either be a
+    /// single [`ConfigModifier`], or an entire [`ConfigPatch`].
     pub fn new(
         net_before: &'n Network<P, Q>,
-        command: ConfigModifier<P>,
-        spec: &'n Specification,
-    ) -> Result<Self, DecompositionError> {
+        command: impl Into<ConfigPatch<P>>,
+        spec: &'n Specification<P>,
+    ) -> Result<Self, DecompositionError<P>> {
+        let command = command.into();
+        check_patch_conflicts(&command)?;
+
         info!("Extract the network state before and after the update.");
         let fw_before = net_before.get_forwarding_state();
         let bgp_before = net_before
@@ -178,7 +723,7 @@ where
             .map(|p| (*p, net_before.get_bgp_state_owned(*p)))
             .collect();
         let mut net_after = net_before.clone();
-        net_after.apply_modifier(&command)?;
+        net_after.apply_patch(&command)?;
         let fw_after = net_after.get_forwarding_state();
         let bgp_after = net_after
             .get_known_prefixes()
@@ -191,38 +736,15 @@ where
             .copied()
             .collect();
 
-        // check if every next-hop is unique
-        for r in net_before.get_topology().node_indices() {
-            for p in prefixes.iter() {
-                if fw_before.get_next_hops(r, *p).len() > 1
-                    || fw_after.get_next_hops(r, *p).len() > 1
-                {
-                    return Err(DecompositionError::LoadBalancingEnabled);
-                }
-            }
-        }
-
-        let fw_diff = fw_before
-            .diff(&fw_after)
-            .into_iter()
-            .map(|(p, diff)| {
-                (
-                    p,
-                    diff.into_iter()
-                        .map(|(r, mut old, mut new)| {
-                            (
-                                r,
-                                FwDiff {
-                                    old: old.pop(),
-                                    new: new.pop(),
-                                },
-                            )
-                        })
-                        .collect(),
-                )
-            })
+        let routers = net_before.get_topology().node_indices().collect::<Vec<_>>();
+        let fw_diff: HashMap<P, HashMap<RouterId, FwDiff>> = prefixes
+            .iter()
+            .map(|p| (*p, FwDiff::compute(&fw_before, &fw_after, &routers, *p)))
+            .filter(|(_, diff)| !diff.is_empty())
             .collect();
 
+        let prefix_deps = prefix_dependencies(&routers, &prefixes, &fw_diff, &fw_before, &fw_after);
+
         Ok(Self {
             command,
             net_before,
@@ -230,6 +752,7 @@ where
             fw_before,
             fw_after,
             fw_diff,
+            prefix_deps,
             prefixes,
             bgp_before,
             bgp_after,
@@ -238,7 +761,105 @@ where
     }
 }
 
-impl<'n, Q> CommandInfo<'n, Q> {
+/// Check that no two commands of the patch modify the same configuration expression. Otherwise,
+/// the state after the patch would depend on the order in which the commands are applied.
+fn check_patch_conflicts<P: Prefix>(patch: &ConfigPatch<P>) -> Result<(), DecompositionError<P>> {
+    let mut seen: HashMap<ConfigExprKey<P>, usize> = HashMap::new();
+    for (i, modifier) in patch.modifiers.iter().enumerate() {
+        let keys = match modifier {
+            ConfigModifier::BatchRouteMapEdit { router, updates } => updates
+                .iter()
+                .filter_map(|u| {
+                    u.new
+                        .as_ref()
+                        .or(u.old.as_ref())
+                        .map(|map| ConfigExprKey::BgpRouteMap {
+                            router: *router,
+                            neighbor: u.neighbor,
+                            direction: u.direction,
+                            order: map.order,
+                        })
+                })
+                .collect(),
+            m => m.key().into_iter().collect::<Vec<_>>(),
+        };
+        for key in keys {
+            if let Some(j) = seen.insert(key, i) {
+                return Err(DecompositionError::ConflictingCommands(j, i));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// State in which a router forwards the traffic of a prefix using a less-specific prefix (see
+/// [`PrefixDependency`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Fallback {
+    /// The router falls back to the less-specific prefix only before the reconfiguration.
+    Initial,
+    /// The router falls back to the less-specific prefix only after the reconfiguration.
+    Final,
+    /// The router falls back to the less-specific prefix both before and after the
+    /// reconfiguration.
+    Both,
+}
+
+/// Dependency of the forwarding state of a prefix on a less-specific prefix. If a router has no
+/// forwarding entry for `prefix` (either before or after the reconfiguration), it forwards the
+/// traffic using the entry of the covering prefix (longest prefix matching). If the forwarding
+/// state of the covering prefix changes on that router, then both prefixes must be scheduled
+/// together (see [`CommandInfo::prefix_groups`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PrefixDependency<P: Prefix> {
+    /// The more-specific prefix.
+    pub prefix: P,
+    /// The less-specific prefix used for forwarding the traffic of `prefix`.
+    pub covering: P,
+    /// The router that falls back to `covering`.
+    pub router: RouterId,
+    /// In which state the router falls back to `covering`.
+    pub fallback: Fallback,
+}
+
+/// Find all routers on which the forwarding state of a prefix depends on a less-specific prefix
+/// whose forwarding state changes on the same router.
+fn prefix_dependencies<P: Prefix>(
+    routers: &[RouterId],
+    prefixes: &HashSet<P>,
+    fw_diff: &HashMap<P, HashMap<RouterId, FwDiff>>,
+    fw_before: &ForwardingState<P>,
+    fw_after: &ForwardingState<P>,
+) -> Vec<PrefixDependency<P>> {
+    let changes = |p: &P, r: RouterId| fw_diff.get(p).map(|d| d.contains_key(&r)).unwrap_or(false);
+    let mut deps = Vec::new();
+    for (prefix, router) in iproduct!(prefixes.iter().copied().sorted(), routers.iter().copied()) {
+        let covering =
+            |fw: &ForwardingState<P>| fw.get_lpm_prefix(router, prefix).filter(|p| *p != prefix);
+        let fallbacks = match (covering(fw_before), covering(fw_after)) {
+            (Some(a), Some(b)) if a == b => vec![(a, Fallback::Both)],
+            (a, b) => a
+                .map(|a| (a, Fallback::Initial))
+                .into_iter()
+                .chain(b.map(|b| (b, Fallback::Final)))
+                .collect(),
+        };
+        deps.extend(
+            fallbacks
+                .into_iter()
+                .filter(|(covering, _)| changes(covering, router))
+                .map(|(covering, fallback)| PrefixDependency {
+                    prefix,
+                    covering,
+                    router,
+                    fallback,
+                }),
+        );
+    }
+    deps
+}
+
+impl<'n, P: Prefix, Q> CommandInfo<'n, P, Q> {
     /// Get an iterator over all routers in the network.
     pub fn routers(&self) -> Vec<RouterId> {
         self.net_before.get_topology().node_indices().collect()
@@ -248,24 +869,66 @@ impl<'n, Q> CommandInfo<'n, Q> {
     pub fn internal_routers(&self) -> Vec<RouterId> {
         self.net_before.get_routers()
     }
+
+    /// Partition all prefixes into groups that must be scheduled together, because the forwarding
+    /// state of one prefix depends on another one (see [`PrefixDependency`]). Each group is sorted
+    /// from the least-specific to the most-specific prefix. Most groups contain only a single
+    /// prefix.
+    pub fn prefix_groups(&self) -> Vec<Vec<P>> {
+        let mut group: HashMap<P, usize> = self
+            .prefixes
+            .iter()
+            .sorted()
+            .enumerate()
+            .map(|(i, p)| (*p, i))
+            .collect();
+        for dep in self.prefix_deps.iter() {
+            let (a, b) = (group[&dep.prefix], group[&dep.covering]);
+            group.values_mut().filter(|g| **g == b).for_each(|g| *g = a);
+        }
+        group
+            .into_iter()
+            .into_group_map_by(|(_, g)| *g)
+            .into_values()
+            .map(|members| {
+                members
+                    .into_iter()
+                    .map(|(p, _)| p)
+                    .sorted_by_key(|p| (Into::<Ipv4Net>::into(*p).prefix_len(), *p))
+                    .collect_vec()
+            })
+            .sorted()
+            .collect()
+    }
 }
 
 /// Error when decomposing a command
 #[derive(Debug, Error)]
-pub enum DecompositionError {
+pub enum DecompositionError<P: Prefix> {
     /// Error while operating with the Network.
     #[error("Network Error: {0}")]
     NetworkError(#[from] NetworkError),
     /// Could not compute the schedule
     #[error("Could not compute the schedule: {0}")]
     SchedulerError(#[from] ResolutionError),
-    /// Load balancing is not yet supported
-    #[error("Load balancing is enabled, but it is not yet supported!")]
-    LoadBalancingEnabled,
     /// Cannot add a temporary BGP session if it already exists.
     #[error("Cannot add a temporary BGP session between {0:?} and {1:?} that already exists.")]
     TemporaryBgpSession(RouterId, RouterId),
     /// The round at which to apply the main command could not be determined
     #[error("Illdefined round at which to apply the main command for prefix {0}: {1}")]
     InconsistentMainCommandRound(P, &'static str),
+    /// Two commands of the patch modify the same configuration expression.
+    #[error("Commands {0} and {1} of the patch modify the same configuration expression.")]
+    ConflictingCommands(usize, usize),
+    /// The re-planned decomposition does not reach the target configuration from the current
+    /// state of the network (see [`replan`]).
+    #[error("The decomposition does not reach the target configuration from the current state.")]
+    UnreachableTarget,
+    /// The patch contains a command that cannot be decomposed.
+    #[error("Cannot decompose command {0} of the patch. Only BGP sessions and route-maps are supported.")]
+    UnsupportedCommand(usize),
+    /// The rollback plan requires a route that the router does not select before or after the
+    /// reconfiguration.
+    #[error("Router {1:?} selects no route for {0} that the rollback plan can return to.")]
+    MissingRoute(P, RouterId),
 }
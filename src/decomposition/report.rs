@@ -0,0 +1,388 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! # Migration plan report
+//!
+//! Human-readable report of a [`Decomposition`] for reviewing the migration plan before executing
+//! it. The report lists each stage and round with all atomic commands and their pre- and
+//! postconditions, the expected forwarding changes of each prefix (from the forwarding state
+//! trace), and the schedule of each prefix as a graphviz graph (see [`super::visualizer`]). It
+//! further summarizes the estimated [`Overhead`] of the temporary configuration. The report can
+//! be rendered as Markdown or as HTML (see [`ReportFormat`]).
+
+use std::{collections::HashMap, path::Path};
+
+use atomic_command::{AtomicCommand, AtomicModifier};
+use bgpsim::{config::ConfigExpr, config::ConfigModifier, prelude::*};
+use itertools::Itertools;
+
+use super::{visualizer, Decomposition};
+
+/// Output format of a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReportFormat {
+    /// Markdown document
+    Markdown,
+    /// Standalone HTML document
+    Html,
+}
+
+impl ReportFormat {
+    /// Get the format from the extension of the file name (`.html` or `.htm` for HTML, and
+    /// Markdown otherwise).
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|x| x.to_str()) {
+            Some("html") | Some("htm") => Self::Html,
+            _ => Self::Markdown,
+        }
+    }
+}
+
+/// Estimated overhead of the temporary configuration during the migration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Overhead {
+    /// Total number of atomic commands.
+    pub num_commands: usize,
+    /// Number of rounds, assuming the rounds of different prefixes are executed in parallel.
+    pub num_rounds: usize,
+    /// Number of temporary BGP sessions.
+    pub temp_sessions: usize,
+    /// Number of route-map entries that are added temporarily, e.g., to prefer a specific route or
+    /// to use a temporary session.
+    pub temp_route_maps: usize,
+    /// Estimated number of additional routes stored in the RIBs of all routers. Each router that
+    /// uses a temporary session for a prefix learns one route in addition to the routes it knows
+    /// before and after the migration.
+    pub rib_overhead: usize,
+}
+
+impl<P: Prefix> Decomposition<P> {
+    /// Compute the estimated overhead of the temporary configuration (see [`Overhead`]).
+    pub fn overhead(&self) -> Overhead {
+        let max_rounds = |stage: &HashMap<P, Vec<Vec<AtomicCommand<P>>>>| {
+            stage.values().map(|x| x.len()).max().unwrap_or(0)
+        };
+        let commands = || {
+            self.setup_commands
+                .iter()
+                .chain(self.atomic_before.values().flatten())
+                .chain(self.main_commands.iter())
+                .chain(self.atomic_after.values().flatten())
+                .chain(self.cleanup_commands.iter())
+                .flatten()
+        };
+
+        Overhead {
+            num_commands: commands().count(),
+            num_rounds: self.setup_commands.len()
+                + max_rounds(&self.atomic_before)
+                + self.main_commands.len()
+                + max_rounds(&self.atomic_after)
+                + self.cleanup_commands.len(),
+            temp_sessions: commands()
+                .filter(|c| matches!(c.command, AtomicModifier::AddTempSession { .. }))
+                .count(),
+            temp_route_maps: commands()
+                .filter(|c| !matches!(c.command, AtomicModifier::Raw(_)))
+                .flat_map(|c| Vec::<ConfigModifier<P>>::from(c.command.clone()))
+                .map(|c| match c {
+                    ConfigModifier::Insert(ConfigExpr::BgpRouteMap { .. }) => 1,
+                    ConfigModifier::BatchRouteMapEdit { updates, .. } => updates
+                        .iter()
+                        .filter(|u| u.old.is_none() && u.new.is_some())
+                        .count(),
+                    _ => 0,
+                })
+                .sum(),
+            rib_overhead: commands()
+                .filter(|c| matches!(c.command, AtomicModifier::UseTempSession { .. }))
+                .count(),
+        }
+    }
+
+    /// Render a report of the migration plan in the given format (see the [module
+    /// documentation](crate::decomposition::report)).
+    pub fn report<Q>(&self, net: &Network<P, Q>, format: ReportFormat) -> String {
+        let mut doc = Document::new(format);
+        let overhead = self.overhead();
+
+        doc.heading(1, "Migration plan");
+        doc.paragraph(&format!(
+            "Reconfiguration: {}",
+            self.original_command
+                .modifiers
+                .iter()
+                .map(|c| doc.code(&c.fmt(net)))
+                .join(", ")
+        ));
+
+        doc.heading(2, "Overview");
+        let rollback = if self.rollback.is_verified() {
+            String::from("verified")
+        } else {
+            format!(
+                "not verified for {}",
+                self.rollback.unverified.iter().sorted().join(", ")
+            )
+        };
+        doc.table(&[
+            ("Prefixes", self.fw_state_trace.len().to_string()),
+            ("Atomic commands", overhead.num_commands.to_string()),
+            ("Rounds", overhead.num_rounds.to_string()),
+            ("Temporary BGP sessions", overhead.temp_sessions.to_string()),
+            (
+                "Temporary route-map entries",
+                overhead.temp_route_maps.to_string(),
+            ),
+            (
+                "Estimated additional RIB entries",
+                overhead.rib_overhead.to_string(),
+            ),
+            ("Rollback plan", rollback),
+        ]);
+
+        doc.heading(2, "Stage 1: Setup");
+        doc.stage(net, 3, &self.setup_commands);
+        doc.heading(2, "Stage 2: Update before the main command");
+        for (p, stage) in self.atomic_before.iter().sorted_by_key(|(p, _)| **p) {
+            doc.heading(3, &format!("Prefix {p}"));
+            doc.stage(net, 4, stage);
+        }
+        doc.heading(2, "Stage 3: Main command");
+        doc.stage(net, 3, &self.main_commands);
+        doc.heading(2, "Stage 4: Update after the main command");
+        for (p, stage) in self.atomic_after.iter().sorted_by_key(|(p, _)| **p) {
+            doc.heading(3, &format!("Prefix {p}"));
+            doc.stage(net, 4, stage);
+        }
+        doc.heading(2, "Stage 5: Cleanup");
+        doc.stage(net, 3, &self.cleanup_commands);
+
+        doc.heading(2, "Expected forwarding changes");
+        for (p, trace) in self.fw_state_trace.iter().sorted_by_key(|(p, _)| **p) {
+            doc.heading(3, &format!("Prefix {p}"));
+            doc.list(
+                trace
+                    .iter()
+                    .map(|step| {
+                        step.iter()
+                            .sorted()
+                            .map(|(r, nh)| {
+                                let nh = if nh.is_empty() {
+                                    String::from("drop")
+                                } else {
+                                    nh.iter().map(|x| x.fmt(net)).join(" | ")
+                                };
+                                format!("{} → {}", r.fmt(net), nh)
+                            })
+                            .join(", ")
+                    })
+                    .collect(),
+            );
+            if let Some(schedule) = self.schedule.get(p).filter(|s| !s.is_empty()) {
+                let mut dot = Vec::new();
+                visualizer::write_schedule_dot(
+                    &visualizer::schedule_rounds(schedule),
+                    &Default::default(),
+                    self.bgp_deps.get(p),
+                    &mut dot,
+                    |r| r.fmt(net),
+                );
+                doc.code_block("Schedule (graphviz)", &String::from_utf8_lossy(&dot));
+            }
+        }
+
+        doc.finish()
+    }
+
+    /// Write the report of the migration plan into a file. The format is determined by the file
+    /// extension (see [`ReportFormat::from_path`]).
+    pub fn write_report<Q>(
+        &self,
+        net: &Network<P, Q>,
+        path: impl AsRef<Path>,
+    ) -> std::io::Result<()> {
+        let format = ReportFormat::from_path(path.as_ref());
+        std::fs::write(path, self.report(net, format))
+    }
+}
+
+/// Document that is rendered either as Markdown or as HTML.
+struct Document {
+    /// Output format
+    format: ReportFormat,
+    /// Content rendered so far
+    out: String,
+}
+
+impl Document {
+    /// Create a new, empty document.
+    fn new(format: ReportFormat) -> Self {
+        let out = match format {
+            ReportFormat::Markdown => String::new(),
+            ReportFormat::Html => String::from(
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+                 <title>Migration plan</title>\n</head>\n<body>\n",
+            ),
+        };
+        Self { format, out }
+    }
+
+    /// Finish the document and return its content.
+    fn finish(mut self) -> String {
+        if self.format == ReportFormat::Html {
+            self.out.push_str("</body>\n</html>\n");
+        }
+        self.out
+    }
+
+    /// Format inline code.
+    fn code(&self, text: &str) -> String {
+        match self.format {
+            ReportFormat::Markdown => format!("`{text}`"),
+            ReportFormat::Html => format!("<code>{}</code>", escape(text)),
+        }
+    }
+
+    /// Add a heading of the given level.
+    fn heading(&mut self, level: usize, text: &str) {
+        match self.format {
+            ReportFormat::Markdown => self.out += &format!("{} {text}\n\n", "#".repeat(level)),
+            ReportFormat::Html => self.out += &format!("<h{level}>{}</h{level}>\n", escape(text)),
+        }
+    }
+
+    /// Add a paragraph. The text must already be formatted (see [`Document::code`]).
+    fn paragraph(&mut self, text: &str) {
+        match self.format {
+            ReportFormat::Markdown => self.out += &format!("{text}\n\n"),
+            ReportFormat::Html => self.out += &format!("<p>{text}</p>\n"),
+        }
+    }
+
+    /// Add a table with two columns.
+    fn table(&mut self, rows: &[(&str, String)]) {
+        match self.format {
+            ReportFormat::Markdown => {
+                self.out += "| | |\n|---|---|\n";
+                for (key, value) in rows {
+                    self.out += &format!("| {key} | {value} |\n");
+                }
+                self.out += "\n";
+            }
+            ReportFormat::Html => {
+                self.out += "<table>\n";
+                for (key, value) in rows {
+                    self.out += &format!(
+                        "<tr><th>{}</th><td>{}</td></tr>\n",
+                        escape(key),
+                        escape(value)
+                    );
+                }
+                self.out += "</table>\n";
+            }
+        }
+    }
+
+    /// Add an ordered list.
+    fn list(&mut self, items: Vec<String>) {
+        if items.is_empty() {
+            return self.paragraph("No changes.");
+        }
+        match self.format {
+            ReportFormat::Markdown => {
+                for (i, item) in items.iter().enumerate() {
+                    self.out += &format!("{}. {item}\n", i + 1);
+                }
+                self.out += "\n";
+            }
+            ReportFormat::Html => {
+                self.out += "<ol>\n";
+                for item in items {
+                    self.out += &format!("<li>{}</li>\n", escape(&item));
+                }
+                self.out += "</ol>\n";
+            }
+        }
+    }
+
+    /// Add a block of code with a caption.
+    fn code_block(&mut self, caption: &str, text: &str) {
+        match self.format {
+            ReportFormat::Markdown => self.out += &format!("{caption}:\n\n```dot\n{text}```\n\n"),
+            ReportFormat::Html => {
+                self.out += &format!(
+                    "<details>\n<summary>{}</summary>\n<pre>{}</pre>\n</details>\n",
+                    escape(caption),
+                    escape(text)
+                )
+            }
+        }
+    }
+
+    /// Add all rounds of a stage, where each round is a heading of the given level.
+    fn stage<P: Prefix, Q>(
+        &mut self,
+        net: &Network<P, Q>,
+        level: usize,
+        stage: &[Vec<AtomicCommand<P>>],
+    ) {
+        if stage.iter().all(|round| round.is_empty()) {
+            return self.paragraph("No commands.");
+        }
+        for (i, round) in stage.iter().enumerate().filter(|(_, r)| !r.is_empty()) {
+            self.heading(level, &format!("Round {}", i + 1));
+            match self.format {
+                ReportFormat::Markdown => {
+                    for (j, cmd) in round.iter().enumerate() {
+                        let marker = format!("{}. ", j + 1);
+                        // nested lists must be indented by the width of the marker.
+                        let tab = " ".repeat(marker.len());
+                        self.out += &format!(
+                            "{marker}**{}**\n{tab}- Precondition: `{}`\n{tab}- Postcondition: `{}`\n",
+                            cmd.command.fmt(net),
+                            cmd.precondition.fmt(net),
+                            cmd.postcondition.fmt(net),
+                        );
+                    }
+                    self.out += "\n";
+                }
+                ReportFormat::Html => {
+                    self.out += "<ol>\n";
+                    for cmd in round {
+                        self.out += &format!(
+                            "<li><b>{}</b><ul><li>Precondition: <code>{}</code></li>\
+                             <li>Postcondition: <code>{}</code></li></ul></li>\n",
+                            escape(&cmd.command.fmt(net)),
+                            escape(&cmd.precondition.fmt(net)),
+                            escape(&cmd.postcondition.fmt(net)),
+                        );
+                    }
+                    self.out += "</ol>\n";
+                }
+            }
+        }
+    }
+}
+
+/// Escape the special characters of HTML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
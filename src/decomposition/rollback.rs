@@ -0,0 +1,174 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! # Rollback plan
+//!
+//! For each stage and each round of a [`Decomposition`](super::Decomposition), the compiler emits
+//! the reverse commands that bring the network back to its original configuration. Each command
+//! of the rollback plan is stored at the same position as the command of the forward plan that it
+//! reverses. Hence, the rollback plan from any point in the update consists of the reverse
+//! commands of all commands applied so far, in reverse order (see
+//! [`Controller::abort`](crate::runtime::controller::Controller::abort)).
+//!
+//! Reversing the commands of round `k` brings each router back to the route it selected before
+//! round `k`. Hence, the rollback traverses the forwarding states of the update in reverse order.
+//! Like the forward plan, the reverse commands carry conditions that enforce this order: Each
+//! reverse command that changes the selected route waits until the route to return to is available,
+//! and then until the router selects it. Sessions that are added again must be established before
+//! the next round. Removing the increased weight of the old route waits until all other routes are
+//! less preferred. The compiler verifies that the complete trace (first performing the update up to
+//! some point, and then rolling back) satisfies the specification for each prefix and each step of
+//! the forwarding state trace. Prefixes for which this is not the case (e.g., because the
+//! specification requires the traffic to leave over the old egress *until* it leaves over the new
+//! egress) are marked as [`Rollback::unverified`].
+
+use std::collections::{HashMap, HashSet};
+
+use atomic_command::{AtomicCommand, AtomicCondition};
+use bgpsim::{forwarding_state::ForwardingState, types::Prefix};
+
+use super::{ilp_scheduler::FwStateTrace, CommandInfo};
+use crate::specification::{Checker, Specification};
+
+/// Type definition for a single stage
+type Stage<P> = Vec<Vec<AtomicCommand<P>>>;
+
+/// The rollback plan of a decomposition. Each stage has the same shape as the corresponding stage
+/// of the forward plan, and each command reverses the command of the forward plan at the same
+/// position.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "P: for<'a> serde::Deserialize<'a>"))
+)]
+pub struct Rollback<P: Prefix> {
+    /// Reverse commands of the setup stage.
+    pub setup_commands: Stage<P>,
+    /// Reverse commands of the rounds before the main command, for each prefix.
+    pub atomic_before: HashMap<P, Stage<P>>,
+    /// Reverse commands of the main stage.
+    pub main_commands: Stage<P>,
+    /// Reverse commands of the rounds after the main command, for each prefix.
+    pub atomic_after: HashMap<P, Stage<P>>,
+    /// Reverse commands of the cleanup stage.
+    pub cleanup_commands: Stage<P>,
+    /// Prefixes for which rolling back may violate the specification.
+    pub unverified: HashSet<P>,
+}
+
+impl<P: Prefix> Default for Rollback<P> {
+    fn default() -> Self {
+        Self {
+            setup_commands: Default::default(),
+            atomic_before: Default::default(),
+            main_commands: Default::default(),
+            atomic_after: Default::default(),
+            cleanup_commands: Default::default(),
+            unverified: Default::default(),
+        }
+    }
+}
+
+impl<P: Prefix> Rollback<P> {
+    /// Returns `true` if rolling back satisfies the specification for all prefixes.
+    pub fn is_verified(&self) -> bool {
+        self.unverified.is_empty()
+    }
+}
+
+/// Reverse all commands of a stage without any conditions. Each command is stored at the same
+/// position as the command it reverses. The compiler adds the conditions afterwards.
+pub(super) fn reverse_stage<P: Prefix>(stage: &Stage<P>) -> Stage<P> {
+    stage
+        .iter()
+        .map(|round| {
+            round
+                .iter()
+                .map(|cmd| AtomicCommand {
+                    command: cmd.command.clone().reverse(),
+                    precondition: AtomicCondition::None,
+                    postcondition: AtomicCondition::None,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Verify that rolling back from any step of the forwarding state trace satisfies the
+/// specification. Any prefix for which this is not the case is returned.
+pub(super) fn verify<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    fw_state_trace: &HashMap<P, FwStateTrace>,
+) -> HashSet<P> {
+    fw_state_trace
+        .iter()
+        .filter(|(p, trace)| !verify_prefix(info, **p, trace))
+        .map(|(p, _)| *p)
+        .inspect(|p| log::warn!("Rolling back may violate the specification for {p}!"))
+        .collect()
+}
+
+/// Verify that, for each step `k` of the trace, the sequence of forwarding states `0, 1, ..., k,
+/// k - 1, ..., 0` satisfies the specification of the prefix.
+fn verify_prefix<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
+    prefix: P,
+    trace: &FwStateTrace,
+) -> bool {
+    let spec: Specification<P> = info
+        .spec
+        .get(&prefix)
+        .map(|expr| (prefix, expr.clone()))
+        .into_iter()
+        .collect();
+    if spec.is_empty() {
+        return true;
+    }
+
+    let mut checker = Checker::new(&spec);
+    let mut fw_state: ForwardingState<P> = info.fw_before.clone();
+    checker.step(&mut fw_state);
+
+    // the previous next-hops of all routers that change in each step.
+    let mut undo: Vec<Vec<_>> = Vec::new();
+    for step in trace {
+        undo.push(
+            step.iter()
+                .map(|(r, _)| (*r, fw_state.get_next_hops(*r, prefix).to_vec()))
+                .collect(),
+        );
+        for (r, nh) in step {
+            fw_state.update(*r, prefix, nh.clone());
+        }
+        checker.step(&mut fw_state);
+
+        // roll back from this step
+        let mut rollback_checker = checker.clone();
+        let mut rollback_state = fw_state.clone();
+        for delta in undo.iter().rev() {
+            for (r, nh) in delta {
+                rollback_state.update(*r, prefix, nh.clone());
+            }
+            rollback_checker.step(&mut rollback_state);
+        }
+        if !rollback_checker.check_prefix(prefix) {
+            return false;
+        }
+    }
+
+    true
+}
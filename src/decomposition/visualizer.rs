@@ -15,37 +15,36 @@
 // with this program; if not, write to the Free Software Foundation, Inc.,
 // 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
 
-//! This module visualizes a single schedule using graphviz. This module will do nothing when used
-//! in test configuration.
+//! This module visualizes a single schedule using graphviz. Creating the PDF (see [`visualize`])
+//! will do nothing when used in test configuration.
 
 use bgpsim::prelude::*;
-use std::{collections::HashMap, fmt::Display};
+use itertools::Itertools;
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    io::Write,
+    iter::repeat_with,
+};
 
 use super::CommandInfo;
-use super::{bgp_dependencies::BgpDependencies, ilp_scheduler::NodeSchedule};
-use crate::P;
-
-#[cfg(not(test))]
-use super::bgp_dependencies::BgpDependency;
+use super::{
+    bgp_dependencies::{BgpDependencies, BgpDependency},
+    ilp_scheduler::NodeSchedule,
+};
 
 #[cfg(not(test))]
 use std::{
-    cmp::Ordering,
-    collections::HashSet,
     fs::{remove_file, OpenOptions},
-    io::Write,
-    iter::repeat_with,
     path::PathBuf,
     process::Command,
 };
 
-#[cfg(not(test))]
-use itertools::Itertools;
-
 /// Create a PDF that visualizes the schedule using graphviz DOT. The `filename_base` should not
 /// contain any file type, as the prefix will be appended automatically.
-pub fn visualize<F, S, Q>(
-    info: &CommandInfo<'_, Q>,
+pub fn visualize<P: Prefix, F, S, Q>(
+    info: &CommandInfo<'_, P, Q>,
     schedules: &HashMap<P, HashMap<RouterId, NodeSchedule>>,
     bgp_deps: &HashMap<P, BgpDependencies>,
     prefix: P,
@@ -86,17 +85,12 @@ pub fn visualize<F, S, Q>(
         let mut dot_file = OpenOptions::new()
             .create(true)
             .write(true)
+            .truncate(true)
             .open(&dot_path)
             .unwrap();
 
         // create the schedule
-        let schedule = schedules.get(&prefix).unwrap();
-        let mut schedule_vec: Vec<Vec<RouterId>> = repeat_with(Vec::new)
-            .take(schedule.values().map(|x| x.fw_state).max().unwrap() + 1)
-            .collect();
-        schedule
-            .iter()
-            .for_each(|(r, x)| schedule_vec[x.fw_state].push(*r));
+        let schedule_vec = schedule_rounds(schedules.get(&prefix).unwrap());
         write_dot(info, &schedule_vec, bgp_deps, prefix, &mut dot_file, name);
 
         // call `dot`
@@ -118,8 +112,8 @@ pub fn visualize<F, S, Q>(
 /// Visualize the schedule for a given prefix using graphviz. This function will write the `dot`
 /// file into the provided `output`.
 #[cfg(not(test))]
-pub fn write_dot<W: Write, S: Display, F, Q>(
-    info: &CommandInfo<'_, Q>,
+pub fn write_dot<P: Prefix, W: Write, S: Display, F, Q>(
+    info: &CommandInfo<'_, P, Q>,
     schedule: &[Vec<RouterId>],
     bgp_deps: &HashMap<P, BgpDependencies>,
     prefix: P,
@@ -128,9 +122,34 @@ pub fn write_dot<W: Write, S: Display, F, Q>(
 ) where
     F: Fn(RouterId) -> S,
 {
-    let bgp_deps = bgp_deps.get(&prefix);
     let fw_deps = get_fw_deps(info, schedule, prefix);
+    write_schedule_dot(schedule, &fw_deps, bgp_deps.get(&prefix), output, name)
+}
 
+/// Group the routers by the round in which they update their forwarding state.
+pub fn schedule_rounds(schedule: &HashMap<RouterId, NodeSchedule>) -> Vec<Vec<RouterId>> {
+    let mut rounds: Vec<Vec<RouterId>> = repeat_with(Vec::new)
+        .take(schedule.values().map(|x| x.fw_state + 1).max().unwrap_or(0))
+        .collect();
+    schedule
+        .iter()
+        .sorted_by_key(|(r, _)| **r)
+        .for_each(|(r, x)| rounds[x.fw_state].push(*r));
+    rounds
+}
+
+/// Write the graphviz `dot` representation of the schedule (grouped into rounds, see
+/// [`schedule_rounds`]) into `output`. Bold edges represent forwarding dependencies `fw_deps`,
+/// and colored edges represent the BGP dependencies (green if satisfied, and red otherwise).
+pub fn write_schedule_dot<W: Write, S: Display, F>(
+    schedule: &[Vec<RouterId>],
+    fw_deps: &HashMap<RouterId, HashSet<RouterId>>,
+    bgp_deps: Option<&BgpDependencies>,
+    output: &mut W,
+    name: F,
+) where
+    F: Fn(RouterId) -> S,
+{
     writeln!(output, "digraph D {{").unwrap();
     writeln!(output, "  splines=true").unwrap();
 
@@ -213,8 +232,8 @@ pub fn write_dot<W: Write, S: Display, F, Q>(
 /// Visualize the schedule for a given prefix using graphviz. This function will write the `dot`
 /// file into the provided `output`.
 #[cfg(not(test))]
-pub fn get_fw_deps<Q>(
-    info: &CommandInfo<'_, Q>,
+pub fn get_fw_deps<P: Prefix, Q>(
+    info: &CommandInfo<'_, P, Q>,
     schedule: &[Vec<RouterId>],
     prefix: P,
 ) -> HashMap<RouterId, HashSet<RouterId>> {
@@ -241,25 +260,16 @@ pub fn get_fw_deps<Q>(
                 continue;
             }
 
-            let old_path = match fw_state.get_paths(*router, prefix) {
-                Ok(mut paths) => paths.pop().unwrap_or_default(),
-                Err(NetworkError::ForwardingLoop(p))
-                | Err(NetworkError::ForwardingBlackHole(p)) => p,
-                _ => unreachable!(),
-            };
+            // follow each next-hop separately, such that a forwarding loop or black hole on one
+            // ECMP path does not hide the routers on the other paths.
+            let old_reach = fw_state.get_nodes_along_paths(*router, prefix);
             fw_state.update(
                 *router,
                 prefix,
                 info.fw_after.get_next_hops(*router, prefix).to_vec(),
             );
-            let new_path = match fw_state.get_paths(*router, prefix) {
-                Ok(mut paths) => paths.pop().unwrap_or_default(),
-                Err(NetworkError::ForwardingLoop(p))
-                | Err(NetworkError::ForwardingBlackHole(p)) => p,
-                _ => unreachable!(),
-            };
-            let reach: HashSet<RouterId> =
-                old_path.into_iter().chain(new_path.into_iter()).collect();
+            let new_reach = fw_state.get_nodes_along_paths(*router, prefix);
+            let reach: HashSet<RouterId> = old_reach.into_iter().chain(new_reach).collect();
 
             for dep in reach.intersection(&changed) {
                 if info.fw_diff.get(&prefix).and_then(|x| x.get(dep)).is_some() && dep != router {
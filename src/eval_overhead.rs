@@ -31,10 +31,10 @@ use chameleon::{
     decomposition::{
         bgp_dependencies::find_dependencies,
         compiler::build,
-        ilp_scheduler::{schedule_smart, NodeSchedule},
+        ilp_scheduler::{schedule_smart_with, NodeSchedule, StepSearch},
         CommandInfo,
     },
-    experiment::{Experiment, Scenario, _TopologyZoo},
+    experiment::{_TopologyZoo, Experiment, Scenario},
     runtime,
     specification::SpecificationBuilder,
     Decomposition, P,
@@ -82,6 +82,10 @@ struct Cli {
     /// Randomize configuration
     #[clap(short, long)]
     rand: bool,
+    /// Perform a binary search over the number of steps, instead of increasing the number of steps
+    /// one by one.
+    #[clap(short = 'b', long = "binary-search")]
+    binary_search: bool,
 }
 
 /// What kind of invariants should be generated
@@ -140,6 +144,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     pretty_env_logger::init_timed();
 
     let args = Cli::parse();
+    let search = if args.binary_search {
+        StepSearch::BinarySearch
+    } else {
+        StepSearch::Linear
+    };
 
     let mut path = generate_folder()?;
 
@@ -170,12 +179,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         let Ok((net, p, c)) = args.scenario.build(topo, BasicEventQueue::new(), args.rand) else {
             println!("Skipping {topo}");
             i += spec_kinds.len() * args.num_repetitions;
-            continue
+            continue;
         };
 
         // for each spec
         for spec_kind in spec_kinds {
-
             // for each repetition
             for _ in 0..args.num_repetitions {
                 i += 1;
@@ -197,12 +205,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 let bgp_deps = find_dependencies(&info);
 
                 let start_time = Instant::now();
-                let (result, size) = schedule_smart(
+                let (result, size) = schedule_smart_with(
                     &info,
                     &bgp_deps,
                     p,
                     Duration::from_secs(args.timeout),
                     (args.num_allowed_temp_sessions * net.num_devices() as f64).round() as usize,
+                    search,
                 );
 
                 let path_len = compute_avg_path_length(&info);
@@ -363,7 +372,7 @@ struct ExperimentData<'a> {
     fw_state_after: &'a ForwardingState<P>,
 }
 
-fn compute_avg_path_length<Q>(info: &CommandInfo<'_, Q>) -> f64 {
+fn compute_avg_path_length<Q>(info: &CommandInfo<'_, P, Q>) -> f64 {
     let mut num_paths = 0usize;
     let mut acc = 0.0;
     let mut fw_before = info.fw_before.clone();
@@ -212,6 +212,6 @@ struct Scenario {
     net: Network<P, BasicEventQueue<P>>,
     event: Event,
     spec_kind: SpecificationBuilder,
-    spec: Specification,
+    spec: Specification<P>,
     command: ConfigModifier<P>,
 }
@@ -148,7 +148,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn measure_naive(net: &Net, c: &Cmd, spec: &Specification, prefix: P) -> Result<f64, NetworkError> {
+fn measure_naive(
+    net: &Net,
+    c: &Cmd,
+    spec: &Specification<P>,
+    prefix: P,
+) -> Result<f64, NetworkError> {
     let invariants = spec
         .get(&prefix)
         .map(|x| x.clone().as_global_invariants(net))
@@ -188,8 +193,8 @@ fn measure_naive(net: &Net, c: &Cmd, spec: &Specification, prefix: P) -> Result<
 
 fn assert_schedule_correct(
     net: &Net,
-    decomp: &Decomposition,
-    spec: &Specification,
+    decomp: &Decomposition<P>,
+    spec: &Specification<P>,
 ) -> Result<(), sim::SimError> {
     sim::run(net.clone(), decomp.clone(), spec).map(|_| ())
 }
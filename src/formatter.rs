@@ -24,19 +24,22 @@ use bgpsim::prelude::*;
 use itertools::Itertools;
 
 use crate::{
-    decomposition::{bgp_dependencies::BgpDependency, Decomposition},
+    decomposition::{
+        bgp_dependencies::BgpDependency,
+        campaign::{CampaignPlan, CampaignStage},
+        Decomposition,
+    },
     runtime::controller::{AtomicCommandState, Controller, ControllerStage, StateItem},
     specification::{Invariant, Property, SpecExpr, Violation},
-    P,
 };
 
 /// Trait to format things using appropriate indentation.
-pub trait IndentedNetworkFormatter<'a, 'n, Q> {
+pub trait IndentedNetworkFormatter<'a, 'n, P: Prefix, Q> {
     /// Format something using the network and some specific indent.
     fn fmt(&'a self, net: &'n Network<P, Q>, indent: usize) -> String;
 }
 
-impl<'a, 'n, Q> IndentedNetworkFormatter<'a, 'n, Q> for AtomicCommand<P> {
+impl<'a, 'n, P: Prefix, Q> IndentedNetworkFormatter<'a, 'n, P, Q> for AtomicCommand<P> {
     fn fmt(&'a self, net: &'n Network<P, Q>, indent: usize) -> String {
         let tab: String = " ".repeat(indent);
         format!(
@@ -55,7 +58,7 @@ impl<'a, 'n, Q> IndentedNetworkFormatter<'a, 'n, Q> for AtomicCommand<P> {
     }
 }
 
-impl<'a, 'n, Q> IndentedNetworkFormatter<'a, 'n, Q> for [AtomicCommand<P>] {
+impl<'a, 'n, P: Prefix, Q> IndentedNetworkFormatter<'a, 'n, P, Q> for [AtomicCommand<P>] {
     fn fmt(&'a self, net: &'n Network<P, Q>, indent: usize) -> String {
         let tab: String = " ".repeat(indent);
         format!(
@@ -66,7 +69,7 @@ impl<'a, 'n, Q> IndentedNetworkFormatter<'a, 'n, Q> for [AtomicCommand<P>] {
     }
 }
 
-impl<'a, 'n, Q> IndentedNetworkFormatter<'a, 'n, Q> for [Vec<AtomicCommand<P>>] {
+impl<'a, 'n, P: Prefix, Q> IndentedNetworkFormatter<'a, 'n, P, Q> for [Vec<AtomicCommand<P>>] {
     fn fmt(&'a self, net: &'n Network<P, Q>, indent: usize) -> String {
         let tab: String = " ".repeat(indent);
         format!(
@@ -77,7 +80,7 @@ impl<'a, 'n, Q> IndentedNetworkFormatter<'a, 'n, Q> for [Vec<AtomicCommand<P>>]
     }
 }
 
-impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for Decomposition {
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for Decomposition<P> {
     type Formatter = String;
 
     fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
@@ -158,7 +161,12 @@ impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for Decomposition {
 
         format!(
             "Decomposition of command: {} {{\n{}\n{}\n{}\n{}\n{}\n{}\n}}",
-            self.original_command.fmt(net),
+            self.original_command
+                .modifiers
+                .iter()
+                .map(|c| c.fmt(net))
+                .collect::<Vec<_>>()
+                .join(", "),
             schedule,
             setup,
             before,
@@ -169,7 +177,39 @@ impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for Decomposition {
     }
 }
 
-impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for Controller {
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for CampaignStage<P> {
+    type Formatter = String;
+
+    fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
+        format!(
+            "{{{}}}: cost {}",
+            self.patch.modifiers.iter().map(|c| c.fmt(net)).join(", "),
+            self.cost
+        )
+    }
+}
+
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for CampaignPlan<P> {
+    type Formatter = String;
+
+    fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
+        format!(
+            "Campaign with {} stages {{
+{}
+}}
+total cost: {}",
+            self.stages.len(),
+            self.stages
+                .iter()
+                .enumerate()
+                .map(|(i, s)| format!("  stage {i}: {}", s.fmt(net)))
+                .join("\n"),
+            self.cost()
+        )
+    }
+}
+
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for Controller<P> {
     type Formatter = String;
 
     fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
@@ -181,7 +221,7 @@ impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for Controller {
     }
 }
 
-impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for ControllerStage {
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for ControllerStage<P> {
     type Formatter = String;
 
     fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
@@ -206,7 +246,7 @@ impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for ControllerStage {
     }
 }
 
-impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for StateItem {
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for StateItem<P> {
     type Formatter = String;
 
     fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
@@ -233,7 +273,7 @@ impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for StateItem {
     }
 }
 
-impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for BgpDependency {
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for BgpDependency {
     type Formatter = String;
 
     fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
@@ -245,7 +285,7 @@ impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for BgpDependency {
     }
 }
 
-impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for SpecExpr {
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for SpecExpr {
     type Formatter = String;
 
     fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
@@ -264,7 +304,7 @@ impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for SpecExpr {
     }
 }
 
-impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for Invariant {
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for Invariant {
     type Formatter = String;
 
     fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
@@ -272,7 +312,7 @@ impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for Invariant {
     }
 }
 
-impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for Property {
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for Property {
     type Formatter = String;
 
     fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
@@ -281,13 +321,20 @@ impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for Property {
             Property::Any(x) => format!("({})", x.iter().map(|p| p.fmt(net)).join(" || ")),
             Property::Not(x) => format!("!{}", x.fmt(net)),
             Property::Waypoint(wp) => wp.fmt(net).to_string(),
+            Property::Edge(a, b) => format!("{}->{}", a.fmt(net), b.fmt(net)),
+            Property::Sequence(ws) => format!("[{}]", ws.iter().map(|w| w.fmt(net)).join(" -> ")),
+            Property::MaxHops(k) => format!("hops<={k}"),
+            Property::Egress(es) => {
+                format!("egress{{{}}}", es.iter().map(|e| e.fmt(net)).join(", "))
+            }
+            Property::Avoid(r) => format!("avoid {}", r.fmt(net)),
             Property::Reachability => String::from("reach"),
             Property::True => String::from('t'),
         }
     }
 }
 
-impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for Violation {
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for Violation<P> {
     type Formatter = String;
 
     fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
@@ -305,7 +352,7 @@ impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for Violation {
 }
 
 #[cfg(feature = "cisco-lab")]
-impl<'a, 'n, Q> NetworkFormatter<'a, 'n, P, Q> for crate::runtime::lab::Event {
+impl<'a, 'n, P: Prefix, Q> NetworkFormatter<'a, 'n, P, Q> for crate::runtime::lab::Event<P> {
     type Formatter = String;
 
     fn fmt(&'a self, net: &'n Network<P, Q>) -> Self::Formatter {
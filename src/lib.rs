@@ -60,7 +60,8 @@
 //! - The module [`decomposition`] (function [`decompose`] and structure [`Decomposition`]) contains
 //!   the entire code for decomposing the command into multiple atomic commands. The module contains
 //!   the Analyzer ([`decomposition::bgp_dependencies`]), the Scheduler
-//!   ([`decomposition::ilp_scheduler`]), and the Compiler ([`decomposition::compiler`]).
+//!   ([`decomposition::ilp_scheduler`]), and the Compiler ([`decomposition::compiler`]). Multiple
+//!   commands can be planned as a campaign of several stages ([`decomposition::campaign`]).
 //! - The module `runtime` is responsible for applying the decomposition to the network, and
 //!   verifying if the decomposition is valid. It contains an implementation to run Chameleon on
 //!   both BgpSim ([`runtime::sim`]) and the test bed ([`runtime::lab`] with the feature
@@ -68,7 +69,8 @@
 //! - The module [`experiment`] contains code to quickly genwerate topoligies, configurations, and
 //!   reconfiguration scenarios.
 //! - The module [`specification`] defines the specification language
-//!   ([`specification::Specification`]).
+//!   ([`specification::Specification`]), and its text format
+//!   ([`specification::parse_specification`]).
 //! - The basic datastructures used for the resulting [`Decomposition`] are defined in a separate
 //!   crate: [`atomic_command`].
 
@@ -89,8 +91,10 @@ pub mod specification;
 #[cfg(test)]
 mod test;
 
+/// The default prefix type used by the experiments, the evaluation binaries, and the tests. The
+/// decomposition, the specification, and the runtimes are generic over any [`bgpsim::types::Prefix`].
 pub use bgpsim::types::{RouterId, SimplePrefix as P};
-pub use decomposition::{decompose, Decomposition};
+pub use decomposition::{decompose, decompose_patch, Decomposition};
 
 #[cfg(feature = "experiment")]
 #[cfg_attr(docsrs, doc(cfg(feature = "experiment")))]
@@ -264,9 +268,9 @@ pub mod experiment {
         /// Specification used to build the specification
         pub spec_builder: Option<SpecificationBuilder>,
         /// Specification for the experiment`
-        pub spec: &'a Specification,
+        pub spec: &'a Specification<P>,
         /// Decomposed schedule for the experiment
-        pub decomp: Option<&'a Decomposition>,
+        pub decomp: Option<&'a Decomposition<P>>,
         /// Wether the configuration was randomized
         pub rand: bool,
         /// Data obtained during the experiment.
@@ -318,8 +322,8 @@ pub mod experiment {
                 topo: Option<TopologyZoo>,
                 scenario: &'b S,
                 spec_builder: Option<SpecificationBuilder>,
-                spec: &'a Specification,
-                decomp: Option<&'a Decomposition>,
+                spec: &'a Specification<P>,
+                decomp: Option<&'a Decomposition<P>>,
                 data: &'b T,
                 net: serde_json::Value,
             }
@@ -386,7 +390,7 @@ mod export_web {
 
     use super::decomposition::Decomposition;
     use super::P;
-    use atomic_command::{AtomicCommand, AtomicCondition, AtomicModifier};
+    use atomic_command::AtomicCommand;
     use bgpsim::{
         event::EventQueue,
         policies::{FwPolicy, Policy, PolicyError},
@@ -399,8 +403,8 @@ mod export_web {
     /// Export the network, the policies and the decomposition to a json file to import into `bgpsim-web`.
     pub fn export_web<Q>(
         net: &Network<P, Q>,
-        spec: &Specification,
-        decomp: Decomposition,
+        spec: &Specification<P>,
+        decomp: Decomposition<P>,
         filename: impl AsRef<str>,
     ) -> Result<(), Box<dyn std::error::Error>>
     where
@@ -424,11 +428,8 @@ mod export_web {
             cleanup_commands,
         ];
 
-        let instant_migration: Vec<Vec<Vec<AtomicCommand<P>>>> = vec![vec![vec![AtomicCommand {
-            command: AtomicModifier::Raw(original_command),
-            precondition: AtomicCondition::None,
-            postcondition: AtomicCondition::None,
-        }]]];
+        let instant_migration: Vec<Vec<Vec<AtomicCommand<P>>>> =
+            vec![Decomposition::baseline(original_command).main_commands];
 
         #[allow(clippy::type_complexity)]
         let mut policies: HashMap<
@@ -22,13 +22,13 @@ use ipnet::Ipv4Net;
 use itertools::Itertools;
 use rand::prelude::*;
 use serde::Serialize;
-use std::{collections::HashMap, net::Ipv4Addr};
+use std::{collections::HashMap, net::Ipv4Addr, path::PathBuf};
 
 use chameleon::{
     decompose,
     experiment::{Experiment, Scenario, _TopologyZoo},
     runtime::{self, lab::ExternalEvent},
-    specification::SpecificationBuilder,
+    specification::{self, SpecificationBuilder},
     P,
 };
 use bgpsim::{prelude::*, topology_zoo::TopologyZoo};
@@ -46,6 +46,10 @@ struct Cli {
     /// Specification to generate.
     #[clap(long = "spec", short = 's', default_value = "reachability")]
     spec_builder: SpecificationBuilder,
+    /// Read the specification from a file in the text format (see `chameleon::specification`)
+    /// instead of generating it.
+    #[clap(long = "spec-file")]
+    spec_file: Option<PathBuf>,
     /// Event (scenario) to generate.
     #[clap(long = "event", short = 'e', default_value = "del-best-route")]
     event: Scenario,
@@ -61,6 +65,13 @@ struct Cli {
     /// Use a randomized configuration
     #[clap(short, long)]
     rand: bool,
+    /// Write a report of the migration plan to the given file. The report is written as HTML if
+    /// the file ends with `.html`, and as Markdown otherwise.
+    #[clap(long = "report")]
+    report: Option<PathBuf>,
+    /// Only compute the migration plan (and the report) without executing it.
+    #[clap(long = "dry-run")]
+    dry_run: bool,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -71,9 +82,23 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let (mut net, p, command) = args
         .event
         .build(args.topo.0, BasicEventQueue::new(), args.rand)?;
-    let spec = args.spec_builder.build_all(&net, Some(&command), [p]);
+    let spec = match &args.spec_file {
+        Some(path) => {
+            let text = std::fs::read_to_string(path)?;
+            specification::parse_specification(&text, &net)
+                .inspect_err(|e| eprintln!("{}", e.annotate(&text)))?
+        }
+        None => args.spec_builder.build_all(&net, Some(&command), [p]),
+    };
     let decomp = decompose(&net, command, &spec)?;
 
+    if let Some(path) = &args.report {
+        decomp.write_report(&net, path)?;
+    }
+    if args.dry_run {
+        return Ok(());
+    }
+
     let failure = args.failure.map(|x| x.build(&mut net, p));
 
     // perform the simulation
@@ -113,7 +138,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     net: &net,
                     topo: Some(TOPO),
                     scenario: Some(args.event),
-                    spec_builder: Some(args.spec_builder),
+                    spec_builder: args.spec_file.is_none().then_some(args.spec_builder),
                     spec: &spec,
                     decomp: Some(&decomp),
                     rand: args.rand,
@@ -153,7 +178,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     net: &net,
                     topo: Some(TOPO),
                     scenario: Some(args.event),
-                    spec_builder: Some(args.spec_builder),
+                    spec_builder: args.spec_file.is_none().then_some(args.spec_builder),
                     spec: &spec,
                     decomp: Some(&decomp),
                     rand: args.rand,
@@ -235,6 +260,7 @@ impl UnexpectedEvent {
                         next_hop: e,
                         local_pref: None,
                         med: None,
+                        origin: Default::default(),
                         community: Default::default(),
                         originator_id: None,
                         cluster_list: Default::default(),
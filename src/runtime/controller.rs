@@ -17,100 +17,429 @@
 
 //! Controller and State Machine for the migration
 
-use std::{collections::HashMap, mem::take};
+#[cfg(feature = "serde")]
+use std::path::{Path, PathBuf};
+use std::{
+    collections::{HashMap, VecDeque},
+    mem::take,
+};
 
 use atomic_command::{AtomicCommand, AtomicCondition};
-use bgpsim::prelude::*;
+use bgpsim::{
+    config::{Config, ConfigModifier, NetworkConfig},
+    event::EventQueue,
+    prelude::*,
+    types::PrefixMap,
+};
 use itertools::Itertools;
+use thiserror::Error;
 
-use crate::{decomposition::Decomposition, P};
+use crate::{
+    decomposition::{self, ilp_scheduler::ScheduleOptions, Decomposition, DecompositionError},
+    specification::Specification,
+};
 
 /// The controller structure keeps track of the current step of the update, and checks if it is safe
 /// to perform the next change. If so, it will perform it.
 #[derive(Debug)]
-pub struct Controller {
+pub struct Controller<P: Prefix> {
     /// The command decomposition
-    pub decomp: Decomposition,
+    pub decomp: Decomposition<P>,
     /// The current state of the update
-    pub state: ControllerStage,
+    pub state: ControllerStage<P>,
+    /// The remaining stages of the rollback plan (after the current one). This is `None` unless
+    /// the update was aborted (see [`Controller::abort`]).
+    pub rollback: Option<VecDeque<ControllerStage<P>>>,
+    /// File to which the runtimes write a [`Checkpoint`] after every transition.
+    #[cfg(feature = "serde")]
+    pub checkpoint_file: Option<PathBuf>,
 }
 
-impl Controller {
+impl<P: Prefix> Controller<P> {
     /// Create a new controller in the initial state
-    pub fn new(mut decomp: Decomposition) -> Self {
+    pub fn new(mut decomp: Decomposition<P>) -> Self {
         let state = ControllerStage::setup(take(&mut decomp.setup_commands));
-        Self { decomp, state }
+        Self {
+            decomp,
+            state,
+            rollback: None,
+            #[cfg(feature = "serde")]
+            checkpoint_file: None,
+        }
+    }
+
+    /// Resume the update from a checkpoint (see [`Controller::checkpoint`]). The decomposition
+    /// must be the same as the one of the controller that created the checkpoint. Before
+    /// continuing, all pre- and postconditions of the current round are checked again on the live
+    /// network `net`: Commands waiting for their precondition whose configuration is already
+    /// present in `net` were applied after the checkpoint was written, and now wait for their
+    /// postcondition. Commands waiting for their postcondition are marked as done if it is now
+    /// satisfied, and commands that are done wait again for their postcondition if it no longer
+    /// holds. All remaining commands are only applied once their precondition is satisfied.
+    pub fn resume<Q>(
+        decomp: Decomposition<P>,
+        checkpoint: Checkpoint<P>,
+        net: &Network<P, Q>,
+    ) -> Result<Self, ResumeError>
+    where
+        Q: EventQueue<P>,
+    {
+        let mut controller = Self::new(decomp);
+        let Checkpoint {
+            mut state,
+            rollback,
+        } = checkpoint;
+
+        // skip all stages that were already executed, and check that the checkpoint matches the
+        // decomposition.
+        if rollback.is_none() {
+            while controller.state.index() < state.index() {
+                controller.next_stage();
+            }
+            if !controller.state.same_commands(&state) {
+                return Err(ResumeError::Mismatch(state.name()));
+            }
+        }
+
+        state.recheck(net)?;
+        log::info!("Resume the update in stage {}.", state.name());
+        controller.state = state;
+        controller.rollback = rollback;
+        Ok(controller)
+    }
+
+    /// Write a checkpoint to the given file after every transition of the controller.
+    #[cfg(feature = "serde")]
+    pub fn with_checkpoint_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.checkpoint_file = Some(path.into());
+        self
+    }
+
+    /// Create a checkpoint of the current progress, from which the update can be resumed (see
+    /// [`Controller::resume`]).
+    pub fn checkpoint(&self) -> Checkpoint<P> {
+        Checkpoint {
+            state: self.state.clone(),
+            rollback: self.rollback.clone(),
+        }
+    }
+
+    /// Store the current progress in the checkpoint file (if set). The runtimes call this function
+    /// after every transition, and stop the update if the checkpoint cannot be written.
+    pub(crate) fn save_checkpoint(&self) -> Result<(), CheckpointError> {
+        #[cfg(feature = "serde")]
+        if let Some(path) = self.checkpoint_file.as_ref() {
+            self.checkpoint().save(path)?;
+        }
+        Ok(())
+    }
+
+    /// Hand over to a new decomposition, discarding the remaining stages of the current one. The
+    /// controller continues with the setup stage of the new decomposition. The rollback plan of the
+    /// new decomposition only reverses the new commands, i.e., it returns the network to the state
+    /// in which the handover happened.
+    pub fn handover(&mut self, decomp: Decomposition<P>) {
+        let Self { decomp, state, .. } = Self::new(decomp);
+        self.decomp = decomp;
+        self.state = state;
+        self.rollback = None;
+    }
+
+    /// Re-plan the update from the current state of `net` towards the `target` configuration, and
+    /// hand over to the new decomposition (see [`decomposition::replan`] and
+    /// [`Controller::handover`]). The network must have converged.
+    pub fn replan<Q>(
+        &mut self,
+        net: &Network<P, Q>,
+        target: &Config<P>,
+        spec: &Specification<P>,
+        options: ScheduleOptions,
+    ) -> Result<(), DecompositionError<P>>
+    where
+        Q: EventQueue<P> + Clone,
+    {
+        log::info!("Re-plan the update in stage {}.", self.state.name());
+        let decomp =
+            decomposition::replan(net, target, &self.decomp.original_command, spec, options)?;
+        self.handover(decomp);
+        Ok(())
     }
 
     /// Get the decomposition of the command
-    pub fn decomposition(&self) -> &Decomposition {
+    pub fn decomposition(&self) -> &Decomposition<P> {
         &self.decomp
     }
 
     /// Get a reference to the current state.
-    pub fn state(&self) -> &ControllerStage {
+    pub fn state(&self) -> &ControllerStage<P> {
         &self.state
     }
 
-    /// Returns `true` if the controller has finished processing the update.
+    /// Returns `true` if the controller has finished processing the update (or the rollback, if
+    /// the update was aborted).
     pub fn is_finished(&self) -> bool {
         matches!(self.state, ControllerStage::Finished)
     }
 
-    /// Turn the current controller into a list of stages that still need to be performed.
-    #[cfg(feature = "cisco-lab")]
-    pub(crate) fn into_remaining_states(self) -> Vec<ControllerStage> {
-        let mut stages = Vec::new();
-        let mut state = self.state;
-        let Decomposition {
-            cleanup_commands,
-            atomic_before,
-            main_commands,
-            atomic_after,
-            ..
-        } = self.decomp;
-        if matches!(state, ControllerStage::Setup(_)) {
-            stages.push(state);
-            state = ControllerStage::update_before(atomic_before);
+    /// Returns `true` if the controller has applied any command, or if the update was aborted.
+    pub fn has_started(&self) -> bool {
+        match &self.state {
+            _ if self.is_aborted() => true,
+            ControllerStage::Setup(s) => {
+                s.round > 0
+                    || s.entries
+                        .iter()
+                        .any(|e| *e != AtomicCommandState::Precondition)
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns `true` if the update was aborted, and the controller is rolling back.
+    pub fn is_aborted(&self) -> bool {
+        self.rollback.is_some()
+    }
+
+    /// Proceed to the next stage, assuming that the current stage is done. After the update was
+    /// aborted, this proceeds to the next stage of the rollback plan.
+    pub(crate) fn next_stage(&mut self) {
+        self.state = if let Some(rollback) = self.rollback.as_mut() {
+            rollback.pop_front().unwrap_or(ControllerStage::Finished)
+        } else {
+            match self.state {
+                ControllerStage::Setup(_) => {
+                    ControllerStage::update_before(take(&mut self.decomp.atomic_before))
+                }
+                ControllerStage::UpdateBefore(_) => {
+                    ControllerStage::main(take(&mut self.decomp.main_commands))
+                }
+                ControllerStage::Main(_) => {
+                    ControllerStage::update_after(take(&mut self.decomp.atomic_after))
+                }
+                ControllerStage::UpdateAfter(_) => {
+                    ControllerStage::cleanup(take(&mut self.decomp.cleanup_commands))
+                }
+                ControllerStage::Cleanup(_) => ControllerStage::Finished,
+                ControllerStage::Finished => ControllerStage::Finished,
+            }
+        };
+    }
+
+    /// Abort the update and switch to the rollback plan (see
+    /// [`Rollback`](crate::decomposition::rollback::Rollback)). The rollback plan reverses all
+    /// commands that were applied so far in reverse order, starting with the current stage. In
+    /// the current round, only commands whose precondition was satisfied (and thus, which were
+    /// applied) are reversed. After the rollback is done, the network is back in its original
+    /// configuration, and the controller is finished.
+    ///
+    /// Aborting fails if the update is already finished, if it was already aborted, or if the
+    /// rollback plan could not be verified for some prefix (unless the controller is still in the
+    /// setup stage, where the forwarding state did not change).
+    pub fn abort(&mut self) -> Result<(), AbortError> {
+        if self.is_aborted() {
+            return Err(AbortError::AlreadyAborted);
         }
-        if matches!(state, ControllerStage::UpdateBefore(_)) {
-            stages.push(state);
-            state = ControllerStage::main(main_commands);
+        if self.is_finished() {
+            return Err(AbortError::Finished);
         }
-        if matches!(state, ControllerStage::Main(_)) {
-            stages.push(state);
-            state = ControllerStage::update_after(atomic_after);
+        let rollback = &self.decomp.rollback;
+        if !matches!(self.state, ControllerStage::Setup(_)) && !rollback.is_verified() {
+            return Err(AbortError::Unverified(rollback.unverified.len()));
         }
-        if matches!(state, ControllerStage::UpdateAfter(_)) {
-            stages.push(state);
-            state = ControllerStage::cleanup(cleanup_commands);
+
+        // the stage that is currently executed.
+        let current = self.state.index();
+
+        // reverse the current stage up to the current round
+        let mut stages = VecDeque::from([match &self.state {
+            ControllerStage::Setup(s) => {
+                ControllerStage::setup(s.rollback(&rollback.setup_commands))
+            }
+            ControllerStage::UpdateBefore(s) => ControllerStage::update_before(
+                s.iter()
+                    .map(|(p, s)| (*p, s.rollback(&rollback.atomic_before[p])))
+                    .collect(),
+            ),
+            ControllerStage::Main(s) => ControllerStage::main(s.rollback(&rollback.main_commands)),
+            ControllerStage::UpdateAfter(s) => ControllerStage::update_after(
+                s.iter()
+                    .map(|(p, s)| (*p, s.rollback(&rollback.atomic_after[p])))
+                    .collect(),
+            ),
+            ControllerStage::Cleanup(s) => {
+                ControllerStage::cleanup(s.rollback(&rollback.cleanup_commands))
+            }
+            ControllerStage::Finished => unreachable!(),
+        }]);
+
+        // then, completely reverse all previous stages
+        /// reverse the order of all rounds in a stage, removing empty rounds.
+        fn rev<P: Prefix>(stage: &[Vec<AtomicCommand<P>>]) -> Vec<Vec<AtomicCommand<P>>> {
+            stage
+                .iter()
+                .rev()
+                .filter(|r| !r.is_empty())
+                .cloned()
+                .collect()
         }
-        if matches!(state, ControllerStage::Cleanup(_)) {
-            stages.push(state);
+        for stage in (0..current).rev() {
+            stages.push_back(match stage {
+                0 => ControllerStage::setup(rev(&rollback.setup_commands)),
+                1 => ControllerStage::update_before(
+                    rollback
+                        .atomic_before
+                        .iter()
+                        .map(|(p, s)| (*p, rev(s)))
+                        .collect(),
+                ),
+                2 => ControllerStage::main(rev(&rollback.main_commands)),
+                _ => ControllerStage::update_after(
+                    rollback
+                        .atomic_after
+                        .iter()
+                        .map(|(p, s)| (*p, rev(s)))
+                        .collect(),
+                ),
+            });
         }
 
-        stages
+        log::info!(
+            "Abort the update and roll back from stage {}.",
+            self.state.name()
+        );
+        self.state = stages.pop_front().unwrap();
+        self.rollback = Some(stages);
+        Ok(())
+    }
+
+    /// Count the number of commands that may be executed from now on, including the rollback plan.
+    #[cfg(feature = "cisco-lab")]
+    pub(crate) fn count_commands(&self) -> usize {
+        /// count the commands of a stage.
+        fn count<P: Prefix>(stage: &[Vec<AtomicCommand<P>>]) -> usize {
+            stage.iter().map(|x| x.len()).sum()
+        }
+        let d = &self.decomp;
+        let r = &d.rollback;
+        self.state.count_commands()
+            + self
+                .rollback
+                .iter()
+                .flatten()
+                .map(|s| s.count_commands())
+                .sum::<usize>()
+            + count(&d.main_commands)
+            + count(&d.cleanup_commands)
+            + d.atomic_before.values().map(|s| count(s)).sum::<usize>()
+            + d.atomic_after.values().map(|s| count(s)).sum::<usize>()
+            + count(&r.setup_commands)
+            + count(&r.main_commands)
+            + count(&r.cleanup_commands)
+            + r.atomic_before.values().map(|s| count(s)).sum::<usize>()
+            + r.atomic_after.values().map(|s| count(s)).sum::<usize>()
+    }
+}
+
+/// Error when aborting the update.
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum AbortError {
+    /// The update has already finished.
+    #[error("The update has already finished")]
+    Finished,
+    /// The update was already aborted.
+    #[error("The update was already aborted")]
+    AlreadyAborted,
+    /// The rollback plan was not verified for some prefixes.
+    #[error("Rolling back may violate the specification for {0} prefixes")]
+    Unverified(usize),
+}
+
+/// Progress of the controller, from which the update can be resumed (see [`Controller::resume`]).
+/// The checkpoint stores the state of the current stage (including the state of each command in
+/// the current round), and the remaining stages of the rollback plan (if the update was aborted).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "P: for<'a> serde::Deserialize<'a>"))
+)]
+pub struct Checkpoint<P: Prefix> {
+    /// The current state of the update
+    pub state: ControllerStage<P>,
+    /// The remaining stages of the rollback plan (after the current one), if the update was
+    /// aborted.
+    pub rollback: Option<VecDeque<ControllerStage<P>>>,
+}
+
+#[cfg(feature = "serde")]
+impl<P: Prefix> Checkpoint<P> {
+    /// Write the checkpoint as JSON to the given file. The checkpoint is first written to a
+    /// temporary file, which then replaces the given file. Hence, the file always contains a
+    /// complete checkpoint, even if the process crashes while writing it.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, serde_json::to_string(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Read a checkpoint from a JSON file (see [`Checkpoint::save`]).
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CheckpointError>
+    where
+        P: for<'a> serde::Deserialize<'a>,
+    {
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
     }
 }
 
+/// Error when reading or writing a checkpoint.
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    /// Error while reading or writing the file.
+    #[error("IO Error: {0}")]
+    Io(#[from] std::io::Error),
+    /// Error while serializing or deserializing the checkpoint.
+    #[cfg(feature = "serde")]
+    #[error("Serialization error: {0}")]
+    Serde(#[from] serde_json::Error),
+}
+
+/// Error when resuming the update from a checkpoint.
+#[derive(Debug, Error)]
+pub enum ResumeError {
+    /// The commands of the checkpoint do not match the decomposition.
+    #[error("The checkpoint does not match the decomposition in stage {0}")]
+    Mismatch(&'static str),
+    /// Error while checking the conditions on the network.
+    #[error("{0}")]
+    NetworkError(#[from] NetworkError),
+}
+
 /// In which state is the controller currently in.
-#[derive(Debug)]
-pub enum ControllerStage {
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "P: for<'a> serde::Deserialize<'a>"))
+)]
+pub enum ControllerStage<P: Prefix> {
     /// The controller is currently setting up the network
-    Setup(StateItem),
+    Setup(StateItem<P>),
     /// The controller is currently performing the all updates before the main command
-    UpdateBefore(HashMap<P, StateItem>),
+    UpdateBefore(HashMap<P, StateItem<P>>),
     /// Performing the main update
-    Main(StateItem),
+    Main(StateItem<P>),
     /// The controller is currently performing the all updates after the main command
-    UpdateAfter(HashMap<P, StateItem>),
+    UpdateAfter(HashMap<P, StateItem<P>>),
     /// The controller is currently cleaning up the update.
-    Cleanup(StateItem),
+    Cleanup(StateItem<P>),
     /// the controller has finished performing the update
     Finished,
 }
 
-impl ControllerStage {
+impl<P: Prefix> ControllerStage<P> {
     /// Create a new setup stage.
     pub fn setup(commands: Vec<Vec<AtomicCommand<P>>>) -> Self {
         Self::Setup(StateItem {
@@ -216,6 +545,51 @@ impl ControllerStage {
         }
     }
 
+    /// Position of the stage in the update, starting with 0 for the setup stage.
+    fn index(&self) -> usize {
+        match self {
+            ControllerStage::Setup(_) => 0,
+            ControllerStage::UpdateBefore(_) => 1,
+            ControllerStage::Main(_) => 2,
+            ControllerStage::UpdateAfter(_) => 3,
+            ControllerStage::Cleanup(_) => 4,
+            ControllerStage::Finished => 5,
+        }
+    }
+
+    /// Check if both stages are of the same kind and contain the same commands.
+    fn same_commands(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ControllerStage::Setup(a), ControllerStage::Setup(b))
+            | (ControllerStage::Main(a), ControllerStage::Main(b))
+            | (ControllerStage::Cleanup(a), ControllerStage::Cleanup(b)) => {
+                a.commands == b.commands
+            }
+            (ControllerStage::UpdateBefore(a), ControllerStage::UpdateBefore(b))
+            | (ControllerStage::UpdateAfter(a), ControllerStage::UpdateAfter(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .all(|(p, a)| b.get(p).map(|b| a.commands == b.commands) == Some(true))
+            }
+            (ControllerStage::Finished, ControllerStage::Finished) => true,
+            _ => false,
+        }
+    }
+
+    /// Check the pre- and postconditions of all commands in the current round again on the network
+    /// (see [`Controller::resume`]).
+    fn recheck<Q: EventQueue<P>>(&mut self, net: &Network<P, Q>) -> Result<(), NetworkError> {
+        match self {
+            ControllerStage::Setup(s) | ControllerStage::Main(s) | ControllerStage::Cleanup(s) => {
+                s.recheck(net)
+            }
+            ControllerStage::UpdateBefore(s) | ControllerStage::UpdateAfter(s) => {
+                s.values_mut().try_for_each(|s| s.recheck(net))
+            }
+            ControllerStage::Finished => Ok(()),
+        }
+    }
+
     /// Return the name of the current stage.
     pub fn name(&self) -> &'static str {
         match self {
@@ -245,8 +619,13 @@ impl ControllerStage {
 }
 
 /// The state of a `Vec<Vec<AtomicCommand>>`
-#[derive(Debug)]
-pub struct StateItem {
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "P: for<'a> serde::Deserialize<'a>"))
+)]
+pub struct StateItem<P: Prefix> {
     /// The current round, as an index into the first array
     pub round: usize,
     /// A vector storing the state for each atomic command in the round.
@@ -255,7 +634,69 @@ pub struct StateItem {
     pub commands: Vec<Vec<AtomicCommand<P>>>,
 }
 
-impl StateItem {
+impl<P: Prefix> StateItem<P> {
+    /// Check the pre- and postconditions of all commands in the current round again on the network
+    /// (see [`Controller::resume`]).
+    fn recheck<Q: EventQueue<P>>(&mut self, net: &Network<P, Q>) -> Result<(), NetworkError> {
+        if let Some(cmds) = self.commands.get(self.round) {
+            let config = net.get_config()?;
+            for (cmd, state) in cmds.iter().zip(self.entries.iter_mut()) {
+                if matches!(state, AtomicCommandState::Precondition) && is_applied(&config, cmd) {
+                    log::warn!("Command was already applied: {}", cmd.command.fmt(net));
+                    *state = AtomicCommandState::Postcondition;
+                }
+                match state {
+                    AtomicCommandState::Precondition => {
+                        if !cmd.precondition.check(net)? {
+                            log::info!(
+                                "Precondition not yet satisfied: {}",
+                                cmd.precondition.fmt(net)
+                            );
+                        }
+                    }
+                    AtomicCommandState::Postcondition => {
+                        if cmd.postcondition.check(net)? {
+                            *state = AtomicCommandState::Done;
+                        }
+                    }
+                    AtomicCommandState::Done => {
+                        if !cmd.postcondition.check(net)? {
+                            log::warn!(
+                                "Postcondition no longer satisfied: {}",
+                                cmd.postcondition.fmt(net)
+                            );
+                            *state = AtomicCommandState::Postcondition;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the rounds of the rollback plan that reverse all commands applied so far, given the
+    /// reverse commands `rollback` of the entire stage (at the same position as the commands they
+    /// reverse). The returned rounds are in the order in which they must be executed, and empty
+    /// rounds are removed.
+    pub fn rollback(&self, rollback: &[Vec<AtomicCommand<P>>]) -> Vec<Vec<AtomicCommand<P>>> {
+        let mut rounds = rollback
+            .iter()
+            .take(self.round)
+            .cloned()
+            .collect::<Vec<_>>();
+        if let Some(current) = rollback.get(self.round) {
+            rounds.push(
+                current
+                    .iter()
+                    .zip(self.entries.iter())
+                    .filter(|(_, state)| !matches!(state, AtomicCommandState::Precondition))
+                    .map(|(cmd, _)| cmd.clone())
+                    .collect(),
+            );
+        }
+        rounds.into_iter().rev().filter(|r| !r.is_empty()).collect()
+    }
+
     /// Print a log of the Atomic Conditions, which consists of information needed to check if we
     /// can make any progress
     pub fn fmt_current_conditions<Q>(&self, net: &Network<P, Q>) -> String {
@@ -284,6 +725,21 @@ impl StateItem {
                             String::from("no route selected")
                         }
                     }
+                    AtomicCondition::NoSelectedRoute { router, prefix } => net
+                        .get_device(router)
+                        .unwrap_internal()
+                        .get_selected_bgp_route(prefix)
+                        .map(|rib| rib.fmt(net))
+                        .unwrap_or_else(|| String::from("no route selected")),
+                    AtomicCondition::NoReceivedRoute { router, prefix } => net
+                        .get_device(router)
+                        .unwrap_internal()
+                        .get_bgp_rib_in()
+                        .get(&prefix)
+                        .into_iter()
+                        .flat_map(|t| t.values())
+                        .map(|e| e.fmt(net))
+                        .join("\n                  "),
                     AtomicCondition::AvailableRoute { router, prefix, .. }
                     | AtomicCondition::RoutesLessPreferred { router, prefix, .. } => net
                         .get_device(router)
@@ -294,9 +750,12 @@ impl StateItem {
                         .flat_map(|t| t.values())
                         .map(|e| e.fmt(net))
                         .join("\n                  "),
-                    AtomicCondition::BgpSessionEstablished { .. } => {
-                        String::from("not established")
-                    }
+                    AtomicCondition::BgpSessionEstablished { router, neighbor } => net
+                        .get_bgp_session_state(router, neighbor)
+                        .ok()
+                        .flatten()
+                        .map(|state| state.to_string())
+                        .unwrap_or_else(|| String::from("not configured")),
                 };
 
                 result.push(format!(
@@ -318,9 +777,30 @@ impl StateItem {
     }
 }
 
+/// Check if all configuration modifiers of the command are already reflected in `config`, i.e., if
+/// the command was already applied.
+fn is_applied<P: Prefix>(config: &Config<P>, cmd: &AtomicCommand<P>) -> bool {
+    /// Check if a single modifier is reflected in `config`.
+    fn check<P: Prefix>(config: &Config<P>, modifier: ConfigModifier<P>) -> bool {
+        match modifier {
+            ConfigModifier::Insert(expr) | ConfigModifier::Update { to: expr, .. } => {
+                config.get(expr.key()) == Some(&expr)
+            }
+            ConfigModifier::Remove(expr) => config.get(expr.key()).is_none(),
+            ConfigModifier::BatchRouteMapEdit { router, updates } => updates
+                .into_iter()
+                .all(|u| check(config, u.into_modifier(router))),
+        }
+    }
+    Vec::<ConfigModifier<P>>::from(cmd.command.clone())
+        .into_iter()
+        .all(|m| check(config, m))
+}
+
 /// The state of a single atomic command. It can either be waiting for the precondition, waiting for
 /// the postcondition, or be executed successfully.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub enum AtomicCommandState {
     /// Waiting for the preconditions to be satisfied
     Precondition,
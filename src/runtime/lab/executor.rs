@@ -21,16 +21,21 @@ use std::{
     collections::{BTreeSet, HashMap, HashSet},
     net::Ipv4Addr,
     ops::DerefMut,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::Duration,
 };
 
 use atomic_command::{AtomicCommand, AtomicCondition};
 use bgpsim::{
     bgp::BgpRibEntry,
-    config::ConfigModifier,
+    config::{Config, ConfigModifier},
+    event::EventQueue,
     export::{Addressor, DefaultAddressor, ExportError, InternalCfgGen, MaybePec},
     prelude::*,
+    types::PrefixMap,
 };
 use cisco_lab::{
     router::{BgpPathType, BgpRoute, CiscoSession, CiscoShell, CiscoShellError},
@@ -38,7 +43,6 @@ use cisco_lab::{
 };
 use ipnet::Ipv4Net;
 use itertools::Itertools;
-use lazy_static::lazy_static;
 use log::info;
 use rand::prelude::*;
 #[cfg(feature = "serde")]
@@ -48,39 +52,46 @@ use tokio::{
     select,
     sync::{
         broadcast::{self, error::RecvError},
-        Mutex,
+        mpsc, Mutex,
     },
     task::{spawn, JoinHandle},
-    time::{sleep_until, Instant},
+    time::{sleep, sleep_until, Instant},
 };
 
 use crate::{
-    runtime::controller::{Controller, ControllerStage, StateItem},
-    P,
+    decomposition::ilp_scheduler::ScheduleOptions,
+    runtime::controller::{AtomicCommandState, Controller, ControllerStage, StateItem},
+    specification::Specification,
 };
 
-use super::LabError;
+use super::{LabError, LabPrefix};
 
 /// The interval by which to check for pre- or postconditions.
 const CHECK_INTERVAL: Duration = Duration::from_millis(500);
 /// Two minutes timeout, until we say we cannot progress.
 const TIMEOUT: Duration = Duration::from_secs(60);
+/// Time to wait for the network to converge after an external event, before re-planning the
+/// update, or after aborting the update, before rolling it back.
+const CONVERGENCE_TIME: Duration = Duration::from_secs(10);
 /// Number of networks to prove when checking for a condition on  a prefix equivalence class.
 ///
 /// This module will always check the first and last network (in alphabetical order). Further, it
 /// will check `PEC_NUM_CHECK - 2` random networks.
 const PEC_NUM_CHECK: usize = 10;
 
-lazy_static! {
-    static ref EVENT_LOG: Arc<Mutex<Vec<Event>>> = Arc::new(Mutex::new(Vec::new()));
-}
+/// Shared event log, to which all runners append their events.
+type EventLog<P> = Arc<Mutex<Vec<Event<P>>>>;
+
+/// Sender over which the tasks executing a stage report their progress. Each message contains
+/// the prefix (if the stage is parallelized per prefix) and the current state of that stage.
+type ProgressSender<P> = mpsc::UnboundedSender<(Option<P>, StateItem<P>)>;
 
 /// Event log entry
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize))]
-pub struct Event {
+pub struct Event<P: Prefix> {
     /// Job ID
-    pub id: JobId,
+    pub id: JobId<P>,
     /// Time when the event occurred,
     #[cfg_attr(feature = "serde", serde(with = "time::serde::rfc3339"))]
     pub time: OffsetDateTime,
@@ -143,20 +154,156 @@ impl KillChannel {
     }
 }
 
-impl Controller {
+/// Handle to abort the update while it is executed on the lab (see
+/// [`Controller::execute_lab_with_abort`]), or to re-plan it (see
+/// [`Controller::execute_lab_with_replan`]). The handle can be cloned and shared with other
+/// threads.
+#[derive(Debug, Clone, Default)]
+pub struct AbortHandle(Arc<AtomicBool>);
+
+impl AbortHandle {
+    /// Create a new handle that is not yet triggered.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trigger the abort. The executor will complete the rounds that are currently executed, and
+    /// then switch to the rollback plan.
+    pub fn abort(&self) {
+        self.0.store(true, Ordering::SeqCst)
+    }
+
+    /// Returns `true` if the abort was triggered.
+    pub fn is_aborted(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// State required to re-plan the update on the lab (see [`Controller::execute_lab_with_replan`]).
+struct LabReplan<'r, P: Prefix, Q, F> {
+    /// Simulated model of the network, to which all commands are applied that are done.
+    model: Network<P, Q>,
+    /// Target configuration of the update.
+    target: &'r Config<P>,
+    /// Specification that must be satisfied by the new decomposition.
+    spec: &'r Specification<P>,
+    /// Options for scheduling the new decomposition.
+    options: ScheduleOptions,
+    /// Handle that is triggered as soon as the external event happens.
+    trigger: AbortHandle,
+    /// Function that applies the external event to the `model`. It is `None` after the event was
+    /// applied.
+    event: Option<F>,
+    /// Whether the update is re-planned as soon as the rollback has finished.
+    after_rollback: bool,
+    /// Number of times the update was re-planned.
+    num_replans: usize,
+}
+
+impl<P: LabPrefix> Controller<P> {
     /// Perform the complete migration (all stages) in parallel using the parallel executor.
     pub async fn execute_lab<'a, 'n: 'a, Q>(
         self,
         lab: &'a mut CiscoLab<'n, P, Q, Active>,
         net: &Network<P, Q>,
-    ) -> Result<Vec<Event>, LabError> {
-        // clear the event log.
-        EVENT_LOG.lock().await.clear();
+    ) -> Result<Vec<Event<P>>, LabError>
+    where
+        Q: EventQueue<P> + Clone,
+    {
+        self.execute_lab_with_abort(lab, net, AbortHandle::new())
+            .await
+            .map(|(log, _)| log)
+    }
 
-        let stages = self.into_remaining_states();
+    /// Perform the migration in parallel using the parallel executor, like
+    /// [`Controller::execute_lab`]. As soon as `abort` is triggered, the executor completes the
+    /// rounds that are currently executed, aborts the update (see [`Controller::abort`]), waits for
+    /// the network to converge, and executes the rollback plan. The function returns the event log,
+    /// and whether the update was rolled back.
+    pub async fn execute_lab_with_abort<'a, 'n: 'a, Q>(
+        self,
+        lab: &'a mut CiscoLab<'n, P, Q, Active>,
+        net: &Network<P, Q>,
+        abort: AbortHandle,
+    ) -> Result<(Vec<Event<P>>, bool), LabError>
+    where
+        Q: EventQueue<P> + Clone,
+    {
+        self.execute_lab_inner(
+            lab,
+            net,
+            abort,
+            None::<LabReplan<'_, P, Q, fn(&mut Network<P, Q>) -> Result<(), NetworkError>>>,
+        )
+        .await
+        .map(|(log, rolled_back, _)| (log, rolled_back))
+    }
+
+    /// Perform the migration in parallel using the parallel executor, like
+    /// [`Controller::execute_lab`], and re-plan the update as soon as `trigger` is triggered by an
+    /// unexpected external event. The executor keeps a simulated `model` of the network, which
+    /// must be in the state of the lab when calling this function, and to which it applies each
+    /// command that is done.
+    ///
+    /// When `trigger` is triggered, the executor completes the rounds that are currently executed
+    /// and waits for the network to converge. Then, it applies the function `event` to the model,
+    /// computes a new decomposition from the state of the model towards the `target`
+    /// configuration, and hands over to it (see [`Controller::replan`]). If the update cannot be
+    /// re-planned from the current state, the executor first executes the rollback plan (see
+    /// [`Controller::abort`]), and re-plans the update from the state reached after the rollback.
+    /// The function returns the event log, and the number of times the update was re-planned.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn execute_lab_with_replan<'a, 'n: 'a, Q, F>(
+        self,
+        lab: &'a mut CiscoLab<'n, P, Q, Active>,
+        net: &Network<P, Q>,
+        model: Network<P, Q>,
+        target: &Config<P>,
+        spec: &Specification<P>,
+        options: ScheduleOptions,
+        trigger: AbortHandle,
+        event: F,
+    ) -> Result<(Vec<Event<P>>, usize), LabError>
+    where
+        Q: EventQueue<P> + Clone,
+        F: FnOnce(&mut Network<P, Q>) -> Result<(), NetworkError>,
+    {
+        let replan = LabReplan {
+            model,
+            target,
+            spec,
+            options,
+            trigger,
+            event: Some(event),
+            after_rollback: false,
+            num_replans: 0,
+        };
+        self.execute_lab_inner(lab, net, AbortHandle::new(), Some(replan))
+            .await
+            .map(|(log, _, num_replans)| (log, num_replans))
+    }
+
+    /// Perform the migration in parallel using the parallel executor, aborting it as soon as
+    /// `abort` is triggered, and re-planning it as described by `replan`. The function returns the
+    /// event log, whether the update was rolled back, and the number of times the update was
+    /// re-planned.
+    async fn execute_lab_inner<'a, 'n: 'a, Q, F>(
+        mut self,
+        lab: &'a mut CiscoLab<'n, P, Q, Active>,
+        net: &Network<P, Q>,
+        abort: AbortHandle,
+        mut replan: Option<LabReplan<'_, P, Q, F>>,
+    ) -> Result<(Vec<Event<P>>, bool, usize), LabError>
+    where
+        Q: EventQueue<P> + Clone,
+        F: FnOnce(&mut Network<P, Q>) -> Result<(), NetworkError>,
+    {
+        // create the event log.
+        let log: EventLog<P> = Arc::new(Mutex::new(Vec::new()));
 
         let num_routers = net.get_routers().len();
-        let queue_size = stages.iter().map(|s| s.count_commands()).sum();
+        // a new decomposition may contain more commands than the current one.
+        let queue_size = self.count_commands() * if replan.is_some() { 2 } else { 1 };
         let mut idx = 0;
 
         let c_kill = KillChannel::new(num_routers);
@@ -191,48 +338,126 @@ impl Controller {
             c_kill.clone(),
         )?;
 
-        for stage in stages {
-            info!("Executing stage {} in parallel...", stage.name());
-            match stage {
+        while !self.is_finished() {
+            if self.is_aborted() {
+                info!("Rolling back stage {} in parallel...", self.state.name());
+            } else {
+                info!("Executing stage {} in parallel...", self.state.name());
+            }
+            // the rollback plan cannot be aborted, and it is not interrupted to re-plan the update.
+            let mut stop = Vec::new();
+            if !self.is_aborted() {
+                stop.push(abort.clone());
+                stop.extend(
+                    replan
+                        .as_ref()
+                        .filter(|r| r.event.is_some())
+                        .map(|r| r.trigger.clone()),
+                );
+            }
+            let (c_progress_tx, mut c_progress_rx) = mpsc::unbounded_channel();
+
+            // spawn a task for the stage (or one for each prefix).
+            let items: Vec<(Option<P>, StateItem<P>)> = match &self.state {
                 ControllerStage::Setup(s)
                 | ControllerStage::Main(s)
                 | ControllerStage::Cleanup(s) => {
-                    execute_stage(
-                        net,
-                        lab,
-                        s,
-                        None,
-                        &pec_addresses,
-                        &mut idx,
-                        c_jobs_tx.clone(),
-                        c_done_rx.resubscribe(),
-                        c_kill.clone(),
-                    )?
-                    .await
-                    .map_err(|e| LabErrorToKill(LabError::ThreadError(e), c_kill.tx.clone()))??;
+                    vec![(None, s.clone())]
                 }
                 ControllerStage::UpdateBefore(s) | ControllerStage::UpdateAfter(s) => {
-                    execute_prefix_stage(
-                        net,
-                        lab,
-                        s,
-                        &pec_addresses,
-                        &mut idx,
-                        c_jobs_tx.clone(),
-                        c_done_rx.resubscribe(),
-                        c_kill.clone(),
-                    )
-                    .await?;
+                    s.iter().map(|(p, s)| (Some(*p), s.clone())).collect()
                 }
-                ControllerStage::Finished => {}
+                ControllerStage::Finished => Vec::new(),
+            };
+            let mut jobs = Vec::new();
+            for (prefix, s) in items {
+                jobs.push(execute_stage(
+                    net,
+                    lab,
+                    s,
+                    prefix,
+                    stop.clone(),
+                    &pec_addresses,
+                    &log,
+                    &mut idx,
+                    c_jobs_tx.clone(),
+                    c_done_rx.resubscribe(),
+                    c_kill.clone(),
+                    c_progress_tx.clone(),
+                )?);
+            }
+            drop(c_progress_tx);
+
+            // update the state (and the model) and store a checkpoint whenever a task makes
+            // progress.
+            while let Some((prefix, s)) = c_progress_rx.recv().await {
+                let item = match (&mut self.state, prefix) {
+                    (ControllerStage::Setup(x), None)
+                    | (ControllerStage::Main(x), None)
+                    | (ControllerStage::Cleanup(x), None) => x,
+                    (ControllerStage::UpdateBefore(x), Some(p))
+                    | (ControllerStage::UpdateAfter(x), Some(p)) => x.get_mut(&p).unwrap(),
+                    _ => unreachable!(),
+                };
+                if let Some(r) = replan.as_mut() {
+                    apply_progress(&mut r.model, item, &s)
+                        .map_err(|e| LabErrorToKill(e.into(), c_kill.tx.clone()))?;
+                }
+                *item = s;
+                self.save_checkpoint()
+                    .map_err(|e| LabErrorToKill(LabError::Checkpoint(e), c_kill.tx.clone()))?;
+            }
+            for job in jobs {
+                job.await
+                    .map_err(|e| LabErrorToKill(LabError::ThreadError(e), c_kill.tx.clone()))??;
             }
+
+            let triggered = replan
+                .as_ref()
+                .map(|r| r.event.is_some() && r.trigger.is_aborted())
+                .unwrap_or(false);
+            if !self.is_aborted() && abort.is_aborted() {
+                self.abort()
+                    .map_err(|e| LabErrorToKill(LabError::Abort(e), c_kill.tx.clone()))?;
+                info!("Wait for the network to converge before rolling back the update...");
+                sleep(CONVERGENCE_TIME).await;
+            } else if !self.is_aborted() && triggered {
+                // wait for the network to converge, and re-plan from the current state.
+                let r = replan.as_mut().unwrap();
+                info!("Wait for the network to converge before re-planning the update...");
+                sleep(CONVERGENCE_TIME).await;
+                (r.event.take().unwrap())(&mut r.model)
+                    .map_err(|e| LabErrorToKill(e.into(), c_kill.tx.clone()))?;
+                self.replan_lab(r)
+                    .map_err(|e| LabErrorToKill(e, c_kill.tx.clone()))?;
+            } else {
+                self.next_stage();
+                if let Some(r) = replan.as_mut().filter(|r| r.after_rollback) {
+                    if self.is_finished() {
+                        // the rollback has finished. Re-plan the update from the current state.
+                        r.after_rollback = false;
+                        self.replan_lab(r)
+                            .map_err(|e| LabErrorToKill(e, c_kill.tx.clone()))?;
+                    }
+                }
+            }
+            self.save_checkpoint()
+                .map_err(|e| LabErrorToKill(LabError::Checkpoint(e), c_kill.tx.clone()))?;
+        }
+        if self.is_aborted() {
+            info!("Rollback complete!");
+        } else {
+            info!("Migration complete!");
         }
-        info!("Migration complete!");
 
         // send the kill command
         let _ = c_kill.send();
         // await all runners
-        let mut result = Ok(std::mem::take(EVENT_LOG.lock().await.deref_mut()));
+        let mut result = Ok((
+            std::mem::take(log.lock().await.deref_mut()),
+            self.is_aborted(),
+            replan.map(|r| r.num_replans).unwrap_or_default(),
+        ));
         for runner in runners {
             match runner.await {
                 Ok(Ok(_)) => {}
@@ -242,14 +467,57 @@ impl Controller {
         }
         result
     }
+
+    /// Re-plan the update from the state of the model (see [`Controller::replan`]). If the update
+    /// cannot be re-planned from the current state, abort it, and re-plan it as soon as the
+    /// rollback has finished.
+    fn replan_lab<Q, F>(&mut self, replan: &mut LabReplan<'_, P, Q, F>) -> Result<(), LabError>
+    where
+        Q: EventQueue<P> + Clone,
+    {
+        match self.replan(&replan.model, replan.target, replan.spec, replan.options) {
+            Ok(()) => {
+                replan.num_replans += 1;
+                Ok(())
+            }
+            Err(e) if self.has_started() && !self.is_finished() => {
+                log::warn!("Cannot re-plan from the current state: {e}");
+                self.abort()?;
+                log::warn!("Roll back the update before re-planning it.");
+                replan.after_rollback = true;
+                Ok(())
+            }
+            Err(e) => Err(LabError::Replan(e.to_string())),
+        }
+    }
+}
+
+/// Apply all commands of a stage to the `model` that are applied in the `new` state of the stage,
+/// but not yet in the `old` one.
+fn apply_progress<P: Prefix, Q: EventQueue<P>>(
+    model: &mut Network<P, Q>,
+    old: &StateItem<P>,
+    new: &StateItem<P>,
+) -> Result<(), NetworkError> {
+    let is_applied = |s: &StateItem<P>, round: usize, i: usize| {
+        round < s.round || (round == s.round && s.entries[i] != AtomicCommandState::Precondition)
+    };
+    for (round, cmds) in new.commands.iter().enumerate().skip(old.round) {
+        for (i, cmd) in cmds.iter().enumerate() {
+            if is_applied(new, round, i) && !is_applied(old, round, i) {
+                cmd.command.apply(model)?;
+            }
+        }
+    }
+    Ok(())
 }
 
 /// Start all shells and return a vector of join handles.
-fn start_runners<Q>(
+fn start_runners<P: LabPrefix, Q>(
     net: &Network<P, Q>,
     lab: &CiscoLab<'_, P, Q, Active>,
-    c_jobs: broadcast::Receiver<Job>,
-    c_done: broadcast::Sender<JobId>,
+    c_jobs: broadcast::Receiver<Job<P>>,
+    c_done: broadcast::Sender<JobId<P>>,
     c_kill: KillChannel,
 ) -> Result<Vec<JoinHandle<Result<(), LabError>>>, LabErrorToKill> {
     let mut jobs = Vec::new();
@@ -266,59 +534,40 @@ fn start_runners<Q>(
     Ok(jobs)
 }
 
-/// Execute a stage that is parallelized per prefix.
-#[allow(clippy::too_many_arguments)]
-async fn execute_prefix_stage<'a, 'n: 'a, Q>(
-    net: &Network<P, Q>,
-    lab: &'a mut CiscoLab<'n, P, Q, Active>,
-    stage: HashMap<P, StateItem>,
-    pec_addresses: &HashMap<P, Vec<Ipv4Net>>,
-    idx: &mut usize,
-    c_jobs: broadcast::Sender<Job>,
-    c_done: broadcast::Receiver<JobId>,
-    c_kill: KillChannel,
-) -> Result<(), LabError> {
-    let mut jobs = Vec::new();
-    for (p, stage) in stage {
-        jobs.push(execute_stage(
-            net,
-            lab,
-            stage,
-            Some(p),
-            pec_addresses,
-            idx,
-            c_jobs.clone(),
-            c_done.resubscribe(),
-            c_kill.clone(),
-        )?);
-    }
-    for job in jobs {
-        job.await
-            .map_err(|e| LabErrorToKill(LabError::ThreadError(e), c_kill.tx.clone()))??;
-    }
-
-    Ok(())
-}
-
-/// Create a single task that executes the entire stage.
+/// Create a single task that executes the entire stage. Before each round, the task checks if any
+/// handle in `stop` was triggered. If so, it stops executing the stage. Commands of the current
+/// round that are already done are skipped, and for those waiting on their postcondition, only the
+/// postcondition is checked. The task sends the new state of the stage over `c_progress` after
+/// each completed command and each completed round.
 #[allow(clippy::too_many_arguments)]
-fn execute_stage<'a, 'n: 'a, Q>(
+fn execute_stage<'a, 'n: 'a, P: LabPrefix, Q>(
     net: &Network<P, Q>,
     lab: &'a mut CiscoLab<'n, P, Q, Active>,
-    stage: StateItem,
+    mut stage: StateItem<P>,
     prefix: Option<P>,
+    stop: Vec<AbortHandle>,
     pec_addresses: &HashMap<P, Vec<Ipv4Net>>,
+    log: &EventLog<P>,
     idx: &mut usize,
-    c_jobs: broadcast::Sender<Job>,
-    mut c_done: broadcast::Receiver<JobId>,
+    c_jobs: broadcast::Sender<Job<P>>,
+    mut c_done: broadcast::Receiver<JobId<P>>,
     mut c_kill: KillChannel,
+    c_progress: ProgressSender<P>,
 ) -> Result<JoinHandle<Result<(), LabError>>, LabErrorToKill> {
     let mut steps_jobs = Vec::new();
     // iterate over all steps in the stage
-    for step in stage.commands.iter() {
+    for (round, step) in stage.commands.iter().enumerate().skip(stage.round) {
         let mut jobs = Vec::new();
         // iterate ovewr all commands of that step
-        for cmd in step {
+        for (i, cmd) in step.iter().enumerate() {
+            let cmd_state = if round == stage.round {
+                stage.entries[i]
+            } else {
+                AtomicCommandState::Precondition
+            };
+            if cmd_state == AtomicCommandState::Done {
+                continue;
+            }
             // iterate over all routers for that command
             for r in cmd.command.routers() {
                 if net.get_device(r).is_external() {
@@ -329,34 +578,42 @@ fn execute_stage<'a, 'n: 'a, Q>(
                 // get the generator and addressor to create the command.
                 let (gen, addressor) = lab.get_router_cfg_gen(r).map_err(|e| (e, &c_kill))?;
 
-                jobs.push(Job {
-                    id: (r, prefix, *idx),
-                    cmd: Vec::<ConfigModifier<P>>::from(cmd.command.clone())
-                        .into_iter()
-                        .filter(|c| c.routers().contains(&r))
-                        .map(|c| gen.generate_command(net, addressor, c))
-                        .collect::<Result<_, _>>()
+                jobs.push((
+                    i,
+                    Job {
+                        id: (r, prefix, *idx),
+                        cmd: Vec::<ConfigModifier<P>>::from(cmd.command.clone())
+                            .into_iter()
+                            .filter(|c| c.routers().contains(&r))
+                            .map(|c| gen.generate_command(net, addressor, c))
+                            .collect::<Result<_, _>>()
+                            .map_err(|e| (e, &c_kill))?,
+                        cmd_repr: cmd.command.fmt(net),
+                        pre: LabCondition::translate(
+                            &cmd.precondition,
+                            r,
+                            net,
+                            addressor,
+                            pec_addresses,
+                        )
+                        .map_err(|e| (e, &c_kill))?,
+                        post: LabCondition::translate(
+                            &cmd.postcondition,
+                            r,
+                            net,
+                            addressor,
+                            pec_addresses,
+                        )
                         .map_err(|e| (e, &c_kill))?,
-                    cmd_repr: cmd.command.fmt(net),
-                    pre: LabCondition::translate(
-                        &cmd.precondition,
-                        r,
-                        net,
-                        addressor,
-                        pec_addresses,
-                    )
-                    .map_err(|e| (e, &c_kill))?,
-                    post: LabCondition::translate(
-                        &cmd.postcondition,
-                        r,
-                        net,
-                        addressor,
-                        pec_addresses,
-                    )
-                    .map_err(|e| (e, &c_kill))?,
-                    state: JobState::Pre,
-                    command: cmd.clone(),
-                });
+                        state: if cmd_state == AtomicCommandState::Postcondition {
+                            JobState::Post
+                        } else {
+                            JobState::Pre
+                        },
+                        command: cmd.clone(),
+                        log: log.clone(),
+                    },
+                ));
             }
         }
         steps_jobs.push(jobs);
@@ -364,24 +621,65 @@ fn execute_stage<'a, 'n: 'a, Q>(
 
     // now, create a task to execute the stage
     Ok(spawn(async move {
-        for (i, jobs) in steps_jobs.into_iter().enumerate() {
+        for jobs in steps_jobs {
+            if stop.iter().any(|a| a.is_aborted()) {
+                info!(
+                    "Stop executing the stage{} due to abort or re-plan",
+                    prefix.map(|p| format!(" for {p}")).unwrap_or_default()
+                );
+                break;
+            }
             info!(
                 "Executing step {}{}",
-                i,
+                stage.round,
                 prefix.map(|p| format!(" for {p}")).unwrap_or_default()
             );
-            execute_jobs(jobs, &c_jobs, &mut c_done, &mut c_kill).await?;
+            // number of pending jobs for each command of the round.
+            let mut pending: HashMap<usize, usize> = HashMap::new();
+            let mut job_cmd: HashMap<JobId<P>, usize> = HashMap::new();
+            for (i, job) in jobs.iter() {
+                *pending.entry(*i).or_default() += 1;
+                job_cmd.insert(job.id, *i);
+            }
+            // commands without any job (only on external routers) are done immediately.
+            for (i, entry) in stage.entries.iter_mut().enumerate() {
+                if !pending.contains_key(&i) {
+                    *entry = AtomicCommandState::Done;
+                }
+            }
+            let jobs = jobs.into_iter().map(|(_, job)| job).collect();
+            execute_jobs(jobs, &c_jobs, &mut c_done, &mut c_kill, |id| {
+                if let Some(i) = job_cmd.get(&id).copied() {
+                    let n = pending.get_mut(&i).unwrap();
+                    *n -= 1;
+                    if *n == 0 {
+                        stage.entries[i] = AtomicCommandState::Done;
+                        let _ = c_progress.send((prefix, stage.clone()));
+                    }
+                }
+            })
+            .await?;
+            stage.round += 1;
+            stage.entries = stage
+                .commands
+                .get(stage.round)
+                .into_iter()
+                .flatten()
+                .map(|_| AtomicCommandState::Precondition)
+                .collect();
+            let _ = c_progress.send((prefix, stage.clone()));
         }
         Ok(())
     }))
 }
 
 /// Execute a set of jobs concurrently.
-async fn execute_jobs(
-    jobs: Vec<Job>,
-    c_jobs: &broadcast::Sender<Job>,
-    c_done: &mut broadcast::Receiver<JobId>,
+async fn execute_jobs<P: LabPrefix>(
+    jobs: Vec<Job<P>>,
+    c_jobs: &broadcast::Sender<Job<P>>,
+    c_done: &mut broadcast::Receiver<JobId<P>>,
     c_kill: &mut KillChannel,
+    mut on_done: impl FnMut(JobId<P>),
 ) -> Result<(), LabErrorToKill> {
     // spawn all threads and wait for all of them to complete.
     let mut ids = HashSet::new();
@@ -406,7 +704,9 @@ async fn execute_jobs(
             }
             r = c_done.recv() => {
                 let id = r.map_err(|e| (e, c_kill.tx.clone()))?;
-                ids.remove(&id);
+                if ids.remove(&id) {
+                    on_done(id);
+                }
             }
             _ = sleep_until(deadline) => {
                 // send the kill command
@@ -420,35 +720,35 @@ async fn execute_jobs(
 }
 
 /// Job runner on a single router.
-async fn runner(
+async fn runner<P: LabPrefix>(
     session: CiscoSession,
     router: RouterId,
-    c_jobs: broadcast::Receiver<Job>,
-    c_done: broadcast::Sender<JobId>,
+    c_jobs: broadcast::Receiver<Job<P>>,
+    c_done: broadcast::Sender<JobId<P>>,
     c_kill: KillChannel,
 ) -> Result<(), LabError> {
     Ok(_runner(session, router, c_jobs, c_done, c_kill).await?)
 }
 
 /// Job runner on a single router, where each error must be unwrapped to send the kill command.
-async fn _runner(
+async fn _runner<P: LabPrefix>(
     session: CiscoSession,
     router: RouterId,
-    mut c_jobs: broadcast::Receiver<Job>,
-    c_done: broadcast::Sender<JobId>,
+    mut c_jobs: broadcast::Receiver<Job<P>>,
+    c_done: broadcast::Sender<JobId<P>>,
     mut c_kill: KillChannel,
 ) -> Result<(), LabErrorToKill> {
     let mut shell = session.shell().await.map_err(|e| (e, &c_kill))?;
-    let mut running_jobs: Vec<Job> = Vec::new();
+    let mut running_jobs: Vec<Job<P>> = Vec::new();
 
     let mut deadline = Instant::now() + CHECK_INTERVAL;
 
     /// Process all jobs. This means getting the current set of routes, processing all jobs,
     /// removing those that are finished, and sending the ID of finished jobs back over the channel.
-    async fn process_jobs(
+    async fn process_jobs<P: LabPrefix>(
         shell: &mut CiscoShell,
-        jobs: &mut Vec<Job>,
-        c_done: &broadcast::Sender<JobId>,
+        jobs: &mut Vec<Job<P>>,
+        c_done: &broadcast::Sender<JobId<P>>,
         c_kill: &KillChannel,
     ) -> Result<(), LabErrorToKill> {
         // early exit if jobs is empty
@@ -510,28 +810,30 @@ async fn _runner(
 }
 
 /// Job Identification
-type JobId = (RouterId, Option<P>, usize);
+type JobId<P> = (RouterId, Option<P>, usize);
 
 /// Arguments to the job
 #[derive(Clone, Debug)]
-struct Job {
+struct Job<P: Prefix> {
     /// The job identification
-    id: JobId,
+    id: JobId<P>,
     /// Command to apply (as a string)
     cmd: String,
     /// Representation of the command as a string, for logging
     cmd_repr: String,
     /// Precondition before applying the command
-    pre: LabCondition,
+    pre: LabCondition<P>,
     /// Postcondition after applying the command.
-    post: LabCondition,
+    post: LabCondition<P>,
     /// State of the job.
     state: JobState,
     /// The original command
     command: AtomicCommand<P>,
+    /// The event log to which events of this job are appended.
+    log: EventLog<P>,
 }
 
-impl Job {
+impl<P: Prefix> Job<P> {
     /// Process the job. The function returns if the job is complete.
     async fn process(
         &mut self,
@@ -574,13 +876,13 @@ impl Job {
 }
 
 /// logging helpe rfunctions
-impl Job {
+impl<P: Prefix> Job<P> {
     /// Create a log entry
     async fn log(&self, event: EventKind, name: &str) {
         let time = OffsetDateTime::now_local()
             .ok()
             .unwrap_or_else(OffsetDateTime::now_utc);
-        let mut logs = EVENT_LOG.lock().await;
+        let mut logs = self.log.lock().await;
         let elapsed_secs = logs
             .first()
             .map(|l| (time - l.time).as_seconds_f64())
@@ -625,7 +927,7 @@ impl Job {
     }
 }
 
-impl std::fmt::Display for Job {
+impl<P: Prefix> std::fmt::Display for Job<P> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
@@ -667,7 +969,7 @@ impl Default for JobState {
 
 /// The atomic Condition, translated to a form that can be checked using a [`CiscoShell`];
 #[derive(Debug, Clone)]
-enum LabCondition {
+enum LabCondition<P: Prefix> {
     /// No condition necessary. This condition is satisfied automatically.
     None,
     /// Condition on the current RIB entry (selected route) of a router and a prefix. This condition
@@ -685,6 +987,16 @@ enum LabCondition {
         /// The selected route has a given next-hop. If `None`, then the next-hop is ignored.
         next_hop: Option<Ipv4Addr>,
     },
+    /// Condition that no route for this prefix is selected.
+    NoSelectedRoute {
+        /// Which prefixes should be checked
+        prefixes: MaybePec<Ipv4Net>,
+    },
+    /// Condition that no route for this prefix is known.
+    NoReceivedRoute {
+        /// Which prefixes should be checked
+        prefixes: MaybePec<Ipv4Net>,
+    },
     /// Condition on the availability of a given route. It implies that there exists at least one
     /// route that is from either one of the given neighbors, and that contains all given community
     /// values. If both options are `None`, then it just asserts that a route for this prefix is
@@ -720,7 +1032,7 @@ enum LabCondition {
     },
 }
 
-impl LabCondition {
+impl<P: Prefix> LabCondition<P> {
     /// translate an `AtomicCondition` to an `LabCondition`.
     ///
     /// The `pec_addrsses` is a lookup for prefix equivalence classes, and which networks to
@@ -733,7 +1045,7 @@ impl LabCondition {
         pec_addresses: &HashMap<P, Vec<Ipv4Net>>,
     ) -> Result<Self, ExportError> {
         /// compute the prefix from the addressor
-        fn get_prefixes<Q>(
+        fn get_prefixes<P: Prefix, Q>(
             prefix: &P,
             addressor: &mut DefaultAddressor<'_, P, Q>,
             pec_addresses: &HashMap<P, Vec<Ipv4Net>>,
@@ -746,7 +1058,7 @@ impl LabCondition {
         }
 
         /// transform all neighbors using `get_router_addr`.
-        fn get_neighbors<Q>(
+        fn get_neighbors<P: Prefix, Q>(
             router: RouterId,
             neighbors: &BTreeSet<RouterId>,
             net: &Network<P, Q>,
@@ -760,7 +1072,7 @@ impl LabCondition {
 
         /// Compute the Ip address of a neighbor (or another router). If it is an external router,
         /// then use the interface address when talking between both.
-        fn get_router_addr<Q>(
+        fn get_router_addr<P: Prefix, Q>(
             router: RouterId,
             neighbor: Option<RouterId>,
             net: &Network<P, Q>,
@@ -791,6 +1103,16 @@ impl LabCondition {
                 weight: *weight,
                 next_hop: get_router_addr(r, *next_hop, net, addressor)?,
             },
+            AtomicCondition::NoSelectedRoute { router, prefix } if r == *router => {
+                LabCondition::NoSelectedRoute {
+                    prefixes: get_prefixes(prefix, addressor, pec_addresses)?,
+                }
+            }
+            AtomicCondition::NoReceivedRoute { router, prefix } if r == *router => {
+                LabCondition::NoReceivedRoute {
+                    prefixes: get_prefixes(prefix, addressor, pec_addresses)?,
+                }
+            }
             AtomicCondition::AvailableRoute {
                 router,
                 prefix,
@@ -893,6 +1215,22 @@ impl LabCondition {
                 }
                 true
             }
+            LabCondition::NoSelectedRoute { prefixes } => {
+                for p in prefixes.iter() {
+                    if get(shell, p, cache).await?.iter().any(|r| r.selected) {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
+            LabCondition::NoReceivedRoute { prefixes } => {
+                for p in prefixes.iter() {
+                    if !get(shell, p, cache).await?.is_empty() {
+                        return Ok(false);
+                    }
+                }
+                true
+            }
             LabCondition::BgpSessionEstablished { neighbor } => shell
                 .get_bgp_neighbors()
                 .await?
@@ -946,7 +1284,7 @@ fn check_route(
 }
 
 /// Check that a route is less preferred than the provided one from the simulation.
-fn check_route_preference(
+fn check_route_preference<P: Prefix>(
     route: &BgpRoute,
     better: &BgpRibEntry<P>,
     good_neighbors: &BTreeSet<Ipv4Addr>,
@@ -969,6 +1307,7 @@ fn check_route_preference(
                 next_hop: 0.into(),
                 local_pref: route.local_pref,
                 med: route.med,
+                origin: Default::default(),
                 community: Default::default(),
                 originator_id: None,
                 cluster_list: Default::default(),
@@ -982,12 +1321,13 @@ fn check_route_preference(
             to_id: None,
             igp_cost: Some((route.igp_cost as f64).try_into().unwrap()),
             weight: route.weight,
+            received: better.received,
         };
         &rib < better
     }
 }
 
-impl std::fmt::Display for LabCondition {
+impl<P: Prefix> std::fmt::Display for LabCondition<P> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::None => f.write_str("None"),
@@ -1023,6 +1363,12 @@ impl std::fmt::Display for LabCondition {
                 let nh = next_hop.map(|nh| format!(" via {nh}")).unwrap_or_default();
                 write!(f, "know a route for {prefixes}{from}{nh}{weight}")
             }
+            Self::NoSelectedRoute { prefixes } => {
+                write!(f, "select no route for {prefixes}")
+            }
+            Self::NoReceivedRoute { prefixes } => {
+                write!(f, "know no route for {prefixes}")
+            }
             LabCondition::BgpSessionEstablished { neighbor } => {
                 write!(f, "BGP Session with {neighbor} established")
             }
@@ -19,7 +19,6 @@
 
 use std::{fs::OpenOptions, io::Write, path::PathBuf, time::Duration};
 
-use atomic_command::{AtomicCommand, AtomicCondition, AtomicModifier};
 use bgpsim::{
     config::NetworkConfig, event::EventQueue, export::ExportError, prelude::*,
     topology_zoo::TopologyZoo,
@@ -28,17 +27,23 @@ use cisco_lab::{export_capture_to_csv, Active, CiscoLab, CiscoLabError, Inactive
 use thiserror::Error;
 use tokio::{sync::broadcast::error::RecvError, task::JoinError};
 
-use super::controller::Controller;
-use crate::{decomposition::Decomposition, P};
+use super::controller::{AbortError, CheckpointError, Controller};
+use crate::{decomposition::Decomposition, specification::Specification};
 
 mod executor;
-pub use executor::{Event, EventKind};
+pub use executor::{AbortHandle, Event, EventKind};
 
 /// Number of pings per second per flow.
 const CAPTURE_FREQ: u64 = 500;
 
+/// Prefix types that can be used in the lab runtime. The runtime executes the commands on all
+/// routers concurrently, such that the prefix must be shared safely among multiple threads.
+pub trait LabPrefix: Prefix<Set: Send + Sync> + Send + Sync + 'static {}
+
+impl<P> LabPrefix for P where P: Prefix<Set: Send + Sync> + Send + Sync + 'static {}
+
 /// Create the [`CiscoLab`] instance from the given network.
-pub async fn setup_cisco_lab<Q>(
+pub async fn setup_cisco_lab<P: Prefix, Q>(
     net: &'_ Network<P, Q>,
     topo: Option<TopologyZoo>,
 ) -> Result<CiscoLab<'_, P, Q, Inactive>, LabError>
@@ -57,10 +62,10 @@ where
 
 /// Perform the decomposed update on the network using the cisco lab. This function returns the
 /// folder where the experiment results were stored.
-pub async fn run<'a, 'n: 'a, Q>(
+pub async fn run<'a, 'n: 'a, P: LabPrefix, Q>(
     net: Network<P, Q>,
     lab: &'a mut CiscoLab<'n, P, Q, Active>,
-    decomp: Decomposition,
+    decomp: Decomposition<P>,
     event: Option<ExternalEvent>,
 ) -> Result<PathBuf, LabError>
 where
@@ -71,25 +76,97 @@ where
         lab,
         decomp,
         event.map(|x| (x, Duration::from_secs(30))),
+        None,
+        None::<(
+            &Specification<P>,
+            fn(&mut Network<P, Q>) -> Result<(), NetworkError>,
+        )>,
         "lab_chameleon",
     )
     .await
 }
 
-/// Perform the decomposed update on the network using the cisco lab. This function returns the
-/// folder where the experiment results were stored.
-async fn run_and_save_results<'a, 'n: 'a, Q>(
+/// Perform the decomposed update on the network using the cisco lab, but abort it as soon as
+/// `abort` is triggered (see [`Controller::abort`]). After aborting, the rollback plan is executed,
+/// and the final state is compared with the original network. This function returns the folder
+/// where the experiment results were stored.
+pub async fn run_with_abort<'a, 'n: 'a, P: LabPrefix, Q>(
+    net: Network<P, Q>,
+    lab: &'a mut CiscoLab<'n, P, Q, Active>,
+    decomp: Decomposition<P>,
+    abort: AbortHandle,
+) -> Result<PathBuf, LabError>
+where
+    Q: Clone + EventQueue<P> + PartialEq + std::fmt::Debug,
+{
+    run_and_save_results(
+        net,
+        lab,
+        decomp,
+        None,
+        Some(abort),
+        None::<(
+            &Specification<P>,
+            fn(&mut Network<P, Q>) -> Result<(), NetworkError>,
+        )>,
+        "lab_rollback",
+    )
+    .await
+}
+
+/// Perform the decomposed update on the network using the cisco lab, and trigger the external
+/// `event` after `delay`. As soon as the event happens, the controller re-plans the update from
+/// the current state towards the target configuration (see
+/// [`Controller::execute_lab_with_replan`]). The function `model` applies the effect of the event
+/// to the simulated network. Finally, this function checks that the network has reached the same
+/// state as if the event happened after the update. This function returns the folder where the
+/// experiment results were stored.
+pub async fn run_with_replan<'a, 'n: 'a, P: LabPrefix, Q, F>(
+    net: Network<P, Q>,
+    lab: &'a mut CiscoLab<'n, P, Q, Active>,
+    decomp: Decomposition<P>,
+    spec: &Specification<P>,
+    event: ExternalEvent,
+    delay: Duration,
+    model: F,
+) -> Result<PathBuf, LabError>
+where
+    Q: Clone + EventQueue<P> + PartialEq + std::fmt::Debug,
+    F: Fn(&mut Network<P, Q>) -> Result<(), NetworkError>,
+{
+    run_and_save_results(
+        net,
+        lab,
+        decomp,
+        Some((event, delay)),
+        None,
+        Some((spec, model)),
+        "lab_replan",
+    )
+    .await
+}
+
+/// Perform the decomposed update on the network using the cisco lab. If `replan` is given, the
+/// update is re-planned as soon as the `event` happens, and the function applies the effect of the
+/// event to the simulated network. This function returns the folder where the experiment results
+/// were stored.
+async fn run_and_save_results<'a, 'n: 'a, P: LabPrefix, Q, F>(
     mut net: Network<P, Q>,
     lab: &'a mut CiscoLab<'n, P, Q, Active>,
-    decomp: Decomposition,
+    decomp: Decomposition<P>,
     event: Option<(ExternalEvent, Duration)>,
+    abort: Option<AbortHandle>,
+    replan: Option<(&Specification<P>, F)>,
     target_dir_base: impl AsRef<str>,
 ) -> Result<PathBuf, LabError>
 where
     Q: Clone + EventQueue<P> + PartialEq + std::fmt::Debug,
+    F: Fn(&mut Network<P, Q>) -> Result<(), NetworkError>,
 {
     // do the update on the simulated net
-    net.apply_modifier(&decomp.original_command)?;
+    let original_net = net.clone();
+    net.apply_patch(&decomp.original_command)?;
+    let target = net.get_config()?;
 
     // create the controller
     let controller = Controller::new(decomp);
@@ -100,13 +177,43 @@ where
     // wait for 10 seconds before doing anything
     std::thread::sleep(Duration::from_secs(10));
 
-    // now, schedule the external event (if some)
+    // now, schedule the external event (if some), and trigger the re-planning at the same time.
+    let trigger = AbortHandle::new();
     if let Some((event, delay)) = event {
         event.schedule(lab, delay)?;
+        let trigger = trigger.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            trigger.abort();
+        });
     }
 
     // execute the controller
-    let event_log = controller.execute_lab(lab, &net).await?;
+    let (event_log, rolled_back) = if let Some((spec, f)) = replan.as_ref() {
+        let (event_log, num_replans) = controller
+            .execute_lab_with_replan(
+                lab,
+                &net,
+                original_net.clone(),
+                &target,
+                spec,
+                Default::default(),
+                trigger,
+                f,
+            )
+            .await?;
+        log::info!("Re-planned the update {num_replans} times.");
+        // the network reaches the same state as if the event happened after the update.
+        f(&mut net)?;
+        (event_log, false)
+    } else {
+        controller
+            .execute_lab_with_abort(lab, &net, abort.unwrap_or_default())
+            .await?
+    };
+    if rolled_back {
+        net = original_net;
+    }
 
     // wait for 10 seconds after the update was complete
     std::thread::sleep(Duration::from_secs(20));
@@ -146,7 +253,7 @@ where
     }
 
     // compare the state
-    if event.is_none() {
+    if event.is_none() || replan.is_some() {
         log::debug!("Comparing the final state...");
         if !lab.equal_bgp_state(&net).await? {
             return Err(LabError::WrongFinalState);
@@ -156,37 +263,27 @@ where
 }
 
 /// run the baseline, which is simply applying the command on the live network.
-pub async fn run_baseline<'a, 'n: 'a, Q>(
+pub async fn run_baseline<'a, 'n: 'a, P: LabPrefix, Q>(
     net: Network<P, Q>,
     lab: &'a mut CiscoLab<'n, P, Q, Active>,
-    decomp: Decomposition,
+    decomp: Decomposition<P>,
     event: Option<ExternalEvent>,
 ) -> Result<PathBuf, LabError>
 where
     Q: Clone + EventQueue<P> + PartialEq + std::fmt::Debug,
 {
-    let cmd = decomp.original_command;
-    let tmp_decomp = Decomposition {
-        original_command: cmd.clone(),
-        setup_commands: Default::default(),
-        cleanup_commands: Default::default(),
-        atomic_before: Default::default(),
-        main_commands: vec![vec![AtomicCommand {
-            command: AtomicModifier::Raw(cmd),
-            precondition: AtomicCondition::None,
-            postcondition: AtomicCondition::None,
-        }]],
-        atomic_after: Default::default(),
-        bgp_deps: Default::default(),
-        schedule: Default::default(),
-        fw_state_trace: Default::default(),
-    };
+    let tmp_decomp = Decomposition::baseline(decomp.original_command);
 
     run_and_save_results(
         net,
         lab,
         tmp_decomp,
         event.map(|x| (x, Duration::from_secs_f64(5.0))),
+        None,
+        None::<(
+            &Specification<P>,
+            fn(&mut Network<P, Q>) -> Result<(), NetworkError>,
+        )>,
         "lab_baseline",
     )
     .await
@@ -203,7 +300,7 @@ pub enum ExternalEvent {
 
 impl ExternalEvent {
     /// Schwedule the event on the lab
-    fn schedule<Q>(
+    fn schedule<P: Prefix, Q>(
         self,
         lab: &mut CiscoLab<'_, P, Q, Active>,
         delay: Duration,
@@ -246,4 +343,13 @@ pub enum LabError {
     /// Error while dealing with IO
     #[error("IO Error: {0}")]
     IoError(#[from] std::io::Error),
+    /// The update could not be aborted.
+    #[error("Cannot abort the update: {0}")]
+    Abort(#[from] AbortError),
+    /// The update could not be re-planned.
+    #[error("Cannot re-plan the update: {0}")]
+    Replan(String),
+    /// The checkpoint could not be written.
+    #[error("Cannot write the checkpoint: {0}")]
+    Checkpoint(#[from] CheckpointError),
 }
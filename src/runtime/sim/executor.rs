@@ -17,23 +17,25 @@
 
 //! This module is the executor, that applies the actual atomic commands to the network (in `bgpsim`).
 
-use std::{collections::HashMap, mem::take};
+use std::collections::HashMap;
 
-use bgpsim::{event::EventQueue, forwarding_state::ForwardingState, prelude::*};
-use itertools::Itertools;
+use bgpsim::{config::Config, event::EventQueue, forwarding_state::ForwardingState, prelude::*};
+use itertools::{iproduct, Itertools};
 use log::{error, info, warn};
 use rand::prelude::*;
 
 use crate::{
-    decomposition::ilp_scheduler::FwStateTrace,
+    decomposition::ilp_scheduler::{FwStateTrace, ScheduleOptions},
     runtime::controller::{AtomicCommandState, Controller, ControllerStage, StateItem},
     specification::{Checker, Specification},
-    P,
 };
 
 use super::{SimError, SimStats};
 
-impl Controller {
+/// Maximum number of times the update is re-planned before giving up.
+const MAX_REPLANS: usize = 5;
+
+impl<P: Prefix> Controller<P> {
     /// Perform the complete migration on the simulated network. During the migration, this function
     /// will check for policy violations at every state during convergence.
     ///
@@ -45,13 +47,113 @@ impl Controller {
     pub fn execute_sim<Q>(
         &mut self,
         net: &mut Network<P, Q>,
-        spec: &Specification,
+        spec: &Specification<P>,
+        prob_controller_step: f64,
+        expected_fw_trace: HashMap<P, FwStateTrace>,
+        check: bool,
+    ) -> Result<SimStats<P>, SimError>
+    where
+        Q: EventQueue<P> + Clone,
+    {
+        self.execute_sim_with_abort(
+            net,
+            spec,
+            prob_controller_step,
+            expected_fw_trace,
+            check,
+            None,
+        )
+    }
+
+    /// Perform the migration on the simulated network like [`Controller::execute_sim`], but abort
+    /// the update (see [`Controller::abort`]) after the controller has made `abort_after` steps
+    /// that changed its state. After aborting, the controller waits for the network to converge,
+    /// and executes the rollback plan. While rolling back, the specification is still checked (if
+    /// `check` is set), but the forwarding deltas are no longer compared with the expected trace.
+    pub fn execute_sim_with_abort<Q>(
+        &mut self,
+        net: &mut Network<P, Q>,
+        spec: &Specification<P>,
+        prob_controller_step: f64,
+        expected_fw_trace: HashMap<P, FwStateTrace>,
+        check: bool,
+        abort_after: Option<usize>,
+    ) -> Result<SimStats<P>, SimError>
+    where
+        Q: EventQueue<P> + Clone,
+    {
+        self.execute_sim_inner(
+            net,
+            spec,
+            prob_controller_step,
+            expected_fw_trace,
+            check,
+            abort_after,
+            None,
+            None::<(usize, fn(&mut Network<P, Q>) -> Result<(), NetworkError>)>,
+        )
+    }
+
+    /// Perform the migration on the simulated network like [`Controller::execute_sim`], but
+    /// re-plan the update if the network diverges from the expected forwarding state trace, or if
+    /// the controller cannot progress. In that case, the controller stops applying commands until
+    /// the network has converged. Then, it computes a new decomposition from the current state of
+    /// the network towards the `target` configuration, and hands over to it (see
+    /// [`Controller::replan`]). The temporary configuration of the current decomposition stays in
+    /// place, and the new decomposition removes it at the end. Only if it conflicts with the
+    /// temporary configuration of the new decomposition, the controller first executes the
+    /// rollback plan (see [`Controller::abort`]), and re-plans the update from the state reached
+    /// after the rollback. The specification is not checked while the network converges after
+    /// diverging.
+    ///
+    /// The function `event` is applied to the network after the controller has made `event_after`
+    /// steps that changed its state. This allows injecting an unexpected event, like a link
+    /// failure. The number of times the update was re-planned is stored in the statistics.
+    #[allow(clippy::too_many_arguments)]
+    pub fn execute_sim_with_replan<Q, F>(
+        &mut self,
+        net: &mut Network<P, Q>,
+        spec: &Specification<P>,
+        prob_controller_step: f64,
+        expected_fw_trace: HashMap<P, FwStateTrace>,
+        target: &Config<P>,
+        options: ScheduleOptions,
+        event: Option<(usize, F)>,
+    ) -> Result<SimStats<P>, SimError>
+    where
+        Q: EventQueue<P> + Clone,
+        F: FnOnce(&mut Network<P, Q>) -> Result<(), NetworkError>,
+    {
+        self.execute_sim_inner(
+            net,
+            spec,
+            prob_controller_step,
+            expected_fw_trace,
+            true,
+            None,
+            Some((target, options)),
+            event,
+        )
+    }
+
+    /// Perform the migration on the simulated network, optionally aborting it after `abort_after`
+    /// steps of the controller, re-planning it towards the given target configuration when the
+    /// network diverges, and injecting an event after a given number of steps of the controller.
+    #[allow(clippy::too_many_arguments)]
+    fn execute_sim_inner<Q, F>(
+        &mut self,
+        net: &mut Network<P, Q>,
+        spec: &Specification<P>,
         prob_controller_step: f64,
         mut expected_fw_trace: HashMap<P, FwStateTrace>,
         check: bool,
-    ) -> Result<SimStats, SimError>
+        abort_after: Option<usize>,
+        replan: Option<(&Config<P>, ScheduleOptions)>,
+        mut event: Option<(usize, F)>,
+    ) -> Result<SimStats<P>, SimError>
     where
-        Q: EventQueue<P>,
+        Q: EventQueue<P> + Clone,
+        F: FnOnce(&mut Network<P, Q>) -> Result<(), NetworkError>,
     {
         // set the net into manual simulation
         let auto_simulation = net.auto_simulation_enabled();
@@ -64,36 +166,109 @@ impl Controller {
             num_routes_after: 0,
             max_routes: 0,
             fw_deltas: Vec::new(),
+            num_replans: 0,
         };
+        let mut num_controller_steps = 0;
+        // whether the network has diverged from the expected trace, and the update must be
+        // re-planned as soon as the network has converged.
+        let mut diverged = false;
+        // whether the update is re-planned as soon as the rollback has finished.
+        let mut replan_after_rollback = false;
+        // whether the update was aborted, and the rollback waits for the network to converge.
+        let mut converge_before_rollback = false;
 
         loop {
             // check for properties and update stats
-            check_and_update_stats(
-                check,
-                net,
-                &mut fw_state,
-                &mut checker,
-                &mut expected_fw_trace,
-                &mut stats,
+            detect_divergence(
+                check_and_update_stats(
+                    check && !diverged,
+                    !self.is_aborted(),
+                    net,
+                    &mut fw_state,
+                    &mut checker,
+                    &mut expected_fw_trace,
+                    &mut stats,
+                ),
+                replan.is_some(),
+                &mut diverged,
             )?;
             // simulate a step on the network
             net.simulate_step()?;
             // check for properties and update stats
-            check_and_update_stats(
-                check,
-                net,
-                &mut fw_state,
-                &mut checker,
-                &mut expected_fw_trace,
-                &mut stats,
+            detect_divergence(
+                check_and_update_stats(
+                    check && !diverged,
+                    !self.is_aborted(),
+                    net,
+                    &mut fw_state,
+                    &mut checker,
+                    &mut expected_fw_trace,
+                    &mut stats,
+                ),
+                replan.is_some(),
+                &mut diverged,
             )?;
 
+            if diverged {
+                // wait until the network has converged before re-planning the update.
+                if let (true, Some((target, options))) = (net.queue().is_empty(), replan.as_ref()) {
+                    if stats.num_replans >= MAX_REPLANS {
+                        error!("Cannot re-plan the update more than {MAX_REPLANS} times!");
+                        return Err(SimError::CannotProgress);
+                    }
+                    match self.replan(net, target, spec, *options) {
+                        Ok(()) => {
+                            self.save_checkpoint()?;
+                            stats.num_replans += 1;
+                            expected_fw_trace = self.decomp.fw_state_trace.clone();
+                            checker = Checker::new(spec);
+                        }
+                        // The temporary configuration of the current decomposition conflicts
+                        // with the new one. Hence, roll back first, and re-plan afterwards.
+                        Err(e) if self.has_started() && !self.is_finished() => {
+                            warn!("Cannot re-plan from the current state: {e}");
+                            self.abort()?;
+                            self.save_checkpoint()?;
+                            warn!("Roll back the update before re-planning it.");
+                            replan_after_rollback = true;
+                        }
+                        Err(e) => return Err(SimError::Replan(e.to_string())),
+                    }
+                    diverged = false;
+                }
+                continue;
+            }
+
+            // Wait for the network to converge before rolling back. Otherwise, outdated BGP
+            // messages could still arrive after a condition of the rollback plan is satisfied.
+            if converge_before_rollback && !net.queue().is_empty() {
+                continue;
+            }
+            converge_before_rollback = false;
+
             // skip the controller if the queue is not empty and with a certain probability
             if net.queue().is_empty() || thread_rng().gen_bool(prob_controller_step) {
                 // do a step on the controller
                 let change = self.step_sim(net)?;
+                if change {
+                    num_controller_steps += 1;
+                    if Some(num_controller_steps) == abort_after && !self.is_finished() {
+                        self.abort()?;
+                        self.save_checkpoint()?;
+                        converge_before_rollback = true;
+                    }
+                    if event.as_ref().map(|(n, _)| *n) == Some(num_controller_steps) {
+                        let (_, f) = event.take().unwrap();
+                        warn!("Inject an external event into the network.");
+                        f(net)?;
+                    }
+                }
                 // check if we are done here.
-                if self.is_finished() && net.queue().is_empty() {
+                if self.is_finished() && net.queue().is_empty() && replan_after_rollback {
+                    // the rollback has finished. Re-plan the update from the current state.
+                    replan_after_rollback = false;
+                    diverged = true;
+                } else if self.is_finished() && net.queue().is_empty() {
                     // controler has finished, and the network has converged
                     break;
                 } else if !change && net.queue().is_empty() {
@@ -105,6 +280,11 @@ impl Controller {
                         "Current conditions:\n{}",
                         self.state().fmt_current_conditions(net)
                     );
+                    if replan.is_some() && !self.is_aborted() {
+                        // re-plan the update from the current state.
+                        diverged = true;
+                        continue;
+                    }
                     // The controller did not make any progress, but the queue is currently empty, meaning
                     // that we are essentially stuck.
                     return Err(SimError::CannotProgress);
@@ -133,26 +313,15 @@ impl Controller {
                 return Ok(false);
             }
             // proceed to the next step
-            self.state = match self.state {
-                ControllerStage::Setup(_) => {
-                    ControllerStage::update_before(take(&mut self.decomp.atomic_before))
-                }
-                ControllerStage::UpdateBefore(_) => {
-                    ControllerStage::main(take(&mut self.decomp.main_commands))
-                }
-                ControllerStage::Main(_) => {
-                    ControllerStage::update_after(take(&mut self.decomp.atomic_after))
-                }
-                ControllerStage::UpdateAfter(_) => {
-                    ControllerStage::cleanup(take(&mut self.decomp.cleanup_commands))
-                }
-                ControllerStage::Cleanup(_) => ControllerStage::Finished,
-                ControllerStage::Finished => ControllerStage::Finished,
-            };
+            self.next_stage();
             info!("Proceed to the next stage: {}.", self.state.name());
+            self.save_checkpoint()?;
             // return true, meaning that there was some change.
             Ok(true)
         } else {
+            if update {
+                self.save_checkpoint()?;
+            }
             Ok(update)
         }
     }
@@ -168,28 +337,63 @@ macro_rules! err {
     };
 }
 
+/// Compute the forwarding delta from `old` to `new` of all prefixes known in `net`, using longest
+/// prefix matching. For each prefix, the delta contains the routers and their new next-hops.
+fn fw_diff<P: Prefix, Q>(
+    net: &Network<P, Q>,
+    old: &ForwardingState<P>,
+    new: &ForwardingState<P>,
+) -> HashMap<P, Vec<(RouterId, Vec<RouterId>)>> {
+    let mut result: HashMap<P, Vec<(RouterId, Vec<RouterId>)>> = HashMap::new();
+    for (p, r) in iproduct!(net.get_known_prefixes(), net.get_topology().node_indices()) {
+        let new_nh = new.get_next_hops(r, *p);
+        if old.get_next_hops(r, *p) != new_nh {
+            result.entry(*p).or_default().push((r, new_nh.to_vec()));
+        }
+    }
+    result
+}
+
+/// Mark the network as diverged if `result` is a mismatch with the expected trace and the update
+/// can be re-planned. All other errors are returned.
+fn detect_divergence(
+    result: Result<(), SimError>,
+    replan: bool,
+    diverged: &mut bool,
+) -> Result<(), SimError> {
+    match result {
+        Err(SimError::TraceMismatch(e)) if replan => {
+            warn!("Network diverged from the expected trace! {e}");
+            *diverged = true;
+            Ok(())
+        }
+        result => result,
+    }
+}
+
 /// Update the forwarding state and log all deltas. Then, check compare the diff with the expected
-/// trace.
-fn check_and_update_stats<Q>(
+/// trace (only if `check_trace` is set).
+fn check_and_update_stats<P: Prefix, Q>(
     check: bool,
+    check_trace: bool,
     net: &Network<P, Q>,
     fw_state: &mut ForwardingState<P>,
-    checker: &mut Checker<'_>,
+    checker: &mut Checker<'_, P>,
     expected_fw_trace: &mut HashMap<P, FwStateTrace>,
-    stats: &mut SimStats,
+    stats: &mut SimStats<P>,
 ) -> Result<(), SimError> {
     // handle the forwarding state
     let new = net.get_forwarding_state();
-    let diff = fw_state.diff(&new);
+    let diff = fw_diff(net, fw_state, &new);
     if !diff.is_empty() {
         log::info!("Forwarding delta!");
     }
     let mut delta = Vec::new();
     for (p, diff) in diff {
-        for (r, nh) in diff.into_iter().map(|(r, _, nh)| (r, nh)).unique() {
+        for (r, nh) in diff {
             log::info!("FW delta: {} => {p}: {}", r.fmt(net), nh.fmt(net));
             // remove the diff from the expected trace
-            if check {
+            if check && check_trace {
                 let prefix_trace = expected_fw_trace
                     .get_mut(&p)
                     .ok_or_else(err!("FW delta for prefix {p} that should not be affected!"))?;
@@ -244,7 +448,7 @@ fn check_and_update_stats<Q>(
     Ok(())
 }
 
-impl ControllerStage {
+impl<P: Prefix> ControllerStage<P> {
     /// Perform an individual step on the state. The first returned boolean tells if there was
     /// something that has changed, and the second one tells if the current state is done, and we
     /// can move to the next state.
@@ -269,7 +473,7 @@ impl ControllerStage {
     }
 }
 
-impl StateItem {
+impl<P: Prefix> StateItem<P> {
     /// Perform an individual step on the state. The first returned boolean tells if there was
     /// something that has changed, and the second one tells if the current state is done, and we
     /// can move to the next state.
@@ -21,9 +21,9 @@ use bgpsim::{config::NetworkConfig, event::EventQueue, prelude::*};
 use log::error;
 use thiserror::Error;
 
-use crate::{decomposition::Decomposition, specification::Specification, P};
+use crate::{decomposition::Decomposition, specification::Specification};
 
-use super::controller::Controller;
+use super::controller::{AbortError, CheckpointError, Controller};
 
 mod executor;
 
@@ -35,16 +35,16 @@ const PROB_CONTROLLER_STEP: f64 = 0.5;
 /// check on each step in the simulation if (1) the policies are satisfied, and (2) if it is safe to
 /// perform any update. The strategy is such that we try to make the update as fast as
 /// possible. This is obviously not easy to do in practice.
-pub fn run<Q>(
+pub fn run<P: Prefix, Q>(
     mut net: Network<P, Q>,
-    decomp: Decomposition,
-    spec: &Specification,
-) -> Result<(Network<P, Q>, SimStats), SimError>
+    decomp: Decomposition<P>,
+    spec: &Specification<P>,
+) -> Result<(Network<P, Q>, SimStats<P>), SimError>
 where
     Q: Clone + EventQueue<P> + PartialEq + std::fmt::Debug,
 {
     let mut exp_net = net.clone();
-    exp_net.apply_modifier(&decomp.original_command)?;
+    exp_net.apply_patch(&decomp.original_command)?;
 
     let trace = decomp.fw_state_trace.clone();
     let mut controller = Controller::new(decomp);
@@ -62,15 +62,15 @@ where
 
 /// Perform the decomposed update on the network using the simulated environment (bgpsim). This
 /// function will not do any kind of checks.
-pub fn run_no_checks<Q>(
+pub fn run_no_checks<P: Prefix, Q>(
     mut net: Network<P, Q>,
-    decomp: Decomposition,
-) -> Result<(Network<P, Q>, SimStats), SimError>
+    decomp: Decomposition<P>,
+) -> Result<(Network<P, Q>, SimStats<P>), SimError>
 where
     Q: Clone + EventQueue<P> + PartialEq + std::fmt::Debug,
 {
     let mut exp_net = net.clone();
-    exp_net.apply_modifier(&decomp.original_command)?;
+    exp_net.apply_patch(&decomp.original_command)?;
 
     let mut controller = Controller::new(decomp);
 
@@ -91,10 +91,99 @@ where
     }
 }
 
+/// Perform the decomposed update on the network using the simulated environment (bgpsim), but
+/// abort it after the controller has made `abort_after` steps (see [`Controller::abort`]). The
+/// controller then executes the rollback plan, while still checking the specification at every
+/// state during convergence. Finally, this function checks that the network is back in its
+/// original state.
+pub fn run_and_abort<P: Prefix, Q>(
+    mut net: Network<P, Q>,
+    decomp: Decomposition<P>,
+    spec: &Specification<P>,
+    abort_after: usize,
+) -> Result<(Network<P, Q>, SimStats<P>), SimError>
+where
+    Q: Clone + EventQueue<P> + PartialEq + std::fmt::Debug,
+{
+    let exp_net = net.clone();
+
+    let trace = decomp.fw_state_trace.clone();
+    let mut controller = Controller::new(decomp);
+
+    let stats = controller.execute_sim_with_abort(
+        &mut net,
+        spec,
+        PROB_CONTROLLER_STEP,
+        trace,
+        true,
+        Some(abort_after),
+    )?;
+
+    if !controller.is_aborted() {
+        return Err(SimError::Abort(AbortError::Finished));
+    }
+
+    // check if they are equal
+    if net != exp_net {
+        pretty_assertions_sorted::assert_eq!(net, exp_net);
+        Err(SimError::WrongFinalState)
+    } else {
+        Ok((net, stats))
+    }
+}
+
+/// Perform the decomposed update on the network using the simulated environment (bgpsim), and
+/// apply the unexpected `event` after the controller has made `event_after` steps. As soon as the
+/// network diverges from the expected trace, the controller re-plans the update from the current
+/// state towards the target configuration (see [`Controller::execute_sim_with_replan`]). Finally,
+/// this function checks that the network has reached the same state as if the event happened
+/// after the update.
+pub fn run_with_event<P: Prefix, Q, F>(
+    mut net: Network<P, Q>,
+    decomp: Decomposition<P>,
+    spec: &Specification<P>,
+    event_after: usize,
+    event: F,
+) -> Result<(Network<P, Q>, SimStats<P>), SimError>
+where
+    Q: Clone + EventQueue<P> + PartialEq + std::fmt::Debug,
+    F: Fn(&mut Network<P, Q>) -> Result<(), NetworkError>,
+{
+    let mut exp_net = net.clone();
+    exp_net.apply_patch(&decomp.original_command)?;
+    let target = exp_net.get_config()?;
+    event(&mut exp_net)?;
+
+    let trace = decomp.fw_state_trace.clone();
+    let mut controller = Controller::new(decomp);
+
+    let stats = controller.execute_sim_with_replan(
+        &mut net,
+        spec,
+        PROB_CONTROLLER_STEP,
+        trace,
+        &target,
+        Default::default(),
+        Some((event_after, &event)),
+    )?;
+
+    // check if they are equal
+    if net != exp_net {
+        pretty_assertions_sorted::assert_eq!(net, exp_net);
+        Err(SimError::WrongFinalState)
+    } else {
+        Ok((net, stats))
+    }
+}
+
 /// Statistics collected during simulation.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
-pub struct SimStats {
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "P: for<'a> serde::Deserialize<'a>"))
+)]
+pub struct SimStats<P: Prefix> {
     /// Number of routes within the BgpRibIn and BgpRib in the initial state.
     pub num_routes_before: usize,
     /// Number of routes within the BgpRibIn and BgpRib in the final state.
@@ -103,6 +192,9 @@ pub struct SimStats {
     pub max_routes: usize,
     /// Sequence of forwarding deltas performed during the migration.
     pub fw_deltas: Vec<Vec<(RouterId, P, Vec<RouterId>)>>,
+    /// Number of times the update was re-planned (see [`Controller::execute_sim_with_replan`]).
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub num_replans: usize,
 }
 
 /// Error of the simulated runtime.
@@ -123,4 +215,13 @@ pub enum SimError {
     /// The simulated trace does not match the scheduled trace
     #[error("Simulated trace does not match the scheduled trace! {0}")]
     TraceMismatch(String),
+    /// The update could not be re-planned.
+    #[error("Cannot re-plan the update: {0}")]
+    Replan(String),
+    /// The update could not be aborted.
+    #[error("Cannot abort the update: {0}")]
+    Abort(#[from] AbortError),
+    /// The checkpoint could not be written.
+    #[error("Cannot write the checkpoint: {0}")]
+    Checkpoint(#[from] CheckpointError),
 }
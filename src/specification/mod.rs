@@ -18,7 +18,7 @@
 //! Module that contains the invariants and policies supported bu this crate.
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     iter::once,
     ops::Not,
 };
@@ -35,25 +35,28 @@ use bgpsim::{
     forwarding_state::ForwardingState,
     policies::FwPolicy,
     prelude::{Network, NetworkError, NetworkFormatter},
-    types::RouterId,
+    types::{Prefix, RouterId},
 };
 
-use crate::P;
+mod parser;
+pub use parser::{
+    parse_spec_expr, parse_specification, SpecParseError, SpecParseErrorKind, SpecText,
+};
 
 /// Structure to check a Specification
-#[derive(Debug)]
-pub struct Checker<'a> {
+#[derive(Debug, Clone)]
+pub struct Checker<'a, P: Prefix> {
     /// Specification that is checked
-    spec: &'a Specification,
+    spec: &'a Specification<P>,
     /// Which expressions are satisfied in which step.
     invariants: HashMap<P, HashMap<Invariant, Vec<bool>>>,
     /// Number of steps already present in the checker
     steps: usize,
 }
 
-impl<'a> Checker<'a> {
+impl<'a, P: Prefix> Checker<'a, P> {
     /// Create a new specification checker
-    pub fn new(spec: &'a Specification) -> Self {
+    pub fn new(spec: &'a Specification<P>) -> Self {
         let invariants = spec
             .iter()
             .map(|(p, expr)| {
@@ -245,7 +248,7 @@ fn check_rec(
 
 /// Specification, that is, a mapping from a prefix to a specification expression. Each
 /// specification expression states a single expression for all properties.
-pub type Specification = HashMap<P, SpecExpr>;
+pub type Specification<P> = HashMap<P, SpecExpr>;
 
 /// Modal and Logical Operators to build a specification.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -374,6 +377,18 @@ pub enum Property {
     Not(Box<Property>),
     /// Waypoint property
     Waypoint(RouterId),
+    /// The path traverses the directed edge from the first to the second router.
+    Edge(RouterId, RouterId),
+    /// The path traverses all waypoints in the given order (but not necessarily consecutively).
+    Sequence(Vec<RouterId>),
+    /// The path has at most the given number of hops (i.e., edges), including the last hop towards
+    /// the external router.
+    MaxHops(usize),
+    /// The path leaves the network at any of the given (external) routers.
+    Egress(BTreeSet<RouterId>),
+    /// The path never traverses the given router. In contrast to all other path properties, this
+    /// property must also hold on paths that don't reach the destination.
+    Avoid(RouterId),
     /// Reachability
     Reachability,
     /// property is always satisfied.
@@ -383,28 +398,35 @@ pub enum Property {
 /// Invariant violation
 #[derive(Debug, Clone, Error)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
-pub enum Violation {
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "P: for<'a> serde::Deserialize<'a>"))
+)]
+pub enum Violation<P: Prefix> {
     /// Path Violation
     #[error("Path violation for {1:?} ({0}) with path {2:?} (valid: {3})")]
     Path(P, Property, Vec<RouterId>, bool),
 }
 
 impl Invariant {
-    /// Check the invariant holds on the forwarding state for a given prefix.
-    pub fn check(&self, fw_state: &mut ForwardingState<P>, prefix: P) -> Result<(), Violation> {
+    /// Check the invariant holds on the forwarding state for a given prefix. If the router load
+    /// balances the traffic over multiple paths, then the property must hold on every path.
+    pub fn check<P: Prefix>(
+        &self,
+        fw_state: &mut ForwardingState<P>,
+        prefix: P,
+    ) -> Result<(), Violation<P>> {
         match fw_state.get_paths(self.router, prefix) {
-            Ok(mut paths) if paths.len() == 1 => {
-                let path = paths.pop().unwrap();
+            Ok(paths) => paths.into_iter().try_for_each(|path| {
                 self.prop
                     .check(&path, true)
                     .ok_or_else(|| Violation::Path(prefix, self.prop.clone(), path, true))
-            }
+            }),
             Err(NetworkError::ForwardingBlackHole(p)) | Err(NetworkError::ForwardingLoop(p)) => {
                 self.prop
                     .check(&p, false)
                     .ok_or_else(|| Violation::Path(prefix, self.prop.clone(), p, false))
             }
-            Ok(_) => unimplemented!("ECMP is not implemented."),
             Err(e) => unreachable!("Unexpected error {e} trown!"),
         }
     }
@@ -418,6 +440,14 @@ impl Property {
             Self::Any(ps) => ps.iter().any(|p| p.check(path, reachable)),
             Self::Not(p) => !p.check(path, reachable),
             Self::Waypoint(w) => !reachable || path.contains(w),
+            Self::Edge(a, b) => !reachable || path.windows(2).any(|e| e == [*a, *b]),
+            Self::Sequence(ws) => {
+                let mut path = path.iter();
+                !reachable || ws.iter().all(|w| path.any(|x| x == w))
+            }
+            Self::MaxHops(k) => !reachable || path.len() <= k + 1,
+            Self::Egress(es) => !reachable || path.last().map(|e| es.contains(e)).unwrap_or(false),
+            Self::Avoid(r) => !path.contains(r),
             Self::Reachability => reachable,
             Self::True => true,
         }
@@ -428,7 +458,18 @@ impl Property {
         let mut props: HashSet<Self> = match self {
             Self::All(xs) | Self::Any(xs) => xs.iter().flat_map(Self::get_subprops).collect(),
             Self::Not(x) => x.get_subprops(),
-            Self::True | Self::Waypoint(_) | Self::Reachability => HashSet::with_capacity(1),
+            // The remainder of the sequence after reaching the first waypoint
+            Self::Sequence(ws) => (1..=ws.len())
+                .map(|i| Self::Sequence(ws[i..].to_vec()))
+                .collect(),
+            // The remaining number of hops after taking the first hop
+            Self::MaxHops(k) => (0..*k).map(Self::MaxHops).collect(),
+            Self::True
+            | Self::Waypoint(_)
+            | Self::Edge(_, _)
+            | Self::Egress(_)
+            | Self::Avoid(_)
+            | Self::Reachability => HashSet::with_capacity(1),
         };
         props.insert(self.clone());
         props
@@ -461,12 +502,12 @@ pub enum SpecificationBuilder {
 
 impl SpecificationBuilder {
     /// Build all invariants for all nodes in the network, and all specified routers
-    pub fn build_all<Q: EventQueue<P> + Clone>(
+    pub fn build_all<P: Prefix, Q: EventQueue<P> + Clone>(
         self,
         net: &Network<P, Q>,
         command: Option<&ConfigModifier<P>>,
         prefixes: impl IntoIterator<Item = P>,
-    ) -> Specification {
+    ) -> Specification<P> {
         let mut old_fws = net.get_forwarding_state();
         let mut new_fws = if let Some(command) = command {
             let mut new_net = net.clone();
@@ -488,7 +529,7 @@ impl SpecificationBuilder {
     }
 
     /// Build the invariant for a given router and prefix.
-    pub fn build(
+    pub fn build<P: Prefix>(
         self,
         old_fws: &mut ForwardingState<P>,
         new_fws: &mut ForwardingState<P>,
@@ -653,7 +694,7 @@ impl SpecExpr {
     /// Get the global invariants from a SpecExpr. This will extract all invariants that must hold
     /// during the entire migration. This function will report warninigs for all expressions that
     /// could not be converted.
-    pub fn as_global_invariants<Q>(self, net: &Network<P, Q>) -> Vec<Invariant> {
+    pub fn as_global_invariants<P: Prefix, Q>(self, net: &Network<P, Q>) -> Vec<Invariant> {
         match self {
             SpecExpr::All(es) => {
                 let mut invariants = Vec::new();
@@ -703,7 +744,7 @@ impl SpecExpr {
 impl Invariant {
     /// Try to transform the invariant into a vector of forewarding policies. This function will
     /// ignore any policy that it cannot transform, and log a warning.
-    pub fn as_fw_policies<Q>(self, net: &Network<P, Q>, prefix: P) -> Vec<FwPolicy<P>> {
+    pub fn as_fw_policies<P: Prefix, Q>(self, net: &Network<P, Q>, prefix: P) -> Vec<FwPolicy<P>> {
         self.prop.as_fw_policies(net, self.router, prefix)
     }
 }
@@ -711,7 +752,7 @@ impl Invariant {
 impl Property {
     /// Try to transform the invariant into a vector of forewarding policies. This function will
     /// ignore any policy that it cannot transform, and log a warning.
-    pub fn as_fw_policies<Q>(
+    pub fn as_fw_policies<P: Prefix, Q>(
         self,
         net: &Network<P, Q>,
         router: RouterId,
@@ -735,6 +776,31 @@ impl Property {
                 prefix,
                 bgpsim::policies::PathCondition::Node(wp),
             )],
+            Property::Edge(a, b) => vec![FwPolicy::PathCondition(
+                router,
+                prefix,
+                bgpsim::policies::PathCondition::Edge(a, b),
+            )],
+            Property::Sequence(ws) => {
+                use bgpsim::policies::Waypoint;
+                let mut positional = vec![Waypoint::Star];
+                for w in ws {
+                    positional.push(Waypoint::Fix(w));
+                    positional.push(Waypoint::Star);
+                }
+                vec![FwPolicy::PathCondition(
+                    router,
+                    prefix,
+                    bgpsim::policies::PathCondition::Positional(positional),
+                )]
+            }
+            Property::MaxHops(_) | Property::Egress(_) | Property::Avoid(_) => {
+                log::warn!(
+                    "Cannot interpret {} as a set of forwarding policies!",
+                    self.fmt(net)
+                );
+                Vec::new()
+            }
             Property::Reachability => vec![FwPolicy::Reachable(router, prefix)],
         }
     }
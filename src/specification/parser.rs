@@ -0,0 +1,921 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Text format for specifications.
+//!
+//! A specification is a sequence of prefix specifications, optionally separated by `;`. Each
+//! prefix specification starts with `prefix <prefix>:`, followed by an LTL expression:
+//!
+//! ```text
+//! # comments start with a `#`.
+//! prefix 100.0.0.0/24: G(reach(r1)) & (egress(r1) = NY U egress(r1) = HOU)
+//! ```
+//!
+//! Expressions are built from the following operators, in order of increasing precedence:
+//!
+//! | Syntax                     | Expression               |
+//! |----------------------------|--------------------------|
+//! | `a U b`, `a W b`           | [`SpecExpr::Until`], [`SpecExpr::WeakUntil`] (right-associative) |
+//! | `a \| b \| ...`            | [`SpecExpr::Any`]        |
+//! | `a & b & ...`              | [`SpecExpr::All`]        |
+//! | `!a`, `X a`, `F a`, `G a`  | [`SpecExpr::Not`], [`SpecExpr::Next`], [`SpecExpr::Finally`], [`SpecExpr::Globally`] |
+//! | `(a)`, `true`, `all(a, ...)`, `any(a, ...)` | Grouping, [`SpecExpr::True`], and explicit conjunctions or disjunctions |
+//!
+//! Invariants are written as `reach(r)`, `waypoint(r, w)`, `edge(r, a, b)`, `sequence(r, w1, w2,
+//! ...)`, `max_hops(r, k)`, `egress(r) = e`, `egress(r) in {e1, e2, ...}`, or `avoid(r, x)`, where
+//! `r` is the router from which the path starts. Invariants with composed properties are written
+//! as `path(r, <property>)`, where the property uses the same operators `!`, `&`, and `|`, and the
+//! path properties from above without the source router, e.g., `path(r1, waypoint(A) | avoid(B))`.
+//!
+//! Routers are referred to by their name. Names that contain anything other than alphanumeric
+//! characters, `_`, `-`, `.` or `/` must be quoted, e.g., `reach("New York")`.
+//!
+//! Use [`parse_specification`] to parse a specification, and [`SpecText`] to print it in this
+//! format.
+
+use std::{
+    collections::BTreeSet,
+    fmt::{Display, Formatter, Result as FmtResult, Write},
+    ops::Range,
+    str::FromStr,
+};
+
+use bgpsim::{
+    prelude::{Network, NetworkFormatter, RouterId},
+    types::Prefix,
+};
+use boolinator::Boolinator;
+use ipnet::Ipv4Net;
+use itertools::Itertools;
+use thiserror::Error;
+
+use super::{Invariant, Property, SpecExpr, Specification};
+
+/// Names of all path properties.
+const PATH_PROPS: [&str; 7] = [
+    "reach", "waypoint", "edge", "sequence", "max_hops", "egress", "avoid",
+];
+
+/// Parse a specification from its text representation. All router names are resolved using
+/// [`Network::get_router_id`].
+pub fn parse_specification<P: Prefix, Q>(
+    text: &str,
+    net: &Network<P, Q>,
+) -> Result<Specification<P>, SpecParseError> {
+    Parser::new(text, net)?.specification()
+}
+
+/// Parse a single specification expression (without the leading `prefix <prefix>:`) from its text
+/// representation.
+pub fn parse_spec_expr<P: Prefix, Q>(
+    text: &str,
+    net: &Network<P, Q>,
+) -> Result<SpecExpr, SpecParseError> {
+    let mut parser = Parser::new(text, net)?;
+    let expr = parser.expr()?;
+    parser.expect_eof()?;
+    Ok(expr)
+}
+
+/// Error while parsing a specification, pointing at the offending span (byte offsets) in the text.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{kind} (at {}..{})", .span.start, .span.end)]
+pub struct SpecParseError {
+    /// The kind of error
+    pub kind: SpecParseErrorKind,
+    /// The span in the text that caused the error.
+    pub span: Range<usize>,
+}
+
+/// The different kinds of errors while parsing a specification.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum SpecParseErrorKind {
+    /// Unexpected character
+    #[error("Unexpected character {0:?}")]
+    UnexpectedChar(char),
+    /// A quoted name is not terminated
+    #[error("Unterminated quoted name")]
+    UnterminatedQuote,
+    /// Unexpected token
+    #[error("Expected {expected}, found {found}")]
+    Unexpected {
+        /// What was expected at that position
+        expected: String,
+        /// The token that was found instead
+        found: String,
+    },
+    /// The router is not part of the network
+    #[error("Unknown router {0:?}")]
+    UnknownRouter(String),
+    /// The prefix cannot be parsed, or is not representable.
+    #[error("Invalid prefix {0:?}, or the prefix cannot be represented")]
+    InvalidPrefix(String),
+    /// The number cannot be parsed.
+    #[error("Invalid number {0:?}")]
+    InvalidNumber(String),
+    /// The path property has a wrong number of arguments.
+    #[error("{name} expects {expected} argument(s), but {found} were given")]
+    WrongArity {
+        /// Name of the path property
+        name: String,
+        /// Number of expected arguments
+        expected: usize,
+        /// Number of arguments given
+        found: usize,
+    },
+    /// The invariant does not specify the source router.
+    #[error("Invariant {0} requires the source router as its first argument")]
+    MissingRouter(String),
+    /// The same prefix is specified twice.
+    #[error("Prefix {0} is specified multiple times")]
+    DuplicatePrefix(String),
+}
+
+impl SpecParseError {
+    /// Create a new error of the given kind at the given span.
+    fn new(kind: SpecParseErrorKind, span: Range<usize>) -> Self {
+        Self { kind, span }
+    }
+
+    /// Render the error together with the line of `text` in which it occurred, underlining the
+    /// offending span.
+    pub fn annotate(&self, text: &str) -> String {
+        let start = self.span.start.min(text.len());
+        let line_start = text[..start].rfind('\n').map(|x| x + 1).unwrap_or(0);
+        let line_end = text[start..]
+            .find('\n')
+            .map(|x| x + start)
+            .unwrap_or(text.len());
+        let end = self.span.end.clamp(start, line_end);
+        let line_no = text[..start].matches('\n').count() + 1;
+        let col = text[line_start..start].chars().count();
+        let width = text[start..end].chars().count().max(1);
+        let margin = " ".repeat(line_no.to_string().len());
+        format!(
+            "error: {}\n{margin} --> line {line_no}, column {}\n{margin} |\n{line_no} | {}\n{margin} | {}{}",
+            self.kind,
+            col + 1,
+            &text[line_start..line_end],
+            " ".repeat(col),
+            "^".repeat(width),
+        )
+    }
+}
+
+/// Tokens of the specification text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// Keyword, name, number, or prefix
+    Word(String),
+    /// Quoted name
+    Quoted(String),
+    /// Punctuation
+    Punct(char),
+    /// End of the input
+    Eof,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Token::Word(w) => write!(f, "`{w}`"),
+            Token::Quoted(w) => write!(f, "{w:?}"),
+            Token::Punct(c) => write!(f, "`{c}`"),
+            Token::Eof => f.write_str("end of input"),
+        }
+    }
+}
+
+/// Check if the character can be part of an unquoted word.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '/')
+}
+
+/// Split the text into tokens.
+fn lex(text: &str) -> Result<Vec<(Token, Range<usize>)>, SpecParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '#' => while chars.next_if(|(_, c)| *c != '\n').is_some() {},
+            '"' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some((i, '"')) => {
+                            tokens.push((Token::Quoted(name), start..i + 1));
+                            break;
+                        }
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, c)) => name.push(c),
+                            None => break,
+                        },
+                        Some((_, c)) => name.push(c),
+                        None => {
+                            return Err(SpecParseError::new(
+                                SpecParseErrorKind::UnterminatedQuote,
+                                start..text.len(),
+                            ))
+                        }
+                    }
+                }
+            }
+            '(' | ')' | '{' | '}' | ',' | ':' | ';' | '!' | '&' | '|' | '=' => {
+                tokens.push((Token::Punct(c), start..start + 1))
+            }
+            c if is_word_char(c) => {
+                let mut end = start + c.len_utf8();
+                while let Some((i, c)) = chars.next_if(|(_, c)| is_word_char(*c)) {
+                    end = i + c.len_utf8();
+                }
+                tokens.push((Token::Word(text[start..end].to_string()), start..end));
+            }
+            c => {
+                return Err(SpecParseError::new(
+                    SpecParseErrorKind::UnexpectedChar(c),
+                    start..start + c.len_utf8(),
+                ))
+            }
+        }
+    }
+    tokens.push((Token::Eof, text.len()..text.len()));
+    Ok(tokens)
+}
+
+/// Parse a prefix. Only prefixes that can be represented by `P` are allowed.
+fn parse_prefix<P: Prefix>(text: &str) -> Option<P> {
+    let net = Ipv4Net::from_str(text).ok()?;
+    let prefix = P::from(net);
+    (Into::<Ipv4Net>::into(prefix) == net).as_some(prefix)
+}
+
+/// Recursive-descent parser for the specification text.
+struct Parser<'n, P: Prefix, Q> {
+    /// The network used to resolve router names
+    net: &'n Network<P, Q>,
+    /// All tokens, including their span. The last token is always [`Token::Eof`].
+    tokens: Vec<(Token, Range<usize>)>,
+    /// Index of the current token
+    pos: usize,
+}
+
+impl<'n, P: Prefix, Q> Parser<'n, P, Q> {
+    /// Tokenize the text and create a new parser.
+    fn new(text: &str, net: &'n Network<P, Q>) -> Result<Self, SpecParseError> {
+        Ok(Self {
+            net,
+            tokens: lex(text)?,
+            pos: 0,
+        })
+    }
+
+    /// Get the current token.
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos].0
+    }
+
+    /// Get the current token and advance to the next one (but never beyond the end of input).
+    fn next(&mut self) -> (Token, Range<usize>) {
+        let tok = self.tokens[self.pos].clone();
+        if tok.0 != Token::Eof {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    /// Create an error about an unexpected token at the current position.
+    fn unexpected(&self, expected: impl Into<String>) -> SpecParseError {
+        let (tok, span) = &self.tokens[self.pos];
+        SpecParseError::new(
+            SpecParseErrorKind::Unexpected {
+                expected: expected.into(),
+                found: tok.to_string(),
+            },
+            span.clone(),
+        )
+    }
+
+    /// Check if the current token is the (unquoted) keyword `kw`.
+    fn is_keyword(&self, kw: &str) -> bool {
+        matches!(self.peek(), Token::Word(w) if w == kw)
+    }
+
+    /// Consume the punctuation `c` if it is the current token.
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == &Token::Punct(c);
+        if found {
+            self.next();
+        }
+        found
+    }
+
+    /// Consume the punctuation `c`, or return an error.
+    fn expect(&mut self, c: char) -> Result<(), SpecParseError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.unexpected(format!("`{c}`")))
+        }
+    }
+
+    /// Make sure that the entire input is consumed.
+    fn expect_eof(&mut self) -> Result<(), SpecParseError> {
+        if self.peek() == &Token::Eof {
+            Ok(())
+        } else {
+            Err(self.unexpected("end of input"))
+        }
+    }
+
+    /// Consume a (quoted or unquoted) word.
+    fn word(&mut self, what: &str) -> Result<(String, Range<usize>), SpecParseError> {
+        match self.peek() {
+            Token::Word(_) | Token::Quoted(_) => match self.next() {
+                (Token::Word(w) | Token::Quoted(w), span) => Ok((w, span)),
+                _ => unreachable!(),
+            },
+            _ => Err(self.unexpected(what)),
+        }
+    }
+
+    /// Resolve the router name.
+    fn router(&self, (name, span): &(String, Range<usize>)) -> Result<RouterId, SpecParseError> {
+        self.net.get_router_id(name).map_err(|_| {
+            SpecParseError::new(
+                SpecParseErrorKind::UnknownRouter(name.clone()),
+                span.clone(),
+            )
+        })
+    }
+
+    /// Parse a comma-separated list of words in parenthesis.
+    fn args(&mut self) -> Result<Vec<(String, Range<usize>)>, SpecParseError> {
+        self.expect('(')?;
+        let mut args = Vec::new();
+        if self.eat(')') {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.word("an argument")?);
+            if !self.eat(',') {
+                self.expect(')')?;
+                return Ok(args);
+            }
+        }
+    }
+
+    /// Parse a comma-separated list of elements in parenthesis.
+    fn list<T>(
+        &mut self,
+        mut elem: impl FnMut(&mut Self) -> Result<T, SpecParseError>,
+    ) -> Result<Vec<T>, SpecParseError> {
+        self.expect('(')?;
+        let mut elems = Vec::new();
+        if self.eat(')') {
+            return Ok(elems);
+        }
+        loop {
+            elems.push(elem(self)?);
+            if !self.eat(',') {
+                self.expect(')')?;
+                return Ok(elems);
+            }
+        }
+    }
+
+    /// Parse the entire specification.
+    fn specification(&mut self) -> Result<Specification<P>, SpecParseError> {
+        let mut spec = Specification::new();
+        loop {
+            while self.eat(';') {}
+            if self.peek() == &Token::Eof {
+                return Ok(spec);
+            }
+            if !self.is_keyword("prefix") {
+                return Err(self.unexpected("`prefix`, `;`, or end of input"));
+            }
+            self.next();
+            let (text, span) = self.word("a prefix")?;
+            let prefix = parse_prefix(&text).ok_or_else(|| {
+                SpecParseError::new(SpecParseErrorKind::InvalidPrefix(text), span.clone())
+            })?;
+            self.expect(':')?;
+            let expr = self.expr()?;
+            if spec.insert(prefix, expr).is_some() {
+                return Err(SpecParseError::new(
+                    SpecParseErrorKind::DuplicatePrefix(prefix.to_string()),
+                    span,
+                ));
+            }
+        }
+    }
+
+    /// Parse an expression (`a U b`, `a W b`, or a disjunction).
+    fn expr(&mut self) -> Result<SpecExpr, SpecParseError> {
+        let a = self.expr_or()?;
+        if self.is_keyword("U") {
+            self.next();
+            Ok(SpecExpr::Until(Box::new(a), Box::new(self.expr()?)))
+        } else if self.is_keyword("W") {
+            self.next();
+            Ok(SpecExpr::WeakUntil(Box::new(a), Box::new(self.expr()?)))
+        } else {
+            Ok(a)
+        }
+    }
+
+    /// Parse a disjunction `a | b | ...`.
+    fn expr_or(&mut self) -> Result<SpecExpr, SpecParseError> {
+        let mut xs = vec![self.expr_and()?];
+        while self.eat('|') {
+            xs.push(self.expr_and()?);
+        }
+        Ok(if xs.len() == 1 {
+            xs.pop().unwrap()
+        } else {
+            SpecExpr::Any(xs)
+        })
+    }
+
+    /// Parse a conjunction `a & b & ...`.
+    fn expr_and(&mut self) -> Result<SpecExpr, SpecParseError> {
+        let mut xs = vec![self.expr_unary()?];
+        while self.eat('&') {
+            xs.push(self.expr_unary()?);
+        }
+        Ok(if xs.len() == 1 {
+            xs.pop().unwrap()
+        } else {
+            SpecExpr::All(xs)
+        })
+    }
+
+    /// Parse a unary expression, a parenthesized expression, or an invariant.
+    fn expr_unary(&mut self) -> Result<SpecExpr, SpecParseError> {
+        let kw = match self.peek() {
+            Token::Punct('!') => {
+                self.next();
+                return Ok(SpecExpr::Not(Box::new(self.expr_unary()?)));
+            }
+            Token::Punct('(') => {
+                self.next();
+                let e = self.expr()?;
+                self.expect(')')?;
+                return Ok(e);
+            }
+            Token::Word(w) => w.clone(),
+            _ => return Err(self.unexpected("an expression")),
+        };
+        let (_, span) = self.next();
+        match kw.as_str() {
+            "true" => Ok(SpecExpr::True),
+            "X" => Ok(SpecExpr::Next(Box::new(self.expr_unary()?))),
+            "F" => Ok(SpecExpr::Finally(Box::new(self.expr_unary()?))),
+            "G" => Ok(SpecExpr::Globally(Box::new(self.expr_unary()?))),
+            "all" => Ok(SpecExpr::All(self.list(Self::expr)?)),
+            "any" => Ok(SpecExpr::Any(self.list(Self::expr)?)),
+            "path" => {
+                self.expect('(')?;
+                let router = self.word("a router name")?;
+                let router = self.router(&router)?;
+                self.expect(',')?;
+                let prop = self.prop()?;
+                self.expect(')')?;
+                Ok(SpecExpr::Invariant(Invariant { router, prop }))
+            }
+            name if PATH_PROPS.contains(&name) => {
+                let mut args = self.args()?.into_iter();
+                let router = args.next().ok_or_else(|| {
+                    SpecParseError::new(SpecParseErrorKind::MissingRouter(kw.clone()), span.clone())
+                })?;
+                let router = self.router(&router)?;
+                let prop = self.path_prop(name, span, args.collect())?;
+                Ok(SpecExpr::Invariant(Invariant { router, prop }))
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.unexpected("an expression"))
+            }
+        }
+    }
+
+    /// Parse a property disjunction `a | b | ...`.
+    fn prop(&mut self) -> Result<Property, SpecParseError> {
+        let mut xs = vec![self.prop_and()?];
+        while self.eat('|') {
+            xs.push(self.prop_and()?);
+        }
+        Ok(if xs.len() == 1 {
+            xs.pop().unwrap()
+        } else {
+            Property::Any(xs)
+        })
+    }
+
+    /// Parse a property conjunction `a & b & ...`.
+    fn prop_and(&mut self) -> Result<Property, SpecParseError> {
+        let mut xs = vec![self.prop_unary()?];
+        while self.eat('&') {
+            xs.push(self.prop_unary()?);
+        }
+        Ok(if xs.len() == 1 {
+            xs.pop().unwrap()
+        } else {
+            Property::All(xs)
+        })
+    }
+
+    /// Parse a negated property, a parenthesized property, or a path property.
+    fn prop_unary(&mut self) -> Result<Property, SpecParseError> {
+        let kw = match self.peek() {
+            Token::Punct('!') => {
+                self.next();
+                return Ok(Property::Not(Box::new(self.prop_unary()?)));
+            }
+            Token::Punct('(') => {
+                self.next();
+                let p = self.prop()?;
+                self.expect(')')?;
+                return Ok(p);
+            }
+            Token::Word(w) => w.clone(),
+            _ => return Err(self.unexpected("a property")),
+        };
+        let (_, span) = self.next();
+        match kw.as_str() {
+            "true" => Ok(Property::True),
+            "all" => Ok(Property::All(self.list(Self::prop)?)),
+            "any" => Ok(Property::Any(self.list(Self::prop)?)),
+            name if PATH_PROPS.contains(&name) => {
+                let args = if self.peek() == &Token::Punct('(') {
+                    self.args()?
+                } else {
+                    Vec::new()
+                };
+                self.path_prop(name, span, args)
+            }
+            _ => {
+                self.pos -= 1;
+                Err(self.unexpected("a property"))
+            }
+        }
+    }
+
+    /// Build the path property `name` from its arguments (without the source router). For
+    /// `egress`, this also parses the set of egresses, i.e., `= e` or `in {e1, e2, ...}`.
+    fn path_prop(
+        &mut self,
+        name: &str,
+        span: Range<usize>,
+        args: Vec<(String, Range<usize>)>,
+    ) -> Result<Property, SpecParseError> {
+        let arity = |expected: usize| {
+            if args.len() == expected {
+                Ok(())
+            } else {
+                Err(SpecParseError::new(
+                    SpecParseErrorKind::WrongArity {
+                        name: name.to_string(),
+                        expected,
+                        found: args.len(),
+                    },
+                    span.clone(),
+                ))
+            }
+        };
+        match name {
+            "reach" => arity(0).map(|_| Property::Reachability),
+            "waypoint" => {
+                arity(1)?;
+                Ok(Property::Waypoint(self.router(&args[0])?))
+            }
+            "edge" => {
+                arity(2)?;
+                Ok(Property::Edge(
+                    self.router(&args[0])?,
+                    self.router(&args[1])?,
+                ))
+            }
+            "sequence" => Ok(Property::Sequence(
+                args.iter()
+                    .map(|a| self.router(a))
+                    .collect::<Result<_, _>>()?,
+            )),
+            "max_hops" => {
+                arity(1)?;
+                let (k, span) = &args[0];
+                k.parse().map(Property::MaxHops).map_err(|_| {
+                    SpecParseError::new(SpecParseErrorKind::InvalidNumber(k.clone()), span.clone())
+                })
+            }
+            "avoid" => {
+                arity(1)?;
+                Ok(Property::Avoid(self.router(&args[0])?))
+            }
+            "egress" => {
+                arity(0)?;
+                let egresses = if self.eat('=') {
+                    vec![self.word("a router name")?]
+                } else if self.is_keyword("in") {
+                    self.next();
+                    self.expect('{')?;
+                    let mut egresses = Vec::new();
+                    if !self.eat('}') {
+                        loop {
+                            egresses.push(self.word("a router name")?);
+                            if !self.eat(',') {
+                                self.expect('}')?;
+                                break;
+                            }
+                        }
+                    }
+                    egresses
+                } else {
+                    return Err(self.unexpected("`=` or `in`"));
+                };
+                Ok(Property::Egress(
+                    egresses
+                        .iter()
+                        .map(|e| self.router(e))
+                        .collect::<Result<_, _>>()?,
+                ))
+            }
+            _ => unreachable!("Unknown path property {name}"),
+        }
+    }
+}
+
+/// Structure to display a [`Specification`], a [`SpecExpr`], an [`Invariant`], or a [`Property`]
+/// in the text format, such that [`parse_specification`] (or [`parse_spec_expr`]) yields the same
+/// value.
+#[derive(Debug)]
+pub struct SpecText<'a, 'n, T, P: Prefix, Q> {
+    /// The value to display
+    value: &'a T,
+    /// The network used to get the router names
+    net: &'n Network<P, Q>,
+}
+
+impl<'a, 'n, T, P: Prefix, Q> SpecText<'a, 'n, T, P, Q> {
+    /// Create a new formatter for the value, using the router names from `net`.
+    pub fn new(value: &'a T, net: &'n Network<P, Q>) -> Self {
+        Self { value, net }
+    }
+}
+
+impl<P: Prefix, Q> Display for SpecText<'_, '_, Specification<P>, P, Q> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        for (i, (prefix, expr)) in self.value.iter().sorted_by_key(|(p, _)| *p).enumerate() {
+            if i > 0 {
+                f.write_char('\n')?;
+            }
+            write!(f, "prefix {prefix}: ")?;
+            write_expr(f, expr, self.net)?;
+        }
+        Ok(())
+    }
+}
+
+impl<P: Prefix, Q> Display for SpecText<'_, '_, SpecExpr, P, Q> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write_expr(f, self.value, self.net)
+    }
+}
+
+impl<P: Prefix, Q> Display for SpecText<'_, '_, Invariant, P, Q> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write_invariant(f, self.value, self.net)
+    }
+}
+
+impl<P: Prefix, Q> Display for SpecText<'_, '_, Property, P, Q> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write_prop(f, self.value, self.net)
+    }
+}
+
+/// Write a router name, and quote it if necessary.
+fn write_router<P: Prefix, Q>(
+    f: &mut Formatter<'_>,
+    r: RouterId,
+    net: &Network<P, Q>,
+) -> FmtResult {
+    let name = r.fmt(net);
+    if !name.is_empty() && name.chars().all(is_word_char) {
+        f.write_str(name)
+    } else {
+        f.write_char('"')?;
+        for c in name.chars() {
+            if matches!(c, '"' | '\\') {
+                f.write_char('\\')?;
+            }
+            f.write_char(c)?;
+        }
+        f.write_char('"')
+    }
+}
+
+/// Write a comma-separated list of router names.
+fn write_routers<P: Prefix, Q>(
+    f: &mut Formatter<'_>,
+    routers: impl IntoIterator<Item = RouterId>,
+    net: &Network<P, Q>,
+) -> FmtResult {
+    for (i, r) in routers.into_iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write_router(f, r, net)?;
+    }
+    Ok(())
+}
+
+/// Write a conjunction or disjunction. Lists with at least two elements are written using the
+/// infix operator `op`, and all others using the function `func`.
+fn write_list<T>(
+    f: &mut Formatter<'_>,
+    xs: &[T],
+    op: &str,
+    func: &str,
+    mut elem: impl FnMut(&mut Formatter<'_>, &T) -> FmtResult,
+) -> FmtResult {
+    let sep = if xs.len() >= 2 {
+        f.write_char('(')?;
+        op
+    } else {
+        write!(f, "{func}(")?;
+        ", "
+    };
+    for (i, x) in xs.iter().enumerate() {
+        if i > 0 {
+            f.write_str(sep)?;
+        }
+        elem(f, x)?;
+    }
+    f.write_char(')')
+}
+
+/// Write a specification expression.
+fn write_expr<P: Prefix, Q>(
+    f: &mut Formatter<'_>,
+    expr: &SpecExpr,
+    net: &Network<P, Q>,
+) -> FmtResult {
+    let rec = |f: &mut Formatter<'_>, x: &SpecExpr| write_expr(f, x, net);
+    match expr {
+        SpecExpr::True => f.write_str("true"),
+        SpecExpr::Not(x) => {
+            f.write_char('!')?;
+            rec(f, x)
+        }
+        SpecExpr::Next(x) => {
+            f.write_str("X ")?;
+            rec(f, x)
+        }
+        SpecExpr::Finally(x) => {
+            f.write_str("F ")?;
+            rec(f, x)
+        }
+        SpecExpr::Globally(x) => {
+            f.write_str("G ")?;
+            rec(f, x)
+        }
+        SpecExpr::All(xs) => write_list(f, xs, " & ", "all", rec),
+        SpecExpr::Any(xs) => write_list(f, xs, " | ", "any", rec),
+        SpecExpr::Until(a, b) | SpecExpr::WeakUntil(a, b) => {
+            let op = if matches!(expr, SpecExpr::Until(_, _)) {
+                "U"
+            } else {
+                "W"
+            };
+            f.write_char('(')?;
+            rec(f, a)?;
+            write!(f, " {op} ")?;
+            rec(f, b)?;
+            f.write_char(')')
+        }
+        SpecExpr::Invariant(i) => write_invariant(f, i, net),
+    }
+}
+
+/// Write an invariant, either using the short form of path properties, or as `path(r, <prop>)`.
+fn write_invariant<P: Prefix, Q>(
+    f: &mut Formatter<'_>,
+    inv: &Invariant,
+    net: &Network<P, Q>,
+) -> FmtResult {
+    let name = match &inv.prop {
+        Property::Reachability => "reach",
+        Property::Waypoint(_) => "waypoint",
+        Property::Edge(_, _) => "edge",
+        Property::Sequence(_) => "sequence",
+        Property::MaxHops(_) => "max_hops",
+        Property::Egress(_) => "egress",
+        Property::Avoid(_) => "avoid",
+        Property::All(_) | Property::Any(_) | Property::Not(_) | Property::True => {
+            f.write_str("path(")?;
+            write_router(f, inv.router, net)?;
+            f.write_str(", ")?;
+            write_prop(f, &inv.prop, net)?;
+            return f.write_char(')');
+        }
+    };
+    write!(f, "{name}(")?;
+    write_router(f, inv.router, net)?;
+    match &inv.prop {
+        Property::Waypoint(x) | Property::Avoid(x) => {
+            f.write_str(", ")?;
+            write_router(f, *x, net)?;
+        }
+        Property::Edge(a, b) => {
+            f.write_str(", ")?;
+            write_routers(f, [*a, *b], net)?;
+        }
+        Property::Sequence(ws) if !ws.is_empty() => {
+            f.write_str(", ")?;
+            write_routers(f, ws.iter().copied(), net)?;
+        }
+        Property::MaxHops(k) => write!(f, ", {k}")?,
+        _ => {}
+    }
+    f.write_char(')')?;
+    if let Property::Egress(es) = &inv.prop {
+        write_egress(f, es, net)?;
+    }
+    Ok(())
+}
+
+/// Write the set of egresses, either as ` = e` or as ` in {e1, e2, ...}`.
+fn write_egress<P: Prefix, Q>(
+    f: &mut Formatter<'_>,
+    egresses: &BTreeSet<RouterId>,
+    net: &Network<P, Q>,
+) -> FmtResult {
+    if egresses.len() == 1 {
+        f.write_str(" = ")?;
+        write_routers(f, egresses.iter().copied(), net)
+    } else {
+        f.write_str(" in {")?;
+        write_routers(f, egresses.iter().copied(), net)?;
+        f.write_char('}')
+    }
+}
+
+/// Write a property (without the source router).
+fn write_prop<P: Prefix, Q>(
+    f: &mut Formatter<'_>,
+    prop: &Property,
+    net: &Network<P, Q>,
+) -> FmtResult {
+    let rec = |f: &mut Formatter<'_>, x: &Property| write_prop(f, x, net);
+    match prop {
+        Property::All(xs) => write_list(f, xs, " & ", "all", rec),
+        Property::Any(xs) => write_list(f, xs, " | ", "any", rec),
+        Property::Not(x) => {
+            f.write_char('!')?;
+            rec(f, x)
+        }
+        Property::True => f.write_str("true"),
+        Property::Reachability => f.write_str("reach"),
+        Property::Waypoint(x) => {
+            f.write_str("waypoint(")?;
+            write_router(f, *x, net)?;
+            f.write_char(')')
+        }
+        Property::Edge(a, b) => {
+            f.write_str("edge(")?;
+            write_routers(f, [*a, *b], net)?;
+            f.write_char(')')
+        }
+        Property::Sequence(ws) => {
+            f.write_str("sequence(")?;
+            write_routers(f, ws.iter().copied(), net)?;
+            f.write_char(')')
+        }
+        Property::MaxHops(k) => write!(f, "max_hops({k})"),
+        Property::Egress(es) => {
+            f.write_str("egress")?;
+            write_egress(f, es, net)
+        }
+        Property::Avoid(x) => {
+            f.write_str("avoid(")?;
+            write_router(f, *x, net)?;
+            f.write_char(')')
+        }
+    }
+}
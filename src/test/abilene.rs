@@ -118,7 +118,7 @@ fn ny_ext() -> RouterId {
 }
 
 /// get the network. The routing advertisements are: `hs, (su, ny)`.
-fn get_net() -> (Network<P, BasicEventQueue<P>>, P, Specification) {
+fn get_net() -> (Network<P, BasicEventQueue<P>>, P, Specification<P>) {
     let mut net = TopologyZoo::Abilene.build(BasicEventQueue::<P>::new());
     let p = P::from(0);
 
@@ -182,7 +182,7 @@ fn get_net() -> (Network<P, BasicEventQueue<P>>, P, Specification) {
 }
 
 #[allow(clippy::type_complexity)]
-fn get_net_two_prefixes() -> (Network<P, BasicEventQueue<P>>, (P, P), Specification) {
+fn get_net_two_prefixes() -> (Network<P, BasicEventQueue<P>>, (P, P), Specification<P>) {
     let (mut net, p0, _) = get_net();
     let p1 = P::from(1);
 
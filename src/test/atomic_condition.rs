@@ -0,0 +1,80 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test that atomic conditions follow the decision process of the router.
+
+use atomic_command::AtomicCondition;
+use bgpsim::prelude::*;
+use maplit::btreeset;
+use test_log::test;
+
+use crate::P;
+
+/// Router 0 with two eBGP neighbors in different ASes. Neighbor 1 advertises its route first, and
+/// neighbor 2 advertises a route with a lower MED afterwards.
+fn get_net() -> (Network<P, BasicEventQueue<P>>, RouterId, RouterId, RouterId) {
+    let mut net: Network<P, BasicEventQueue<P>> = Network::default();
+    let r = net.add_router("r");
+    let e1 = net.add_external_router("e1", AsId(65101));
+    let e2 = net.add_external_router("e2", AsId(65102));
+    for e in [e1, e2] {
+        net.add_link(r, e);
+        net.set_link_weight(r, e, 1.0).unwrap();
+        net.set_link_weight(e, r, 1.0).unwrap();
+        net.set_bgp_session(r, e, Some(BgpSessionType::EBgp))
+            .unwrap();
+    }
+    let p = P::from(0);
+    net.advertise_external_route(e1, p, [65101, 10], Some(20), [])
+        .unwrap();
+    net.advertise_external_route(e2, p, [65102, 10], Some(10), [])
+        .unwrap();
+    (net, r, e1, e2)
+}
+
+#[test]
+fn routes_less_preferred_always_compare_med() {
+    let (mut net, r, e1, e2) = get_net();
+    let p = P::from(0);
+
+    // without always-compare-med, the older route from e1 is selected.
+    let selected = |net: &Network<P, BasicEventQueue<P>>| {
+        net.get_device(r)
+            .unwrap_internal()
+            .get_selected_bgp_route(p)
+            .unwrap()
+            .clone()
+    };
+    assert_eq!(selected(&net).from_id, e1);
+
+    // with always-compare-med, the route from e2 with the lower MED is selected.
+    net.set_always_compare_med(r, true).unwrap();
+    let route = selected(&net);
+    assert_eq!(route.from_id, e2);
+
+    let cond = AtomicCondition::RoutesLessPreferred {
+        router: r,
+        prefix: p,
+        good_neighbors: btreeset! {e2},
+        route,
+    };
+    assert!(cond.check(&net).unwrap());
+
+    // without always-compare-med, the route from e1 is preferred again.
+    net.set_always_compare_med(r, false).unwrap();
+    assert!(!cond.check(&net).unwrap());
+}
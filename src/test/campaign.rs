@@ -0,0 +1,140 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test planning and executing a campaign of multiple commands.
+
+use bgpsim::{
+    builder::{constant_link_weight, NetworkBuilder},
+    config::{ConfigExpr, ConfigModifier, NetworkConfig},
+    prelude::*,
+};
+use test_log::test;
+
+use crate::{
+    decomposition::campaign::Campaign,
+    runtime::sim::run,
+    specification::{Specification, SpecificationBuilder},
+    P,
+};
+
+/// Clique with 4 nodes and three external routers connected to 0, 1, and 2.
+#[allow(clippy::type_complexity)]
+fn prepare() -> (
+    Network<P, BasicEventQueue<P>>,
+    Specification<P>,
+    Vec<ConfigModifier<P>>,
+) {
+    let mut net: Network<P, BasicEventQueue<P>> =
+        NetworkBuilder::build_complete_graph(BasicEventQueue::<P>::new(), 4);
+    net.build_external_routers(
+        |_, _| vec![RouterId::from(0), RouterId::from(1), RouterId::from(2)],
+        (),
+    )
+    .unwrap();
+    net.build_link_weights(constant_link_weight, 1.0).unwrap();
+    net.build_ibgp_full_mesh().unwrap();
+    net.build_ebgp_sessions().unwrap();
+    let p = P::from(0);
+    net.build_advertisements(
+        p,
+        |_, _| vec![vec![4.into()], vec![5.into()], vec![6.into()]],
+        (),
+    )
+    .unwrap();
+    let spec = SpecificationBuilder::Reachability.build_all(&net, None, [p]);
+
+    let commands = vec![remove_ebgp_session(0, 4), remove_ebgp_session(2, 6)];
+
+    (net, spec, commands)
+}
+
+/// Command that removes the eBGP session between `r` and `e`.
+fn remove_ebgp_session(r: u32, e: u32) -> ConfigModifier<P> {
+    ConfigModifier::Remove(ConfigExpr::BgpSession {
+        source: r.into(),
+        target: e.into(),
+        session_type: BgpSessionType::EBgp,
+    })
+}
+
+/// Check that the campaign applies all commands, and that each stage can be executed.
+fn check(campaign: Campaign<P>) {
+    let (net, spec, _) = prepare();
+    let commands = campaign.commands().to_vec();
+    let plan = campaign.plan(&net, &spec).unwrap();
+
+    let num_commands: usize = plan.stages.iter().map(|s| s.patch.modifiers.len()).sum();
+    assert_eq!(num_commands, commands.len());
+
+    let mut exp_net = net.clone();
+    for cmd in commands.iter() {
+        exp_net.apply_modifier(cmd).unwrap();
+    }
+
+    let mut net = net;
+    for decomp in plan.decompositions() {
+        net = run(net, decomp.clone(), &spec).unwrap().0;
+    }
+    assert_eq!(net, exp_net);
+}
+
+#[test]
+fn unordered() {
+    let (_, _, commands) = prepare();
+    check(Campaign::new(commands));
+}
+
+#[test]
+fn ordered() {
+    let (_, _, commands) = prepare();
+    check(Campaign::ordered(commands));
+}
+
+#[test]
+fn no_grouping() {
+    let (net, spec, commands) = prepare();
+    let plan = Campaign::new(commands.clone())
+        .grouping(false)
+        .plan(&net, &spec)
+        .unwrap();
+    assert_eq!(plan.stages.len(), commands.len());
+    check(Campaign::new(commands).grouping(false));
+}
+
+/// Removing the session of router 0 before the one of router 1 requires more rounds than the
+/// opposite order. The campaign must pick the cheaper order, and not the given one.
+#[test]
+fn cheaper_order() {
+    let (net, spec, _) = prepare();
+    let commands = vec![remove_ebgp_session(0, 4), remove_ebgp_session(1, 5)];
+
+    let given = Campaign::ordered(commands.clone())
+        .grouping(false)
+        .plan(&net, &spec)
+        .unwrap();
+    let plan = Campaign::new(commands.clone())
+        .grouping(false)
+        .plan(&net, &spec)
+        .unwrap();
+
+    assert_eq!(plan.stages.len(), 2);
+    assert_eq!(plan.stages[0].patch.modifiers, vec![commands[1].clone()]);
+    assert_eq!(plan.stages[1].patch.modifiers, vec![commands[0].clone()]);
+    assert!(plan.cost().total() < given.cost().total());
+
+    check(Campaign::new(commands).grouping(false));
+}
@@ -0,0 +1,200 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test storing the progress of the controller in a checkpoint and resuming from it.
+
+use bgpsim::{config::ConfigModifier, prelude::*};
+use test_log::test;
+
+use crate::{
+    decomposition::decompose,
+    runtime::{
+        controller::{AtomicCommandState, Checkpoint, Controller, ControllerStage, ResumeError},
+        sim::run_no_checks,
+    },
+    specification::SpecificationBuilder,
+    P,
+};
+
+use super::{clique_net, remove_ebgp_session, remove_session_0_6, remove_session_0_8, ring_net};
+
+/// Pass the checkpoint through its serialized form (if serde is enabled).
+fn round_trip(checkpoint: Checkpoint<P>) -> Checkpoint<P> {
+    #[cfg(feature = "serde")]
+    {
+        serde_json::from_str(&serde_json::to_string(&checkpoint).unwrap()).unwrap()
+    }
+    #[cfg(not(feature = "serde"))]
+    {
+        checkpoint
+    }
+}
+
+/// Resume the update from a checkpoint written after each step of the controller. The resumed
+/// update must reach the same final state as the update without interruption.
+fn check_resume_after_every_step(net: Network<P, BasicEventQueue<P>>, command: ConfigModifier<P>) {
+    let spec = SpecificationBuilder::Reachability.build_all(&net, None, [P::from(0)]);
+    let decomp = decompose(&net, command, &spec).unwrap();
+    let (exp_net, _) = run_no_checks(net.clone(), decomp.clone()).unwrap();
+
+    for steps in 1.. {
+        // perform the first `steps` steps of the controller
+        let mut net = net.clone();
+        let mut controller = Controller::new(decomp.clone());
+        for _ in 0..steps {
+            controller.step_sim(&mut net).unwrap();
+        }
+        if controller.is_finished() {
+            break;
+        }
+
+        // resume the update from the checkpoint, and complete it.
+        let checkpoint = round_trip(controller.checkpoint());
+        let mut controller = Controller::resume(decomp.clone(), checkpoint, &net).unwrap();
+        let mut remaining = 1000;
+        while !controller.is_finished() {
+            controller.step_sim(&mut net).unwrap();
+            remaining -= 1;
+            assert!(
+                remaining > 0,
+                "Resumed update did not finish after {steps} steps"
+            );
+        }
+        assert!(
+            net.weak_eq(&exp_net),
+            "Wrong final state after {steps} steps"
+        );
+    }
+}
+
+#[test]
+fn resume_after_every_step() {
+    check_resume_after_every_step(clique_net(), remove_session_0_8());
+}
+
+#[test]
+fn ring_resume_after_every_step() {
+    check_resume_after_every_step(ring_net(), remove_session_0_6());
+}
+
+/// Resuming from the checkpoint of a different decomposition must fail.
+fn check_resume_mismatch(
+    net: Network<P, BasicEventQueue<P>>,
+    command: ConfigModifier<P>,
+    other: ConfigModifier<P>,
+) {
+    let spec = SpecificationBuilder::Reachability.build_all(&net, None, [P::from(0)]);
+    let decomp = decompose(&net, command, &spec).unwrap();
+    let other = decompose(&net, other, &spec).unwrap();
+
+    let mut controller = Controller::new(decomp);
+    while controller.state().name() != "Main" {
+        controller.next_stage();
+    }
+    assert!(matches!(
+        Controller::resume(other, controller.checkpoint(), &net),
+        Err(ResumeError::Mismatch(_))
+    ));
+}
+
+#[test]
+fn resume_mismatch() {
+    check_resume_mismatch(
+        clique_net(),
+        remove_session_0_8(),
+        remove_ebgp_session(1.into(), 9.into()),
+    );
+}
+
+#[test]
+fn ring_resume_mismatch() {
+    check_resume_mismatch(
+        ring_net(),
+        remove_session_0_6(),
+        remove_ebgp_session(3.into(), 7.into()),
+    );
+}
+
+/// Resume the update from a checkpoint that was written before the main command was applied,
+/// although the main command was applied afterwards.
+fn check_resume_applied_command(
+    mut net: Network<P, BasicEventQueue<P>>,
+    command: ConfigModifier<P>,
+) {
+    let spec = SpecificationBuilder::Reachability.build_all(&net, None, [P::from(0)]);
+    let decomp = decompose(&net, command, &spec).unwrap();
+    let (exp_net, _) = run_no_checks(net.clone(), decomp.clone()).unwrap();
+
+    let mut controller = Controller::new(decomp.clone());
+    while controller.state().name() != "Main" {
+        controller.step_sim(&mut net).unwrap();
+    }
+    let checkpoint = round_trip(controller.checkpoint());
+
+    // apply the main command without writing a new checkpoint.
+    let s = match controller.state() {
+        ControllerStage::Main(s) => s,
+        _ => unreachable!(),
+    };
+    assert_eq!(s.entries, vec![AtomicCommandState::Precondition]);
+    s.commands[0][0].command.apply(&mut net).unwrap();
+
+    // the resumed controller must not apply the main command again.
+    let mut controller = Controller::resume(decomp, checkpoint, &net).unwrap();
+    let s = match controller.state() {
+        ControllerStage::Main(s) => s,
+        _ => unreachable!(),
+    };
+    assert_ne!(s.entries, vec![AtomicCommandState::Precondition]);
+    while !controller.is_finished() {
+        controller.step_sim(&mut net).unwrap();
+    }
+    assert!(net.weak_eq(&exp_net));
+}
+
+#[test]
+fn resume_applied_command() {
+    check_resume_applied_command(clique_net(), remove_session_0_8());
+}
+
+#[test]
+fn ring_resume_applied_command() {
+    check_resume_applied_command(ring_net(), remove_session_0_6());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn checkpoint_write_error() {
+    use crate::runtime::sim::SimError;
+
+    let mut net = clique_net();
+    let spec = SpecificationBuilder::Reachability.build_all(&net, None, [P::from(0)]);
+    let decomp = decompose(&net, remove_session_0_8(), &spec).unwrap();
+
+    // the parent of the checkpoint file is not a directory.
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("Cargo.toml")
+        .join("checkpoint.json");
+    let mut controller = Controller::new(decomp).with_checkpoint_file(path);
+    let result = loop {
+        match controller.step_sim(&mut net) {
+            Ok(_) if !controller.is_finished() => {}
+            x => break x,
+        }
+    };
+    assert!(matches!(result, Err(SimError::Checkpoint(_))));
+}
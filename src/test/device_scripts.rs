@@ -0,0 +1,150 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test the export of a migration plan as device change scripts.
+
+use std::collections::HashMap;
+
+use bgpsim::{
+    config::ConfigModifier,
+    export::{cisco_frr_generators::Target, CiscoFrrCfgGen, DefaultAddressorBuilder},
+    prelude::*,
+};
+use itertools::Itertools;
+use test_log::test;
+
+use crate::{decomposition::decompose, specification::SpecificationBuilder, P};
+
+use super::{clique_net, remove_session_0_6, remove_session_0_8, ring_net};
+
+fn iface_names(target: Target) -> Vec<String> {
+    match target {
+        Target::CiscoNexus7000 => (1..=48).map(|i| format!("Ethernet8/{i}")).collect(),
+        Target::CiscoIos => (0..48).map(|i| format!("GigabitEthernet0/{i}")).collect(),
+        Target::Frr => (1..=8).map(|i| format!("eth{i}")).collect(),
+        Target::Junos => (0..48).map(|i| format!("ge-0/0/{i}")).collect(),
+    }
+}
+
+/// Export the migration plan for the command for all targets, and check the generated scripts.
+fn check_device_scripts(net: &Network<P, BasicEventQueue<P>>, command: ConfigModifier<P>) {
+    let spec = SpecificationBuilder::Reachability.build_all(net, None, [P::from(0)]);
+    let decomp = decompose(net, command, &spec).unwrap();
+
+    for target in [
+        Target::CiscoNexus7000,
+        Target::CiscoIos,
+        Target::Frr,
+        Target::Junos,
+    ] {
+        let mut addressor = DefaultAddressorBuilder {
+            internal_ip_range: "10.0.0.0/8".parse().unwrap(),
+            external_ip_range: "20.0.0.0/8".parse().unwrap(),
+            ..Default::default()
+        }
+        .build(net)
+        .unwrap();
+        let mut gens: HashMap<RouterId, CiscoFrrCfgGen<P>> = net
+            .get_routers()
+            .into_iter()
+            .map(|r| {
+                (
+                    r,
+                    CiscoFrrCfgGen::new(net, r, target, iface_names(target)).unwrap(),
+                )
+            })
+            .collect();
+
+        let scripts = decomp
+            .device_scripts(net, &mut addressor, &mut gens)
+            .unwrap();
+        assert!(scripts
+            .stages
+            .iter()
+            .map(|s| s.index)
+            .tuple_windows()
+            .all(|(a, b)| a <= b));
+        assert!(scripts
+            .stages
+            .iter()
+            .all(|s| s.steps.iter().all(|x| !x.is_empty())));
+
+        // the main stage removes the session on router 0.
+        let main = scripts.stages.iter().find(|s| s.name == "main").unwrap();
+        let r0 = main
+            .steps
+            .iter()
+            .flatten()
+            .find(|s| s.router == 0.into())
+            .unwrap();
+        assert_eq!(r0.target, target);
+        assert!(match target {
+            Target::CiscoNexus7000 | Target::CiscoIos | Target::Frr => {
+                r0.config.contains("no neighbor")
+            }
+            Target::Junos => r0.config.contains("delete protocols bgp group"),
+        });
+
+        // all checks are show commands in JSON format, except on Cisco IOS
+        let checks = scripts
+            .stages
+            .iter()
+            .flat_map(|s| s.steps.iter().flatten())
+            .flat_map(|s| s.pre.iter().chain(s.post.iter()))
+            .collect_vec();
+        assert!(!checks.is_empty());
+        for check in checks {
+            assert!(check.command.starts_with("show "));
+            match target {
+                Target::CiscoNexus7000 => assert!(check.command.ends_with(" | json")),
+                Target::CiscoIos => assert!(!check.command.contains("json")),
+                Target::Frr => assert!(check.command.ends_with(" json")),
+                Target::Junos => assert!(check.command.ends_with(" | display json")),
+            }
+        }
+
+        // write all scripts into a directory, which is removed when `tmp` is dropped.
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        scripts.write_to_dir(dir).unwrap();
+        let plan = std::fs::read_to_string(dir.join("plan.txt")).unwrap();
+        let steps = plan.lines().filter(|l| !l.starts_with('#')).collect_vec();
+        assert_eq!(
+            steps.len(),
+            scripts.stages.iter().map(|s| s.steps.len()).sum::<usize>()
+        );
+        for line in steps {
+            let mut parts = line.split(' ');
+            let step_dir = dir.join(parts.next().unwrap());
+            for router in parts {
+                assert!(["pre", "cfg", "post"]
+                    .iter()
+                    .any(|ext| step_dir.join(format!("{router}.{ext}")).exists()));
+            }
+        }
+    }
+}
+
+#[test]
+fn device_scripts() {
+    check_device_scripts(&clique_net(), remove_session_0_8());
+}
+
+#[test]
+fn ring_device_scripts() {
+    check_device_scripts(&ring_net(), remove_session_0_6());
+}
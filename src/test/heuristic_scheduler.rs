@@ -0,0 +1,115 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test the heuristic scheduler, both when selected explicitly and as a fallback for the ILP
+//! scheduler.
+
+use std::time::Duration;
+
+use bgpsim::{
+    config::{ConfigModifier, ConfigPatch},
+    prelude::*,
+};
+use test_log::test;
+
+use crate::{
+    decomposition::{
+        bgp_dependencies::find_dependencies,
+        decompose_patch_with,
+        ilp_scheduler::{schedule_heuristic, NodeSchedule, ScheduleOptions, SchedulerKind},
+        CommandInfo,
+    },
+    runtime::sim::run,
+    specification::SpecificationBuilder,
+    P,
+};
+
+use super::{clique_net, remove_session_0_6, remove_session_0_8, ring_net};
+
+/// Schedule the command with the heuristic scheduler. Every router that changes its forwarding must
+/// change it in its own step.
+fn check_heuristic_schedule(net: &Network<P, BasicEventQueue<P>>, command: ConfigModifier<P>) {
+    let spec = SpecificationBuilder::Reachability.build_all(net, None, [P::from(0)]);
+    let info = CommandInfo::new(net, command, &spec).unwrap();
+    let bgp_deps = find_dependencies(&info);
+
+    let (schedule, trace) = schedule_heuristic(&info, &bgp_deps, P::from(0)).unwrap();
+    assert_eq!(trace.len(), info.fw_diff[&P::from(0)].len());
+    assert!(trace.iter().all(|step| step.len() == 1));
+    let cost: usize = schedule.values().map(NodeSchedule::cost).sum();
+    log::info!("Heuristic schedule with {cost} temporary sessions: {schedule:#?}");
+}
+
+/// Decompose the command using the given options, and run the decomposition.
+fn decompose_and_run(
+    net: Network<P, BasicEventQueue<P>>,
+    command: ConfigModifier<P>,
+    options: ScheduleOptions,
+) {
+    let spec = SpecificationBuilder::Reachability.build_all(&net, None, [P::from(0)]);
+    let decomposition =
+        decompose_patch_with(&net, &ConfigPatch::from(command), &spec, options).unwrap();
+    run(net, decomposition, &spec).unwrap();
+}
+
+/// Options that select the heuristic scheduler.
+fn heuristic() -> ScheduleOptions {
+    ScheduleOptions {
+        scheduler: SchedulerKind::Heuristic,
+        ..Default::default()
+    }
+}
+
+/// Options that select the ILP scheduler without any time budget, such that it falls back to the
+/// heuristic scheduler.
+fn fallback() -> ScheduleOptions {
+    ScheduleOptions {
+        time_budget: Duration::ZERO,
+        scheduler: SchedulerKind::IlpWithFallback,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn heuristic_schedule() {
+    check_heuristic_schedule(&clique_net(), remove_session_0_8());
+}
+
+#[test]
+fn ring_heuristic_schedule() {
+    check_heuristic_schedule(&ring_net(), remove_session_0_6());
+}
+
+#[test]
+fn heuristic_decomposition() {
+    decompose_and_run(clique_net(), remove_session_0_8(), heuristic());
+}
+
+#[test]
+fn ring_heuristic_decomposition() {
+    decompose_and_run(ring_net(), remove_session_0_6(), heuristic());
+}
+
+#[test]
+fn fallback_without_time_budget() {
+    decompose_and_run(clique_net(), remove_session_0_8(), fallback());
+}
+
+#[test]
+fn ring_fallback_without_time_budget() {
+    decompose_and_run(ring_net(), remove_session_0_6(), fallback());
+}
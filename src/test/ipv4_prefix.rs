@@ -0,0 +1,113 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test the decomposition with overlapping IPv4 prefixes.
+
+use bgpsim::{
+    builder::{constant_link_weight, NetworkBuilder},
+    config::{ConfigExpr, ConfigModifier},
+    prelude::*,
+};
+use test_log::test;
+
+use crate::{
+    decomposition::decompose,
+    runtime::sim::run,
+    specification::{Invariant, Property, SpecExpr, Specification},
+};
+
+/// Type alias for the network with IPv4 prefixes.
+type Net = Network<Ipv4Prefix, BasicEventQueue<Ipv4Prefix>>;
+
+/// Clique with 4 nodes, and two external nodes (4 connected to 0, and 5 connected to 2).
+fn get_net() -> Net {
+    let mut net: Net = NetworkBuilder::build_complete_graph(BasicEventQueue::new(), 4);
+    net.build_external_routers(|_, _| vec![RouterId::from(0), RouterId::from(2)], ())
+        .unwrap();
+    net.build_link_weights(constant_link_weight, 1.0).unwrap();
+    net.build_ibgp_full_mesh().unwrap();
+    net.build_ebgp_sessions().unwrap();
+    net
+}
+
+/// Require reachability for all internal routers and all given prefixes.
+fn build_spec(net: &Net, prefixes: &[Ipv4Prefix]) -> Specification<Ipv4Prefix> {
+    prefixes
+        .iter()
+        .map(|p| {
+            let invariants = net
+                .get_routers()
+                .into_iter()
+                .map(|router| {
+                    SpecExpr::Invariant(Invariant {
+                        router,
+                        prop: Property::Reachability,
+                    })
+                })
+                .collect();
+            (*p, SpecExpr::Globally(Box::new(SpecExpr::All(invariants))))
+        })
+        .collect()
+}
+
+/// The command that removes the session from 0 to 4.
+fn command() -> ConfigModifier<Ipv4Prefix> {
+    ConfigModifier::Remove(ConfigExpr::BgpSession {
+        source: 0.into(),
+        target: 4.into(),
+        session_type: BgpSessionType::EBgp,
+    })
+}
+
+/// Both the aggregate and the more-specific prefix are advertised by both egresses. Each router has
+/// an entry for both prefixes, before and after the reconfiguration, such that both prefixes can be
+/// scheduled independently.
+#[test]
+fn aggregate_and_more_specific() {
+    let mut net = get_net();
+    let aggregate: Ipv4Prefix = "10.0.0.0/8".parse().unwrap();
+    let specific: Ipv4Prefix = "10.1.0.0/16".parse().unwrap();
+    for p in [aggregate, specific] {
+        net.build_advertisements(p, |_, _| vec![vec![4.into()], vec![5.into()]], ())
+            .unwrap();
+    }
+    let spec = build_spec(&net, &[aggregate, specific]);
+
+    let decomposition = decompose(&net, command(), &spec).unwrap();
+    run(net, decomposition, &spec).unwrap();
+}
+
+/// The more-specific prefix is only advertised by the egress that disappears. Afterwards, the
+/// routers forward the traffic for the more-specific prefix using the aggregate, whose forwarding
+/// state changes as well. Both prefixes are scheduled together, and the commands of the
+/// more-specific prefix are executed in lockstep with the aggregate.
+#[test]
+fn fallback_to_aggregate() {
+    let mut net = get_net();
+    let aggregate: Ipv4Prefix = "10.0.0.0/8".parse().unwrap();
+    let specific: Ipv4Prefix = "10.1.0.0/16".parse().unwrap();
+    net.build_advertisements(aggregate, |_, _| vec![vec![4.into()], vec![5.into()]], ())
+        .unwrap();
+    net.build_advertisements(specific, |_, _| vec![vec![4.into()]], ())
+        .unwrap();
+    let spec = build_spec(&net, &[aggregate, specific]);
+
+    let decomposition = decompose(&net, command(), &spec).unwrap();
+    assert!(decomposition.atomic_before.contains_key(&aggregate));
+    assert!(!decomposition.atomic_before.contains_key(&specific));
+    run(net, decomposition, &spec).unwrap();
+}
@@ -0,0 +1,123 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test the system with a scenario in which routers load-balance their traffic over multiple paths.
+
+use bgpsim::{
+    builder::{constant_link_weight, NetworkBuilder},
+    config::{ConfigExpr, ConfigModifier},
+    prelude::*,
+};
+use test_log::test;
+
+use crate::{
+    decomposition::decompose,
+    runtime::sim::run,
+    specification::{Invariant, Property, Specification, SpecificationBuilder},
+    P,
+};
+
+/// Clique with 4 nodes, where the ring 0 -> 1 -> 2 -> 3 -> 0 has a low link weight. Hence, router 0
+/// reaches router 2 both via 1 and 3, and vice-versa.
+fn get_net() -> Network<P, BasicEventQueue<P>> {
+    let mut net: Network<P, BasicEventQueue<P>> =
+        NetworkBuilder::build_complete_graph(BasicEventQueue::new(), 4);
+    for r in net.get_routers() {
+        net.set_load_balancing(r, true).unwrap();
+    }
+    net.build_external_routers(|_, _| vec![RouterId::from(0), RouterId::from(2)], ())
+        .unwrap();
+    net.build_ibgp_full_mesh().unwrap();
+    net.build_ebgp_sessions().unwrap();
+    net.build_link_weights(constant_link_weight, 10.0).unwrap();
+    for (a, b) in [(0, 1), (1, 2), (2, 3), (3, 0)] {
+        net.set_link_weight(a.into(), b.into(), 1.0).unwrap();
+        net.set_link_weight(b.into(), a.into(), 1.0).unwrap();
+    }
+    net
+}
+
+#[allow(clippy::type_complexity)]
+fn prepare() -> (
+    Network<P, BasicEventQueue<P>>,
+    RouterId,
+    RouterId,
+    Specification<P>,
+    P,
+) {
+    let mut net = get_net();
+    let p = P::from(0);
+    net.build_advertisements(p, |_, _| vec![vec![4.into()], vec![5.into()]], ())
+        .unwrap();
+
+    let spec = SpecificationBuilder::Reachability.build_all(&net, None, [p]);
+
+    let e = RouterId::from(4);
+    let r = RouterId::from(0);
+
+    (net, r, e, spec, p)
+}
+
+/// Router 0 changes from its own egress to load-balancing towards router 2.
+#[test]
+fn remove_session() {
+    let (net, r, e, spec, _) = prepare();
+
+    let command = ConfigModifier::Remove(ConfigExpr::BgpSession {
+        source: r,
+        target: e,
+        session_type: BgpSessionType::EBgp,
+    });
+
+    let decomposition = decompose(&net, command, &spec).unwrap();
+    run(net, decomposition, &spec).unwrap();
+}
+
+/// Router 0 changes from load-balancing towards router 2 to its own egress.
+#[test]
+fn add_session() {
+    let (mut net, r, e, spec, _) = prepare();
+
+    net.set_bgp_session(r, e, None).unwrap();
+
+    let command = ConfigModifier::Insert(ConfigExpr::BgpSession {
+        source: r,
+        target: e,
+        session_type: BgpSessionType::EBgp,
+    });
+
+    let decomposition = decompose(&net, command, &spec).unwrap();
+    run(net, decomposition, &spec).unwrap();
+}
+
+/// A waypoint must be traversed on every path.
+#[test]
+fn waypoint_on_every_path() {
+    let (mut net, r, e, _, p) = prepare();
+
+    net.set_bgp_session(r, e, None).unwrap();
+    let mut fw_state = net.get_forwarding_state();
+    assert_eq!(fw_state.get_next_hops(r, p), &[1.into(), 3.into()]);
+
+    let via = |w: u32| Invariant {
+        router: r,
+        prop: Property::Waypoint(w.into()),
+    };
+    assert!(via(2).check(&mut fw_state, p).is_ok());
+    assert!(via(1).check(&mut fw_state, p).is_err());
+    assert!(via(3).check(&mut fw_state, p).is_err());
+}
@@ -17,10 +17,90 @@
 
 //! Module to do tests
 
+use bgpsim::{
+    builder::{constant_link_weight, NetworkBuilder},
+    config::{ConfigExpr, ConfigModifier},
+    prelude::*,
+};
+
+use crate::P;
+
 mod abilene;
+mod atomic_condition;
 #[cfg(feature = "experiment")]
 mod builder;
+mod campaign;
+mod checkpoint;
+#[cfg(feature = "export-config")]
+mod device_scripts;
+mod heuristic_scheduler;
+mod ipv4_prefix;
+mod load_balancing;
+mod parallel_scheduling;
+mod path_properties;
+mod replan;
+mod report;
+mod rollback;
 mod route_reflection_dep;
 mod simple_no_dependencies;
 mod simple_route_reflection;
 mod single_fw_dependency;
+mod spec_parser;
+mod step_search;
+
+/// Clique of 8 routers with two route reflectors (2 and 3), and two external routers (8 connected
+/// to 0, and 9 connected to 1). Both external routers advertise prefix 0, and 8 is preferred.
+fn clique_net() -> Network<P, BasicEventQueue<P>> {
+    let mut net: Network<P, BasicEventQueue<P>> =
+        NetworkBuilder::build_complete_graph(BasicEventQueue::new(), 8);
+    net.build_external_routers(|_, _| vec![RouterId::from(0), RouterId::from(1)], ())
+        .unwrap();
+    net.build_link_weights(constant_link_weight, 1.0).unwrap();
+    net.build_ibgp_route_reflection(|_, _| vec![RouterId::from(2), RouterId::from(3)], ())
+        .unwrap();
+    net.build_ebgp_sessions().unwrap();
+    net.build_advertisements(P::from(0), |_, _| vec![vec![8.into()], vec![9.into()]], ())
+        .unwrap();
+    net
+}
+
+/// The command that removes the eBGP session between `internal` and `external`.
+fn remove_ebgp_session(internal: RouterId, external: RouterId) -> ConfigModifier<P> {
+    ConfigModifier::Remove(ConfigExpr::BgpSession {
+        source: internal,
+        target: external,
+        session_type: BgpSessionType::EBgp,
+    })
+}
+
+/// The command that removes the session from 0 to 8 in the [`clique_net`].
+fn remove_session_0_8() -> ConfigModifier<P> {
+    remove_ebgp_session(0.into(), 8.into())
+}
+
+/// Ring of 6 routers with two route reflectors (1 and 4), and two external routers (6 connected
+/// to 0, and 7 connected to 3). Both external routers advertise prefix 0, and 6 is preferred. The
+/// link between 5 and 0 has a higher weight, such that all shortest paths are unique.
+fn ring_net() -> Network<P, BasicEventQueue<P>> {
+    let mut net: Network<P, BasicEventQueue<P>> = Network::new(BasicEventQueue::new());
+    let routers: Vec<RouterId> = (0..6).map(|i| net.add_router(format!("R{i}"))).collect();
+    for (a, b) in routers.iter().zip(routers.iter().cycle().skip(1)) {
+        net.add_link(*a, *b);
+    }
+    net.build_external_routers(|_, _| vec![RouterId::from(0), RouterId::from(3)], ())
+        .unwrap();
+    net.build_link_weights(constant_link_weight, 1.0).unwrap();
+    net.set_link_weight(5.into(), 0.into(), 2.0).unwrap();
+    net.set_link_weight(0.into(), 5.into(), 2.0).unwrap();
+    net.build_ibgp_route_reflection(|_, _| vec![RouterId::from(1), RouterId::from(4)], ())
+        .unwrap();
+    net.build_ebgp_sessions().unwrap();
+    net.build_advertisements(P::from(0), |_, _| vec![vec![6.into()], vec![7.into()]], ())
+        .unwrap();
+    net
+}
+
+/// The command that removes the session from 0 to 6 in the [`ring_net`].
+fn remove_session_0_6() -> ConfigModifier<P> {
+    remove_ebgp_session(0.into(), 6.into())
+}
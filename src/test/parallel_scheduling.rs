@@ -0,0 +1,115 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test scheduling multiple prefixes concurrently, and reusing the schedule for identical prefixes.
+
+use std::time::Duration;
+
+use bgpsim::{
+    builder::{constant_link_weight, NetworkBuilder},
+    config::{ConfigExpr, ConfigModifier, ConfigPatch},
+    prelude::*,
+};
+use test_log::test;
+
+#[cfg(feature = "microlp")]
+use crate::decomposition::{
+    compiler::build,
+    ilp_scheduler::{schedule_all_with, MicroLp},
+};
+use crate::{
+    decomposition::{
+        bgp_dependencies::find_dependencies,
+        decompose_patch_with,
+        ilp_scheduler::{schedule_all, ScheduleOptions},
+        CommandInfo,
+    },
+    runtime::sim::run,
+    specification::{Specification, SpecificationBuilder},
+    P,
+};
+
+/// Clique with 4 nodes, and two external nodes (4 connected to 0, and 5 connected to 2). All four
+/// prefixes are advertised by both external nodes. Only the first three prefixes are constrained by
+/// the specification, such that the last prefix results in a different model.
+fn get_net() -> (Network<P, BasicEventQueue<P>>, Specification<P>) {
+    let mut net: Network<P, BasicEventQueue<P>> =
+        NetworkBuilder::build_complete_graph(BasicEventQueue::new(), 4);
+    net.build_external_routers(|_, _| vec![RouterId::from(0), RouterId::from(2)], ())
+        .unwrap();
+    net.build_link_weights(constant_link_weight, 1.0).unwrap();
+    net.build_ibgp_full_mesh().unwrap();
+    net.build_ebgp_sessions().unwrap();
+    for p in 0..4 {
+        net.build_advertisements(P::from(p), |_, _| vec![vec![4.into()], vec![5.into()]], ())
+            .unwrap();
+    }
+    let spec = SpecificationBuilder::Reachability.build_all(&net, None, (0..3).map(P::from));
+    (net, spec)
+}
+
+/// The command that removes the session from 0 to 4.
+fn command() -> ConfigModifier<P> {
+    ConfigModifier::Remove(ConfigExpr::BgpSession {
+        source: 0.into(),
+        target: 4.into(),
+        session_type: BgpSessionType::EBgp,
+    })
+}
+
+#[test]
+fn identical_prefixes() {
+    let (net, spec) = get_net();
+    let info = CommandInfo::new(&net, command(), &spec).unwrap();
+    let bgp_deps = find_dependencies(&info);
+
+    let schedules = schedule_all(&info, &bgp_deps, Default::default()).unwrap();
+    assert_eq!(schedules.len(), 4);
+    for p in 1..3 {
+        assert_eq!(schedules[&P::from(0)], schedules[&P::from(p)]);
+    }
+}
+
+/// Schedule all prefixes using the pure-Rust solver backend, and execute the resulting
+/// decomposition.
+#[cfg(feature = "microlp")]
+#[test]
+fn microlp_backend() {
+    let (net, spec) = get_net();
+    let info = CommandInfo::new(&net, command(), &spec).unwrap();
+    let bgp_deps = find_dependencies(&info);
+
+    let schedules =
+        schedule_all_with::<MicroLp, _, _>(&info, &bgp_deps, Default::default()).unwrap();
+    assert_eq!(schedules.len(), 4);
+    let decomposition = build(&info, bgp_deps, schedules).unwrap();
+    run(net, decomposition, &spec).unwrap();
+}
+
+#[test]
+fn decompose_concurrently() {
+    let (net, spec) = get_net();
+    let options = ScheduleOptions {
+        time_budget: Duration::from_secs(600),
+        threads: Some(2),
+        ..Default::default()
+    };
+    let decomposition =
+        decompose_patch_with(&net, &ConfigPatch::from(command()), &spec, options).unwrap();
+    assert_eq!(decomposition.schedule.len(), 4);
+    run(net, decomposition, &spec).unwrap();
+}
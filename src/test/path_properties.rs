@@ -0,0 +1,150 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test the path properties edge, sequence, max-hops, egress, and avoid.
+
+use bgpsim::{
+    builder::{constant_link_weight, NetworkBuilder},
+    config::{ConfigExpr, ConfigModifier},
+    prelude::*,
+};
+use test_log::test;
+
+use crate::{
+    decomposition::decompose,
+    runtime::sim::run,
+    specification::{Invariant, Property, SpecExpr, Specification},
+    P,
+};
+
+/// Clique with 4 nodes, and two external nodes (4 connected to 0, and 5 connected to 2).
+fn get_net() -> Network<P, BasicEventQueue<P>> {
+    let mut net: Network<P, BasicEventQueue<P>> =
+        NetworkBuilder::build_complete_graph(BasicEventQueue::<P>::new(), 4);
+    net.build_external_routers(|_, _| vec![RouterId::from(0), RouterId::from(2)], ())
+        .unwrap();
+    net.build_link_weights(constant_link_weight, 1.0).unwrap();
+    net.build_ibgp_full_mesh().unwrap();
+    net.build_ebgp_sessions().unwrap();
+    net
+}
+
+/// Build the specification that requires reachability for all internal routers, and the property
+/// generated by `prop` to hold globally.
+fn build_spec(
+    net: &Network<P, BasicEventQueue<P>>,
+    p: P,
+    prop: impl Fn(RouterId) -> Property,
+) -> Specification<P> {
+    let invariants = net
+        .get_routers()
+        .into_iter()
+        .map(|router| {
+            SpecExpr::Invariant(Invariant {
+                router,
+                prop: Property::All(vec![Property::Reachability, prop(router)]),
+            })
+        })
+        .collect();
+    [(p, SpecExpr::Globally(Box::new(SpecExpr::All(invariants))))]
+        .into_iter()
+        .collect()
+}
+
+/// Remove the session from 0 to 4, such that all routers move from egress 4 to egress 5.
+fn migrate(prop: impl Fn(RouterId) -> Property) {
+    let mut net = get_net();
+    let p = P::from(0);
+    net.build_advertisements(p, |_, _| vec![vec![4.into()], vec![5.into()]], ())
+        .unwrap();
+    let spec = build_spec(&net, p, prop);
+
+    let command = ConfigModifier::Remove(ConfigExpr::BgpSession {
+        source: 0.into(),
+        target: 4.into(),
+        session_type: BgpSessionType::EBgp,
+    });
+
+    let decomposition = decompose(&net, command, &spec).unwrap();
+    run(net, decomposition, &spec).unwrap();
+}
+
+#[test]
+fn check_path_properties() {
+    let path: Vec<RouterId> = vec![1.into(), 0.into(), 2.into(), 5.into()];
+    let check = |prop: Property| prop.check(&path, true);
+
+    assert!(check(Property::Edge(0.into(), 2.into())));
+    assert!(!check(Property::Edge(2.into(), 0.into())));
+    assert!(check(Property::Sequence(vec![1.into(), 2.into()])));
+    assert!(check(Property::Sequence(vec![0.into(), 5.into()])));
+    assert!(!check(Property::Sequence(vec![2.into(), 0.into()])));
+    assert!(check(Property::MaxHops(3)));
+    assert!(!check(Property::MaxHops(2)));
+    assert!(check(Property::Egress([4.into(), 5.into()].into())));
+    assert!(!check(Property::Egress([4.into()].into())));
+    assert!(check(Property::Avoid(3.into())));
+    assert!(!check(Property::Avoid(2.into())));
+
+    // unreachable paths
+    let path: Vec<RouterId> = vec![1.into(), 0.into()];
+    let check = |prop: Property| prop.check(&path, false);
+    assert!(check(Property::Edge(0.into(), 2.into())));
+    assert!(check(Property::MaxHops(0)));
+    assert!(check(Property::Egress([4.into()].into())));
+    assert!(!check(Property::Avoid(0.into())));
+}
+
+/// The traffic may only leave the network at any of the two egresses.
+#[test]
+fn egress() {
+    migrate(|_| Property::Egress([4.into(), 5.into()].into()));
+}
+
+/// Router 1 and 3 must never reach the destination in more than two hops. Hence, both must switch
+/// to egress 5 before router 0 does.
+#[test]
+fn max_hops() {
+    migrate(|_| Property::MaxHops(2));
+}
+
+/// Router 1 and 3 must never forward traffic over the edge from 0 to 2. Hence, both must switch to
+/// egress 5 before router 0 does.
+#[test]
+fn not_edge() {
+    migrate(|r| {
+        if r == 0.into() {
+            Property::True
+        } else {
+            Property::Not(Box::new(Property::Edge(0.into(), 2.into())))
+        }
+    });
+}
+
+/// Router 1 must never traverse router 3, and router 3 must reach the destination via the sequence
+/// of waypoints 3, and then either 4 or 5.
+#[test]
+fn avoid_and_sequence() {
+    migrate(|r| match r.index() {
+        1 => Property::Avoid(3.into()),
+        3 => Property::Any(vec![
+            Property::Sequence(vec![3.into(), 4.into()]),
+            Property::Sequence(vec![3.into(), 5.into()]),
+        ]),
+        _ => Property::True,
+    });
+}
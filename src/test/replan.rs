@@ -0,0 +1,182 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test re-planning the update when an unexpected event happens during the update.
+
+use bgpsim::{
+    config::{ConfigModifier, NetworkConfig},
+    prelude::*,
+};
+use test_log::test;
+
+use crate::{
+    decomposition::{
+        decompose,
+        ilp_scheduler::{ScheduleOptions, SchedulerKind},
+        replan,
+    },
+    runtime::{controller::Controller, sim::run_with_event},
+    specification::SpecificationBuilder,
+    P,
+};
+
+use super::{clique_net, remove_session_0_6, remove_session_0_8, ring_net};
+
+/// Remove the link between `a` and `b` after each step of the controller. The update must
+/// re-plan at least once, and it must succeed for every step.
+fn check_replan_after_link_failure(
+    net: Network<P, BasicEventQueue<P>>,
+    command: ConfigModifier<P>,
+    (a, b): (RouterId, RouterId),
+) {
+    let spec = SpecificationBuilder::Reachability.build_all(&net, None, [P::from(0)]);
+    let decomp = decompose(&net, command, &spec).unwrap();
+
+    // count the steps of the controller without any event.
+    let mut num_steps = 0;
+    let mut controller = Controller::new(decomp.clone());
+    let mut n = net.clone();
+    while !controller.is_finished() {
+        if controller.step_sim(&mut n).unwrap() {
+            num_steps += 1;
+        }
+    }
+
+    let mut num_replans = 0;
+    for event_after in 1..num_steps {
+        match run_with_event(net.clone(), decomp.clone(), &spec, event_after, |net| {
+            net.remove_link(a, b)
+        }) {
+            Ok((_, stats)) => num_replans += stats.num_replans,
+            Err(e) => panic!("Re-planning after {event_after} steps failed: {e}"),
+        }
+    }
+    assert!(num_replans > 0);
+}
+
+#[test]
+fn replan_after_link_failure() {
+    check_replan_after_link_failure(clique_net(), remove_session_0_8(), (4.into(), 1.into()));
+}
+
+#[test]
+fn ring_replan_after_link_failure() {
+    check_replan_after_link_failure(ring_net(), remove_session_0_6(), (1.into(), 2.into()));
+}
+
+/// Re-plan the command on the unmodified network.
+fn check_replan_converged(net: Network<P, BasicEventQueue<P>>, command: ConfigModifier<P>) {
+    let spec = SpecificationBuilder::Reachability.build_all(&net, None, [P::from(0)]);
+
+    // re-planning without any difference to the target requires no commands.
+    let decomp = replan(
+        &net,
+        &net.get_config().unwrap(),
+        &command.clone().into(),
+        &spec,
+        Default::default(),
+    )
+    .unwrap();
+    assert!(decomp.original_command.modifiers.is_empty());
+    assert!(decomp.main_commands.iter().all(|round| round.is_empty()));
+
+    // re-planning towards the target configuration yields the same command.
+    let mut target = net.clone();
+    target.apply_modifier(&command).unwrap();
+    let decomp = replan(
+        &net,
+        &target.get_config().unwrap(),
+        &command.clone().into(),
+        &spec,
+        Default::default(),
+    )
+    .unwrap();
+    assert_eq!(decomp.original_command.modifiers, vec![command]);
+}
+
+#[test]
+fn replan_converged() {
+    check_replan_converged(clique_net(), remove_session_0_8());
+}
+
+#[test]
+fn ring_replan_converged() {
+    check_replan_converged(ring_net(), remove_session_0_6());
+}
+
+/// Perform the setup stage, remove the link between `a` and `b`, and re-plan the update from the
+/// current state without rolling back, using the given scheduler.
+fn check_replan_from_intermediate_state(
+    mut net: Network<P, BasicEventQueue<P>>,
+    command: ConfigModifier<P>,
+    (a, b): (RouterId, RouterId),
+    scheduler: SchedulerKind,
+) {
+    let spec = SpecificationBuilder::Reachability.build_all(&net, None, [P::from(0)]);
+    let decomp = decompose(&net, command.clone(), &spec).unwrap();
+    let mut target = net.clone();
+
+    // perform the setup stage, such that the temporary configuration is in place.
+    let mut controller = Controller::new(decomp);
+    while controller.state().name() == "Setup" {
+        controller.step_sim(&mut net).unwrap();
+    }
+    net.remove_link(a, b).unwrap();
+
+    target.remove_link(a, b).unwrap();
+    target.apply_modifier(&command).unwrap();
+    let target = target.get_config().unwrap();
+    assert!(net.get_config().unwrap().get_diff(&target).modifiers.len() > 1);
+
+    // re-plan from the current state without rolling back.
+    let options = ScheduleOptions {
+        scheduler,
+        ..Default::default()
+    };
+    controller.replan(&net, &target, &spec, options).unwrap();
+    assert_eq!(controller.decomposition().original_command, command.into());
+    while !controller.is_finished() {
+        controller.step_sim(&mut net).unwrap();
+    }
+    assert!(net
+        .get_config()
+        .unwrap()
+        .get_diff(&target)
+        .modifiers
+        .is_empty());
+}
+
+#[test]
+fn replan_from_intermediate_state() {
+    check_replan_from_intermediate_state(
+        clique_net(),
+        remove_session_0_8(),
+        (4.into(), 1.into()),
+        SchedulerKind::IlpWithFallback,
+    );
+}
+
+#[test]
+fn ring_replan_from_intermediate_state() {
+    // the ILP of the re-planned update on the ring takes too long with the default solver.
+    check_replan_from_intermediate_state(
+        ring_net(),
+        remove_session_0_6(),
+        (1.into(), 2.into()),
+        SchedulerKind::Heuristic,
+    );
+}
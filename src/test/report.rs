@@ -0,0 +1,86 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test the report of a migration plan.
+
+use bgpsim::{config::ConfigModifier, prelude::*};
+use itertools::Itertools;
+use test_log::test;
+
+use crate::{
+    decomposition::{decompose, report::ReportFormat},
+    specification::SpecificationBuilder,
+    P,
+};
+
+use super::{clique_net, remove_session_0_6, remove_session_0_8, ring_net};
+
+/// Check the overhead and both report formats of the migration plan for the command.
+fn check_report(net: &Network<P, BasicEventQueue<P>>, command: ConfigModifier<P>) {
+    let spec = SpecificationBuilder::Reachability.build_all(net, None, [P::from(0)]);
+    let decomp = decompose(net, command, &spec).unwrap();
+    let overhead = decomp.overhead();
+    let commands = decomp
+        .setup_commands
+        .iter()
+        .chain(decomp.atomic_before.values().flatten())
+        .chain(decomp.main_commands.iter())
+        .chain(decomp.atomic_after.values().flatten())
+        .chain(decomp.cleanup_commands.iter())
+        .flatten()
+        .collect_vec();
+    assert_eq!(overhead.num_commands, commands.len());
+    assert!(overhead.temp_route_maps >= overhead.rib_overhead);
+
+    let md = decomp.report(net, ReportFormat::Markdown);
+    assert!(md.starts_with("# Migration plan\n"));
+    assert!(md.contains(&format!(
+        "| Temporary BGP sessions | {} |",
+        overhead.temp_sessions
+    )));
+    assert!(md.contains("## Stage 3: Main command"));
+    assert!(md.contains("## Expected forwarding changes"));
+    assert!(md.contains("```dot\ndigraph D {"));
+    for cmd in &commands {
+        assert!(md.contains(&format!("**{}**", cmd.command.fmt(net))));
+    }
+
+    let html = decomp.report(net, ReportFormat::Html);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.ends_with("</html>\n"));
+    assert!(html.contains("<h2>Stage 3: Main command</h2>"));
+    assert!(!html.contains("->"));
+    assert!(html.contains("-&gt;"));
+}
+
+#[test]
+fn report() {
+    check_report(&clique_net(), remove_session_0_8());
+}
+
+#[test]
+fn ring_report() {
+    check_report(&ring_net(), remove_session_0_6());
+}
+
+#[test]
+fn report_format() {
+    assert_eq!(ReportFormat::from_path("plan.html"), ReportFormat::Html);
+    assert_eq!(ReportFormat::from_path("plan.htm"), ReportFormat::Html);
+    assert_eq!(ReportFormat::from_path("plan.md"), ReportFormat::Markdown);
+    assert_eq!(ReportFormat::from_path("plan"), ReportFormat::Markdown);
+}
@@ -0,0 +1,192 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+//! Test aborting the update and rolling back to the original configuration.
+
+use atomic_command::{AtomicCondition, AtomicModifier};
+use bgpsim::{
+    config::{ConfigModifier, ConfigPatch},
+    prelude::*,
+};
+use test_log::test;
+
+use crate::{
+    decomposition::{
+        decompose, decompose_patch_with,
+        ilp_scheduler::{ScheduleOptions, SchedulerKind},
+    },
+    runtime::{
+        controller::{AbortError, Controller},
+        sim::{run_and_abort, SimError},
+    },
+    specification::{Specification, SpecificationBuilder},
+    P,
+};
+
+use super::{clique_net, remove_session_0_6, remove_session_0_8, ring_net};
+
+/// Abort the update after each step of the controller, until the update finishes before it can be
+/// aborted. The network must be back in its original state after each rollback.
+fn abort_after_every_step(
+    net: &Network<P, BasicEventQueue<P>>,
+    command: ConfigModifier<P>,
+    spec: &Specification<P>,
+) {
+    let patch = ConfigPatch::from(command);
+    let options = ScheduleOptions {
+        scheduler: SchedulerKind::Heuristic,
+        ..Default::default()
+    };
+    let decomp = decompose_patch_with(net, &patch, spec, options).unwrap();
+    assert!(decomp.rollback.is_verified());
+
+    for abort_after in 1.. {
+        match run_and_abort(net.clone(), decomp.clone(), spec, abort_after) {
+            Ok(_) => {}
+            Err(SimError::Abort(AbortError::Finished)) => {
+                // the update must have been aborted in all stages.
+                assert!(abort_after > 4);
+                break;
+            }
+            Err(e) => panic!("Rollback after {abort_after} steps failed: {e}"),
+        }
+    }
+}
+
+#[test]
+fn rollback_reachability() {
+    let net = clique_net();
+    let spec = SpecificationBuilder::Reachability.build_all(&net, None, [P::from(0)]);
+    abort_after_every_step(&net, remove_session_0_8(), &spec);
+}
+
+#[test]
+fn ring_rollback_reachability() {
+    let net = ring_net();
+    let spec = SpecificationBuilder::Reachability.build_all(&net, None, [P::from(0)]);
+    abort_after_every_step(&net, remove_session_0_6(), &spec);
+}
+
+#[test]
+fn rollback_egress_waypoint() {
+    let net = clique_net();
+    let spec = SpecificationBuilder::EgressWaypoint.build_all(
+        &net,
+        Some(&remove_session_0_8()),
+        [P::from(0)],
+    );
+    abort_after_every_step(&net, remove_session_0_8(), &spec);
+}
+
+#[test]
+fn ring_rollback_egress_waypoint() {
+    let net = ring_net();
+    let spec = SpecificationBuilder::EgressWaypoint.build_all(
+        &net,
+        Some(&remove_session_0_6()),
+        [P::from(0)],
+    );
+    abort_after_every_step(&net, remove_session_0_6(), &spec);
+}
+
+/// Rolling back the command would violate the specification that the old egress is used until the
+/// new one is used. Hence, the update can only be aborted in the setup stage.
+fn check_rollback_unverified(net: &Network<P, BasicEventQueue<P>>, command: ConfigModifier<P>) {
+    let spec = SpecificationBuilder::OldUntilNewEgress.build_all(net, Some(&command), [P::from(0)]);
+    let decomp = decompose(net, command, &spec).unwrap();
+    // rolling back would use the old egress after the new one.
+    assert!(decomp.rollback.unverified.contains(&P::from(0)));
+
+    // in the setup stage, the update can still be aborted.
+    let mut controller = Controller::new(decomp.clone());
+    assert_eq!(controller.abort(), Ok(()));
+    assert!(controller.is_aborted());
+    assert_eq!(controller.abort(), Err(AbortError::AlreadyAborted));
+
+    // but not after the setup stage.
+    let mut controller = Controller::new(decomp);
+    controller.next_stage();
+    assert_eq!(controller.abort(), Err(AbortError::Unverified(1)));
+    assert!(!controller.is_aborted());
+}
+
+#[test]
+fn rollback_unverified() {
+    check_rollback_unverified(&clique_net(), remove_session_0_8());
+}
+
+#[test]
+fn ring_rollback_unverified() {
+    check_rollback_unverified(&ring_net(), remove_session_0_6());
+}
+
+/// Check the conditions of the rollback plan of the command.
+fn check_rollback_conditions(net: &Network<P, BasicEventQueue<P>>, command: ConfigModifier<P>) {
+    let spec = SpecificationBuilder::Reachability.build_all(net, None, [P::from(0)]);
+    let decomp = decompose(net, command, &spec).unwrap();
+    let rollback = &decomp.rollback;
+
+    // every reverse command that changes the preference must wait for the route to be selected.
+    let stages = rollback
+        .setup_commands
+        .iter()
+        .chain(rollback.atomic_before.values().flatten())
+        .chain(rollback.main_commands.iter())
+        .chain(rollback.atomic_after.values().flatten())
+        .chain(rollback.cleanup_commands.iter());
+    let mut num_preference = 0;
+    for cmd in stages.flatten() {
+        match cmd.command {
+            AtomicModifier::ChangePreference { .. } | AtomicModifier::ClearPreference { .. } => {
+                num_preference += 1;
+                assert!(matches!(
+                    cmd.postcondition,
+                    AtomicCondition::SelectedRoute { .. }
+                ));
+            }
+            AtomicModifier::AddTempSession { .. } => {
+                assert!(matches!(
+                    cmd.postcondition,
+                    AtomicCondition::BgpSessionEstablished { .. }
+                ));
+            }
+            _ => {}
+        }
+    }
+    assert!(num_preference > 0);
+
+    // removing the weight of the old route waits until all other routes are less preferred.
+    assert!(rollback.setup_commands.iter().flatten().any(|cmd| matches!(
+        cmd.precondition,
+        AtomicCondition::RoutesLessPreferred { .. }
+    )));
+
+    // adding back the removed session waits until it is established.
+    assert!(rollback.main_commands.iter().flatten().any(|cmd| matches!(
+        cmd.postcondition,
+        AtomicCondition::BgpSessionEstablished { .. }
+    )));
+}
+
+#[test]
+fn rollback_conditions() {
+    check_rollback_conditions(&clique_net(), remove_session_0_8());
+}
+
+#[test]
+fn ring_rollback_conditions() {
+    check_rollback_conditions(&ring_net(), remove_session_0_6());
+}
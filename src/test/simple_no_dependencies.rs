@@ -17,15 +17,21 @@
 
 //! Test the system with a scenario that is simple and has no dependencies whatsoever.
 
+use atomic_command::AtomicCommand;
 use bgpsim::{
     builder::{constant_link_weight, NetworkBuilder},
-    config::{ConfigExpr, ConfigModifier},
+    config::{ConfigExpr, ConfigModifier, ConfigPatch},
     prelude::*,
+    route_map::{RouteMapBuilder, RouteMapDirection},
 };
 use test_log::test;
 
 use crate::{
-    decomposition::decompose,
+    decomposition::{
+        decompose, decompose_patch, decompose_patch_with,
+        ilp_scheduler::{ScheduleOptions, SchedulerKind},
+        DecompositionError,
+    },
     runtime::sim::run,
     specification::{Specification, SpecificationBuilder},
     P,
@@ -47,7 +53,7 @@ fn prepare() -> (
     Network<P, BasicEventQueue<P>>,
     RouterId,
     RouterId,
-    Specification,
+    Specification<P>,
     P,
 ) {
     let mut net = get_net();
@@ -99,7 +105,7 @@ fn prepare_2_prefixes() -> (
     Network<P, BasicEventQueue<P>>,
     RouterId,
     RouterId,
-    Specification,
+    Specification<P>,
     Vec<P>,
 ) {
     let mut net = get_net();
@@ -147,3 +153,171 @@ fn add_session_2_prefixes() {
     let decomposition = decompose(&net, command, &spec).unwrap();
     run(net, decomposition, &spec).unwrap();
 }
+
+#[test]
+fn remove_session_patch_2_prefixes() {
+    let (net, r, e, spec, _) = prepare_2_prefixes();
+
+    let patch = ConfigPatch::from(ConfigModifier::Remove(ConfigExpr::BgpSession {
+        source: r,
+        target: e,
+        session_type: BgpSessionType::EBgp,
+    }));
+
+    let decomposition = decompose_patch(&net, &patch, &spec).unwrap();
+    assert_eq!(decomposition.original_command, patch);
+    run(net, decomposition, &spec).unwrap();
+}
+
+/// Route-map on router `r` that sets the local preference of all routes learned from `neighbor`.
+fn local_pref_map(r: RouterId, neighbor: RouterId, local_pref: u32) -> ConfigExpr<P> {
+    ConfigExpr::BgpRouteMap {
+        router: r,
+        neighbor,
+        direction: RouteMapDirection::Incoming,
+        map: RouteMapBuilder::new()
+            .order(10)
+            .allow()
+            .set_local_pref(local_pref)
+            .build(),
+    }
+}
+
+/// Collect the raw modifiers of a stage, round by round.
+fn raw_rounds(stage: &[Vec<AtomicCommand<P>>]) -> Vec<Vec<ConfigModifier<P>>> {
+    stage
+        .iter()
+        .map(|round| {
+            round
+                .iter()
+                .flat_map(|c| c.command.clone().into_raw())
+                .collect()
+        })
+        .collect()
+}
+
+/// Patch with two route-maps on the same router that together move the traffic from the old to
+/// the new egress. No command removes a session, so both are applied in a single main round.
+#[test]
+fn route_map_patch_2_prefixes() {
+    let (net, r, e, spec, _) = prepare_2_prefixes();
+
+    let patch = ConfigPatch::from(vec![
+        ConfigModifier::Insert(local_pref_map(r, e, 50)),
+        ConfigModifier::Insert(local_pref_map(r, 2.into(), 200)),
+    ]);
+
+    let decomposition = decompose_patch(&net, &patch, &spec).unwrap();
+    assert_eq!(decomposition.original_command, patch);
+    let main = raw_rounds(&decomposition.main_commands);
+    assert_eq!(main.len(), 1);
+    for cmd in &patch.modifiers {
+        assert!(main[0].contains(cmd));
+    }
+    run(net, decomposition, &spec).unwrap();
+}
+
+/// Patch that both removes a session and adds a route-map. The route-map must be applied before
+/// the main round, and the session must be removed after it.
+#[test]
+fn remove_session_and_route_map_patch_2_prefixes() {
+    let (net, r, e, spec, _) = prepare_2_prefixes();
+
+    let insert = ConfigModifier::Insert(local_pref_map(r, 2.into(), 200));
+    let remove = ConfigModifier::Remove(ConfigExpr::BgpSession {
+        source: r,
+        target: e,
+        session_type: BgpSessionType::EBgp,
+    });
+    let patch = ConfigPatch::from(vec![insert.clone(), remove.clone()]);
+
+    let decomposition = decompose_patch(&net, &patch, &spec).unwrap();
+    assert_eq!(decomposition.original_command, patch);
+    let main = raw_rounds(&decomposition.main_commands);
+    assert!(main.len() >= 2);
+    assert!(main.first().unwrap().contains(&insert));
+    assert!(!main.first().unwrap().contains(&remove));
+    assert!(main.last().unwrap().contains(&remove));
+    assert!(!main.last().unwrap().contains(&insert));
+    run(net, decomposition, &spec).unwrap();
+}
+
+/// Clique with 4 nodes, and three external nodes at routers 0, 1, and 2. The external nodes at
+/// routers 0 and 1 advertise equally preferred routes, and the one at router 2 a worse route.
+fn prepare_3_externals() -> (Network<P, BasicEventQueue<P>>, Specification<P>, P) {
+    let mut net: Network<P, BasicEventQueue<P>> =
+        NetworkBuilder::build_complete_graph(BasicEventQueue::<P>::new(), 4);
+    net.build_external_routers(|_, _| vec![0.into(), 1.into(), 2.into()], ())
+        .unwrap();
+    net.build_link_weights(constant_link_weight, 1.0).unwrap();
+    net.build_ibgp_full_mesh().unwrap();
+    net.build_ebgp_sessions().unwrap();
+    let p = P::from(0);
+    net.build_advertisements(p, |_, _| vec![vec![4.into(), 5.into()], vec![6.into()]], ())
+        .unwrap();
+    let spec = SpecificationBuilder::Reachability.build_all(&net, None, [p]);
+    (net, spec, p)
+}
+
+/// Patch that removes the eBGP sessions of two different routers. Both routers must change their
+/// forwarding in the same round, as the main commands are applied at once.
+#[test]
+fn remove_sessions_on_two_routers_patch() {
+    for scheduler in [SchedulerKind::IlpWithFallback, SchedulerKind::Heuristic] {
+        let (net, spec, p) = prepare_3_externals();
+        let (r0, r1) = (RouterId::from(0), RouterId::from(1));
+
+        let patch = ConfigPatch::from(vec![
+            ConfigModifier::Remove(ConfigExpr::BgpSession {
+                source: r0,
+                target: 4.into(),
+                session_type: BgpSessionType::EBgp,
+            }),
+            ConfigModifier::Remove(ConfigExpr::BgpSession {
+                source: r1,
+                target: 5.into(),
+                session_type: BgpSessionType::EBgp,
+            }),
+        ]);
+
+        let options = ScheduleOptions {
+            scheduler,
+            ..Default::default()
+        };
+        let decomposition = decompose_patch_with(&net, &patch, &spec, options).unwrap();
+        let schedule = &decomposition.schedule[&p];
+        assert_eq!(schedule[&r0].fw_state, schedule[&r1].fw_state);
+        assert_eq!(schedule[&r0].cost() + schedule[&r1].cost(), 0);
+        let main = raw_rounds(&decomposition.main_commands);
+        assert_eq!(main.len(), 1);
+        for cmd in &patch.modifiers {
+            assert!(main[0].contains(cmd));
+        }
+        run(net, decomposition, &spec).unwrap();
+    }
+}
+
+#[test]
+fn conflicting_patch() {
+    let (mut net, r, e, spec, _) = prepare();
+
+    net.set_bgp_session(r, e, None).unwrap();
+
+    let patch = ConfigPatch::from(vec![
+        ConfigModifier::Insert(ConfigExpr::BgpSession {
+            source: r,
+            target: e,
+            session_type: BgpSessionType::EBgp,
+        }),
+        ConfigModifier::Insert(ConfigExpr::BgpSession {
+            source: e,
+            target: r,
+            session_type: BgpSessionType::EBgp,
+        }),
+    ]);
+
+    assert!(matches!(
+        decompose_patch(&net, &patch, &spec),
+        Err(DecompositionError::ConflictingCommands(0, 1))
+    ));
+}
@@ -53,7 +53,7 @@ fn prepare() -> (
     Network<P, BasicEventQueue<P>>,
     RouterId,
     RouterId,
-    Specification,
+    Specification<P>,
     P,
 ) {
     let mut net = get_net();
@@ -105,7 +105,7 @@ fn prepare_2_prefixes() -> (
     Network<P, BasicEventQueue<P>>,
     RouterId,
     RouterId,
-    Specification,
+    Specification<P>,
     Vec<P>,
 ) {
     let mut net = get_net();
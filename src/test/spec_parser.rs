@@ -0,0 +1,172 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test the text format of the specification.
+
+use bgpsim::prelude::*;
+use pretty_assertions_sorted::assert_eq;
+use test_log::test;
+
+use crate::{
+    specification::{
+        parse_spec_expr, parse_specification, Invariant, Property, SpecExpr, SpecParseErrorKind,
+        SpecText, Specification,
+    },
+    P,
+};
+
+fn get_net() -> (Network<P, BasicEventQueue<P>>, [RouterId; 5]) {
+    let mut net: Network<P, BasicEventQueue<P>> = Network::new(BasicEventQueue::new());
+    let r1 = net.add_router("r1");
+    let r2 = net.add_router("r2");
+    let ny = net.add_external_router("NY", AsId(100));
+    let hou = net.add_external_router("HOU", AsId(200));
+    let zrh = net.add_router("Zurich \"Main\"");
+    (net, [r1, r2, ny, hou, zrh])
+}
+
+fn inv(router: RouterId, prop: Property) -> SpecExpr {
+    SpecExpr::Invariant(Invariant { router, prop })
+}
+
+#[test]
+fn parse_example() {
+    let (net, [r1, _, ny, hou, _]) = get_net();
+    let text = "prefix 100.0.0.0/24: G(reach(r1)) & (egress(r1)=NY U egress(r1)=HOU)";
+    let spec = parse_specification(text, &net).unwrap();
+    let expected: Specification<P> = [(
+        P::from(0),
+        SpecExpr::All(vec![
+            SpecExpr::Globally(Box::new(inv(r1, Property::Reachability))),
+            SpecExpr::Until(
+                Box::new(inv(r1, Property::Egress([ny].into()))),
+                Box::new(inv(r1, Property::Egress([hou].into()))),
+            ),
+        ]),
+    )]
+    .into_iter()
+    .collect();
+    assert_eq!(spec, expected);
+}
+
+#[test]
+fn round_trip() {
+    use Property::*;
+    let (net, [r1, r2, ny, hou, zrh]) = get_net();
+    let spec: Specification<P> = [
+        (
+            P::from(0),
+            SpecExpr::All(vec![
+                SpecExpr::Globally(Box::new(SpecExpr::All(vec![
+                    inv(r1, Reachability),
+                    inv(zrh, Waypoint(r2)),
+                    inv(r2, Edge(r2, r1)),
+                    inv(r1, Sequence(vec![r2, zrh, ny])),
+                    inv(r1, Sequence(vec![])),
+                    inv(r2, MaxHops(3)),
+                    inv(r2, Egress([ny, hou].into())),
+                    inv(r2, Egress([].into())),
+                    inv(zrh, Avoid(r1)),
+                ]))),
+                SpecExpr::WeakUntil(
+                    Box::new(inv(r1, Egress([ny].into()))),
+                    Box::new(SpecExpr::Until(
+                        Box::new(SpecExpr::Not(Box::new(inv(r2, Avoid(ny))))),
+                        Box::new(SpecExpr::Finally(Box::new(inv(r2, Egress([hou].into()))))),
+                    )),
+                ),
+            ]),
+        ),
+        (
+            P::from(1),
+            SpecExpr::Any(vec![
+                SpecExpr::Next(Box::new(SpecExpr::True)),
+                SpecExpr::All(vec![]),
+                SpecExpr::Any(vec![inv(r1, True)]),
+                inv(
+                    r1,
+                    All(vec![
+                        Not(Box::new(Any(vec![Waypoint(r2), Avoid(zrh)]))),
+                        Egress([hou].into()),
+                        Any(vec![]),
+                        All(vec![Reachability]),
+                        Sequence(vec![]),
+                    ]),
+                ),
+            ]),
+        ),
+    ]
+    .into_iter()
+    .collect();
+
+    let text = SpecText::new(&spec, &net).to_string();
+    assert_eq!(parse_specification(&text, &net).unwrap(), spec);
+
+    for expr in spec.values() {
+        let text = SpecText::new(expr, &net).to_string();
+        assert_eq!(&parse_spec_expr(&text, &net).unwrap(), expr);
+    }
+}
+
+#[test]
+fn errors() {
+    let (net, _) = get_net();
+    let err = |text: &str| parse_specification(text, &net).unwrap_err();
+
+    let text = "prefix 100.0.0.0/24: G reach(r3)";
+    let e = err(text);
+    assert_eq!(e.kind, SpecParseErrorKind::UnknownRouter("r3".to_string()));
+    assert_eq!(&text[e.span.clone()], "r3");
+    assert_eq!(
+        e.annotate(text),
+        "error: Unknown router \"r3\"\n  --> line 1, column 30\n  |\n1 | prefix 100.0.0.0/24: G reach(r3)\n  |                              ^^"
+    );
+
+    let text = "prefix 10.0.0.0/8: G reach(r1)";
+    let e = err(text);
+    assert_eq!(
+        e.kind,
+        SpecParseErrorKind::InvalidPrefix("10.0.0.0/8".to_string())
+    );
+    assert_eq!(&text[e.span], "10.0.0.0/8");
+
+    let text = "prefix 100.0.0.0/24: G reach(r1) U\n  & reach(r2)";
+    let e = err(text);
+    assert!(matches!(e.kind, SpecParseErrorKind::Unexpected { .. }));
+    assert_eq!(&text[e.span], "&");
+
+    let text = "prefix 100.0.0.0/24: waypoint(r1)";
+    assert!(matches!(
+        err(text).kind,
+        SpecParseErrorKind::WrongArity { .. }
+    ));
+
+    let text = "prefix 100.0.0.0/24: max_hops(r1, many)";
+    assert_eq!(
+        err(text).kind,
+        SpecParseErrorKind::InvalidNumber("many".to_string())
+    );
+
+    let text = "prefix 100.0.0.0/24: reach(r1); prefix 100.0.0.0/24: reach(r2)";
+    assert_eq!(
+        err(text).kind,
+        SpecParseErrorKind::DuplicatePrefix("100.0.0.0/24".to_string())
+    );
+
+    let text = "prefix 100.0.0.0/24: reach(\"r1)";
+    assert_eq!(err(text).kind, SpecParseErrorKind::UnterminatedQuote);
+}
@@ -0,0 +1,101 @@
+// Chameleon: Taming the transient while reconfiguring BGP
+// Copyright (C) 2023 Tibor Schneider <sctibor@ethz.ch>
+//
+// This program is free software; you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation; either version 2 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along
+// with this program; if not, write to the Free Software Foundation, Inc.,
+// 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA.
+
+//! Test the different strategies to explore the number of steps in the ILP scheduler.
+
+use std::{collections::HashMap, time::Duration};
+
+use bgpsim::{config::ConfigModifier, prelude::*};
+use test_log::test;
+
+use crate::{
+    decomposition::{
+        bgp_dependencies::find_dependencies,
+        compiler::build,
+        ilp_scheduler::{schedule_smart_with, NodeSchedule, StepSearch},
+        CommandInfo,
+    },
+    runtime::sim::run,
+    specification::SpecificationBuilder,
+    P,
+};
+
+use super::{clique_net, remove_session_0_6, remove_session_0_8, ring_net};
+
+/// Apply the command with both the linear and the binary search, allowing at most
+/// `allowed_temp_sessions` temporary sessions. Fewer temporary sessions require more steps, such
+/// that the search explores models that have a solution which is not acceptable. Both searches must
+/// find a schedule with the same number of steps, and the schedule found by the binary search must
+/// be valid. This function returns the number of steps.
+fn compare(
+    net: &Network<P, BasicEventQueue<P>>,
+    command: ConfigModifier<P>,
+    allowed_temp_sessions: usize,
+) -> usize {
+    let p = P::from(0);
+    let spec = SpecificationBuilder::Reachability.build_all(net, None, [p]);
+
+    let info = CommandInfo::new(net, command, &spec).unwrap();
+    let bgp_deps = find_dependencies(&info);
+
+    let budget = Duration::from_secs(600);
+    let schedule = |search: StepSearch| {
+        let (result, size) =
+            schedule_smart_with(&info, &bgp_deps, p, budget, allowed_temp_sessions, search);
+        let (schedule, trace) = result.unwrap();
+        let cost: usize = schedule.values().map(NodeSchedule::cost).sum();
+        assert!(cost <= allowed_temp_sessions);
+        (size.steps, schedule, trace)
+    };
+
+    let (linear_steps, _, _) = schedule(StepSearch::Linear);
+    let (binary_steps, schedule, trace) = schedule(StepSearch::BinarySearch);
+    assert_eq!(linear_steps, binary_steps);
+
+    let schedules: HashMap<_, _> = [(p, (schedule, trace))].into_iter().collect();
+    let decomposition = build(&info, bgp_deps, schedules).unwrap();
+    run(net.clone(), decomposition, &spec).unwrap();
+
+    binary_steps
+}
+
+#[test]
+fn any_temp_sessions() {
+    compare(&clique_net(), remove_session_0_8(), usize::MAX);
+}
+
+#[test]
+fn few_temp_sessions() {
+    let steps_any = compare(&clique_net(), remove_session_0_8(), usize::MAX);
+    let steps_few = compare(&clique_net(), remove_session_0_8(), 4);
+    assert!(steps_few > steps_any);
+}
+
+#[test]
+fn no_temp_sessions() {
+    let steps_few = compare(&clique_net(), remove_session_0_8(), 4);
+    let steps_none = compare(&clique_net(), remove_session_0_8(), 0);
+    assert!(steps_none > steps_few);
+}
+
+#[test]
+fn ring_no_temp_sessions() {
+    let net = ring_net();
+    let steps_any = compare(&net, remove_session_0_6(), usize::MAX);
+    let steps_none = compare(&net, remove_session_0_6(), 0);
+    assert!(steps_none > steps_any);
+}